@@ -0,0 +1,92 @@
+//! Keyboard and screen-reader support for the custom viewport panes.
+//!
+//! Regular egui widgets are focusable with Tab and report themselves to
+//! AccessKit on their own; the 3-D panes are bare interaction rects, so they
+//! need their label, focus handling and a keyboard alternative to dragging.
+
+/// Orbit step per arrow key press.
+const ORBIT_STEP: f32 = 15.0_f32.to_radians();
+/// Pan step (points) per Shift+arrow key press.
+const PAN_STEP: f32 = 20.0;
+/// Zoom factor per +/- key press.
+const ZOOM_STEP: f32 = 1.15;
+
+/// Camera movement requested from the keyboard in one frame.
+#[derive(Default)]
+pub(crate) struct CameraKeys {
+    /// Yaw / pitch (radians).
+    pub orbit: egui::Vec2,
+    /// Screen-space pan (points).
+    pub pan: egui::Vec2,
+    /// Multiplicative; above 1 moves away.
+    pub zoom: f32,
+    pub reset: bool,
+}
+
+impl CameraKeys {
+    pub(crate) fn any(&self) -> bool {
+        self.orbit != egui::Vec2::ZERO || self.pan != egui::Vec2::ZERO || (self.zoom - 1.0).abs() > f32::EPSILON || self.reset
+    }
+}
+
+/// Make a viewport pane keyboard-focusable and describe it to assistive
+/// technology. Returns the camera keys pressed while it has focus.
+///
+/// Arrow keys orbit (or pan with Shift; panes that cannot orbit pan
+/// either way), `+`/`-` zoom and Home resets the view.
+pub(crate) fn viewport_pane(ui: &egui::Ui, response: &egui::Response, name: &str, can_orbit: bool) -> CameraKeys {
+    let help = if can_orbit {
+        "arrow keys orbit, Shift+arrow keys pan, plus and minus zoom, Home resets the view"
+    } else {
+        "arrow keys pan, plus and minus zoom, Home resets the view"
+    };
+    response.widget_info(|| egui::WidgetInfo::labeled(egui::WidgetType::Other, true, format!("{name} view: {help}")));
+
+    if response.clicked() || response.drag_started() {
+        response.request_focus();
+    }
+    let mut keys = CameraKeys { zoom: 1.0, ..CameraKeys::default() };
+    if !response.has_focus() {
+        return keys;
+    }
+    // Arrow keys move the camera instead of the focus; Tab still leaves.
+    ui.memory_mut(|m| {
+        m.set_focus_lock_filter(
+            response.id,
+            egui::EventFilter { horizontal_arrows: true, vertical_arrows: true, ..Default::default() },
+        );
+    });
+
+    ui.input(|i| {
+        let pan = !can_orbit || i.modifiers.shift;
+        for (key, dir) in [
+            (egui::Key::ArrowLeft, egui::vec2(-1.0, 0.0)),
+            (egui::Key::ArrowRight, egui::vec2(1.0, 0.0)),
+            (egui::Key::ArrowUp, egui::vec2(0.0, -1.0)),
+            (egui::Key::ArrowDown, egui::vec2(0.0, 1.0)),
+        ] {
+            if i.key_pressed(key) {
+                if pan {
+                    keys.pan += dir * PAN_STEP;
+                } else {
+                    keys.orbit += dir * ORBIT_STEP;
+                }
+            }
+        }
+        if i.key_pressed(egui::Key::Plus) || i.key_pressed(egui::Key::Equals) {
+            keys.zoom /= ZOOM_STEP;
+        }
+        if i.key_pressed(egui::Key::Minus) {
+            keys.zoom *= ZOOM_STEP;
+        }
+        keys.reset = i.key_pressed(egui::Key::Home);
+    });
+    keys
+}
+
+/// Outline a focused pane; call after painting the scene so it stays on top.
+pub(crate) fn focus_ring(ui: &egui::Ui, response: &egui::Response) {
+    if response.has_focus() {
+        ui.painter().rect_stroke(response.rect.shrink(1.0), 0.0, ui.visuals().selection.stroke);
+    }
+}
@@ -0,0 +1,550 @@
+//! Camera-based workpiece alignment.
+//!
+//! Two sets of clicked points, both on a webcam frame of the bed:
+//!
+//! 1. **Calibration** (once per camera mount): points whose bed position is
+//!    known, e.g. the tool tip jogged to a spot. They fix the image → bed
+//!    mapping (scale, rotation, offset). Stored with the other settings.
+//! 2. **Fiducials** (per workpiece): features whose position in the design
+//!    is known, e.g. corners or registration holes. Mapped to the bed, they
+//!    give the rotation and offset of the workpiece.
+//!
+//! The result is applied as a work offset: X/Y (and arc I/J) words of a job
+//! are rotated and moved before it is streamed.
+
+use std::{cell::RefCell, rc::Rc};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    AluminaApp,
+    job::Job,
+    machine::{format_words, gcode_words},
+    platform::{Camera, open_camera, storage},
+    toasts,
+};
+
+const LS_KEY: &str = "alumina.camera";
+
+/// `p ↦ scale · R(angle) · p + offset` in the XY plane.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) struct Transform2 {
+    /// Counter-clockwise (radians).
+    pub angle: f64,
+    pub scale: f64,
+    pub offset: [f64; 2],
+}
+
+impl Transform2 {
+    fn rotate(&self, [x, y]: [f64; 2]) -> [f64; 2] {
+        let (s, c) = self.angle.sin_cos();
+        [self.scale * (c * x - s * y), self.scale * (s * x + c * y)]
+    }
+
+    pub(crate) fn apply(&self, p: [f64; 2]) -> [f64; 2] {
+        let [x, y] = self.rotate(p);
+        [x + self.offset[0], y + self.offset[1]]
+    }
+
+    fn inverse(&self) -> Self {
+        let inv = Self { angle: -self.angle, scale: 1.0 / self.scale, offset: [0.0; 2] };
+        let [x, y] = inv.rotate(self.offset);
+        Self { offset: [-x, -y], ..inv }
+    }
+}
+
+/// Least-squares fit of `from → to` (Umeyama in 2-D). Without `scale` the
+/// fit is rigid and a single pair already gives a translation.
+pub(crate) fn fit(pairs: &[([f64; 2], [f64; 2])], scale: bool) -> Option<Transform2> {
+    let n = pairs.len() as f64;
+    if pairs.is_empty() || (scale && pairs.len() < 2) {
+        return None;
+    }
+    let centroid = |pick: fn(&([f64; 2], [f64; 2])) -> [f64; 2]| {
+        let sum = pairs.iter().map(pick).fold([0.0; 2], |a, p| [a[0] + p[0], a[1] + p[1]]);
+        [sum[0] / n, sum[1] / n]
+    };
+    let ca = centroid(|p| p.0);
+    let cb = centroid(|p| p.1);
+    let (mut dot, mut cross, mut norm) = (0.0, 0.0, 0.0);
+    for (a, b) in pairs {
+        let a = [a[0] - ca[0], a[1] - ca[1]];
+        let b = [b[0] - cb[0], b[1] - cb[1]];
+        dot += a[0] * b[0] + a[1] * b[1];
+        cross += a[0] * b[1] - a[1] * b[0];
+        norm += a[0] * a[0] + a[1] * a[1];
+    }
+    let angle = if pairs.len() > 1 { cross.atan2(dot) } else { 0.0 };
+    let scale = if scale {
+        if norm < 1e-9 {
+            return None;
+        }
+        dot.hypot(cross) / norm
+    } else {
+        1.0
+    };
+    let t = Transform2 { angle, scale, offset: [0.0; 2] };
+    let [x, y] = t.rotate(ca);
+    Some(Transform2 { offset: [cb[0] - x, cb[1] - y], ..t })
+}
+
+/// Root-mean-square distance between `t(from)` and `to`.
+fn rms(t: &Transform2, pairs: &[([f64; 2], [f64; 2])]) -> f64 {
+    let sum: f64 = pairs
+        .iter()
+        .map(|(a, b)| {
+            let p = t.apply(*a);
+            (p[0] - b[0]).powi(2) + (p[1] - b[1]).powi(2)
+        })
+        .sum();
+    (sum / pairs.len().max(1) as f64).sqrt()
+}
+
+/// Image coordinates grow downwards, bed Y upwards.
+fn flip([u, v]: [f64; 2]) -> [f64; 2] {
+    [u, -v]
+}
+
+/// Rotate and move the XY motion of a G-code program by `t`: absolute
+/// targets get the full transform, relative moves and arc centres (I/J)
+/// only the rotation. Coordinate-system commands pass unchanged, though
+/// `G28` and `G92` reset the position moves naming one axis start from.
+/// Comments are kept.
+pub(crate) fn transform_program(text: &str, t: &Transform2) -> String {
+    let mut absolute = true;
+    // last XY target in program coordinates, for moves naming only one axis
+    let mut pos = [0.0; 2];
+    let lines: Vec<String> = text
+        .lines()
+        .map(|line| {
+            let words = gcode_words(line);
+            let g = |n: i32| words.iter().any(|&(c, v)| c == 'G' && v as i32 == n);
+            let word = |l: char| words.iter().find(|(c, _)| *c == l).map(|&(_, v)| v);
+            if g(90) {
+                absolute = true;
+            }
+            if g(91) {
+                absolute = false;
+            }
+            if g(28) {
+                // homed: back at the origin
+                pos = [0.0, 0.0];
+            }
+            if words.iter().any(|&(c, v)| c == 'G' && v == 92.0) {
+                // the tool stays put; the named axes are given new coordinates
+                pos = [word('X').unwrap_or(pos[0]), word('Y').unwrap_or(pos[1])];
+            }
+            if g(10) || g(28) || g(53) || g(92) {
+                return line.to_owned();
+            }
+            let (x, y) = (word('X'), word('Y'));
+            let (i, j) = (word('I'), word('J'));
+            if x.is_none() && y.is_none() && i.is_none() && j.is_none() {
+                return line.to_owned();
+            }
+
+            let xy = if absolute {
+                pos = [x.unwrap_or(pos[0]), y.unwrap_or(pos[1])];
+                t.apply(pos)
+            } else {
+                let d = [x.unwrap_or(0.0), y.unwrap_or(0.0)];
+                pos = [pos[0] + d[0], pos[1] + d[1]];
+                t.rotate(d)
+            };
+            let ij = t.rotate([i.unwrap_or(0.0), j.unwrap_or(0.0)]);
+
+            let mut out = Vec::with_capacity(words.len() + 2);
+            let (mut xy_done, mut ij_done) = (false, false);
+            for &(c, v) in &words {
+                match c {
+                    'X' | 'Y' if !xy_done => {
+                        out.extend([('X', xy[0]), ('Y', xy[1])]);
+                        xy_done = true;
+                    }
+                    'I' | 'J' if !ij_done => {
+                        out.extend([('I', ij[0]), ('J', ij[1])]);
+                        ij_done = true;
+                    }
+                    'X' | 'Y' | 'I' | 'J' => {}
+                    _ => out.push((c, v)),
+                }
+            }
+            let mut rewritten = format_words(&out);
+            for comment in comments(line) {
+                rewritten.push(' ');
+                rewritten.push_str(comment);
+            }
+            rewritten
+        })
+        .collect();
+    lines.join("\n")
+}
+
+/// The `( … )` comments of `line` and its `;` comment, in order.
+fn comments(line: &str) -> Vec<&str> {
+    let mut out = Vec::new();
+    let mut open = None;
+    for (k, c) in line.char_indices() {
+        match (c, open) {
+            ('(', None) => open = Some(k),
+            (')', Some(start)) => {
+                out.push(&line[start..=k]);
+                open = None;
+            }
+            (';', None) => {
+                out.push(&line[k..]);
+                break;
+            }
+            _ => {}
+        }
+    }
+    out
+}
+
+/// A point known in one frame (bed or design) and clicked in the image.
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+struct PointPair {
+    known: [f64; 2],
+    /// Image pixel; `None` until clicked.
+    pixel: Option<[f64; 2]>,
+}
+
+fn pairs(points: &[PointPair]) -> Vec<([f64; 2], [f64; 2])> {
+    points.iter().filter_map(|p| Some((p.known, p.pixel?))).collect()
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Pick {
+    Calibration(usize),
+    Fiducial(usize),
+}
+
+pub(crate) struct Alignment {
+    pub(crate) open: bool,
+    camera: Option<Camera>,
+    opening: Rc<RefCell<Option<Result<Camera, String>>>>,
+    error: Option<String>,
+    texture: Option<egui::TextureHandle>,
+    /// Keep the last frame for precise clicking.
+    frozen: bool,
+    calibration: Vec<PointPair>,
+    fiducials: Vec<PointPair>,
+    /// Row that receives the next click in the image.
+    pick: Option<Pick>,
+    /// Rotate and move jobs by the fitted transform when they are loaded.
+    pub(crate) apply: bool,
+}
+
+impl Default for Alignment {
+    fn default() -> Self {
+        let calibration = storage()
+            .and_then(|s| s.get_item(LS_KEY))
+            .and_then(|json| match serde_json::from_str(&json) {
+                Ok(points) => Some(points),
+                Err(e) => {
+                    log::error!("stored camera calibration is invalid: {e}");
+                    None
+                }
+            })
+            .unwrap_or_default();
+        Self {
+            open: false,
+            camera: None,
+            opening: Rc::default(),
+            error: None,
+            texture: None,
+            frozen: false,
+            calibration,
+            fiducials: Vec::new(),
+            pick: None,
+            apply: false,
+        }
+    }
+}
+
+impl Alignment {
+    fn save_calibration(&self) {
+        let Some(store) = storage() else { return };
+        match serde_json::to_string(&self.calibration) {
+            Ok(json) => {
+                if let Err(e) = store.set_item(LS_KEY, &json) {
+                    log::error!("saving camera calibration failed: {e}");
+                }
+            }
+            Err(e) => log::error!("serialising camera calibration failed: {e}"),
+        }
+    }
+
+    /// Image pixel (y flipped) → bed (mm).
+    fn camera_fit(&self) -> Option<Transform2> {
+        let pairs: Vec<_> = pairs(&self.calibration).into_iter().map(|(bed, px)| (flip(px), bed)).collect();
+        fit(&pairs, true)
+    }
+
+    /// Design → bed: the work offset.
+    pub(crate) fn work_offset(&self) -> Option<Transform2> {
+        let camera = self.camera_fit()?;
+        let pairs: Vec<_> = pairs(&self.fiducials)
+            .into_iter()
+            .map(|(design, px)| (design, camera.apply(flip(px))))
+            .collect();
+        fit(&pairs, false)
+    }
+
+    /// Offset to apply to a job being loaded, if enabled and available.
+    pub(crate) fn active(&self) -> Option<Transform2> {
+        self.work_offset().filter(|_| self.apply)
+    }
+
+    fn poll_camera(&mut self, ctx: &egui::Context) {
+        if let Some(result) = self.opening.borrow_mut().take() {
+            match result {
+                Ok(camera) => {
+                    self.camera = Some(camera);
+                    self.error = None;
+                }
+                Err(e) => self.error = Some(e),
+            }
+        }
+        if self.frozen {
+            return;
+        }
+        let Some(image) = self.camera.as_ref().and_then(Camera::frame) else { return };
+        match &mut self.texture {
+            Some(t) => t.set(image, egui::TextureOptions::LINEAR),
+            None => self.texture = Some(ctx.load_texture("alignment_camera", image, egui::TextureOptions::LINEAR)),
+        }
+        ctx.request_repaint_after(std::time::Duration::from_millis(33));
+    }
+
+    fn image_ui(&mut self, ui: &mut egui::Ui) {
+        let Some(texture) = &self.texture else {
+            ui.weak("No camera image");
+            return;
+        };
+        let [w, h] = texture.size().map(|v| v as f32);
+        let width = ui.available_width().min(640.0);
+        let size = egui::vec2(width, width * h / w);
+        let response = ui.add(egui::Image::new((texture.id(), size)).sense(egui::Sense::click()));
+        let rect = response.rect;
+        let to_screen = |[u, v]: [f64; 2]| rect.min + egui::vec2(u as f32 / w * size.x, v as f32 / h * size.y);
+
+        if let (Some(pos), Some(pick)) = (response.interact_pointer_pos().filter(|_| response.clicked()), self.pick) {
+            let rel = (pos - rect.min) / size;
+            let pixel = Some([f64::from(rel.x * w), f64::from(rel.y * h)]);
+            match pick {
+                Pick::Calibration(i) => {
+                    if let Some(p) = self.calibration.get_mut(i) {
+                        p.pixel = pixel;
+                    }
+                    self.save_calibration();
+                }
+                Pick::Fiducial(i) => {
+                    if let Some(p) = self.fiducials.get_mut(i) {
+                        p.pixel = pixel;
+                    }
+                }
+            }
+            self.pick = None;
+        }
+        if self.pick.is_some() {
+            response.on_hover_cursor(egui::CursorIcon::Crosshair);
+        }
+
+        let painter = ui.painter_at(rect);
+        let font = egui::FontId::proportional(12.0);
+        for (prefix, points, color) in [
+            ("C", &self.calibration, egui::Color32::from_rgb(80, 160, 255)),
+            ("F", &self.fiducials, egui::Color32::from_rgb(255, 170, 40)),
+        ] {
+            for (i, p) in points.iter().enumerate() {
+                let Some(px) = p.pixel else { continue };
+                let c = to_screen(px);
+                painter.circle_stroke(c, 6.0, egui::Stroke::new(2.0, color));
+                painter.text(c + egui::vec2(8.0, -8.0), egui::Align2::LEFT_BOTTOM, format!("{prefix}{}", i + 1), font.clone(), color);
+            }
+        }
+        // where the fit puts each fiducial; the gap to its click is the residual
+        if let (Some(camera), Some(offset)) = (self.camera_fit(), self.work_offset()) {
+            let to_image = camera.inverse();
+            let stroke = egui::Stroke::new(1.5, egui::Color32::from_rgb(80, 220, 120));
+            for p in self.fiducials.iter().filter(|p| p.pixel.is_some()) {
+                let c = to_screen(flip(to_image.apply(offset.apply(p.known))));
+                painter.line_segment([c - egui::vec2(5.0, 0.0), c + egui::vec2(5.0, 0.0)], stroke);
+                painter.line_segment([c - egui::vec2(0.0, 5.0), c + egui::vec2(0.0, 5.0)], stroke);
+            }
+        }
+    }
+}
+
+/// Editable rows of point pairs; returns whether anything changed.
+fn points_ui(
+    ui: &mut egui::Ui,
+    id: &str,
+    points: &mut Vec<PointPair>,
+    pick: &mut Option<Pick>,
+    as_pick: fn(usize) -> Pick,
+    tool_xy: Option<[f64; 2]>,
+) -> bool {
+    let mut changed = false;
+    let mut remove = None;
+    egui::Grid::new(id).num_columns(5).show(ui, |ui| {
+        for (i, p) in points.iter_mut().enumerate() {
+            ui.label(format!("{}", i + 1));
+            ui.horizontal(|ui| {
+                changed |= ui.add(egui::DragValue::new(&mut p.known[0]).speed(0.1).prefix("X ")).changed();
+                changed |= ui.add(egui::DragValue::new(&mut p.known[1]).speed(0.1).prefix("Y ")).changed();
+            });
+            if let Some(xy) = tool_xy {
+                if ui.small_button("⌖").on_hover_text("Use the current tool position").clicked() {
+                    p.known = xy;
+                    changed = true;
+                }
+            }
+            let armed = *pick == Some(as_pick(i));
+            let text = if armed { "click image…" } else if p.pixel.is_some() { "re-pick" } else { "pick" };
+            if ui.selectable_label(armed, text).on_hover_text("Click the point in the camera image").clicked() {
+                *pick = if armed { None } else { Some(as_pick(i)) };
+            }
+            if ui.small_button("✖").clicked() {
+                remove = Some(i);
+            }
+            ui.end_row();
+        }
+    });
+    if let Some(i) = remove {
+        points.remove(i);
+        *pick = None;
+        changed = true;
+    }
+    if ui.button("+ Add point").clicked() {
+        points.push(PointPair::default());
+        *pick = Some(as_pick(points.len() - 1));
+    }
+    changed
+}
+
+impl AluminaApp {
+    /// Rotate and move the loaded job by the work offset (before it starts).
+    fn align_loaded_job(&mut self, t: &Transform2) {
+        let Some(job) = self.jobs.active.as_ref().filter(|j| !j.is_started()) else { return };
+        let aligned = Job::from_gcode(job.name.clone(), &transform_program(&job.text(), t));
+        self.jobs.active = Some(aligned);
+        self.diag_log("job aligned to the workpiece");
+        toasts::info("Job aligned to the workpiece");
+    }
+
+    pub(crate) fn alignment_window(&mut self, ctx: &egui::Context) {
+        let a = &mut self.alignment;
+        if !a.open {
+            // release the webcam as soon as the window closes
+            a.camera = None;
+            return;
+        }
+        a.poll_camera(ctx);
+
+        let tool_xy = self.dro_pos.as_ref().map(|p| {
+            let [x, y, ..] = p.display();
+            [x, y]
+        });
+        let job_waiting = self.jobs.active.as_ref().is_some_and(|j| !j.is_started());
+        let mut align_job = None;
+        let mut open = true;
+        egui::Window::new("Workpiece alignment")
+            .open(&mut open)
+            .default_width(420.0)
+            .show(ctx, |ui| {
+                let a = &mut self.alignment;
+                ui.horizontal(|ui| {
+                    if a.camera.is_none() {
+                        if ui.button("Start camera").clicked() {
+                            open_camera(Rc::clone(&a.opening));
+                        }
+                    } else if ui.button("Stop camera").clicked() {
+                        a.camera = None;
+                    }
+                    ui.checkbox(&mut a.frozen, "Freeze frame");
+                });
+                if let Some(e) = &a.error {
+                    ui.colored_label(ui.visuals().error_fg_color, e);
+                }
+                a.image_ui(ui);
+
+                ui.separator();
+                ui.collapsing("1. Camera calibration", |ui| {
+                    ui.weak("Points with a known bed position, e.g. the tool tip jogged there. Two or more, far apart.");
+                    if points_ui(ui, "calibration_points", &mut a.calibration, &mut a.pick, Pick::Calibration, tool_xy) {
+                        a.save_calibration();
+                    }
+                    let pairs: Vec<_> = pairs(&a.calibration).into_iter().map(|(bed, px)| (flip(px), bed)).collect();
+                    match a.camera_fit() {
+                        Some(t) => {
+                            ui.label(format!(
+                                "{:.3} mm/px, {:.1}° · error {:.2} mm",
+                                t.scale,
+                                t.angle.to_degrees(),
+                                rms(&t, &pairs)
+                            ));
+                        }
+                        None => {
+                            ui.colored_label(ui.visuals().warn_fg_color, "Needs two picked points");
+                        }
+                    }
+                });
+
+                ui.collapsing("2. Fiducials", |ui| {
+                    ui.weak("Features with a known position in the design, e.g. corners or holes. One gives an offset, two or more also the rotation.");
+                    points_ui(ui, "fiducial_points", &mut a.fiducials, &mut a.pick, Pick::Fiducial, None);
+                });
+
+                ui.separator();
+                match (a.camera_fit(), a.work_offset()) {
+                    (Some(camera), Some(t)) => {
+                        let pairs: Vec<_> = pairs(&a.fiducials)
+                            .into_iter()
+                            .map(|(design, px)| (design, camera.apply(flip(px))))
+                            .collect();
+                        ui.strong(format!(
+                            "Offset X {:.2} Y {:.2} mm, rotation {:.2}° · error {:.2} mm",
+                            t.offset[0],
+                            t.offset[1],
+                            t.angle.to_degrees(),
+                            rms(&t, &pairs)
+                        ));
+                        ui.checkbox(&mut a.apply, "Apply to jobs as they are loaded");
+                        if ui.add_enabled(job_waiting, egui::Button::new("Apply to loaded job")).clicked() {
+                            align_job = Some(t);
+                        }
+                    }
+                    _ => {
+                        ui.weak("Calibrate the camera and pick at least one fiducial.");
+                    }
+                }
+            });
+        self.alignment.open = open;
+        if let Some(t) = align_job {
+            self.align_loaded_job(&t);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transform_program_moves_absolute_targets_and_keeps_comments() {
+        let t = Transform2 { angle: 0.0, scale: 1.0, offset: [5.0, -2.0] };
+        let program = "G90\nG0 X10 Y10 (start)\nG1 X20 ; edge\nG92 X0 Y0\nG1 Y5\nG28\nG1 X1\nG91\nG1 X1 Y0";
+        assert_eq!(
+            transform_program(program, &t),
+            "G90\nG0 X15 Y8 (start)\nG1 X25 Y8 ; edge\nG92 X0 Y0\nG1 X5 Y3\nG28\nG1 X6 Y-2\nG91\nG1 X1 Y0"
+        );
+    }
+
+    #[test]
+    fn transform_program_rotates_arc_centres() {
+        let t = Transform2 { angle: std::f64::consts::FRAC_PI_2, scale: 1.0, offset: [0.0; 2] };
+        assert_eq!(transform_program("G2 X10 Y10 I5 J0 F300", &t), "G2 X-10 Y10 I0 J5 F300");
+    }
+}
@@ -0,0 +1,136 @@
+//! Settings that survive a reload: the selected tool, work area, tool and
+//! slicer settings and view toggles.
+//!
+//! They are kept in storage (`localStorage`, or the config directory on the
+//! desktop) and restored when the app starts. Changes are written at most
+//! once every [`SAVE_INTERVAL_MS`], and only when something changed. The
+//! machine profile and tool presets persist themselves; the settings file
+//! (see [`crate::settings_file`]) carries all of them together.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{AluminaApp, SettingsSnapshot, Tool, now_ms, platform::storage};
+
+const LS_KEY: &str = "alumina.settings";
+/// Time between checks for changed settings.
+const SAVE_INTERVAL_MS: f64 = 1_000.0;
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+struct ViewPrefs {
+    wireframe: bool,
+    edges: bool,
+    faces: bool,
+    normals: bool,
+    vertices: bool,
+    workarea: bool,
+    quad_view: bool,
+    show_profiler: bool,
+}
+
+impl Default for ViewPrefs {
+    fn default() -> Self {
+        Self {
+            wireframe: true,
+            edges: true,
+            faces: true,
+            normals: true,
+            vertices: true,
+            workarea: true,
+            quad_view: false,
+            show_profiler: false,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct AppSettings {
+    tool: Tool,
+    /// Work area X/Y/Z (mm).
+    work_size: [f32; 3],
+    common_line: bool,
+    optimize_order: bool,
+    #[serde(flatten)]
+    settings: SettingsSnapshot,
+    #[serde(default)]
+    view: ViewPrefs,
+}
+
+/// What was last written to storage, and when it was checked.
+#[derive(Default)]
+pub(crate) struct Persisted {
+    written: String,
+    checked_ms: f64,
+}
+
+impl AluminaApp {
+    pub(crate) fn app_settings(&self) -> AppSettings {
+        AppSettings {
+            tool: self.selected_tool,
+            work_size: self.work_size.into(),
+            common_line: self.common_line,
+            optimize_order: self.optimize_order,
+            settings: self.settings_snapshot(),
+            view: ViewPrefs {
+                wireframe: self.wireframe,
+                edges: self.edges,
+                faces: self.faces,
+                normals: self.normals,
+                vertices: self.vertices,
+                workarea: self.workarea,
+                quad_view: self.quad_view,
+                show_profiler: self.show_profiler,
+            },
+        }
+    }
+
+    pub(crate) fn apply_app_settings(&mut self, s: AppSettings) {
+        self.selected_tool = s.tool;
+        self.work_size = s.work_size.into();
+        self.common_line = s.common_line;
+        self.optimize_order = s.optimize_order;
+        self.apply_settings(s.settings);
+
+        let v = s.view;
+        self.wireframe = v.wireframe;
+        self.edges = v.edges;
+        self.faces = v.faces;
+        self.normals = v.normals;
+        self.vertices = v.vertices;
+        self.workarea = v.workarea;
+        self.quad_view = v.quad_view;
+        self.show_profiler = v.show_profiler;
+    }
+
+    /// Take over the settings saved by the last session, if any.
+    pub(crate) fn restore_app_settings(&mut self) {
+        let Some(json) = storage().and_then(|s| s.get_item(LS_KEY)) else { return };
+        match serde_json::from_str::<AppSettings>(&json) {
+            Ok(settings) => {
+                self.apply_app_settings(settings);
+                self.persisted.written = json;
+            }
+            Err(e) => log::warn!("stored settings are invalid: {e}"),
+        }
+    }
+
+    /// Write the settings to storage when due and changed.
+    pub(crate) fn tick_app_settings(&mut self) {
+        let now = now_ms();
+        if now - self.persisted.checked_ms < SAVE_INTERVAL_MS {
+            return;
+        }
+        self.persisted.checked_ms = now;
+        let Some(store) = storage() else { return };
+        match serde_json::to_string(&self.app_settings()) {
+            Ok(json) if json != self.persisted.written => {
+                if let Err(e) = store.set_item(LS_KEY, &json) {
+                    log::error!("saving settings failed: {e}");
+                }
+                self.persisted.written = json;
+            }
+            Ok(_) => {}
+            Err(e) => log::error!("serialising settings failed: {e}"),
+        }
+    }
+}
@@ -0,0 +1,155 @@
+//! Minimal PNG and ZIP writers for exported layer images, and a ZIP reader
+//! for project files.
+//!
+//! Masks are written as 8-bit greyscale PNGs, deflated with `miniz_oxide`.
+//! The ZIP stores its entries as they are (PNGs are compressed already) and
+//! has no ZIP64 records, so it holds at most 65 535 files of under 4 GiB.
+//! The reader takes stored and deflated entries, so an archive repacked by
+//! another tool still opens.
+
+/// CRC-32 (IEEE) of `bytes`, as PNG chunks and ZIP entries use.
+pub fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &b in bytes {
+        crc ^= u32::from(b);
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+/// PNG of a `width` × `height` greyscale image, rows top to bottom.
+pub fn png_grey(width: usize, height: usize, pixels: &[u8]) -> Vec<u8> {
+    let width = width.max(1);
+    let mut raw = Vec::with_capacity((width + 1) * height);
+    for row in pixels.chunks_exact(width).take(height) {
+        // filter type 0: the row as it is
+        raw.push(0);
+        raw.extend_from_slice(row);
+    }
+    let mut header = Vec::with_capacity(13);
+    header.extend(u32::try_from(width).unwrap_or(u32::MAX).to_be_bytes());
+    header.extend(u32::try_from(height).unwrap_or(u32::MAX).to_be_bytes());
+    // 8 bits, greyscale, deflate, no filtering variants, not interlaced
+    header.extend([8, 0, 0, 0, 0]);
+
+    let mut out = vec![0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1A, b'\n'];
+    png_chunk(&mut out, b"IHDR", &header);
+    png_chunk(&mut out, b"IDAT", &miniz_oxide::deflate::compress_to_vec_zlib(&raw, 6));
+    png_chunk(&mut out, b"IEND", &[]);
+    out
+}
+
+fn png_chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    out.extend(u32::try_from(data.len()).unwrap_or(u32::MAX).to_be_bytes());
+    let start = out.len();
+    out.extend_from_slice(kind);
+    out.extend_from_slice(data);
+    let crc = crc32(&out[start..]);
+    out.extend(crc.to_be_bytes());
+}
+
+/// ZIP archive built up in memory, one stored entry at a time.
+#[derive(Default)]
+pub struct Zip {
+    data: Vec<u8>,
+    central: Vec<u8>,
+    entries: u16,
+}
+
+impl Zip {
+    pub fn add(&mut self, name: &str, bytes: &[u8]) {
+        let crc = crc32(bytes);
+        let size = u32::try_from(bytes.len()).unwrap_or(u32::MAX);
+        let name_len = u16::try_from(name.len()).unwrap_or(u16::MAX);
+        let offset = u32::try_from(self.data.len()).unwrap_or(u32::MAX);
+        // version 2.0, UTF-8 names, stored, dated 1980-01-01 00:00
+        let common = |out: &mut Vec<u8>| {
+            out.extend(20u16.to_le_bytes());
+            out.extend(0x0800u16.to_le_bytes());
+            out.extend(0u16.to_le_bytes());
+            out.extend(0u16.to_le_bytes());
+            out.extend(0x0021u16.to_le_bytes());
+            out.extend(crc.to_le_bytes());
+            out.extend(size.to_le_bytes());
+            out.extend(size.to_le_bytes());
+            out.extend(name_len.to_le_bytes());
+            out.extend(0u16.to_le_bytes());
+        };
+
+        self.data.extend(0x0403_4b50u32.to_le_bytes());
+        common(&mut self.data);
+        self.data.extend_from_slice(name.as_bytes());
+        self.data.extend_from_slice(bytes);
+
+        self.central.extend(0x0201_4b50u32.to_le_bytes());
+        self.central.extend(20u16.to_le_bytes());
+        common(&mut self.central);
+        // comment length, disk, internal and external attributes
+        self.central.extend([0; 10]);
+        self.central.extend(offset.to_le_bytes());
+        self.central.extend_from_slice(name.as_bytes());
+        self.entries = self.entries.saturating_add(1);
+    }
+
+    pub fn finish(mut self) -> Vec<u8> {
+        let offset = u32::try_from(self.data.len()).unwrap_or(u32::MAX);
+        let size = u32::try_from(self.central.len()).unwrap_or(u32::MAX);
+        self.data.append(&mut self.central);
+        self.data.extend(0x0605_4b50u32.to_le_bytes());
+        self.data.extend([0; 4]);
+        self.data.extend(self.entries.to_le_bytes());
+        self.data.extend(self.entries.to_le_bytes());
+        self.data.extend(size.to_le_bytes());
+        self.data.extend(offset.to_le_bytes());
+        self.data.extend(0u16.to_le_bytes());
+        self.data
+    }
+}
+
+fn u16_at(bytes: &[u8], at: usize) -> Option<usize> {
+    Some(usize::from(u16::from_le_bytes(bytes.get(at..at + 2)?.try_into().ok()?)))
+}
+
+fn u32_at(bytes: &[u8], at: usize) -> Option<usize> {
+    usize::try_from(u32::from_le_bytes(bytes.get(at..at + 4)?.try_into().ok()?)).ok()
+}
+
+/// Entries of a ZIP archive, name and contents, in central directory order.
+pub fn unzip(bytes: &[u8]) -> anyhow::Result<Vec<(String, Vec<u8>)>> {
+    let malformed = || anyhow::anyhow!("not a ZIP archive, or a damaged one");
+    // end of central directory: 22 bytes, then a comment of up to 64 KiB
+    let end = (0..=bytes.len().saturating_sub(22))
+        .rev()
+        .take(22 + 0xFFFF)
+        .find(|&i| bytes[i..].starts_with(&0x0605_4b50u32.to_le_bytes()))
+        .ok_or_else(malformed)?;
+    let count = u16_at(bytes, end + 10).ok_or_else(malformed)?;
+    let mut at = u32_at(bytes, end + 16).ok_or_else(malformed)?;
+
+    let mut out = Vec::with_capacity(count);
+    for _ in 0..count {
+        if !bytes.get(at..).is_some_and(|b| b.starts_with(&0x0201_4b50u32.to_le_bytes())) {
+            return Err(malformed());
+        }
+        let field = |offset| u16_at(bytes, at + offset).ok_or_else(malformed);
+        let (method, name_len, extra_len, comment_len) = (field(10)?, field(28)?, field(30)?, field(32)?);
+        let size = u32_at(bytes, at + 20).ok_or_else(malformed)?;
+        let local = u32_at(bytes, at + 42).ok_or_else(malformed)?;
+        let name = bytes.get(at + 46..at + 46 + name_len).ok_or_else(malformed)?;
+        let name = String::from_utf8_lossy(name).into_owned();
+        at += 46 + name_len + extra_len + comment_len;
+
+        // the local header's own name and extra field come before the data
+        let start = local + 30 + u16_at(bytes, local + 26).ok_or_else(malformed)? + u16_at(bytes, local + 28).ok_or_else(malformed)?;
+        let data = bytes.get(start..start + size).ok_or_else(malformed)?;
+        let data = match method {
+            0 => data.to_vec(),
+            8 => miniz_oxide::inflate::decompress_to_vec(data).map_err(|e| anyhow::anyhow!("`{name}`: {e:?}"))?,
+            m => anyhow::bail!("`{name}` uses an unsupported compression method ({m})"),
+        };
+        out.push((name, data));
+    }
+    Ok(out)
+}
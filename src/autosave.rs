@@ -0,0 +1,225 @@
+//! Periodic snapshot of the session (models, design graph and slicer
+//! settings) so work survives a crash or an accidentally closed tab.
+//!
+//! While there are unsaved changes the session is written to the blob store
+//! (IndexedDB in the browser) every [`INTERVAL_MS`], but only if it changed
+//! since the last snapshot. On start-up a snapshot left behind is offered
+//! for restoring; discarding it deletes it.
+
+use std::{
+    hash::{Hash, Hasher},
+    sync::{Arc, Mutex},
+};
+
+use base64::Engine;
+use csgrs::mesh::Mesh;
+use nalgebra::Vector3;
+use serde::{Deserialize, Serialize};
+
+use crate::{AluminaApp, ModelEntry, SettingsSnapshot, design_graph, execute, now_ms, platform, slicer, toasts};
+
+const KEY: &str = "autosave";
+/// Time between snapshots while there are unsaved changes.
+const INTERVAL_MS: f64 = 30_000.0;
+
+#[derive(Serialize, Deserialize)]
+struct SavedModel {
+    name: String,
+    /// Base geometry as binary STL, base64.
+    stl: String,
+    scale: [f32; 3],
+    offset: [f32; 3],
+    overrides: slicer::SliceOverrides,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct Session {
+    /// Workspace name.
+    #[serde(default)]
+    pub(crate) name: String,
+    /// Wall-clock time of the snapshot (ms since the Unix epoch).
+    saved_at_ms: f64,
+    models: Vec<SavedModel>,
+    graph: Option<design_graph::SavedGraph>,
+    settings: SettingsSnapshot,
+}
+
+#[derive(Default)]
+pub(crate) struct Autosave {
+    last_ms: f64,
+    /// Hash of the last snapshot written.
+    last_written: Option<u64>,
+    /// Snapshot found at start-up, waiting for the user's decision.
+    offer: Arc<Mutex<Option<Session>>>,
+}
+
+impl Autosave {
+    /// Look for a snapshot left by an earlier session.
+    pub(crate) fn start() -> Self {
+        let this = Self::default();
+        let offer = Arc::clone(&this.offer);
+        execute(async move {
+            match platform::blob_get(KEY).await {
+                Ok(Some(bytes)) => match serde_json::from_slice::<Session>(&bytes) {
+                    Ok(session) => *offer.lock().unwrap() = Some(session),
+                    Err(e) => log::warn!("[autosave] unreadable snapshot: {e}"),
+                },
+                Ok(None) => {}
+                Err(e) => log::warn!("[autosave] {e}"),
+            }
+        });
+        this
+    }
+}
+
+pub(crate) fn models_hash(models: &[ModelEntry], state: &mut impl Hasher) {
+    for m in models {
+        m.name.hash(state);
+        m.base.polygons.len().hash(state);
+        for v in m.scale.iter().chain(m.offset.iter()) {
+            v.to_bits().hash(state);
+        }
+        format!("{:?}", m.overrides).hash(state);
+    }
+}
+
+impl AluminaApp {
+    pub(crate) fn session(&self) -> anyhow::Result<Session> {
+        let mut models = Vec::with_capacity(self.models.len());
+        for m in &self.models {
+            let stl = m.base.to_stl_binary(&m.name)?;
+            models.push(SavedModel {
+                name: m.name.clone(),
+                stl: base64::engine::general_purpose::STANDARD.encode(stl),
+                scale: m.scale.into(),
+                offset: m.offset.into(),
+                overrides: m.overrides.clone(),
+            });
+        }
+        Ok(Session {
+            name: self.workspaces.active_name().to_owned(),
+            saved_at_ms: platform::unix_ms(),
+            models,
+            graph: design_graph::SavedGraph::from_state(&self.design_state).ok(),
+            settings: self.settings_snapshot(),
+        })
+    }
+
+    /// Write a snapshot when due and something changed.
+    pub(crate) fn tick_autosave(&mut self) {
+        let now = now_ms();
+        if now - self.autosave.last_ms < INTERVAL_MS || !self.dirty.any() {
+            return;
+        }
+        self.autosave.last_ms = now;
+
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        design_graph::fingerprint(&self.design_state.graph).hash(&mut hasher);
+        models_hash(&self.models, &mut hasher);
+        serde_json::to_string(&self.settings_snapshot()).unwrap_or_default().hash(&mut hasher);
+        let hash = hasher.finish();
+        if self.autosave.last_written == Some(hash) {
+            return;
+        }
+
+        let bytes = match self.session().and_then(|s| serde_json::to_vec(&s).map_err(Into::into)) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                log::error!("[autosave] snapshot failed: {e}");
+                return;
+            }
+        };
+        self.autosave.last_written = Some(hash);
+        execute(async move {
+            match platform::blob_put(KEY, bytes).await {
+                Ok(()) => log::debug!("[autosave] session saved"),
+                Err(e) => {
+                    log::error!("[autosave] {e}");
+                    toasts::warn("Auto-save failed", Some(e));
+                }
+            }
+        });
+    }
+
+    pub(crate) fn restore_session(&mut self, session: Session) {
+        let mut failed = 0;
+        let mut models = Vec::with_capacity(session.models.len());
+        for saved in session.models {
+            let mesh = base64::engine::general_purpose::STANDARD
+                .decode(&saved.stl)
+                .ok()
+                .and_then(|stl| Mesh::<()>::from_stl(&stl, None).ok());
+            let Some(mesh) = mesh else {
+                failed += 1;
+                continue;
+            };
+            let mut entry = ModelEntry::new(saved.name, mesh);
+            entry.scale = Vector3::from(saved.scale);
+            entry.offset = Vector3::from(saved.offset);
+            entry.overrides = saved.overrides;
+            entry.refresh();
+            models.push(entry);
+        }
+        self.selected_model = (!models.is_empty()).then_some(0);
+        self.models = models;
+        self.model_history.clear();
+        self.dirty.models = true;
+
+        if let Some(graph) = session.graph {
+            let (state, missing) = graph.into_state();
+            failed += missing;
+            self.design_state = state;
+        }
+
+        self.apply_settings(session.settings);
+
+        if failed > 0 {
+            toasts::warn(
+                "Part of the previous session could not be restored",
+                Some(format!("{failed} model(s) or plugin node(s) missing")),
+            );
+        } else {
+            toasts::info("Previous session restored");
+        }
+    }
+
+    /// Offer the snapshot found at start-up.
+    pub(crate) fn session_dialog(&mut self, ctx: &egui::Context) {
+        let mut offer = self.autosave.offer.lock().unwrap();
+        let Some(session) = offer.as_ref() else { return };
+        let minutes = ((platform::unix_ms() - session.saved_at_ms) / 60_000.0).max(0.0);
+        let mut answer = None;
+        egui::Window::new("Restore previous session")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "An auto-saved session from {} ago was found: {} model(s){}.",
+                    if minutes < 1.0 { "less than a minute".to_owned() } else { format!("{minutes:.0} min") },
+                    session.models.len(),
+                    if session.graph.as_ref().is_some_and(|g| !g.is_empty()) { " and a design graph" } else { "" },
+                ));
+                ui.horizontal(|ui| {
+                    if ui.button("Restore").clicked() {
+                        answer = Some(true);
+                    }
+                    if ui.button("Discard").clicked() {
+                        answer = Some(false);
+                    }
+                });
+            });
+        let Some(restore) = answer else { return };
+        let session = offer.take();
+        drop(offer);
+        if let (true, Some(session)) = (restore, session) {
+            self.restore_session(session);
+        } else {
+            execute(async {
+                if let Err(e) = platform::blob_delete(KEY).await {
+                    log::warn!("[autosave] {e}");
+                }
+            });
+        }
+    }
+}
@@ -0,0 +1,158 @@
+//! Bill of materials: the loaded models and the meshes the graph's Export
+//! Mesh nodes produced, with identical parts listed once with a quantity,
+//! their size, volume, material and an estimated cost, exportable as CSV.
+//!
+//! Parts count as identical when their polygon count, volume and sorted
+//! bounding box dimensions agree, so a copy turned by 90° still matches.
+
+use csgrs::mesh::Mesh;
+
+use crate::{AluminaApp, download_bytes, engine};
+
+pub(crate) struct BomRow {
+    name: String,
+    quantity: usize,
+    /// Bounding box width, depth and height (mm).
+    size: [f64; 3],
+    /// mm³ per part.
+    volume: f64,
+    material: String,
+    /// Estimated cost of all `quantity` parts.
+    cost: f64,
+}
+
+pub(crate) struct Bom {
+    open: bool,
+    /// Material cost per cm³, in whatever currency the user thinks in.
+    cost_per_cm3: f64,
+    /// Rebuilt on opening and on Refresh, not every frame.
+    rows: Option<Vec<BomRow>>,
+}
+
+impl Default for Bom {
+    fn default() -> Self {
+        Self { open: false, cost_per_cm3: 0.05, rows: None }
+    }
+}
+
+impl Bom {
+    pub(crate) fn open(&mut self) {
+        self.open = true;
+        self.rows = None;
+    }
+}
+
+fn part_key(s: &engine::MeshStats) -> (usize, i64, [i64; 3]) {
+    let mut dims: Vec<i64> = (0..3).map(|i| ((s.max[i] - s.min[i]) * 100.0).round() as i64).collect();
+    dims.sort_unstable();
+    (s.polygons, (s.volume * 100.0).round() as i64, [dims[0], dims[1], dims[2]])
+}
+
+fn rows<'a>(parts: impl IntoIterator<Item = (&'a str, &'a Mesh<()>)>, material: &str, cost_per_cm3: f64) -> Vec<BomRow> {
+    let mut keys = Vec::new();
+    let mut rows: Vec<BomRow> = Vec::new();
+    for (name, mesh) in parts {
+        let stats = engine::mesh_stats(mesh);
+        let key = part_key(&stats);
+        if let Some(i) = keys.iter().position(|k| *k == key) {
+            rows[i].quantity += 1;
+            rows[i].cost += rows[i].volume.abs() / 1000.0 * cost_per_cm3;
+            continue;
+        }
+        keys.push(key);
+        let stem = name.rsplit_once('.').map_or(name, |(stem, _)| stem);
+        rows.push(BomRow {
+            name: stem.to_owned(),
+            quantity: 1,
+            size: [0, 1, 2].map(|i| stats.max[i] - stats.min[i]),
+            volume: stats.volume,
+            material: material.to_owned(),
+            cost: stats.volume.abs() / 1000.0 * cost_per_cm3,
+        });
+    }
+    rows
+}
+
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n']) { format!("\"{}\"", text.replace('"', "\"\"")) } else { text.to_owned() }
+}
+
+fn csv(rows: &[BomRow]) -> String {
+    let mut out = String::from("part,quantity,width_mm,depth_mm,height_mm,volume_cm3,material,cost\n");
+    for r in rows {
+        out += &format!(
+            "{},{},{:.2},{:.2},{:.2},{:.3},{},{:.2}\n",
+            csv_field(&r.name),
+            r.quantity,
+            r.size[0],
+            r.size[1],
+            r.size[2],
+            r.volume / 1000.0,
+            csv_field(&r.material),
+            r.cost,
+        );
+    }
+    out
+}
+
+impl AluminaApp {
+    fn bom_rows(&self) -> Vec<BomRow> {
+        let material = self.stock.as_ref().map_or("", |s| s.material.as_str());
+        let models = self.models.iter().map(|m| (m.name.as_str(), &m.mesh));
+        let exports = self.graph_exports.iter().map(|e| (e.file_name.as_str(), &e.mesh));
+        rows(models.chain(exports), material, self.bom.cost_per_cm3)
+    }
+
+    pub(crate) fn bom_window(&mut self, ctx: &egui::Context) {
+        if !self.bom.open {
+            return;
+        }
+        if self.bom.rows.is_none() {
+            self.bom.rows = Some(self.bom_rows());
+        }
+        let mut open = true;
+        let mut refresh = false;
+        egui::Window::new("Bill of materials").open(&mut open).default_width(560.0).show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Cost per cm³:");
+                refresh |= ui.add(egui::DragValue::new(&mut self.bom.cost_per_cm3).speed(0.005).range(0.0..=1000.0)).changed();
+                refresh |= ui.button("Refresh").on_hover_text("Read the models and graph exports again").clicked();
+            });
+            if self.stock.as_ref().is_none_or(|s| s.material.is_empty()) {
+                ui.weak("Set a stock material in the Models panel to fill the material column.");
+            }
+            let rows = self.bom.rows.as_deref().unwrap_or_default();
+            if rows.is_empty() {
+                ui.weak("No models or graph exports.");
+                return;
+            }
+            egui::ScrollArea::vertical().max_height(360.0).show(ui, |ui| {
+                egui::Grid::new("bom_grid").num_columns(6).striped(true).show(ui, |ui| {
+                    for heading in ["Part", "Qty", "Size (mm)", "Volume (cm³)", "Material", "Cost"] {
+                        ui.strong(heading);
+                    }
+                    ui.end_row();
+                    for r in rows {
+                        ui.label(&r.name);
+                        ui.label(r.quantity.to_string());
+                        ui.label(format!("{:.1} × {:.1} × {:.1}", r.size[0], r.size[1], r.size[2]));
+                        ui.label(format!("{:.2}", r.volume / 1000.0));
+                        ui.label(if r.material.is_empty() { "—" } else { r.material.as_str() });
+                        ui.label(format!("{:.2}", r.cost));
+                        ui.end_row();
+                    }
+                });
+            });
+            let parts: usize = rows.iter().map(|r| r.quantity).sum();
+            let cost: f64 = rows.iter().map(|r| r.cost).sum();
+            ui.label(format!("{parts} parts, {} distinct · estimated {cost:.2}", rows.len()));
+            if ui.button("Export CSV").clicked() {
+                download_bytes("bom.csv", csv(rows).as_bytes());
+            }
+        });
+        if refresh {
+            self.bom.rows = None;
+        }
+        self.bom.open = open;
+    }
+}
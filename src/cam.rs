@@ -0,0 +1,547 @@
+//! 2.5-D profiling and pocketing for the Endmill.
+//!
+//! Where [`crate::milling`] roughs whole parts out of height maps, these
+//! toolpaths follow the outline of one slice. A profile runs around it
+//! outside, the last pass one tool radius off the wall and any roughing
+//! passes further out; a pocket clears its inside in zig-zag rows or in
+//! concentric rings spiralling out from the middle. Either is repeated at
+//! every depth step down to the full depth, and broken off wherever the
+//! tool would run into a fixture.
+//!
+//! A V-carve engraves the slice with a V-bit instead. Each point of the
+//! outline is carved from the centre of the largest circle inside the shape
+//! touching it there, as deep as the bit must go for its flanks to reach
+//! the outline: narrow strokes are cut down their centreline at the depth
+//! their width calls for. Past the full depth the bottom is flat, cleared
+//! by rings inset further. Like the others it is cut a depth step at a
+//! time.
+//!
+//! Each pass is entered from the depth the one before left the material
+//! at. A straight entry plunges there; a ramp zig-zags down along the start
+//! of the pass at the ramp angle, and a helix spirals down at the same
+//! angle on a circle beside the pass's first point. Both end back at that
+//! point, at depth, so small endmills never drill straight down.
+
+use std::collections::HashMap;
+
+use csgrs::{sketch::Sketch, traits::CSG};
+use geo::{BooleanOps, Coord, LineString, MultiLineString, MultiPolygon};
+use serde::{Deserialize, Serialize};
+
+use crate::{collision::Fixture, slicer};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Strategy {
+    /// Cut the part out along its outline.
+    #[default]
+    Profile,
+    /// Clear the area inside the outline.
+    Pocket,
+    /// Engrave the inside with a V-bit, deeper where it is wider.
+    VCarve,
+}
+
+impl Strategy {
+    pub const ALL: [Strategy; 3] = [Strategy::Profile, Strategy::Pocket, Strategy::VCarve];
+}
+
+impl std::fmt::Display for Strategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Strategy::Profile => "Profile",
+            Strategy::Pocket => "Pocket",
+            Strategy::VCarve => "V-carve",
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PocketPattern {
+    #[default]
+    Zigzag,
+    Spiral,
+}
+
+impl std::fmt::Display for PocketPattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            PocketPattern::Zigzag => "Zig-zag",
+            PocketPattern::Spiral => "Spiral",
+        })
+    }
+}
+
+/// How a pass goes down into the material.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Entry {
+    #[default]
+    Straight,
+    Ramp,
+    Helix,
+}
+
+impl Entry {
+    pub const ALL: [Entry; 3] = [Entry::Straight, Entry::Ramp, Entry::Helix];
+}
+
+impl std::fmt::Display for Entry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Entry::Straight => "Straight plunge",
+            Entry::Ramp => "Ramp",
+            Entry::Helix => "Helix",
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CamSettings {
+    pub strategy: Strategy,
+    pub pattern: PocketPattern,
+    /// Distance between neighbouring passes, as a fraction of the tool
+    /// diameter.
+    pub stepover: f64,
+    /// Full depth below the top of the material, and depth of each pass (mm).
+    pub depth: f64,
+    pub depth_per_pass: f64,
+    /// Profile passes, the last one at the tool radius from the outline.
+    pub profile_passes: u32,
+    /// Included angle of the V-bit (degrees).
+    pub v_angle: f64,
+    pub entry: Entry,
+    /// Descent of ramps and helices, from the horizontal (degrees).
+    pub ramp_angle: f64,
+    /// Diameter of the helix the tool centre follows (mm).
+    pub helix_diameter: f64,
+}
+
+impl Default for CamSettings {
+    fn default() -> Self {
+        Self {
+            strategy: Strategy::Profile,
+            pattern: PocketPattern::Zigzag,
+            stepover: 0.4,
+            depth: 3.0,
+            depth_per_pass: 1.0,
+            profile_passes: 1,
+            v_angle: 60.0,
+            entry: Entry::Straight,
+            ramp_angle: 3.0,
+            helix_diameter: 2.0,
+        }
+    }
+}
+
+/// Most rings a spiral pocket is cleared with.
+const MAX_RINGS: usize = 10_000;
+
+/// Segments per turn of a helical entry.
+const HELIX_SEGMENTS: usize = 24;
+
+/// Most back-and-forth legs or turns of one entry.
+const MAX_ENTRY_LEGS: usize = 1_000;
+
+/// Tool-centre paths for `slice` with a tool of `tool_diameter`, cutting
+/// down from `top`: every pass of the outline at the first depth, then
+/// again one step deeper, to `top - depth`.
+pub fn toolpaths(slice: &Sketch<()>, settings: &CamSettings, tool_diameter: f64, top: f64) -> Vec<Vec<[f64; 3]>> {
+    let r = tool_diameter.max(0.01) * 0.5;
+    let step = (tool_diameter * settings.stepover.clamp(0.05, 1.0)).max(0.01);
+    let flat: Vec<LineString<f64>> = match settings.strategy {
+        Strategy::Profile => (0..settings.profile_passes.max(1))
+            .rev()
+            .flat_map(|k| rings(&slice.offset(r + f64::from(k) * step)))
+            .collect(),
+        Strategy::Pocket => pocket(slice, r, step, settings.pattern),
+        Strategy::VCarve => return v_carve(slice, settings, step, top),
+    };
+    let depth = settings.depth.max(0.0);
+    let per_pass = settings.depth_per_pass.max(0.01);
+    let passes = ((depth / per_pass).ceil() as usize).max(1);
+    (1..=passes)
+        .flat_map(|k| {
+            let z = top - (k as f64 * per_pass).min(depth);
+            flat.iter().map(move |ls| ls.0.iter().map(|c| [c.x, c.y, z]).collect())
+        })
+        .collect()
+}
+
+/// `paths` without the stretches that would bring a tool of radius `r`
+/// into a fixture standing above them; what is left of a pass is cut as
+/// separate passes, at the heights the pass had there.
+pub fn avoid_fixtures(paths: Vec<Vec<[f64; 3]>>, fixtures: &[Fixture], r: f64) -> Vec<Vec<[f64; 3]>> {
+    if fixtures.is_empty() {
+        return paths;
+    }
+    paths
+        .into_iter()
+        .flat_map(|path| {
+            // V-carve passes vary in depth; a fixture above the lowest point is in the way
+            let z = path.iter().map(|p| p[2]).fold(f64::INFINITY, f64::min);
+            let blocked = MultiPolygon(fixtures.iter().filter(|f| f.top() > z).map(|f| f.footprint(r)).collect());
+            if blocked.0.is_empty() {
+                return vec![path];
+            }
+            let line = LineString::from(path.iter().map(|p| Coord { x: p[0], y: p[1] }).collect::<Vec<_>>());
+            blocked
+                .clip(&MultiLineString(vec![line]), true)
+                .0
+                .into_iter()
+                .filter(|ls| ls.0.len() >= 2)
+                .map(|ls| ls.0.iter().map(|&c| [c.x, c.y, height_at(&path, c)]).collect())
+                .collect()
+        })
+        .collect()
+}
+
+/// Height of `path` where it passes nearest to `c`.
+fn height_at(path: &[[f64; 3]], c: Coord<f64>) -> f64 {
+    let Some(first) = path.first() else { return 0.0 };
+    path.windows(2)
+        .map(|w| {
+            let (a, b) = (Coord { x: w[0][0], y: w[0][1] }, Coord { x: w[1][0], y: w[1][1] });
+            let d = b - a;
+            let len2 = d.x * d.x + d.y * d.y;
+            let t = if len2 > 0.0 { (((c.x - a.x) * d.x + (c.y - a.y) * d.y) / len2).clamp(0.0, 1.0) } else { 0.0 };
+            (segment_distance(c, a, b), w[0][2] + (w[1][2] - w[0][2]) * t)
+        })
+        .min_by(|x, y| x.0.total_cmp(&y.0))
+        .map_or(first[2], |(_, z)| z)
+}
+
+/// Flat `paths` with the entry moves of `settings` in front: each starts
+/// one depth step higher (no higher than `top`) and works down to its
+/// pass's depth without plunging.
+pub fn with_entries(paths: Vec<Vec<[f64; 3]>>, settings: &CamSettings, top: f64) -> Vec<Vec<[f64; 3]>> {
+    if settings.entry == Entry::Straight {
+        return paths;
+    }
+    let slope = settings.ramp_angle.clamp(0.5, 90.0).to_radians().tan();
+    let per_pass = settings.depth_per_pass.max(0.01);
+    paths
+        .into_iter()
+        .map(|path| {
+            let Some(&[x, y, z]) = path.first() else { return path };
+            let drop = (z + per_pass).min(top) - z;
+            if drop <= 0.0 {
+                return path;
+            }
+            let mut out = match settings.entry {
+                Entry::Helix if settings.helix_diameter > 0.0 => helix([x, y], z, drop, slope, settings.helix_diameter * 0.5),
+                _ => ramp(&path, drop, slope),
+            };
+            out.extend(path);
+            out
+        })
+        .collect()
+}
+
+/// Points down to the start of flat `path`, from `drop` above it: forth
+/// and back along it, descending `slope` per mm of travel.
+fn ramp(path: &[[f64; 3]], drop: f64, slope: f64) -> Vec<[f64; 3]> {
+    let [x0, y0, z] = path[0];
+    let run = drop / slope;
+    let total: f64 = path.windows(2).map(|w| (w[1][0] - w[0][0]).hypot(w[1][1] - w[0][1])).sum();
+    if total <= 1e-9 {
+        return vec![[x0, y0, z + drop]];
+    }
+    // out and back is one leg pair; as many as it takes to keep the slope
+    let legs = ((run / (2.0 * total)).ceil() as usize).clamp(1, MAX_ENTRY_LEGS);
+    let reach = run / (2.0 * legs as f64);
+    let mut stretch = vec![[x0, y0]];
+    let mut walked = 0.0;
+    for w in path.windows(2) {
+        let len = (w[1][0] - w[0][0]).hypot(w[1][1] - w[0][1]);
+        if walked + len >= reach {
+            let t = if len > 0.0 { (reach - walked) / len } else { 0.0 };
+            stretch.push([w[0][0] + t * (w[1][0] - w[0][0]), w[0][1] + t * (w[1][1] - w[0][1])]);
+            break;
+        }
+        walked += len;
+        stretch.push([w[1][0], w[1][1]]);
+    }
+    let step = drop / (2.0 * legs as f64 * reach);
+    let mut out = vec![[x0, y0, z + drop]];
+    let mut height = z + drop;
+    for _ in 0..legs {
+        for back in [false, true] {
+            let points: Vec<[f64; 2]> = if back { stretch.iter().rev().copied().collect() } else { stretch.clone() };
+            for w in points.windows(2) {
+                height -= step * (w[1][0] - w[0][0]).hypot(w[1][1] - w[0][1]);
+                out.push([w[1][0], w[1][1], height.max(z)]);
+            }
+        }
+    }
+    if let Some(last) = out.last_mut() {
+        last[2] = z;
+    }
+    out
+}
+
+/// Points down to `(x, y, z)` from `drop` above it on a helix of `radius`
+/// beside it, descending `slope` per mm of travel.
+fn helix(start: [f64; 2], z: f64, drop: f64, slope: f64, radius: f64) -> Vec<[f64; 3]> {
+    let circumference = std::f64::consts::TAU * radius;
+    let turns = ((drop / slope / circumference).ceil() as usize).clamp(1, MAX_ENTRY_LEGS);
+    let centre = [start[0] + radius, start[1]];
+    let steps = turns * HELIX_SEGMENTS;
+    (0..=steps)
+        .map(|k| {
+            let t = k as f64 / steps as f64;
+            let a = std::f64::consts::PI + std::f64::consts::TAU * turns as f64 * t;
+            [centre[0] + radius * a.cos(), centre[1] + radius * a.sin(), z + drop * (1.0 - t)]
+        })
+        .collect()
+}
+
+/// V-bit passes engraving `slice` from `top`. Every point of the outline,
+/// `step` apart, is carved from the centre of the largest circle touching
+/// it there that stays inside the shape, as deep as a bit of
+/// `settings.v_angle` is wide across that circle: the local half-width
+/// decides the depth, and in narrow strokes the tool runs down the
+/// centreline. Where the circle would take the bit past `settings.depth`
+/// it stops at that depth, and rings further in clear the flat bottom.
+/// Everything is cut in steps of `settings.depth_per_pass`.
+fn v_carve(slice: &Sketch<()>, settings: &CamSettings, step: f64, top: f64) -> Vec<Vec<[f64; 3]>> {
+    let half = (settings.v_angle.clamp(10.0, 170.0) * 0.5).to_radians().tan();
+    let depth = settings.depth.max(0.0);
+    // widest the bit cuts at full depth, on either side of its axis
+    let reach = depth * half;
+    let polys = slicer::polygons(slice);
+    let outline = Outline::new(polys.iter().flat_map(|p| std::iter::once(p.exterior()).chain(p.interiors())), reach.max(step));
+    let tol = (step * 1e-3).max(1e-6);
+
+    let mut carve: Vec<Vec<[f64; 3]>> = Vec::new();
+    for p in &polys {
+        for (i, ring) in std::iter::once(p.exterior()).chain(p.interiors()).enumerate() {
+            // material lies left of a counter-clockwise exterior and of a clockwise hole
+            let left = (signed_area(ring) > 0.0) == (i == 0);
+            let path: Vec<[f64; 3]> = ring_samples(ring, step, left)
+                .into_iter()
+                .map(|(at, inward)| {
+                    let r = inscribed_radius(&outline, at, inward, reach, tol);
+                    [at.x + inward.x * r, at.y + inward.y * r, top - r / half]
+                })
+                .collect();
+            if path.len() >= 2 {
+                carve.push(path);
+            }
+        }
+    }
+    // flat bottom wider than the bit reaches at full depth
+    for k in (1..MAX_RINGS).take_while(|_| reach > 0.0) {
+        let ring = rings(&slice.offset(-(reach + k as f64 * step)));
+        if ring.is_empty() {
+            break;
+        }
+        carve.extend(ring.into_iter().map(|ls| ls.0.iter().map(|c| [c.x, c.y, top - depth]).collect()));
+    }
+
+    let per_pass = settings.depth_per_pass.max(0.01);
+    let passes = ((depth / per_pass).ceil() as usize).max(1);
+    (1..=passes)
+        .flat_map(|k| {
+            let floor = top - (k as f64 * per_pass).min(depth);
+            let above = top - (k - 1) as f64 * per_pass;
+            // a path entirely above this pass's material was cut in full already
+            carve
+                .iter()
+                .filter(move |path| path.iter().any(|p| p[2] < above - 1e-9))
+                .map(move |path| path.iter().map(|p| [p[0], p[1], p[2].max(floor)]).collect())
+        })
+        .collect()
+}
+
+/// Twice the signed area of `ring`, positive when counter-clockwise.
+fn signed_area(ring: &LineString<f64>) -> f64 {
+    ring.0.windows(2).map(|w| w[0].x * w[1].y - w[1].x * w[0].y).sum()
+}
+
+/// Points along closed `ring` at most `step` apart, each with the unit
+/// normal on the `left` (or right) side; corners get the mean of the
+/// normals of the edges meeting there.
+fn ring_samples(ring: &LineString<f64>, step: f64, left: bool) -> Vec<(Coord<f64>, Coord<f64>)> {
+    let sign = if left { 1.0 } else { -1.0 };
+    let normal = |a: Coord<f64>, b: Coord<f64>| {
+        let d = b - a;
+        let len = d.x.hypot(d.y);
+        Coord { x: -d.y / len * sign, y: d.x / len * sign }
+    };
+    let edges: Vec<(Coord<f64>, Coord<f64>)> = ring.0.windows(2).map(|w| (w[0], w[1])).filter(|(a, b)| a != b).collect();
+    let mut out = Vec::new();
+    for (k, &(a, b)) in edges.iter().enumerate() {
+        let n = normal(a, b);
+        let (pa, pb) = edges[(k + edges.len() - 1) % edges.len()];
+        let corner = n + normal(pa, pb);
+        let len = corner.x.hypot(corner.y);
+        out.push((a, if len > 1e-9 { corner / len } else { n }));
+        let d = b - a;
+        let pieces = (d.x.hypot(d.y) / step).ceil().max(1.0) as usize;
+        out.extend((1..pieces).map(|j| (a + d * (j as f64 / pieces as f64), n)));
+    }
+    if let Some(&first) = out.first() {
+        out.push(first);
+    }
+    out
+}
+
+/// Radius, up to `limit`, of the largest circle touching the outline at
+/// `at` with its centre along `inward` that no other part of the outline
+/// cuts into.
+fn inscribed_radius(outline: &Outline, at: Coord<f64>, inward: Coord<f64>, limit: f64, tol: f64) -> f64 {
+    let fits = |r: f64| outline.distance(at + inward * r, r) >= r - tol;
+    if fits(limit) {
+        return limit;
+    }
+    let (mut lo, mut hi) = (0.0, limit);
+    while hi - lo > tol {
+        let mid = (lo + hi) * 0.5;
+        if fits(mid) {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+    lo
+}
+
+/// Outline segments bucketed on a grid, to find the nearest one quickly.
+struct Outline {
+    cell: f64,
+    buckets: HashMap<(i64, i64), Vec<(Coord<f64>, Coord<f64>)>>,
+}
+
+impl Outline {
+    fn new<'a>(rings: impl IntoIterator<Item = &'a LineString<f64>>, cell: f64) -> Self {
+        let cell = cell.max(0.01);
+        let mut buckets: HashMap<(i64, i64), Vec<(Coord<f64>, Coord<f64>)>> = HashMap::new();
+        for ring in rings {
+            for w in ring.0.windows(2) {
+                let (a, b) = (w[0], w[1]);
+                let (x0, x1) = ((a.x.min(b.x) / cell).floor() as i64, (a.x.max(b.x) / cell).floor() as i64);
+                let (y0, y1) = ((a.y.min(b.y) / cell).floor() as i64, (a.y.max(b.y) / cell).floor() as i64);
+                for x in x0..=x1 {
+                    for y in y0..=y1 {
+                        buckets.entry((x, y)).or_default().push((a, b));
+                    }
+                }
+            }
+        }
+        Self { cell, buckets }
+    }
+
+    /// Distance from `p` to the nearest segment, or `limit` if none is nearer.
+    fn distance(&self, p: Coord<f64>, limit: f64) -> f64 {
+        let span = |v: f64| ((v - limit) / self.cell).floor() as i64..=((v + limit) / self.cell).floor() as i64;
+        let mut best = limit;
+        for x in span(p.x) {
+            for y in span(p.y) {
+                for &(a, b) in self.buckets.get(&(x, y)).into_iter().flatten() {
+                    best = best.min(segment_distance(p, a, b));
+                }
+            }
+        }
+        best
+    }
+}
+
+fn segment_distance(p: Coord<f64>, a: Coord<f64>, b: Coord<f64>) -> f64 {
+    let d = b - a;
+    let len2 = d.x * d.x + d.y * d.y;
+    let t = if len2 > 0.0 { (((p.x - a.x) * d.x + (p.y - a.y) * d.y) / len2).clamp(0.0, 1.0) } else { 0.0 };
+    let q = a + d * t - p;
+    q.x.hypot(q.y)
+}
+
+/// Every ring of a sketch, exteriors and holes alike.
+fn rings(sketch: &Sketch<()>) -> Vec<LineString<f64>> {
+    slicer::polygons(sketch)
+        .iter()
+        .flat_map(|p| std::iter::once(p.exterior()).chain(p.interiors()))
+        .filter(|r| r.0.len() >= 2)
+        .cloned()
+        .collect()
+}
+
+/// Passes clearing the inside of `slice` with a tool of radius `r`, `step`
+/// apart. Zig-zag rows are followed by one pass around the walls to take
+/// off the scallops they leave there.
+fn pocket(slice: &Sketch<()>, r: f64, step: f64, pattern: PocketPattern) -> Vec<LineString<f64>> {
+    let region = slice.offset(-r);
+    match pattern {
+        PocketPattern::Zigzag => {
+            let mut out = slicer::hatch(&slicer::polygons(&region), step, 0.0);
+            out.extend(rings(&region));
+            out
+        }
+        PocketPattern::Spiral => {
+            let mut out = Vec::new();
+            for k in 0..MAX_RINGS {
+                let ring = rings(&region.offset(-(k as f64) * step));
+                if ring.is_empty() {
+                    break;
+                }
+                out.push(ring);
+            }
+            out.into_iter().rev().flatten().collect()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use geo::{Geometry, GeometryCollection, Polygon};
+
+    use super::*;
+
+    fn rectangle(w: f64, h: f64) -> Sketch<()> {
+        let ring = LineString::from(vec![(0.0, 0.0), (w, 0.0), (w, h), (0.0, h), (0.0, 0.0)]);
+        Sketch::from_geo(GeometryCollection(vec![Geometry::Polygon(Polygon::new(ring, Vec::new()))]), None)
+    }
+
+    fn v_settings(v_angle: f64, depth: f64, depth_per_pass: f64) -> CamSettings {
+        CamSettings { strategy: Strategy::VCarve, v_angle, depth, depth_per_pass, ..CamSettings::default() }
+    }
+
+    fn lowest(paths: &[Vec<[f64; 3]>]) -> f64 {
+        paths.iter().flatten().map(|p| p[2]).fold(f64::INFINITY, f64::min)
+    }
+
+    #[test]
+    fn inscribed_radius_is_half_the_gap() {
+        let lines = [LineString::from(vec![(0.0, 0.0), (10.0, 0.0)]), LineString::from(vec![(0.0, 2.0), (10.0, 2.0)])];
+        let outline = Outline::new(&lines, 1.0);
+        let r = inscribed_radius(&outline, Coord { x: 5.0, y: 0.0 }, Coord { x: 0.0, y: 1.0 }, 5.0, 1e-6);
+        assert!((r - 1.0).abs() < 1e-5);
+        assert_eq!(inscribed_radius(&outline, Coord { x: 5.0, y: 0.0 }, Coord { x: 0.0, y: 1.0 }, 0.5, 1e-6), 0.5);
+    }
+
+    #[test]
+    fn v_carve_stroke_depth_follows_its_width() {
+        // a 2 mm stroke: half-width 1 over tan(angle / 2)
+        for angle in [60.0, 90.0] {
+            let paths = v_carve(&rectangle(40.0, 2.0), &v_settings(angle, 5.0, 5.0), 0.5, 0.0);
+            let expected = 1.0 / (angle * 0.5_f64).to_radians().tan();
+            assert!((lowest(&paths) + expected).abs() < 1e-3, "{angle}°: {}", lowest(&paths));
+        }
+    }
+
+    #[test]
+    fn v_carve_stops_at_the_depth_in_passes() {
+        let paths = v_carve(&rectangle(40.0, 20.0), &v_settings(90.0, 2.0, 0.5), 0.5, 10.0);
+        assert!((lowest(&paths) - 8.0).abs() < 1e-9);
+        // every pass leaves its own floor
+        for floor in [9.5, 9.0, 8.5, 8.0] {
+            assert!(paths.iter().any(|path| (lowest(std::slice::from_ref(path)) - floor).abs() < 1e-9), "{floor}");
+        }
+        // the flat bottom beyond the bit's reach is cleared at full depth
+        assert!(paths.iter().any(|path| path.iter().all(|p| (p[2] - 8.0).abs() < 1e-9)));
+    }
+
+    #[test]
+    fn v_carve_without_outline_or_depth_is_empty() {
+        assert!(v_carve(&Sketch::new(), &v_settings(90.0, 2.0, 0.5), 0.5, 0.0).is_empty());
+        assert!(v_carve(&rectangle(10.0, 10.0), &v_settings(90.0, 0.0, 0.5), 0.5, 0.0).is_empty());
+    }
+}
@@ -0,0 +1,171 @@
+//! Fixtures holding the stock, and collisions of the tool holder with the
+//! stock and of tool or holder with the fixtures.
+//!
+//! Fixtures (clamps, vise jaws, locating pins) are placed on the bed like
+//! the stock: they are never sliced, and count as keep-out geometry. The
+//! toolpath generators leave them a tool radius clear (see
+//! [`crate::cam`] and [`crate::engine::mill`]), and neither tool nor holder
+//! may enter them, cutting or not.
+//!
+//! The endmill sticks `length` out of its holder, modelled as a plain
+//! cylinder above the tool. While a program is simulated (see
+//! [`crate::milling::Simulation`]) every sample along a move checks the
+//! material left under the holder's face: anything standing higher than
+//! the stick-out would be hit by the holder. Each offending move is
+//! flagged once, with the first place it hits.
+
+use geo::{Coord, LineString, Polygon};
+use serde::{Deserialize, Serialize};
+
+/// Cylinder the tool is held in.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Holder {
+    /// mm
+    pub diameter: f64,
+    pub length: f64,
+}
+
+impl Default for Holder {
+    fn default() -> Self {
+        Self { diameter: 30.0, length: 40.0 }
+    }
+}
+
+/// Endmill and holder as one body, positioned by the tool tip.
+#[derive(Clone, Debug)]
+pub struct ToolAssembly {
+    pub diameter: f64,
+    /// Stick-out below the holder (mm).
+    pub length: f64,
+    pub holder: Holder,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FixtureKind {
+    #[default]
+    Clamp,
+    ViseJaw,
+    /// Round locating pin, standing in the middle of its box.
+    Pin,
+}
+
+impl FixtureKind {
+    pub const ALL: [FixtureKind; 3] = [FixtureKind::Clamp, FixtureKind::ViseJaw, FixtureKind::Pin];
+}
+
+impl std::fmt::Display for FixtureKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            FixtureKind::Clamp => "Clamp",
+            FixtureKind::ViseJaw => "Vise jaw",
+            FixtureKind::Pin => "Pin",
+        })
+    }
+}
+
+/// Something on the bed nothing may run into.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Fixture {
+    pub name: String,
+    #[serde(default)]
+    pub kind: FixtureKind,
+    /// Opposite corners of its bounding box (mm).
+    pub min: [f64; 3],
+    pub max: [f64; 3],
+}
+
+/// Sides of the polygon a pin's footprint is drawn and clipped with.
+const PIN_SIDES: usize = 24;
+
+impl Fixture {
+    /// A fixture of typical size at the middle of the bed.
+    pub fn new(kind: FixtureKind, name: impl Into<String>) -> Self {
+        let (half, height) = match kind {
+            FixtureKind::Clamp => ([10.0, 20.0], 15.0),
+            FixtureKind::ViseJaw => ([60.0, 6.0], 40.0),
+            FixtureKind::Pin => ([3.0, 3.0], 10.0),
+        };
+        Self { name: name.into(), kind, min: [-half[0], -half[1], 0.0], max: [half[0], half[1], height] }
+    }
+
+    pub fn top(&self) -> f64 {
+        self.max[2]
+    }
+
+    fn centre(&self) -> [f64; 2] {
+        [(self.min[0] + self.max[0]) * 0.5, (self.min[1] + self.max[1]) * 0.5]
+    }
+
+    fn pin_radius(&self) -> f64 {
+        (self.max[0] - self.min[0]).min(self.max[1] - self.min[1]) * 0.5
+    }
+
+    /// Distance in XY from `(x, y)` to the fixture's footprint.
+    pub fn distance(&self, x: f64, y: f64) -> f64 {
+        match self.kind {
+            FixtureKind::Pin => {
+                let [cx, cy] = self.centre();
+                ((x - cx).hypot(y - cy) - self.pin_radius()).max(0.0)
+            }
+            FixtureKind::Clamp | FixtureKind::ViseJaw => {
+                let dx = (self.min[0] - x).max(x - self.max[0]).max(0.0);
+                let dy = (self.min[1] - y).max(y - self.max[1]).max(0.0);
+                dx.hypot(dy)
+            }
+        }
+    }
+
+    /// Whether a vertical cylinder of `radius` around `(x, y)`, from `z0`
+    /// up to `z1`, reaches into the fixture.
+    fn meets_cylinder(&self, x: f64, y: f64, radius: f64, z0: f64, z1: f64) -> bool {
+        z1 > self.min[2] && z0 < self.max[2] && self.distance(x, y) < radius
+    }
+
+    /// Outline of the footprint grown by `margin`, counter-clockwise.
+    pub fn footprint(&self, margin: f64) -> Polygon<f64> {
+        let ring: Vec<Coord<f64>> = match self.kind {
+            FixtureKind::Pin => {
+                let ([cx, cy], r) = (self.centre(), self.pin_radius() + margin);
+                (0..PIN_SIDES)
+                    .map(|k| {
+                        let a = std::f64::consts::TAU * k as f64 / PIN_SIDES as f64;
+                        Coord { x: cx + r * a.cos(), y: cy + r * a.sin() }
+                    })
+                    .collect()
+            }
+            // square corners: a little more clearance than needed there
+            FixtureKind::Clamp | FixtureKind::ViseJaw => {
+                let (lo, hi) = ([self.min[0] - margin, self.min[1] - margin], [self.max[0] + margin, self.max[1] + margin]);
+                vec![Coord { x: lo[0], y: lo[1] }, Coord { x: hi[0], y: lo[1] }, Coord { x: hi[0], y: hi[1] }, Coord { x: lo[0], y: hi[1] }]
+            }
+        };
+        Polygon::new(LineString::from(ring), Vec::new())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Hit {
+    /// The holder runs into material.
+    Stock,
+    /// Tool or holder enters this fixture.
+    Fixture(usize),
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Collision {
+    /// Index of the move, in the program's moves.
+    pub index: usize,
+    /// Tool-tip position where it first hits.
+    pub at: [f64; 3],
+    pub hit: Hit,
+}
+
+/// First fixture the assembly enters with its tip at `p`.
+pub fn fixture_hit(tool: &ToolAssembly, fixtures: &[Fixture], p: [f64; 3]) -> Option<usize> {
+    let face = p[2] + tool.length;
+    fixtures.iter().position(|b| {
+        b.meets_cylinder(p[0], p[1], tool.diameter * 0.5, p[2], face)
+            || b.meets_cylinder(p[0], p[1], tool.holder.diameter * 0.5, face, face + tool.holder.length)
+    })
+}
@@ -0,0 +1,1944 @@
+//! Live machine controls shown in the Control tab's right-hand panel.
+
+use crate::machine::{Auth, AuxControl, AuxOutput, Firmware, LimitCheck, Override, Scheme, SoftLimits};
+use crate::job::{Job, PauseKind, format_duration};
+use crate::{AluminaApp, Tool, engine, net::Endpoint, send_queue_command, spawn_file_picker};
+use csgrs::{sketch::Sketch, traits::CSG};
+use eframe::egui;
+use std::sync::Arc;
+
+/// Window title while no job is running (matches `index.html`).
+pub(crate) const PAGE_TITLE: &str = "Alumina CNC";
+
+impl AluminaApp {
+    /// Right-hand "Machine" panel: profile, overrides and other live controls.
+    pub(crate) fn machine_panel_ui(&mut self, ui: &mut egui::Ui) {
+        ui.heading("Machine");
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            ui.label("Firmware:");
+            let before = self.machine.firmware;
+            egui::ComboBox::from_id_salt("firmware_select")
+                .selected_text(self.machine.firmware.to_string())
+                .show_ui(ui, |ui| {
+                    for fw in [Firmware::Alumina, Firmware::Marlin, Firmware::Grbl] {
+                        ui.selectable_value(&mut self.machine.firmware, fw, fw.to_string());
+                    }
+                });
+            if self.machine.firmware != before {
+                self.machine.save();
+            }
+        });
+        ui.collapsing("Connection", |ui| self.connection_ui(ui));
+        crate::net::link_ui(ui);
+        if ui.button("Setup wizard…").on_hover_text("Machine type, work area, origin and a connection test").clicked() {
+            self.open_wizard();
+        }
+        if ui.button("Align workpiece…").on_hover_text("Find the workpiece on the bed with the camera").clicked() {
+            self.alignment.open = true;
+        }
+        if self.alignment.active().is_some() {
+            ui.weak("Jobs are aligned to the workpiece");
+        }
+
+        ui.separator();
+        egui::CollapsingHeader::new("Job")
+            .default_open(true)
+            .show(ui, |ui| {
+                self.job_ui(ui);
+                self.job_queue_ui(ui);
+                self.trail_ui(ui);
+            });
+
+        ui.separator();
+        egui::CollapsingHeader::new("Position")
+            .default_open(true)
+            .show(ui, |ui| self.dro_ui(ui));
+
+        ui.collapsing("Homing", |ui| {
+            let homing = self.homing.is_some();
+            let busy = homing || self.jobs.is_running();
+            ui.horizontal(|ui| {
+                for (i, name) in ["X", "Y", "Z"].into_iter().enumerate() {
+                    if ui
+                        .add_enabled(!busy, egui::Button::new(format!("Home {name}")))
+                        .clicked()
+                    {
+                        self.start_homing(Some(i));
+                    }
+                }
+            });
+            ui.horizontal(|ui| {
+                if ui.add_enabled(!busy, egui::Button::new("Home all")).clicked() {
+                    self.start_homing(None);
+                }
+                if homing {
+                    ui.spinner();
+                }
+            });
+            ui.horizontal(|ui| {
+                for (name, homed) in ["X", "Y", "Z"].into_iter().zip(self.homed) {
+                    let (col, txt) = if homed {
+                        (egui::Color32::from_rgb(80, 200, 120), "homed")
+                    } else {
+                        (ui.visuals().warn_fg_color, "unhomed")
+                    };
+                    ui.colored_label(col, format!("{name}: {txt}"));
+                }
+            });
+            if ui.checkbox(&mut self.machine.require_homing, "Require homing before moves").changed() {
+                self.machine.save();
+            }
+        });
+
+        if let Some(msg) = &self.limit_warning {
+            ui.colored_label(ui.visuals().warn_fg_color, msg);
+        }
+        egui::CollapsingHeader::new("Jog")
+            .default_open(true)
+            .show(ui, |ui| self.jog_ui(ui));
+        ui.collapsing("Move to", |ui| {
+            ui.horizontal(|ui| {
+                for (name, v) in ["X", "Y", "Z"].into_iter().zip(self.goto_target.iter_mut()) {
+                    ui.label(name);
+                    ui.add(egui::DragValue::new(v).speed(1.0));
+                }
+            });
+            if ui.add_enabled(!self.jobs.is_running(), egui::Button::new("Go (G0)")).clicked() {
+                let [x, y, z] = self.goto_target;
+                self.send_motion(&format!("G90 G0 X{x:.3} Y{y:.3} Z{z:.3}"));
+            }
+        });
+
+        ui.collapsing("Travel limits", |ui| {
+            let mut changed = false;
+            egui::Grid::new("travel_limits").num_columns(3).show(ui, |ui| {
+                ui.label("");
+                ui.label("min");
+                ui.label("max");
+                ui.end_row();
+                for (i, name) in ["X", "Y", "Z"].into_iter().enumerate() {
+                    ui.label(name);
+                    // kept apart so the envelope cannot be turned inside out
+                    let (min, max) = (self.machine.travel_min[i], self.machine.travel_max[i]);
+                    changed |= ui.add(egui::DragValue::new(&mut self.machine.travel_min[i]).range(f64::NEG_INFINITY..=max)).changed();
+                    changed |= ui.add(egui::DragValue::new(&mut self.machine.travel_max[i]).range(min..=f64::INFINITY)).changed();
+                    ui.end_row();
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.label("Out of range:");
+                egui::ComboBox::from_id_salt("soft_limits")
+                    .selected_text(self.machine.soft_limits.to_string())
+                    .show_ui(ui, |ui| {
+                        for m in [SoftLimits::Refuse, SoftLimits::Clamp, SoftLimits::Off] {
+                            changed |= ui.selectable_value(&mut self.machine.soft_limits, m, m.to_string()).changed();
+                        }
+                    });
+            });
+            if changed {
+                self.machine.save();
+            }
+        });
+
+        if matches!(self.selected_tool, Tool::Laser | Tool::Plasma | Tool::Endmill) {
+            ui.separator();
+            ui.collapsing("Test fire", |ui| self.test_fire_ui(ui));
+        } else if self.test_fire.is_firing() {
+            self.stop_test_fire();
+        }
+
+        ui.separator();
+        ui.collapsing("Z offset (babystep)", |ui| self.babystep_ui(ui));
+
+        ui.separator();
+        ui.collapsing("Outputs", |ui| self.aux_outputs_ui(ui));
+
+        ui.separator();
+        ui.collapsing("Fan", |ui| self.fan_ui(ui));
+
+        ui.separator();
+        ui.collapsing("Overrides", |ui| {
+            let fw = self.machine.firmware;
+            let power_tool = matches!(self.selected_tool, Tool::Laser | Tool::Plasma | Tool::Endmill);
+            let flow_tool = self.selected_tool == Tool::Extruder;
+
+            override_slider(ui, fw, Override::Feed, "Feed", &mut self.feed_override, true);
+            override_slider(ui, fw, Override::Power, "Power", &mut self.power_override, power_tool);
+            override_slider(ui, fw, Override::Flow, "Flow", &mut self.flow_override, flow_tool);
+
+            if ui.button("Reset all to 100 %").clicked() {
+                for (which, value, shown) in [
+                    (Override::Feed, &mut self.feed_override, true),
+                    (Override::Power, &mut self.power_override, power_tool),
+                    (Override::Flow, &mut self.flow_override, flow_tool),
+                ] {
+                    *value = 100;
+                    if let Some(cmd) = fw.override_command(which, 100).filter(|_| shown) {
+                        crate::net::send_all(cmd);
+                    }
+                }
+            }
+        });
+    }
+}
+
+impl AluminaApp {
+    /// Manual part-cooling fan slider; sent when released.
+    fn fan_ui(&mut self, ui: &mut egui::Ui) {
+        let fw = self.machine.firmware;
+        let supported = fw.fan_command(0.0).is_some();
+        ui.horizontal(|ui| {
+            let label = ui.label("Part fan:");
+            let resp = ui
+                .add_enabled(supported, egui::Slider::new(&mut self.fan_manual, 0.0..=100.0).suffix(" %"))
+                .labelled_by(label.id)
+                .on_disabled_hover_text(format!("{fw} has no fan command; switch it under Outputs if wired to one"));
+            let mut send = resp.drag_stopped() || (resp.changed() && !resp.dragged());
+            if ui.add_enabled(supported, egui::Button::new("Off")).clicked() {
+                self.fan_manual = 0.0;
+                send = true;
+            }
+            if send {
+                if let Some(cmd) = fw.fan_command(self.fan_manual) {
+                    send_queue_command(cmd);
+                }
+            }
+        });
+    }
+
+    /// Controller address and credentials, stored with the machine profile.
+    fn connection_ui(&mut self, ui: &mut egui::Ui) {
+        let conn = &mut self.machine.connection;
+        let mut changed = self.discovery.ui(ui, conn);
+
+        egui::Grid::new("connection_settings").num_columns(2).show(ui, |ui| {
+            ui.label("Protocol:");
+            egui::ComboBox::from_id_salt("connection_scheme")
+                .selected_text(match conn.scheme {
+                    Scheme::Http => "HTTP / WS",
+                    Scheme::Https => "HTTPS / WSS",
+                })
+                .show_ui(ui, |ui| {
+                    changed |= ui.selectable_value(&mut conn.scheme, Scheme::Http, "HTTP / WS").changed();
+                    changed |= ui.selectable_value(&mut conn.scheme, Scheme::Https, "HTTPS / WSS").changed();
+                });
+            ui.end_row();
+
+            let label = ui.label("Host:");
+            changed |= ui
+                .add(egui::TextEdit::singleline(&mut conn.host).hint_text(crate::net::default_url()).desired_width(140.0))
+                .labelled_by(label.id)
+                .on_hover_text("Leave empty for the default")
+                .changed();
+            ui.end_row();
+
+            let label = ui.label("Port:");
+            ui.horizontal(|ui| {
+                let mut custom = conn.port.is_some();
+                changed |= ui.checkbox(&mut custom, "").labelled_by(label.id).changed();
+                if custom {
+                    let port = conn.port.get_or_insert(80);
+                    changed |= ui.add(egui::DragValue::new(port).range(1..=65535)).changed();
+                } else {
+                    conn.port = None;
+                    ui.weak("default");
+                }
+            });
+            ui.end_row();
+
+            let label = ui.label("Base path:");
+            changed |= ui
+                .add(egui::TextEdit::singleline(&mut conn.base_path).hint_text("/").desired_width(140.0))
+                .labelled_by(label.id)
+                .on_hover_text("Prefix of every firmware endpoint, e.g. /api for /api/queue")
+                .changed();
+            ui.end_row();
+
+            ui.label("Authentication:");
+            let kind = |a: &Auth| match a {
+                Auth::None => "None",
+                Auth::Token(_) => "API token",
+                Auth::Basic { .. } => "Basic",
+            };
+            egui::ComboBox::from_id_salt("connection_auth")
+                .selected_text(kind(&conn.auth))
+                .show_ui(ui, |ui| {
+                    for option in [Auth::None, Auth::Token(String::new()), Auth::Basic { user: String::new(), password: String::new() }] {
+                        let selected = kind(&option) == kind(&conn.auth);
+                        if ui.selectable_label(selected, kind(&option)).clicked() && !selected {
+                            conn.auth = option;
+                            changed = true;
+                        }
+                    }
+                });
+            ui.end_row();
+
+            match &mut conn.auth {
+                Auth::None => {}
+                Auth::Token(token) => {
+                    let label = ui.label("Token:");
+                    changed |= ui.add(egui::TextEdit::singleline(token).password(true)).labelled_by(label.id).changed();
+                    ui.end_row();
+                }
+                Auth::Basic { user, password } => {
+                    let label = ui.label("User:");
+                    changed |= ui.text_edit_singleline(user).labelled_by(label.id).changed();
+                    ui.end_row();
+                    let label = ui.label("Password:");
+                    changed |= ui.add(egui::TextEdit::singleline(password).password(true)).labelled_by(label.id).changed();
+                    ui.end_row();
+                }
+            }
+        });
+        if !matches!(conn.auth, Auth::None) {
+            ui.weak("Credentials are saved with the machine profile and included in settings exports.");
+        }
+        ui.weak(format!("Requests go to {}", display_url(&crate::net::base_url())));
+
+        if changed {
+            crate::net::configure(&self.machine.connection);
+            self.machine.save();
+        }
+    }
+}
+
+fn display_url(url: &str) -> &str {
+    if url.is_empty() { "this page's controller" } else { url }
+}
+
+/// One percentage slider; the command is sent when the user lets go, not on
+/// every intermediate drag value, so the queue isn't flooded.
+fn override_slider(
+    ui: &mut egui::Ui,
+    fw: Firmware,
+    which: Override,
+    label: &str,
+    value: &mut u32,
+    applicable: bool,
+) {
+    if !applicable {
+        return;
+    }
+    let supported = fw.override_command(which, *value).is_some();
+    ui.horizontal(|ui| {
+        ui.label(format!("{label}:"));
+        let resp = ui
+            .add_enabled(supported, egui::Slider::new(value, 10..=200).suffix(" %"))
+            .on_disabled_hover_text(format!("{fw} has no {label} override"));
+        if resp.drag_stopped() || (resp.changed() && !resp.dragged()) {
+            if let Some(cmd) = fw.override_command(which, *value) {
+                crate::net::send_all(cmd);
+            }
+        }
+    });
+}
+
+// ---------- job recovery ----------------------------------------------------------------------------
+
+/// State of the "resume interrupted job" dialog.
+pub struct Recovery {
+    job: Job,
+    /// 1-based line to resume at.
+    line: usize,
+    layer: usize,
+    by_layer: bool,
+}
+
+impl Recovery {
+    pub fn new(job: Job, line: usize) -> Self {
+        let layer = job.layer_of_line(line).unwrap_or(1);
+        Self {
+            job,
+            line,
+            layer,
+            by_layer: false,
+        }
+    }
+}
+
+// ---------- filament change -------------------------------------------------------------------------
+
+/// Values used by the filament-change dialog.
+pub struct FilamentChange {
+    pub retract_mm: f64,
+    pub load_mm: f64,
+    pub temp_c: f64,
+}
+
+impl Default for FilamentChange {
+    fn default() -> Self {
+        Self {
+            retract_mm: 50.0,
+            load_mm: 60.0,
+            temp_c: 210.0,
+        }
+    }
+}
+
+// ---------- test fire -------------------------------------------------------------------------------
+
+/// Absolute ceiling for a single test-fire pulse, whatever the user sets.
+const TEST_FIRE_HARD_CAP_MS: f64 = 5000.0;
+/// Arming lapses this long after the safety prompt was acknowledged.
+const TEST_FIRE_ARM_MS: f64 = 30_000.0;
+
+/// Momentary tool activation for focusing / alignment.
+pub struct TestFire {
+    pub power_pct: f32,
+    /// Pulse length; the firmware dwells this long between switching the
+    /// tool on and off, so the pulse ends even if the link drops.
+    pub max_ms: f64,
+    /// When the user acknowledged the safety prompt, while armed.
+    armed_ms: Option<f64>,
+    confirm_open: bool,
+    /// While firing: time at which the pulse is over.
+    until: Option<f64>,
+}
+
+impl Default for TestFire {
+    fn default() -> Self {
+        Self {
+            power_pct: 1.0,
+            max_ms: 500.0,
+            armed_ms: None,
+            confirm_open: false,
+            until: None,
+        }
+    }
+}
+
+impl TestFire {
+    pub fn is_firing(&self) -> bool {
+        self.until.is_some()
+    }
+}
+
+// ---------- jogging ---------------------------------------------------------------------------------
+
+/// Step sizes offered for jogging (mm).
+pub const JOG_STEPS: [f64; 3] = [0.1, 1.0, 10.0];
+
+/// Length of one continuous-jog move, as travel time at the jog feed (s).
+/// The next is sent once this one is acknowledged. Firmware acknowledges a
+/// move once it is queued rather than done, so when the button is let go
+/// the rest is dropped with [`Firmware::jog_cancel`].
+const JOG_MOVE_S: f64 = 0.2;
+
+/// Manual jogging from the Machine panel.
+pub struct Jog {
+    /// Distance per click (mm).
+    pub step: f64,
+    /// mm/min
+    pub feed: f64,
+    /// Keep moving while a button is held instead of one step per click.
+    pub continuous: bool,
+    /// Reply to the last jog move.
+    pending: Option<crate::net::Pending>,
+    /// A continuous jog is under way, to be cancelled on release.
+    moving: bool,
+}
+
+impl Default for Jog {
+    fn default() -> Self {
+        Self {
+            step: 1.0,
+            feed: 1000.0,
+            continuous: false,
+            pending: None,
+            moving: false,
+        }
+    }
+}
+
+// ---------- homing ----------------------------------------------------------------------------------
+
+/// Time between position queries while waiting for homing to finish (ms).
+const HOME_POLL_MS: f64 = 250.0;
+
+/// A homing cycle under way. Firmware may acknowledge the command once it
+/// is queued, so the axes count as homed only once position reports show
+/// the machine has stopped: two alike in a row, and for GRBL no longer in
+/// its `Home` state.
+pub struct Homing {
+    axes: Vec<usize>,
+    /// Reply to the homing command, then to the latest position query.
+    reply: crate::net::Pending,
+    acked: bool,
+    /// Last position reported since the command was acknowledged.
+    last: Option<[f64; 3]>,
+    /// When to ask for the position next (ms).
+    query_at: Option<f64>,
+}
+
+// ---------- position / DRO --------------------------------------------------------------------------
+
+/// Poll intervals offered for the readout (ms).
+pub const DRO_INTERVALS: [f64; 5] = [100.0, 200.0, 500.0, 1000.0, 2000.0];
+
+/// Reports older than this many poll intervals are shown as stale.
+const DRO_STALE_INTERVALS: f64 = 3.0;
+
+/// Last reported tool position.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Position {
+    /// Machine coordinates X, Y, Z, A (mm / deg).
+    pub machine: [f64; 4],
+    /// Work coordinates when the firmware reports them separately.
+    pub work: Option<[f64; 4]>,
+    /// Whether a fourth (rotary) axis was reported.
+    pub has_a: bool,
+    /// Whether `machine` really is in machine coordinates: Marlin's `M114`
+    /// reports them shifted by `G92`, and a GRBL report may only carry
+    /// work coordinates.
+    pub exact: bool,
+}
+
+impl Position {
+    /// Coordinates shown on the readout: work if known, else machine.
+    pub fn display(&self) -> [f64; 4] {
+        self.work.unwrap_or(self.machine)
+    }
+}
+
+/// Parse a position report. Understands:
+///
+/// * Alumina JSON: `{"x":1,"y":2,"z":3}` or `{"mpos":[..],"wpos":[..]}`
+/// * GRBL status: `<Idle|MPos:1.000,2.000,3.000|FS:0,0|WCO:…>`, with
+///   `MPos` or `WPos` (per `$10`)
+/// * Marlin `M114`: `X:1.00 Y:2.00 Z:3.00 E:0.00 Count X:…`
+///
+/// GRBL sends its work coordinate offset (`WCO`) only now and then, so it
+/// is kept in `wco` from one report to the next and gives the coordinate
+/// set the report leaves out (WPos = MPos − WCO).
+pub fn parse_position(body: &str, wco: &mut Option<[f64; 4]>) -> Option<Position> {
+    let body = body.trim();
+
+    if let Ok(serde_json::Value::Object(map)) = serde_json::from_str::<serde_json::Value>(body) {
+        let arr = |key: &str| -> Option<([f64; 4], bool)> {
+            let list = map.get(key)?.as_array()?;
+            let mut out = [0.0; 4];
+            for (o, v) in out.iter_mut().zip(list) {
+                *o = v.as_f64()?;
+            }
+            Some((out, list.len() > 3))
+        };
+        if let Some((machine, has_a)) = arr("mpos") {
+            return Some(Position {
+                machine,
+                work: arr("wpos").map(|(w, _)| w),
+                has_a,
+                exact: true,
+            });
+        }
+        let axis = |k: &str| map.get(k).and_then(serde_json::Value::as_f64);
+        return Some(Position {
+            machine: [axis("x")?, axis("y")?, axis("z")?, axis("a").unwrap_or(0.0)],
+            work: None,
+            has_a: map.contains_key("a"),
+            exact: true,
+        });
+    }
+
+    // over serial the status report comes with the `ok` for the line it was sent on
+    if let Some(status) = body.lines().map(str::trim).find(|l| l.starts_with('<')) {
+        let field = |name: &str| -> Option<([f64; 4], bool)> {
+            let rest = status.split('|').find_map(|f| f.strip_prefix(name))?;
+            let rest = rest.trim_end_matches('>');
+            let mut out = [0.0; 4];
+            let mut n = 0;
+            for (o, v) in out.iter_mut().zip(rest.split(',')) {
+                *o = v.trim().parse().ok()?;
+                n += 1;
+            }
+            (n >= 3).then_some((out, n > 3))
+        };
+        if let Some((offset, _)) = field("WCO:") {
+            *wco = Some(offset);
+        }
+        let shift = |p: [f64; 4], by: [f64; 4], sign: f64| [0, 1, 2, 3].map(|i| p[i] + sign * by[i]);
+        return match (field("MPos:"), field("WPos:"), *wco) {
+            (Some((machine, has_a)), w, offset) => Some(Position {
+                machine,
+                work: w.map(|(p, _)| p).or(offset.map(|o| shift(machine, o, -1.0))),
+                has_a,
+                exact: true,
+            }),
+            (None, Some((work, has_a)), Some(offset)) => Some(Position {
+                machine: shift(work, offset, 1.0),
+                work: Some(work),
+                has_a,
+                exact: true,
+            }),
+            // no offset yet: the work coordinates stand in for the machine's
+            (None, Some((work, has_a)), None) => Some(Position { machine: work, work: None, has_a, exact: false }),
+            (None, None, _) => None,
+        };
+    }
+
+    // Marlin: ignore the stepper "Count" section
+    let text = body.split(" Count").next().unwrap_or(body);
+    let mut p = Position::default();
+    let mut seen = 0;
+    for tok in text.split_whitespace() {
+        let Some((k, v)) = tok.split_once(':') else { continue };
+        let Ok(v) = v.parse::<f64>() else { continue };
+        let idx = match k {
+            "X" => 0,
+            "Y" => 1,
+            "Z" => 2,
+            "A" => {
+                p.has_a = true;
+                3
+            }
+            _ => continue,
+        };
+        p.machine[idx] = v;
+        seen += 1;
+    }
+    (seen >= 3).then_some(p)
+}
+
+/// Ask the firmware for its position using the dialect's query.
+pub(crate) fn spawn_position_query(fw: Firmware) -> crate::net::Pending {
+    crate::net::spawn(match fw {
+        Firmware::Alumina => Endpoint::Position,
+        Firmware::Marlin => Endpoint::Queue("M114".into()),
+        // the status report, then the `ok` for the empty line left behind
+        Firmware::Grbl => Endpoint::Queue("?".into()),
+    })
+}
+
+/// Small orange crosshair + stem marking the tool position (scene coordinates).
+pub(crate) fn push_tool_marker(p: nalgebra::Vector3<f32>, size: f32, out: &mut Vec<f32>) {
+    const ORANGE: [f32; 3] = [1.0, 0.55, 0.0];
+    let c = ORANGE;
+    for (a, b) in [
+        (p - nalgebra::Vector3::x() * size, p + nalgebra::Vector3::x() * size),
+        (p - nalgebra::Vector3::y() * size, p + nalgebra::Vector3::y() * size),
+        (p, p + nalgebra::Vector3::z() * size * 4.0),
+    ] {
+        out.extend_from_slice(&[a.x, a.y, a.z, c[0], c[1], c[2], b.x, b.y, b.z, c[0], c[1], c[2]]);
+    }
+}
+
+impl AluminaApp {
+    /// Per-frame machine housekeeping (position polling, …), independent of the visible tab.
+    pub(crate) fn tick_machine(&mut self, ctx: &egui::Context) {
+        let now = crate::now_ms();
+
+        if let Some(slot) = &self.dro_reply {
+            let reply = slot.lock().unwrap().take();
+            if let Some(result) = reply {
+                self.dro_reply = None;
+                match result {
+                    Ok(body) => match parse_position(&body, &mut self.dro_wco) {
+                        Some(p) => {
+                            // keep soft-limit tracking in step with reality, though not
+                            // while a job streams ahead of the reports
+                            if p.exact && !self.jobs.is_running() {
+                                self.motion.pos = [p.machine[0], p.machine[1], p.machine[2]];
+                            }
+                            self.dro_pos = Some(p);
+                            self.dro_updated = now;
+                            self.dro_error = None;
+                        }
+                        None => {
+                            log::warn!("unrecognised position report: {body:?}");
+                            self.dro_error = Some("unrecognised position report".to_owned());
+                        }
+                    },
+                    Err(e) => {
+                        log::warn!("position query failed: {e}");
+                        self.dro_error = Some(e);
+                    }
+                }
+            }
+        }
+        if let Some(h) = &mut self.homing {
+            let mut finished = None;
+            if let Some(result) = h.reply.lock().unwrap().take() {
+                match result {
+                    Ok(_) if !h.acked => {
+                        h.acked = true;
+                        h.query_at = Some(now);
+                    }
+                    Ok(body) => match parse_position(&body, &mut self.dro_wco) {
+                        Some(p) => {
+                            let at = [p.machine[0], p.machine[1], p.machine[2]];
+                            if h.last == Some(at) && !body.lines().any(|l| l.trim_start().starts_with("<Home")) {
+                                finished = Some(Ok(p));
+                            } else {
+                                h.last = Some(at);
+                                h.query_at = Some(now + HOME_POLL_MS);
+                            }
+                        }
+                        None => finished = Some(Err(format!("unrecognised position report: {}", body.trim()))),
+                    },
+                    Err(e) => finished = Some(Err(e)),
+                }
+            }
+            if let Some(at) = h.query_at.filter(|&at| now >= at) {
+                h.query_at = None;
+                h.reply = spawn_position_query(self.machine.firmware);
+            }
+            match finished {
+                Some(Ok(p)) => {
+                    for &i in &h.axes {
+                        self.homed[i] = true;
+                    }
+                    self.homing = None;
+                    // moves are checked from where homing left the machine
+                    self.motion.pos = [p.machine[0], p.machine[1], p.machine[2]];
+                    self.dro_pos = Some(p);
+                    self.dro_updated = now;
+                    self.diag_log("homing complete");
+                }
+                Some(Err(e)) => {
+                    self.homing = None;
+                    log::error!("homing failed: {e}");
+                    crate::toasts::error("Homing failed", Some(e));
+                }
+                None => ctx.request_repaint_after(std::time::Duration::from_millis(100)),
+            }
+        }
+
+        if let Some(until) = self.test_fire.until {
+            if now >= until {
+                self.test_fire.until = None;
+            } else {
+                ctx.request_repaint_after(std::time::Duration::from_millis(10));
+            }
+        }
+        if let Some(armed) = self.test_fire.armed_ms {
+            if now - armed >= TEST_FIRE_ARM_MS {
+                self.test_fire.armed_ms = None;
+            } else {
+                ctx.request_repaint_after(std::time::Duration::from_millis(500));
+            }
+        }
+
+        self.tick_dry_run(ctx, now);
+        self.tick_job(ctx, now);
+        self.tick_trail();
+
+        if self.dro_poll && !self.dry_running() {
+            if self.dro_reply.is_none() && now - self.dro_last_poll >= self.dro_interval_ms {
+                self.dro_last_poll = now;
+                self.dro_reply = Some(spawn_position_query(self.machine.firmware));
+            }
+            ctx.request_repaint_after(std::time::Duration::from_millis(100));
+        }
+    }
+
+    /// Send a motion line after checking it against the profile's travel
+    /// limits. Returns `false` if the move was refused.
+    pub(crate) fn send_motion(&mut self, line: &str) -> bool {
+        match self.checked_motion(line) {
+            Some(l) => {
+                send_queue_command(l);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// `line` as it may be sent (clamped to the travel limits if need be),
+    /// or `None` if it is refused.
+    fn checked_motion(&mut self, line: &str) -> Option<String> {
+        if self.jobs.is_running() {
+            // it would land between the job's lines
+            crate::toasts::warn("Move refused while a job runs", Some(line.trim().to_owned()));
+            return None;
+        }
+        if self.machine.require_homing {
+            let unhomed: Vec<&str> = crate::machine::gcode_words(line)
+                .iter()
+                .filter_map(|(c, _)| match c {
+                    'X' => Some(0),
+                    'Y' => Some(1),
+                    'Z' => Some(2),
+                    _ => None,
+                })
+                .filter(|&i| !self.homed[i])
+                .map(|i| ["X", "Y", "Z"][i])
+                .collect();
+            if !unhomed.is_empty() {
+                let why = format!("`{}` refused: {} not homed", line.trim(), unhomed.join("/"));
+                log::warn!("{why}");
+                crate::toasts::warn("Move refused: axis not homed", Some(why.clone()));
+                self.limit_warning = Some(why);
+                return None;
+            }
+        }
+        match self.motion.check(line, &self.machine) {
+            LimitCheck::Pass(l) => {
+                self.limit_warning = None;
+                self.observe_outgoing(&l);
+                Some(l)
+            }
+            LimitCheck::Clamped(l, why) => {
+                log::warn!("soft limit: {why}");
+                self.diag_log(format!("soft limit: {why}"));
+                self.limit_warning = Some(why);
+                Some(l)
+            }
+            LimitCheck::Refused(why) => {
+                log::warn!("soft limit: {why}");
+                crate::toasts::warn("Move refused by soft limits", Some(why.clone()));
+                self.limit_warning = Some(why);
+                None
+            }
+        }
+    }
+
+    /// Move `axis` by `distance` at the jog feed: a relative move, then back
+    /// to absolute positioning, sent in that order. GRBL gets a `$J=` jog,
+    /// which leaves the modal state alone and can be cancelled.
+    fn jog(&mut self, axis: usize, distance: f64) {
+        let line = format!("G91 G1 {}{distance:.3} F{:.0}", ["X", "Y", "Z"][axis], self.jog.feed);
+        let checked = self.checked_motion(&line);
+        // the machine is put back in G90 after the move; so is the tracker
+        self.motion.absolute = true;
+        let Some(line) = checked else { return };
+        let grbl = self.machine.firmware == Firmware::Grbl;
+        let line = if grbl {
+            let words: String = crate::machine::gcode_words(&line)
+                .iter()
+                .filter(|(c, _)| "XYZF".contains(*c))
+                .map(|(c, v)| format!(" {c}{v:.3}"))
+                .collect();
+            format!("$J=G91{words}")
+        } else {
+            line
+        };
+        let slot: crate::net::Pending = Arc::new(std::sync::Mutex::new(None));
+        let target = Arc::clone(&slot);
+        crate::execute(async move {
+            let mut result = crate::net::fetch(&Endpoint::Queue(line)).await;
+            if result.is_ok() && !grbl {
+                result = crate::net::fetch(&Endpoint::Queue("G90".into())).await;
+            }
+            *target.lock().unwrap() = Some(result.map_err(|e| e.to_string()));
+        });
+        self.jog.pending = Some(slot);
+    }
+
+    /// X/Y/Z ± buttons with step size and feed. In continuous mode a held
+    /// button sends short moves back to back until it is let go, then
+    /// cancels the ones still queued. Off while a job runs.
+    fn jog_ui(&mut self, ui: &mut egui::Ui) {
+        if self.jobs.is_running() {
+            ui.weak("Jogging is off while a job runs");
+            return;
+        }
+        let reply = self.jog.pending.as_ref().and_then(|p| p.lock().unwrap().take());
+        if let Some(result) = reply {
+            self.jog.pending = None;
+            if let Err(e) = result {
+                crate::toasts::error("Jog move failed", Some(e));
+            }
+        }
+        let busy = self.jog.pending.is_some();
+
+        let mut clicked = None;
+        let mut held = None;
+        egui::Grid::new("jog_buttons").num_columns(4).show(ui, |ui| {
+            let mut button = |ui: &mut egui::Ui, axis: usize, dir: f64| {
+                let text = format!("{}{}", ["X", "Y", "Z"][axis], if dir > 0.0 { "+" } else { "−" });
+                let resp = ui.add_sized([36.0, 28.0], egui::Button::new(text));
+                if resp.clicked() {
+                    clicked = Some((axis, dir));
+                }
+                if resp.is_pointer_button_down_on() {
+                    held = Some((axis, dir));
+                }
+            };
+            ui.label("");
+            button(ui, 1, 1.0);
+            ui.label("");
+            button(ui, 2, 1.0);
+            ui.end_row();
+            button(ui, 0, -1.0);
+            ui.label("");
+            button(ui, 0, 1.0);
+            ui.label("");
+            ui.end_row();
+            ui.label("");
+            button(ui, 1, -1.0);
+            ui.label("");
+            button(ui, 2, -1.0);
+            ui.end_row();
+        });
+        ui.horizontal(|ui| {
+            ui.label("Step:");
+            for step in JOG_STEPS {
+                ui.selectable_value(&mut self.jog.step, step, format!("{step}"));
+            }
+            ui.label("mm");
+        });
+        ui.horizontal(|ui| {
+            let label = ui.label("Feed (mm/min):");
+            ui.add(egui::DragValue::new(&mut self.jog.feed).speed(10.0).range(1.0..=20_000.0)).labelled_by(label.id);
+        });
+        ui.checkbox(&mut self.jog.continuous, "Continuous")
+            .on_hover_text("Keep moving while a button is held");
+
+        if self.jog.continuous {
+            if let Some((axis, dir)) = held {
+                if !busy {
+                    self.jog(axis, dir * self.jog.feed / 60.0 * JOG_MOVE_S);
+                    self.jog.moving = true;
+                }
+                ui.ctx().request_repaint();
+            } else if self.jog.moving {
+                self.jog.moving = false;
+                crate::net::send(self.machine.firmware.jog_cancel());
+                // where it stopped is unknown; ask rather than trust the tracker
+                if self.dro_reply.is_none() {
+                    self.dro_reply = Some(spawn_position_query(self.machine.firmware));
+                }
+            }
+        } else if let Some((axis, dir)) = clicked {
+            self.jog(axis, dir * self.jog.step);
+        }
+        if busy {
+            ui.ctx().request_repaint_after(std::time::Duration::from_millis(50));
+        }
+    }
+
+    /// Make `text` the job (or queue it behind the running one):
+    /// post-processed, aligned to the stock and, for the extruder, with its
+    /// pauses and fan schedule.
+    fn load_program(&mut self, name: &str, text: String) {
+        let text = crate::plugins::post_process(text.clone()).unwrap_or_else(|e| {
+            crate::toasts::warn("Post-processor failed; job left unchanged", Some(e.to_string()));
+            text
+        });
+        let text = match self.alignment.active() {
+            Some(t) => crate::alignment::transform_program(&text, &t),
+            None => text,
+        };
+        let mut job = Job::from_gcode(name, &text);
+        self.diag_log(format!("job loaded: {} lines", job.total_lines()));
+        if self.selected_tool == Tool::Extruder && !self.pause_heights.is_empty() {
+            let n = job.insert_pauses_at_z(&self.pause_heights);
+            self.diag_log(format!("inserted {n} of {} hardware pause(s)", self.pause_heights.len()));
+        }
+        if self.selected_tool == Tool::Extruder && self.fan.apply_to_jobs {
+            let n = job.apply_fan(&self.fan, self.machine.firmware);
+            self.diag_log(format!("fan schedule applied: {n} fan command(s)"));
+        }
+        let name = job.name.clone();
+        if self.jobs.load(job) {
+            crate::toasts::info(format!("{name} queued behind the running job"));
+        }
+    }
+
+    /// Laser or plasma job from the flat part, or from every layer of the
+    /// models, with the selected tool's settings.
+    fn generate_cut_job(&mut self) {
+        // scene coordinates have the bed centred on the origin, the machine's start at its corner
+        let (dx, dy) = (f64::from(self.work_size.x) * 0.5, f64::from(self.work_size.y) * 0.5);
+        let home = geo::Coord { x: 0.0, y: 0.0 };
+        let kerf = if self.selected_tool == Tool::Plasma { self.plasma.kerf } else { f64::from(self.kerf) };
+        let (common_line, optimize) = (self.common_line, self.optimize_order);
+        let z_offset = self.machine.z_offset;
+        // parts stay apart until cut_layer, so edges they share can be merged
+        let layer = |parts: Vec<Sketch<()>>, z| {
+            let parts: Vec<Sketch<()>> = parts.iter().map(|p| p.translate(dx, dy, 0.0)).collect();
+            engine::cut_layer(&parts, z, kerf, common_line, optimize, home)
+        };
+        let layers: Vec<engine::CutLayer> = if let Some(part) = self.flat_source() {
+            vec![layer(engine::split_parts(part), (z_offset != 0.0).then_some(z_offset))]
+        } else {
+            let plan = self.layer_plan().to_vec();
+            plan.iter()
+                .map(|l| {
+                    let parts = engine::slice_parts(self.models.iter().map(|m| &m.mesh), l.slice_z())
+                        .iter()
+                        .map(|p| engine::simplify_slice(p, self.simplify_tolerance).0)
+                        .collect();
+                    layer(parts, Some(f64::from(l.top()) + z_offset))
+                })
+                .collect()
+        };
+        if layers.iter().all(engine::CutLayer::is_empty) {
+            crate::toasts::warn("Nothing to cut: load a model or send a sketch from the Design tab", None);
+            return;
+        }
+        let fw = self.machine.firmware;
+        if self.selected_tool == Tool::Plasma {
+            let text = engine::plasma_gcode(&layers, &self.plasma, self.touch_off, fw, home);
+            self.load_program("Plasma job", text);
+        } else {
+            let text = engine::laser_gcode(&layers, &self.laser, fw, home);
+            self.load_program("Laser job", text);
+        }
+    }
+
+    /// Extruder job from every layer of the models: skirt or brim on the
+    /// first layer, then each layer's perimeters and infill, with every
+    /// model's own overrides.
+    fn generate_print_job(&mut self) {
+        if self.models.is_empty() {
+            crate::toasts::warn("Nothing to print: load a model first", None);
+            return;
+        }
+        let (dx, dy) = (f64::from(self.work_size.x) * 0.5, f64::from(self.work_size.y) * 0.5);
+        let home = geo::Coord { x: 0.0, y: 0.0 };
+        let w = self.line_width;
+        let plan = self.layer_plan().to_vec();
+        let adhesion = self.print_adhesion(&plan);
+        // the raft is printed first and the part stands on it, lifted by its height
+        let raft_h = f64::from(self.layer_settings.base_height);
+        let lift = adhesion.raft.len() as f64 * raft_h;
+        let mut layers: Vec<engine::PrintLayer> = adhesion
+            .raft
+            .iter()
+            .enumerate()
+            .map(|(k, paths)| engine::PrintLayer {
+                z: (k + 1) as f64 * raft_h + self.machine.z_offset,
+                height: raft_h,
+                adhesion: paths.clone(),
+                ..engine::PrintLayer::default()
+            })
+            .collect();
+        layers.extend((0..plan.len()).map(|i| {
+            let mut layer = self.print_layer(&plan, i, &adhesion.first_layer, None);
+            layer.z += lift;
+            layer
+        }));
+        for layer in &mut layers {
+            layer.translate(dx, dy);
+        }
+        let text = engine::extruder_gcode(&layers, &self.extruder, &self.retraction, &self.fan, w, self.machine.firmware, home);
+        self.load_program("Print job", text);
+    }
+
+    /// Skirt, brim and raft of the print, around layer 0 of the models.
+    pub(crate) fn print_adhesion(&self, plan: &[engine::Layer]) -> engine::Adhesion {
+        plan.first()
+            .and_then(|l| engine::slice_union(self.models.iter().map(|m| &m.mesh), l.slice_z()))
+            .map(|s| engine::first_layer_adhesion(&self.adhesion, &s, self.line_width))
+            .unwrap_or_default()
+    }
+
+    /// Layer `i` of `plan` as the print job lays it down, in scene
+    /// coordinates: skirt and brim (`first_layer`, on layer 0 only), walls,
+    /// infill (computed unless given) and supports. The layer preview shows
+    /// the same.
+    pub(crate) fn print_layer(
+        &self,
+        plan: &[engine::Layer],
+        i: usize,
+        first_layer: &[geo::LineString<f64>],
+        infill: Option<engine::Infill>,
+    ) -> engine::PrintLayer {
+        let l = plan[i];
+        let w = self.line_width;
+        let shells = engine::layer_perimeters(self.models.iter().map(|m| (&m.mesh, m.overrides.perimeters(self.perimeters))), w, l.slice_z());
+        let infill = infill.unwrap_or_else(|| {
+            let models = self
+                .models
+                .iter()
+                .map(|m| (&m.mesh, m.overrides.infill(&self.infill_settings), m.overrides.perimeters(self.perimeters)));
+            engine::layer_infill(models, self.infill_type, w, plan, i)
+        });
+        engine::PrintLayer {
+            z: f64::from(l.top()) + self.machine.z_offset,
+            height: f64::from(l.height),
+            adhesion: if i == 0 { first_layer.to_vec() } else { Vec::new() },
+            moves: engine::layer_moves(&shells, &self.seam, &self.retraction, i),
+            infill,
+            support: self.supports.as_ref().map(|(s, supports)| supports.layer_paths(s, l.z, l.height, w as f32)).unwrap_or_default(),
+        }
+    }
+
+    /// Endmill job from the computed profile or pocket passes.
+    fn generate_mill_job(&mut self) {
+        let Some((paths, top)) = &self.cam_paths else {
+            crate::toasts::warn("Nothing to mill: compute the profile or pocket passes first", None);
+            return;
+        };
+        let (dx, dy) = (f64::from(self.work_size.x) * 0.5, f64::from(self.work_size.y) * 0.5);
+        let home = geo::Coord { x: 0.0, y: 0.0 };
+        let dz = self.machine.z_offset;
+        let paths: Vec<Vec<[f64; 3]>> = paths.iter().map(|p| p.iter().map(|&[x, y, z]| [x + dx, y + dy, z + dz]).collect()).collect();
+        let text = engine::mill_gcode(&paths, &self.mill, top + dz, self.machine.firmware, home);
+        self.load_program("Endmill job", text);
+    }
+
+    /// Drill job for the round holes of the flat part, or of the current
+    /// layer, that match the drill width.
+    fn generate_drill_job(&mut self) {
+        let (dx, dy) = (f64::from(self.work_size.x) * 0.5, f64::from(self.work_size.y) * 0.5);
+        let home = geo::Coord { x: 0.0, y: 0.0 };
+        let slice = match self.flat_source() {
+            Some(part) => Some(part.clone()),
+            None => {
+                let index = usize::try_from(self.current_layer).unwrap_or(0);
+                let layer = self.layer_plan().get(index).copied();
+                layer.and_then(|l| engine::slice_union(self.models.iter().map(|m| &m.mesh), l.slice_z()))
+            }
+        };
+        let holes = slice
+            .map(|s| engine::drill_points(&s.translate(dx, dy, 0.0), f64::from(self.drill_width), self.drill.tolerance))
+            .unwrap_or_default();
+        if holes.is_empty() {
+            crate::toasts::warn(format!("No round holes of {:.2} mm to drill in the current part", self.drill_width), None);
+            return;
+        }
+        let holes: Vec<geo::Coord<f64>> = if self.optimize_order {
+            let (plan, _) = engine::plan_cuts(&[], &holes, home);
+            plan.ops
+                .iter()
+                .filter_map(|op| match op {
+                    crate::cutting::CutOp::Drill(c) => Some(*c),
+                    crate::cutting::CutOp::Path(_) => None,
+                })
+                .collect()
+        } else {
+            holes
+        };
+        self.diag_log(format!("drill job: {} hole(s)", holes.len()));
+        let text = engine::drill_gcode(&holes, &self.drill, self.machine.firmware, home);
+        self.load_program("Drill job", text);
+    }
+
+    fn job_ui(&mut self, ui: &mut egui::Ui) {
+        let running = self.jobs.is_running();
+        ui.horizontal(|ui| {
+            if ui.add_enabled(!running, egui::Button::new("Load G-code…")).clicked() {
+                spawn_file_picker(
+                    Arc::clone(&self.job_data),
+                    "G-code (gcode,nc,ngc)",
+                    &["gcode", "gco", "nc", "ngc", "txt"],
+                );
+            }
+            if matches!(self.selected_tool, Tool::Laser | Tool::Plasma)
+                && ui
+                    .add_enabled(!running, egui::Button::new("Generate from slices"))
+                    .on_hover_text(format!("G-code from the current part with the {} settings", self.selected_tool))
+                    .clicked()
+            {
+                self.generate_cut_job();
+            }
+            if self.selected_tool == Tool::Extruder
+                && ui
+                    .add_enabled(!running, egui::Button::new("Generate from slices"))
+                    .on_hover_text("Print G-code from the models with the Extruder settings")
+                    .clicked()
+            {
+                self.generate_print_job();
+            }
+            if self.selected_tool == Tool::Endmill
+                && ui
+                    .add_enabled(!running, egui::Button::new("Generate from passes"))
+                    .on_hover_text("G-code from the computed profile or pocket passes")
+                    .clicked()
+            {
+                self.generate_mill_job();
+            }
+            if self.selected_tool == Tool::Drill
+                && ui
+                    .add_enabled(!running, egui::Button::new("Generate from holes"))
+                    .on_hover_text("Canned drilling cycles for the round holes that match the drill width")
+                    .clicked()
+            {
+                self.generate_drill_job();
+            }
+            ui.add_enabled(!running, egui::Checkbox::new(&mut self.jobs.dry_run, "Dry run"))
+                .on_hover_text("Stream jobs to a virtual machine at real speed; nothing is sent to the firmware");
+        });
+        let now = crate::now_ms();
+        let dry = self.jobs.dry_run;
+        let held = self.jobs.is_held();
+        let Some(job) = self.jobs.active.as_mut() else {
+            ui.weak("No job loaded");
+            return;
+        };
+        ui.label(format!("{} ({} lines)", job.name, job.total_lines()));
+        if !job.is_started() {
+            ui.horizontal(|ui| {
+                let label = ui.label("Pause at layers:");
+                ui.add(egui::TextEdit::singleline(&mut self.pause_layers).hint_text("e.g. 5, 12").desired_width(70.0)).labelled_by(label.id);
+                if ui.button("Insert").clicked() {
+                    let layers: Vec<usize> = self
+                        .pause_layers
+                        .split(|c: char| c == ',' || c.is_whitespace())
+                        .filter_map(|t| t.parse().ok())
+                        .collect();
+                    let n = job.insert_pauses(&layers);
+                    log::info!("[alumina] inserted {n} filament-change pause(s)");
+                }
+            });
+        }
+        if !job.is_started() {
+            if ui.button(if dry { "Start dry run" } else { "Start" }).clicked() {
+                job.dry_run = dry;
+                job.start(now);
+                if dry {
+                    self.reset_dry_run();
+                }
+            }
+            return;
+        }
+        let (mut pause, mut cancel) = (None, false);
+        ui.horizontal(|ui| {
+            if running && !held && ui.button("⏸ Pause").on_hover_text("Stop sending after the line in progress").clicked() {
+                pause = Some(true);
+            }
+            if held && ui.button("▶ Resume").clicked() {
+                pause = Some(false);
+            }
+            if running && ui.button("⏹ Cancel").clicked() {
+                cancel = true;
+            }
+        });
+        match pause {
+            Some(true) => self.pause_job(),
+            Some(false) => self.resume_job(),
+            None => {}
+        }
+        if cancel {
+            self.cancel_job();
+        }
+        let Some(job) = self.jobs.active.as_ref() else { return };
+        if held {
+            ui.colored_label(ui.visuals().warn_fg_color, "Paused");
+        }
+        if job.dry_run {
+            ui.colored_label(ui.visuals().warn_fg_color, "Dry run: the machine is not moving");
+        }
+
+        ui.add(egui::ProgressBar::new(job.progress()).show_percentage());
+        egui::Grid::new("job_progress").num_columns(2).show(ui, |ui| {
+            if let Some((cur, total)) = job.layer() {
+                ui.label("Layer");
+                ui.label(format!("{cur} / {total}"));
+                ui.end_row();
+            }
+            ui.label("Line");
+            ui.label(format!("{} / {}", job.acked_lines(), job.total_lines()));
+            ui.end_row();
+            ui.label("Elapsed");
+            ui.label(format_duration(job.elapsed_ms(now)));
+            ui.end_row();
+            if let Some(rem) = job.remaining_ms(now).filter(|_| job.is_running()) {
+                ui.label("Remaining");
+                ui.label(format!("~{}", format_duration(rem)));
+                ui.end_row();
+            }
+        });
+        if let Some((n, text)) = job.current_line() {
+            ui.small(format!("{n}: {}", text.trim()));
+        }
+        if let Some(e) = &job.error {
+            ui.colored_label(ui.visuals().error_fg_color, format!("stopped: {e}"));
+            if ui.button("Resume from line / layer…").clicked() {
+                let line = job.acked_lines() + 1;
+                if let Some(job) = self.jobs.active.take() {
+                    self.recovery = Some(Recovery::new(job, line));
+                }
+            }
+        } else if job.is_finished() {
+            ui.label("finished");
+        }
+    }
+
+    /// Jobs waiting behind the active one, and progress over all of them.
+    fn job_queue_ui(&mut self, ui: &mut egui::Ui) {
+        if self.jobs.waiting().next().is_none() {
+            return;
+        }
+        ui.separator();
+        ui.label("Queued");
+        ui.add(egui::ProgressBar::new(self.jobs.progress()).text(format!("{:.0} % of all jobs", self.jobs.progress() * 100.0)));
+        let mut remove = None;
+        for (i, job) in self.jobs.waiting().enumerate() {
+            ui.horizontal(|ui| {
+                ui.label(format!("{}. {} ({} lines)", i + 1, job.name, job.total_lines()));
+                if ui.small_button("✖").on_hover_text("Remove from the queue").clicked() {
+                    remove = Some(i);
+                }
+            });
+        }
+        if let Some(i) = remove {
+            self.jobs.remove_waiting(i);
+        }
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.jobs.run_through, "Run back to back")
+                .on_hover_text("Start each queued job as soon as the one before finishes");
+            if !self.jobs.is_running() && ui.button("Next job").clicked() {
+                self.advance_job(crate::now_ms(), true);
+            }
+            if ui.button("Clear queue").clicked() {
+                self.jobs.clear_waiting();
+            }
+        });
+    }
+
+    /// Offer to continue an interrupted job from a chosen line or layer.
+    fn recovery_dialog(&mut self, ctx: &egui::Context) {
+        let Some(rec) = self.recovery.as_mut() else {
+            return;
+        };
+        let fw = self.machine.firmware;
+        let total = rec.job.total_lines();
+        let layers = rec.job.layer_count();
+        let (mut resume, mut discard) = (false, false);
+        egui::Window::new("Resume interrupted job")
+            .collapsible(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                ui.label(format!("{} stopped after line {} of {total}.", rec.job.name, rec.line.saturating_sub(1)));
+                ui.horizontal(|ui| {
+                    ui.radio_value(&mut rec.by_layer, false, "Line");
+                    ui.add_enabled(!rec.by_layer, egui::DragValue::new(&mut rec.line).range(1..=total.max(1)));
+                });
+                if layers > 0 {
+                    ui.horizontal(|ui| {
+                        ui.radio_value(&mut rec.by_layer, true, "Layer");
+                        ui.add_enabled(rec.by_layer, egui::DragValue::new(&mut rec.layer).range(1..=layers));
+                    });
+                    if rec.by_layer {
+                        rec.line = rec.job.layer_start_line(rec.layer).unwrap_or(rec.line);
+                    } else {
+                        rec.layer = rec.job.layer_of_line(rec.line).unwrap_or(1);
+                    }
+                }
+                ui.collapsing("Preamble", |ui| {
+                    ui.monospace(rec.job.preamble_preview(rec.line, fw));
+                });
+                ui.small("X/Y are re-homed; Z is assumed to be where it stopped.");
+                ui.horizontal(|ui| {
+                    resume = ui.button("Resume").clicked();
+                    discard = ui.button("Discard").clicked();
+                });
+            });
+        if resume {
+            let line = rec.line;
+            let mut job = rec.job.resumed_at(line, fw);
+            self.diag_log(format!("job resumed at line {line}"));
+            job.start(crate::now_ms());
+            self.jobs.active = Some(job);
+            self.recovery = None;
+        } else if discard {
+            Job::clear_saved();
+            self.recovery = None;
+        }
+    }
+
+    /// Guided operator dialog shown while a job waits on `M600` / `M6` / `M0`.
+    fn pause_dialog(&mut self, ctx: &egui::Context) {
+        let Some(kind) = self.jobs.active.as_ref().and_then(|j| j.paused.clone()) else {
+            return;
+        };
+        let fw = self.machine.firmware;
+        // a dry run's pause leaves the real machine alone
+        let live = !self.jobs.active.as_ref().is_some_and(|j| j.dry_run);
+        let mut resume = false;
+        let title = match &kind {
+            PauseKind::FilamentChange => "Filament change".to_owned(),
+            PauseKind::ToolChange(t) => format!("Tool change: {t}"),
+            PauseKind::Stop => "Program stop".to_owned(),
+            PauseKind::InsertHardware => "Insert hardware".to_owned(),
+        };
+        egui::Window::new(title)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                match &kind {
+                    PauseKind::FilamentChange => {
+                        ui.label("1. Retract the old filament");
+                        ui.horizontal(|ui| {
+                            ui.add(egui::DragValue::new(&mut self.filament_change.retract_mm).suffix(" mm").range(0.0..=200.0));
+                            if ui.add_enabled(live, egui::Button::new("Retract")).clicked() {
+                                let e = -self.filament_change.retract_mm;
+                                for cmd in ["M83".to_owned(), format!("G1 E{e:.2} F1800")] {
+                                    send_queue_command(cmd);
+                                }
+                            }
+                        });
+                        ui.label("2. Heat the nozzle");
+                        ui.horizontal(|ui| {
+                            ui.add(egui::DragValue::new(&mut self.filament_change.temp_c).suffix(" °C").range(0.0..=320.0));
+                            if ui.add_enabled(live, egui::Button::new("Heat")).clicked() {
+                                send_queue_command(format!("M104 S{:.0}", self.filament_change.temp_c));
+                            }
+                        });
+                        ui.label("3. Insert new filament and purge");
+                        ui.horizontal(|ui| {
+                            ui.add(egui::DragValue::new(&mut self.filament_change.load_mm).suffix(" mm").range(0.0..=200.0));
+                            if ui.add_enabled(live, egui::Button::new("Extrude")).clicked() {
+                                send_queue_command("M83");
+                                send_queue_command(format!("G1 E{:.2} F300", self.filament_change.load_mm));
+                            }
+                        });
+                        ui.label("4. Remove the purged strand, then resume");
+                    }
+                    PauseKind::ToolChange(t) => {
+                        ui.label(format!("Fit {t} in the spindle and re-zero Z if needed."));
+                        if ui.add_enabled(live, egui::Button::new("Spindle off (safety)")).clicked() {
+                            send_queue_command(fw.tool_off_command());
+                        }
+                    }
+                    PauseKind::Stop => {
+                        ui.label("The program requested a stop (M0/M1).");
+                    }
+                    PauseKind::InsertHardware => {
+                        ui.label("The pockets for hardware are printed up to their top.");
+                        ui.label("Press the nuts, magnets or other inserts in, keeping hands clear of the hot nozzle, then resume.");
+                    }
+                }
+                if !live {
+                    ui.weak("Dry run: nothing is sent to the machine");
+                }
+                ui.separator();
+                if ui.button("Resume job").clicked() {
+                    resume = true;
+                }
+            });
+        if resume {
+            if let Some(job) = self.jobs.active.as_mut() {
+                // our helpers switch to relative E; restore the job's mode
+                if live && kind == PauseKind::FilamentChange && !job.e_relative {
+                    send_queue_command("M82");
+                }
+                job.resume();
+            }
+            self.diag_log("job resumed");
+        }
+    }
+
+    /// Bring on the next queued job (see [`crate::job::JobQueue::advance`]), a dry run
+    /// starting the virtual machine afresh.
+    fn advance_job(&mut self, now: f64, force: bool) -> bool {
+        if !self.jobs.advance(now, force) {
+            return false;
+        }
+        if self.jobs.active.as_ref().is_some_and(|j| j.dry_run) {
+            self.reset_dry_run();
+        }
+        true
+    }
+
+    /// Stream the active job: collect the last acknowledgement, then send the
+    /// next line through the soft-limit check.
+    fn tick_job(&mut self, ctx: &egui::Context, now: f64) {
+        let loaded = self.job_data.lock().unwrap().take();
+        if let Some(bytes) = loaded {
+            self.load_program("G-code job", String::from_utf8_lossy(&bytes).into_owned());
+        }
+
+        let ack = self.jobs.poll_ack(now);
+        match ack {
+            Some(Err(e)) => {
+                log::error!("job stopped: {e}");
+                crate::toasts::error("Job stopped", Some(e));
+            }
+            Some(Ok(())) => {
+                if let Some(j) = self.jobs.active.as_mut() {
+                    if j.is_finished() {
+                        Job::clear_saved();
+                    } else {
+                        j.save_checkpoint();
+                    }
+                }
+                if self.advance_job(now, false) {
+                    let name = self.jobs.active.as_ref().map(|j| j.name.clone()).unwrap_or_default();
+                    self.diag_log(format!("next queued job: {name}"));
+                }
+            }
+            None => {}
+        }
+
+        let cmd = self.jobs.next_command(now);
+        if let Some(cmd) = cmd {
+            match self.motion.check(&cmd, &self.machine) {
+                LimitCheck::Pass(l) => self.dispatch_job_line(l),
+                LimitCheck::Clamped(l, why) => {
+                    log::warn!("soft limit: {why}");
+                    self.diag_log(format!("soft limit: {why}"));
+                    self.limit_warning = Some(why);
+                    self.dispatch_job_line(l);
+                }
+                LimitCheck::Refused(why) => {
+                    log::error!("job stopped: {why}");
+                    crate::toasts::error("Job stopped at a soft limit", Some(why.clone()));
+                    self.limit_warning = Some(why.clone());
+                    self.jobs.fail(why);
+                }
+            }
+        }
+
+        // Progress in the tab title so it's visible while backgrounded
+        let title = match &self.jobs.active {
+            Some(j) if j.is_running() => {
+                format!("{:.0}% · {} – {PAGE_TITLE}", j.progress() * 100.0, j.name)
+            }
+            _ => PAGE_TITLE.to_owned(),
+        };
+        if title != self.page_title {
+            crate::platform::set_title(ctx, &title);
+            self.page_title = title;
+        }
+
+        self.pause_dialog(ctx);
+        self.recovery_dialog(ctx);
+
+        if self.jobs.is_running() {
+            ctx.request_repaint_after(std::time::Duration::from_millis(20));
+        }
+    }
+
+    fn dispatch_job_line(&mut self, line: String) {
+        if self.dry_running() {
+            let reply = self.dry_run_line(&line);
+            self.jobs.sent(reply);
+            return;
+        }
+        self.observe_outgoing(&line);
+        if let (Some(on), Some(job)) = (self.machine.firmware.tool_switch(&line), self.jobs.active.as_mut()) {
+            job.tool_on = on.then(|| line.trim().to_owned());
+        }
+        let reply = crate::net::spawn(Endpoint::Queue(line));
+        self.jobs.sent(reply);
+    }
+
+    fn test_fire_ui(&mut self, ui: &mut egui::Ui) {
+        let tool = self.selected_tool;
+        ui.horizontal(|ui| {
+            let label = ui.label("Power:");
+            ui.add(egui::Slider::new(&mut self.test_fire.power_pct, 0.0..=100.0).suffix(" %")).labelled_by(label.id);
+        });
+        ui.horizontal(|ui| {
+            let label = ui.label("Max pulse:");
+            ui.add(
+                egui::Slider::new(&mut self.test_fire.max_ms, 10.0..=TEST_FIRE_HARD_CAP_MS)
+                    .suffix(" ms")
+                    .logarithmic(true),
+            )
+            .labelled_by(label.id);
+        });
+
+        if let Some(armed) = self.test_fire.armed_ms {
+            let fire = ui.add_enabled(
+                !self.test_fire.is_firing(),
+                egui::Button::new(egui::RichText::new("Fire pulse").strong()).fill(egui::Color32::from_rgb(150, 30, 30)),
+            );
+            if fire.clicked() {
+                self.fire_test_pulse();
+            }
+            ui.weak(format!("Disarms in {:.0} s", (TEST_FIRE_ARM_MS - (crate::now_ms() - armed)).max(0.0) / 1000.0));
+            if ui.button("Disarm").clicked() {
+                if self.test_fire.is_firing() {
+                    self.stop_test_fire();
+                }
+                self.test_fire.armed_ms = None;
+            }
+        } else if ui.button("Arm test fire…").clicked() {
+            self.test_fire.confirm_open = true;
+        }
+
+        if self.test_fire.confirm_open {
+            egui::Window::new("Confirm test fire")
+                .collapsible(false)
+                .resizable(false)
+                .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+                .show(ui.ctx(), |ui| {
+                    ui.label(format!(
+                        "The {tool} will switch on at {:.0} % for {:.0} ms each time the button is pressed.",
+                        self.test_fire.power_pct, self.test_fire.max_ms
+                    ));
+                    ui.label("Make sure eye protection is worn and the work area is clear.");
+                    ui.horizontal(|ui| {
+                        if ui.button("I understand, arm").clicked() {
+                            self.test_fire.armed_ms = Some(crate::now_ms());
+                            self.test_fire.confirm_open = false;
+                        }
+                        if ui.button("Cancel").clicked() {
+                            self.test_fire.confirm_open = false;
+                        }
+                    });
+                });
+        }
+    }
+
+    /// Tool on, dwell, tool off, queued together so the firmware itself
+    /// ends the pulse; the dwell is capped at [`TEST_FIRE_HARD_CAP_MS`].
+    fn fire_test_pulse(&mut self) {
+        let fw = self.machine.firmware;
+        let ms = self.test_fire.max_ms.min(TEST_FIRE_HARD_CAP_MS);
+        let block = [fw.tool_on_command(self.test_fire.power_pct), fw.dwell_command(ms), fw.tool_off_command()];
+        self.diag_log(format!("test fire: {}", block.join("; ")));
+        // the dwell is only acknowledged once it is over
+        let policy = crate::net::Policy { timeout_ms: ms as i32 + 5000, retries: 0, ..crate::net::Policy::default() };
+        crate::execute(async move {
+            for cmd in block {
+                // the off command goes out whatever became of the others
+                if let Err(e) = crate::net::fetch_with(&Endpoint::Queue(cmd.clone()), policy).await {
+                    log::error!("test fire: {cmd}: {e}");
+                    crate::toasts::error("Test fire command failed", Some(format!("{cmd}: {e}")));
+                }
+            }
+        });
+        self.test_fire.until = Some(crate::now_ms() + ms);
+    }
+
+    fn stop_test_fire(&mut self) {
+        self.test_fire.until = None;
+        let cmd = self.machine.firmware.tool_off_command();
+        self.diag_log(format!("test fire: {cmd}"));
+        send_queue_command(cmd);
+    }
+
+    fn babystep_ui(&mut self, ui: &mut egui::Ui) {
+        let fw = self.machine.firmware;
+        if fw.babystep_command(0.0).is_none() {
+            ui.weak(format!("{fw} has no babystep command"));
+            return;
+        }
+        ui.horizontal(|ui| {
+            for dz in [-0.05, -0.01, 0.01, 0.05] {
+                if ui.button(format!("{dz:+.2}")).clicked() {
+                    if let Some(cmd) = fw.babystep_command(dz) {
+                        send_queue_command(cmd);
+                        self.babystep_total += dz;
+                    }
+                }
+            }
+        });
+        ui.label(format!(
+            "Session: {:+.3} mm   Profile: {:+.3} mm",
+            self.babystep_total, self.machine.z_offset
+        ));
+        ui.horizontal(|ui| {
+            let any = self.babystep_total.abs() > 1e-9;
+            if ui
+                .add_enabled(any, egui::Button::new("Bake into profile"))
+                .on_hover_text("Add the session adjustment to the profile's Z offset used for generated jobs")
+                .clicked()
+            {
+                self.machine.z_offset += self.babystep_total;
+                self.babystep_total = 0.0;
+                self.machine.save();
+            }
+            if ui.add_enabled(any, egui::Button::new("Reset")).clicked() {
+                self.babystep_total = 0.0;
+            }
+        });
+    }
+
+    fn aux_outputs_ui(&mut self, ui: &mut egui::Ui) {
+        self.aux_state.resize(self.machine.aux_outputs.len(), false);
+        for i in 0..self.machine.aux_outputs.len() {
+            let name = self.machine.aux_outputs[i].name.clone();
+            let mut on = self.aux_state[i];
+            if ui.toggle_value(&mut on, &name).changed() {
+                match self.machine.aux_outputs[i].command(&self.machine, on) {
+                    Some(cmd) => {
+                        send_queue_command(cmd.clone());
+                        self.observe_outgoing(&cmd);
+                        // pin-backed outputs aren't recognisable in the G-code stream
+                        self.aux_state[i] = on;
+                    }
+                    None => self.diag_log(format!("{name}: pin not in the pin map")),
+                }
+            }
+        }
+        // the firmware has one state per command, so outputs sharing one switch together
+        let outs = &self.machine.aux_outputs;
+        for (i, out) in outs.iter().enumerate() {
+            let Some(cmd) = out.on_command() else { continue };
+            let shared: Vec<&str> = outs.iter().filter(|o| o.on_command().is_some_and(|c| c.eq_ignore_ascii_case(cmd))).map(|o| o.name.as_str()).collect();
+            let first = outs.iter().position(|o| o.on_command().is_some_and(|c| c.eq_ignore_ascii_case(cmd)));
+            if shared.len() > 1 && first == Some(i) {
+                ui.colored_label(
+                    ui.visuals().warn_fg_color,
+                    format!("⚠ {} share {cmd}: they switch on and off together", shared.join(" and ")),
+                );
+            }
+        }
+
+        ui.collapsing("Configure outputs", |ui| {
+            let mut changed = false;
+            let mut remove = None;
+            for (i, out) in self.machine.aux_outputs.iter_mut().enumerate() {
+                ui.push_id(i, |ui| {
+                    ui.horizontal(|ui| {
+                        changed |= ui
+                            .add(egui::TextEdit::singleline(&mut out.name).desired_width(90.0))
+                            .changed();
+                        let is_pin = matches!(out.control, AuxControl::Pin(_));
+                        if ui.selectable_label(!is_pin, "cmd").clicked() && is_pin {
+                            out.control = AuxControl::Gcode { on: "M8".into(), off: "M9".into() };
+                            changed = true;
+                        }
+                        if ui.selectable_label(is_pin, "pin").clicked() && !is_pin {
+                            out.control = AuxControl::Pin("D0".into());
+                            changed = true;
+                        }
+                        if ui.button("x").clicked() {
+                            remove = Some(i);
+                        }
+                    });
+                    ui.horizontal(|ui| match &mut out.control {
+                        AuxControl::Gcode { on, off } => {
+                            let label = ui.label("on");
+                            changed |= ui.add(egui::TextEdit::singleline(on).desired_width(50.0)).labelled_by(label.id).changed();
+                            let label = ui.label("off");
+                            changed |= ui.add(egui::TextEdit::singleline(off).desired_width(50.0)).labelled_by(label.id).changed();
+                        }
+                        AuxControl::Pin(pin) => {
+                            let label = ui.label("pin");
+                            changed |= ui.add(egui::TextEdit::singleline(pin).desired_width(50.0)).labelled_by(label.id).changed();
+                        }
+                    });
+                });
+            }
+            if let Some(i) = remove {
+                self.machine.aux_outputs.remove(i);
+                if i < self.aux_state.len() {
+                    self.aux_state.remove(i);
+                }
+                changed = true;
+            }
+            if ui.button("Add output").clicked() {
+                self.machine.aux_outputs.push(AuxOutput {
+                    name: "Output".into(),
+                    control: AuxControl::Gcode { on: "M8".into(), off: "M9".into() },
+                });
+                changed = true;
+            }
+            if changed {
+                self.machine.save();
+            }
+        });
+    }
+
+    /// Hold the running job and switch the tool off while it waits.
+    fn pause_job(&mut self) {
+        if !self.jobs.is_running() {
+            return;
+        }
+        if !self.dry_running() {
+            let fw = self.machine.firmware;
+            if let Some(hold) = fw.feed_hold() {
+                crate::net::send(hold);
+            }
+            if fw == Firmware::Grbl {
+                // an `M5` would wait behind the held moves: stop the spindle with
+                // its override instead, which GRBL lifts again on cycle start
+                crate::net::send(Endpoint::Realtime(0x9E));
+            } else {
+                let job = self.jobs.active.as_ref();
+                let mut restore: Vec<String> = job.and_then(|j| j.tool_on.clone()).into_iter().collect();
+                for (out, &on) in self.machine.aux_outputs.iter().zip(&self.aux_state) {
+                    if let (true, AuxControl::Gcode { on: cmd, .. }) = (on, &out.control) {
+                        if !restore.contains(cmd) {
+                            restore.push(cmd.clone());
+                        }
+                    }
+                }
+                if fw == Firmware::Marlin {
+                    restore.extend(job.and_then(Job::fan_on));
+                    send_queue_command("M107");
+                }
+                self.stop_outputs();
+                self.jobs.restore = restore;
+            }
+        }
+        self.jobs.pause();
+        self.diag_log("job paused");
+    }
+
+    /// Switch back on what the hold switched off, then carry on.
+    fn resume_job(&mut self) {
+        if !self.dry_running() {
+            for cmd in std::mem::take(&mut self.jobs.restore) {
+                send_queue_command(cmd.clone());
+                self.observe_outgoing(&cmd);
+            }
+            if let Some(start) = self.machine.firmware.cycle_start() {
+                crate::net::send(start);
+            }
+        }
+        self.jobs.resume();
+        self.diag_log("job resumed");
+    }
+
+    /// Stop the running job and leave the tool and coolant off.
+    fn cancel_job(&mut self) {
+        if !self.jobs.is_running() {
+            return;
+        }
+        if !self.dry_running() {
+            self.stop_outputs();
+        }
+        self.jobs.cancel();
+        self.jobs.restore.clear();
+        self.diag_log("job cancelled");
+    }
+
+    /// Switch off the tool and every output switched by G-code (coolant,
+    /// air assist), each distinct off command once.
+    fn stop_outputs(&mut self) {
+        let mut sent = vec![self.machine.firmware.tool_off_command()];
+        for out in &self.machine.aux_outputs {
+            if let AuxControl::Gcode { off, .. } = &out.control {
+                if !off.is_empty() && !sent.contains(off) {
+                    sent.push(off.clone());
+                }
+            }
+        }
+        for cmd in sent {
+            send_queue_command(cmd.clone());
+            self.observe_outgoing(&cmd);
+        }
+    }
+
+    /// Track side effects of a line sent to the machine (aux outputs switched
+    /// by `M7`/`M8`/`M9` etc. inside a job), so the panel mirrors reality.
+    pub(crate) fn observe_outgoing(&mut self, line: &str) {
+        let line = line.trim();
+        self.aux_state.resize(self.machine.aux_outputs.len(), false);
+        for (out, state) in self.machine.aux_outputs.iter().zip(self.aux_state.iter_mut()) {
+            if let AuxControl::Gcode { on, off } = &out.control {
+                if !on.is_empty() && line.eq_ignore_ascii_case(on) {
+                    *state = true;
+                } else if !off.is_empty() && line.eq_ignore_ascii_case(off) {
+                    *state = false;
+                }
+            }
+        }
+    }
+
+    /// Send the firmware's homing command; axes are marked homed once the
+    /// machine reports it has stopped (see [`Homing`]).
+    pub(crate) fn start_homing(&mut self, axis: Option<usize>) {
+        if self.jobs.is_running() {
+            crate::toasts::warn("Homing refused while a job runs", None);
+            return;
+        }
+        let cmd = self.machine.firmware.home_command(axis);
+        let axes = axis.map_or_else(|| vec![0, 1, 2], |i| vec![i]);
+        for &i in &axes {
+            self.homed[i] = false;
+        }
+        self.diag_log(format!("homing: {cmd}"));
+        let reply = crate::net::spawn(Endpoint::Queue(cmd));
+        self.homing = Some(Homing { axes, reply, acked: false, last: None, query_at: None });
+    }
+
+    /// Machine coordinates (origin at the front-left bed corner) → scene
+    /// coordinates (bed centred on the origin, as drawn by `sync_buffers`).
+    pub(crate) fn machine_to_scene(&self, p: [f64; 4]) -> nalgebra::Vector3<f32> {
+        nalgebra::Vector3::new(
+            p[0] as f32 - self.work_size.x * 0.5,
+            p[1] as f32 - self.work_size.y * 0.5,
+            p[2] as f32,
+        )
+    }
+
+    /// Whether the readout no longer shows where the machine is: polling
+    /// failed, or nothing has come back for a few intervals.
+    fn dro_stale(&self) -> bool {
+        self.dro_poll
+            && !self.dry_running()
+            && (self.dro_error.is_some() || crate::now_ms() - self.dro_updated > self.dro_interval_ms.max(250.0) * DRO_STALE_INTERVALS)
+    }
+
+    /// Work and machine coordinates side by side, greyed out when stale.
+    fn dro_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.dro_poll, "Poll position");
+            egui::ComboBox::from_id_salt("dro_interval")
+                .selected_text(format!("every {} ms", self.dro_interval_ms))
+                .show_ui(ui, |ui| {
+                    for ms in DRO_INTERVALS {
+                        ui.selectable_value(&mut self.dro_interval_ms, ms, format!("every {ms} ms"));
+                    }
+                })
+                .response
+                .on_hover_text("How often the firmware is asked for its position");
+        });
+        let Some(pos) = self.dro_pos else {
+            ui.weak("No position reported yet");
+            if let Some(e) = &self.dro_error {
+                ui.colored_label(ui.visuals().error_fg_color, e);
+            }
+            return;
+        };
+        let stale = self.dro_stale();
+        let axes = if pos.has_a { 4 } else { 3 };
+        let value = |v: f64| {
+            let text = egui::RichText::new(format!("{v:>9.3}")).size(22.0).monospace();
+            if stale { text.weak() } else { text }
+        };
+        egui::Grid::new("dro").num_columns(3).show(ui, |ui| {
+            ui.label("");
+            ui.small(if pos.work.is_some() { "Work" } else { "Position" });
+            ui.small(if pos.work.is_some() { "Machine" } else { "" });
+            ui.end_row();
+            for (i, name) in ["X", "Y", "Z", "A"].into_iter().enumerate().take(axes) {
+                ui.label(egui::RichText::new(name).size(22.0).strong());
+                ui.label(value(pos.display()[i]));
+                match pos.work {
+                    Some(_) => ui.label(value(pos.machine[i]).size(16.0)),
+                    None => ui.label(""),
+                };
+                ui.end_row();
+            }
+        });
+        if pos.work.is_none() {
+            ui.small("The firmware reports a single position");
+        }
+        if stale {
+            let age = (crate::now_ms() - self.dro_updated) / 1000.0;
+            let why = self.dro_error.as_deref().unwrap_or("no reply");
+            ui.colored_label(ui.visuals().warn_fg_color, format!("⚠ Stale: last update {age:.1} s ago ({why})"));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_position_marlin_m114_is_not_exact() {
+        let p = parse_position("X:10.00 Y:20.00 Z:5.00 E:0.00 Count X:800 Y:1600 Z:2000\nok", &mut None).unwrap();
+        assert_eq!(p, Position { machine: [10.0, 20.0, 5.0, 0.0], work: None, has_a: false, exact: false });
+    }
+
+    #[test]
+    fn parse_position_grbl_mpos_keeps_the_offset() {
+        let mut wco = None;
+        let p = parse_position("<Idle|MPos:15.000,25.000,5.000|FS:0,0|WCO:5.000,5.000,0.000>\nok", &mut wco).unwrap();
+        assert_eq!(wco, Some([5.0, 5.0, 0.0, 0.0]));
+        assert_eq!(p, Position { machine: [15.0, 25.0, 5.0, 0.0], work: Some([10.0, 20.0, 5.0, 0.0]), has_a: false, exact: true });
+        // later reports leave the offset out
+        let p = parse_position("<Run|MPos:20.000,25.000,5.000|FS:500,0>", &mut wco).unwrap();
+        assert_eq!(p.work, Some([15.0, 20.0, 5.0, 0.0]));
+    }
+
+    #[test]
+    fn parse_position_grbl_wpos_only() {
+        let mut wco = None;
+        let p = parse_position("<Idle|WPos:1.000,2.000,3.000|FS:0,0>", &mut wco).unwrap();
+        assert_eq!(p, Position { machine: [1.0, 2.0, 3.0, 0.0], work: None, has_a: false, exact: false });
+        let p = parse_position("<Idle|WPos:1.000,2.000,3.000|FS:0,0|WCO:10.000,0.000,-5.000>", &mut wco).unwrap();
+        assert_eq!(p, Position { machine: [11.0, 2.0, -2.0, 0.0], work: Some([1.0, 2.0, 3.0, 0.0]), has_a: false, exact: true });
+    }
+
+    #[test]
+    fn parse_position_alumina_json() {
+        let p = parse_position(r#"{"mpos":[1,2,3,90],"wpos":[0,0,0,90]}"#, &mut None).unwrap();
+        assert_eq!(p, Position { machine: [1.0, 2.0, 3.0, 90.0], work: Some([0.0, 0.0, 0.0, 90.0]), has_a: true, exact: true });
+        assert!(parse_position("ok", &mut None).is_none());
+    }
+}
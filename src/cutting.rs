@@ -0,0 +1,409 @@
+//! 2-D cut paths for laser and plasma.
+//!
+//! Outlines come from the current slice. When parts are nested edge to edge,
+//! the shared edges would be cut twice; `merge_common_lines` splits every
+//! segment where other segments' ends touch it, drops the duplicates and
+//! chains what is left back into as few polylines as possible.
+//!
+//! `order_cuts` then sequences loops and drill points to keep rapid moves
+//! short, never cutting an outer contour before the contours inside it (a
+//! freed part may shift or drop).
+//!
+//! For the drill, `round_holes` picks the holes of a slice that are round
+//! and as wide as the drill, so they can be drilled instead of cut.
+
+use std::collections::{HashMap, HashSet};
+
+use geo::{Contains, Coord, Geometry, LineString, Point, Polygon};
+
+/// Centres of the holes in `polys` that are round and `diameter` across,
+/// both within `tolerance` (mm). A ring counts as round when every vertex
+/// lies within the tolerance of the mean radius about the vertex centroid.
+pub fn round_holes(polys: &[Polygon<f64>], diameter: f64, tolerance: f64) -> Vec<Coord<f64>> {
+    polys
+        .iter()
+        .flat_map(|p| p.interiors())
+        .filter_map(|ring| {
+            let pts = &ring.0[..ring.0.len().saturating_sub(1)];
+            if pts.len() < 6 {
+                return None;
+            }
+            let n = pts.len() as f64;
+            let c = Coord {
+                x: pts.iter().map(|c| c.x).sum::<f64>() / n,
+                y: pts.iter().map(|c| c.y).sum::<f64>() / n,
+            };
+            let radii: Vec<f64> = pts.iter().map(|&p| dist(c, p)).collect();
+            let r = radii.iter().sum::<f64>() / n;
+            let round = radii.iter().all(|ri| (ri - r).abs() <= tolerance);
+            (round && (2.0 * r - diameter).abs() <= tolerance).then_some(c)
+        })
+        .collect()
+}
+
+/// Length totals before and after merging.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MergeStats {
+    pub length_before: f64,
+    pub length_after: f64,
+    pub shared_segments: usize,
+}
+
+impl MergeStats {
+    pub fn saved(&self) -> f64 {
+        self.length_before - self.length_after
+    }
+}
+
+/// Every ring of every polygon (and open line) in a slice.
+pub fn outlines(geometry: &[Geometry<f64>]) -> Vec<LineString<f64>> {
+    let mut out = Vec::new();
+    for g in geometry {
+        match g {
+            Geometry::LineString(ls) => out.push(ls.clone()),
+            Geometry::Polygon(p) => {
+                out.push(p.exterior().clone());
+                out.extend(p.interiors().iter().cloned());
+            }
+            Geometry::MultiPolygon(mp) => {
+                for p in &mp.0 {
+                    out.push(p.exterior().clone());
+                    out.extend(p.interiors().iter().cloned());
+                }
+            }
+            _ => {}
+        }
+    }
+    out
+}
+
+pub fn path_length(ls: &LineString<f64>) -> f64 {
+    ls.0.windows(2).map(|w| (w[1] - w[0]).x.hypot((w[1] - w[0]).y)).sum()
+}
+
+type Key = (i64, i64);
+
+fn key(c: Coord<f64>, tol: f64) -> Key {
+    ((c.x / tol).round() as i64, (c.y / tol).round() as i64)
+}
+
+/// Parameter of `p` along `a→b` if it lies on the segment's interior within `tol`.
+fn split_param(a: Coord<f64>, b: Coord<f64>, p: Coord<f64>, tol: f64) -> Option<f64> {
+    let d = b - a;
+    let len2 = d.x * d.x + d.y * d.y;
+    if len2 < tol * tol {
+        return None;
+    }
+    let t = ((p.x - a.x) * d.x + (p.y - a.y) * d.y) / len2;
+    let off = a + d * t - p;
+    let len = len2.sqrt();
+    (t * len > tol && (1.0 - t) * len > tol && off.x.hypot(off.y) < tol).then_some(t)
+}
+
+/// Cut each shared edge once. `tol` is the distance under which two
+/// points or lines are considered the same (mm).
+pub fn merge_common_lines(paths: &[LineString<f64>], tol: f64) -> (Vec<LineString<f64>>, MergeStats) {
+    let tol = tol.max(1e-6);
+    let mut stats = MergeStats {
+        length_before: paths.iter().map(path_length).sum(),
+        ..MergeStats::default()
+    };
+
+    let segments: Vec<(Coord<f64>, Coord<f64>)> = paths
+        .iter()
+        .flat_map(|ls| ls.0.windows(2).map(|w| (w[0], w[1])))
+        .filter(|(a, b)| (*b - *a).x.hypot((*b - *a).y) > tol)
+        .collect();
+    let ends: Vec<Coord<f64>> = segments.iter().flat_map(|&(a, b)| [a, b]).collect();
+
+    // split at foreign end points so partly overlapping edges line up
+    let mut pieces: Vec<(Coord<f64>, Coord<f64>)> = Vec::new();
+    for &(a, b) in &segments {
+        let (lo, hi) = (
+            Coord { x: a.x.min(b.x) - tol, y: a.y.min(b.y) - tol },
+            Coord { x: a.x.max(b.x) + tol, y: a.y.max(b.y) + tol },
+        );
+        let mut ts: Vec<f64> = ends
+            .iter()
+            .filter(|p| p.x >= lo.x && p.x <= hi.x && p.y >= lo.y && p.y <= hi.y)
+            .filter_map(|&p| split_param(a, b, p, tol))
+            .collect();
+        ts.sort_by(f64::total_cmp);
+        ts.dedup_by(|x, y| (*x - *y).abs() < 1e-9);
+        let mut prev = a;
+        for t in ts {
+            let p = a + (b - a) * t;
+            pieces.push((prev, p));
+            prev = p;
+        }
+        pieces.push((prev, b));
+    }
+
+    // keep one copy of each undirected piece
+    let mut seen: HashSet<(Key, Key)> = HashSet::new();
+    let mut unique = Vec::new();
+    for (a, b) in pieces {
+        let (ka, kb) = (key(a, tol), key(b, tol));
+        let k = if ka <= kb { (ka, kb) } else { (kb, ka) };
+        if seen.insert(k) {
+            unique.push((a, b));
+        } else {
+            stats.shared_segments += 1;
+        }
+    }
+
+    let merged = chain(&unique, tol);
+    stats.length_after = merged.iter().map(path_length).sum();
+    (merged, stats)
+}
+
+/// Join segments end to end into polylines.
+fn chain(segments: &[(Coord<f64>, Coord<f64>)], tol: f64) -> Vec<LineString<f64>> {
+    let mut at: HashMap<Key, Vec<usize>> = HashMap::new();
+    for (i, &(a, b)) in segments.iter().enumerate() {
+        at.entry(key(a, tol)).or_default().push(i);
+        at.entry(key(b, tol)).or_default().push(i);
+    }
+    let mut used = vec![false; segments.len()];
+    let mut out = Vec::new();
+    for start in 0..segments.len() {
+        if used[start] {
+            continue;
+        }
+        used[start] = true;
+        let (a, b) = segments[start];
+        let mut pts = vec![a, b];
+        // extend forward from the tail, then backward from the head
+        for forward in [true, false] {
+            loop {
+                let end = if forward { pts[pts.len() - 1] } else { pts[0] };
+                let next = at.get(&key(end, tol)).and_then(|c| c.iter().copied().find(|&i| !used[i]));
+                let Some(i) = next else { break };
+                used[i] = true;
+                let (p, q) = segments[i];
+                let other = if key(p, tol) == key(end, tol) { q } else { p };
+                if forward {
+                    pts.push(other);
+                } else {
+                    pts.insert(0, other);
+                }
+            }
+        }
+        out.push(LineString::new(pts));
+    }
+    out
+}
+
+/// One step of a cutting job.
+#[derive(Clone, Debug)]
+pub enum CutOp {
+    /// Cut along the path, starting at its first point.
+    Path(LineString<f64>),
+    Drill(Coord<f64>),
+}
+
+impl CutOp {
+    fn start(&self) -> Coord<f64> {
+        match self {
+            CutOp::Path(ls) => ls.0.first().copied().unwrap_or_default(),
+            CutOp::Drill(c) => *c,
+        }
+    }
+
+    fn end(&self) -> Coord<f64> {
+        match self {
+            CutOp::Path(ls) => ls.0.last().copied().unwrap_or_default(),
+            CutOp::Drill(c) => *c,
+        }
+    }
+}
+
+/// Ordered operations and the rapid moves between them.
+#[derive(Clone, Debug, Default)]
+pub struct CutPlan {
+    pub ops: Vec<CutOp>,
+    /// From / to of every rapid, starting at the home position.
+    pub rapids: Vec<(Coord<f64>, Coord<f64>)>,
+}
+
+impl CutPlan {
+    pub fn rapid_length(&self) -> f64 {
+        self.rapids.iter().map(|&(a, b)| dist(a, b)).sum()
+    }
+}
+
+fn dist(a: Coord<f64>, b: Coord<f64>) -> f64 {
+    (b - a).x.hypot((b - a).y)
+}
+
+fn is_closed(ls: &LineString<f64>) -> bool {
+    ls.0.len() > 3 && ls.0.first() == ls.0.last()
+}
+
+/// Rapid travel if the operations are run in the given order.
+pub fn rapid_length_in_order(paths: &[LineString<f64>], drills: &[Coord<f64>], home: Coord<f64>) -> f64 {
+    let mut at = home;
+    let mut total = 0.0;
+    for op in paths.iter().cloned().map(CutOp::Path).chain(drills.iter().copied().map(CutOp::Drill)) {
+        total += dist(at, op.start());
+        at = op.end();
+    }
+    total
+}
+
+/// Greedy nearest-neighbour ordering from `home`. Closed loops are entered
+/// at their closest vertex and open paths from their closer end; a loop is
+/// only eligible once every loop inside it has been cut.
+pub fn order_cuts(paths: &[LineString<f64>], drills: &[Coord<f64>], home: Coord<f64>) -> CutPlan {
+    // children[i]: loops whose start lies inside closed loop i
+    let polys: Vec<Option<Polygon<f64>>> = paths
+        .iter()
+        .map(|ls| is_closed(ls).then(|| Polygon::new(ls.clone(), Vec::new())))
+        .collect();
+    let mut pending_children = vec![0usize; paths.len()];
+    let mut parents: Vec<Vec<usize>> = vec![Vec::new(); paths.len()];
+    for (i, ls) in paths.iter().enumerate() {
+        let Some(&probe) = ls.0.first() else { continue };
+        for (j, poly) in polys.iter().enumerate() {
+            if i != j && poly.as_ref().is_some_and(|p| p.contains(&Point::from(probe))) {
+                pending_children[j] += 1;
+                parents[i].push(j);
+            }
+        }
+    }
+
+    let mut plan = CutPlan::default();
+    let mut done: Vec<bool> = paths.iter().map(|ls| ls.0.is_empty()).collect();
+    let mut drills_left: Vec<Coord<f64>> = drills.to_vec();
+    let mut at = home;
+    loop {
+        // best (distance, op) among ready paths and remaining drills
+        let mut best: Option<(f64, CutOp, Option<usize>)> = None;
+        for (i, ls) in paths.iter().enumerate() {
+            if done[i] || pending_children[i] > 0 {
+                continue;
+            }
+            let op = if is_closed(ls) {
+                let (k, _) = ls.0[..ls.0.len() - 1]
+                    .iter()
+                    .enumerate()
+                    .min_by(|a, b| dist(*a.1, at).total_cmp(&dist(*b.1, at)))
+                    .unwrap_or((0, &at));
+                crate::slicer::start_ring_at(ls, k)
+            } else if dist(ls.0[ls.0.len() - 1], at) < dist(ls.0[0], at) {
+                LineString::new(ls.0.iter().rev().copied().collect())
+            } else {
+                ls.clone()
+            };
+            let d = dist(at, op.0[0]);
+            if best.as_ref().is_none_or(|b| d < b.0) {
+                best = Some((d, CutOp::Path(op), Some(i)));
+            }
+        }
+        for &c in &drills_left {
+            let d = dist(at, c);
+            if best.as_ref().is_none_or(|b| d < b.0) {
+                best = Some((d, CutOp::Drill(c), None));
+            }
+        }
+        let Some((_, op, index)) = best else {
+            if done.iter().all(|&d| d) {
+                break;
+            }
+            // mutual containment (duplicate loops): release the loop waiting on
+            // the fewest others, keeping the rest of the nesting order
+            let stuck = (0..paths.len()).filter(|&i| !done[i]).min_by_key(|&i| pending_children[i]);
+            if let Some(i) = stuck {
+                pending_children[i] = 0;
+            }
+            continue;
+        };
+        match index {
+            Some(i) => {
+                done[i] = true;
+                for &p in &parents[i] {
+                    // a released loop may have children still to come
+                    pending_children[p] = pending_children[p].saturating_sub(1);
+                }
+            }
+            None => {
+                let c = op.start();
+                drills_left.retain(|&d| d != c);
+            }
+        }
+        plan.rapids.push((at, op.start()));
+        at = op.end();
+        plan.ops.push(op);
+    }
+    plan
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ring(pts: &[(f64, f64)]) -> LineString<f64> {
+        let mut ls: LineString<f64> = pts.iter().map(|&(x, y)| Coord { x, y }).collect();
+        ls.close();
+        ls
+    }
+
+    fn square(x0: f64, y0: f64, x1: f64, y1: f64) -> LineString<f64> {
+        ring(&[(x0, y0), (x1, y0), (x1, y1), (x0, y1)])
+    }
+
+    fn lengths(plan: &CutPlan) -> Vec<f64> {
+        plan.ops
+            .iter()
+            .map(|op| match op {
+                CutOp::Path(ls) => path_length(ls),
+                CutOp::Drill(_) => 0.0,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn merge_common_lines_cuts_a_shared_edge_once() {
+        let (merged, stats) = merge_common_lines(&[square(0.0, 0.0, 10.0, 10.0), square(10.0, 0.0, 20.0, 10.0)], 0.01);
+        assert_eq!(stats.shared_segments, 1);
+        assert!((stats.length_before - 80.0).abs() < 1e-9);
+        assert!((stats.length_after - 70.0).abs() < 1e-9);
+        assert!((merged.iter().map(path_length).sum::<f64>() - 70.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn merge_common_lines_splits_partly_shared_edges() {
+        let (_, stats) = merge_common_lines(&[square(0.0, 0.0, 10.0, 10.0), square(10.0, 0.0, 20.0, 5.0)], 0.01);
+        assert_eq!(stats.shared_segments, 1);
+        assert!((stats.saved() - 5.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn order_cuts_cuts_inner_loops_first() {
+        let paths = [square(0.0, 0.0, 20.0, 20.0), square(5.0, 5.0, 15.0, 15.0)];
+        let home = Coord { x: 0.0, y: 0.0 };
+        let plan = order_cuts(&paths, &[Coord { x: 30.0, y: 30.0 }], home);
+        assert_eq!(lengths(&plan), [40.0, 80.0, 0.0]);
+        assert_eq!(plan.rapids.len(), plan.ops.len());
+        assert_eq!(plan.rapids[0], (home, Coord { x: 5.0, y: 5.0 }));
+    }
+
+    #[test]
+    fn order_cuts_enters_open_paths_from_the_closer_end() {
+        let home = Coord { x: 0.0, y: 0.0 };
+        let line: LineString<f64> = vec![Coord { x: 10.0, y: 0.0 }, home].into();
+        let plan = order_cuts(&[line], &[], home);
+        assert_eq!(plan.rapid_length(), 0.0);
+        assert!(matches!(&plan.ops[..], [CutOp::Path(ls)] if ls.0[0] == home));
+    }
+
+    #[test]
+    fn order_cuts_releases_mutually_nested_loops() {
+        let paths = [
+            ring(&[(2.0, 2.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)]),
+            ring(&[(8.0, 8.0), (0.0, 10.0), (0.0, 0.0), (10.0, 0.0)]),
+        ];
+        let plan = order_cuts(&paths, &[], Coord { x: 0.0, y: 0.0 });
+        assert_eq!(plan.ops.len(), 2);
+    }
+}
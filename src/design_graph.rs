@@ -1,13 +1,16 @@
 use csgrs::{mesh::Mesh, mesh::plane::Plane, sketch::Sketch, traits::CSG};
 use egui::{self, DragValue};
 use egui_node_graph2::*;
-use nalgebra::{Point3, Vector3};
-use crate::fonts;
+use nalgebra::{Matrix4, Point3, Rotation3, Vector2, Vector3};
+use crate::{drawing::Dimension, fonts};
 
+/// Node actions the app applies after the editor is drawn.
 #[derive(Clone, Debug)]
-pub struct EmptyUserResponse;
+pub enum GraphResponse {
+    ToggleBypass(NodeId),
+}
 
-impl UserResponseTrait for EmptyUserResponse {}
+impl UserResponseTrait for GraphResponse {}
 
 /// Ports may carry scalars, vectors, planar **sketches**, or volumetric **meshes**.
 #[derive(PartialEq, Eq, Copy, Clone)]
@@ -15,8 +18,11 @@ pub enum DType {
     Mesh,
     Sketch,
     Scalar,
+    Vec2,
     Vec3,
     Text,
+    /// Translation, rotation and scale in one affine matrix.
+    Transform,
 }
 
 /// Run-time value carried by a port when the graph is evaluated.
@@ -25,8 +31,10 @@ pub enum DValue {
     Mesh(Mesh<()>),
     Sketch(Sketch<()>),
     Scalar(f64),
+    Vec2(Vector2<f64>),
     Vec3(Vector3<f64>),
     Text(String),
+    Transform(Matrix4<f64>),
 }
 
 impl Default for DValue {
@@ -36,7 +44,7 @@ impl Default for DValue {
 }
 
 /// A node “template” = what appears in the “add node” pop-up.
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, serde::Serialize, serde::Deserialize)]
 pub enum Template {
     /* ---- Sketch primitives ---- */
     Square,
@@ -91,6 +99,7 @@ pub enum Template {
     RotateMesh,
     ScaleMesh,
     MirrorMesh,
+    SymmetryMesh,
     CenterMesh,
     FloatMesh,
     InverseMesh,
@@ -103,6 +112,7 @@ pub enum Template {
     RotateSketch,
     ScaleSketch,
     MirrorSketch,
+    SymmetrySketch,
     CenterSketch,
     FloatSketch,
     InverseSketch,
@@ -110,12 +120,19 @@ pub enum Template {
     DistributeLinearSketch,
     DistributeGridSketch,
 
+    /* ---- Transform values ---- */
+    MakeTransform,
+    ComposeTransform,
+    TransformMesh,
+    TransformSketch,
+
     /* ---- 2D -> 3D ---- */
     Extrude,
     ExtrudeVector,
     Revolve,
     Loft,
     Sweep,
+    Shell,
 
     /* ---- Mesh <-> Sketch helpers ---- */
     Flatten,
@@ -127,6 +144,15 @@ pub enum Template {
     SchwarzD,
     /* ---- Text ---- */
     TruetypeText,
+
+    /* ---- Sinks ---- */
+    /// Offers its mesh as a file download on Apply.
+    ExportMesh,
+
+    /* ---- Plugin node (slot in the plugin registry) ---- */
+    /// Slots are per session; saved graphs refer to plugin nodes by name.
+    #[serde(skip)]
+    Plugin(u32),
 }
 
 impl Default for Template {
@@ -153,12 +179,83 @@ pub fn graph_roots(graph: &GraphT) -> Vec<OutputId> {
         .collect()
 }
 
+/// Sketch outputs of `node`, in port order.
+pub fn sketch_outputs(graph: &GraphT, node: NodeId) -> Vec<OutputId> {
+    graph[node].outputs.iter().map(|&(_, id)| id).filter(|&id| graph[id].typ == DType::Sketch).collect()
+}
+
+/// The operation of a two-operand boolean node (inputs `A` and `B`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BooleanOp {
+    Union,
+    Subtract,
+    Intersect,
+}
+
+pub fn boolean_op(graph: &GraphT, node: NodeId) -> Option<BooleanOp> {
+    use Template::*;
+    match graph[node].user_data.template {
+        MeshUnion | SketchUnion => Some(BooleanOp::Union),
+        MeshSubtract | SketchSubtract => Some(BooleanOp::Subtract),
+        MeshIntersect | SketchIntersect => Some(BooleanOp::Intersect),
+        _ => None,
+    }
+}
+
+/// Evaluate what arrives at input `name` of `node`: the connected output,
+/// or the input's constant.
+pub fn evaluate_input(graph: &GraphT, node: NodeId, name: &str) -> anyhow::Result<DValue> {
+    let input = graph[node].get_input(name)?;
+    match graph.connections(input).first() {
+        Some(&src) => eval_rec(graph, src, &mut Cache::default()),
+        None => Ok(graph[input].value.clone()),
+    }
+}
+
+/// Evaluate output `out` to whatever value it carries.
+pub fn evaluate_value(graph: &GraphT, out: OutputId) -> anyhow::Result<DValue> {
+    eval_rec(graph, out, &mut Cache::default())
+}
+
+/// Export Mesh nodes, in node order.
+pub fn mesh_exports(graph: &GraphT) -> Vec<NodeId> {
+    graph.nodes.iter().filter(|(_, n)| matches!(n.user_data.template, Template::ExportMesh)).map(|(id, _)| id).collect()
+}
+
+/// A file an Export Mesh node offers, and the mesh in it.
+pub struct MeshExport {
+    pub file_name: String,
+    pub bytes: Vec<u8>,
+    pub mesh: Mesh<()>,
+}
+
+pub fn evaluate_export(graph: &GraphT, node: NodeId) -> anyhow::Result<MeshExport> {
+    let mesh = evaluate_input(graph, node, "mesh").and_then(|v| v.mesh()).map_err(|_| anyhow::anyhow!("no mesh connected"))?;
+    let name = evaluate_input(graph, node, "file_name")?.text()?;
+    let format = evaluate_input(graph, node, "format")?.text()?.trim().to_ascii_lowercase();
+    let stem = name.trim().trim_end_matches(&format!(".{format}")).trim();
+    let stem = if stem.is_empty() { "part" } else { stem };
+    let bytes = match format.as_str() {
+        "stl" => mesh.to_stl_binary(stem)?,
+        "amf" => mesh.to_amf(stem, "millimeter").into_bytes(),
+        other => anyhow::bail!("unknown export format “{other}” (use stl or amf)"),
+    };
+    Ok(MeshExport { file_name: format!("{stem}.{format}"), bytes, mesh })
+}
+
 #[derive(Default)]
 pub struct UserState;
 
 #[derive(Default, Debug)]
 pub struct NodeData {
     pub template: Template,
+    /// Left out of evaluation: the node passes its first input of the
+    /// output's type through unchanged, or yields nothing without one.
+    pub bypass: bool,
+    /// Time the node took in the last Apply (ms), inputs not included.
+    pub eval_ms: Option<f64>,
+    /// Annotations on the node's sketch, placed in the sketch preview.
+    pub dimensions: Vec<Dimension>,
 }
 
 /// Color & label palette for sockets
@@ -168,8 +265,10 @@ impl DataTypeTrait<UserState> for DType {
             DType::Mesh => egui::Color32::from_rgb(110, 200, 255),
             DType::Sketch => egui::Color32::from_rgb(120, 180, 120),
             DType::Scalar => egui::Color32::from_rgb(38, 109, 211),
+            DType::Vec2 => egui::Color32::from_rgb(214, 160, 90),
             DType::Vec3 => egui::Color32::from_rgb(238, 207, 109),
             DType::Text => egui::Color32::from_rgb(200, 160, 240),
+            DType::Transform => egui::Color32::from_rgb(230, 120, 120),
         }
     }
     fn name(&self) -> std::borrow::Cow<'_, str> {
@@ -177,8 +276,10 @@ impl DataTypeTrait<UserState> for DType {
             DType::Mesh => "mesh".into(),
             DType::Sketch => "sketch".into(),
             DType::Scalar => "scalar".into(),
+            DType::Vec2 => "vec2".into(),
             DType::Vec3 => "vec3".into(),
             DType::Text => "text".into(),
+            DType::Transform => "transform".into(),
         }
     }
 }
@@ -196,6 +297,16 @@ fn scalar_in(g: &mut Graph<NodeData, DType, DValue>, id: NodeId, name: &str, def
         true,
     );
 }
+fn vec2_in(g: &mut Graph<NodeData, DType, DValue>, id: NodeId, name: &str, def: Vector2<f64>) {
+    g.add_input_param(
+        id,
+        name.into(),
+        DType::Vec2,
+        DValue::Vec2(def),
+        InputParamKind::ConnectionOrConstant,
+        true,
+    );
+}
 fn vec3_in(g: &mut Graph<NodeData, DType, DValue>, id: NodeId, name: &str, def: Vector3<f64>) {
     g.add_input_param(
         id,
@@ -241,6 +352,17 @@ fn text_in(
         true,
     );
 }
+/// Unconnected transform inputs are the identity.
+fn transform_in(g: &mut Graph<NodeData, DType, DValue>, id: NodeId, name: &str) {
+    g.add_input_param(
+        id,
+        name.into(),
+        DType::Transform,
+        DValue::Transform(Matrix4::identity()),
+        InputParamKind::ConnectionOnly,
+        true,
+    );
+}
 fn mesh_out(g: &mut Graph<NodeData, DType, DValue>, id: NodeId, name: &str) {
     g.add_output_param(id, name.into(), DType::Mesh);
 }
@@ -312,6 +434,7 @@ impl NodeTemplateTrait for Template {
             RotateMesh => "Rotate Mesh".into(),
             ScaleMesh => "Scale Mesh".into(),
             MirrorMesh => "Mirror Mesh".into(),
+            SymmetryMesh => "Symmetry (Mesh)".into(),
             CenterMesh => "Center Mesh".into(),
             FloatMesh => "Float Mesh".into(),
             InverseMesh => "Inverse Mesh".into(),
@@ -324,6 +447,7 @@ impl NodeTemplateTrait for Template {
             RotateSketch => "Rotate Sketch".into(),
             ScaleSketch => "Scale Sketch".into(),
             MirrorSketch => "Mirror Sketch".into(),
+            SymmetrySketch => "Symmetry (Sketch)".into(),
             CenterSketch => "Center Sketch".into(),
             FloatSketch => "Float Sketch".into(),
             InverseSketch => "Inverse Sketch".into(),
@@ -331,12 +455,19 @@ impl NodeTemplateTrait for Template {
             DistributeLinearSketch => "Distribute Linear (Sketch)".into(),
             DistributeGridSketch => "Distribute Grid (Sketch)".into(),
 
+            /* transform values */
+            MakeTransform => "Make Transform".into(),
+            ComposeTransform => "Compose Transform".into(),
+            TransformMesh => "Transform Mesh".into(),
+            TransformSketch => "Transform Sketch".into(),
+
             /* 2D -> 3D */
             Extrude => "Extrude".into(),
             ExtrudeVector => "Extrude Vector".into(),
             Revolve => "Revolve".into(),
             Loft => "Loft".into(),
             Sweep => "Sweep".into(),
+            Shell => "Shell".into(),
 
             /* mesh<->sketch */
             Flatten => "Flatten".into(),
@@ -346,6 +477,9 @@ impl NodeTemplateTrait for Template {
             SchwarzP => "Schwarz P".into(),
             SchwarzD => "Schwarz D".into(),
             TruetypeText=>"TrueType text".into(),
+            ExportMesh => "Export Mesh".into(),
+            Plugin(slot) => crate::plugins::node_spec(*slot)
+                .map_or_else(|| "(missing plugin)".into(), |s| s.label.into()),
         }
     }
     fn node_finder_categories(&self, _: &mut UserState) -> Vec<Self::CategoryType> {
@@ -368,6 +502,7 @@ impl NodeTemplateTrait for Template {
             | RotateMesh
             | ScaleMesh
             | MirrorMesh
+            | SymmetryMesh
             | CenterMesh
             | FloatMesh
             | InverseMesh
@@ -378,23 +513,30 @@ impl NodeTemplateTrait for Template {
             | RotateSketch
             | ScaleSketch
             | MirrorSketch
+            | SymmetrySketch
             | CenterSketch
             | FloatSketch
             | InverseSketch
             | DistributeArcSketch
             | DistributeLinearSketch
-            | DistributeGridSketch => vec!["Transform"],
+            | DistributeGridSketch
+            | MakeTransform
+            | ComposeTransform
+            | TransformMesh
+            | TransformSketch => vec!["Transform"],
 
-            Extrude | ExtrudeVector | Revolve | Loft | Sweep => vec!["2D -> 3D"],
+            Extrude | ExtrudeVector | Revolve | Loft | Sweep | Shell => vec!["2D -> 3D"],
             Flatten | Slice => vec!["Mesh/Sketch"],
+            ExportMesh => vec!["Export"],
             Gyroid | SchwarzP | SchwarzD => vec!["Lattice"],
+            Plugin(_) => vec!["Plugins"],
         }
     }
     fn node_graph_label(&self, u: &mut UserState) -> String {
         self.node_finder_label(u).into()
     }
     fn user_data(&self, _: &mut UserState) -> Self::NodeData {
-        NodeData { template: *self }
+        NodeData { template: *self, bypass: false, eval_ms: None, dimensions: Vec::new() }
     }
 
     fn build_node(&self, g: &mut Graph<NodeData, DType, DValue>, _: &mut UserState, id: NodeId) {
@@ -671,6 +813,14 @@ impl NodeTemplateTrait for Template {
                 scalar_in(g, id, "plane_w", 0.0);
                 mesh_out(g, id, "out");
             }
+            SymmetryMesh => {
+                mesh_in(g, id, "in");
+                scalar_in(g, id, "mirror_x", 1.0);
+                scalar_in(g, id, "mirror_y", 0.0);
+                scalar_in(g, id, "mirror_z", 0.0);
+                vec3_in(g, id, "origin", Vector3::zeros());
+                mesh_out(g, id, "out");
+            }
             CenterMesh | FloatMesh | InverseMesh => {
                 mesh_in(g, id, "in");
                 mesh_out(g, id, "out");
@@ -702,7 +852,7 @@ impl NodeTemplateTrait for Template {
             /* ---- sketch transforms ---- */
             TranslateSketch => {
                 sketch_in(g, id, "in");
-                vec3_in(g, id, "offset", Vector3::zeros());
+                vec2_in(g, id, "offset", Vector2::zeros());
                 sketch_out(g, id, "out");
             }
             RotateSketch => {
@@ -713,15 +863,22 @@ impl NodeTemplateTrait for Template {
             }
             ScaleSketch => {
                 sketch_in(g, id, "in");
-                vec3_in(g, id, "factors", Vector3::new(1.0, 1.0, 1.0));
+                vec2_in(g, id, "factors", Vector2::new(1.0, 1.0));
                 sketch_out(g, id, "out");
             }
             MirrorSketch => {
                 sketch_in(g, id, "in");
-                vec3_in(g, id, "plane_normal", Vector3::x());
+                vec2_in(g, id, "plane_normal", Vector2::x());
                 scalar_in(g, id, "plane_w", 0.0);
                 sketch_out(g, id, "out");
             }
+            SymmetrySketch => {
+                sketch_in(g, id, "in");
+                scalar_in(g, id, "mirror_x", 1.0);
+                scalar_in(g, id, "mirror_y", 0.0);
+                vec2_in(g, id, "origin", Vector2::zeros());
+                sketch_out(g, id, "out");
+            }
             CenterSketch | FloatSketch | InverseSketch => {
                 sketch_in(g, id, "in");
                 sketch_out(g, id, "out");
@@ -737,7 +894,7 @@ impl NodeTemplateTrait for Template {
             DistributeLinearSketch => {
                 sketch_in(g, id, "in");
                 scalar_in(g, id, "count", 3.0);
-                vec3_in(g, id, "dir", Vector3::x());
+                vec2_in(g, id, "dir", Vector2::x());
                 scalar_in(g, id, "spacing", 2.0);
                 sketch_out(g, id, "out");
             }
@@ -750,6 +907,29 @@ impl NodeTemplateTrait for Template {
                 sketch_out(g, id, "out");
             }
 
+            /* ---- transform values ---- */
+            MakeTransform => {
+                vec3_in(g, id, "translate", Vector3::zeros());
+                vec3_in(g, id, "rotate_deg", Vector3::zeros());
+                vec3_in(g, id, "scale", Vector3::new(1.0, 1.0, 1.0));
+                g.add_output_param(id, "out".into(), DType::Transform);
+            }
+            ComposeTransform => {
+                transform_in(g, id, "first");
+                transform_in(g, id, "then");
+                g.add_output_param(id, "out".into(), DType::Transform);
+            }
+            TransformMesh => {
+                mesh_in(g, id, "in");
+                transform_in(g, id, "transform");
+                mesh_out(g, id, "out");
+            }
+            TransformSketch => {
+                sketch_in(g, id, "in");
+                transform_in(g, id, "transform");
+                sketch_out(g, id, "out");
+            }
+
             /* ---- 2D -> 3D ---- */
             Extrude => {
                 sketch_in(g, id, "profile");
@@ -779,6 +959,13 @@ impl NodeTemplateTrait for Template {
                 vec3_in(g, id, "p1", Vector3::new(0.0, 0.0, 5.0));
                 mesh_out(g, id, "out");
             }
+            Shell => {
+                sketch_in(g, id, "profile");
+                scalar_in(g, id, "wall", 2.0);
+                scalar_in(g, id, "height", 20.0);
+                scalar_in(g, id, "floor", 0.0);
+                mesh_out(g, id, "out");
+            }
 
             /* mesh<->sketch */
             Flatten => {
@@ -791,6 +978,11 @@ impl NodeTemplateTrait for Template {
                 scalar_in(g, id, "plane_w", 0.0);
                 sketch_out(g, id, "out");
             }
+            ExportMesh => {
+                mesh_in(g, id, "mesh");
+                text_in(g, id, "file_name", "part");
+                text_in(g, id, "format", "stl");
+            }
 
             Gyroid | SchwarzP | SchwarzD => {
                 mesh_in(g, id, "in");
@@ -803,6 +995,145 @@ impl NodeTemplateTrait for Template {
               //    scalar_in(g,id,"size",20.0);
               //    sketch_out(g,id,"out");
               //}
+            Plugin(slot) => {
+                let Some(spec) = crate::plugins::node_spec(*slot) else { return };
+                for port in &spec.inputs {
+                    let def = &port.default;
+                    match port.ty {
+                        crate::plugins::PortType::Scalar => scalar_in(g, id, &port.name, def.as_f64().unwrap_or(0.0)),
+                        crate::plugins::PortType::Vec2 => {
+                            let v: [f64; 2] = serde_json::from_value(def.clone()).unwrap_or_default();
+                            vec2_in(g, id, &port.name, Vector2::from(v));
+                        }
+                        crate::plugins::PortType::Vec3 => {
+                            let v: [f64; 3] = serde_json::from_value(def.clone()).unwrap_or_default();
+                            vec3_in(g, id, &port.name, Vector3::from(v));
+                        }
+                        crate::plugins::PortType::Text => text_in(g, id, &port.name, def.as_str().unwrap_or("")),
+                        crate::plugins::PortType::Mesh => mesh_in(g, id, &port.name),
+                        crate::plugins::PortType::Sketch => sketch_in(g, id, &port.name),
+                    }
+                }
+                g.add_output_param(id, "out".into(), spec.output.dtype());
+            }
+        }
+    }
+}
+
+/// Add a node built from `template` at `pos` (graph coordinates).
+pub fn add_node(state: &mut EditorState, template: Template, pos: egui::Pos2) -> NodeId {
+    let mut user = UserState;
+    let label = template.node_graph_label(&mut user);
+    let id = state.graph.add_node(label, template.user_data(&mut user), |g, id| {
+        template.build_node(g, &mut user, id);
+    });
+    state.node_positions.insert(id, pos);
+    state.node_order.push(id);
+    id
+}
+
+/* ------------------------------------------------------------------------- */
+/*  Layout                                                                   */
+/* ------------------------------------------------------------------------- */
+
+/// Column and row pitch of [`auto_layout`], in graph units.
+const LAYOUT_STEP: egui::Vec2 = egui::vec2(240.0, 160.0);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Align {
+    Left,
+    Right,
+    Top,
+}
+
+/// Line up the selected nodes on one edge. All nodes are drawn the same
+/// width, so right alignment lines up their left edges on the rightmost one.
+pub fn align_nodes(state: &mut EditorState, edge: Align) {
+    let selected: Vec<NodeId> = state.selected_nodes.iter().copied().filter(|&n| state.node_positions.contains_key(n)).collect();
+    let positions = selected.iter().map(|&n| state.node_positions[n]);
+    let target = match edge {
+        Align::Left => positions.map(|p| p.x).fold(f32::INFINITY, f32::min),
+        Align::Right => positions.map(|p| p.x).fold(f32::NEG_INFINITY, f32::max),
+        Align::Top => positions.map(|p| p.y).fold(f32::INFINITY, f32::min),
+    };
+    for n in selected {
+        let pos = &mut state.node_positions[n];
+        match edge {
+            Align::Left | Align::Right => pos.x = target,
+            Align::Top => pos.y = target,
+        }
+    }
+}
+
+/// Space the selected nodes evenly between the outermost two, across
+/// (`horizontal`) or down the canvas, keeping their order.
+pub fn distribute_nodes(state: &mut EditorState, horizontal: bool) {
+    let coord = |p: egui::Pos2| if horizontal { p.x } else { p.y };
+    let mut selected: Vec<NodeId> = state.selected_nodes.iter().copied().filter(|&n| state.node_positions.contains_key(n)).collect();
+    if selected.len() < 3 {
+        return;
+    }
+    selected.sort_by(|&a, &b| coord(state.node_positions[a]).total_cmp(&coord(state.node_positions[b])));
+    let first = coord(state.node_positions[selected[0]]);
+    let step = (coord(state.node_positions[selected[selected.len() - 1]]) - first) / (selected.len() - 1) as f32;
+    for (i, n) in selected.into_iter().enumerate() {
+        let pos = &mut state.node_positions[n];
+        *if horizontal { &mut pos.x } else { &mut pos.y } = first + step * i as f32;
+    }
+}
+
+/// Arrange the whole graph left to right in layers: every node one column
+/// right of its furthest input, each column ordered by where its inputs sit
+/// to keep wires from crossing. The layout starts at the graph's current
+/// top-left corner.
+pub fn auto_layout(state: &mut EditorState) {
+    use std::collections::HashMap;
+    let graph = &state.graph;
+    let mut sources: HashMap<NodeId, Vec<NodeId>> = HashMap::new();
+    for input_id in graph.inputs.keys() {
+        let to = graph[input_id].node;
+        for from in graph.connections(input_id) {
+            sources.entry(to).or_default().push(graph[from].node);
+        }
+    }
+
+    // longest path from a node without inputs; graphs are acyclic
+    let mut depth: HashMap<NodeId, usize> = HashMap::new();
+    fn depth_of(n: NodeId, sources: &HashMap<NodeId, Vec<NodeId>>, depth: &mut HashMap<NodeId, usize>) -> usize {
+        if let Some(&d) = depth.get(&n) {
+            return d;
+        }
+        depth.insert(n, 0); // guards against a cycle
+        let d = sources.get(&n).map_or(0, |s| s.iter().map(|&m| depth_of(m, sources, depth) + 1).max().unwrap_or(0));
+        depth.insert(n, d);
+        d
+    }
+    let order: Vec<NodeId> = state.node_order.iter().copied().filter(|&n| graph.nodes.contains_key(n)).collect();
+    let mut columns: Vec<Vec<NodeId>> = Vec::new();
+    for &n in &order {
+        let d = depth_of(n, &sources, &mut depth);
+        if columns.len() <= d {
+            columns.resize(d + 1, Vec::new());
+        }
+        columns[d].push(n);
+    }
+
+    let origin = order
+        .iter()
+        .filter_map(|&n| state.node_positions.get(n))
+        .fold(egui::pos2(f32::INFINITY, f32::INFINITY), |a, p| a.min(*p));
+    let origin = if origin.x.is_finite() { origin } else { egui::Pos2::ZERO };
+    let mut row: HashMap<NodeId, f32> = HashMap::new();
+    for (x, column) in columns.iter_mut().enumerate() {
+        // barycentre of the rows feeding each node; sources keep graph order
+        let key = |n: &NodeId| {
+            let feeds: Vec<f32> = sources.get(n).into_iter().flatten().filter_map(|m| row.get(m).copied()).collect();
+            if feeds.is_empty() { f32::MAX } else { feeds.iter().sum::<f32>() / feeds.len() as f32 }
+        };
+        column.sort_by(|a, b| key(a).total_cmp(&key(b)));
+        for (y, &n) in column.iter().enumerate() {
+            row.insert(n, y as f32);
+            state.node_positions.insert(n, origin + LAYOUT_STEP * egui::vec2(x as f32, y as f32));
         }
     }
 }
@@ -813,7 +1144,7 @@ impl NodeTemplateIter for AllTemplates {
     type Item = Template;
     fn all_kinds(&self) -> Vec<Self::Item> {
         use Template::*;
-        vec![
+        let mut kinds = vec![
             /* sketch */
             Square,
             Rectangle,
@@ -867,6 +1198,7 @@ impl NodeTemplateIter for AllTemplates {
             RotateMesh,
             ScaleMesh,
             MirrorMesh,
+            SymmetryMesh,
             CenterMesh,
             FloatMesh,
             InverseMesh,
@@ -877,31 +1209,40 @@ impl NodeTemplateIter for AllTemplates {
             RotateSketch,
             ScaleSketch,
             MirrorSketch,
+            SymmetrySketch,
             CenterSketch,
             FloatSketch,
             InverseSketch,
             DistributeArcSketch,
             DistributeLinearSketch,
             DistributeGridSketch,
+            MakeTransform,
+            ComposeTransform,
+            TransformMesh,
+            TransformSketch,
             /* 2D -> 3D */
             Extrude,
             ExtrudeVector,
             Revolve,
             Loft,
             Sweep,
+            Shell,
             Flatten,
             Slice,
+            ExportMesh,
             Gyroid,
             SchwarzP,
             SchwarzD,
-        ]
+        ];
+        kinds.extend(crate::plugins::node_slots().into_iter().map(Plugin));
+        kinds
     }
 }
 
 /// We draw scalars/vec3 widgets exactly like in the sample
 impl WidgetValueTrait for DValue {
     type NodeData = NodeData;
-    type Response = EmptyUserResponse;
+    type Response = GraphResponse;
     type UserState = UserState;
 
     fn value_widget(
@@ -919,15 +1260,24 @@ impl WidgetValueTrait for DValue {
                     ui.add(DragValue::new(x));
                 });
             }
+            DValue::Vec2(v) => {
+                ui.label(name);
+                ui.horizontal(|ui| {
+                    let label = ui.label("x");
+                    ui.add(DragValue::new(&mut v.x)).labelled_by(label.id);
+                    let label = ui.label("y");
+                    ui.add(DragValue::new(&mut v.y)).labelled_by(label.id);
+                });
+            }
             DValue::Vec3(v) => {
                 ui.label(name);
                 ui.horizontal(|ui| {
-                    ui.label("x");
-                    ui.add(DragValue::new(&mut v.x));
-                    ui.label("y");
-                    ui.add(DragValue::new(&mut v.y));
-                    ui.label("z");
-                    ui.add(DragValue::new(&mut v.z));
+                    let label = ui.label("x");
+                    ui.add(DragValue::new(&mut v.x)).labelled_by(label.id);
+                    let label = ui.label("y");
+                    ui.add(DragValue::new(&mut v.y)).labelled_by(label.id);
+                    let label = ui.label("z");
+                    ui.add(DragValue::new(&mut v.z)).labelled_by(label.id);
                 });
             }
             DValue::Sketch(_) => {
@@ -936,6 +1286,9 @@ impl WidgetValueTrait for DValue {
             DValue::Mesh(_) => {
                 ui.label("mesh");
             }
+            DValue::Transform(_) => {
+                ui.label(name);
+            }
             DValue::Text(s) => {
                 // Plain editor for generic text fields:
                 let is_truetype = matches!(node_data.template, Template::TruetypeText);
@@ -949,22 +1302,20 @@ impl WidgetValueTrait for DValue {
 
                 // For truetype: show a persisted-font dropdown (localStorage)
                 if is_family {
-                    #[cfg(target_arch = "wasm32")]
-                    {
-                        if let Ok(list) = fonts::list_persisted_ttf() {
-                            // Unique, sorted family names:
-                            let mut families: Vec<String> =
-                                list.into_iter().map(|p| p.family).collect();
-                            families.sort();
-                            families.dedup();
-
-                            // Filter by the current text (acts as a search query)
-                            let query = s.to_ascii_lowercase();
-                            let filtered = families.into_iter()
-                                .filter(|f| f.to_ascii_lowercase().contains(&query))
-                                .collect::<Vec<_>>();
-
-                            egui::ComboBox::from_id_salt("ttf_family_combo")
+                    if let Ok(list) = fonts::list_persisted_ttf() {
+                        // Unique, sorted family names:
+                        let mut families: Vec<String> =
+                            list.into_iter().map(|p| p.family).collect();
+                        families.sort();
+                        families.dedup();
+
+                        // Filter by the current text (acts as a search query)
+                        let query = s.to_ascii_lowercase();
+                        let filtered = families.into_iter()
+                            .filter(|f| f.to_ascii_lowercase().contains(&query))
+                            .collect::<Vec<_>>();
+
+                        egui::ComboBox::from_id_salt("ttf_family_combo")
 								.selected_text(if s.is_empty() { "(pick font)" } else { s.as_str() }) // <-- &str, not String
 								.show_ui(ui, |ui| {
 									for fam in &filtered {
@@ -973,13 +1324,8 @@ impl WidgetValueTrait for DValue {
 										}
 									}
 								});
-                        } else {
-                            ui.small("No persisted fonts found (localStorage).");
-                        }
-                    }
-                    #[cfg(not(target_arch = "wasm32"))]
-                    {
-                        ui.small("Font picker available on web build (wasm32).");
+                    } else {
+                        ui.small("No persisted fonts found (localStorage).");
                     }
                 }
 
@@ -1005,40 +1351,445 @@ impl WidgetValueTrait for DValue {
     }
 }
 
-/// Only bottom-panel UI (none here)
+/// Bypass toggle and last evaluation time under each node. Bypassed nodes
+/// get a dimmed title bar; timed ones are tinted from green to red by their
+/// share of the slowest node's time.
 impl NodeDataTrait for NodeData {
-    type Response = EmptyUserResponse;
+    type Response = GraphResponse;
     type UserState = UserState;
     type DataType = DType;
     type ValueType = DValue;
 
     fn bottom_ui(
         &self,
-        _ui: &mut egui::Ui,
-        _id: NodeId,
+        ui: &mut egui::Ui,
+        id: NodeId,
         _graph: &Graph<NodeData, DType, DValue>,
         _state: &mut UserState,
-    ) -> Vec<NodeResponse<EmptyUserResponse, Self>> {
-        Vec::new()
+    ) -> Vec<NodeResponse<GraphResponse, Self>> {
+        let hover = if self.bypass { "Evaluate this node again" } else { "Skip this node, passing its input through" };
+        let mut responses = Vec::new();
+        ui.horizontal(|ui| {
+            if ui.selectable_label(self.bypass, "Bypass").on_hover_text(hover).clicked() {
+                responses.push(NodeResponse::User(GraphResponse::ToggleBypass(id)));
+            }
+            if let Some(ms) = self.eval_ms {
+                ui.weak(format!("{ms:.1} ms")).on_hover_text("Evaluation time in the last Apply, inputs not included");
+            }
+        });
+        responses
+    }
+
+    fn titlebar_color(
+        &self,
+        ui: &egui::Ui,
+        _id: NodeId,
+        graph: &Graph<NodeData, DType, DValue>,
+        _state: &mut UserState,
+    ) -> Option<egui::Color32> {
+        if self.bypass {
+            return Some(ui.visuals().widgets.inactive.bg_fill.gamma_multiply(0.5));
+        }
+        let ms = self.eval_ms?;
+        let slowest = graph.nodes.values().filter_map(|n| n.user_data.eval_ms).fold(0.0, f64::max);
+        // only worth flagging once something is noticeably slow
+        if slowest < 1.0 {
+            return None;
+        }
+        let heat = (ms / slowest) as f32;
+        let cool = egui::Color32::from_rgb(60, 120, 70);
+        let hot = egui::Color32::from_rgb(190, 60, 40);
+        Some(cool.lerp_to_gamma(hot, heat))
+    }
+}
+
+// ---------- saving ----------------------------------------------------------------------------------
+
+pub type EditorState = GraphEditorState<NodeData, DType, DValue, Template, UserState>;
+
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+enum SavedTemplate {
+    Builtin(Template),
+    Plugin { plugin: String, entry: String },
+}
+
+/// Constant input value; meshes, sketches and transforms only ever arrive
+/// by connection.
+#[derive(Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+enum SavedValue {
+    Scalar(f64),
+    Vec2([f64; 2]),
+    Vec3([f64; 3]),
+    Text(String),
+}
+
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+struct SavedNode {
+    template: SavedTemplate,
+    pos: [f32; 2],
+    inputs: Vec<(String, SavedValue)>,
+    #[serde(default)]
+    bypass: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    dimensions: Vec<Dimension>,
+}
+
+/// Self-contained description of a design graph: nodes by template, their
+/// constant inputs and the connections between named ports.
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct SavedGraph {
+    version: u32,
+    nodes: Vec<SavedNode>,
+    /// (from node, output, to node, input), nodes by index into `nodes`.
+    connections: Vec<(usize, String, usize, String)>,
+}
+
+impl SavedGraph {
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn from_state(state: &EditorState) -> anyhow::Result<Self> {
+        let graph = &state.graph;
+        let ids: Vec<NodeId> = graph.nodes.keys().collect();
+        let index = |id: NodeId| ids.iter().position(|&n| n == id);
+        let mut nodes = Vec::with_capacity(ids.len());
+        let mut connections = Vec::new();
+        for (to, &id) in ids.iter().enumerate() {
+            let node = &graph[id];
+            let template = match node.user_data.template {
+                Template::Plugin(slot) => {
+                    let (plugin, entry) = crate::plugins::slot_key(slot)
+                        .ok_or_else(|| anyhow::anyhow!("node `{}` belongs to an unknown plugin", node.label))?;
+                    SavedTemplate::Plugin { plugin, entry }
+                }
+                t => SavedTemplate::Builtin(t),
+            };
+            let pos = state.node_positions.get(id).map_or([0.0; 2], |p| [p.x, p.y]);
+            let mut inputs = Vec::new();
+            for (name, input) in &node.inputs {
+                let value = match &graph[*input].value {
+                    DValue::Scalar(x) => Some(SavedValue::Scalar(*x)),
+                    DValue::Vec2(v) => Some(SavedValue::Vec2([v.x, v.y])),
+                    DValue::Vec3(v) => Some(SavedValue::Vec3([v.x, v.y, v.z])),
+                    DValue::Text(s) => Some(SavedValue::Text(s.clone())),
+                    DValue::Mesh(_) | DValue::Sketch(_) | DValue::Transform(_) => None,
+                };
+                if let Some(v) = value {
+                    inputs.push((name.clone(), v));
+                }
+                for src in graph.connections(*input) {
+                    let owner = graph[src].node;
+                    let Some(out) = graph[owner].outputs.iter().find(|(_, o)| *o == src) else { continue };
+                    if let Some(from) = index(owner) {
+                        connections.push((from, out.0.clone(), to, name.clone()));
+                    }
+                }
+            }
+            nodes.push(SavedNode {
+                template,
+                pos,
+                inputs,
+                bypass: node.user_data.bypass,
+                dimensions: node.user_data.dimensions.clone(),
+            });
+        }
+        Ok(Self { version: 1, nodes, connections })
+    }
+
+    /// Rebuild an editor state. Plugin nodes whose plugin is not loaded are
+    /// dropped (with their connections) and counted in the second value.
+    pub fn into_state(self) -> (EditorState, usize) {
+        let mut state = EditorState::default();
+        let mut ids = Vec::with_capacity(self.nodes.len());
+        let mut missing = 0;
+        for saved in self.nodes {
+            let template = match saved.template {
+                SavedTemplate::Builtin(t) => Some(t),
+                SavedTemplate::Plugin { plugin, entry } => crate::plugins::slot_for(&plugin, &entry).map(Template::Plugin),
+            };
+            let Some(template) = template else {
+                missing += 1;
+                ids.push(None);
+                continue;
+            };
+            let id = add_node(&mut state, template, egui::pos2(saved.pos[0], saved.pos[1]));
+            state.graph[id].user_data.bypass = saved.bypass;
+            state.graph[id].user_data.dimensions = saved.dimensions;
+            for (name, value) in saved.inputs {
+                let Ok(input) = state.graph[id].get_input(&name) else { continue };
+                let planar = state.graph[input].typ == DType::Vec2;
+                state.graph[input].value = match value {
+                    SavedValue::Scalar(x) => DValue::Scalar(x),
+                    SavedValue::Vec2([x, y]) => DValue::Vec2(Vector2::new(x, y)),
+                    // planar inputs were vec3 before vec2 existed
+                    SavedValue::Vec3([x, y, _]) if planar => DValue::Vec2(Vector2::new(x, y)),
+                    SavedValue::Vec3([x, y, z]) => DValue::Vec3(Vector3::new(x, y, z)),
+                    SavedValue::Text(s) => DValue::Text(s),
+                };
+            }
+            ids.push(Some(id));
+        }
+        for (from, out, to, input) in self.connections {
+            let (Some(Some(from)), Some(Some(to))) = (ids.get(from), ids.get(to)) else { continue };
+            let (Ok(out), Ok(input)) = (state.graph[*from].get_output(&out), state.graph[*to].get_input(&input)) else {
+                continue;
+            };
+            let pos = state.graph.connections(input).len();
+            state.graph.add_connection(out, input, pos);
+        }
+        (state, missing)
+    }
+
+    /// Deflated, URL-safe base64 form for sharing in a link fragment.
+    pub fn to_link(&self) -> anyhow::Result<String> {
+        use base64::Engine;
+        let json = serde_json::to_vec(self)?;
+        let packed = miniz_oxide::deflate::compress_to_vec(&json, 9);
+        Ok(base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(packed))
+    }
+
+    pub fn from_link(text: &str) -> anyhow::Result<Self> {
+        use base64::Engine;
+        let packed = base64::engine::general_purpose::URL_SAFE_NO_PAD.decode(text.trim())?;
+        let json = miniz_oxide::inflate::decompress_to_vec_with_limit(&packed, 16 << 20)
+            .map_err(|e| anyhow::anyhow!("corrupt graph link: {e:?}"))?;
+        Ok(serde_json::from_slice(&json)?)
+    }
+}
+
+impl std::fmt::Display for SavedValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SavedValue::Scalar(x) => write!(f, "{x}"),
+            SavedValue::Vec2([x, y]) => write!(f, "({x}, {y})"),
+            SavedValue::Vec3([x, y, z]) => write!(f, "({x}, {y}, {z})"),
+            SavedValue::Text(s) => write!(f, "“{s}”"),
+        }
+    }
+}
+
+impl SavedTemplate {
+    fn label(&self) -> String {
+        match self {
+            SavedTemplate::Builtin(t) => t.node_finder_label(&mut UserState).to_string(),
+            SavedTemplate::Plugin { entry, .. } => entry.clone(),
+        }
+    }
+}
+
+/// What changed from one saved graph to another. Nodes are matched by
+/// template and order of appearance, so a node that was deleted and added
+/// again counts as changed rather than removed and added.
+#[derive(Clone, Debug, Default)]
+pub struct GraphDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    /// One line per changed parameter or bypass state.
+    pub changed: Vec<String>,
+    /// Connections made plus connections broken.
+    pub rewired: usize,
+}
+
+impl GraphDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty() && self.rewired == 0
+    }
+
+    /// One-line summary, e.g. `+2 nodes, −1 node, 3 changes`.
+    pub fn summary(&self) -> String {
+        let plural = |n: usize, what: &str| format!("{n} {what}{}", if n == 1 { "" } else { "s" });
+        let mut parts = Vec::new();
+        if !self.added.is_empty() {
+            parts.push(format!("+{}", plural(self.added.len(), "node")));
+        }
+        if !self.removed.is_empty() {
+            parts.push(format!("−{}", plural(self.removed.len(), "node")));
+        }
+        if !self.changed.is_empty() {
+            parts.push(plural(self.changed.len(), "change"));
+        }
+        if self.rewired > 0 {
+            parts.push(plural(self.rewired, "connection change"));
+        }
+        if parts.is_empty() { "no changes".to_owned() } else { parts.join(", ") }
+    }
+}
+
+impl SavedGraph {
+    /// Per node: its template label and how many earlier nodes share it.
+    fn node_keys(&self) -> Vec<(String, usize)> {
+        let mut seen = std::collections::HashMap::<String, usize>::new();
+        self.nodes
+            .iter()
+            .map(|n| {
+                let label = n.template.label();
+                let count = seen.entry(label.clone()).or_default();
+                *count += 1;
+                (label, *count)
+            })
+            .collect()
+    }
+
+    /// Changes that turn `self` into `newer`.
+    pub fn diff(&self, newer: &SavedGraph) -> GraphDiff {
+        use std::collections::{HashMap, HashSet};
+        let name = |(label, n): &(String, usize)| if *n == 1 { label.clone() } else { format!("{label} #{n}") };
+        let (old_keys, new_keys) = (self.node_keys(), newer.node_keys());
+        let old: HashMap<&(String, usize), &SavedNode> = old_keys.iter().zip(&self.nodes).collect();
+        let new: HashMap<&(String, usize), &SavedNode> = new_keys.iter().zip(&newer.nodes).collect();
+
+        let mut diff = GraphDiff::default();
+        for key in &new_keys {
+            let after = new[key];
+            let Some(before) = old.get(key) else {
+                diff.added.push(name(key));
+                continue;
+            };
+            for (input, value) in &after.inputs {
+                match before.inputs.iter().find(|(i, _)| i == input) {
+                    Some((_, was)) if was == value => {}
+                    Some((_, was)) => diff.changed.push(format!("{}: {input} {was} → {value}", name(key))),
+                    None => diff.changed.push(format!("{}: {input} set to {value}", name(key))),
+                }
+            }
+            if before.bypass != after.bypass {
+                let state = if after.bypass { "bypassed" } else { "no longer bypassed" };
+                diff.changed.push(format!("{} {state}", name(key)));
+            }
+            if before.dimensions != after.dimensions {
+                diff.changed.push(format!("{}: dimensions edited", name(key)));
+            }
+        }
+        diff.removed = old_keys.iter().filter(|k| !new.contains_key(k)).map(name).collect();
+
+        let wires = |graph: &SavedGraph, keys: &[(String, usize)]| -> HashSet<_> {
+            graph
+                .connections
+                .iter()
+                .filter_map(|(from, out, to, input)| Some((keys.get(*from)?.clone(), out.clone(), keys.get(*to)?.clone(), input.clone())))
+                .collect()
+        };
+        diff.rewired = wires(self, &old_keys).symmetric_difference(&wires(newer, &new_keys)).count();
+        diff
     }
 }
 
 // ---------- evaluation ----------------------------------------------------------------------------------
 
-type Cache = std::collections::HashMap<OutputId, DValue>;
+/// Milliseconds each node spent evaluating, not counting its inputs.
+pub type NodeTimes = std::collections::HashMap<NodeId, f64>;
+
+/// One output reached by evaluation, in the order it was reached.
+#[derive(Clone, Debug)]
+pub struct TraceEvent {
+    pub node: NodeId,
+    pub label: String,
+    pub output: String,
+    /// Outputs between this one and the root.
+    pub depth: usize,
+    /// Taken from earlier in the same evaluation instead of evaluated again.
+    pub cached: bool,
+    /// Including the inputs.
+    pub ms: f64,
+    /// Short description of the value, or the error.
+    pub result: String,
+}
+
+/// What an evaluation measured: node times always, a trace on request.
+#[derive(Default)]
+pub struct EvalReport {
+    pub times: NodeTimes,
+    pub trace: Option<Vec<TraceEvent>>,
+}
+
+/// Values of the outputs evaluated so far, and where the time went.
+#[derive(Default)]
+struct Cache {
+    values: std::collections::HashMap<OutputId, DValue>,
+    times: NodeTimes,
+    /// Time spent in inputs of the node being evaluated.
+    inputs_ms: f64,
+    trace: Option<Vec<TraceEvent>>,
+    depth: usize,
+}
+
+fn describe(value: &DValue) -> String {
+    match value {
+        DValue::Mesh(m) => format!("mesh, {} polygons", m.polygons.len()),
+        DValue::Sketch(s) => format!("sketch, {} shapes", s.geometry.0.len()),
+        DValue::Scalar(x) => format!("{x}"),
+        DValue::Vec2(v) => format!("({}, {})", v.x, v.y),
+        DValue::Vec3(v) => format!("({}, {}, {})", v.x, v.y, v.z),
+        DValue::Text(s) => format!("“{s}”"),
+        DValue::Transform(_) => "transform".to_owned(),
+    }
+}
 type GraphT = Graph<NodeData, DType, DValue>;
 
 /// --------------------------------------------------------------------------
 /// **Graph evaluation** – returns a 3-D `Mesh<()>` to display.
 pub fn evaluate(graph: &GraphT, root: OutputId) -> anyhow::Result<Mesh<()>> {
-    let mut cache = Cache::new();
-    let val = eval_rec(graph, root, &mut cache)?;
-    match val {
+    evaluate_reported(graph, root, &mut EvalReport::default())
+}
+
+/// [`evaluate`], adding the time each node took to `report`, and the trace
+/// if `report` asks for one.
+pub fn evaluate_reported(graph: &GraphT, root: OutputId, report: &mut EvalReport) -> anyhow::Result<Mesh<()>> {
+    let mut cache = Cache { trace: report.trace.is_some().then(Vec::new), ..Cache::default() };
+    let val = eval_rec(graph, root, &mut cache);
+    for (node, ms) in cache.times {
+        *report.times.entry(node).or_default() += ms;
+    }
+    if let (Some(all), Some(events)) = (&mut report.trace, cache.trace) {
+        all.extend(events);
+    }
+    match val? {
         DValue::Mesh(mesh) => Ok(mesh),
         _ => anyhow::bail!("root output does not evaluate to a mesh"),
     }
 }
 
+/// Evaluate `root` to a flat 2-D sketch (for cutting it directly).
+pub fn evaluate_sketch(graph: &GraphT, root: OutputId) -> anyhow::Result<Sketch<()>> {
+    let mut cache = Cache::default();
+    match eval_rec(graph, root, &mut cache)? {
+        DValue::Sketch(sketch) => Ok(sketch),
+        DValue::Mesh(_) => anyhow::bail!("root output is a mesh, not a sketch; use \"Apply to model\""),
+        _ => anyhow::bail!("root output does not evaluate to a sketch"),
+    }
+}
+
+/// Hash of the nodes, connections and constant inputs; node positions are
+/// ignored. Used to tell whether the graph changed since it was saved.
+pub fn fingerprint(graph: &GraphT) -> u64 {
+    use std::hash::{Hash, Hasher};
+    let mut h = std::collections::hash_map::DefaultHasher::new();
+    graph.nodes.len().hash(&mut h);
+    for (id, node) in graph.nodes.iter() {
+        (id, node.user_data.bypass).hash(&mut h);
+        for dim in &node.user_data.dimensions {
+            let coords = match dim {
+                Dimension::Length { from, to, offset } => vec![from[0], from[1], to[0], to[1], *offset],
+                Dimension::Diameter { center, radius } => vec![center[0], center[1], *radius],
+            };
+            coords.iter().for_each(|c| c.to_bits().hash(&mut h));
+        }
+    }
+    for (id, input) in graph.inputs.iter() {
+        id.hash(&mut h);
+        match &input.value {
+            DValue::Scalar(x) => x.to_bits().hash(&mut h),
+            DValue::Vec2(v) => v.iter().for_each(|c| c.to_bits().hash(&mut h)),
+            DValue::Vec3(v) => v.iter().for_each(|c| c.to_bits().hash(&mut h)),
+            DValue::Text(s) => s.hash(&mut h),
+            DValue::Mesh(_) | DValue::Sketch(_) | DValue::Transform(_) => {}
+        }
+        graph.connections(id).hash(&mut h);
+    }
+    h.finish()
+}
+
 fn as_usize(x: f64) -> usize {
     if x <= 0.0 { 0 } else { x.round() as usize }
 }
@@ -1046,14 +1797,81 @@ fn as_bool(x: f64) -> bool {
     x.abs() > std::f64::EPSILON
 }
 
+/// `shape` unioned with its mirror images about the planes through `origin`
+/// with the given `normals`. Each plane mirrors everything before it, so
+/// two planes give four copies and three give eight.
+fn symmetric<S: CSG>(shape: S, normals: &[Vector3<f64>], origin: Vector3<f64>) -> S {
+    normals.iter().fold(shape, |acc, n| {
+        let mirrored = acc.mirror(Plane::from_normal(*n, n.dot(&origin)));
+        acc.union(&mirrored)
+    })
+}
+
+/// Value of a bypassed node's output of type `typ`: its first input of that
+/// type, or empty geometry when it has none (a primitive switched off).
+fn pass_through(graph: &GraphT, node: NodeId, typ: DType, cache: &mut Cache) -> anyhow::Result<DValue> {
+    for &(_, input) in &graph[node].inputs {
+        if graph[input].typ != typ {
+            continue;
+        }
+        return match graph.connections(input).first() {
+            Some(&src) => eval_rec(graph, src, cache),
+            None => Ok(graph[input].value.clone()),
+        };
+    }
+    match typ {
+        DType::Mesh => Ok(DValue::Mesh(Mesh::new())),
+        DType::Sketch => Ok(DValue::Sketch(Sketch::new())),
+        _ => anyhow::bail!("bypassed node `{}` has no input to pass through", graph[node].label),
+    }
+}
+
+fn trace_event(graph: &GraphT, out: OutputId, depth: usize, cached: bool) -> TraceEvent {
+    let node = graph[out].node;
+    let output = graph[node].outputs.iter().find(|(_, o)| *o == out).map_or_else(String::new, |(name, _)| name.clone());
+    TraceEvent { node, label: graph[node].label.clone(), output, depth, cached, ms: 0.0, result: String::new() }
+}
+
 fn eval_rec(graph: &GraphT, out: OutputId, cache: &mut Cache) -> anyhow::Result<DValue> {
-    if let Some(v) = cache.get(&out) {
-        return Ok(v.clone());
+    if let Some(v) = cache.values.get(&out) {
+        let v = v.clone();
+        if let Some(trace) = &mut cache.trace {
+            trace.push(TraceEvent { result: describe(&v), ..trace_event(graph, out, cache.depth, true) });
+        }
+        return Ok(v);
     }
+    // reserve the slot so the trace reads top-down from the root
+    let slot = cache.trace.as_mut().map(|trace| {
+        trace.push(trace_event(graph, out, cache.depth, false));
+        trace.len() - 1
+    });
+    let outer_inputs_ms = std::mem::take(&mut cache.inputs_ms);
+    let started = crate::platform::now_ms();
+    cache.depth += 1;
+    let value = eval_output(graph, out, cache);
+    cache.depth -= 1;
+    let elapsed = crate::platform::now_ms() - started;
+    *cache.times.entry(graph[out].node).or_default() += elapsed - cache.inputs_ms;
+    cache.inputs_ms = outer_inputs_ms + elapsed;
+    if let (Some(trace), Some(i)) = (&mut cache.trace, slot) {
+        trace[i].ms = elapsed;
+        trace[i].result = match &value {
+            Ok(v) => describe(v),
+            Err(e) => format!("error: {e}"),
+        };
+    }
+    let value = value?;
+    cache.values.insert(out, value.clone());
+    Ok(value)
+}
+
+/// Evaluate the node behind `out`; `eval_rec` caches, times and traces it.
+fn eval_output(graph: &GraphT, out: OutputId, cache: &mut Cache) -> anyhow::Result<DValue> {
     let node_id = graph[out].node;
-    log::warn!("node_id: {:#?}", node_id);
     let node = &graph[node_id];
-    log::warn!("node: {:#?}", node);
+    if node.user_data.bypass {
+        return pass_through(graph, node_id, graph[out].typ, cache);
+    }
 
     // Helper to fetch (recursively) an input
     let mut get = |name: &str| -> anyhow::Result<DValue> {
@@ -1260,24 +2078,17 @@ fn eval_rec(graph: &GraphT, out: OutputId, cache: &mut Cache) -> anyhow::Result<
 			let height  = get("height_mm")?.scalar()?;
 
 			// Load persisted bytes for (family, variant)
-			#[cfg(target_arch = "wasm32")]
-			{
-				let maybe_bytes = fonts::load_persisted_ttf(&family, &variant)
-					.map_err(|e| anyhow::anyhow!("load_persisted_ttf failed: {:?}", e))?;
-				match maybe_bytes {
-					Some(bytes) => {
-						// Call into csgrs: Sketch::truetype_text(ttf_bytes, text, height_mm, None)
-						// If your csgrs signature differs, adjust here accordingly.
-						DValue::Sketch(Sketch::text(&content, &bytes, height.into(), None))
-					}
-					None => {
-						anyhow::bail!("No persisted TTF for \"{family}:{variant}\". Pick a font that’s saved to localStorage.");
-					}
+			let maybe_bytes = fonts::load_persisted_ttf(&family, &variant)
+				.map_err(|e| anyhow::anyhow!("load_persisted_ttf failed: {e}"))?;
+			match maybe_bytes {
+				Some(bytes) => {
+					// Call into csgrs: Sketch::truetype_text(ttf_bytes, text, height_mm, None)
+					// If your csgrs signature differs, adjust here accordingly.
+					DValue::Sketch(Sketch::text(&content, &bytes, height.into(), None))
+				}
+				None => {
+					anyhow::bail!("No persisted TTF for \"{family}:{variant}\". Pick a font that’s been saved.");
 				}
-			}
-			#[cfg(not(target_arch = "wasm32"))]
-			{
-				anyhow::bail!("TrueType text requires web build (wasm32) with persisted fonts.");
 			}
 		}
 
@@ -1444,6 +2255,17 @@ fn eval_rec(graph: &GraphT, out: OutputId, cache: &mut Cache) -> anyhow::Result<
                 w.into(),
             )))
         }
+        SymmetryMesh => {
+            let m = get("in")?.mesh()?;
+            let axes = [get("mirror_x")?, get("mirror_y")?, get("mirror_z")?];
+            let mut normals = Vec::new();
+            for (axis, on) in [Vector3::x(), Vector3::y(), Vector3::z()].into_iter().zip(axes) {
+                if as_bool(on.scalar()?) {
+                    normals.push(axis);
+                }
+            }
+            DValue::Mesh(symmetric(m, &normals, get("origin")?.vec3()?))
+        }
         CenterMesh => {
             let m = get("in")?.mesh()?;
             DValue::Mesh(m.center())
@@ -1488,8 +2310,8 @@ fn eval_rec(graph: &GraphT, out: OutputId, cache: &mut Cache) -> anyhow::Result<
         ,
         TranslateSketch => {
             let s = get("in")?.sketch()?;
-            let o = get("offset")?.vec3()?;
-            DValue::Sketch(s.translate(o.x.into(), o.y.into(), o.z.into()))
+            let o = get("offset")?.vec2()?;
+            DValue::Sketch(s.translate(o.x.into(), o.y.into(), 0.0))
         }
         RotateSketch => {
             let s = get("in")?.sketch()?;
@@ -1500,17 +2322,26 @@ fn eval_rec(graph: &GraphT, out: OutputId, cache: &mut Cache) -> anyhow::Result<
         }
         ScaleSketch => {
             let s = get("in")?.sketch()?;
-            let f = get("factors")?.vec3()?;
-            DValue::Sketch(s.scale(f.x.into(), f.y.into(), f.z.into()))
+            let f = get("factors")?.vec2()?;
+            DValue::Sketch(s.scale(f.x.into(), f.y.into(), 1.0))
         }
         MirrorSketch => {
             let s = get("in")?.sketch()?;
-            let n = get("plane_normal")?.vec3()?;
+            let n = get("plane_normal")?.vec2()?;
             let w = get("plane_w")?.scalar()?;
-            DValue::Sketch(s.mirror(Plane::from_normal(
-                Vector3::new(n.x.into(), n.y.into(), n.z.into()),
-                w.into(),
-            )))
+            DValue::Sketch(s.mirror(Plane::from_normal(Vector3::new(n.x.into(), n.y.into(), 0.0), w.into())))
+        }
+        SymmetrySketch => {
+            let s = get("in")?.sketch()?;
+            let axes = [get("mirror_x")?, get("mirror_y")?];
+            let mut normals = Vec::new();
+            for (axis, on) in [Vector3::x(), Vector3::y()].into_iter().zip(axes) {
+                if as_bool(on.scalar()?) {
+                    normals.push(axis);
+                }
+            }
+            let o = get("origin")?.vec2()?;
+            DValue::Sketch(symmetric(s, &normals, Vector3::new(o.x, o.y, 0.0)))
         }
         CenterSketch => {
             let s = get("in")?.sketch()?;
@@ -1535,13 +2366,9 @@ fn eval_rec(graph: &GraphT, out: OutputId, cache: &mut Cache) -> anyhow::Result<
         DistributeLinearSketch => {
             let s = get("in")?.sketch()?;
             let count = as_usize(get("count")?.scalar()?);
-            let dir = get("dir")?.vec3()?;
+            let dir = get("dir")?.vec2()?;
             let spacing = get("spacing")?.scalar()?;
-            DValue::Sketch(s.distribute_linear(
-                count,
-                Vector3::new(dir.x.into(), dir.y.into(), dir.z.into()),
-                spacing.into(),
-            ))
+            DValue::Sketch(s.distribute_linear(count, Vector3::new(dir.x.into(), dir.y.into(), 0.0), spacing.into()))
         }
         DistributeGridSketch => {
             let s = get("in")?.sketch()?;
@@ -1552,6 +2379,34 @@ fn eval_rec(graph: &GraphT, out: OutputId, cache: &mut Cache) -> anyhow::Result<
             DValue::Sketch(s.distribute_grid(rows, cols, dx.into(), dy.into()))
         }
 
+        /* ---- transform values ---- */
+        ,
+        MakeTransform => {
+            let t = get("translate")?.vec3()?;
+            let r = get("rotate_deg")?.vec3()?.map(f64::to_radians);
+            let s = get("scale")?.vec3()?;
+            // scale, then rotate about X, Y, Z, then translate
+            let m = Matrix4::new_translation(&t)
+                * Rotation3::from_euler_angles(r.x, r.y, r.z).to_homogeneous()
+                * Matrix4::new_nonuniform_scaling(&s);
+            DValue::Transform(m)
+        }
+        ComposeTransform => {
+            let first = get("first")?.transform()?;
+            let then = get("then")?.transform()?;
+            DValue::Transform(then * first)
+        }
+        TransformMesh => {
+            let m = get("in")?.mesh()?;
+            let t = get("transform")?.transform()?;
+            DValue::Mesh(m.transform(&t))
+        }
+        TransformSketch => {
+            let s = get("in")?.sketch()?;
+            let t = get("transform")?.transform()?;
+            DValue::Sketch(s.transform(&t))
+        }
+
         /* ---- 2D -> 3D ---- */
         ,
         Extrude => {
@@ -1587,6 +2442,21 @@ fn eval_rec(graph: &GraphT, out: OutputId, cache: &mut Cache) -> anyhow::Result<
             ];
             DValue::Mesh(s.sweep(&path))
         }
+        Shell => {
+            let s = get("profile")?.sketch()?;
+            let wall = get("wall")?.scalar()?;
+            let h = get("height")?.scalar()?;
+            if h.is_nan() || h <= 0.0 {
+                anyhow::bail!("shell height must be greater than 0");
+            }
+            if wall.is_nan() || wall <= 0.0 {
+                anyhow::bail!("shell wall must be thicker than 0");
+            }
+            let floor = get("floor")?.scalar()?.clamp(0.0, h);
+            // the wall grows inward, so the outline keeps its size
+            let walls = s.difference(&s.offset(-wall)).extrude(h);
+            DValue::Mesh(if floor > 0.0 { walls.union(&s.extrude(floor)) } else { walls })
+        }
 
         /* mesh<->sketch */
         ,
@@ -1602,6 +2472,8 @@ fn eval_rec(graph: &GraphT, out: OutputId, cache: &mut Cache) -> anyhow::Result<
                 Plane::from_normal(Vector3::new(n.x.into(), n.y.into(), n.z.into()), w.into());
             DValue::Sketch(m.slice(plane))
         }
+        // a sink: nothing reads its output, but the mesh passes through
+        ExportMesh => get("mesh")?,
 
         Gyroid => {
             let m = get("in")?.mesh()?;
@@ -1623,6 +2495,15 @@ fn eval_rec(graph: &GraphT, out: OutputId, cache: &mut Cache) -> anyhow::Result<
             let period = get("period")?.scalar()?;
             let iso = get("iso_value")?.scalar()?;
             DValue::Mesh(m.schwarz_d(res, period.into(), iso.into(), None))
+        }
+        Plugin(slot) => {
+            let spec = crate::plugins::node_spec(slot)
+                .ok_or_else(|| anyhow::anyhow!("plugin node no longer available"))?;
+            let mut inputs = Vec::with_capacity(spec.inputs.len());
+            for port in &spec.inputs {
+                inputs.push((port.name.clone(), get(&port.name)?));
+            }
+            crate::plugins::eval_node(slot, &inputs)?
         } //,Text => {
           //    // Supply a font in your project (adjust path)
           //    const FONT:&[u8]=include_bytes!("../assets/DejaVuSans.ttf");
@@ -1632,19 +2513,18 @@ fn eval_rec(graph: &GraphT, out: OutputId, cache: &mut Cache) -> anyhow::Result<
           //}
     };
 
-    cache.insert(out, value.clone());
-    log::warn!("value: {:#?}", value);
-    log::warn!("cache: {:#?}", cache);
     Ok(value)
 }
 
 /// Small helpers for type-safe extraction -----------------------------------
 trait AsTyped {
     fn scalar(self) -> anyhow::Result<f64>;
+    fn vec2(self) -> anyhow::Result<Vector2<f64>>;
     fn vec3(self) -> anyhow::Result<Vector3<f64>>;
     fn mesh(self) -> anyhow::Result<Mesh<()>>;
     fn sketch(self) -> anyhow::Result<Sketch<()>>;
     fn text(self) -> anyhow::Result<String>;
+    fn transform(self) -> anyhow::Result<Matrix4<f64>>;
 }
 impl AsTyped for DValue {
     fn scalar(self) -> anyhow::Result<f64> {
@@ -1654,6 +2534,13 @@ impl AsTyped for DValue {
             anyhow::bail!("expected scalar")
         }
     }
+    fn vec2(self) -> anyhow::Result<Vector2<f64>> {
+        if let DValue::Vec2(v) = self {
+            Ok(v)
+        } else {
+            anyhow::bail!("expected vec2")
+        }
+    }
     fn vec3(self) -> anyhow::Result<Vector3<f64>> {
         if let DValue::Vec3(v) = self {
             Ok(v)
@@ -1678,4 +2565,7 @@ impl AsTyped for DValue {
     fn text(self) -> anyhow::Result<String> {
         if let DValue::Text(s) = self { Ok(s) } else { anyhow::bail!("expected text") }
     }
+    fn transform(self) -> anyhow::Result<Matrix4<f64>> {
+        if let DValue::Transform(m) = self { Ok(m) } else { anyhow::bail!("expected transform") }
+    }
 }
@@ -0,0 +1,746 @@
+//! Scriptable diagnostic test sequences for controller bring-up.
+//!
+//! A script is plain text with one step per line:
+//!
+//! ```text
+//! # comments start with '#'
+//! send status_on     # POST to /queue, remember the response body
+//! get /pins          # GET an endpoint, remember the response body
+//! wait 500           # pause for 500 ms
+//! expect "D13":1     # the last response must contain this text
+//! expect \#3         # '#' starts a comment only after a space; `\#` is a literal '#'
+//! ```
+//!
+//! The [`ScriptRunner`] is ticked once per frame and never blocks the UI;
+//! every finished step is reported back as a console line with PASS / FAIL.
+
+use crate::net::{Endpoint, Feed, FeedState, Pending, Policy, spawn, spawn_with, subscribe};
+
+/// Example shown in the editor the first time the Diagnostics tab is opened.
+pub const DEFAULT_SCRIPT: &str = "\
+# blink the status LED and check the pin report
+send status_on
+wait 250
+get /pins
+send status_off
+";
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Step {
+    /// POST a command to `/queue`.
+    Send(String),
+    /// GET an arbitrary firmware endpoint.
+    Get(String),
+    /// Pause for the given number of milliseconds.
+    Wait(f64),
+    /// The most recent response body must contain this text.
+    Expect(String),
+}
+
+impl std::fmt::Display for Step {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Step::Send(cmd) => write!(f, "send {cmd}"),
+            Step::Get(path) => write!(f, "get {path}"),
+            Step::Wait(ms) => write!(f, "wait {ms} ms"),
+            Step::Expect(text) => write!(f, "expect {text:?}"),
+        }
+    }
+}
+
+/// Parse a script into steps. Errors name the offending (1-based) line.
+pub fn parse_script(src: &str) -> Result<Vec<Step>, String> {
+    let mut steps = Vec::new();
+    for (n, raw) in src.lines().enumerate() {
+        let line = strip_script_comment(raw);
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let (word, arg) = match line.split_once(char::is_whitespace) {
+            Some((w, a)) => (w, a.trim()),
+            None => (line, ""),
+        };
+        let step = match word.to_ascii_lowercase().as_str() {
+            "send" if !arg.is_empty() => Step::Send(arg.to_owned()),
+            "get" if !arg.is_empty() => Step::Get(arg.to_owned()),
+            "wait" => Step::Wait(
+                arg.parse::<f64>()
+                    .ok()
+                    .filter(|ms| ms.is_finite() && *ms >= 0.0)
+                    .ok_or_else(|| format!("line {}: `wait` needs a duration of 0 ms or more", n + 1))?,
+            ),
+            "expect" if !arg.is_empty() => Step::Expect(arg.to_owned()),
+            "send" | "get" | "expect" => {
+                return Err(format!("line {}: `{word}` needs an argument", n + 1));
+            }
+            _ => return Err(format!("line {}: unknown step `{word}`", n + 1)),
+        };
+        steps.push(step);
+    }
+    Ok(steps)
+}
+
+/// `line` up to its comment: a `#` at the start or after whitespace. `\#`
+/// stands for a literal `#` anywhere.
+fn strip_script_comment(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.peek() == Some(&'#') => {
+                out.push('#');
+                chars.next();
+            }
+            '#' if out.chars().last().is_none_or(char::is_whitespace) => break,
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Executes a parsed script one step at a time, driven by [`ScriptRunner::tick`].
+#[derive(Default)]
+pub struct ScriptRunner {
+    steps: Vec<Step>,
+    index: usize,
+    running: bool,
+    waiting_until: Option<f64>,
+    in_flight: Option<Pending>,
+    last_response: String,
+    passed: usize,
+    failed: usize,
+}
+
+impl ScriptRunner {
+    pub fn is_running(&self) -> bool {
+        self.running
+    }
+
+    /// Reset all counters and begin executing `steps`.
+    pub fn start(&mut self, steps: Vec<Step>) {
+        *self = Self {
+            steps,
+            running: true,
+            ..Self::default()
+        };
+    }
+
+    /// Abort the script; an in-flight request is left to finish unobserved.
+    pub fn stop(&mut self) {
+        self.running = false;
+        self.in_flight = None;
+        self.waiting_until = None;
+    }
+
+    /// Advance as far as possible without blocking and return console lines
+    /// describing every step that finished during this call.
+    pub fn tick(&mut self, now_ms: f64) -> Vec<String> {
+        let mut out = Vec::new();
+        while self.running {
+            // 1) waiting for a firmware response?
+            if let Some(slot) = &self.in_flight {
+                let Some(result) = slot.lock().unwrap().take() else {
+                    break;
+                };
+                self.in_flight = None;
+                match result {
+                    Ok(body) => {
+                        let detail = format!("→ {}", body.trim());
+                        self.last_response = body;
+                        self.record(true, &detail, &mut out);
+                    }
+                    Err(e) => {
+                        self.last_response.clear();
+                        self.record(false, &e, &mut out);
+                    }
+                }
+                continue;
+            }
+
+            // 2) sleeping?
+            if let Some(until) = self.waiting_until {
+                if now_ms < until {
+                    break;
+                }
+                self.waiting_until = None;
+                self.record(true, "", &mut out);
+                continue;
+            }
+
+            // 3) start the next step
+            let Some(step) = self.steps.get(self.index).cloned() else {
+                out.push(format!(
+                    "script finished: {} passed, {} failed",
+                    self.passed, self.failed
+                ));
+                self.running = false;
+                break;
+            };
+            match step {
+                Step::Send(cmd) => {
+                    self.in_flight = Some(spawn(Endpoint::Queue(cmd)));
+                }
+                Step::Get(path) => {
+                    self.in_flight = Some(spawn(Endpoint::Get(path)));
+                }
+                Step::Wait(ms) => self.waiting_until = Some(now_ms + ms),
+                Step::Expect(text) => {
+                    let ok = self.last_response.contains(&text);
+                    let detail = if ok {
+                        String::new()
+                    } else {
+                        format!("got {:?}", self.last_response.trim())
+                    };
+                    self.record(ok, &detail, &mut out);
+                }
+            }
+        }
+        out
+    }
+
+    /// Log the outcome of the current step and move on to the next one.
+    fn record(&mut self, ok: bool, detail: &str, out: &mut Vec<String>) {
+        if ok {
+            self.passed += 1;
+        } else {
+            self.failed += 1;
+        }
+        out.push(format!(
+            "[{}/{}] {} … {} {}",
+            self.index + 1,
+            self.steps.len(),
+            self.steps[self.index],
+            if ok { "PASS" } else { "FAIL" },
+            detail
+        ));
+        self.index += 1;
+    }
+}
+
+// ---------- bus scan --------------------------------------------------------------------------------
+
+/// Devices found by the firmware's `bus_scan` command.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BusScan {
+    /// 7-bit I²C addresses that ACKed.
+    pub i2c: Vec<u8>,
+    /// Free-form SPI device descriptions (chip select + ID as reported).
+    pub spi: Vec<String>,
+}
+
+/// Parse a scan reply. Accepted shapes:
+///
+/// * `{"i2c": [60, "0x76"], "spi": ["cs0: W25Q32"]}`
+/// * `[60, 118]` (I²C only)
+/// * plain text such as `0x3c 0x76` (I²C only)
+pub fn parse_bus_scan(body: &str) -> Result<BusScan, String> {
+    fn addr(v: &serde_json::Value) -> Option<u8> {
+        match v {
+            serde_json::Value::Number(n) => n.as_u64().and_then(|u| u8::try_from(u).ok()),
+            serde_json::Value::String(s) => parse_addr(s),
+            _ => None,
+        }
+    }
+
+    let mut scan = BusScan::default();
+    match serde_json::from_str::<serde_json::Value>(body) {
+        Ok(serde_json::Value::Object(map)) => {
+            if let Some(list) = map.get("i2c").and_then(|v| v.as_array()) {
+                scan.i2c = list.iter().filter_map(addr).collect();
+            }
+            if let Some(list) = map.get("spi").and_then(|v| v.as_array()) {
+                scan.spi = list
+                    .iter()
+                    .map(|v| v.as_str().map_or_else(|| v.to_string(), str::to_owned))
+                    .collect();
+            }
+        }
+        Ok(serde_json::Value::Array(list)) => scan.i2c = list.iter().filter_map(addr).collect(),
+        _ => {
+            for tok in body.split(|c: char| c.is_whitespace() || c == ',') {
+                if tok.is_empty() {
+                    continue;
+                }
+                scan.i2c.push(parse_addr(tok).ok_or_else(|| format!("bad address `{tok}`"))?);
+            }
+        }
+    }
+    scan.i2c.sort_unstable();
+    scan.i2c.dedup();
+    Ok(scan)
+}
+
+fn parse_addr(s: &str) -> Option<u8> {
+    let s = s.trim();
+    match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => u8::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok(),
+    }
+}
+
+/// Common parts living at a given 7-bit I²C address.
+pub fn known_i2c_device(addr: u8) -> Option<&'static str> {
+    Some(match addr {
+        0x1E => "HMC5883L magnetometer",
+        0x20..=0x26 => "MCP23017 / PCF8574 I/O expander",
+        0x27 => "PCF8574 LCD backpack",
+        0x29 => "VL53L0X ToF sensor",
+        0x36 => "AS5600 magnetic encoder",
+        0x3C | 0x3D => "SSD1306 OLED",
+        0x40 => "INA219 / PCA9685",
+        0x44 | 0x45 => "SHT3x humidity sensor",
+        0x48..=0x4B => "ADS1115 ADC / TMP102",
+        0x50..=0x57 => "AT24Cxx EEPROM",
+        0x5A => "MLX90614 IR thermometer",
+        0x68 => "MPU-6050 IMU / DS3231 RTC",
+        0x70 => "TCA9548A I²C mux",
+        0x76 | 0x77 => "BMP280 / BME280",
+        _ => return None,
+    })
+}
+
+impl BusScan {
+    /// Console lines describing the scan result.
+    pub fn report(&self) -> Vec<String> {
+        let mut out = vec![format!(
+            "bus scan: {} I²C device(s), {} SPI device(s)",
+            self.i2c.len(),
+            self.spi.len()
+        )];
+        for a in &self.i2c {
+            out.push(format!(
+                "  i2c 0x{a:02X}  {}",
+                known_i2c_device(*a).unwrap_or("unknown device")
+            ));
+        }
+        for d in &self.spi {
+            out.push(format!("  spi {d}"));
+        }
+        out
+    }
+}
+
+// ---------- trigger capture -------------------------------------------------------------------------
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TriggerEdge {
+    Rising,
+    Falling,
+    Either,
+}
+
+impl std::fmt::Display for TriggerEdge {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            TriggerEdge::Rising => "rising",
+            TriggerEdge::Falling => "falling",
+            TriggerEdge::Either => "either",
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CaptureState {
+    Idle,
+    /// Waiting for `source` to cross `threshold`.
+    Armed,
+    /// Triggered; still recording post-trigger samples.
+    Triggered,
+    /// Capture complete, plot shows the frozen window.
+    Frozen,
+}
+
+/// Oscilloscope-style single-shot capture over the polled pin samples.
+pub struct Capture {
+    /// Series the trigger watches (pin name).
+    pub source: String,
+    pub threshold: f64,
+    pub edge: TriggerEdge,
+    pub pre_samples: usize,
+    pub post_samples: usize,
+    state: CaptureState,
+    history: std::collections::VecDeque<(f64, std::collections::HashMap<String, f64>)>,
+    remaining: usize,
+    trigger_t: f64,
+    frozen: std::collections::HashMap<String, Vec<[f64; 2]>>,
+}
+
+impl Default for Capture {
+    fn default() -> Self {
+        Self {
+            source: "D0".to_owned(),
+            threshold: 0.5,
+            edge: TriggerEdge::Rising,
+            pre_samples: 20,
+            post_samples: 20,
+            state: CaptureState::Idle,
+            history: std::collections::VecDeque::new(),
+            remaining: 0,
+            trigger_t: 0.0,
+            frozen: std::collections::HashMap::new(),
+        }
+    }
+}
+
+impl Capture {
+    pub fn state(&self) -> CaptureState {
+        self.state
+    }
+
+    pub fn arm(&mut self) {
+        self.history.clear();
+        self.frozen.clear();
+        self.state = CaptureState::Armed;
+    }
+
+    /// Drop the frozen window and return to live display.
+    pub fn release(&mut self) {
+        self.history.clear();
+        self.frozen.clear();
+        self.state = CaptureState::Idle;
+    }
+
+    /// The captured window (per-series points) and trigger time once frozen.
+    pub fn frozen(&self) -> Option<(&std::collections::HashMap<String, Vec<[f64; 2]>>, f64)> {
+        (self.state == CaptureState::Frozen).then_some((&self.frozen, self.trigger_t))
+    }
+
+    /// Feed one sample (`t` in seconds). Returns `true` on the sample that fires the trigger.
+    pub fn push(&mut self, t: f64, sample: &std::collections::HashMap<String, f64>) -> bool {
+        let mut fired = false;
+        match self.state {
+            CaptureState::Idle | CaptureState::Frozen => return false,
+            CaptureState::Armed => {
+                let prev = self
+                    .history
+                    .back()
+                    .and_then(|(_, s)| s.get(&self.source))
+                    .copied();
+                let cur = sample.get(&self.source).copied();
+                self.history.push_back((t, sample.clone()));
+                while self.history.len() > self.pre_samples + 1 {
+                    self.history.pop_front();
+                }
+                if let (Some(p), Some(c)) = (prev, cur) {
+                    if self.crossed(p, c) {
+                        self.state = CaptureState::Triggered;
+                        self.trigger_t = t;
+                        self.remaining = self.post_samples;
+                        fired = true;
+                    }
+                }
+            }
+            CaptureState::Triggered => {
+                self.history.push_back((t, sample.clone()));
+                self.remaining = self.remaining.saturating_sub(1);
+            }
+        }
+        if self.state == CaptureState::Triggered && self.remaining == 0 {
+            self.freeze();
+        }
+        fired
+    }
+
+    fn crossed(&self, prev: f64, cur: f64) -> bool {
+        let rising = prev < self.threshold && cur >= self.threshold;
+        let falling = prev > self.threshold && cur <= self.threshold;
+        match self.edge {
+            TriggerEdge::Rising => rising,
+            TriggerEdge::Falling => falling,
+            TriggerEdge::Either => rising || falling,
+        }
+    }
+
+    fn freeze(&mut self) {
+        self.frozen.clear();
+        for (t, sample) in &self.history {
+            for (name, v) in sample {
+                self.frozen.entry(name.clone()).or_default().push([*t, *v]);
+            }
+        }
+        for series in self.frozen.values_mut() {
+            series.sort_by(|a, b| a[0].total_cmp(&b[0]));
+        }
+        self.state = CaptureState::Frozen;
+    }
+}
+
+// ---------- connection health -----------------------------------------------------------------------
+
+/// Number of pings kept for statistics and the RTT plot.
+const HEALTH_WINDOW: usize = 120;
+
+/// Periodically pings a firmware endpoint and tracks round-trip time and loss.
+pub struct HealthMonitor {
+    pub enabled: bool,
+    /// Endpoint that is fetched with GET; any successful reply counts.
+    pub path: String,
+    pub interval_ms: f64,
+    /// A ping without reply after this long counts as lost.
+    pub timeout_ms: f64,
+    pub warn_latency_ms: f64,
+    pub warn_loss_pct: f64,
+    in_flight: Option<(f64, Pending)>,
+    last_ping: f64,
+    /// `(time [s], round trip [ms])`; `None` = lost.
+    samples: std::collections::VecDeque<(f64, Option<f64>)>,
+    degraded: bool,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct HealthStats {
+    pub sent: usize,
+    pub mean_ms: f64,
+    pub max_ms: f64,
+    pub loss_pct: f64,
+}
+
+impl Default for HealthMonitor {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            path: "/queue".to_owned(),
+            interval_ms: 1000.0,
+            timeout_ms: 2000.0,
+            warn_latency_ms: 250.0,
+            warn_loss_pct: 5.0,
+            in_flight: None,
+            last_ping: f64::NEG_INFINITY,
+            samples: std::collections::VecDeque::new(),
+            degraded: false,
+        }
+    }
+}
+
+impl HealthMonitor {
+    pub fn is_degraded(&self) -> bool {
+        self.degraded
+    }
+
+    pub fn clear(&mut self) {
+        self.samples.clear();
+        self.degraded = false;
+    }
+
+    /// Poll the outstanding ping and start a new one when due. Returns a
+    /// console line whenever the link changes between healthy and degraded.
+    pub fn tick(&mut self, now_ms: f64) -> Option<String> {
+        if let Some((sent_at, slot)) = &self.in_flight {
+            let sent_at = *sent_at;
+            let reply = slot.lock().unwrap().take();
+            if let Some(result) = reply {
+                let rtt = now_ms - sent_at;
+                self.push(sent_at, (result.is_ok() && rtt <= self.timeout_ms).then_some(rtt));
+                self.in_flight = None;
+            } else if now_ms - sent_at > self.timeout_ms {
+                self.push(sent_at, None);
+                self.in_flight = None;
+            }
+        }
+
+        if self.enabled && self.in_flight.is_none() && now_ms - self.last_ping >= self.interval_ms {
+            self.last_ping = now_ms;
+            // one attempt: a retry would hide the loss we are measuring
+            let policy = Policy {
+                timeout_ms: self.timeout_ms as i32,
+                retries: 0,
+                ..Policy::default()
+            };
+            self.in_flight = Some((now_ms, spawn_with(Endpoint::Get(self.path.clone()), policy)));
+        }
+
+        let s = self.stats();
+        let degraded =
+            s.sent >= 5 && (s.mean_ms > self.warn_latency_ms || s.loss_pct > self.warn_loss_pct);
+        if degraded == self.degraded {
+            return None;
+        }
+        self.degraded = degraded;
+        Some(if degraded {
+            format!(
+                "WARNING: link degraded – mean RTT {:.0} ms, loss {:.1} %",
+                s.mean_ms, s.loss_pct
+            )
+        } else {
+            format!("link recovered – mean RTT {:.0} ms", s.mean_ms)
+        })
+    }
+
+    fn push(&mut self, sent_at_ms: f64, rtt: Option<f64>) {
+        self.samples.push_back((sent_at_ms / 1000.0, rtt));
+        while self.samples.len() > HEALTH_WINDOW {
+            self.samples.pop_front();
+        }
+    }
+
+    pub fn stats(&self) -> HealthStats {
+        let ok: Vec<f64> = self.samples.iter().filter_map(|(_, r)| *r).collect();
+        let sent = self.samples.len();
+        if sent == 0 {
+            return HealthStats::default();
+        }
+        HealthStats {
+            sent,
+            mean_ms: if ok.is_empty() { 0.0 } else { ok.iter().sum::<f64>() / ok.len() as f64 },
+            max_ms: ok.iter().copied().fold(0.0, f64::max),
+            loss_pct: 100.0 * (sent - ok.len()) as f64 / sent as f64,
+        }
+    }
+
+    /// Successful round trips as plot points `[t s, rtt ms]`.
+    pub fn rtt_points(&self) -> Vec<[f64; 2]> {
+        self.samples
+            .iter()
+            .filter_map(|(t, r)| r.map(|ms| [*t, ms]))
+            .collect()
+    }
+
+    /// Times of lost pings, for marking on the plot.
+    pub fn lost_points(&self) -> Vec<[f64; 2]> {
+        self.samples
+            .iter()
+            .filter(|(_, r)| r.is_none())
+            .map(|(t, _)| [*t, self.timeout_ms])
+            .collect()
+    }
+}
+
+// ---------- telemetry stream ------------------------------------------------------------------------
+
+/// Samples the firmware pushes over a WebSocket, one JSON object per frame:
+///
+/// ```text
+/// {"t": 12.5, "spindle_rpm": 11800, "temp": 41.2}
+/// ```
+///
+/// `t` is the firmware's clock in seconds (optional); every other numeric
+/// field is a series. Timestamps are moved onto the pin plot's clock,
+/// keeping the firmware's spacing between samples; each new connection
+/// starts over, as the firmware may have restarted its clock.
+pub struct Telemetry {
+    pub enabled: bool,
+    /// WebSocket path on the controller.
+    pub path: String,
+    /// Series seen so far, in order of appearance.
+    pub series: Vec<String>,
+    feed: Option<Feed>,
+    state: Option<FeedState>,
+    /// Plot time minus firmware time, fixed by the first timestamped frame
+    /// of the connection it belongs to.
+    offset: Option<f64>,
+    connection: Option<u32>,
+}
+
+impl Default for Telemetry {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            path: "/telemetry".to_owned(),
+            series: Vec::new(),
+            feed: None,
+            state: None,
+            offset: None,
+            connection: None,
+        }
+    }
+}
+
+/// One decoded telemetry frame: time (s) and the values of its series.
+pub type TelemetrySample = (f64, Vec<(String, f64)>);
+
+impl Telemetry {
+    /// State of the connection, when enabled.
+    pub fn state(&self) -> Option<FeedState> {
+        self.feed.as_ref().map(Feed::state)
+    }
+
+    /// Open or drop the stream as `enabled` says and decode what arrived,
+    /// `now_s` being the plot clock. The console line reports connection
+    /// changes.
+    pub fn tick(&mut self, now_s: f64) -> (Vec<TelemetrySample>, Option<String>) {
+        match (self.enabled, &self.feed) {
+            (true, None) => {
+                self.feed = Some(subscribe(&self.path));
+                self.offset = None;
+                self.connection = None;
+            }
+            (false, Some(_)) => self.feed = None,
+            _ => {}
+        }
+        let Some(feed) = &self.feed else {
+            self.state = None;
+            return (Vec::new(), None);
+        };
+        let frames = feed.take();
+        let state = feed.state();
+        let line = (self.state != Some(state)).then(|| format!("telemetry {}: {state}", self.path));
+        self.state = Some(state);
+        let samples = frames
+            .iter()
+            .filter_map(|(connection, f)| {
+                if self.connection != Some(*connection) {
+                    self.connection = Some(*connection);
+                    self.offset = None;
+                }
+                self.decode(f, now_s)
+            })
+            .collect();
+        (samples, line)
+    }
+
+    fn decode(&mut self, frame: &str, now_s: f64) -> Option<TelemetrySample> {
+        let serde_json::Value::Object(fields) = serde_json::from_str(frame).ok()? else {
+            return None;
+        };
+        let t = match fields.get("t").and_then(serde_json::Value::as_f64) {
+            Some(t) => t + *self.offset.get_or_insert(now_s - t),
+            None => now_s,
+        };
+        let values: Vec<(String, f64)> =
+            fields.iter().filter(|(k, _)| k.as_str() != "t").filter_map(|(k, v)| Some((k.clone(), v.as_f64()?))).collect();
+        for (name, _) in &values {
+            if !self.series.contains(name) {
+                self.series.push(name.clone());
+            }
+        }
+        Some((t, values))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strip_script_comment_needs_a_space_before_the_hash() {
+        assert_eq!(strip_script_comment("# whole line"), "");
+        assert_eq!(strip_script_comment("send M115 # firmware info"), "send M115 ");
+        assert_eq!(strip_script_comment("expect tool#3"), "expect tool#3");
+        assert_eq!(strip_script_comment(r"expect \#3 # escaped"), "expect #3 ");
+        assert_eq!(strip_script_comment(r"expect a\#b"), "expect a#b");
+    }
+
+    #[test]
+    fn parse_script_reads_steps_and_comments() {
+        let script = "# bring-up\nsend status_on\nget /pins   # pin states\nwait 250\nexpect \"D13\":1\nexpect \\#3\nexpect T#2";
+        assert_eq!(
+            parse_script(script),
+            Ok(vec![
+                Step::Send("status_on".to_owned()),
+                Step::Get("/pins".to_owned()),
+                Step::Wait(250.0),
+                Step::Expect("\"D13\":1".to_owned()),
+                Step::Expect("#3".to_owned()),
+                Step::Expect("T#2".to_owned()),
+            ])
+        );
+    }
+
+    #[test]
+    fn parse_script_rejects_bad_steps() {
+        for bad in ["wait -5", "wait NaN", "wait inf", "wait", "expect #3", "jump 3"] {
+            assert!(parse_script(&format!("send M115\n{bad}")).is_err_and(|e| e.starts_with("line 2:")), "{bad}");
+        }
+    }
+}
@@ -0,0 +1,197 @@
+//! Finding controllers on the local network.
+//!
+//! Candidates come from mDNS (`_http._tcp` and `_alumina._tcp` services) on
+//! the desktop, and from the controller that served the page (its own
+//! origin plus the peers it lists at `/discover`) in the browser, which
+//! cannot browse mDNS itself. Every candidate is confirmed by asking for
+//! `/board`; only controllers that answer are offered in the picker.
+
+use std::sync::{Arc, Mutex};
+
+use crate::{
+    machine::Connection,
+    net::{self, Endpoint, Policy},
+};
+
+/// A controller that answered the `/board` probe.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Device {
+    pub name: String,
+    /// Base URL; empty for the page origin.
+    pub url: String,
+}
+
+impl Device {
+    fn label(&self) -> String {
+        if self.url.is_empty() { format!("{} (this page)", self.name) } else { format!("{} ({})", self.name, self.url) }
+    }
+}
+
+#[derive(Default)]
+struct Scan {
+    devices: Vec<Device>,
+    /// Probes (and the mDNS browse) still running.
+    pending: usize,
+}
+
+#[derive(Default)]
+pub(crate) struct Discovery {
+    scan: Arc<Mutex<Scan>>,
+    /// Whether a controller was picked this session (by hand or automatically).
+    chosen: bool,
+}
+
+/// Ask `url` for its board description; `Some` if it is a controller.
+async fn probe(url: String) -> Option<Device> {
+    let policy = Policy { timeout_ms: 1500, retries: 0, ..Policy::default() };
+    let body = net::fetch_from(&url, &Endpoint::Get("/board".into()), policy).await.ok()?;
+    let board: serde_json::Value = serde_json::from_str(&body).ok()?;
+    let name = board.get("name")?.as_str()?.to_owned();
+    Some(Device { name, url })
+}
+
+fn spawn_probe(scan: &Arc<Mutex<Scan>>, url: String) {
+    {
+        let mut s = scan.lock().unwrap();
+        if s.devices.iter().any(|d| d.url == url) {
+            return;
+        }
+        s.pending += 1;
+    }
+    let scan = Arc::clone(scan);
+    crate::execute(async move {
+        let found = probe(url).await;
+        let mut s = scan.lock().unwrap();
+        s.pending -= 1;
+        if let Some(device) = found.filter(|d| !s.devices.iter().any(|e| e.url == d.url)) {
+            log::info!("[discovery] found {}", device.label());
+            s.devices.push(device);
+        }
+    });
+}
+
+/// Base URLs of HTTP services announced over mDNS within a few seconds.
+#[cfg(not(target_arch = "wasm32"))]
+fn browse_mdns(scan: Arc<Mutex<Scan>>) {
+    use std::time::{Duration, Instant};
+
+    scan.lock().unwrap().pending += 1;
+    crate::execute(async move {
+        let browse = || -> Result<(), mdns_sd::Error> {
+            let daemon = mdns_sd::ServiceDaemon::new()?;
+            let receivers = [daemon.browse("_http._tcp.local.")?, daemon.browse("_alumina._tcp.local.")?];
+            let deadline = Instant::now() + Duration::from_secs(3);
+            while let Some(left) = deadline.checked_duration_since(Instant::now()) {
+                for rx in &receivers {
+                    let Ok(event) = rx.recv_timeout(left.min(Duration::from_millis(100))) else { continue };
+                    if let mdns_sd::ServiceEvent::ServiceResolved(info) = event {
+                        // link-local IPv6 needs a scope id; IPv4 is what ESP boards announce
+                        for addr in info.get_addresses().iter().filter(|a| a.is_ipv4()) {
+                            spawn_probe(&scan, format!("http://{addr}:{}", info.get_port()));
+                        }
+                    }
+                }
+            }
+            daemon.shutdown().ok();
+            Ok(())
+        };
+        if let Err(e) = browse() {
+            log::warn!("[discovery] mDNS browse failed: {e}");
+        }
+        scan.lock().unwrap().pending -= 1;
+    });
+}
+
+/// Peers listed by the controller that served the page: a JSON array of
+/// base URLs, or of objects with a `url` field.
+#[cfg(target_arch = "wasm32")]
+fn ask_peers(scan: Arc<Mutex<Scan>>) {
+    scan.lock().unwrap().pending += 1;
+    crate::execute(async move {
+        let policy = Policy { timeout_ms: 2000, retries: 0, ..Policy::default() };
+        if let Ok(body) = net::fetch_from("", &Endpoint::Get("/discover".into()), policy).await {
+            let peers: Vec<serde_json::Value> = serde_json::from_str(&body).unwrap_or_default();
+            for peer in peers {
+                let url = peer.as_str().or_else(|| peer.get("url").and_then(|u| u.as_str()));
+                if let Some(url) = url {
+                    spawn_probe(&scan, url.trim_end_matches('/').to_owned());
+                }
+            }
+        }
+        scan.lock().unwrap().pending -= 1;
+    });
+}
+
+impl Discovery {
+    /// Start a scan; results accumulate while the picker is shown.
+    pub(crate) fn start(&self) {
+        spawn_probe(&self.scan, net::default_url());
+        #[cfg(not(target_arch = "wasm32"))]
+        browse_mdns(Arc::clone(&self.scan));
+        #[cfg(target_arch = "wasm32")]
+        {
+            spawn_probe(&self.scan, "http://alumina.local".to_owned());
+            ask_peers(Arc::clone(&self.scan));
+        }
+    }
+
+    fn select(&mut self, connection: &mut Connection, url: &str) {
+        if url == net::default_url() {
+            connection.host.clear();
+            connection.port = None;
+        } else {
+            connection.set_url(url);
+        }
+        self.chosen = true;
+    }
+
+    /// Connection picker: the discovered controllers and a rescan button.
+    /// Returns whether `connection` was changed.
+    pub(crate) fn ui(&mut self, ui: &mut egui::Ui, connection: &mut Connection) -> bool {
+        let (devices, scanning) = {
+            let s = self.scan.lock().unwrap();
+            (s.devices.clone(), s.pending > 0)
+        };
+        let before = connection.clone();
+        // no host configured and a single controller found: just use it
+        if !self.chosen && !scanning && connection.host.is_empty() {
+            if let [only] = devices.as_slice() {
+                self.select(connection, &only.url);
+            }
+        }
+
+        let current = net::FirmwareClient::current().host_url();
+        let mut pick = None;
+        ui.horizontal(|ui| {
+            let label = ui.label("Found:");
+            let shown = devices
+                .iter()
+                .find(|d| d.url == current)
+                .map_or_else(|| "(other)".to_owned(), Device::label);
+            egui::ComboBox::from_id_salt("controller_select")
+                .selected_text(shown)
+                .show_ui(ui, |ui| {
+                    for d in &devices {
+                        if ui.selectable_label(d.url == current, d.label()).clicked() {
+                            pick = Some(d.url.clone());
+                        }
+                    }
+                    if devices.is_empty() {
+                        ui.weak(if scanning { "Searching…" } else { "No controllers found" });
+                    }
+                })
+                .response
+                .labelled_by(label.id);
+            if scanning {
+                ui.spinner();
+                ui.ctx().request_repaint_after(std::time::Duration::from_millis(200));
+            } else if ui.small_button("⟳").on_hover_text("Search the network again").clicked() {
+                self.start();
+            }
+        });
+        if let Some(url) = pick {
+            self.select(connection, &url);
+        }
+        *connection != before
+    }
+}
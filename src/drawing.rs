@@ -0,0 +1,412 @@
+//! 2-D drawings of a sketch or slice for other CAD and laser tools, as DXF
+//! or SVG: the outlines and the dimensions the user placed on them, each on
+//! its own layer. The print layout tiles the outlines at 1:1 over as many
+//! PDF pages as they need, for paper templates.
+//!
+//! Dimensions belong to the design node whose sketch they measure and are
+//! saved with the graph. They are stored as the picked geometry, not as
+//! values, so the drawn length always follows the points it was taken
+//! between.
+
+use geo::LineString;
+
+/// Layer of the sketch outlines.
+pub(crate) const PROFILE_LAYER: &str = "PROFILE";
+/// Layer of the dimension lines and labels.
+pub(crate) const DIMENSION_LAYER: &str = "DIMENSIONS";
+/// Label height and arrow length (mm), the usual drafting size.
+pub(crate) const TEXT_HEIGHT: f64 = 2.5;
+
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum Dimension {
+    /// Distance between two points, drawn `offset` mm to the left of the
+    /// direction from `from` to `to`.
+    Length { from: [f64; 2], to: [f64; 2], offset: f64 },
+    /// Diameter of a circle, drawn across it.
+    Diameter { center: [f64; 2], radius: f64 },
+}
+
+/// A dimension as lines and a centred label.
+pub(crate) struct Annotation {
+    pub(crate) lines: Vec<[[f64; 2]; 2]>,
+    pub(crate) text: String,
+    pub(crate) at: [f64; 2],
+    /// Label rotation (degrees, counter-clockwise).
+    pub(crate) angle: f64,
+}
+
+fn add(a: [f64; 2], b: [f64; 2]) -> [f64; 2] {
+    [a[0] + b[0], a[1] + b[1]]
+}
+
+fn scale(a: [f64; 2], k: f64) -> [f64; 2] {
+    [a[0] * k, a[1] * k]
+}
+
+/// Arrowhead at `tip` pointing along the unit vector `dir`.
+fn arrow(tip: [f64; 2], dir: [f64; 2]) -> [[[f64; 2]; 2]; 2] {
+    let back = scale(dir, -TEXT_HEIGHT);
+    let side = scale([-dir[1], dir[0]], TEXT_HEIGHT * 0.3);
+    [[tip, add(add(tip, back), side)], [tip, add(add(tip, back), scale(side, -1.0))]]
+}
+
+/// Keep labels readable: never upside down.
+fn upright(degrees: f64) -> f64 {
+    if degrees > 90.0 {
+        degrees - 180.0
+    } else if degrees <= -90.0 {
+        degrees + 180.0
+    } else {
+        degrees
+    }
+}
+
+impl Dimension {
+    /// The measured value (mm).
+    pub(crate) fn value(&self) -> f64 {
+        match self {
+            Self::Length { from, to, .. } => (to[0] - from[0]).hypot(to[1] - from[1]),
+            Self::Diameter { radius, .. } => 2.0 * radius,
+        }
+    }
+
+    pub(crate) fn label(&self) -> String {
+        match self {
+            Self::Length { .. } => format!("{:.2}", self.value()),
+            Self::Diameter { .. } => format!("⌀{:.2}", self.value()),
+        }
+    }
+
+    pub(crate) fn annotation(&self) -> Annotation {
+        match *self {
+            Self::Length { from, to, offset } => {
+                let len = self.value().max(1e-9);
+                let dir = [(to[0] - from[0]) / len, (to[1] - from[1]) / len];
+                let normal = [-dir[1], dir[0]];
+                let shift = scale(normal, offset);
+                let (a, b) = (add(from, shift), add(to, shift));
+                // extension lines stop short of the part and run a little past the dimension line
+                let overshoot = scale(normal, TEXT_HEIGHT * 0.5 * offset.signum());
+                let gap = scale(normal, TEXT_HEIGHT * 0.3 * offset.signum());
+                let mut lines = vec![[add(from, gap), add(a, overshoot)], [add(to, gap), add(b, overshoot)], [a, b]];
+                lines.extend(arrow(a, scale(dir, -1.0)));
+                lines.extend(arrow(b, dir));
+                let side = if offset < 0.0 { -1.0 } else { 1.0 };
+                let at = add(scale(add(a, b), 0.5), scale(normal, side * TEXT_HEIGHT * 0.8));
+                let angle = upright(dir[1].atan2(dir[0]).to_degrees());
+                Annotation { lines, text: self.label(), at, angle }
+            }
+            Self::Diameter { center, radius } => {
+                let dir = [std::f64::consts::FRAC_1_SQRT_2; 2];
+                let (a, b) = (add(center, scale(dir, -radius)), add(center, scale(dir, radius)));
+                // the leader continues past the circle to carry the label
+                let tail = add(b, scale(dir, TEXT_HEIGHT * 2.0));
+                let mut lines = vec![[a, tail]];
+                lines.extend(arrow(a, scale(dir, -1.0)));
+                lines.extend(arrow(b, dir));
+                let at = add(tail, [TEXT_HEIGHT * 2.0, 0.0]);
+                Annotation { lines, text: self.label(), at, angle: 0.0 }
+            }
+        }
+    }
+}
+
+/// Circle through the points of `ring`: their centroid and mean distance
+/// from it. Good for the round holes a diameter is put on.
+pub(crate) fn fit_circle(ring: &LineString<f64>) -> Option<([f64; 2], f64)> {
+    let pts: Vec<[f64; 2]> = ring.0.iter().map(|c| [c.x, c.y]).collect();
+    // a closed ring repeats its first point
+    let pts = if pts.len() > 1 && pts.first() == pts.last() { &pts[..pts.len() - 1] } else { &pts[..] };
+    if pts.len() < 3 {
+        return None;
+    }
+    let n = pts.len() as f64;
+    let center = [pts.iter().map(|p| p[0]).sum::<f64>() / n, pts.iter().map(|p| p[1]).sum::<f64>() / n];
+    let radius = pts.iter().map(|p| (p[0] - center[0]).hypot(p[1] - center[1])).sum::<f64>() / n;
+    Some((center, radius))
+}
+
+/// One DXF group: code and value lines.
+fn group(out: &mut String, code: u16, value: impl std::fmt::Display) {
+    out.push_str(&format!("{code:>3}\n{value}\n"));
+}
+
+/// AutoCAD R12 DXF (millimetres) with the outlines on [`PROFILE_LAYER`]
+/// and the dimensions, as plain lines and text, on [`DIMENSION_LAYER`].
+pub(crate) fn dxf(rings: &[LineString<f64>], dimensions: &[Dimension]) -> String {
+    let mut out = String::new();
+    group(&mut out, 0, "SECTION");
+    group(&mut out, 2, "HEADER");
+    group(&mut out, 9, "$INSUNITS");
+    group(&mut out, 70, 4);
+    group(&mut out, 0, "ENDSEC");
+
+    group(&mut out, 0, "SECTION");
+    group(&mut out, 2, "TABLES");
+    group(&mut out, 0, "TABLE");
+    group(&mut out, 2, "LAYER");
+    group(&mut out, 70, 2);
+    for (name, color) in [(PROFILE_LAYER, 7), (DIMENSION_LAYER, 3)] {
+        group(&mut out, 0, "LAYER");
+        group(&mut out, 2, name);
+        group(&mut out, 70, 0);
+        group(&mut out, 62, color);
+        group(&mut out, 6, "CONTINUOUS");
+    }
+    group(&mut out, 0, "ENDTAB");
+    group(&mut out, 0, "ENDSEC");
+
+    group(&mut out, 0, "SECTION");
+    group(&mut out, 2, "ENTITIES");
+    for ring in rings {
+        let closed = ring.is_closed();
+        let pts = if closed && ring.0.len() > 1 { &ring.0[..ring.0.len() - 1] } else { &ring.0[..] };
+        group(&mut out, 0, "POLYLINE");
+        group(&mut out, 8, PROFILE_LAYER);
+        group(&mut out, 66, 1);
+        group(&mut out, 70, u8::from(closed));
+        for c in pts {
+            group(&mut out, 0, "VERTEX");
+            group(&mut out, 8, PROFILE_LAYER);
+            group(&mut out, 10, c.x);
+            group(&mut out, 20, c.y);
+        }
+        group(&mut out, 0, "SEQEND");
+    }
+    for dim in dimensions {
+        let ann = dim.annotation();
+        for [a, b] in &ann.lines {
+            group(&mut out, 0, "LINE");
+            group(&mut out, 8, DIMENSION_LAYER);
+            group(&mut out, 10, a[0]);
+            group(&mut out, 20, a[1]);
+            group(&mut out, 11, b[0]);
+            group(&mut out, 21, b[1]);
+        }
+        group(&mut out, 0, "TEXT");
+        group(&mut out, 8, DIMENSION_LAYER);
+        group(&mut out, 10, ann.at[0]);
+        group(&mut out, 20, ann.at[1]);
+        group(&mut out, 40, TEXT_HEIGHT);
+        // %%c is DXF's diameter sign
+        group(&mut out, 1, ann.text.replace('⌀', "%%c"));
+        group(&mut out, 50, ann.angle);
+        group(&mut out, 72, 1);
+        group(&mut out, 11, ann.at[0]);
+        group(&mut out, 21, ann.at[1]);
+        group(&mut out, 73, 2);
+    }
+    group(&mut out, 0, "ENDSEC");
+    group(&mut out, 0, "EOF");
+    out
+}
+
+/// SVG text content with the markup characters escaped.
+fn xml_text(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+/// SVG at 1:1 in millimetres, Y up as in the app, with the outlines and the
+/// dimensions as separate Inkscape layers (plain groups elsewhere).
+pub(crate) fn svg(rings: &[LineString<f64>], dimensions: &[Dimension]) -> String {
+    let annotations: Vec<Annotation> = dimensions.iter().map(Dimension::annotation).collect();
+    let points = rings.iter().flat_map(|r| r.0.iter().map(|c| [c.x, c.y])).chain(
+        annotations
+            .iter()
+            .flat_map(|a| a.lines.iter().flatten().copied().chain(std::iter::once(a.at))),
+    );
+    let (mut lo, mut hi) = ([f64::INFINITY; 2], [f64::NEG_INFINITY; 2]);
+    for p in points {
+        for i in 0..2 {
+            lo[i] = lo[i].min(p[i]);
+            hi[i] = hi[i].max(p[i]);
+        }
+    }
+    if lo[0] > hi[0] {
+        (lo, hi) = ([0.0; 2], [0.0; 2]);
+    }
+    let margin = TEXT_HEIGHT * 2.0;
+    let (w, h) = (hi[0] - lo[0] + 2.0 * margin, hi[1] - lo[1] + 2.0 * margin);
+
+    // SVG's Y runs down: every y is negated
+    let mut out = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <svg xmlns=\"http://www.w3.org/2000/svg\" xmlns:inkscape=\"http://www.inkscape.org/namespaces/inkscape\" \
+         width=\"{w:.3}mm\" height=\"{h:.3}mm\" viewBox=\"{:.3} {:.3} {w:.3} {h:.3}\">\n",
+        lo[0] - margin,
+        -hi[1] - margin
+    );
+    out += &format!(
+        "<g id=\"{PROFILE_LAYER}\" inkscape:groupmode=\"layer\" inkscape:label=\"{PROFILE_LAYER}\" \
+         fill=\"none\" stroke=\"#000000\" stroke-width=\"0.1\">\n"
+    );
+    for ring in rings {
+        let Some((first, rest)) = ring.0.split_first() else { continue };
+        let mut d = format!("M{:.4},{:.4}", first.x, -first.y);
+        for c in rest {
+            d += &format!(" L{:.4},{:.4}", c.x, -c.y);
+        }
+        if ring.is_closed() {
+            d += " Z";
+        }
+        out += &format!("<path d=\"{d}\"/>\n");
+    }
+    out += "</g>\n";
+    out += &format!(
+        "<g id=\"{DIMENSION_LAYER}\" inkscape:groupmode=\"layer\" inkscape:label=\"{DIMENSION_LAYER}\" \
+         stroke=\"#00a000\" stroke-width=\"0.1\" fill=\"#00a000\">\n"
+    );
+    for ann in &annotations {
+        let d: Vec<String> = ann.lines.iter().map(|[a, b]| format!("M{:.4},{:.4} L{:.4},{:.4}", a[0], -a[1], b[0], -b[1])).collect();
+        out += &format!("<path fill=\"none\" d=\"{}\"/>\n", d.join(" "));
+        let (x, y) = (ann.at[0], -ann.at[1]);
+        out += &format!(
+            "<text x=\"{x:.4}\" y=\"{y:.4}\" font-size=\"{TEXT_HEIGHT}\" font-family=\"sans-serif\" stroke=\"none\" \
+             text-anchor=\"middle\" dominant-baseline=\"middle\" transform=\"rotate({:.2} {x:.4} {y:.4})\">{}</text>\n",
+            -ann.angle,
+            xml_text(&ann.text)
+        );
+    }
+    out += "</g>\n</svg>\n";
+    out
+}
+
+/// Paper the print layout is tiled onto.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Paper {
+    A4,
+    Letter,
+}
+
+impl std::fmt::Display for Paper {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Paper::A4 => "A4",
+            Paper::Letter => "Letter",
+        })
+    }
+}
+
+impl Paper {
+    /// Portrait width and height (mm).
+    fn size(self) -> [f64; 2] {
+        match self {
+            Paper::A4 => [210.0, 297.0],
+            Paper::Letter => [215.9, 279.4],
+        }
+    }
+}
+
+/// Unprinted border of every page (mm); most printers cannot reach closer
+/// to the edge. Tiles meet at the inner edge of the border.
+const PRINT_MARGIN: f64 = 12.0;
+/// Length of the scale check bar on every page (mm).
+const SCALE_BAR: f64 = 50.0;
+
+/// PDF points from millimetres.
+fn pt(mm: f64) -> f64 {
+    mm * 72.0 / 25.4
+}
+
+/// Page content: this tile of the outlines, clipped to the printable area,
+/// with crop marks at its corners, a scale bar and the page's position.
+fn tile_content(rings: &[LineString<f64>], origin: [f64; 2], paper: [f64; 2], label: &str) -> String {
+    let m = PRINT_MARGIN;
+    let area = [paper[0] - 2.0 * m, paper[1] - 2.0 * m];
+    let mut c = String::new();
+    c += &format!("BT /F1 8 Tf {:.2} {:.2} Td ({label}) Tj ET\n", pt(m), pt(m * 0.4));
+
+    // scale bar in the top margin: must measure 50 mm on paper
+    let y = paper[1] - m * 0.5;
+    c += &format!("0.5 w {:.2} {y:.2} m {:.2} {y:.2} l S\n", pt(m), pt(m + SCALE_BAR), y = pt(y));
+    for x in [m, m + SCALE_BAR] {
+        c += &format!("{x:.2} {:.2} m {x:.2} {:.2} l S\n", pt(y - 1.5), pt(y + 1.5), x = pt(x));
+    }
+    c += &format!("BT /F1 7 Tf {:.2} {:.2} Td ({SCALE_BAR:.0} mm) Tj ET\n", pt(m + SCALE_BAR + 2.0), pt(y - 1.0));
+
+    // crop marks just outside each corner of the printable area
+    c += "0.3 w\n";
+    for (cx, cy) in [(m, m), (m + area[0], m), (m, m + area[1]), (m + area[0], m + area[1])] {
+        let (sx, sy) = (if cx > m { 1.0 } else { -1.0 }, if cy > m { 1.0 } else { -1.0 });
+        c += &format!("{:.2} {:.2} m {:.2} {:.2} l S\n", pt(cx + sx), pt(cy), pt(cx + sx * 6.0), pt(cy));
+        c += &format!("{:.2} {:.2} m {:.2} {:.2} l S\n", pt(cx), pt(cy + sy), pt(cx), pt(cy + sy * 6.0));
+    }
+
+    c += &format!("q {:.2} {:.2} {:.2} {:.2} re W n\n0.6 w\n", pt(m), pt(m), pt(area[0]), pt(area[1]));
+    for ring in rings {
+        let Some((first, rest)) = ring.0.split_first() else { continue };
+        let at = |p: &geo::Coord<f64>| (pt(m + p.x - origin[0]), pt(m + p.y - origin[1]));
+        let (x, y) = at(first);
+        c += &format!("{x:.2} {y:.2} m\n");
+        for p in rest {
+            let (x, y) = at(p);
+            c += &format!("{x:.2} {y:.2} l\n");
+        }
+        c += if ring.is_closed() { "h S\n" } else { "S\n" };
+    }
+    c += "Q\n";
+    c
+}
+
+/// Print layout: the outlines at 1:1, tiled row by row from the top left
+/// over portrait pages of `paper`.
+pub(crate) fn print_pdf(rings: &[LineString<f64>], paper: Paper) -> Vec<u8> {
+    let size = paper.size();
+    let area = [size[0] - 2.0 * PRINT_MARGIN, size[1] - 2.0 * PRINT_MARGIN];
+    let (mut lo, mut hi) = ([f64::INFINITY; 2], [f64::NEG_INFINITY; 2]);
+    for c in rings.iter().flat_map(|r| r.0.iter()) {
+        lo = [lo[0].min(c.x), lo[1].min(c.y)];
+        hi = [hi[0].max(c.x), hi[1].max(c.y)];
+    }
+    if lo[0] > hi[0] {
+        (lo, hi) = ([0.0; 2], [0.0; 2]);
+    }
+    let count = |extent: f64, step: f64| ((extent / step).ceil() as usize).max(1);
+    let (cols, rows) = (count(hi[0] - lo[0], area[0]), count(hi[1] - lo[1], area[1]));
+
+    let mut pages = Vec::with_capacity(cols * rows);
+    for row in 0..rows {
+        for col in 0..cols {
+            let origin = [lo[0] + col as f64 * area[0], hi[1] - (row + 1) as f64 * area[1]];
+            let label = format!(
+                "Page {} of {} - row {}, column {} - 1:1, check the bar measures {SCALE_BAR:.0} mm",
+                pages.len() + 1,
+                cols * rows,
+                row + 1,
+                col + 1
+            );
+            pages.push(tile_content(rings, origin, size, &label));
+        }
+    }
+
+    // objects: 1 catalog, 2 page tree, 3 font, then a page and its content per page
+    let kids: Vec<String> = (0..pages.len()).map(|i| format!("{} 0 R", 4 + 2 * i)).collect();
+    let mut objects = vec![
+        "<< /Type /Catalog /Pages 2 0 R >>".to_owned(),
+        format!("<< /Type /Pages /Kids [{}] /Count {} >>", kids.join(" "), pages.len()),
+        "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica >>".to_owned(),
+    ];
+    for (i, content) in pages.iter().enumerate() {
+        objects.push(format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {:.2} {:.2}] /Resources << /Font << /F1 3 0 R >> >> /Contents {} 0 R >>",
+            pt(size[0]),
+            pt(size[1]),
+            5 + 2 * i
+        ));
+        objects.push(format!("<< /Length {} >>\nstream\n{content}endstream", content.len()));
+    }
+
+    let mut out = String::from("%PDF-1.4\n");
+    let mut offsets = Vec::with_capacity(objects.len());
+    for (i, body) in objects.iter().enumerate() {
+        offsets.push(out.len());
+        out += &format!("{} 0 obj\n{body}\nendobj\n", i + 1);
+    }
+    let xref = out.len();
+    out += &format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1);
+    for offset in offsets {
+        out += &format!("{offset:010} 00000 n \n");
+    }
+    out += &format!("trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{xref}\n%%EOF\n", objects.len() + 1);
+    out.into_bytes()
+}
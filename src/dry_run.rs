@@ -0,0 +1,139 @@
+//! Dry runs: a job streamed to a virtual machine instead of the real one,
+//! to rehearse it without anything moving.
+//!
+//! The virtual machine acknowledges each line once the move it commands
+//! would have finished at its feed rate (rapids at [`RAPID_MM_MIN`]), and
+//! reports its position along the way, so the readout, the tool marker
+//! and the position trail follow the job at real speed. Soft limits are
+//! checked as for a real job. Nothing is sent to the firmware and no
+//! recovery checkpoint is kept; once the rehearsal is over the move
+//! tracking is put back to where the real machine is.
+
+use std::sync::Arc;
+
+use eframe::egui;
+
+use crate::{
+    AluminaApp,
+    control::Position,
+    machine::{MotionTracker, gcode_words},
+    net::Pending,
+};
+
+/// Rapid (`G0`) rate of the virtual machine (mm/min).
+const RAPID_MM_MIN: f64 = 3_000.0;
+/// Feed rate until a program sets one (mm/min).
+const DEFAULT_FEED_MM_MIN: f64 = 1_000.0;
+
+struct Move {
+    from: [f64; 3],
+    to: [f64; 3],
+    start_ms: f64,
+    duration_ms: f64,
+    reply: Pending,
+}
+
+pub(crate) struct VirtualMachine {
+    /// Tool position in machine coordinates.
+    at: [f64; 3],
+    /// Modal feed rate (mm/min) and motion mode.
+    feed: f64,
+    rapid: bool,
+    moving: Option<Move>,
+    /// Move tracking of the real machine, put back after the dry run.
+    real: Option<MotionTracker>,
+}
+
+impl Default for VirtualMachine {
+    fn default() -> Self {
+        Self { at: [0.0; 3], feed: DEFAULT_FEED_MM_MIN, rapid: true, moving: None, real: None }
+    }
+}
+
+impl VirtualMachine {
+    /// How long `line` keeps the machine busy going to `to` (ms).
+    fn duration_ms(&mut self, line: &str, to: [f64; 3]) -> f64 {
+        let mut dwell = None;
+        let words = gcode_words(line);
+        for &(c, v) in &words {
+            match (c, v as i32) {
+                ('G', 0) => self.rapid = true,
+                ('G', 1..=3) => self.rapid = false,
+                ('G', 4) => dwell = Some(0.0),
+                ('F', _) if v > 0.0 => self.feed = v,
+                _ => {}
+            }
+        }
+        if let Some(mut ms) = dwell {
+            // `G4 P` in milliseconds, `G4 S` in seconds
+            for &(c, v) in &words {
+                match c {
+                    'P' => ms += v,
+                    'S' => ms += v * 1000.0,
+                    _ => {}
+                }
+            }
+            return ms.max(0.0);
+        }
+        let distance = (0..3).map(|i| (to[i] - self.at[i]).powi(2)).sum::<f64>().sqrt();
+        let rate = if self.rapid { RAPID_MM_MIN } else { self.feed };
+        distance / rate * 60_000.0
+    }
+
+    /// Where the tool is at `now`, and whether the move in flight is over.
+    fn position(&self, now: f64) -> ([f64; 3], bool) {
+        let Some(m) = &self.moving else { return (self.at, false) };
+        let t = if m.duration_ms > 0.0 { ((now - m.start_ms) / m.duration_ms).clamp(0.0, 1.0) } else { 1.0 };
+        let p = [0, 1, 2].map(|i| m.from[i] + (m.to[i] - m.from[i]) * t);
+        (p, t >= 1.0)
+    }
+}
+
+impl AluminaApp {
+    /// Whether the running job goes to the virtual machine.
+    pub(crate) fn dry_running(&self) -> bool {
+        self.jobs.active.as_ref().is_some_and(|j| j.dry_run && j.is_running())
+    }
+
+    /// Start the virtual machine where the real one was last seen, also
+    /// between dry runs back to back.
+    pub(crate) fn reset_dry_run(&mut self) {
+        let real = self.dry_run.real.take().unwrap_or_else(|| self.motion.clone());
+        self.motion = real.clone();
+        self.dry_run = VirtualMachine { at: real.pos, real: Some(real), ..VirtualMachine::default() };
+    }
+
+    /// Take `line` as the virtual machine; the reply arrives once the move
+    /// is done. `self.motion` already holds the line's target.
+    pub(crate) fn dry_run_line(&mut self, line: &str) -> Pending {
+        let reply = Pending::default();
+        let to = self.motion.pos;
+        let duration_ms = self.dry_run.duration_ms(line, to);
+        let from = self.dry_run.at;
+        self.dry_run.moving = Some(Move { from, to, start_ms: crate::now_ms(), duration_ms, reply: Arc::clone(&reply) });
+        reply
+    }
+
+    /// Move the virtual tool on and acknowledge the line once it arrives.
+    pub(crate) fn tick_dry_run(&mut self, ctx: &egui::Context, now: f64) {
+        if self.dry_run.moving.is_none() {
+            if !self.dry_running() {
+                if let Some(real) = self.dry_run.real.take() {
+                    self.motion = real;
+                }
+            }
+            return;
+        }
+        let (p, arrived) = self.dry_run.position(now);
+        self.dro_pos = Some(Position { machine: [p[0], p[1], p[2], 0.0], work: None, has_a: false, exact: true });
+        self.dro_updated = now;
+        self.dro_error = None;
+        if arrived {
+            if let Some(m) = self.dry_run.moving.take() {
+                self.dry_run.at = m.to;
+                *m.reply.lock().unwrap() = Some(Ok("ok".to_owned()));
+            }
+        }
+        ctx.request_repaint_after(std::time::Duration::from_millis(16));
+    }
+}
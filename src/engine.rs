@@ -0,0 +1,466 @@
+//! UI-independent core: slicing, toolpath generation and design graph
+//! evaluation.
+//!
+//! Everything here works on plain meshes, sketches and settings structs and
+//! never touches egui, the browser or the controller, so it can be driven
+//! from a command line tool or a test just as well as from the app. The app
+//! caches the results per frame; the engine itself keeps no state.
+//!
+//! ```no_run
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use alumina_ui::engine;
+//!
+//! let mesh = engine::load_mesh(&std::fs::read("part.stl")?).ok_or("not a mesh")?;
+//! let layers = engine::plan_layers(&engine::LayerSettings::default(), [&mesh]);
+//! let mut sliced = Vec::new();
+//! for (i, layer) in layers.iter().enumerate() {
+//!     let outline = engine::slice_union([&mesh], layer.slice_z());
+//!     let model = (&mesh, engine::InfillSettings::default(), 2);
+//!     let fill = engine::layer_infill([model], engine::InfillType::Gyroid, 0.4, &layers, i);
+//!     sliced.push((layer.z, outline, fill));
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Design graphs saved from the app (`.graph` files or share links)
+//! evaluate the same way:
+//!
+//! ```no_run
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! # use alumina_ui::engine;
+//! let graph = engine::parse_graph(&std::fs::read("bracket.graph")?)?;
+//! let volume: f64 = engine::evaluate_graph(graph)?.iter().map(|mesh| engine::mesh_stats(mesh).volume).sum();
+//! # let _ = volume;
+//! # Ok(())
+//! # }
+//! ```
+
+use csgrs::{
+    mesh::{Mesh, plane::Plane},
+    sketch::Sketch,
+    traits::CSG,
+};
+use geo::{Coord, Geometry, GeometryCollection, LineString, Polygon};
+use nalgebra::Vector3;
+use serde::{Deserialize, Serialize};
+
+pub use crate::cam::{CamSettings, Entry, PocketPattern, Strategy};
+pub use crate::collision::{Collision, Fixture, FixtureKind, Hit, Holder, ToolAssembly};
+pub use crate::cutting::{CutOp, CutPlan, MergeStats};
+pub use crate::design_graph::SavedGraph;
+pub use crate::gcode::{CutLayer, DrillSettings, ExtruderSettings, LaserSettings, MillSettings, PlasmaSettings, PrintLayer};
+pub use crate::machine::Firmware;
+pub use crate::milling::{HeightMap, Operation, OperationResult, Simulation};
+pub use crate::resin::{HollowSettings, ResinRange, ResinSettings};
+pub use crate::slicer::{
+    Adhesion, AdhesionSettings, FanSettings, Infill, InfillSettings, InfillType, Layer, LayerMoves, LayerRange, LayerSettings, RetractionSettings,
+    SeamSettings, SeamStrategy, SimplifyStats, SliceOverrides, Travel,
+};
+pub use crate::support::{SupportSettings, Supports};
+pub use crate::trapped::TrappedVolume;
+
+use crate::{cam, cutting, design_graph, gcode, milling, resin, slicer, support, trapped};
+
+/* ------------------------------------------------------------------------- */
+/*  Input                                                                    */
+/* ------------------------------------------------------------------------- */
+
+/// Parse an STL (binary or ASCII) or DXF file. Plugin importers are not
+/// consulted; they are a feature of the app.
+pub fn load_mesh(bytes: &[u8]) -> Option<Mesh<()>> {
+    Mesh::<()>::from_stl(bytes, None)
+        .or_else(|_| Mesh::<()>::from_dxf(bytes, None))
+        .ok()
+}
+
+/// Lowest and highest Z over all meshes.
+pub fn z_extent<'a>(meshes: impl IntoIterator<Item = &'a Mesh<()>>) -> Option<(f32, f32)> {
+    slicer::z_extent(meshes)
+}
+
+/* ------------------------------------------------------------------------- */
+/*  Slicing                                                                  */
+/* ------------------------------------------------------------------------- */
+
+/// Layer stack covering all meshes, from their lowest to their highest point.
+pub fn plan_layers<'a, I>(settings: &LayerSettings, meshes: I) -> Vec<Layer>
+where
+    I: IntoIterator<Item = &'a Mesh<()>>,
+    I::IntoIter: Clone,
+{
+    let meshes = meshes.into_iter();
+    let Some((lo, hi)) = slicer::z_extent(meshes.clone()) else {
+        return Vec::new();
+    };
+    slicer::plan_layers(settings, meshes, lo, hi)
+}
+
+/// Outline of the union of `meshes` at height `z`, or `None` without meshes.
+pub fn slice_union<'a>(meshes: impl IntoIterator<Item = &'a Mesh<()>>, z: f32) -> Option<Sketch<()>> {
+    let mut iter = meshes.into_iter();
+    let mut combined = iter.next()?.clone();
+    for m in iter {
+        combined = combined.union(m);
+    }
+    Some(combined.slice(Plane::from_normal(Vector3::z(), z.into())))
+}
+
+/// Cavity left inside the union of `meshes` at height `z` when they are
+/// hollowed with `hollow`; `None` where it is solid, or hollowing is off.
+pub fn resin_cavity(meshes: &[&Mesh<()>], z: f32, hollow: &HollowSettings) -> Option<Sketch<()>> {
+    if !hollow.enabled {
+        return None;
+    }
+    let (first, rest) = meshes.split_first()?;
+    let combined = rest.iter().fold((*first).clone(), |acc, m| acc.union(m));
+    cavity_of(&combined, z, hollow)
+}
+
+fn cavity_of(combined: &Mesh<()>, z: f32, hollow: &HollowSettings) -> Option<Sketch<()>> {
+    let at = |z: f32| combined.slice(Plane::from_normal(Vector3::z(), z.into()));
+    // the roof and floor are checked at half and full wall thickness
+    let w = hollow.wall as f32;
+    let nearby: Vec<Sketch<()>> = [-1.0, -0.5, 0.5, 1.0].iter().map(|k| at(z + k * w)).collect();
+    resin::cavity(&at(z), &nearby, hollow.wall)
+}
+
+/// Voids in `layers` of the models (hollowed with `hollow`) whose floor is
+/// sealed, so they pull on the vat film at every peel.
+pub fn trapped_volumes(meshes: &[&Mesh<()>], layers: &[Layer], hollow: &HollowSettings) -> Vec<TrappedVolume> {
+    let Some((first, rest)) = meshes.split_first() else { return Vec::new() };
+    let combined = rest.iter().fold((*first).clone(), |acc, m| acc.union(m));
+    let voids: Vec<trapped::LayerVoids> = layers
+        .iter()
+        .map(|l| {
+            let slice = combined.slice(Plane::from_normal(Vector3::z(), l.slice_z().into()));
+            let cavity = if hollow.enabled { cavity_of(&combined, l.slice_z(), hollow) } else { None };
+            let cavity = cavity.map(|c| slicer::polygons(&c)).unwrap_or_default();
+            trapped::LayerVoids::new(*l, &slicer::polygons(&slice), &cavity)
+        })
+        .collect();
+    trapped::trapped_volumes(&voids)
+}
+
+/// Outline of one mesh at height `z`.
+pub fn slice_at(mesh: &Mesh<()>, z: f32) -> Sketch<()> {
+    mesh.slice(Plane::from_normal(Vector3::z(), z.into()))
+}
+
+/// `slice` with contour points closer than `tolerance` (mm) to a straight
+/// line removed, and how many there were before and after.
+pub fn simplify_slice(slice: &Sketch<()>, tolerance: f64) -> (Sketch<()>, SimplifyStats) {
+    slicer::simplify(slice, tolerance)
+}
+
+/// Extruder fill for layer `index` of `layers`, inside each model's
+/// perimeter shells. Each mesh is filled with its own settings and
+/// perimeter count, so per-model overrides apply to that model alone.
+pub fn layer_infill<'a>(
+    models: impl IntoIterator<Item = (&'a Mesh<()>, InfillSettings, i32)>,
+    pattern: InfillType,
+    line_width: f64,
+    layers: &[Layer],
+    index: usize,
+) -> Infill {
+    let mut fill = Infill::default();
+    let z = layers[index].slice_z();
+    for (mesh, settings, perimeters) in models {
+        let slice = |i: usize| slice_at(mesh, layers[i].slice_z());
+        let this = slicer::inside_perimeters(&slice(index), perimeters, line_width);
+        let above: Vec<Sketch<()>> = (index + 1..layers.len()).take(settings.top_layers as usize).map(&slice).collect();
+        let below: Vec<Sketch<()>> = (0..index).rev().take(settings.bottom_layers as usize).map(&slice).collect();
+        fill.extend(slicer::infill(&settings, pattern, line_width, index, z.into(), &this, &above, &below));
+    }
+    fill
+}
+
+/// Perimeter shells of every model at height `z`, each with its own count.
+pub fn layer_perimeters<'a>(models: impl IntoIterator<Item = (&'a Mesh<()>, i32)>, line_width: f64, z: f32) -> Vec<Polygon<f64>> {
+    models
+        .into_iter()
+        .flat_map(|(mesh, count)| slicer::perimeter_shells(&slice_at(mesh, z), count, line_width))
+        .collect()
+}
+
+/// Print order of the perimeter loops of layer `index` (from the bed
+/// origin) and the travels between them, with retractions where they cross
+/// open air.
+pub fn layer_moves(shells: &[Polygon<f64>], seam: &SeamSettings, retraction: &RetractionSettings, index: usize) -> LayerMoves {
+    slicer::plan_layer_moves(shells, seam, retraction, Coord { x: 0.0, y: 0.0 }, index)
+}
+
+/// Skirt or brim around the outer outlines of a first-layer slice.
+pub fn first_layer_adhesion(settings: &AdhesionSettings, slice: &Sketch<()>, line_width: f64) -> Adhesion {
+    let outline: Vec<LineString<f64>> = slice
+        .geometry
+        .0
+        .iter()
+        .filter_map(|g| match g {
+            Geometry::Polygon(p) => Some(p.exterior().clone()),
+            _ => None,
+        })
+        .collect();
+    slicer::adhesion(settings, &outline, line_width)
+}
+
+/// Support structures for `meshes`, standing on their lowest point.
+pub fn supports<'a, I>(settings: &SupportSettings, meshes: I, line_width: f32) -> Supports
+where
+    I: IntoIterator<Item = &'a Mesh<()>>,
+    I::IntoIter: Clone,
+{
+    let meshes = meshes.into_iter();
+    let floor = slicer::z_extent(meshes.clone()).map_or(0.0, |(lo, _)| lo);
+    support::generate(settings, meshes, floor, line_width)
+}
+
+/* ------------------------------------------------------------------------- */
+/*  2-D toolpaths (laser, plasma, drill)                                     */
+/* ------------------------------------------------------------------------- */
+
+/// Cut paths of a slice: every ring, open.
+pub fn cut_paths(slice: &Sketch<()>) -> Vec<LineString<f64>> {
+    cutting::outlines(&slice.geometry.0)
+}
+
+/// Each polygon (with its holes) of `slice` as a part of its own.
+pub fn split_parts(slice: &Sketch<()>) -> Vec<Sketch<()>> {
+    slicer::polygons(slice)
+        .into_iter()
+        .map(|p| Sketch::from_geo(GeometryCollection(vec![Geometry::Polygon(p)]), None))
+        .collect()
+}
+
+/// Slices of `meshes` at height `z`, one per part and not unioned, so the
+/// edges neighbouring parts share are still there to merge.
+pub fn slice_parts<'a>(meshes: impl IntoIterator<Item = &'a Mesh<()>>, z: f32) -> Vec<Sketch<()>> {
+    meshes.into_iter().flat_map(|m| split_parts(&slice_at(m, z))).collect()
+}
+
+/// Cut paths of `parts` with edges shared by neighbouring parts cut only once.
+pub fn merged_cut_paths(parts: &[Sketch<()>], tolerance: f64) -> (Vec<LineString<f64>>, MergeStats) {
+    let rings: Vec<LineString<f64>> = parts.iter().flat_map(cut_paths).collect();
+    cutting::merge_common_lines(&rings, tolerance)
+}
+
+/// One plunge at the centre of every hole of a slice that is round and
+/// `diameter` across, within `tolerance`.
+pub fn drill_points(slice: &Sketch<()>, diameter: f64, tolerance: f64) -> Vec<Coord<f64>> {
+    cutting::round_holes(&slicer::polygons(slice), diameter, tolerance)
+}
+
+/// Order cuts and plunges to shorten rapid travel from `home`. Also returns
+/// the rapid length of the unoptimised order, for comparison.
+pub fn plan_cuts(paths: &[LineString<f64>], drills: &[Coord<f64>], home: Coord<f64>) -> (CutPlan, f64) {
+    let before = cutting::rapid_length_in_order(paths, drills, home);
+    (cutting::order_cuts(paths, drills, home), before)
+}
+
+/* ------------------------------------------------------------------------- */
+/*  G-code                                                                   */
+/* ------------------------------------------------------------------------- */
+
+/// Cut paths of one laser or plasma layer from its `parts` (see
+/// [`slice_parts`]). Each part is grown by half the `kerf` first, so parts
+/// come out at their drawn size and holes are not cut oversize. With
+/// `common_line` the grown parts are merged where their edges meet, so
+/// parts nested one kerf apart share a single cut; without it they are
+/// unioned. `optimize` orders the cuts from `home`.
+pub fn cut_layer(parts: &[Sketch<()>], z: Option<f64>, kerf: f64, common_line: bool, optimize: bool, home: Coord<f64>) -> CutLayer {
+    let grow = |s: &Sketch<()>| if kerf > 0.0 { s.offset(kerf * 0.5) } else { s.clone() };
+    let paths = if common_line {
+        merged_cut_paths(&parts.iter().map(grow).collect::<Vec<_>>(), 0.01).0
+    } else {
+        parts.iter().cloned().reduce(|a, b| a.union(&b)).map(|all| cut_paths(&grow(&all))).unwrap_or_default()
+    };
+    let paths = if optimize {
+        cutting::order_cuts(&paths, &[], home)
+            .ops
+            .into_iter()
+            .filter_map(|op| match op {
+                CutOp::Path(p) => Some(p),
+                CutOp::Drill(_) => None,
+            })
+            .collect()
+    } else {
+        paths
+    };
+    CutLayer { z, paths }
+}
+
+/// Laser G-code for `layers` in `fw`'s dialect, returning to `home`.
+pub fn laser_gcode(layers: &[CutLayer], settings: &LaserSettings, fw: Firmware, home: Coord<f64>) -> String {
+    gcode::laser_program(layers, settings, fw, home)
+}
+
+/// Plasma G-code for `layers`: pierce cycles, optional touch-off probing
+/// before every pierce and torch height control around the cuts.
+pub fn plasma_gcode(layers: &[CutLayer], settings: &PlasmaSettings, touch_off: bool, fw: Firmware, home: Coord<f64>) -> String {
+    gcode::plasma_program(layers, settings, touch_off, fw, home)
+}
+
+/// Extruder G-code for `layers` in `fw`'s dialect: heat-up, per-layer fan
+/// speeds, retractions and relative E values for `line_width` beads.
+pub fn extruder_gcode(
+    layers: &[PrintLayer],
+    settings: &ExtruderSettings,
+    retraction: &RetractionSettings,
+    fan: &FanSettings,
+    line_width: f64,
+    fw: Firmware,
+    home: Coord<f64>,
+) -> String {
+    gcode::extruder_program(layers, settings, retraction, fan, line_width, fw, home)
+}
+
+/// Resin job commands (`run.gcode`) for `layers`: each layer's mask,
+/// exposure and lift, `lift` and `delay` (s) applying outside every range.
+pub fn resin_gcode(layers: &[Layer], settings: &ResinSettings, lift: f32, delay: f32) -> String {
+    resin::job_gcode(layers, settings, lift, delay)
+}
+
+/// Drill G-code in `fw`'s dialect: a canned cycle at every hole, in order.
+pub fn drill_gcode(holes: &[Coord<f64>], settings: &DrillSettings, fw: Firmware, home: Coord<f64>) -> String {
+    gcode::drill_program(holes, settings, fw, home)
+}
+
+/// Endmill G-code in `fw`'s dialect for tool-centre `paths` cut into
+/// material whose surface is at `top`.
+pub fn mill_gcode(paths: &[Vec<[f64; 3]>], settings: &MillSettings, top: f64, fw: Firmware, home: Coord<f64>) -> String {
+    gcode::mill_program(paths, settings, top, fw, home)
+}
+
+/* ------------------------------------------------------------------------- */
+/*  Milling                                                                  */
+/* ------------------------------------------------------------------------- */
+
+/// 2.5-D profile or pocket passes around `slice` for a tool of
+/// `tool_diameter`, stepping down from the material surface at `top` and
+/// keeping clear of `fixtures`. Every pass begins with the entry move
+/// `settings` asks for.
+pub fn contour_toolpaths(slice: &Sketch<()>, settings: &CamSettings, tool_diameter: f64, top: f64, fixtures: &[Fixture]) -> Vec<Vec<[f64; 3]>> {
+    let paths = cam::avoid_fixtures(cam::toolpaths(slice, settings, tool_diameter, top), fixtures, tool_diameter * 0.5);
+    cam::with_entries(paths, settings, top)
+}
+
+/// Machine `parts` out of `stock` with `ops` in turn. Without stock, the
+/// parts' bounding block is used. Nothing is cut below the bottom of the
+/// stock, nor under or beside `fixtures`. Returns the results of every
+/// operation and the height map of the finished part, or `None` without
+/// parts.
+pub fn mill(stock: Option<&Mesh<()>>, parts: &[&Mesh<()>], ops: &[Operation], fixtures: &[Fixture]) -> Option<(Vec<OperationResult>, HeightMap)> {
+    let smallest = ops.iter().map(|o| o.tool_diameter).fold(f64::INFINITY, f64::min);
+    let (stock_map, mut part) = height_maps(stock, parts, (smallest / 4.0).max(0.1))?;
+    for f in fixtures {
+        part.raise_fixture(f);
+    }
+    Some((milling::run_sequence(&stock_map, &part, ops), part))
+}
+
+/// Simulation of a flat endmill in its holder following `moves` (tool-tip
+/// positions, see [`program_moves`]) through `stock`, or the parts'
+/// bounding block, flagging moves that run the holder into material or
+/// anything into one of the `fixtures`. Advance it with
+/// [`Simulation::advance`].
+pub fn mill_simulation(stock: Option<&Mesh<()>>, parts: &[&Mesh<()>], moves: Vec<[f64; 3]>, tool: ToolAssembly, fixtures: Vec<Fixture>) -> Option<Simulation> {
+    let (stock_map, part) = height_maps(stock, parts, (tool.diameter / 4.0).max(0.1))?;
+    Some(Simulation::new(stock_map, part, moves, tool, fixtures))
+}
+
+/// Tool-tip positions of a G-code program's moves.
+pub fn program_moves(text: &str) -> Vec<[f64; 3]> {
+    milling::program_moves(text)
+}
+
+/// Stock and part height maps on a shared grid of about `cell` mm; the
+/// part is filled with the stock bottom where it has no surface.
+fn height_maps(stock: Option<&Mesh<()>>, parts: &[&Mesh<()>], cell: f64) -> Option<(HeightMap, HeightMap)> {
+    let (_, part_top) = slicer::z_extent(parts.iter().copied())?;
+    let all = || stock.into_iter().chain(parts.iter().copied());
+    let (mut lo, mut hi) = ([f64::INFINITY; 2], [f64::NEG_INFINITY; 2]);
+    for p in all().flat_map(|m| &m.polygons).flat_map(|p| &p.vertices) {
+        lo = [lo[0].min(p.pos.x), lo[1].min(p.pos.y)];
+        hi = [hi[0].max(p.pos.x), hi[1].max(p.pos.y)];
+    }
+    let (bottom, _) = slicer::z_extent(all()).unwrap_or((0.0, part_top));
+
+    let mut part = HeightMap::covering(lo, hi, cell, 200, bottom);
+    for m in parts {
+        part.raise_to(m);
+    }
+    let stock_map = match stock {
+        None => HeightMap::covering(lo, hi, cell, 200, part_top),
+        Some(m) => {
+            let mut map = HeightMap::covering(lo, hi, cell, 200, f32::NEG_INFINITY);
+            map.raise_to(m);
+            map
+        }
+    };
+    Some((stock_map, part))
+}
+
+/* ------------------------------------------------------------------------- */
+/*  Design graphs                                                            */
+/* ------------------------------------------------------------------------- */
+
+/// Read a design graph from a `.graph` file (JSON). Share links are read
+/// with [`SavedGraph::from_link`].
+pub fn parse_graph(bytes: &[u8]) -> anyhow::Result<SavedGraph> {
+    Ok(serde_json::from_slice(bytes)?)
+}
+
+/// The `.graph` file form of a design graph.
+pub fn graph_json(graph: &SavedGraph) -> anyhow::Result<String> {
+    Ok(serde_json::to_string_pretty(graph)?)
+}
+
+/// Meshes of every unconnected output of a design graph (as stored in a
+/// `.graph` file or share link), in node order. Plugin nodes only evaluate
+/// when their plugin is loaded.
+pub fn evaluate_graph(graph: SavedGraph) -> anyhow::Result<Vec<Mesh<()>>> {
+    let (state, missing) = graph.into_state();
+    if missing > 0 {
+        anyhow::bail!("{missing} node(s) need plugins that are not loaded");
+    }
+    design_graph::graph_roots(&state.graph)
+        .into_iter()
+        .map(|root| design_graph::evaluate(&state.graph, root))
+        .collect()
+}
+
+/// Summary of a mesh, for comparing evaluation results.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MeshStats {
+    pub polygons: usize,
+    pub vertices: usize,
+    /// Enclosed volume (mm³); only meaningful for closed meshes.
+    pub volume: f64,
+    /// Bounding box corners; zero for an empty mesh.
+    pub min: [f64; 3],
+    pub max: [f64; 3],
+}
+
+pub fn mesh_stats(mesh: &Mesh<()>) -> MeshStats {
+    let mut volume = 0.0;
+    for poly in &mesh.polygons {
+        let v = &poly.vertices;
+        // signed tetrahedra from the origin over a triangle fan
+        for i in 1..v.len().saturating_sub(1) {
+            volume += v[0].pos.coords.dot(&v[i].pos.coords.cross(&v[i + 1].pos.coords)) / 6.0;
+        }
+    }
+    let (min, max) = if mesh.polygons.is_empty() {
+        ([0.0; 3], [0.0; 3])
+    } else {
+        let bb = mesh.bounding_box();
+        ([bb.mins.x, bb.mins.y, bb.mins.z], [bb.maxs.x, bb.maxs.y, bb.maxs.z])
+    };
+    MeshStats {
+        polygons: mesh.polygons.len(),
+        vertices: mesh.polygons.iter().map(|p| p.vertices.len()).sum(),
+        volume,
+        min,
+        max,
+    }
+}
@@ -0,0 +1,67 @@
+//! Opt-in trace of design graph evaluation: every output Apply evaluated,
+//! as a tree from the roots, with its time and a short description of the
+//! value or error. Off by default, since recording describes every value.
+
+use crate::{AluminaApp, design_graph::TraceEvent};
+
+#[derive(Default)]
+pub(crate) struct EvalTrace {
+    /// Record a trace on the next Apply.
+    pub(crate) enabled: bool,
+    events: Vec<TraceEvent>,
+}
+
+impl EvalTrace {
+    pub(crate) fn set(&mut self, events: Vec<TraceEvent>) {
+        self.events = events;
+    }
+}
+
+fn line(e: &TraceEvent) -> String {
+    let timing = if e.cached { "cached".to_owned() } else { format!("{:.2} ms", e.ms) };
+    format!("{}{}.{}  {timing}  {}", "  ".repeat(e.depth), e.label, e.output, e.result)
+}
+
+impl AluminaApp {
+    /// "Evaluation trace" section of the Design sidebar.
+    pub(crate) fn eval_trace_ui(&mut self, ui: &mut egui::Ui) {
+        ui.checkbox(&mut self.eval_trace.enabled, "Record on Apply")
+            .on_hover_text("Trace every evaluated output on the next Apply; slows evaluation slightly");
+        let events = &self.eval_trace.events;
+        if events.is_empty() {
+            ui.weak(if self.eval_trace.enabled { "Apply the graph to record a trace" } else { "No trace recorded" });
+            return;
+        }
+        ui.horizontal(|ui| {
+            ui.weak(format!("{} outputs", events.len()));
+            if ui.small_button("Copy").clicked() {
+                ui.ctx().copy_text(events.iter().map(line).collect::<Vec<_>>().join("\n"));
+            }
+        });
+        let mut focus = None;
+        egui::ScrollArea::vertical().id_salt("eval_trace").max_height(260.0).show(ui, |ui| {
+            egui::Grid::new("eval_trace_grid").num_columns(3).striped(true).show(ui, |ui| {
+                for e in events {
+                    let name = format!("{}{}", "  ".repeat(e.depth), e.label);
+                    if ui.selectable_label(false, name).on_hover_text(format!("output “{}”", e.output)).clicked() {
+                        focus = Some(e.node);
+                    }
+                    if e.cached {
+                        ui.weak("cached");
+                    } else {
+                        ui.monospace(format!("{:.2} ms", e.ms));
+                    }
+                    if e.result.starts_with("error") {
+                        ui.colored_label(ui.visuals().error_fg_color, &e.result);
+                    } else {
+                        ui.weak(&e.result);
+                    }
+                    ui.end_row();
+                }
+            });
+        });
+        if let Some(node) = focus {
+            self.focus_node(node);
+        }
+    }
+}
@@ -1,8 +1,8 @@
-//! Minimal Google Fonts integration for wasm32.
+//! Minimal Google Fonts integration.
 //!
-//! - Fetch Google Fonts index (names, variants, download URLs) via the public REST API
-//! - Download font bytes on demand
-//! - Persist bytes to `localStorage` (base64) and list what’s already persisted
+//! - Fetch Google Fonts index (names, variants, download URLs) via the public REST API (wasm32)
+//! - Download font bytes on demand (wasm32)
+//! - Persist bytes to `localStorage` (base64; a file per font on the desktop) and list what’s already persisted
 //!
 //! Keep deps small: `gloo-net`, `serde`, `serde_json`, `base64`.
 //!
@@ -22,6 +22,8 @@ use gloo_net::http::Request;
 use wasm_bindgen::JsValue;
 use base64::Engine;
 
+use crate::platform::{self, Storage};
+
 #[derive(Debug, Clone, serde::Deserialize)]
 pub struct FontItem {
     pub family: String,
@@ -51,20 +53,10 @@ pub struct PersistedFont {
 
 const LS_PREFIX: &str = "ttf:";
 
-#[cfg(target_arch = "wasm32")]
-fn window() -> web_sys::Window {
-    web_sys::window().expect("no `window` in this context")
+fn storage() -> anyhow::Result<Storage> {
+    platform::storage().ok_or_else(|| anyhow::anyhow!("no persistent storage"))
 }
 
-#[cfg(target_arch = "wasm32")]
-fn storage() -> Result<web_sys::Storage, JsValue> {
-    web_sys::window()
-        .ok_or_else(|| JsValue::from_str("no window"))?
-        .local_storage()?
-        .ok_or_else(|| JsValue::from_str("no localStorage"))
-}
-
-#[cfg(target_arch = "wasm32")]
 fn storage_key(family: &str, variant: &str) -> String {
     format!("alumina.ttf:{family}:{variant}")
 }
@@ -113,42 +105,33 @@ pub async fn gf_download_bytes(url: &str) -> Result<Vec<u8>, JsValue> {
 }
 
 /// Persist bytes in `localStorage` as base64 with key `ttf:{family}:{variant}`.
-#[cfg(target_arch = "wasm32")]
-pub fn persist_ttf_localstorage(family: &str, variant: &str, bytes: &[u8]) -> Result<(), JsValue> {
+pub fn persist_ttf_localstorage(family: &str, variant: &str, bytes: &[u8]) -> anyhow::Result<()> {
     let key = storage_key(family, variant);
     let b64 = base64::engine::general_purpose::STANDARD.encode(bytes);
-    storage()?.set_item(&key, &b64).map_err(|e| e.into())
+    storage()?.set_item(&key, &b64).map_err(anyhow::Error::msg)
 }
 
 /// Load bytes back from `localStorage` for `(family, variant)`.
-#[cfg(target_arch = "wasm32")]
-pub fn load_persisted_ttf(family: &str, variant: &str) -> Result<Option<Vec<u8>>, JsValue> {
+pub fn load_persisted_ttf(family: &str, variant: &str) -> anyhow::Result<Option<Vec<u8>>> {
     let key = storage_key(family, variant);
-    let store = storage()?;
-    // get_item already returns Result<_, JsValue>
-    let Some(b64) = store.get_item(&key)? else {
+    let Some(b64) = storage()?.get_item(&key) else {
         return Ok(None);
     };
-    // base64::decode returns a Rust error — convert that to JsValue explicitly
-    let bytes = base64::decode(b64)
-        .map_err(|e| JsValue::from_str(&format!("base64 decode error: {e}")))?;
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(b64)
+        .map_err(|e| anyhow::anyhow!("base64 decode error: {e}"))?;
     Ok(Some(bytes))
 }
 
-/// Enumerate all persisted fonts (`ttf:{family}:{variant}`) with decoded sizes.
-#[cfg(target_arch = "wasm32")]
-pub fn list_persisted_ttf() -> Result<Vec<PersistedFont>, JsValue> {
-    let store = storage()?;
-    let len = store.length()?; // no map_err here
+/// Enumerate all persisted fonts (`ttf:{family}:{variant}`).
+pub fn list_persisted_ttf() -> anyhow::Result<Vec<PersistedFont>> {
     let mut out = Vec::new();
-    for i in 0..len {
-        if let Some(key) = store.key(i)? {
-            if let Some(rest) = key.strip_prefix("alumina.ttf:") {
-                let mut parts = rest.splitn(2, ':');
-                let family = parts.next().unwrap_or_default().to_string();
-                let variant = parts.next().unwrap_or_default().to_string();
-                out.push(PersistedFont { family, variant });
-            }
+    for key in storage()?.keys() {
+        if let Some(rest) = key.strip_prefix("alumina.ttf:") {
+            let mut parts = rest.splitn(2, ':');
+            let family = parts.next().unwrap_or_default().to_string();
+            let variant = parts.next().unwrap_or_default().to_string();
+            out.push(PersistedFont { family, variant });
         }
     }
     Ok(out)
@@ -166,32 +149,7 @@ pub async fn gf_fetch_index(_api_key: &str) -> Result<Vec<FontItem>, ()> {
     Ok(Vec::new())
 }
 
-#[cfg(not(target_arch = "wasm32"))]
-pub fn gf_search<'a>(index: &'a [FontItem], _query: &str) -> Vec<&'a FontItem> {
-    index.iter().collect()
-}
-
-#[cfg(not(target_arch = "wasm32"))]
-pub fn gf_find_file_url<'a>(_index: &'a [FontItem], _family: &str, _variant: &str) -> Option<&'a str> {
-    None
-}
-
 #[cfg(not(target_arch = "wasm32"))]
 pub async fn gf_download_bytes(_url: &str) -> Result<Vec<u8>, ()> {
     Ok(Vec::new())
 }
-
-#[cfg(not(target_arch = "wasm32"))]
-pub fn persist_ttf_localstorage(_family: &str, _variant: &str, _bytes: &[u8]) -> Result<(), ()> {
-    Ok(())
-}
-
-#[cfg(not(target_arch = "wasm32"))]
-pub fn load_persisted_ttf(_family: &str, _variant: &str) -> Result<Option<Vec<u8>>, ()> {
-    Ok(None)
-}
-
-#[cfg(not(target_arch = "wasm32"))]
-pub fn list_persisted_ttf() -> Result<Vec<PersistedFont>, ()> {
-    Ok(Vec::new())
-}
@@ -0,0 +1,624 @@
+//! Laser and plasma G-code: every layer's cut paths as tool-on feed moves
+//! joined by tool-off rapids, ready to be streamed as a job.
+//!
+//! The tool is switched with the firmware's own commands (`M3 S…` / `M5`
+//! on Marlin and GRBL), so power is scaled to the dialect's range. Layers
+//! are tagged `;LAYER:n` for the job's resume-by-layer.
+//!
+//! Thick sheet is lasered in several passes over the whole layer, the
+//! head lowered by the step-down before each pass after the first so the
+//! focus follows the kerf into the material, and raised back after.
+//!
+//! The plasma torch pierces every cut from above the plate: it probes for
+//! the surface first when touch-off is on, fires at pierce height, waits
+//! out the pierce delay, drops to cut height and only then enables the
+//! torch height control.
+//!
+//! Extruder jobs print every layer's perimeter loops, then its solid and
+//! sparse infill, with relative E values for a bead one line width wide and
+//! one layer high, retracting on the travels the slicer marked.
+//!
+//! Endmill jobs run the spindle throughout, plunge into every path at the
+//! plunge feed and retract to the safe height between paths.
+//!
+//! Drill jobs use a canned cycle per hole (G81, or G83 when pecking) with
+//! Z 0 at the top of the material, or the same moves written out for
+//! controllers without canned cycles.
+
+use geo::{Coord, LineString};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    machine::Firmware,
+    slicer::{FanSettings, Infill, LayerMoves, RetractionSettings},
+};
+
+/// Feeds and power of the laser while cutting.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LaserSettings {
+    /// Cutting feed rate (mm/min).
+    pub cut_feed: f64,
+    /// Rapid feed rate (mm/min), for firmware that takes F on G0.
+    pub travel_feed: f64,
+    /// Tool power while cutting (% of full power).
+    pub power_pct: f32,
+    /// Times every layer is cut, and how far the head is lowered before
+    /// each pass after the first (mm).
+    pub passes: u32,
+    pub z_step: f64,
+}
+
+impl Default for LaserSettings {
+    fn default() -> Self {
+        Self {
+            cut_feed: 600.0,
+            travel_feed: 3000.0,
+            power_pct: 80.0,
+            passes: 1,
+            z_step: 0.0,
+        }
+    }
+}
+
+/// Heights, timing and torch height control of plasma cuts.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PlasmaSettings {
+    /// Cut width (mm); paths are offset by half of it.
+    pub kerf: f64,
+    /// Cutting feed rate (mm/min).
+    pub cut_feed: f64,
+    /// Rapid feed rate (mm/min), for firmware that takes F on G0.
+    pub travel_feed: f64,
+    /// Torch height above the plate while piercing (mm).
+    pub pierce_height: f64,
+    /// Time the torch dwells at pierce height after the arc starts (ms).
+    pub pierce_delay_ms: f64,
+    /// Torch height above the plate while cutting (mm).
+    pub cut_height: f64,
+    /// Clearance height for rapids (mm).
+    pub safe_height: f64,
+    /// Touch-off: probing feed (mm/min) and the furthest it probes down
+    /// from the safe height (mm).
+    pub probe_feed: f64,
+    pub probe_depth: f64,
+    /// Travel of the floating head from touching the plate to tripping
+    /// its switch (mm).
+    pub switch_offset: f64,
+    /// Send the torch height control enable / disable codes around cuts.
+    pub thc: bool,
+    pub thc_on: String,
+    pub thc_off: String,
+}
+
+impl Default for PlasmaSettings {
+    fn default() -> Self {
+        Self {
+            kerf: 1.5,
+            cut_feed: 2000.0,
+            travel_feed: 6000.0,
+            pierce_height: 3.8,
+            pierce_delay_ms: 500.0,
+            cut_height: 1.5,
+            safe_height: 15.0,
+            probe_feed: 300.0,
+            probe_depth: 30.0,
+            switch_offset: 1.5,
+            thc: true,
+            thc_on: "M62 P0".to_owned(),
+            thc_off: "M63 P0".to_owned(),
+        }
+    }
+}
+
+/// Temperatures, filament and speeds of extruder jobs.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExtruderSettings {
+    /// Nozzle and bed temperatures (°C); zero leaves a heater alone.
+    pub nozzle_temp: f32,
+    pub bed_temp: f32,
+    pub filament_diameter: f64,
+    /// Scales every E value, to calibrate under- or over-extrusion.
+    pub flow: f64,
+    /// Print speed, and the slower speed of the first layer (mm/s).
+    pub print_speed: f64,
+    pub first_layer_speed: f64,
+    pub travel_speed: f64,
+}
+
+impl Default for ExtruderSettings {
+    fn default() -> Self {
+        Self {
+            nozzle_temp: 210.0,
+            bed_temp: 60.0,
+            filament_diameter: 1.75,
+            flow: 1.0,
+            print_speed: 50.0,
+            first_layer_speed: 20.0,
+            travel_speed: 150.0,
+        }
+    }
+}
+
+/// Spindle, feeds and clearance of endmill jobs.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MillSettings {
+    /// Spindle speed (% of full speed).
+    pub spindle_pct: f32,
+    /// Cutting and plunging feed rates (mm/min).
+    pub feed: f64,
+    pub plunge_feed: f64,
+    /// Rapid feed rate (mm/min), for firmware that takes F on G0.
+    pub travel_feed: f64,
+    /// Height above the top of the material rapids are made at (mm).
+    pub clearance: f64,
+}
+
+impl Default for MillSettings {
+    fn default() -> Self {
+        Self {
+            spindle_pct: 100.0,
+            feed: 800.0,
+            plunge_feed: 200.0,
+            travel_feed: 3000.0,
+            clearance: 5.0,
+        }
+    }
+}
+
+/// Depths, pecking and feeds of drill jobs; Z 0 is the top of the material.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DrillSettings {
+    /// Hole depth below the surface (mm).
+    pub depth: f64,
+    /// Depth of each peck (mm); zero drills every hole in one plunge.
+    pub peck: f64,
+    /// Height above the surface the drill retracts to between pecks (the R
+    /// plane), and the height rapids between holes are made at (mm).
+    pub retract: f64,
+    pub clearance: f64,
+    /// Drilling and rapid feed rates (mm/min).
+    pub feed: f64,
+    pub travel_feed: f64,
+    /// Spindle speed (% of full speed).
+    pub spindle_pct: f32,
+    /// How far a hole's diameter and roundness may be off the drill width
+    /// for it to be drilled (mm).
+    pub tolerance: f64,
+    /// Write the cycles out as plain moves, for controllers without G81 / G83.
+    pub expand: bool,
+}
+
+impl Default for DrillSettings {
+    fn default() -> Self {
+        Self {
+            depth: 5.0,
+            peck: 1.5,
+            retract: 2.0,
+            clearance: 10.0,
+            feed: 100.0,
+            travel_feed: 3000.0,
+            spindle_pct: 100.0,
+            tolerance: 0.2,
+            expand: false,
+        }
+    }
+}
+
+/// One layer of a print: loops from the slicer's move plan, then infill.
+#[derive(Clone, Debug, Default)]
+pub struct PrintLayer {
+    /// Nozzle height (top of the layer) and layer height (mm).
+    pub z: f64,
+    pub height: f64,
+    /// Skirt and brim, printed first.
+    pub adhesion: Vec<LineString<f64>>,
+    pub moves: LayerMoves,
+    pub infill: Infill,
+    /// Support paths, printed along with the infill.
+    pub support: Vec<LineString<f64>>,
+}
+
+impl PrintLayer {
+    /// Shift every path by (`dx`, `dy`).
+    pub fn translate(&mut self, dx: f64, dy: f64) {
+        use geo::Translate;
+        let paths = self
+            .adhesion
+            .iter_mut()
+            .chain(&mut self.moves.loops)
+            .chain(&mut self.infill.solid)
+            .chain(&mut self.infill.sparse)
+            .chain(&mut self.support);
+        for p in paths {
+            p.translate_mut(dx, dy);
+        }
+        for t in &mut self.moves.travels {
+            t.from = Coord { x: t.from.x + dx, y: t.from.y + dy };
+            t.to = Coord { x: t.to.x + dx, y: t.to.y + dy };
+        }
+    }
+}
+
+/// One layer of a cutting job: paths in cutting order.
+#[derive(Clone, Debug, Default)]
+pub struct CutLayer {
+    /// Height of the work surface; the laser moves there to focus, the
+    /// plasma torch measures its heights from it. `None` leaves Z alone
+    /// (laser) or takes the surface as Z 0 (plasma).
+    pub z: Option<f64>,
+    pub paths: Vec<LineString<f64>>,
+}
+
+impl CutLayer {
+    pub fn is_empty(&self) -> bool {
+        self.paths.iter().all(|p| p.0.len() < 2)
+    }
+}
+
+/// G1 moves along `path` after its first point, the first one setting `feed`.
+fn feed_moves(out: &mut Vec<String>, path: &LineString<f64>, feed: f64) {
+    for (k, c) in path.0[1..].iter().enumerate() {
+        if k == 0 {
+            out.push(format!("G1 X{:.3} Y{:.3} F{feed:.0}", c.x, c.y));
+        } else {
+            out.push(format!("G1 X{:.3} Y{:.3}", c.x, c.y));
+        }
+    }
+}
+
+/// The whole laser program, ending with the tool off back at `home`.
+pub fn laser_program(layers: &[CutLayer], settings: &LaserSettings, fw: Firmware, home: Coord<f64>) -> String {
+    let on = fw.tool_on_command(settings.power_pct);
+    let off = fw.tool_off_command();
+    let cuts: usize = layers.iter().map(|l| l.paths.len()).sum();
+    let passes = settings.passes.max(1);
+    let mut out = vec![
+        "; Alumina laser job".to_owned(),
+        format!(
+            "; {} layer(s), {cuts} cut(s), {:.0} % power at {:.0} mm/min",
+            layers.len(),
+            settings.power_pct,
+            settings.cut_feed
+        ),
+        format!("; {passes} pass(es), {:.3} mm step-down", settings.z_step),
+        "G21 ; mm".to_owned(),
+        "G90 ; absolute".to_owned(),
+        off.clone(),
+    ];
+    for (i, layer) in layers.iter().enumerate() {
+        out.push(format!(";LAYER:{i}"));
+        if let Some(z) = layer.z {
+            out.push(format!("G0 Z{z:.3} F{:.0}", settings.travel_feed));
+        }
+        for pass in 0..passes {
+            if pass > 0 {
+                out.push(format!("; pass {} of {passes}", pass + 1));
+                // without a known surface height, step down relative to where Z is
+                match layer.z {
+                    Some(z) => out.push(format!("G0 Z{:.3}", z - f64::from(pass) * settings.z_step)),
+                    None if settings.z_step != 0.0 => {
+                        out.extend(["G91".to_owned(), format!("G0 Z{:.3}", -settings.z_step), "G90".to_owned()]);
+                    }
+                    None => {}
+                }
+            }
+            for path in layer.paths.iter().filter(|p| p.0.len() >= 2) {
+                let start = path.0[0];
+                out.push(format!("G0 X{:.3} Y{:.3} F{:.0}", start.x, start.y, settings.travel_feed));
+                out.push(on.clone());
+                feed_moves(&mut out, path, settings.cut_feed);
+                out.push(off.clone());
+            }
+        }
+        let lowered = f64::from(passes - 1) * settings.z_step;
+        if lowered != 0.0 {
+            match layer.z {
+                Some(z) => out.push(format!("G0 Z{z:.3}")),
+                None => out.extend(["G91".to_owned(), format!("G0 Z{lowered:.3}"), "G90".to_owned()]),
+            }
+        }
+    }
+    out.push(format!("G0 X{:.3} Y{:.3} F{:.0}", home.x, home.y, settings.travel_feed));
+    out.join("\n") + "\n"
+}
+
+/// The whole plasma program, ending with the torch off and raised back at
+/// `home`. With `touch_off` the plate is probed before every pierce, and
+/// the `G92` offset each probe sets is cleared at the end.
+pub fn plasma_program(layers: &[CutLayer], settings: &PlasmaSettings, touch_off: bool, fw: Firmware, home: Coord<f64>) -> String {
+    let s = settings;
+    let on = fw.tool_on_command(100.0);
+    let off = fw.tool_off_command();
+    let cuts: usize = layers.iter().map(|l| l.paths.len()).sum();
+    let mut out = vec![
+        "; Alumina plasma job".to_owned(),
+        format!(
+            "; {} layer(s), {cuts} cut(s), pierce {:.1} mm for {:.0} ms, cut {:.1} mm at {:.0} mm/min{}",
+            layers.len(),
+            s.pierce_height,
+            s.pierce_delay_ms,
+            s.cut_height,
+            s.cut_feed,
+            if touch_off { ", touch-off" } else { "" }
+        ),
+        "G21 ; mm".to_owned(),
+        "G90 ; absolute".to_owned(),
+        off.clone(),
+    ];
+    let thc = s.thc && !s.thc_on.trim().is_empty();
+    if thc {
+        out.push(s.thc_off.clone());
+    }
+    let mut safe = s.safe_height;
+    for (i, layer) in layers.iter().enumerate() {
+        out.push(format!(";LAYER:{i}"));
+        let surface = layer.z.unwrap_or(0.0);
+        if !touch_off {
+            safe = surface + s.safe_height;
+        }
+        for path in layer.paths.iter().filter(|p| p.0.len() >= 2) {
+            let start = path.0[0];
+            // after a touch-off Z is relative to the plate, so the clearance is too
+            out.push(format!("G0 Z{safe:.3} F{:.0}", s.travel_feed));
+            out.push(format!("G0 X{:.3} Y{:.3} F{:.0}", start.x, start.y, s.travel_feed));
+            let top = if touch_off {
+                out.push(format!("G38.2 Z{:.3} F{:.0} ; probe the plate", safe - s.probe_depth, s.probe_feed));
+                out.push(format!("G92 Z{:.3} ; plate top is Z0", -s.switch_offset));
+                safe = s.safe_height;
+                0.0
+            } else {
+                surface
+            };
+            out.push(format!("G0 Z{:.3}", top + s.pierce_height));
+            out.push(on.clone());
+            out.push(fw.dwell_command(s.pierce_delay_ms));
+            out.push(format!("G1 Z{:.3} F{:.0}", top + s.cut_height, s.cut_feed));
+            if thc {
+                out.push(s.thc_on.clone());
+            }
+            feed_moves(&mut out, path, s.cut_feed);
+            if thc {
+                out.push(s.thc_off.clone());
+            }
+            out.push(off.clone());
+        }
+    }
+    out.push(format!("G0 Z{safe:.3} F{:.0}", s.travel_feed));
+    out.push(format!("G0 X{:.3} Y{:.3}", home.x, home.y));
+    if touch_off {
+        // machine coordinates again for whatever runs next
+        out.push("G92.1 ; clear the touch-off offset".to_owned());
+    }
+    out.join("\n") + "\n"
+}
+
+/// Extrusion moves along `path`, `e_per_mm` of filament per mm travelled.
+fn extrude_moves(out: &mut Vec<String>, path: &LineString<f64>, e_per_mm: f64, feed: f64) {
+    for (k, w) in path.0.windows(2).enumerate() {
+        let e = (w[1].x - w[0].x).hypot(w[1].y - w[0].y) * e_per_mm;
+        if k == 0 {
+            out.push(format!("G1 X{:.3} Y{:.3} E{e:.5} F{feed:.0}", w[1].x, w[1].y));
+        } else {
+            out.push(format!("G1 X{:.3} Y{:.3} E{e:.5}", w[1].x, w[1].y));
+        }
+    }
+}
+
+/// Non-extruding move to `to` at height `z`, retracted and hopped if asked.
+fn travel(out: &mut Vec<String>, to: Coord<f64>, z: f64, retract: bool, r: &RetractionSettings, feed: f64) {
+    let retract = retract && r.length > 0.0;
+    if retract {
+        out.push(format!("G1 E{:.3} F{:.0}", -r.length, r.speed * 60.0));
+        if r.z_hop > 0.0 {
+            out.push(format!("G0 Z{:.3} F{feed:.0}", z + r.z_hop));
+        }
+    }
+    out.push(format!("G0 X{:.3} Y{:.3} F{feed:.0}", to.x, to.y));
+    if retract {
+        if r.z_hop > 0.0 {
+            out.push(format!("G0 Z{z:.3}"));
+        }
+        out.push(format!("G1 E{:.3} F{:.0}", r.length, r.speed * 60.0));
+    }
+}
+
+/// The whole print: heat up, every layer with its fan speed, then heaters
+/// off and the nozzle lifted clear back at `home`. Extrusion is relative
+/// (M83). Infill lines are taken nearest-first and reversed when their far
+/// end is closer; travels to them retract when longer than `min_travel`.
+pub fn extruder_program(
+    layers: &[PrintLayer],
+    settings: &ExtruderSettings,
+    retraction: &RetractionSettings,
+    fan: &FanSettings,
+    line_width: f64,
+    fw: Firmware,
+    home: Coord<f64>,
+) -> String {
+    let s = settings;
+    let filament = std::f64::consts::FRAC_PI_4 * s.filament_diameter.powi(2);
+    let travel_feed = s.travel_speed * 60.0;
+    let mut out = vec![
+        "; Alumina extruder job".to_owned(),
+        format!(
+            "; {} layer(s), {:.2} mm lines, nozzle {:.0} °C, bed {:.0} °C",
+            layers.len(),
+            line_width,
+            s.nozzle_temp,
+            s.bed_temp
+        ),
+        "G21 ; mm".to_owned(),
+        "G90 ; absolute".to_owned(),
+        "M83 ; relative extrusion".to_owned(),
+    ];
+    if s.bed_temp > 0.0 {
+        out.push(format!("M140 S{:.0}", s.bed_temp));
+    }
+    if s.nozzle_temp > 0.0 {
+        out.push(format!("M104 S{:.0}", s.nozzle_temp));
+    }
+    if s.bed_temp > 0.0 {
+        out.push(format!("M190 S{:.0} ; wait for the bed", s.bed_temp));
+    }
+    if s.nozzle_temp > 0.0 {
+        out.push(format!("M109 S{:.0} ; wait for the nozzle", s.nozzle_temp));
+    }
+    let mut nozzle = home;
+    let mut top = 0.0;
+    for (i, layer) in layers.iter().enumerate() {
+        out.push(format!(";LAYER:{i}"));
+        out.extend(fw.fan_command(fan.layer_speed(i)));
+        top = layer.z;
+        out.push(format!("G0 Z{:.3} F{travel_feed:.0}", layer.z));
+        let e_per_mm = line_width * layer.height * s.flow / filament;
+        let feed = 60.0 * if i == 0 { s.first_layer_speed } else { s.print_speed };
+
+        let dist = |a: Coord<f64>, b: Coord<f64>| (a.x - b.x).hypot(a.y - b.y);
+        let print = |out: &mut Vec<String>, path: &LineString<f64>, retract: bool, nozzle: &mut Coord<f64>| {
+            travel(out, path.0[0], layer.z, retract, retraction, travel_feed);
+            extrude_moves(out, path, e_per_mm, feed);
+            *nozzle = path.0[path.0.len() - 1];
+        };
+        for path in layer.adhesion.iter().filter(|p| p.0.len() >= 2) {
+            let retract = dist(nozzle, path.0[0]) >= retraction.min_travel;
+            print(&mut out, path, retract, &mut nozzle);
+        }
+        // the move plan has a travel before every loop that does not start where the last one ended
+        let mut travels = layer.moves.travels.iter().peekable();
+        for path in layer.moves.loops.iter().filter(|p| p.0.len() >= 2) {
+            let retract = travels.next_if(|t| t.to == path.0[0]).is_some_and(|t| t.retract);
+            print(&mut out, path, retract, &mut nozzle);
+        }
+        let mut fill: Vec<&LineString<f64>> =
+            layer.infill.solid.iter().chain(&layer.infill.sparse).chain(&layer.support).filter(|p| p.0.len() >= 2).collect();
+        while !fill.is_empty() {
+            let near = |p: &LineString<f64>| dist(p.0[0], nozzle).min(dist(p.0[p.0.len() - 1], nozzle));
+            let k = (0..fill.len()).min_by(|&a, &b| near(fill[a]).total_cmp(&near(fill[b]))).unwrap_or(0);
+            let path = fill.swap_remove(k);
+            let path = if dist(path.0[0], nozzle) <= dist(path.0[path.0.len() - 1], nozzle) {
+                path.clone()
+            } else {
+                LineString::new(path.0.iter().rev().copied().collect())
+            };
+            let retract = dist(nozzle, path.0[0]) >= retraction.min_travel;
+            print(&mut out, &path, retract, &mut nozzle);
+        }
+    }
+    if retraction.length > 0.0 {
+        out.push(format!("G1 E{:.3} F{:.0}", -retraction.length, retraction.speed * 60.0));
+    }
+    out.push("M104 S0".to_owned());
+    out.push("M140 S0".to_owned());
+    out.extend(fw.fan_command(0.0));
+    out.push(format!("G0 Z{:.3} F{travel_feed:.0}", top + 10.0));
+    out.push(format!("G0 X{:.3} Y{:.3}", home.x, home.y));
+    out.join("\n") + "\n"
+}
+
+/// The whole endmill program: tool-centre `paths` in cutting order, each
+/// plunged into and left at the clearance above `top` (the material
+/// surface), then the spindle off back at `home`.
+pub fn mill_program(paths: &[Vec<[f64; 3]>], settings: &MillSettings, top: f64, fw: Firmware, home: Coord<f64>) -> String {
+    let s = settings;
+    let safe = top + s.clearance;
+    let mut out = vec![
+        "; Alumina endmill job".to_owned(),
+        format!(
+            "; {} path(s), {:.0} % spindle, {:.0} mm/min, plunge {:.0} mm/min",
+            paths.len(),
+            s.spindle_pct,
+            s.feed,
+            s.plunge_feed
+        ),
+        "G21 ; mm".to_owned(),
+        "G90 ; absolute".to_owned(),
+        format!("G0 Z{safe:.3} F{:.0}", s.travel_feed),
+        fw.tool_on_command(s.spindle_pct),
+    ];
+    for path in paths.iter().filter(|p| p.len() >= 2) {
+        let [x, y, z] = path[0];
+        out.push(format!("G0 X{x:.3} Y{y:.3} F{:.0}", s.travel_feed));
+        out.push(format!("G1 Z{z:.3} F{:.0}", s.plunge_feed));
+        for (k, [x, y, z]) in path[1..].iter().enumerate() {
+            if k == 0 {
+                out.push(format!("G1 X{x:.3} Y{y:.3} Z{z:.3} F{:.0}", s.feed));
+            } else {
+                out.push(format!("G1 X{x:.3} Y{y:.3} Z{z:.3}"));
+            }
+        }
+        out.push(format!("G0 Z{safe:.3}"));
+    }
+    out.push(fw.tool_off_command());
+    out.push(format!("G0 X{:.3} Y{:.3} F{:.0}", home.x, home.y, s.travel_feed));
+    out.join("\n") + "\n"
+}
+
+/// Peck (or plunge) down to `bottom` and back up to the R plane as plain
+/// moves: what G83 (or G81) does at the current position.
+fn expanded_cycle(out: &mut Vec<String>, s: &DrillSettings, bottom: f64) {
+    out.push(format!("G0 Z{:.3}", s.retract));
+    if s.peck <= 0.0 {
+        out.push(format!("G1 Z{bottom:.3} F{:.0}", s.feed));
+        out.push(format!("G0 Z{:.3}", s.retract));
+        return;
+    }
+    let mut z = 0.0_f64;
+    while z > bottom {
+        if z < 0.0 {
+            // rapid back down to just above the last peck
+            out.push(format!("G0 Z{:.3}", z + 0.2));
+        }
+        z = (z - s.peck).max(bottom);
+        out.push(format!("G1 Z{z:.3} F{:.0}", s.feed));
+        out.push(format!("G0 Z{:.3}", s.retract));
+    }
+}
+
+/// The whole drill program: every hole in order, returning to the
+/// clearance height between them (G98), then the spindle off back at `home`.
+pub fn drill_program(holes: &[Coord<f64>], settings: &DrillSettings, fw: Firmware, home: Coord<f64>) -> String {
+    let s = settings;
+    let bottom = -s.depth.abs();
+    let pecking = s.peck > 0.0;
+    let mut out = vec![
+        "; Alumina drill job".to_owned(),
+        format!(
+            "; {} hole(s), {:.2} mm deep, {}, R {:.1} mm at {:.0} mm/min",
+            holes.len(),
+            s.depth.abs(),
+            if pecking { format!("{:.2} mm pecks", s.peck) } else { "one plunge".to_owned() },
+            s.retract,
+            s.feed
+        ),
+        "G21 ; mm".to_owned(),
+        "G90 ; absolute".to_owned(),
+        format!("G0 Z{:.3} F{:.0}", s.clearance, s.travel_feed),
+        fw.tool_on_command(s.spindle_pct),
+    ];
+    if !s.expand {
+        out.push("G98 ; back to the clearance height after each hole".to_owned());
+    }
+    for h in holes {
+        if s.expand {
+            out.push(format!("G0 X{:.3} Y{:.3} F{:.0}", h.x, h.y, s.travel_feed));
+            expanded_cycle(&mut out, s, bottom);
+            out.push(format!("G0 Z{:.3}", s.clearance));
+        } else if pecking {
+            out.push(format!("G83 X{:.3} Y{:.3} Z{bottom:.3} R{:.3} Q{:.3} F{:.0}", h.x, h.y, s.retract, s.peck, s.feed));
+        } else {
+            out.push(format!("G81 X{:.3} Y{:.3} Z{bottom:.3} R{:.3} F{:.0}", h.x, h.y, s.retract, s.feed));
+        }
+    }
+    if !s.expand {
+        out.push("G80 ; cancel the cycle".to_owned());
+    }
+    out.push(fw.tool_off_command());
+    out.push(format!("G0 Z{:.3} F{:.0}", s.clearance, s.travel_feed));
+    out.push(format!("G0 X{:.3} Y{:.3}", home.x, home.y));
+    out.join("\n") + "\n"
+}
@@ -0,0 +1,132 @@
+//! Checkpoints of the design graph, taken every time it is applied to the
+//! model, and the panel to roll back to one of them.
+//!
+//! A checkpoint is the graph in its saved form, so restoring one goes
+//! through the same path as opening a `.graph` file. Each checkpoint keeps
+//! its diff from the one before for the list; the diff to the current graph
+//! is only worked out while hovering over Restore.
+
+use crate::{AluminaApp, design_graph, platform, toasts};
+
+/// Oldest checkpoints are dropped past this many.
+const MAX_CHECKPOINTS: usize = 50;
+
+struct Checkpoint {
+    graph: design_graph::SavedGraph,
+    fingerprint: u64,
+    taken_ms: f64,
+    /// Changes since the previous checkpoint.
+    diff: design_graph::GraphDiff,
+}
+
+#[derive(Default)]
+pub(crate) struct GraphHistory {
+    checkpoints: Vec<Checkpoint>,
+}
+
+impl GraphHistory {
+    /// Add a checkpoint of `state` unless it matches the latest one.
+    fn record(&mut self, state: &design_graph::EditorState) {
+        let fingerprint = design_graph::fingerprint(&state.graph);
+        if self.checkpoints.last().is_some_and(|c| c.fingerprint == fingerprint) {
+            return;
+        }
+        let graph = match design_graph::SavedGraph::from_state(state) {
+            Ok(graph) => graph,
+            Err(e) => {
+                log::warn!("graph checkpoint skipped: {e}");
+                return;
+            }
+        };
+        let diff = match self.checkpoints.last() {
+            Some(prev) => prev.graph.diff(&graph),
+            None => design_graph::SavedGraph::from_state(&design_graph::EditorState::default())
+                .map(|empty| empty.diff(&graph))
+                .unwrap_or_default(),
+        };
+        self.checkpoints.push(Checkpoint { graph, fingerprint, taken_ms: platform::unix_ms(), diff });
+        if self.checkpoints.len() > MAX_CHECKPOINTS {
+            self.checkpoints.remove(0);
+        }
+    }
+}
+
+fn age(taken_ms: f64) -> String {
+    let secs = ((platform::unix_ms() - taken_ms) / 1000.0).max(0.0);
+    match secs {
+        s if s < 60.0 => "just now".to_owned(),
+        s if s < 3600.0 => format!("{:.0} min ago", s / 60.0),
+        s => format!("{:.1} h ago", s / 3600.0),
+    }
+}
+
+fn diff_lines(ui: &mut egui::Ui, diff: &design_graph::GraphDiff) {
+    if diff.is_empty() {
+        ui.weak("No changes");
+    }
+    for node in &diff.added {
+        ui.label(format!("+ {node}"));
+    }
+    for node in &diff.removed {
+        ui.label(format!("− {node}"));
+    }
+    for change in &diff.changed {
+        ui.label(format!("~ {change}"));
+    }
+    if diff.rewired > 0 {
+        ui.label(format!("{} connection(s) made or broken", diff.rewired));
+    }
+}
+
+impl AluminaApp {
+    /// Checkpoint the graph; called on every Apply.
+    pub(crate) fn checkpoint_graph(&mut self) {
+        self.graph_history.record(&self.design_state);
+    }
+
+    /// Checkpoint list in the Design sidebar, newest first.
+    pub(crate) fn graph_history_ui(&mut self, ui: &mut egui::Ui) {
+        if self.graph_history.checkpoints.is_empty() {
+            ui.weak("Checkpoints are taken on every Apply");
+            return;
+        }
+        let mut restore = None;
+        egui::ScrollArea::vertical().id_salt("graph_history").max_height(220.0).show(ui, |ui| {
+            for (i, cp) in self.graph_history.checkpoints.iter().enumerate().rev() {
+                ui.horizontal(|ui| {
+                    ui.label(format!("#{}", i + 1)).on_hover_ui(|ui| diff_lines(ui, &cp.diff));
+                    ui.weak(age(cp.taken_ms));
+                    let button = ui.small_button("Restore").on_hover_ui(|ui| {
+                        ui.label("Restoring changes the current graph by:");
+                        match design_graph::SavedGraph::from_state(&self.design_state) {
+                            Ok(current) => diff_lines(ui, &current.diff(&cp.graph)),
+                            Err(e) => {
+                                ui.label(e.to_string());
+                            }
+                        }
+                    });
+                    if button.clicked() {
+                        restore = Some(i);
+                    }
+                });
+                ui.weak(cp.diff.summary());
+            }
+        });
+        if let Some(i) = restore {
+            self.restore_checkpoint(i);
+        }
+    }
+
+    /// Replace the graph with checkpoint `i`, checkpointing the current
+    /// graph first so the rollback can itself be undone.
+    fn restore_checkpoint(&mut self, i: usize) {
+        let Some(graph) = self.graph_history.checkpoints.get(i).map(|c| c.graph.clone()) else { return };
+        self.checkpoint_graph();
+        let (mut state, missing) = graph.into_state();
+        if missing > 0 {
+            toasts::warn(format!("{missing} plugin node(s) of the checkpoint are unavailable"), None);
+        }
+        state.pan_zoom.pan = self.design_state.pan_zoom.pan;
+        self.design_state = state;
+    }
+}
@@ -0,0 +1,819 @@
+//! Streaming a G-code job to the firmware, one acknowledged line at a time.
+//!
+//! While a job runs, its program and the last acknowledged line are kept in
+//! `localStorage`, so a job interrupted by a dropped connection or a reload
+//! can be resumed from a chosen line or layer.
+//!
+//! Jobs go through a [`JobQueue`]: programs loaded while one streams wait
+//! behind it, and the operator can pause the stream between lines, resume
+//! it or cancel the running job.
+
+use std::borrow::Cow;
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+
+use crate::net::Pending;
+use crate::machine::{Firmware, gcode_words};
+use crate::platform::storage;
+use crate::slicer::FanSettings;
+
+const LS_PROGRAM: &str = "alumina.job.program";
+const LS_CHECKPOINT: &str = "alumina.job.checkpoint";
+/// Comment on the `M0`s inserted by "pause at Z".
+const HARDWARE_TAG: &str = "; insert hardware";
+/// Z lift before X/Y home in a recovery preamble (mm).
+const RECOVERY_CLEARANCE_MM: f64 = 2.0;
+
+/// Why streaming is halted waiting for the operator.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PauseKind {
+    /// `M600`: swap filament.
+    FilamentChange,
+    /// `M6 Tn`: swap the cutting tool.
+    ToolChange(String),
+    /// `M0` / `M1` program stop.
+    Stop,
+    /// `M0` inserted by "pause at Z": place nuts, magnets, … in the part.
+    InsertHardware,
+}
+
+impl PauseKind {
+    /// Classify a (comment-stripped) command that must not be streamed.
+    pub fn from_command(code: &str) -> Option<Self> {
+        let words = gcode_words(code);
+        let m = words.iter().find(|(c, _)| *c == 'M').map(|&(_, v)| v as i32)?;
+        match m {
+            600 => Some(PauseKind::FilamentChange),
+            6 => {
+                let tool = words
+                    .iter()
+                    .find(|(c, _)| *c == 'T')
+                    .map_or_else(|| "next tool".to_owned(), |&(_, t)| format!("T{t:.0}"));
+                Some(PauseKind::ToolChange(tool))
+            }
+            0 | 1 => Some(PauseKind::Stop),
+            _ => None,
+        }
+    }
+}
+
+/// A G-code program being (or about to be) streamed.
+pub struct Job {
+    pub name: String,
+    /// Program lines exactly as loaded (comments included, for display).
+    lines: Vec<String>,
+    /// Index of the first line of every layer.
+    layer_starts: Vec<usize>,
+    /// Next line to send.
+    next: usize,
+    /// Number of lines the firmware has acknowledged.
+    acked: usize,
+    in_flight: Option<Pending>,
+    started_ms: Option<f64>,
+    finished_ms: Option<f64>,
+    pub error: Option<String>,
+    /// Operator action required before streaming continues.
+    pub paused: Option<PauseKind>,
+    /// Extruder is in relative mode (`M83`) at the current position.
+    pub e_relative: bool,
+    /// Last line that switched the tool on, while it is on: sent again when
+    /// the job resumes after a pause.
+    pub tool_on: Option<String>,
+    /// Streamed to the virtual machine (see [`crate::dry_run`]); never
+    /// saved for recovery.
+    pub dry_run: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoredProgram {
+    name: String,
+    text: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Checkpoint {
+    acked: usize,
+}
+
+/// Strip `;` and `(…)` comments and surrounding whitespace. Code after a
+/// parenthesised comment is kept, parentheses nest, and a `(` never closed
+/// comments out the rest of the line.
+pub fn strip_comment(line: &str) -> Cow<'_, str> {
+    if !line.contains('(') {
+        return Cow::Borrowed(line.split(';').next().unwrap_or("").trim());
+    }
+    let mut code = String::with_capacity(line.len());
+    let mut depth = 0usize;
+    for c in line.chars() {
+        match c {
+            ';' if depth == 0 => break,
+            '(' => depth += 1,
+            ')' if depth > 0 => {
+                depth -= 1;
+                if depth == 0 {
+                    code.push(' ');
+                }
+            }
+            _ if depth == 0 => code.push(c),
+            _ => {}
+        }
+    }
+    Cow::Owned(code.trim().to_owned())
+}
+
+impl Job {
+    pub fn from_gcode(name: impl Into<String>, text: &str) -> Self {
+        let lines: Vec<String> = text.lines().map(str::to_owned).collect();
+        let layer_starts = detect_layers(&lines);
+        Self {
+            name: name.into(),
+            lines,
+            layer_starts,
+            next: 0,
+            acked: 0,
+            in_flight: None,
+            started_ms: None,
+            finished_ms: None,
+            error: None,
+            paused: None,
+            e_relative: false,
+            tool_on: None,
+            dry_run: false,
+        }
+    }
+
+    /// The program as loaded, plus any inserted pauses.
+    pub fn text(&self) -> String {
+        self.lines.join("\n")
+    }
+
+    pub fn total_lines(&self) -> usize {
+        self.lines.len()
+    }
+
+    pub fn acked_lines(&self) -> usize {
+        self.acked
+    }
+
+    /// When streaming began (`now_ms` clock).
+    pub fn started_ms(&self) -> Option<f64> {
+        self.started_ms
+    }
+
+    pub fn is_started(&self) -> bool {
+        self.started_ms.is_some()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished_ms.is_some()
+    }
+
+    /// Started, not finished and not stopped by an error.
+    pub fn is_running(&self) -> bool {
+        self.is_started() && !self.is_finished() && self.error.is_none()
+    }
+
+    /// The `M106` that set the part fan running, if the lines sent so far
+    /// leave it on.
+    pub fn fan_on(&self) -> Option<String> {
+        let last = self.lines[..self.next].iter().rev().find(|l| {
+            gcode_words(&strip_comment(l)).iter().any(|&(c, v)| c == 'M' && (v == 106.0 || v == 107.0))
+        })?;
+        let on = gcode_words(&strip_comment(last)).iter().any(|&(c, v)| c == 'M' && v == 106.0);
+        on.then(|| strip_comment(last).into_owned())
+    }
+
+    /// Continue after an operator pause.
+    pub fn resume(&mut self) {
+        self.paused = None;
+    }
+
+    /// Insert `M600` at the start of each given (1-based) layer. Only valid
+    /// before the job starts; returns how many pauses were inserted.
+    pub fn insert_pauses(&mut self, layers: &[usize]) -> usize {
+        if self.is_started() {
+            return 0;
+        }
+        let mut at: Vec<usize> = layers
+            .iter()
+            .filter_map(|&l| self.layer_starts.get(l.checked_sub(1)?).copied())
+            .collect();
+        at.sort_unstable();
+        at.dedup();
+        for &i in at.iter().rev() {
+            self.lines.insert(i, "M600 ; pause inserted by Alumina".to_owned());
+        }
+        self.layer_starts = detect_layers(&self.lines);
+        at.len()
+    }
+
+    /// Insert a hardware pause before the first layer printed above each of
+    /// `heights` (mm). Only valid before the job starts; returns how many
+    /// pauses were inserted.
+    pub fn insert_pauses_at_z(&mut self, heights: &[f32]) -> usize {
+        if self.is_started() {
+            return 0;
+        }
+        let layers: Vec<(usize, f64)> = self
+            .layer_starts
+            .iter()
+            .filter_map(|&start| Some((start, layer_z(&self.lines[start..])?)))
+            .collect();
+        let mut at: Vec<(usize, f32)> = heights
+            .iter()
+            .filter_map(|&h| layers.iter().find(|&&(_, z)| z > f64::from(h) + 1e-4).map(|&(i, _)| (i, h)))
+            .collect();
+        at.sort_unstable_by_key(|&(i, _)| i);
+        at.dedup_by_key(|&mut (i, _)| i);
+        for &(i, h) in at.iter().rev() {
+            self.lines.insert(i, format!("M0 {HARDWARE_TAG} at Z{h:.2} (pause inserted by Alumina)"));
+        }
+        self.layer_starts = detect_layers(&self.lines);
+        at.len()
+    }
+
+    /// Replace the program's fan commands with `fan`'s schedule: a command
+    /// wherever the layer speed changes, and `bridge_speed` inside `;TYPE:`
+    /// sections named as bridges (PrusaSlicer / Cura comments). Only valid
+    /// before the job starts; returns how many fan commands were written.
+    pub fn apply_fan(&mut self, fan: &FanSettings, fw: Firmware) -> usize {
+        if self.is_started() || fw.fan_command(0.0).is_none() {
+            return 0;
+        }
+        let mut out = Vec::with_capacity(self.lines.len() + self.layer_starts.len());
+        let mut current: Option<f32> = None;
+        let mut written = 0;
+        let mut set = |out: &mut Vec<String>, pct: f32| {
+            if current != Some(pct) {
+                if let Some(cmd) = fw.fan_command(pct) {
+                    out.push(cmd);
+                    current = Some(pct);
+                    written += 1;
+                }
+            }
+        };
+        let (mut layer, mut bridging) = (None, false);
+        for (i, line) in self.lines.iter().enumerate() {
+            if let Ok(n) = self.layer_starts.binary_search(&i) {
+                layer = Some(n);
+                set(&mut out, if bridging { fan.bridge_speed } else { fan.layer_speed(n) });
+            }
+            let m = gcode_words(&strip_comment(line)).iter().find(|(c, _)| *c == 'M').map(|&(_, v)| v as i32);
+            if matches!(m, Some(106 | 107)) {
+                out.push(format!("; {} (fan set by Alumina)", line.trim()));
+                continue;
+            }
+            out.push(line.clone());
+            if let Some(kind) = line.trim_start().strip_prefix(";TYPE:") {
+                bridging = kind.to_ascii_lowercase().contains("bridge");
+                if let Some(n) = layer {
+                    set(&mut out, if bridging { fan.bridge_speed } else { fan.layer_speed(n) });
+                }
+            }
+        }
+        self.lines = out;
+        self.layer_starts = detect_layers(&self.lines);
+        written
+    }
+
+    pub fn start(&mut self, now_ms: f64) {
+        self.started_ms = Some(now_ms);
+        if !self.dry_run {
+            self.save_program();
+            self.save_checkpoint();
+        }
+    }
+
+    /// First line (1-based) of a 1-based layer.
+    pub fn layer_start_line(&self, layer: usize) -> Option<usize> {
+        self.layer_starts.get(layer.checked_sub(1)?).map(|i| i + 1)
+    }
+
+    /// Layer (1-based) containing a 1-based line.
+    pub fn layer_of_line(&self, line: usize) -> Option<usize> {
+        let n = self.layer_starts.iter().filter(|&&s| s < line).count();
+        (n > 0).then_some(n)
+    }
+
+    pub fn layer_count(&self) -> usize {
+        self.layer_starts.len()
+    }
+
+    /// A new, unstarted job that restores machine state and continues at
+    /// `line` (1-based).
+    pub fn resumed_at(&self, line: usize, fw: Firmware) -> Job {
+        let idx = line.saturating_sub(1).min(self.lines.len());
+        let mut lines = recovery_preamble(&self.lines[..idx], line, fw);
+        lines.extend_from_slice(&self.lines[idx..]);
+        let mut job = Job::from_gcode(self.name.clone(), "");
+        job.layer_starts = detect_layers(&lines);
+        job.lines = lines;
+        job
+    }
+
+    /// Text of the recovery preamble `resumed_at` would emit.
+    pub fn preamble_preview(&self, line: usize, fw: Firmware) -> String {
+        let idx = line.saturating_sub(1).min(self.lines.len());
+        recovery_preamble(&self.lines[..idx], line, fw).join("\n")
+    }
+
+    fn save_program(&self) {
+        let Some(store) = storage() else { return };
+        let program = StoredProgram {
+            name: self.name.clone(),
+            text: self.lines.join("\n"),
+        };
+        match serde_json::to_string(&program) {
+            Ok(json) => {
+                // Large programs can exceed the quota; recovery is then unavailable
+                if let Err(e) = store.set_item(LS_PROGRAM, &json) {
+                    log::warn!("job not saved for recovery: {e}");
+                }
+            }
+            Err(e) => log::error!("serialising job failed: {e}"),
+        }
+    }
+
+    /// Record the acknowledged line, after every acknowledgement: a line
+    /// run twice on recovery would gouge or over-extrude.
+    pub fn save_checkpoint(&self) {
+        if self.dry_run {
+            return;
+        }
+        let Some(store) = storage() else { return };
+        if let Ok(json) = serde_json::to_string(&Checkpoint { acked: self.acked }) {
+            let _ = store.set_item(LS_CHECKPOINT, &json);
+        }
+    }
+
+    /// Forget the stored job (finished or deliberately cancelled).
+    pub fn clear_saved() {
+        if let Some(store) = storage() {
+            store.remove_item(LS_PROGRAM);
+            store.remove_item(LS_CHECKPOINT);
+        }
+    }
+
+    /// A job interrupted in an earlier session, with the 1-based line to
+    /// resume from.
+    pub fn load_interrupted() -> Option<(Job, usize)> {
+        let store = storage()?;
+        let program: StoredProgram = serde_json::from_str(&store.get_item(LS_PROGRAM)?).ok()?;
+        let checkpoint: Checkpoint = serde_json::from_str(&store.get_item(LS_CHECKPOINT)?).ok()?;
+        let job = Job::from_gcode(program.name, &program.text);
+        Some((job, checkpoint.acked + 1))
+    }
+
+    /// Line most recently acknowledged (1-based number and text).
+    pub fn current_line(&self) -> Option<(usize, &str)> {
+        self.acked
+            .checked_sub(1)
+            .and_then(|i| self.lines.get(i).map(|l| (i + 1, l.as_str())))
+    }
+
+    pub fn progress(&self) -> f32 {
+        if self.lines.is_empty() {
+            1.0
+        } else {
+            self.acked as f32 / self.lines.len() as f32
+        }
+    }
+
+    /// `(current layer 1-based, total layers)`; `None` if no layers were detected.
+    pub fn layer(&self) -> Option<(usize, usize)> {
+        if self.layer_starts.is_empty() {
+            return None;
+        }
+        let done = self.layer_starts.iter().filter(|&&s| s < self.acked).count();
+        Some((done.max(1), self.layer_starts.len()))
+    }
+
+    pub fn elapsed_ms(&self, now_ms: f64) -> f64 {
+        match self.started_ms {
+            Some(s) => self.finished_ms.unwrap_or(now_ms) - s,
+            None => 0.0,
+        }
+    }
+
+    /// Linear projection from the acknowledged-line rate so far.
+    pub fn remaining_ms(&self, now_ms: f64) -> Option<f64> {
+        if self.acked == 0 || !self.is_started() {
+            return None;
+        }
+        let per_line = self.elapsed_ms(now_ms) / self.acked as f64;
+        Some(per_line * (self.lines.len() - self.acked) as f64)
+    }
+
+    /// Collect the reply for the in-flight line. Returns `Some(Err)` if the
+    /// firmware rejected it (the job is then stopped).
+    pub fn poll_ack(&mut self, now_ms: f64) -> Option<Result<(), String>> {
+        let slot = self.in_flight.as_ref()?;
+        let reply = slot.lock().unwrap().take()?;
+        self.in_flight = None;
+        match reply {
+            Ok(_) => {
+                self.acked = self.next;
+                if self.acked >= self.lines.len() {
+                    self.finished_ms = Some(now_ms);
+                }
+                Some(Ok(()))
+            }
+            Err(e) => {
+                let msg = format!("line {}: {e}", self.next);
+                self.error = Some(msg.clone());
+                Some(Err(msg))
+            }
+        }
+    }
+
+    /// Next command to send, skipping comment-only lines. `None` while a line
+    /// is in flight or when nothing is left.
+    pub fn next_command(&mut self, now_ms: f64) -> Option<String> {
+        if !self.is_running() || self.in_flight.is_some() || self.paused.is_some() {
+            return None;
+        }
+        while let Some(line) = self.lines.get(self.next) {
+            let code = strip_comment(line).into_owned();
+            let hardware = line.contains(HARDWARE_TAG);
+            self.next += 1;
+            if code.is_empty() {
+                self.acked = self.next;
+                continue;
+            }
+            // operator pauses are handled by the UI, not streamed
+            if let Some(kind) = PauseKind::from_command(&code) {
+                self.acked = self.next;
+                self.paused = Some(if hardware && kind == PauseKind::Stop { PauseKind::InsertHardware } else { kind });
+                return None;
+            }
+            match code.to_ascii_uppercase().as_str() {
+                "M82" => self.e_relative = false,
+                "M83" => self.e_relative = true,
+                _ => {}
+            }
+            return Some(code);
+        }
+        self.finished_ms = Some(now_ms);
+        None
+    }
+
+    /// The command returned by `next_command` has been dispatched.
+    pub fn sent(&mut self, reply: Pending) {
+        self.in_flight = Some(reply);
+    }
+
+    /// Stop streaming with an error (e.g. a soft-limit violation).
+    pub fn fail(&mut self, why: impl Into<String>) {
+        self.in_flight = None;
+        self.error = Some(why.into());
+    }
+}
+
+/// The job being streamed and those waiting behind it.
+#[derive(Default)]
+pub struct JobQueue {
+    /// Job shown in the Control tab: streaming, or loaded and not started.
+    pub active: Option<Job>,
+    waiting: VecDeque<Job>,
+    /// Held by the operator: the line in flight completes, no more are sent.
+    held: bool,
+    /// Commands that switch back on what was switched off for the hold.
+    pub restore: Vec<String>,
+    /// Start each waiting job as soon as the one before finishes.
+    pub run_through: bool,
+    /// Start jobs as dry runs, on the virtual machine.
+    pub dry_run: bool,
+}
+
+impl JobQueue {
+    /// Make `job` the active job, or queue it behind the running one.
+    /// Returns whether it was queued.
+    pub fn load(&mut self, job: Job) -> bool {
+        if self.is_running() {
+            self.waiting.push_back(job);
+            true
+        } else {
+            self.active = Some(job);
+            false
+        }
+    }
+
+    pub fn waiting(&self) -> impl Iterator<Item = &Job> {
+        self.waiting.iter()
+    }
+
+    pub fn remove_waiting(&mut self, i: usize) -> Option<Job> {
+        self.waiting.remove(i)
+    }
+
+    pub fn clear_waiting(&mut self) {
+        self.waiting.clear();
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.active.as_ref().is_some_and(Job::is_running)
+    }
+
+    pub fn is_held(&self) -> bool {
+        self.held && self.is_running()
+    }
+
+    pub fn pause(&mut self) {
+        self.held = self.is_running();
+    }
+
+    pub fn resume(&mut self) {
+        self.held = false;
+    }
+
+    /// Stop the running job where it is; the waiting ones stay queued.
+    pub fn cancel(&mut self) {
+        if let Some(job) = self.active.as_mut().filter(|j| j.is_running()) {
+            job.fail("cancelled by user");
+            Job::clear_saved();
+        }
+        self.held = false;
+    }
+
+    /// Acknowledged lines over all lines of the active and waiting jobs.
+    pub fn progress(&self) -> f32 {
+        let jobs = self.active.iter().chain(&self.waiting);
+        let (acked, total) = jobs.fold((0, 0), |(a, t), j| (a + j.acked_lines(), t + j.total_lines()));
+        if total == 0 { 1.0 } else { acked as f32 / total as f32 }
+    }
+
+    /// See [`Job::poll_ack`].
+    pub fn poll_ack(&mut self, now_ms: f64) -> Option<Result<(), String>> {
+        self.active.as_mut()?.poll_ack(now_ms)
+    }
+
+    /// See [`Job::next_command`]; nothing while held.
+    pub fn next_command(&mut self, now_ms: f64) -> Option<String> {
+        if self.held {
+            return None;
+        }
+        self.active.as_mut()?.next_command(now_ms)
+    }
+
+    pub fn sent(&mut self, reply: Pending) {
+        if let Some(job) = self.active.as_mut() {
+            job.sent(reply);
+        }
+    }
+
+    pub fn fail(&mut self, why: impl Into<String>) {
+        if let Some(job) = self.active.as_mut() {
+            job.fail(why);
+        }
+        self.held = false;
+    }
+
+    /// Replace a finished (or stopped, when `force`) active job with the
+    /// next waiting one, started if running through. Returns whether one
+    /// was brought on.
+    pub fn advance(&mut self, now_ms: f64, force: bool) -> bool {
+        let done = self.active.as_ref().is_none_or(|j| j.is_finished() || (force && !j.is_running()));
+        if !done {
+            return false;
+        }
+        let Some(mut next) = self.waiting.pop_front() else { return false };
+        if self.run_through && !force {
+            next.dry_run = self.dry_run;
+            next.start(now_ms);
+        }
+        self.active = Some(next);
+        true
+    }
+}
+
+/// Commands that bring a machine from power-up back to the state it was in
+/// after `done` (the lines already executed): units, temperatures, homed
+/// X/Y, restored Z and extruder position, fan / spindle and modal modes.
+/// Z is assumed not to have moved, since homing it would hit the part; it
+/// is lifted by [`RECOVERY_CLEARANCE_MM`] before X/Y home so the nozzle or
+/// tool clears the part.
+fn recovery_preamble(done: &[String], line: usize, fw: Firmware) -> Vec<String> {
+    let mut absolute = true;
+    let mut e_relative = false;
+    let mut extrudes = false;
+    let mut units = None;
+    let mut pos = [0.0f64; 4];
+    let mut feed = None;
+    let mut hotend = None;
+    let mut bed = None;
+    let mut fan = None;
+    let mut spindle: Option<String> = None;
+
+    for raw in done {
+        let code = strip_comment(raw);
+        if code.is_empty() {
+            continue;
+        }
+        let words = gcode_words(&code);
+        let s = words.iter().find(|(c, _)| *c == 'S').map(|&(_, v)| v);
+        for &(c, v) in &words {
+            match (c, v as i32) {
+                ('G', 20 | 21) => units = Some(format!("G{v:.0}")),
+                ('G', 90) => absolute = true,
+                ('G', 91) => absolute = false,
+                ('G', 92) => {
+                    for &(a, av) in &words {
+                        if let Some(i) = "XYZE".find(a) {
+                            pos[i] = av;
+                        }
+                    }
+                }
+                ('G', 0..=3) => {
+                    for &(a, av) in &words {
+                        if let Some(i) = "XYZE".find(a) {
+                            extrudes |= i == 3;
+                            let rel = if i == 3 { e_relative || !absolute } else { !absolute };
+                            pos[i] = if rel { pos[i] + av } else { av };
+                        }
+                        if a == 'F' {
+                            feed = Some(av);
+                        }
+                    }
+                }
+                ('M', 82) => e_relative = false,
+                ('M', 83) => e_relative = true,
+                ('M', 104 | 109) => hotend = s.or(hotend),
+                ('M', 140 | 190) => bed = s.or(bed),
+                ('M', 106) => fan = Some(s.unwrap_or(255.0)),
+                ('M', 107) => fan = None,
+                ('M', 3 | 4) => spindle = Some(code.to_string()),
+                ('M', 5) => spindle = None,
+                _ => {}
+            }
+        }
+    }
+
+    let [x, y, z, e] = pos;
+    let mut out = vec![format!("; --- recovery preamble: resume at line {line} ---")];
+    out.extend(units);
+    if let Some(t) = bed {
+        out.push(format!("M190 S{t:.0}"));
+    }
+    if let Some(t) = hotend {
+        out.push(format!("M109 S{t:.0}"));
+    }
+    out.push("G91".to_owned());
+    out.push(format!("G0 Z{RECOVERY_CLEARANCE_MM:.3}"));
+    out.push("G90".to_owned());
+    out.push(fw.home_command(Some(0)));
+    out.push(fw.home_command(Some(1)));
+    out.push(format!("G92 Z{:.3}", z + RECOVERY_CLEARANCE_MM));
+    out.push(format!("G0 X{x:.3} Y{y:.3}"));
+    if let Some(f) = fan {
+        out.push(format!("M106 S{f:.0}"));
+    }
+    out.extend(spindle);
+    out.push(format!("G1 Z{z:.3} F300"));
+    if extrudes {
+        out.push(if e_relative { "M83" } else { "M82" }.to_owned());
+        if !e_relative {
+            out.push(format!("G92 E{e:.5}"));
+        }
+    }
+    if let Some(f) = feed {
+        out.push(format!("G1 F{f:.0}"));
+    }
+    if !absolute {
+        out.push("G91".to_owned());
+    }
+    out.push("; --- end of recovery preamble ---".to_owned());
+    out
+}
+
+/// Layer boundaries: slicer comments (`;LAYER:n`, `;LAYER_CHANGE`) if present,
+/// otherwise every increase of Z in a G0/G1 move.
+fn detect_layers(lines: &[String]) -> Vec<usize> {
+    let tagged: Vec<usize> = lines
+        .iter()
+        .enumerate()
+        .filter(|(_, l)| {
+            let l = l.trim_start();
+            l.starts_with(";LAYER:") || l.starts_with(";LAYER_CHANGE")
+        })
+        .map(|(i, _)| i)
+        .collect();
+    if !tagged.is_empty() {
+        return tagged;
+    }
+
+    let mut out = Vec::new();
+    let mut z = f64::NEG_INFINITY;
+    for (i, line) in lines.iter().enumerate() {
+        let words = gcode_words(line);
+        let is_move = words.iter().any(|&(c, v)| c == 'G' && (v == 0.0 || v == 1.0));
+        if !is_move {
+            continue;
+        }
+        if let Some(&(_, nz)) = words.iter().find(|(c, _)| *c == 'Z') {
+            if nz > z + 1e-9 {
+                out.push(i);
+            }
+            z = nz;
+        }
+    }
+    out
+}
+
+/// Z of the first move in `lines` that sets one.
+fn layer_z(lines: &[String]) -> Option<f64> {
+    lines.iter().find_map(|line| {
+        let words = gcode_words(line);
+        let is_move = words.iter().any(|&(c, v)| c == 'G' && (v == 0.0 || v == 1.0));
+        if !is_move {
+            return None;
+        }
+        words.iter().find(|(c, _)| *c == 'Z').map(|&(_, z)| z)
+    })
+}
+
+/// `h:mm:ss` for durations in milliseconds.
+pub fn format_duration(ms: f64) -> String {
+    let s = (ms / 1000.0).max(0.0).round() as u64;
+    format!("{}:{:02}:{:02}", s / 3600, (s / 60) % 60, s % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &[&str]) -> Vec<String> {
+        text.iter().map(|&l| l.to_owned()).collect()
+    }
+
+    #[test]
+    fn strip_comment_drops_every_comment_form() {
+        assert_eq!(strip_comment("  G1 X1 ; move"), "G1 X1");
+        assert_eq!(strip_comment("G1 (fast) X1 (then) Y2"), "G1   X1   Y2");
+        assert_eq!(strip_comment("G1 (outer (inner) still) X1"), "G1   X1");
+        assert_eq!(strip_comment("G1 (a; b) X1 ; c"), "G1   X1");
+        // never closed: a comment to the end of the line
+        assert_eq!(strip_comment("G1 X1 (no end Y2"), "G1 X1");
+        assert_eq!(strip_comment("(only a comment)"), "");
+    }
+
+    #[test]
+    fn gcode_words_agree_with_strip_comment() {
+        for line in ["G1 X1 (no end Y2", "G1 (outer (inner) Z9) X1", "G0 (a; b) X1 ; Y3", "G1 X1 Y2"] {
+            assert_eq!(gcode_words(line), gcode_words(&strip_comment(line)), "{line}");
+        }
+        assert_eq!(gcode_words("G1 X1 (no end Y2"), [('G', 1.0), ('X', 1.0)]);
+        assert_eq!(gcode_words("G1 (outer (inner) Z9) X1"), [('G', 1.0), ('X', 1.0)]);
+    }
+
+    #[test]
+    fn recovery_preamble_restores_print_state() {
+        let done = lines(&["G21", "M140 S60", "M104 S200", "M83", "G90", "G1 X10 Y20 Z0.3 F1200", "G1 X15 E0.5 ; wipe", "M106 S128"]);
+        let out = recovery_preamble(&done, 9, Firmware::Marlin);
+        assert_eq!(
+            out,
+            [
+                "; --- recovery preamble: resume at line 9 ---",
+                "G21",
+                "M190 S60",
+                "M109 S200",
+                "G91",
+                "G0 Z2.000",
+                "G90",
+                "G28 X",
+                "G28 Y",
+                "G92 Z2.300",
+                "G0 X15.000 Y20.000",
+                "M106 S128",
+                "G1 Z0.300 F300",
+                "M83",
+                "G1 F1200",
+                "; --- end of recovery preamble ---",
+            ]
+        );
+    }
+
+    #[test]
+    fn recovery_preamble_restores_spindle_and_relative_mode() {
+        let done = lines(&["G90", "M3 S12000 (spindle on)", "G0 X5 Y5", "G91", "G1 Z-1 F100"]);
+        let out = recovery_preamble(&done, 6, Firmware::Grbl);
+        assert_eq!(
+            out,
+            [
+                "; --- recovery preamble: resume at line 6 ---",
+                "G91",
+                "G0 Z2.000",
+                "G90",
+                "$HX",
+                "$HY",
+                "G92 Z1.000",
+                "G0 X5.000 Y5.000",
+                "M3 S12000",
+                "G1 Z-1.000 F300",
+                "G1 F100",
+                "G91",
+                "; --- end of recovery preamble ---",
+            ]
+        );
+    }
+}
@@ -0,0 +1,171 @@
+//! Per-layer statistics of the sliced job: perimeter and infill length,
+//! islands and an estimated time, in a table whose rows jump the preview to
+//! their layer.
+//!
+//! Every layer has to be sliced and filled, so the table is filled a few
+//! layers per frame rather than in one go, and restarts whenever the models
+//! or the slicing settings change.
+
+use std::hash::{Hash, Hasher};
+
+use geo::Coord;
+
+use crate::{AluminaApp, Tool, autosave, cutting, engine, now_ms, slicer};
+
+/// Slicing time spent per frame while the table fills (ms).
+const FRAME_BUDGET_MS: f64 = 12.0;
+
+#[derive(Clone, Copy, Debug, Default)]
+struct LayerStats {
+    perimeter_mm: f64,
+    infill_mm: f64,
+    travel_mm: f64,
+    islands: usize,
+    retractions: usize,
+}
+
+pub(crate) struct StatsTable {
+    rows: Vec<LayerStats>,
+    /// Hash of the inputs the rows were made from.
+    key: Option<u64>,
+    /// Filling in the background.
+    running: bool,
+    /// Speeds the time estimate assumes (mm/s).
+    print_speed: f64,
+    travel_speed: f64,
+}
+
+impl Default for StatsTable {
+    fn default() -> Self {
+        Self {
+            rows: Vec::new(),
+            key: None,
+            running: false,
+            print_speed: 50.0,
+            travel_speed: 150.0,
+        }
+    }
+}
+
+impl StatsTable {
+    fn time_s(&self, s: &LayerStats, retraction: &slicer::RetractionSettings) -> f64 {
+        let retract_s = if retraction.speed > 0.0 { 2.0 * retraction.length / retraction.speed } else { 0.0 };
+        (s.perimeter_mm + s.infill_mm) / self.print_speed.max(1.0)
+            + s.travel_mm / self.travel_speed.max(1.0)
+            + s.retractions as f64 * retract_s
+    }
+}
+
+impl AluminaApp {
+    /// Everything the statistics depend on.
+    fn stats_key(&self) -> u64 {
+        let mut h = std::collections::hash_map::DefaultHasher::new();
+        autosave::models_hash(&self.models, &mut h);
+        serde_json::to_string(&self.settings_snapshot()).unwrap_or_default().hash(&mut h);
+        (self.perimeters, self.infill_type as u8, self.selected_tool as u8).hash(&mut h);
+        h.finish()
+    }
+
+    fn layer_stats(&self, plan: &[slicer::Layer], index: usize) -> LayerStats {
+        let Some(slice) = engine::slice_union(self.models.iter().map(|m| &m.mesh), plan[index].slice_z()) else {
+            return LayerStats::default();
+        };
+        let (slice, _) = engine::simplify_slice(&slice, self.simplify_tolerance);
+        let outline: f64 = engine::cut_paths(&slice).iter().map(cutting::path_length).sum();
+        let mut stats = LayerStats {
+            // every perimeter loop counted at the outline's length
+            perimeter_mm: outline * f64::from(self.perimeters.max(1)),
+            islands: slicer::polygons(&slice).len(),
+            ..LayerStats::default()
+        };
+        if self.selected_tool == Tool::Extruder {
+            let models = self
+                .models
+                .iter()
+                .map(|m| (&m.mesh, m.overrides.infill(&self.infill_settings), m.overrides.perimeters(self.perimeters)));
+            let fill = engine::layer_infill(models, self.infill_type, self.line_width, plan, index);
+            stats.infill_mm = fill.solid.iter().chain(&fill.sparse).map(cutting::path_length).sum();
+            let models = self.models.iter().map(|m| (&m.mesh, m.overrides.perimeters(self.perimeters)));
+            let shells = engine::layer_perimeters(models, self.line_width, plan[index].slice_z());
+            let moves = engine::layer_moves(&shells, &self.seam, &self.retraction, index);
+            stats.perimeter_mm = moves.loops.iter().map(cutting::path_length).sum();
+            let dist = |a: Coord<f64>, b: Coord<f64>| ((a.x - b.x).powi(2) + (a.y - b.y).powi(2)).sqrt();
+            stats.travel_mm = moves.travels.iter().map(|t| dist(t.from, t.to)).sum();
+            stats.retractions = moves.retractions();
+        }
+        stats
+    }
+
+    /// Table of per-layer statistics; clicking a row shows that layer.
+    pub(crate) fn layer_stats_ui(&mut self, ui: &mut egui::Ui) {
+        let key = self.stats_key();
+        if self.layer_stats.key != Some(key) {
+            self.layer_stats.rows.clear();
+            self.layer_stats.key = Some(key);
+        }
+        let plan = self.layer_plan().to_vec();
+
+        // fill a few more rows
+        if self.layer_stats.running {
+            let started = now_ms();
+            while self.layer_stats.rows.len() < plan.len() && now_ms() - started < FRAME_BUDGET_MS {
+                let row = self.layer_stats(&plan, self.layer_stats.rows.len());
+                self.layer_stats.rows.push(row);
+            }
+            if self.layer_stats.rows.len() >= plan.len() {
+                self.layer_stats.running = false;
+            } else {
+                ui.ctx().request_repaint();
+            }
+        }
+
+        let table = &mut self.layer_stats;
+        ui.horizontal(|ui| {
+            let label = ui.label("Print / travel speed:");
+            ui.add(egui::DragValue::new(&mut table.print_speed).speed(1.0).range(1.0..=500.0).suffix(" mm/s"))
+                .labelled_by(label.id);
+            ui.add(egui::DragValue::new(&mut table.travel_speed).speed(1.0).range(1.0..=1000.0).suffix(" mm/s"));
+        });
+        ui.horizontal(|ui| {
+            if table.running {
+                ui.add(egui::ProgressBar::new(table.rows.len() as f32 / plan.len().max(1) as f32).desired_width(140.0));
+                if ui.button("Stop").clicked() {
+                    table.running = false;
+                }
+            } else if table.rows.len() < plan.len() && ui.button("Compute").on_hover_text("Slice every layer").clicked() {
+                table.running = true;
+            }
+        });
+        if table.rows.is_empty() {
+            return;
+        }
+
+        let current = usize::try_from(self.current_layer).unwrap_or(0);
+        let mut jump = None;
+        let total_s: f64 = table.rows.iter().map(|r| table.time_s(r, &self.retraction)).sum();
+        ui.small(format!("Estimated time of the computed layers: {}", crate::job::format_duration(total_s * 1000.0)));
+        egui::ScrollArea::vertical().max_height(240.0).show(ui, |ui| {
+            egui::Grid::new("layer_stats").num_columns(6).striped(true).show(ui, |ui| {
+                for h in ["Layer", "Z (mm)", "Perimeter (mm)", "Infill (mm)", "Islands", "Time"] {
+                    ui.strong(h);
+                }
+                ui.end_row();
+                for (i, row) in table.rows.iter().enumerate() {
+                    if ui.selectable_label(i == current, format!("{}", i + 1)).clicked() {
+                        jump = Some(i);
+                    }
+                    ui.label(plan.get(i).map_or_else(String::new, |l| format!("{:.2}", l.top())));
+                    ui.label(format!("{:.0}", row.perimeter_mm));
+                    ui.label(format!("{:.0}", row.infill_mm));
+                    ui.label(row.islands.to_string());
+                    ui.label(crate::job::format_duration(table.time_s(row, &self.retraction) * 1000.0));
+                    ui.end_row();
+                }
+            });
+        });
+        if let Some(i) = jump {
+            self.current_layer = i32::try_from(i).unwrap_or(0);
+            self.refresh_slice();
+        }
+    }
+}
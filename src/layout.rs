@@ -0,0 +1,176 @@
+//! Dockable panel layout of the Control and Diagnostics tabs.
+//!
+//! Every panel is a dock tab that can be resized, rearranged into other
+//! splits or dragged out into a floating window. The arrangement is saved
+//! (`localStorage`, or the config directory on the desktop) whenever a drag
+//! ends, and "Reset layout" brings back the default one.
+
+use egui_dock::{DockArea, DockState, NodeIndex, Style, TabViewer};
+use serde::{Deserialize, Serialize};
+
+use crate::{AluminaApp, Tab, platform::storage};
+
+const LS_KEY: &str = "alumina.layout";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub(crate) enum Pane {
+    Models,
+    View,
+    Tool,
+    Slicing,
+    Viewport,
+    Machine,
+    DiagControls,
+    Plot,
+    Console,
+}
+
+impl Pane {
+    fn title(self) -> &'static str {
+        match self {
+            Pane::Models => "Models",
+            Pane::View => "View",
+            Pane::Tool => "Tool",
+            Pane::Slicing => "Slicing",
+            Pane::Viewport => "Viewport",
+            Pane::Machine => "Machine",
+            Pane::DiagControls => "Diagnostics",
+            Pane::Plot => "Graph",
+            Pane::Console => "Console",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct Layout {
+    control: DockState<Pane>,
+    diagnostics: DockState<Pane>,
+    /// JSON last written to storage.
+    #[serde(skip)]
+    saved: String,
+    /// "Reset layout" was clicked inside a pane; applied after the dock is drawn.
+    #[serde(skip)]
+    reset: bool,
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        let mut control = DockState::new(vec![Pane::Viewport]);
+        let tree = control.main_surface_mut();
+        let [viewport, _] = tree.split_left(NodeIndex::root(), 0.78, vec![Pane::Models, Pane::View, Pane::Tool, Pane::Slicing]);
+        tree.split_right(viewport, 0.78, vec![Pane::Machine]);
+
+        let mut diagnostics = DockState::new(vec![Pane::Plot]);
+        let tree = diagnostics.main_surface_mut();
+        let [plot, _] = tree.split_left(NodeIndex::root(), 0.78, vec![Pane::DiagControls]);
+        tree.split_below(plot, 0.5, vec![Pane::Console]);
+
+        Self { control, diagnostics, saved: String::new(), reset: false }
+    }
+}
+
+impl Layout {
+    /// Restore the saved arrangement, falling back to the default one.
+    pub(crate) fn load() -> Self {
+        storage()
+            .and_then(|s| s.get_item(LS_KEY))
+            .and_then(|json| match serde_json::from_str(&json) {
+                Ok(layout) => Some(layout),
+                Err(e) => {
+                    log::warn!("stored panel layout is invalid: {e}");
+                    None
+                }
+            })
+            .unwrap_or_default()
+    }
+
+    /// Write the arrangement to storage if it changed since the last save.
+    fn save(&mut self) {
+        let Some(store) = storage() else { return };
+        match serde_json::to_string(self) {
+            Ok(json) if json != self.saved => {
+                if let Err(e) = store.set_item(LS_KEY, &json) {
+                    log::error!("saving panel layout failed: {e}");
+                }
+                self.saved = json;
+            }
+            Ok(_) => {}
+            Err(e) => log::error!("serialising panel layout failed: {e}"),
+        }
+    }
+
+    fn state_mut(&mut self, tab: Tab) -> &mut DockState<Pane> {
+        match tab {
+            Tab::Diagnostics => &mut self.diagnostics,
+            Tab::Control | Tab::Design => &mut self.control,
+        }
+    }
+}
+
+struct Viewer<'a> {
+    app: &'a mut AluminaApp,
+    frame: &'a eframe::Frame,
+}
+
+impl TabViewer for Viewer<'_> {
+    type Tab = Pane;
+
+    fn title(&mut self, tab: &mut Pane) -> egui::WidgetText {
+        tab.title().into()
+    }
+
+    fn id(&mut self, tab: &mut Pane) -> egui::Id {
+        egui::Id::new(("dock_pane", *tab))
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui, tab: &mut Pane) {
+        match tab {
+            Pane::Models => self.app.models_pane(ui),
+            Pane::View => self.app.view_pane(ui),
+            Pane::Tool => self.app.tool_pane(ui),
+            Pane::Slicing => self.app.slicing_pane(ui),
+            Pane::Viewport => self.app.viewport_pane(ui, self.frame),
+            Pane::Machine => self.app.machine_panel_ui(ui),
+            Pane::DiagControls => self.app.diag_controls_pane(ui),
+            Pane::Plot => self.app.diag_plot_pane(ui),
+            Pane::Console => self.app.diag_console_pane(ui),
+        }
+    }
+
+    /// Panels can be moved but not lost.
+    fn closeable(&mut self, _: &mut Pane) -> bool {
+        false
+    }
+
+    /// The viewport, plot and console fill their area and scroll on their own.
+    fn scroll_bars(&self, tab: &Pane) -> [bool; 2] {
+        let fills = matches!(tab, Pane::Viewport | Pane::Plot | Pane::Console);
+        [false, !fills]
+    }
+}
+
+impl AluminaApp {
+    /// Show the dock area of `tab` in the remaining space of the window.
+    pub(crate) fn show_dock(&mut self, ctx: &egui::Context, frame: &eframe::Frame, tab: Tab) {
+        let mut state = std::mem::replace(self.layout.state_mut(tab), DockState::new(Vec::new()));
+        DockArea::new(&mut state)
+            .id(egui::Id::new(("dock", tab as u8)))
+            .style(Style::from_egui(ctx.style().as_ref()))
+            .show_close_buttons(false)
+            .show(ctx, &mut Viewer { app: self, frame });
+        *self.layout.state_mut(tab) = state;
+
+        if std::mem::take(&mut self.layout.reset) {
+            let saved = std::mem::take(&mut self.layout.saved);
+            self.layout = Layout { saved, ..Layout::default() };
+            self.layout.save();
+        } else if ctx.input(|i| i.pointer.any_released()) {
+            self.layout.save();
+        }
+    }
+
+    /// Put every panel back in its default place.
+    pub(crate) fn reset_layout(&mut self) {
+        self.layout.reset = true;
+    }
+}
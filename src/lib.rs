@@ -1,34 +1,75 @@
 #![warn(clippy::pedantic)]
+mod a11y;
+mod alignment;
+mod app_settings;
+mod archive;
+mod autosave;
+mod bom;
+mod cam;
+mod collision;
+mod control;
+mod cutting;
 mod design_graph;
+mod diagnostics;
+mod discovery;
+mod drawing;
+mod dry_run;
+mod eval_trace;
+pub mod engine;
+mod machine;
+mod milling;
+mod model_history;
+mod msla;
+mod palette;
+mod net;
+mod platform;
+mod plugins;
+mod presets;
+mod profiler;
+mod project;
+mod projector;
 mod renderer;
+mod resin;
+mod settings_file;
 mod fonts;
+mod gcode;
+mod graph_history;
+mod job;
+mod layout;
+mod layer_stats;
+mod sketch_preview;
+mod slicer;
+mod stock;
+mod support;
+mod toasts;
+mod trail;
+mod trapped;
+mod wizard;
+mod workspace;
 
 use crate::design_graph::{AllTemplates, UserState};
 use csgrs::{mesh::Mesh, sketch::Sketch, traits::CSG};
 use eframe::egui;
 use egui_node_graph2::GraphEditorState;
-use egui_plot::{Plot, PlotPoints, Line};
-use futures_channel::oneshot;
+use egui_plot::{HLine, Line, Plot, PlotPoints, Points, VLine};
 use geo::{Geometry, LineString};
 use glow::HasContext as _;
-use js_sys::Uint8Array;
-use log::Level;
-use nalgebra::{Matrix4, Perspective3, Point3, Translation3, UnitQuaternion, Vector3};
+use nalgebra::{Matrix4, Orthographic3, Perspective3, Point3, Translation3, UnitQuaternion, Vector3};
 use std::{
-    cell::RefCell,
+    cell::Cell,
     collections::{HashMap, HashSet},
     f32::consts::{FRAC_PI_2, PI},
-    future::Future,
     rc::Rc,
     sync::{Arc, Mutex},
 };
+use platform::{download_bytes, execute, install_unload_guard, now_ms, spawn_file_picker};
+use slicer::InfillType;
+#[cfg(target_arch = "wasm32")]
 use wasm_bindgen::{JsCast, prelude::*};
-use wasm_bindgen_futures::JsFuture;
-use web_sys::{Event, HtmlCanvasElement, HtmlInputElement, window};
 
 const INVALID_SCALE: Vector3<f32> = Vector3::new(-1.0, -1.0, -1.0);
 
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 enum Tool {
     Laser,
     Plasma,
@@ -71,6 +112,8 @@ struct ModelEntry {
     /// Desired user offset (mm) and last-applied offset.
     offset: Vector3<f32>,
     applied_offset: Vector3<f32>,
+    /// Slicing settings that differ from the global ones for this model.
+    overrides: slicer::SliceOverrides,
 }
 
 impl ModelEntry {
@@ -81,14 +124,17 @@ impl ModelEntry {
             applied_scale: Vector3::new(1.0, 1.0, 1.0),
             offset: Vector3::zeros(),
             applied_offset: Vector3::zeros(),
+            overrides: slicer::SliceOverrides::default(),
             mesh: base.clone(), // immediately rebuilt below
             base,
         }
     }
 
     /// Apply pending scale / offset if the user changed either parameter.
-    fn refresh(&mut self) {
-        if self.scale != self.applied_scale || self.offset != self.applied_offset {
+    /// Returns `true` if the mesh was rebuilt.
+    fn refresh(&mut self) -> bool {
+        let dirty = self.scale != self.applied_scale || self.offset != self.applied_offset;
+        if dirty {
             self.mesh = self
                 .base
                 .clone()
@@ -105,31 +151,19 @@ impl ModelEntry {
             self.applied_scale = self.scale;
             self.applied_offset = self.offset;
         }
+        dirty
     }
 }
 
-#[derive(Clone, Copy, PartialEq, Eq)]
-enum InfillType {
-    Linear,
-    Gyroid,
-    SchwarzP,
-    SchwarzD,
-}
-
-impl std::fmt::Display for InfillType {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        use InfillType::*;
-        write!(
-            f,
-            "{}",
-            match self {
-                Linear => "Linear",
-                Gyroid => "Gyroid",
-                SchwarzP => "Schwarz P",
-                SchwarzD => "Schwarz D",
-            }
-        )
-    }
+/// Everything the cached infill of one layer depends on, besides geometry.
+#[derive(PartialEq)]
+struct InfillKey {
+    index: usize,
+    global: slicer::InfillSettings,
+    pattern: InfillType,
+    perimeters: i32,
+    line_width: f64,
+    overrides: Vec<slicer::SliceOverrides>,
 }
 
 #[derive(Clone, Copy, PartialEq, Eq)]
@@ -139,16 +173,258 @@ enum Tab {
     Design,
 }
 
+/// Settings that are not persisted on their own, compared to detect edits.
+#[derive(Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+struct SettingsSnapshot {
+    layers: slicer::LayerSettings,
+    seam: slicer::SeamSettings,
+    adhesion: slicer::AdhesionSettings,
+    infill: slicer::InfillSettings,
+    supports: support::SupportSettings,
+    line_width: f64,
+    mill_ops: Vec<milling::Operation>,
+    #[serde(default)]
+    stock: Option<stock::Stock>,
+    #[serde(default)]
+    pause_heights: Vec<f32>,
+    #[serde(default = "default_simplify")]
+    simplify_tolerance: f64,
+    #[serde(default)]
+    retraction: slicer::RetractionSettings,
+    #[serde(default)]
+    fan: slicer::FanSettings,
+    #[serde(default)]
+    laser: gcode::LaserSettings,
+    #[serde(default)]
+    plasma: gcode::PlasmaSettings,
+    #[serde(default)]
+    extruder: gcode::ExtruderSettings,
+    #[serde(default)]
+    resin: resin::ResinSettings,
+    #[serde(default)]
+    cam: cam::CamSettings,
+    #[serde(default)]
+    mill: gcode::MillSettings,
+    #[serde(default)]
+    drill: gcode::DrillSettings,
+    #[serde(default, alias = "keep_out")]
+    fixtures: Vec<collision::Fixture>,
+    #[serde(default = "default_kerf")]
+    kerf: f32,
+    #[serde(default = "default_touch_off")]
+    touch_off: bool,
+    #[serde(default = "default_perimeters")]
+    perimeters: i32,
+    #[serde(default = "default_infill_type")]
+    infill_type: InfillType,
+    /// Endmill and drill width and length (mm).
+    #[serde(default = "default_tool_size")]
+    endmill_size: [f32; 2],
+    #[serde(default = "default_tool_size")]
+    drill_size: [f32; 2],
+}
+
+/// Contour simplification tolerance (mm): well below any tool's resolution.
+const DEFAULT_SIMPLIFY_MM: f64 = 0.01;
+/// Laser kerf until one is set (mm).
+const DEFAULT_KERF_MM: f32 = 0.1;
+const DEFAULT_PERIMETERS: i32 = 2;
+/// Width and length of the endmill and drill until they are set (mm).
+const DEFAULT_TOOL_SIZE_MM: [f32; 2] = [10.0, 60.0];
+
+fn default_simplify() -> f64 {
+    DEFAULT_SIMPLIFY_MM
+}
+
+fn default_kerf() -> f32 {
+    DEFAULT_KERF_MM
+}
+
+fn default_touch_off() -> bool {
+    true
+}
+
+fn default_perimeters() -> i32 {
+    DEFAULT_PERIMETERS
+}
+
+fn default_infill_type() -> InfillType {
+    InfillType::Linear
+}
+
+fn default_tool_size() -> [f32; 2] {
+    DEFAULT_TOOL_SIZE_MM
+}
+
+/// What changed since the active workspace was opened or last saved (the
+/// machine profile persists itself).
+#[derive(Default)]
+struct Dirty {
+    models: bool,
+    graph_saved: Option<u64>,
+    graph: bool,
+    settings_saved: Option<SettingsSnapshot>,
+    settings: bool,
+    /// Read by the `beforeunload` handler.
+    unload_guard: Rc<Cell<bool>>,
+}
+
+impl Dirty {
+    fn any(&self) -> bool {
+        self.models || self.graph || self.settings
+    }
+}
+
+/// Fixed-direction orthographic camera of one quad-view pane.
+#[derive(Clone, Copy)]
+struct OrthoView {
+    name: &'static str,
+    rotation: UnitQuaternion<f32>,
+    translation: egui::Vec2,
+    zoom: f32,
+}
+
+impl OrthoView {
+    fn new(name: &'static str, rotation: UnitQuaternion<f32>) -> Self {
+        Self {
+            name,
+            rotation,
+            translation: egui::Vec2::ZERO,
+            zoom: 1.0,
+        }
+    }
+}
+
+/// Standard viewing directions of the perspective view.
+#[derive(Clone, Copy)]
+enum Snap {
+    Front,
+    Back,
+    Left,
+    Right,
+    Top,
+    Bottom,
+}
+
+impl Snap {
+    const ALL: [Snap; 6] = [Snap::Front, Snap::Back, Snap::Left, Snap::Right, Snap::Top, Snap::Bottom];
+
+    fn name(self) -> &'static str {
+        match self {
+            Snap::Front => "Front",
+            Snap::Back => "Back",
+            Snap::Left => "Left",
+            Snap::Right => "Right",
+            Snap::Top => "Top",
+            Snap::Bottom => "Bottom",
+        }
+    }
+
+    fn rotation(self) -> UnitQuaternion<f32> {
+        let pitch = UnitQuaternion::from_axis_angle(&Vector3::x_axis(), -FRAC_PI_2); //  -90° about X  (Z-up ➜ Y-up)
+        match self {
+            Snap::Front => pitch,
+            Snap::Back => pitch * UnitQuaternion::from_axis_angle(&Vector3::z_axis(), PI), // 180° roll
+            Snap::Left => UnitQuaternion::from_axis_angle(&Vector3::y_axis(), FRAC_PI_2) * pitch, // +90° yaw
+            Snap::Right => UnitQuaternion::from_axis_angle(&Vector3::y_axis(), -FRAC_PI_2) * pitch, // –90° yaw
+            Snap::Top => UnitQuaternion::identity(),
+            Snap::Bottom => UnitQuaternion::from_axis_angle(&Vector3::x_axis(), PI), // look from below
+        }
+    }
+}
+
+/// Kinds of extruder path the slice view colours, each of which can be hidden.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Feature {
+    OuterWall,
+    InnerWall,
+    SolidInfill,
+    SparseInfill,
+    Support,
+    Travel,
+}
+
+impl Feature {
+    const ALL: [Feature; 6] = [
+        Feature::OuterWall,
+        Feature::InnerWall,
+        Feature::SolidInfill,
+        Feature::SparseInfill,
+        Feature::Support,
+        Feature::Travel,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Feature::OuterWall => "Outer wall",
+            Feature::InnerWall => "Inner wall",
+            Feature::SolidInfill => "Solid infill",
+            Feature::SparseInfill => "Sparse infill",
+            Feature::Support => "Support",
+            Feature::Travel => "Travel",
+        }
+    }
+
+    const fn colour(self) -> [f32; 3] {
+        match self {
+            Feature::OuterWall => [1.0, 0.6, 0.1],
+            Feature::InnerWall => [0.3, 0.55, 1.0],
+            Feature::SolidInfill => [0.9, 0.3, 0.3],
+            Feature::SparseInfill => [0.9, 0.8, 0.2],
+            Feature::Support => [0.2, 0.8, 0.9],
+            Feature::Travel => [0.6, 0.6, 0.65],
+        }
+    }
+}
+
+/// Destructive action waiting for the user to confirm.
+#[derive(Clone, Copy)]
+enum Confirm {
+    ClearGraph,
+    RemoveModel(usize),
+    /// Closing the desktop window (the browser asks on its own).
+    Quit,
+    CloseWorkspace(usize),
+}
+
 pub struct AluminaApp {
     rotation: UnitQuaternion<f32>,
     translation: egui::Vec2,
     zoom: f32,
+    /// Show top / front / right / perspective panes instead of one view.
+    quad_view: bool,
+    ortho_views: [OrthoView; 3],
+    /// Timings / geometry overlay in the viewport.
+    show_profiler: bool,
+    profile: profiler::Profile,
+    autosave: autosave::Autosave,
+    palette: palette::Palette,
+    /// Arrangement of the docked panels.
+    layout: layout::Layout,
+    /// Tool, work area and view settings last written to storage.
+    persisted: app_settings::Persisted,
+    /// Other open projects; the active one lives in the fields above.
+    workspaces: workspace::Workspaces,
+    /// Machine setup wizard, while open.
+    wizard: Option<wizard::Wizard>,
+    /// Camera calibration, fiducials and the resulting work offset.
+    alignment: alignment::Alignment,
+    /// Controllers found on the network.
+    discovery: discovery::Discovery,
     /// All user-loaded models (plus the default one).
     models: Vec<ModelEntry>,
     /// Index of the *currently-selected* model in the sidebar (if any).
     selected_model: Option<usize>,
     workpiece_data: Arc<Mutex<Option<Vec<u8>>>>,
     model_data: Arc<Mutex<Option<Vec<u8>>>>,
+    plugin_data: Arc<Mutex<Option<Vec<u8>>>>,
+    /// Settings file picked for import.
+    settings_data: Arc<Mutex<Option<Vec<u8>>>>,
+    /// Bytes of a `.graph` file picked by the user.
+    graph_data: Arc<Mutex<Option<Vec<u8>>>>,
+    /// Bytes of a `.alumina` project file picked by the user.
+    project_data: Arc<Mutex<Option<Vec<u8>>>>,
+    show_plugins: bool,
     wireframe: bool,
     edges: bool,
     faces: bool,
@@ -157,42 +433,138 @@ pub struct AluminaApp {
     workarea: bool,
     /// CNC working area dimensions (mm)
     work_size: Vector3<f32>, // x, y, z
-    layer_height: f32,
+    /// Layer height, ranges and adaptive settings.
+    layer_settings: slicer::LayerSettings,
+    /// Cached layer stack; `None` after models or layer settings change.
+    layer_plan: Option<Vec<slicer::Layer>>,
     /// Index of the layer currently being inspected (0-based)
     current_layer: i32,
     /// `true` while the “tool-path” view is active
     show_slice: bool,
+    /// Visibility of each [`Feature`] in the slice view, in `Feature::ALL` order.
+    features_shown: [bool; Feature::ALL.len()],
     /// The last slice that was generated for `current_layer`
     sliced_layer: Option<Sketch<()>>,
+    /// Contour points closer than this (mm) to a straight line are dropped
+    /// from each slice before toolpaths are made from it.
+    simplify_tolerance: f64,
+    /// Points in the current slice before and after simplifying.
+    simplify_stats: Option<slicer::SimplifyStats>,
+    /// Raw material the parts are cut from.
+    stock: Option<stock::Stock>,
+    /// Clamps, vise jaws and pins on the bed: never sliced, kept clear of.
+    fixtures: Vec<collision::Fixture>,
+    /// Sketch sent from the design graph; the 2-D tools cut it as is
+    /// instead of slicing the models.
+    flat_part: Option<Sketch<()>>,
+    /// Slice along `plane_normal · p = plane_offset` instead of the layer stack.
+    custom_plane: bool,
+    plane_normal: Vector3<f32>,
+    plane_offset: f32,
+    /// Outlines of the custom-plane cut in world coordinates.
+    plane_slice: Vec<Vec<Vector3<f32>>>,
     gpu: Option<Arc<Mutex<renderer::GpuLines>>>,
     gpu_faces: Option<Arc<Mutex<renderer::GpuLines>>>,
     vertex_storage: Vec<f32>,
     selected_tab: Tab,
     diag_poll: bool,
     diag_led: bool,
-    // Diagnostics – per‑GPIO desired state (false = low)
-    diag_d0:bool,diag_d1:bool,diag_d2:bool,diag_d3:bool,
-    diag_d4:bool,diag_d5:bool,diag_d6:bool,diag_d7:bool,
-    diag_d9:bool,diag_d11:bool,diag_d12:bool,diag_d13:bool,
+    /// Board description (pin map, …); persisted to localStorage.
+    machine: machine::MachineProfile,
+    // Diagnostics – per‑GPIO desired state, keyed by pin name (false = low / untracked)
+    diag_pin_on: HashMap<String, bool>,
+    // Last value written to each analog output pin
+    diag_pin_level: HashMap<String, u16>,
     selected_tool: Tool,
+    /// Saved named tool settings.
+    presets: presets::Presets,
     // Laser
     kerf: f32,
+    laser: gcode::LaserSettings,
     // Plasma
     touch_off: bool,
+    plasma: gcode::PlasmaSettings,
+    // Laser / plasma: cut shared edges of nested parts once
+    common_line: bool,
+    /// Merged cut paths of the current slice (when `common_line` is on).
+    cut_paths: Option<(Vec<LineString<f64>>, cutting::MergeStats)>,
+    /// Order loops / drill points for short rapids (inner contours first).
+    optimize_order: bool,
+    /// Sequenced operations and the rapid length of the unoptimised order.
+    cut_plan: Option<(cutting::CutPlan, f64)>,
     // Extruder
     perimeters: i32,
     infill_type: InfillType,
+    seam: slicer::SeamSettings,
+    adhesion: slicer::AdhesionSettings,
+    retraction: slicer::RetractionSettings,
+    fan: slicer::FanSettings,
+    /// Part-fan speed last set by hand (%).
+    fan_manual: f32,
+    /// The inspected layer as the print job has it: loop order and
+    /// travels, infill, supports and skirt / brim.
+    print_preview: Option<engine::PrintLayer>,
+    layer_stats: layer_stats::StatsTable,
+    sketch_preview: sketch_preview::SketchPreview,
+    /// Design sidebar node search text.
+    node_search: String,
+    /// Checkpoints of the design graph, one per Apply.
+    graph_history: graph_history::GraphHistory,
+    model_history: model_history::ModelHistory,
+    eval_trace: eval_trace::EvalTrace,
+    /// Files the Export Mesh nodes produced in the last Apply.
+    graph_exports: Vec<design_graph::MeshExport>,
+    bom: bom::Bom,
+    /// Where the graph editor was drawn last frame.
+    graph_rect: egui::Rect,
+    /// Extrusion width (mm); spacing of loops and fill lines.
+    line_width: f64,
+    infill_settings: slicer::InfillSettings,
+    /// Fill paths of the inspected layer, keyed by the inputs they came from.
+    infill: Option<(InfillKey, slicer::Infill)>,
+    support_settings: support::SupportSettings,
+    /// Generated supports and the settings they were built with.
+    supports: Option<(support::SupportSettings, support::Supports)>,
+    /// Anchors of regions the user switched off; re-applied after regeneration.
+    support_disabled: Vec<Vector3<f32>>,
+    /// Skirt / brim / raft for the current slice; only set on layer 0.
+    adhesion_paths: Option<slicer::Adhesion>,
+    /// Temperatures, filament and speeds of generated extruder jobs.
+    extruder: gcode::ExtruderSettings,
     // Endmill
     endmill_width: f32,
     endmill_length: f32,
+    /// Holder the endmill sticks out of, checked by the simulation.
+    holder: collision::Holder,
+    /// Milling operations run in order, each on the stock the last one left.
+    mill_ops: Vec<milling::Operation>,
+    mill_results: Option<Vec<milling::OperationResult>>,
+    /// Part height map the results were computed against (for rest display).
+    mill_part: Option<milling::HeightMap>,
+    /// Material removal simulation of the loaded job.
+    mill_sim: Option<engine::Simulation>,
+    /// 2.5-D profile / pocket settings and the feeds of endmill jobs.
+    cam: cam::CamSettings,
+    mill: gcode::MillSettings,
+    /// Passes computed around the current layer's outline, and the material
+    /// surface they step down from.
+    cam_paths: Option<(Vec<Vec<[f64; 3]>>, f64)>,
+    /// Sealed voids found in the resin layers, once checked.
+    trapped: Option<Vec<engine::TrappedVolume>>,
     // Drill
     drill_width: f32,
     drill_length: f32,
+    /// Pecking, depths and feeds of the canned cycles.
+    drill: gcode::DrillSettings,
     // DLP / LCD
     pixels_wide: i32,
     pixels_tall: i32,
     layer_delay: f32,
     peel_distance: f32,
+    /// Exposure, lift speed and per-layer overrides of resin jobs.
+    resin: resin::ResinSettings,
+    /// Mask window and layer run for direct projection.
+    projector: projector::Projector,
     design_state: GraphEditorState<
         design_graph::NodeData,
         design_graph::DType,
@@ -201,16 +573,80 @@ pub struct AluminaApp {
         UserState,
     >,
     design_user_state: UserState,
+    /// Live job overrides in percent of the programmed value.
+    feed_override: u32,
+    power_override: u32,
+    flow_override: u32,
+    /// Digital readout: polling switch, outstanding query and last report.
+    dro_poll: bool,
+    dro_reply: Option<net::Pending>,
+    dro_last_poll: f64,
+    dro_pos: Option<control::Position>,
+    /// GRBL's last reported work coordinate offset.
+    dro_wco: Option<[f64; 4]>,
+    /// Time between position queries (ms), when the last report arrived and
+    /// why the last query failed, if it did.
+    dro_interval_ms: f64,
+    dro_updated: f64,
+    dro_error: Option<String>,
+    /// Reported positions of the running job over its planned path.
+    trail: trail::Trail,
+    /// Machine the job goes to instead of the real one in a dry run.
+    dry_run: dry_run::VirtualMachine,
+    /// Modal G-code state used to validate moves against the travel limits.
+    motion: machine::MotionTracker,
+    limit_warning: Option<String>,
+    goto_target: [f64; 3],
+    /// Per-axis homed flag (X, Y, Z) and the homing cycle under way.
+    homed: [bool; 3],
+    homing: Option<control::Homing>,
+    test_fire: control::TestFire,
+    jog: control::Jog,
+    /// On/off state of each `machine.aux_outputs` entry.
+    aux_state: Vec<bool>,
+    /// G-code job being streamed, and bytes of a job file picked by the user.
+    jobs: job::JobQueue,
+    job_data: Arc<Mutex<Option<Vec<u8>>>>,
+    /// Last value written to `document.title`.
+    page_title: String,
+    /// Sum of babysteps sent this session (mm).
+    babystep_total: f64,
+    /// Layer list typed into the job panel for manual M600 insertion.
+    pause_layers: String,
+    /// Extruder: stop before the first layer above each height (mm) to
+    /// embed nuts, magnets, …
+    pause_heights: Vec<f32>,
+    filament_change: control::FilamentChange,
+    /// Interrupted job waiting for the operator to resume or discard it.
+    recovery: Option<control::Recovery>,
     diag_console: String, // Text console buffer (read-only UI)
+    toasts: toasts::Toasts,
+    dirty: Dirty,
+    confirm: Option<Confirm>,
+    /// The user chose to quit despite unsaved changes.
+    quit_confirmed: bool,
 	// Per-pin series: "D0", "D1", ...
     diag_series: HashMap<String, Vec<[f64;2]>>,
     // Latest sample from /pins (name -> 0.0/1.0)
     diag_last_pins: Arc<Mutex<Option<HashMap<String, f64>>>>,
 	last_poll_ms: f64,
+    /// Source text of the diagnostic test script (see `diagnostics`).
+    diag_script: String,
+    diag_runner: diagnostics::ScriptRunner,
+    /// Pending reply of a `bus_scan` request and the last decoded result.
+    diag_scan_reply: Option<net::Pending>,
+    diag_last_scan: Option<diagnostics::BusScan>,
+    /// Single-shot trigger capture over the pin samples.
+    diag_capture: diagnostics::Capture,
+    /// Periodic ping of the firmware for RTT / loss monitoring.
+    diag_health: diagnostics::HealthMonitor,
+    /// Series streamed by the firmware, plotted with the pins.
+    diag_telemetry: diagnostics::Telemetry,
 }
 
 impl AluminaApp {
-    pub fn new(cc: &eframe::CreationContext<'_>) -> Self {		
+    pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
+        plugins::restore();
         let mut entry =
             ModelEntry::new("icosahedron", Mesh::<()>::icosahedron(100.0, None).float());
         entry.refresh();
@@ -224,17 +660,44 @@ impl AluminaApp {
         //       zoom = 3 · tan(fov / 2)  ≈ 1.732 …
         //     Using a touch more distance (1.75) leaves a 2–3 % safety margin.
         // ------------------------------------------------------------------
+        let shared_design = graph_from_location();
         let front_rot = UnitQuaternion::from_axis_angle(&Vector3::x_axis(), -FRAC_PI_2); // “Front”
         let initial_zoom = 1.75_f32;
+        let discovery = discovery::Discovery::default();
+        discovery.start();
+        let machine = machine::MachineProfile::load();
+        net::configure(&machine.connection);
 
-        Self {
+        let mut app = Self {
             rotation: front_rot,
+            quad_view: false,
+            autosave: autosave::Autosave::start(),
+            palette: palette::Palette::default(),
+            layout: layout::Layout::load(),
+            persisted: app_settings::Persisted::default(),
+            workspaces: workspace::Workspaces::default(),
+            wizard: (!machine::MachineProfile::is_saved())
+                .then(|| wizard::Wizard::new(&machine::MachineProfile::default(), Tool::Laser)),
+            alignment: alignment::Alignment::default(),
+            discovery,
+            show_profiler: false,
+            profile: profiler::Profile::default(),
+            ortho_views: [
+                OrthoView::new("Top", UnitQuaternion::identity()),
+                OrthoView::new("Front", front_rot),
+                OrthoView::new("Right", UnitQuaternion::from_axis_angle(&Vector3::y_axis(), -FRAC_PI_2) * front_rot),
+            ],
             translation: egui::Vec2::new(0.0, -250.0),
             zoom: initial_zoom,
             models: vec![entry],
             selected_model: Some(0),
             workpiece_data: Arc::new(Mutex::new(None)),
             model_data: Arc::new(Mutex::new(None)),
+            plugin_data: Arc::new(Mutex::new(None)),
+            settings_data: Arc::new(Mutex::new(None)),
+            graph_data: Arc::new(Mutex::new(None)),
+            project_data: Arc::new(Mutex::new(None)),
+            show_plugins: false,
             wireframe: true,
             edges: true,
             faces: true,
@@ -242,39 +705,148 @@ impl AluminaApp {
             vertices: true,
             workarea: true,
             work_size: Vector3::new(200.0, 200.0, 200.0),
-            layer_height: 0.20,
+            layer_settings: slicer::LayerSettings::default(),
+            layer_plan: None,
             current_layer: 0,
             show_slice: false,
+            features_shown: [true; Feature::ALL.len()],
             sliced_layer: None,
+            simplify_tolerance: DEFAULT_SIMPLIFY_MM,
+            simplify_stats: None,
+            flat_part: None,
+            stock: None,
+            fixtures: Vec::new(),
+            custom_plane: false,
+            plane_normal: Vector3::x(),
+            plane_offset: 0.0,
+            plane_slice: Vec::new(),
             gpu: None,
             gpu_faces: None,
             vertex_storage: Vec::new(),
-            selected_tab: Tab::Control,
+            selected_tab: if shared_design.is_some() { Tab::Design } else { Tab::Control },
             diag_poll: false,
             diag_led: false,
-            diag_d0:false,diag_d1:false,diag_d2:false,diag_d3:false,
-			diag_d4:false,diag_d5:false,diag_d6:false,diag_d7:false,
-			diag_d9:false,diag_d11:false,diag_d12:false,diag_d13:false,
+            machine,
+            diag_pin_on: HashMap::new(),
+            diag_pin_level: HashMap::new(),
             selected_tool: Tool::Laser, // default
-            kerf: 0.1,
-            touch_off: true,
-            perimeters: 2,
-            infill_type: InfillType::Linear,
-            endmill_width: 10.0,
-            endmill_length: 60.0,
-            drill_width: 10.0,
-            drill_length: 60.0,
+            presets: presets::Presets::load(),
+            kerf: DEFAULT_KERF_MM,
+            laser: gcode::LaserSettings::default(),
+            plasma: gcode::PlasmaSettings::default(),
+            touch_off: default_touch_off(),
+            common_line: false,
+            cut_paths: None,
+            optimize_order: true,
+            cut_plan: None,
+            perimeters: DEFAULT_PERIMETERS,
+            infill_type: default_infill_type(),
+            seam: slicer::SeamSettings::default(),
+            adhesion: slicer::AdhesionSettings::default(),
+            retraction: slicer::RetractionSettings::default(),
+            fan: slicer::FanSettings::default(),
+            fan_manual: 0.0,
+            print_preview: None,
+            layer_stats: layer_stats::StatsTable::default(),
+            sketch_preview: sketch_preview::SketchPreview::default(),
+            node_search: String::new(),
+            graph_history: graph_history::GraphHistory::default(),
+            model_history: model_history::ModelHistory::default(),
+            eval_trace: eval_trace::EvalTrace::default(),
+            graph_exports: Vec::new(),
+            bom: bom::Bom::default(),
+            graph_rect: egui::Rect::NOTHING,
+            line_width: 0.45,
+            infill_settings: slicer::InfillSettings::default(),
+            infill: None,
+            support_settings: support::SupportSettings::default(),
+            supports: None,
+            support_disabled: Vec::new(),
+            adhesion_paths: None,
+            extruder: gcode::ExtruderSettings::default(),
+            endmill_width: DEFAULT_TOOL_SIZE_MM[0],
+            endmill_length: DEFAULT_TOOL_SIZE_MM[1],
+            holder: collision::Holder::default(),
+            mill_ops: vec![
+                milling::Operation {
+                    tool_diameter: 6.0,
+                    stepdown: 1.0,
+                    stepover: 0.5,
+                },
+                milling::Operation {
+                    tool_diameter: 2.0,
+                    stepdown: 0.5,
+                    stepover: 0.4,
+                },
+            ],
+            mill_results: None,
+            mill_part: None,
+            mill_sim: None,
+            cam: cam::CamSettings::default(),
+            mill: gcode::MillSettings::default(),
+            cam_paths: None,
+            trapped: None,
+            drill_width: DEFAULT_TOOL_SIZE_MM[0],
+            drill_length: DEFAULT_TOOL_SIZE_MM[1],
+            drill: gcode::DrillSettings::default(),
             pixels_wide: 2048,
             pixels_tall: 1024,
             layer_delay: 2.0,
             peel_distance: 15.0,
-            design_state: GraphEditorState::default(),
+            resin: resin::ResinSettings::default(),
+            projector: projector::Projector::default(),
+            design_state: shared_design.unwrap_or_default(),
             design_user_state: UserState::default(),
+            feed_override: 100,
+            power_override: 100,
+            flow_override: 100,
+            dro_poll: false,
+            dro_reply: None,
+            dro_last_poll: 0.0,
+            dro_pos: None,
+            dro_wco: None,
+            dro_interval_ms: 200.0,
+            dro_updated: 0.0,
+            dro_error: None,
+            trail: trail::Trail::default(),
+            dry_run: dry_run::VirtualMachine::default(),
+            motion: machine::MotionTracker::default(),
+            limit_warning: None,
+            goto_target: [0.0; 3],
+            homed: [false; 3],
+            homing: None,
+            test_fire: control::TestFire::default(),
+            jog: control::Jog::default(),
+            aux_state: Vec::new(),
+            jobs: job::JobQueue::default(),
+            job_data: Arc::new(Mutex::new(None)),
+            page_title: String::new(),
+            babystep_total: 0.0,
+            pause_layers: String::new(),
+            pause_heights: Vec::new(),
+            filament_change: control::FilamentChange::default(),
+            recovery: job::Job::load_interrupted().map(|(job, line)| control::Recovery::new(job, line)),
             diag_console: String::new(),
+            toasts: toasts::Toasts::default(),
+            dirty: Dirty {
+                unload_guard: install_unload_guard(),
+                ..Dirty::default()
+            },
+            confirm: None,
+            quit_confirmed: false,
 			diag_series: HashMap::new(),
 			diag_last_pins: Arc::new(Mutex::new(None)),
 			last_poll_ms: 0.0,
-        }
+            diag_script: diagnostics::DEFAULT_SCRIPT.to_owned(),
+            diag_runner: diagnostics::ScriptRunner::default(),
+            diag_scan_reply: None,
+            diag_last_scan: None,
+            diag_capture: diagnostics::Capture::default(),
+            diag_health: diagnostics::HealthMonitor::default(),
+            diag_telemetry: diagnostics::Telemetry::default(),
+        };
+        app.restore_app_settings();
+        app
     }
     
     /// Ensure `selected_model` is within bounds or `None` if there are no models.
@@ -290,224 +862,1249 @@ impl AluminaApp {
 
     /// Refresh *all* models (each entry decides whether it needs to rebuild).
     fn refresh_models(&mut self) {
+        let mut changed = false;
         for m in &mut self.models {
-            m.refresh();
-        }
-    }
-
-    /// Re-builds `sliced_layer` for the current Z level.
-    fn refresh_slice(&mut self) {
-        if !self.show_slice {
-            return;
+            changed |= m.refresh();
         }
-
-        // slice a *union* of all models
-        let z = self.current_layer as f32 * self.layer_height;
-        let plane = csgrs::mesh::plane::Plane::from_normal(Vector3::z(), z.into());
-        let mut iter = self.models.iter();
-        if let Some(first) = iter.next() {
-            let mut combined = first.mesh.clone();
-            for m in iter {
-                combined = combined.union(&m.mesh);
-            }
-            self.sliced_layer = Some(combined.slice(plane));
+        if changed {
+            self.dirty.models = true;
+            self.invalidate_layers();
         }
     }
 
-    /// Marks `model` as dirty so that next frame will rebuild
-    fn invalidate_selected_model(&mut self) {
-        if let Some(m) = self.sel_mut() {
-            m.applied_scale = INVALID_SCALE;
-            m.applied_offset = Vector3::repeat(f32::NAN);
+    fn settings_snapshot(&self) -> SettingsSnapshot {
+        SettingsSnapshot {
+            layers: self.layer_settings.clone(),
+            seam: self.seam.clone(),
+            adhesion: self.adhesion.clone(),
+            infill: self.infill_settings.clone(),
+            supports: self.support_settings.clone(),
+            line_width: self.line_width,
+            mill_ops: self.mill_ops.clone(),
+            stock: self.stock.clone(),
+            pause_heights: self.pause_heights.clone(),
+            simplify_tolerance: self.simplify_tolerance,
+            retraction: self.retraction.clone(),
+            fan: self.fan.clone(),
+            laser: self.laser.clone(),
+            plasma: self.plasma.clone(),
+            extruder: self.extruder.clone(),
+            resin: self.resin.clone(),
+            cam: self.cam.clone(),
+            mill: self.mill.clone(),
+            drill: self.drill.clone(),
+            fixtures: self.fixtures.clone(),
+            kerf: self.kerf,
+            touch_off: self.touch_off,
+            perimeters: self.perimeters,
+            infill_type: self.infill_type,
+            endmill_size: [self.endmill_width, self.endmill_length],
+            drill_size: [self.drill_width, self.drill_length],
         }
     }
 
-    /// Replace currently-selected entry’s *base* geometry.
-    fn set_selected_base(&mut self, mesh: Mesh<()>, name: String) {
-        if let Some(m) = self.sel_mut() {
-            m.base = mesh;
-            m.name = name;
-            self.invalidate_selected_model();
-            self.refresh_models();
-            self.refresh_slice();
-        }
+    fn apply_settings(&mut self, s: SettingsSnapshot) {
+        self.layer_settings = s.layers;
+        self.seam = s.seam;
+        self.adhesion = s.adhesion;
+        self.infill_settings = s.infill;
+        self.support_settings = s.supports;
+        self.line_width = s.line_width;
+        self.mill_ops = s.mill_ops;
+        self.stock = s.stock;
+        self.pause_heights = s.pause_heights;
+        self.simplify_tolerance = s.simplify_tolerance;
+        self.retraction = s.retraction;
+        self.fan = s.fan;
+        self.laser = s.laser;
+        self.plasma = s.plasma;
+        self.extruder = s.extruder;
+        self.resin = s.resin;
+        self.cam = s.cam;
+        self.mill = s.mill;
+        self.drill = s.drill;
+        self.fixtures = s.fixtures;
+        self.kerf = s.kerf;
+        self.touch_off = s.touch_off;
+        self.perimeters = s.perimeters;
+        self.infill_type = s.infill_type;
+        [self.endmill_width, self.endmill_length] = s.endmill_size;
+        [self.drill_width, self.drill_length] = s.drill_size;
+        self.mill_results = None;
+        self.invalidate_layers();
+        self.refresh_slice();
     }
 
-    /// convenience: currently-selected entry (mutable)
-    fn sel_mut(&mut self) -> Option<&mut ModelEntry> {
-        self.selected_model
-            .and_then(move |i| self.models.get_mut(i))
+    /// Compare graph and settings with their saved state and publish the
+    /// result to the page-unload guard.
+    fn track_dirty(&mut self) {
+        let graph = design_graph::fingerprint(&self.design_state.graph);
+        self.dirty.graph = *self.dirty.graph_saved.get_or_insert(graph) != graph;
+        let settings = self.settings_snapshot();
+        let saved = self.dirty.settings_saved.get_or_insert_with(|| settings.clone());
+        self.dirty.settings = *saved != settings;
+        self.dirty.unload_guard.set(self.unsaved_anywhere());
     }
 
-    /// Add a *new* model and make it the selection.
-    fn add_model(&mut self, mesh: Mesh<()>, name: String) {
-        let mut e = ModelEntry::new(name, mesh);
-        e.refresh();
-        self.models.push(e);
-        self.selected_model = Some(self.models.len() - 1);
-        self.refresh_slice();
+    /// Unsaved changes in the active workspace, a parked one or the settings.
+    fn unsaved_anywhere(&self) -> bool {
+        self.dirty.any() || self.workspaces.parked_dirty()
     }
-    
-    fn diag_log(&mut self, line: impl Into<String>) {
-        if !self.diag_console.is_empty() { self.diag_console.push('\n'); }
-        self.diag_console.push_str(&line.into());
+
+    /// Run `action` now, or ask first when it would discard unsaved work.
+    fn guarded(&mut self, action: Confirm) {
+        let unsaved = match action {
+            Confirm::ClearGraph => self.dirty.graph,
+            Confirm::RemoveModel(_) => self.dirty.models,
+            Confirm::Quit => self.unsaved_anywhere(),
+            Confirm::CloseWorkspace(i) => self.workspace_dirty(i),
+        };
+        if unsaved {
+            self.confirm = Some(action);
+        } else {
+            self.perform(action);
+        }
     }
-    fn diag_push_point_named(&mut self, name: &str, x: f64, y: f64) {
-        self.diag_series.entry(name.to_string()).or_default().push([x, y]);
+
+    fn perform(&mut self, action: Confirm) {
+        match action {
+            Confirm::ClearGraph => {
+                self.design_state = GraphEditorState::default();
+                self.dirty.graph_saved = None;
+            }
+            Confirm::RemoveModel(idx) => {
+                if idx < self.models.len() {
+                    let entry = self.models.remove(idx);
+                    self.record_model_removed(idx, entry);
+                    self.invalidate_layers();
+                    self.clamp_selection();
+                }
+            }
+            Confirm::Quit => self.quit_confirmed = true,
+            Confirm::CloseWorkspace(i) => self.close_workspace(i),
+        }
     }
 
-    /// Kick one async GET /pins, store as HashMap<String, f64> in `target`.
-    fn poll_pins_once(target: Arc<Mutex<Option<HashMap<String, f64>>>>) {
-        execute(async move {
-            match http_get_text("/pins").await {
-                Ok(body) => {
-                    // Expect shape: {"D0":0, "D1":1, ...}
-                    let parsed: Result<HashMap<String, f64>, _> = serde_json::from_str::<HashMap<String, serde_json::Value>>(&body)
-                        .map(|m| {
-                            m.into_iter()
-                                .filter_map(|(k, v)| v.as_f64().or_else(|| v.as_u64().map(|u| u as f64)).map(|f| (k, f)))
-                                .collect()
-                        });
-                    match parsed {
-                        Ok(map) => { *target.lock().unwrap() = Some(map); }
-                        Err(e) => log::error!("parse /pins failed: {:?}", e),
+    fn confirm_dialog(&mut self, ctx: &egui::Context) {
+        let Some(action) = self.confirm else { return };
+        let what = match action {
+            Confirm::ClearGraph => "Clear the design graph?".to_owned(),
+            Confirm::RemoveModel(i) => format!(
+                "Remove model “{}”?",
+                self.models.get(i).map_or("", |m| m.name.as_str())
+            ),
+            Confirm::Quit => "Quit Alumina?".to_owned(),
+            Confirm::CloseWorkspace(_) => format!("Close workspace “{}”?", self.workspaces.active_name()),
+        };
+        let mut answer = None;
+        egui::Window::new("Unsaved changes")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                ui.label(what);
+                ui.label("Changes that have not been saved will be lost.");
+                ui.horizontal(|ui| {
+                    if ui.button("Discard").clicked() {
+                        answer = Some(true);
                     }
-                }
-                Err(e) => log::error!("poll /pins failed: {:?}", e),
+                    if ui.button("Cancel").clicked() || ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                        answer = Some(false);
+                    }
+                });
+            });
+        if let Some(discard) = answer {
+            self.confirm = None;
+            if discard {
+                self.perform(action);
             }
-        });
+        }
     }
-    
-    fn is_pin_checked(&self, name: &str) -> bool {
-        match name {
-            "D0"  => self.diag_d0,
-            "D1"  => self.diag_d1,
-            "D2"  => self.diag_d2,
-            "D3"  => self.diag_d3,
-            "D4"  => self.diag_d4,
-            "D5"  => self.diag_d5,
-            "D6"  => self.diag_d6,
-            "D7"  => self.diag_d7,
-            "D9"  => self.diag_d9,
-            "D11" => self.diag_d11,
-            "D12" => self.diag_d12,
-            "D13" => self.diag_d13,
-            _ => false,
-        }
-	}
-}
 
-impl AluminaApp {
-    /// (Re-)builds the VBO if the model, grid or scale changed.
-    unsafe fn sync_buffers(&mut self, gl: &glow::Context) {
-        self.vertex_storage.clear();
-        let mut faces: Vec<f32> = Vec::new();
+    /// Forget everything derived from the layer stack.
+    fn invalidate_layers(&mut self) {
+        self.layer_plan = None;
+        self.infill = None;
+        self.supports = None;
+        self.mill_results = None;
+        self.mill_sim = None;
+        self.cam_paths = None;
+        self.trapped = None;
+    }
 
-        // ── 1) grid (10 mm spacing, ±work_size/2) ───────────────────────
-        if self.workarea {
-            let minor = [0.55, 0.55, 0.55];
-            let major = [1.0, 1.0, 1.0];
+    /// (Re-)generate supports when enabled and out of date.
+    fn refresh_supports(&mut self) {
+        let wanted = self.support_settings.enabled && matches!(self.selected_tool, Tool::Extruder | Tool::DlpLcd);
+        if !wanted {
+            self.supports = None;
+            return;
+        }
+        if self.supports.as_ref().is_some_and(|(s, _)| *s == self.support_settings) {
+            return;
+        }
+        let mut supports = engine::supports(&self.support_settings, self.models.iter().map(|m| &m.mesh), self.line_width as f32);
+        for r in &mut supports.regions {
+            r.enabled = !self.support_disabled.iter().any(|a| (a - r.anchor).norm() < 0.5);
+        }
+        self.supports = Some((self.support_settings.clone(), supports));
+    }
 
-            let hx = self.work_size.x * 0.5;
-            let hy = self.work_size.y * 0.5;
-            let hz = self.work_size.z;
+    /// Toggle the support region drawn nearest to a click in the viewport.
+    /// Main (orbiting, perspective) viewport in `rect`.
+    fn perspective_pane(&mut self, ui: &mut egui::Ui, rect: egui::Rect) {
+        let response = ui.interact(rect, ui.id().with("perspective_pane"), egui::Sense::click_and_drag());
+        let keys = a11y::viewport_pane(ui, &response, "3-D", true);
+        if keys.reset {
+            self.rotation = Snap::Front.rotation();
+            self.translation = egui::Vec2::new(0.0, -250.0);
+            self.zoom = 1.75;
+        }
+        self.rotation = UnitQuaternion::from_axis_angle(&Vector3::y_axis(), keys.orbit.x)
+            * UnitQuaternion::from_axis_angle(&Vector3::x_axis(), keys.orbit.y)
+            * self.rotation;
+        self.translation += keys.pan;
+        self.zoom = (self.zoom * keys.zoom).clamp(0.1, 500.0);
+        if keys.any() {
+            ui.ctx().request_repaint();
+        }
 
-            // vertical (X) lines
-            for i in 0..=(self.work_size.x / 10.0) as i32 {
-                let x = -hx + i as f32 * 10.0;
-                let col = if i % 10 == 0 { major } else { minor };
-                self.vertex_storage.extend_from_slice(&[
-                    x, -hy, 0.0, col[0], col[1], col[2], x, hy, 0.0, col[0], col[1], col[2],
-                ]);
+        // click → switch the support region under the pointer on / off
+        if response.clicked() && self.supports.is_some() {
+            if let Some(pos) = response.interact_pointer_pos() {
+                self.pick_support(rect, pos);
             }
+        }
 
-            // horizontal (Y) lines
-            for i in 0..=(self.work_size.y / 10.0) as i32 {
-                let y = -hy + i as f32 * 10.0;
-                let col = if i % 10 == 0 { major } else { minor };
-                self.vertex_storage.extend_from_slice(&[
-                    -hx, y, 0.0, col[0], col[1], col[2], hx, y, 0.0, col[0], col[1], col[2],
-                ]);
+        // ───── Interaction ─────
+        if response.dragged() {
+            let delta = response.drag_delta();
+            let input = ui.input(|i| i.clone());
+            if input.pointer.primary_down() {
+                // left‑drag → rotate
+                let yaw = delta.x * 0.01;
+                let pitch = delta.y * 0.01;
+                self.rotation = UnitQuaternion::from_axis_angle(&Vector3::y_axis(), yaw)
+                    * UnitQuaternion::from_axis_angle(&Vector3::x_axis(), pitch)
+                    * self.rotation;
+            } else if input.pointer.middle_down() {
+                // middle‑drag → pan
+                self.translation += -delta;
             }
+        }
 
-            // outline the remaining cuboid edges
-            let edge = major;
+        if response.hovered() {
+            // pinch-to-zoom: `zoom_delta` is multiplicative, 1.0 == no change;
+            // >1 → fingers move apart → zoom-in (move camera closer)
+            let pinch = ui.input(|i| i.zoom_delta());
+            if (pinch - 1.0).abs() > f32::EPSILON {
+                self.zoom = (self.zoom / pinch).clamp(0.1, 500.0);
+            }
 
-            //  four vertical edges
-            for (sx, sy) in [(-1.0, -1.0), (-1.0, 1.0), (1.0, -1.0), (1.0, 1.0)] {
-                let x = sx * hx;
-                let y = sy * hy;
-                self.vertex_storage.extend_from_slice(&[
-                    x, y, 0.0, edge[0], edge[1], edge[2], // bottom
-                    x, y, hz, edge[0], edge[1], edge[2], // top
-                ]);
+            // scroll → zoom
+            let scroll = ui.input(|i| i.raw_scroll_delta.y);
+            if scroll.abs() > 0.0 {
+                self.zoom = (self.zoom * (1.0 + scroll * 0.001)).clamp(0.0, 500.0);
             }
+        }
 
-            //  top rectangle (Z = hz)
-            self.vertex_storage.extend_from_slice(&[
-                -hx, -hy, hz, edge[0], edge[1], edge[2], hx, -hy, hz, edge[0], edge[1], edge[2],
-                hx, -hy, hz, edge[0], edge[1], edge[2], hx, hy, hz, edge[0], edge[1], edge[2], hx,
-                hy, hz, edge[0], edge[1], edge[2], -hx, hy, hz, edge[0], edge[1], edge[2], -hx, hy,
-                hz, edge[0], edge[1], edge[2], -hx, -hy, hz, edge[0], edge[1], edge[2],
-            ]);
+        let m = mvp(self, rect);
+        self.paint_scene(ui, rect, m);
+        a11y::focus_ring(ui, &response);
+        if self.quad_view {
+            pane_label(ui, rect, "Perspective");
         }
+    }
 
-        // ── 2) model / slice ──────────────────────────────────────────────
-        fn add_line_string(ls: &LineString<f64>, z: f32, col: [f32; 3], out: &mut Vec<f32>) {
-            for w in ls.0.windows(2) {
-                let a = w[0];
-                let b = w[1];
-                out.extend_from_slice(&[
-                    a.x as f32, a.y as f32, z, col[0], col[1], col[2], b.x as f32, b.y as f32, z,
-                    col[0], col[1], col[2],
-                ]);
+    /// Orthographic quad-view pane `k`: drag pans, scroll zooms.
+    fn ortho_pane(&mut self, ui: &mut egui::Ui, k: usize, rect: egui::Rect) {
+        let response = ui.interact(rect, ui.id().with(("ortho_pane", k)), egui::Sense::click_and_drag());
+        let view = &mut self.ortho_views[k];
+        let keys = a11y::viewport_pane(ui, &response, view.name, false);
+        if keys.reset {
+            *view = OrthoView::new(view.name, view.rotation);
+        }
+        view.translation += keys.pan;
+        view.zoom = (view.zoom * keys.zoom).clamp(0.05, 500.0);
+        if response.dragged() {
+            view.translation += -response.drag_delta();
+        }
+        if response.hovered() {
+            let scroll = ui.input(|i| i.raw_scroll_delta.y);
+            if scroll.abs() > 0.0 {
+                view.zoom = (view.zoom * (1.0 + scroll * 0.001)).clamp(0.05, 500.0);
+            }
+            if response.double_clicked() {
+                *view = OrthoView::new(view.name, view.rotation);
             }
         }
+        let view = self.ortho_views[k];
+        self.paint_scene(ui, rect, ortho_mvp(self.work_size, &view, rect));
+        pane_label(ui, rect, view.name);
+        a11y::focus_ring(ui, &response);
+    }
 
-        if self.show_slice {
-            const PURPLE: [f32; 3] = [0.6, 0.1, 0.8];
-            if let Some(slice) = &self.sliced_layer {
-                let z = self.current_layer as f32 * self.layer_height;
+    /// Schedule the shared GPU buffers to be drawn into `rect` with `mvp`.
+    fn paint_scene(&self, ui: &egui::Ui, rect: egui::Rect, mvp: Matrix4<f32>) {
+        let Some(lines_gpu) = &self.gpu else { return };
+        let lines_gpu = lines_gpu.clone();
+        let faces_gpu = self.gpu_faces.clone();
 
-                for geom in &slice.geometry.0 {
-                    match geom {
-                        Geometry::LineString(ls) => {
-                            add_line_string(ls, z, PURPLE, &mut self.vertex_storage)
-                        }
-                        Geometry::Polygon(poly) => {
-                            add_line_string(&poly.exterior(), z, PURPLE, &mut self.vertex_storage);
-                            for inner in poly.interiors() {
-                                add_line_string(inner, z, PURPLE, &mut self.vertex_storage);
-                            }
-                        }
-                        _ => {} // ignore points etc.
+        let callback = egui_glow::CallbackFn::new(move |_info, painter| {
+            let gl = painter.gl();
+            unsafe {
+                gl.enable(glow::DEPTH_TEST);
+                gl.depth_func(glow::LEQUAL);
+                gl.clear(glow::DEPTH_BUFFER_BIT);
+
+                // draw filled faces first (slight offset keeps outlines crisp)
+                if let Some(faces_gpu) = &faces_gpu {
+                    if let Ok(f) = faces_gpu.lock() {
+                        gl.enable(glow::POLYGON_OFFSET_FILL);
+                        gl.polygon_offset(1.0, 1.0);
+                        f.paint_tris(gl, mvp);
+                        gl.disable(glow::POLYGON_OFFSET_FILL);
                     }
                 }
-            }
-        } else {
-            /* ---------- model wire-frame (edges) ----------------------------- */
-            if self.edges {
-                const WHITE: [f32; 3] = [1.0, 1.0, 1.0];
-                for model_entry in &self.models {
-                    let model = &model_entry.mesh;
-                    for p in &model.polygons {
-                        for (a, b) in p.edges() {
-                            self.vertex_storage.extend_from_slice(&[
-                                a.pos.x as f32,
-                                a.pos.y as f32,
-                                a.pos.z as f32,
-                                WHITE[0],
-                                WHITE[1],
-                                WHITE[2],
-                                b.pos.x as f32,
-                                b.pos.y as f32,
-                                b.pos.z as f32,
-                                WHITE[0],
-                                WHITE[1],
-                                WHITE[2],
-                            ]);
-                        }
-                    }
+                // then draw outlines
+                if let Ok(l) = lines_gpu.lock() {
+                    l.paint(gl, mvp);
+                }
+            }
+        });
+
+        ui.painter().add(egui::PaintCallback {
+            rect,
+            callback: Arc::new(callback),
+        });
+    }
+
+    fn pick_support(&mut self, rect: egui::Rect, click: egui::Pos2) {
+        let m = mvp(self, rect);
+        let Some((_, supports)) = self.supports.as_mut() else { return };
+        let nearest = supports
+            .regions
+            .iter()
+            .enumerate()
+            .filter_map(|(i, r)| {
+                let clip = m * r.anchor.push(1.0);
+                if clip.w <= 0.0 {
+                    return None;
+                }
+                let ndc = clip.xy() / clip.w;
+                let screen = egui::pos2(
+                    rect.center().x + ndc.x * rect.width() * 0.5,
+                    rect.center().y - ndc.y * rect.height() * 0.5,
+                );
+                Some((i, screen.distance(click)))
+            })
+            .filter(|&(_, d)| d < 20.0)
+            .min_by(|a, b| a.1.total_cmp(&b.1));
+        if let Some((i, _)) = nearest {
+            let anchor = supports.regions[i].anchor;
+            match supports.toggle(i) {
+                Some(false) => self.support_disabled.push(anchor),
+                Some(true) => self.support_disabled.retain(|a| (a - anchor).norm() >= 0.5),
+                None => {}
+            }
+        }
+    }
+
+    /// Layer stack over all models, re-planned lazily.
+    fn layer_plan(&mut self) -> &[slicer::Layer] {
+        if self.layer_plan.is_none() {
+            self.layer_plan = Some(engine::plan_layers(&self.layer_settings, self.models.iter().map(|m| &m.mesh)));
+        }
+        self.layer_plan.as_deref().unwrap_or_default()
+    }
+
+    /// Find nodes by label or template name; picking one selects it and pans
+    /// the editor to it.
+    fn node_search_ui(&mut self, ui: &mut egui::Ui) {
+        use egui_node_graph2::NodeTemplateTrait;
+
+        let resp = ui.add(egui::TextEdit::singleline(&mut self.node_search).hint_text("Find node…"));
+        let query = self.node_search.trim().to_lowercase();
+        if query.is_empty() {
+            return;
+        }
+        let graph = &self.design_state.graph;
+        let hits: Vec<(egui_node_graph2::NodeId, String)> = self
+            .design_state
+            .node_order
+            .iter()
+            .filter_map(|&id| {
+                let node = graph.nodes.get(id)?;
+                let template = node.user_data.template.node_finder_label(&mut self.design_user_state).into_owned();
+                let found = node.label.to_lowercase().contains(&query) || template.to_lowercase().contains(&query);
+                found.then(|| (id, if node.label == template { template } else { format!("{} ({template})", node.label) }))
+            })
+            .collect();
+        let mut pick = (resp.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)))
+            .then(|| hits.first().map(|(id, _)| *id))
+            .flatten();
+        if hits.is_empty() {
+            ui.weak("No matching nodes");
+        }
+        egui::ScrollArea::vertical().id_salt("node_search_hits").max_height(160.0).show(ui, |ui| {
+            for (id, label) in &hits {
+                let selected = self.design_state.selected_nodes.contains(id);
+                if ui.selectable_label(selected, label).clicked() {
+                    pick = Some(*id);
+                }
+            }
+        });
+        if let Some(id) = pick {
+            self.focus_node(id);
+        }
+    }
+
+    /// Select `id` and pan the graph editor so it sits in the middle.
+    fn focus_node(&mut self, id: egui_node_graph2::NodeId) {
+        let Some(pos) = self.design_state.node_positions.get(id).copied() else { return };
+        let size = if self.graph_rect.is_positive() { self.graph_rect.size() } else { egui::vec2(800.0, 600.0) };
+        // node positions are editor-relative, offset by the pan
+        self.design_state.pan_zoom.pan = size * 0.5 - pos.to_vec2() - egui::vec2(80.0, 30.0);
+        self.design_state.selected_nodes = vec![id];
+    }
+
+    /// Align, distribute and auto-layout buttons in the Design sidebar.
+    fn arrange_nodes_ui(&mut self, ui: &mut egui::Ui) {
+        use design_graph::Align;
+        let selected = self.design_state.selected_nodes.len();
+        ui.label("Arrange");
+        ui.horizontal_wrapped(|ui| {
+            ui.add_enabled_ui(selected >= 2, |ui| {
+                for (label, hover, edge) in [
+                    ("⇤", "Align selected nodes left", Align::Left),
+                    ("⇥", "Align selected nodes right", Align::Right),
+                    ("⤒", "Align selected nodes top", Align::Top),
+                ] {
+                    if ui.button(label).on_hover_text(hover).clicked() {
+                        design_graph::align_nodes(&mut self.design_state, edge);
+                    }
+                }
+            });
+            ui.add_enabled_ui(selected >= 3, |ui| {
+                if ui.button("↔").on_hover_text("Distribute selected nodes evenly across").clicked() {
+                    design_graph::distribute_nodes(&mut self.design_state, true);
+                }
+                if ui.button("↕").on_hover_text("Distribute selected nodes evenly down").clicked() {
+                    design_graph::distribute_nodes(&mut self.design_state, false);
+                }
+            });
+        });
+        if ui.button("Auto-layout").on_hover_text("Arrange the whole graph in columns, inputs to outputs").clicked() {
+            design_graph::auto_layout(&mut self.design_state);
+        }
+    }
+
+    /// Downloads offered by the graph's Export Mesh nodes.
+    fn graph_exports_ui(&mut self, ui: &mut egui::Ui) {
+        if self.graph_exports.is_empty() {
+            return;
+        }
+        ui.separator();
+        ui.label("Exports");
+        for file in &self.graph_exports {
+            let size = format!("{:.1} kB", file.bytes.len() as f64 / 1024.0);
+            if ui.button(format!("⬇ {}", file.file_name)).on_hover_text(size).clicked() {
+                download_bytes(&file.file_name, &file.bytes);
+            }
+        }
+        if self.graph_exports.len() > 1 && ui.button("Download all").clicked() {
+            for file in &self.graph_exports {
+                download_bytes(&file.file_name, &file.bytes);
+            }
+        }
+    }
+
+    /// Evaluate the graph's sketch outputs and cut their union as a flat part.
+    fn send_sketch_to_cutting(&mut self) {
+        let roots = design_graph::graph_roots(&self.design_state.graph);
+        let mut part: Option<Sketch<()>> = None;
+        let mut errors = Vec::new();
+        for root in roots {
+            match design_graph::evaluate_sketch(&self.design_state.graph, root) {
+                Ok(sketch) => part = Some(part.map_or_else(|| sketch.clone(), |p| p.union(&sketch))),
+                Err(e) => errors.push(e.to_string()),
+            }
+        }
+        let Some(part) = part else {
+            toasts::warn("The graph has no sketch output to cut", (!errors.is_empty()).then(|| errors.join("\n")));
+            return;
+        };
+        self.flat_part = Some(part);
+        if !matches!(self.selected_tool, Tool::Laser | Tool::Plasma | Tool::Drill) {
+            self.selected_tool = Tool::Laser;
+        }
+        self.show_slice = true;
+        self.selected_tab = Tab::Control;
+        self.refresh_slice();
+        toasts::info(format!("Sketch sent to the {} as a flat part", self.selected_tool));
+    }
+
+    /// Download the design graph as a `.graph` file.
+    fn save_graph(&mut self) {
+        let json = design_graph::SavedGraph::from_state(&self.design_state).and_then(|g| engine::graph_json(&g));
+        match json {
+            Ok(json) => {
+                download_bytes("design.graph", json.as_bytes());
+                self.dirty.graph_saved = None;
+            }
+            Err(e) => toasts::error("The graph could not be saved", Some(e.to_string())),
+        }
+    }
+
+    /// Replace the design graph with a `.graph` file's, checkpointing the
+    /// current one first so it can be restored from the history.
+    fn open_graph(&mut self, bytes: &[u8]) {
+        let saved = match engine::parse_graph(bytes) {
+            Ok(saved) => saved,
+            Err(e) => {
+                toasts::error("The graph file could not be read", Some(e.to_string()));
+                return;
+            }
+        };
+        self.checkpoint_graph();
+        let (state, missing) = saved.into_state();
+        if missing > 0 {
+            toasts::warn(
+                format!("{missing} plugin node(s) of the graph are unavailable"),
+                Some("load the plugins it uses, then open the file again".into()),
+            );
+        }
+        self.design_state = state;
+        self.dirty.graph_saved = None;
+        self.selected_tab = Tab::Design;
+        toasts::info("Graph loaded");
+    }
+
+    fn feature_shown(&self, feature: Feature) -> bool {
+        Feature::ALL.iter().position(|&f| f == feature).is_some_and(|i| self.features_shown[i])
+    }
+
+    /// Colour key of the slice view, each feature with its visibility toggle.
+    fn feature_legend_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal_wrapped(|ui| {
+            for (feature, shown) in Feature::ALL.into_iter().zip(&mut self.features_shown) {
+                let [r, g, b] = feature.colour().map(|c| (c * 255.0) as u8);
+                ui.colored_label(egui::Color32::from_rgb(r, g, b), "■");
+                ui.checkbox(shown, feature.name());
+            }
+        });
+    }
+
+    /// The flat part, while a 2-D tool is selected.
+    fn flat_source(&self) -> Option<&Sketch<()>> {
+        self.flat_part
+            .as_ref()
+            .filter(|_| matches!(self.selected_tool, Tool::Laser | Tool::Plasma | Tool::Drill))
+    }
+
+    /// Re-builds `sliced_layer` for the current Z level.
+    fn refresh_slice(&mut self) {
+        if !self.show_slice {
+            return;
+        }
+        self.print_preview = None;
+
+        // a flat part is the cut itself: no layers, no slicing
+        if let Some(sketch) = self.flat_source().cloned() {
+            self.plane_slice.clear();
+            self.infill = None;
+            self.adhesion_paths = None;
+            self.cut_paths = (self.common_line && matches!(self.selected_tool, Tool::Laser | Tool::Plasma))
+                .then(|| engine::merged_cut_paths(&engine::split_parts(&sketch), 0.01));
+            self.refresh_cut_plan(&sketch);
+            self.sliced_layer = Some(sketch);
+            self.simplify_stats = None;
+            return;
+        }
+
+        if self.custom_plane {
+            let n = self.plane_normal.cast::<f64>();
+            self.sliced_layer = None;
+            self.adhesion_paths = None;
+            self.plane_slice = self
+                .models
+                .iter()
+                .flat_map(|m| slicer::slice_oriented(&m.mesh, n, self.plane_offset.into()))
+                .collect();
+            return;
+        }
+        self.plane_slice.clear();
+
+        // slice a *union* of all models
+        let index = usize::try_from(self.current_layer).unwrap_or(0);
+        let Some(layer) = self.layer_plan().get(index).copied() else {
+            self.sliced_layer = None;
+            self.simplify_stats = None;
+            return;
+        };
+        if let Some(slice) = engine::slice_union(self.models.iter().map(|m| &m.mesh), layer.slice_z()) {
+            let (slice, stats) = engine::simplify_slice(&slice, self.simplify_tolerance);
+            self.simplify_stats = Some(stats);
+            self.refresh_infill(index);
+            // merged per part, as the job does: the union has no shared edges left
+            self.cut_paths = (self.common_line && matches!(self.selected_tool, Tool::Laser | Tool::Plasma)).then(|| {
+                let parts: Vec<Sketch<()>> = engine::slice_parts(self.models.iter().map(|m| &m.mesh), layer.slice_z())
+                    .iter()
+                    .map(|p| engine::simplify_slice(p, self.simplify_tolerance).0)
+                    .collect();
+                engine::merged_cut_paths(&parts, 0.01)
+            });
+            self.refresh_cut_plan(&slice);
+            self.adhesion_paths = None;
+            if self.selected_tool == Tool::Extruder {
+                // the same layer the print job gets
+                let plan = self.layer_plan().to_vec();
+                let adhesion = (index == 0).then(|| self.print_adhesion(&plan));
+                let first_layer = adhesion.as_ref().map_or(&[][..], |a| &a.first_layer[..]);
+                let infill = self.infill.as_ref().map(|(_, fill)| fill.clone());
+                let mut print = self.print_layer(&plan, index, first_layer, infill);
+                print.moves.mark_outer_walls(&slicer::polygons(&slice), self.line_width);
+                self.print_preview = Some(print);
+                self.adhesion_paths = adhesion;
+            }
+            self.sliced_layer = Some(slice);
+        }
+    }
+
+    /// Front-left corner of the work area, where cutting jobs start and end.
+    fn work_home(&self) -> geo::Coord<f64> {
+        geo::Coord {
+            x: f64::from(-self.work_size.x * 0.5),
+            y: f64::from(-self.work_size.y * 0.5),
+        }
+    }
+
+    /// Sequence the current slice's cuts (or drill points) for the 2-D tools.
+    fn refresh_cut_plan(&mut self, slice: &Sketch<()>) {
+        let home = self.work_home();
+        let (paths, drills) = match self.selected_tool {
+            Tool::Laser | Tool::Plasma => {
+                let paths = match &self.cut_paths {
+                    Some((merged, _)) => merged.clone(),
+                    None => engine::cut_paths(slice),
+                };
+                (paths, Vec::new())
+            }
+            Tool::Drill => (Vec::new(), engine::drill_points(slice, f64::from(self.drill_width), self.drill.tolerance)),
+            _ => {
+                self.cut_plan = None;
+                return;
+            }
+        };
+        if !self.optimize_order {
+            self.cut_plan = None;
+            return;
+        }
+        self.cut_plan = Some(engine::plan_cuts(&paths, &drills, home));
+    }
+
+    /// Machine the parts out of the stock with `mill_ops` in turn. Without
+    /// stock, the parts' bounding block is used.
+    fn compute_milling(&mut self) {
+        let stock = self.stock.as_ref().map(stock::Stock::mesh);
+        let parts: Vec<&Mesh<()>> = self.models.iter().map(|m| &m.mesh).collect();
+        let Some((results, part)) = engine::mill(stock.as_ref(), &parts, &self.mill_ops, &self.fixtures) else {
+            self.diag_log("milling: no part to machine");
+            return;
+        };
+        self.diag_log(format!(
+            "milling: {} operations, {:.0} mm³ left",
+            results.len(),
+            results.last().map_or(0.0, |r| r.rest_volume)
+        ));
+        self.mill_results = Some(results);
+        self.mill_part = Some(part);
+    }
+
+    /// Start simulating the loaded job cutting the stock with the endmill.
+    fn start_mill_simulation(&mut self) {
+        let Some(job) = &self.jobs.active else {
+            toasts::warn("Nothing to simulate: load or generate a job first", None);
+            return;
+        };
+        // jobs are in machine coordinates: the bed's corner at the origin, Z shifted by the offset
+        let (dx, dy) = (f64::from(self.work_size.x) * 0.5, f64::from(self.work_size.y) * 0.5);
+        let dz = self.machine.z_offset;
+        let moves: Vec<[f64; 3]> = engine::program_moves(&job.text()).into_iter().map(|[x, y, z]| [x - dx, y - dy, z - dz]).collect();
+        let stock = self.stock.as_ref().map(stock::Stock::mesh);
+        let parts: Vec<&Mesh<()>> = self.models.iter().map(|m| &m.mesh).collect();
+        let tool = engine::ToolAssembly {
+            diameter: f64::from(self.endmill_width),
+            length: f64::from(self.endmill_length),
+            holder: self.holder.clone(),
+        };
+        self.mill_sim = engine::mill_simulation(stock.as_ref(), &parts, moves, tool, self.fixtures.clone());
+        if self.mill_sim.is_none() {
+            toasts::warn("Nothing to simulate against: load a model first", None);
+        }
+    }
+
+    /// Run the simulation on for a frame's worth of moves, and show how far
+    /// it got and what it found.
+    fn mill_simulation_ui(&mut self, ui: &mut egui::Ui) {
+        if ui
+            .add_enabled(self.jobs.active.is_some(), egui::Button::new("Simulate loaded job"))
+            .on_hover_text("Sweep the endmill along the job through the stock and compare the result with the models")
+            .clicked()
+        {
+            self.start_mill_simulation();
+        }
+        let Some(sim) = &mut self.mill_sim else { return };
+        if !sim.is_done() {
+            let started = now_ms();
+            while !sim.is_done() && now_ms() - started < 12.0 {
+                sim.advance(50);
+            }
+            ui.add(egui::ProgressBar::new(sim.progress()).show_percentage());
+            ui.ctx().request_repaint();
+            return;
+        }
+        let (gouged, rest) = (sim.gouge_volume(), sim.rest_volume());
+        if gouged > 0.5 {
+            ui.colored_label(ui.visuals().error_fg_color, format!("Gouges: {gouged:.0} mm³ cut out of the part (magenta)"));
+        } else {
+            ui.label("No gouges");
+        }
+        ui.label(format!("Uncut: {rest:.0} mm³ left above the part (orange)"));
+        if sim.collisions.is_empty() {
+            ui.label("No collisions");
+        } else {
+            ui.colored_label(ui.visuals().error_fg_color, format!("{} move(s) collide (red)", sim.collisions.len()));
+            egui::ScrollArea::vertical().id_salt("collisions").max_height(120.0).show(ui, |ui| {
+                for c in &sim.collisions {
+                    let what = match c.hit {
+                        engine::Hit::Stock => "holder hits the stock".to_owned(),
+                        engine::Hit::Fixture(k) => match self.fixtures.get(k) {
+                            Some(f) if !f.name.is_empty() => format!("enters {}", f.name),
+                            _ => format!("enters fixture {}", k + 1),
+                        },
+                    };
+                    ui.small(format!("Move {}: {what} at ({:.1}, {:.1}, {:.1})", c.index, c.at[0], c.at[1], c.at[2]));
+                }
+            });
+        }
+        if ui.small_button("Clear").clicked() {
+            self.mill_sim = None;
+        }
+    }
+
+    /// Profile or pocket passes around the current layer's outline, cut down
+    /// from the top of the stock (or of the models without stock).
+    fn compute_contours(&mut self) {
+        let index = usize::try_from(self.current_layer).unwrap_or(0);
+        let Some(layer) = self.layer_plan().get(index).copied() else {
+            toasts::warn("Nothing to mill: load a model first", None);
+            return;
+        };
+        let Some(slice) = engine::slice_union(self.models.iter().map(|m| &m.mesh), layer.slice_z()) else {
+            return;
+        };
+        let top = match &self.stock {
+            Some(s) => f64::from(s.origin.z + s.size.z),
+            None => engine::z_extent(self.models.iter().map(|m| &m.mesh)).map_or(0.0, |(_, hi)| f64::from(hi)),
+        };
+        let paths = engine::contour_toolpaths(&slice, &self.cam, f64::from(self.endmill_width), top, &self.fixtures);
+        self.diag_log(format!("{}: {} passes from z {top:.2}", self.cam.strategy, paths.len()));
+        self.cam_paths = Some((paths, top));
+    }
+
+    /// Look for voids in the resin layers that seal against the vat film.
+    fn find_trapped_volumes(&mut self) {
+        let plan = self.layer_plan().to_vec();
+        let meshes: Vec<&Mesh<()>> = self.models.iter().map(|m| &m.mesh).collect();
+        let found = engine::trapped_volumes(&meshes, &plan, &self.resin.hollow);
+        self.diag_log(format!("trapped volumes: {} in {} layers", found.len(), plan.len()));
+        self.trapped = Some(found);
+    }
+
+    /// Re-generate extruder fill paths for layer `index` if its inputs changed.
+    /// Each model is filled on its own so its overrides apply to it alone.
+    fn refresh_infill(&mut self, index: usize) {
+        if self.selected_tool != Tool::Extruder {
+            self.infill = None;
+            return;
+        }
+        let key = InfillKey {
+            index,
+            global: self.infill_settings.clone(),
+            pattern: self.infill_type,
+            perimeters: self.perimeters,
+            line_width: self.line_width,
+            overrides: self.models.iter().map(|m| m.overrides.clone()).collect(),
+        };
+        if self.infill.as_ref().is_some_and(|(k, _)| *k == key) {
+            return;
+        }
+        let plan = self.layer_plan().to_vec();
+        let models = self
+            .models
+            .iter()
+            .map(|m| (&m.mesh, m.overrides.infill(&self.infill_settings), m.overrides.perimeters(self.perimeters)));
+        let fill = engine::layer_infill(models, self.infill_type, self.line_width, &plan, index);
+        self.infill = Some((key, fill));
+    }
+
+    /// Marks `model` as dirty so that next frame will rebuild
+    fn invalidate_selected_model(&mut self) {
+        if let Some(m) = self.sel_mut() {
+            m.applied_scale = INVALID_SCALE;
+            m.applied_offset = Vector3::repeat(f32::NAN);
+        }
+    }
+
+    /// Replace currently-selected entry’s *base* geometry, as one undoable step.
+    fn set_selected_base(&mut self, mesh: Mesh<()>, name: String) {
+        let Some(index) = self.selected_model else { return };
+        let before = self.models.get(index).cloned();
+        if let (Some(before), Some(m)) = (before, self.sel_mut()) {
+            m.base = mesh;
+            m.name = name;
+            self.dirty.models = true;
+            // recorded invalidated, so a redo rebuilds the mesh too
+            self.invalidate_selected_model();
+            self.record_model_replaced(index, before);
+            self.refresh_models();
+            self.refresh_slice();
+        }
+    }
+
+    /// convenience: currently-selected entry (mutable)
+    fn sel_mut(&mut self) -> Option<&mut ModelEntry> {
+        self.selected_model
+            .and_then(move |i| self.models.get_mut(i))
+    }
+
+    /// Add a *new* model and make it the selection.
+    fn add_model(&mut self, mesh: Mesh<()>, name: String) {
+        let mut e = ModelEntry::new(name, mesh);
+        e.refresh();
+        self.models.push(e);
+        self.record_model_added(self.models.len() - 1);
+        self.dirty.models = true;
+        self.selected_model = Some(self.models.len() - 1);
+        self.invalidate_layers();
+        self.refresh_slice();
+    }
+    
+    /// Ask for a mesh file to add as a new model.
+    fn pick_model_file(&mut self) {
+        self.selected_model = None; // -> add after file dialog
+        spawn_file_picker(
+            Arc::clone(&self.model_data),
+            "Model mesh (stl,dxf)",
+            &plugins::import_extensions(&["stl", "dxf", "obj", "ply", "amf"]),
+        );
+    }
+
+    /// Download the selected model, as transformed, as binary STL.
+    fn export_selected_stl(&self) {
+        let Some(m) = self.selected_model.and_then(|i| self.models.get(i)) else {
+            toasts::warn("No model selected", None);
+            return;
+        };
+        match m.mesh.to_stl_binary(&m.name) {
+            Ok(bytes) => {
+                let stem = m.name.rsplit_once('.').map_or(m.name.as_str(), |(stem, _)| stem);
+                download_bytes(&format!("{stem}.stl"), &bytes);
+            }
+            Err(e) => toasts::error("STL export failed", Some(e.to_string())),
+        }
+    }
+
+    fn diag_log(&mut self, line: impl Into<String>) {
+        if !self.diag_console.is_empty() { self.diag_console.push('\n'); }
+        self.diag_console.push_str(&line.into());
+    }
+    fn diag_push_point_named(&mut self, name: &str, x: f64, y: f64) {
+        self.diag_series.entry(name.to_string()).or_default().push([x, y]);
+    }
+
+    /// Kick one async GET /pins, store as HashMap<String, f64> in `target`.
+    fn poll_pins_once(target: Arc<Mutex<Option<HashMap<String, f64>>>>) {
+        execute(async move {
+            match net::fetch(&net::Endpoint::Get("/pins".into())).await {
+                Ok(body) => {
+                    // Expect shape: {"D0":0, "D1":1, ...}
+                    let parsed: Result<HashMap<String, f64>, _> = serde_json::from_str::<HashMap<String, serde_json::Value>>(&body)
+                        .map(|m| {
+                            m.into_iter()
+                                .filter_map(|(k, v)| v.as_f64().or_else(|| v.as_u64().map(|u| u as f64)).map(|f| (k, f)))
+                                .collect()
+                        });
+                    match parsed {
+                        Ok(map) => { *target.lock().unwrap() = Some(map); }
+                        Err(e) => log::error!("parse /pins failed: {:?}", e),
+                    }
+                }
+                Err(e) => log::error!("poll /pins failed: {e}"),
+            }
+        });
+    }
+    
+    fn is_pin_checked(&self, name: &str) -> bool {
+        self.diag_pin_on.get(name).copied().unwrap_or(false)
+    }
+}
+
+impl AluminaApp {
+    /// (Re-)builds the VBO if the model, grid or scale changed.
+    unsafe fn sync_buffers(&mut self, gl: &glow::Context) {
+        self.vertex_storage.clear();
+        let mut faces: Vec<f32> = Vec::new();
+
+        // ── 1) grid (10 mm spacing, ±work_size/2) ───────────────────────
+        if self.workarea {
+            let minor = [0.55, 0.55, 0.55];
+            let major = [1.0, 1.0, 1.0];
+
+            let hx = self.work_size.x * 0.5;
+            let hy = self.work_size.y * 0.5;
+            let hz = self.work_size.z;
+
+            // vertical (X) lines
+            for i in 0..=(self.work_size.x / 10.0) as i32 {
+                let x = -hx + i as f32 * 10.0;
+                let col = if i % 10 == 0 { major } else { minor };
+                self.vertex_storage.extend_from_slice(&[
+                    x, -hy, 0.0, col[0], col[1], col[2], x, hy, 0.0, col[0], col[1], col[2],
+                ]);
+            }
+
+            // horizontal (Y) lines
+            for i in 0..=(self.work_size.y / 10.0) as i32 {
+                let y = -hy + i as f32 * 10.0;
+                let col = if i % 10 == 0 { major } else { minor };
+                self.vertex_storage.extend_from_slice(&[
+                    -hx, y, 0.0, col[0], col[1], col[2], hx, y, 0.0, col[0], col[1], col[2],
+                ]);
+            }
+
+            // outline the remaining cuboid edges
+            let edge = major;
+
+            //  four vertical edges
+            for (sx, sy) in [(-1.0, -1.0), (-1.0, 1.0), (1.0, -1.0), (1.0, 1.0)] {
+                let x = sx * hx;
+                let y = sy * hy;
+                self.vertex_storage.extend_from_slice(&[
+                    x, y, 0.0, edge[0], edge[1], edge[2], // bottom
+                    x, y, hz, edge[0], edge[1], edge[2], // top
+                ]);
+            }
+
+            //  top rectangle (Z = hz)
+            self.vertex_storage.extend_from_slice(&[
+                -hx, -hy, hz, edge[0], edge[1], edge[2], hx, -hy, hz, edge[0], edge[1], edge[2],
+                hx, -hy, hz, edge[0], edge[1], edge[2], hx, hy, hz, edge[0], edge[1], edge[2], hx,
+                hy, hz, edge[0], edge[1], edge[2], -hx, hy, hz, edge[0], edge[1], edge[2], -hx, hy,
+                hz, edge[0], edge[1], edge[2], -hx, -hy, hz, edge[0], edge[1], edge[2],
+            ]);
+        }
+
+        // current tool position reported by the DRO
+        if let Some(pos) = self.dro_pos {
+            let p = self.machine_to_scene(pos.display());
+            let size = self.work_size.norm() * 0.015;
+            control::push_tool_marker(p, size, &mut self.vertex_storage);
+        }
+        let mut lines = std::mem::take(&mut self.vertex_storage);
+        self.push_trail(&mut lines);
+        self.vertex_storage = lines;
+
+        // stock outline, amber so it is not mistaken for a part
+        if let Some(stock) = &self.stock {
+            stock.push_outline([1.0, 0.75, 0.3], &mut self.vertex_storage);
+        }
+        // fixtures, each kind its own colour
+        for f in &self.fixtures {
+            stock::push_fixture(f, &mut self.vertex_storage);
+        }
+
+        // ── 2) model / slice ──────────────────────────────────────────────
+        fn add_dashed_line(a: geo::Coord<f64>, b: geo::Coord<f64>, z: f32, dash: f64, col: [f32; 3], out: &mut Vec<f32>) {
+            let len = ((b.x - a.x).powi(2) + (b.y - a.y).powi(2)).sqrt();
+            let n = (len / dash).ceil().max(1.0) as usize;
+            for k in (0..n).step_by(2) {
+                let (t0, t1) = (k as f64 / n as f64, ((k + 1) as f64 / n as f64).min(1.0));
+                let p = |t: f64| [(a.x + (b.x - a.x) * t) as f32, (a.y + (b.y - a.y) * t) as f32];
+                let ([x0, y0], [x1, y1]) = (p(t0), p(t1));
+                out.extend_from_slice(&[x0, y0, z, col[0], col[1], col[2], x1, y1, z, col[0], col[1], col[2]]);
+            }
+        }
+
+        fn add_line_string(ls: &LineString<f64>, z: f32, col: [f32; 3], out: &mut Vec<f32>) {
+            for w in ls.0.windows(2) {
+                let a = w[0];
+                let b = w[1];
+                out.extend_from_slice(&[
+                    a.x as f32, a.y as f32, z, col[0], col[1], col[2], b.x as f32, b.y as f32, z,
+                    col[0], col[1], col[2],
+                ]);
+            }
+        }
+
+        if self.show_slice {
+            const PURPLE: [f32; 3] = [0.6, 0.1, 0.8];
+            for pl in &self.plane_slice {
+                for w in pl.windows(2) {
+                    let (a, b) = (w[0], w[1]);
+                    self.vertex_storage.extend_from_slice(&[a.x, a.y, a.z, PURPLE[0], PURPLE[1], PURPLE[2], b.x, b.y, b.z, PURPLE[0], PURPLE[1], PURPLE[2]]);
+                }
+            }
+            if let Some(slice) = &self.sliced_layer {
+                let z = self
+                    .layer_plan
+                    .as_ref()
+                    .filter(|_| self.flat_source().is_none())
+                    .and_then(|plan| plan.get(usize::try_from(self.current_layer).ok()?))
+                    .map_or(0.0, slicer::Layer::slice_z);
+
+                for geom in &slice.geometry.0 {
+                    match geom {
+                        Geometry::LineString(ls) => {
+                            add_line_string(ls, z, PURPLE, &mut self.vertex_storage)
+                        }
+                        Geometry::Polygon(poly) => {
+                            add_line_string(&poly.exterior(), z, PURPLE, &mut self.vertex_storage);
+                            for inner in poly.interiors() {
+                                add_line_string(inner, z, PURPLE, &mut self.vertex_storage);
+                            }
+                        }
+                        _ => {} // ignore points etc.
+                    }
+                }
+
+                // rapid moves between cuts, slightly above the slice
+                if let Some((plan, _)) = &self.cut_plan {
+                    const SKY: [f32; 3] = [0.5, 0.8, 1.0];
+                    let rz = z + self.work_size.z * 0.01;
+                    for &(a, b) in &plan.rapids {
+                        self.vertex_storage.extend_from_slice(&[
+                            a.x as f32, a.y as f32, rz, SKY[0], SKY[1], SKY[2], b.x as f32, b.y as f32, rz, SKY[0], SKY[1], SKY[2],
+                        ]);
+                    }
+                }
+
+                if let Some((paths, _)) = &self.cut_paths {
+                    const RED: [f32; 3] = [1.0, 0.25, 0.2];
+                    for ls in paths {
+                        add_line_string(ls, z, RED, &mut self.vertex_storage);
+                    }
+                }
+
+                const ADHESION: [f32; 3] = [0.2, 0.8, 0.3];
+                // the inspected layer as the print job lays it down
+                if let Some(print) = &self.print_preview {
+                    let fill = &print.infill;
+                    for (feature, paths) in [(Feature::SolidInfill, &fill.solid), (Feature::SparseInfill, &fill.sparse)] {
+                        if self.feature_shown(feature) {
+                            for ls in paths {
+                                add_line_string(ls, z, feature.colour(), &mut self.vertex_storage);
+                            }
+                        }
+                    }
+                    if self.feature_shown(Feature::Support) {
+                        for ls in &print.support {
+                            add_line_string(ls, z, Feature::Support.colour(), &mut self.vertex_storage);
+                        }
+                    }
+                    // skirt / brim around the first layer
+                    for ls in &print.adhesion {
+                        add_line_string(ls, z, ADHESION, &mut self.vertex_storage);
+                    }
+                }
+
+                // raft stacked underneath the first layer
+                if let Some(adhesion) = &self.adhesion_paths {
+                    let h = self.layer_settings.base_height;
+                    let bottom = z - self.layer_plan.as_ref().and_then(|p| p.first()).map_or(0.0, |l| l.height * 0.5);
+                    let n = adhesion.raft.len();
+                    for (k, layer) in adhesion.raft.iter().enumerate() {
+                        let rz = bottom - (n - k) as f32 * h + h * 0.5;
+                        for ls in layer {
+                            add_line_string(ls, rz, ADHESION, &mut self.vertex_storage);
+                        }
+                    }
+                }
+
+                // seam start of every loop, and the travels between loops (dashed;
+                // retracted ones lifted by the z-hop)
+                if let Some(moves) = self.print_preview.as_ref().map(|l| &l.moves) {
+                    const ORANGE: [f32; 3] = [1.0, 0.5, 0.0];
+                    const TRAVEL: [f32; 3] = Feature::Travel.colour();
+                    const RETRACT: [f32; 3] = [0.95, 0.4, 0.8];
+                    let r = self.work_size.norm() * 0.004;
+                    for (k, ring) in moves.loops.iter().enumerate() {
+                        let wall = if moves.outer.get(k).copied().unwrap_or(true) { Feature::OuterWall } else { Feature::InnerWall };
+                        if !self.feature_shown(wall) {
+                            continue;
+                        }
+                        add_line_string(ring, z, wall.colour(), &mut self.vertex_storage);
+                        if let Some(&c) = ring.0.first() {
+                            add_vertex_sphere(Vector3::new(c.x as f32, c.y as f32, z), r, ORANGE, &mut faces);
+                        }
+                    }
+                    let hop = self.retraction.z_hop as f32;
+                    let travels = if self.feature_shown(Feature::Travel) { moves.travels.as_slice() } else { &[] };
+                    for t in travels {
+                        let (a, b) = (t.from, t.to);
+                        let (col, tz) = if t.retract { (RETRACT, z + hop) } else { (TRAVEL, z) };
+                        if t.retract && hop > 0.0 {
+                            self.vertex_storage.extend_from_slice(&[
+                                a.x as f32, a.y as f32, z, col[0], col[1], col[2], a.x as f32, a.y as f32, tz, col[0], col[1], col[2],
+                                b.x as f32, b.y as f32, tz, col[0], col[1], col[2], b.x as f32, b.y as f32, z, col[0], col[1], col[2],
+                            ]);
+                        }
+                        add_dashed_line(a, b, tz, 1.0, col, &mut self.vertex_storage);
+                    }
+                }
+            }
+        } else {
+            /* ---------- milling paths and rest material --------------------- */
+            if let Some(results) = &self.mill_results {
+                const OP_COLS: [[f32; 3]; 4] = [[0.9, 0.6, 0.1], [0.2, 0.8, 0.9], [0.8, 0.3, 0.9], [0.3, 0.9, 0.3]];
+                for (n, r) in results.iter().enumerate() {
+                    let c = OP_COLS[n % OP_COLS.len()];
+                    for path in &r.paths {
+                        for w in path.windows(2) {
+                            let (a, b) = (w[0], w[1]);
+                            self.vertex_storage.extend_from_slice(&[
+                                a[0] as f32, a[1] as f32, a[2] as f32, c[0], c[1], c[2], b[0] as f32, b[1] as f32, b[2] as f32, c[0], c[1], c[2],
+                            ]);
+                        }
+                    }
+                }
+                // red whiskers where material is still above the part
+                if let (Some(last), Some(part)) = (results.last(), &self.mill_part) {
+                    const REST: [f32; 3] = [1.0, 0.1, 0.1];
+                    let rest = &last.remaining;
+                    for j in 0..rest.ny {
+                        for i in 0..rest.nx {
+                            let k = j * rest.nx + i;
+                            let (top, floor) = (rest.z[k], part.z[k]);
+                            if top.is_finite() && top - floor > 0.1 {
+                                let [x, y] = rest.xy(i, j);
+                                let (x, y) = (x as f32, y as f32);
+                                self.vertex_storage.extend_from_slice(&[x, y, floor, REST[0], REST[1], REST[2], x, y, top, REST[0], REST[1], REST[2]]);
+                            }
+                        }
+                    }
+                }
+            }
+
+            /* ---------- simulated result: surface, gouges, uncut stock -------- */
+            if let Some(sim) = &self.mill_sim {
+                const SURFACE: [f32; 3] = [0.6, 0.6, 0.65];
+                const GOUGE: [f32; 3] = [1.0, 0.2, 0.9];
+                const UNCUT: [f32; 3] = [1.0, 0.55, 0.1];
+                let (stock, part) = (&sim.stock, &sim.part);
+                // every fourth row as a profile line over the machined surface
+                for j in (0..stock.ny).step_by(4) {
+                    for i in 1..stock.nx {
+                        let (a, b) = (stock.z[j * stock.nx + i - 1], stock.z[j * stock.nx + i]);
+                        if a.is_finite() && b.is_finite() {
+                            let ([x0, y0], [x1, y1]) = (stock.xy(i - 1, j), stock.xy(i, j));
+                            self.vertex_storage.extend_from_slice(&[
+                                x0 as f32, y0 as f32, a, SURFACE[0], SURFACE[1], SURFACE[2], x1 as f32, y1 as f32, b, SURFACE[0], SURFACE[1], SURFACE[2],
+                            ]);
+                        }
+                    }
+                }
+                for j in 0..stock.ny {
+                    for i in 0..stock.nx {
+                        let k = j * stock.nx + i;
+                        let (cut, want) = (stock.z[k], part.z[k]);
+                        if !cut.is_finite() || !want.is_finite() {
+                            continue;
+                        }
+                        let c = if cut < want - milling::SIM_TOLERANCE {
+                            GOUGE
+                        } else if cut > want + milling::SIM_TOLERANCE {
+                            UNCUT
+                        } else {
+                            continue;
+                        };
+                        let [x, y] = stock.xy(i, j);
+                        let (x, y) = (x as f32, y as f32);
+                        self.vertex_storage.extend_from_slice(&[x, y, cut, c[0], c[1], c[2], x, y, want, c[0], c[1], c[2]]);
+                    }
+                }
+                // collisions: a red post from the tool tip up through the holder
+                const COLLISION: [f32; 3] = [1.0, 0.1, 0.1];
+                let reach = f64::from(self.endmill_length) + self.holder.length;
+                for c in &sim.collisions {
+                    let [x, y, z] = c.at.map(|v| v as f32);
+                    let top = (c.at[2] + reach) as f32;
+                    self.vertex_storage.extend_from_slice(&[x, y, z, COLLISION[0], COLLISION[1], COLLISION[2], x, y, top, COLLISION[0], COLLISION[1], COLLISION[2]]);
+                }
+            }
+
+            /* ---------- 2.5-D profile / pocket passes ------------------------- */
+            if let Some((paths, _)) = &self.cam_paths {
+                const CAM: [f32; 3] = [0.95, 0.8, 0.2];
+                for path in paths {
+                    for w in path.windows(2) {
+                        let (a, b) = (w[0], w[1]);
+                        self.vertex_storage.extend_from_slice(&[
+                            a[0] as f32, a[1] as f32, a[2] as f32, CAM[0], CAM[1], CAM[2], b[0] as f32, b[1] as f32, b[2] as f32, CAM[0], CAM[1], CAM[2],
+                        ]);
+                    }
+                }
+            }
+
+            /* ---------- supports (grey when switched off) -------------------- */
+            if let Some((_, supports)) = &self.supports {
+                const CYAN: [f32; 3] = [0.2, 0.8, 0.9];
+                const GREY: [f32; 3] = [0.4, 0.4, 0.4];
+                for (a, b, on) in supports.segments() {
+                    let c = if on { CYAN } else { GREY };
+                    self.vertex_storage.extend_from_slice(&[a.x, a.y, a.z, c[0], c[1], c[2], b.x, b.y, b.z, c[0], c[1], c[2]]);
+                }
+            }
+
+            /* ---------- trapped volumes and their drain holes ----------------- */
+            if let (Some(volumes), Some(plan)) = (&self.trapped, &self.layer_plan) {
+                const TRAPPED: [f32; 3] = [1.0, 0.55, 0.1];
+                const DRAIN: [f32; 3] = [1.0, 0.1, 0.1];
+                for v in volumes {
+                    for (i, ring) in &v.outlines {
+                        if let Some(layer) = plan.get(*i) {
+                            add_line_string(ring, layer.slice_z(), TRAPPED, &mut self.vertex_storage);
+                        }
+                    }
+                    // a short red post down through the floor
+                    let (x, y, z) = (v.drain.x as f32, v.drain.y as f32, v.drain_z as f32);
+                    self.vertex_storage.extend_from_slice(&[x, y, z + 2.0, DRAIN[0], DRAIN[1], DRAIN[2], x, y, z - 5.0, DRAIN[0], DRAIN[1], DRAIN[2]]);
+                }
+            }
+
+            /* ---------- model wire-frame (edges) ----------------------------- */
+            if self.edges {
+                const WHITE: [f32; 3] = [1.0, 1.0, 1.0];
+                for model_entry in &self.models {
+                    let model = &model_entry.mesh;
+                    for p in &model.polygons {
+                        for (a, b) in p.edges() {
+                            self.vertex_storage.extend_from_slice(&[
+                                a.pos.x as f32,
+                                a.pos.y as f32,
+                                a.pos.z as f32,
+                                WHITE[0],
+                                WHITE[1],
+                                WHITE[2],
+                                b.pos.x as f32,
+                                b.pos.y as f32,
+                                b.pos.z as f32,
+                                WHITE[0],
+                                WHITE[1],
+                                WHITE[2],
+                            ]);
+                        }
+                    }
                 }
             }
 
@@ -532,532 +2129,1326 @@ impl AluminaApp {
                             }
                         }
                     }
-                }
+                }
+            }
+
+            // === Polygon normals ========================================================
+            if self.normals {
+                const NORMAL_COL: [f32; 3] = [1.0, 0.0, 0.0]; // red
+                let normal_len = (self.work_size.norm() * 0.04) as f32; // ≈ 4 % of diag
+
+                for model_entry in &self.models {
+                    let model = &model_entry.mesh;
+                    for p in &model.polygons {
+                        // polygon centroid
+                        let mut c = Vector3::zeros();
+                        for v in &p.vertices {
+                            c += Vector3::new(v.pos.x as f32, v.pos.y as f32, v.pos.z as f32);
+                        }
+                        c /= p.vertices.len() as f32;
+
+                        let n = Vector3::<f32>::new(
+                            p.plane.normal().x as f32,
+                            p.plane.normal().y as f32,
+                            p.plane.normal().z as f32,
+                        )
+                        .normalize()
+                            * normal_len;
+
+                        self.vertex_storage.extend_from_slice(&[
+                            c.x,
+                            c.y,
+                            c.z,
+                            NORMAL_COL[0],
+                            NORMAL_COL[1],
+                            NORMAL_COL[2],
+                            c.x + n.x,
+                            c.y + n.y,
+                            c.z + n.z,
+                            NORMAL_COL[0],
+                            NORMAL_COL[1],
+                            NORMAL_COL[2],
+                        ]);
+                    }
+                }
+            }
+
+            // === Vertex spheres =========================================================
+            if self.vertices {
+                const VERT_COL: [f32; 3] = [1.0, 1.0, 0.0]; // yellow
+                let r = (self.work_size.norm() * 0.005) as f32; // ≈ 0.5 % of diag
+
+                // de-dupe identical vertices so we don’t draw the same sphere many times
+                let mut seen: HashSet<(i64, i64, i64)> = HashSet::new();
+                let quant = 1_000_000.0; // 1 µm grid
+
+                for model_entry in &self.models {
+                    let model = &model_entry.mesh;
+                    for p in &model.polygons {
+                        for v in &p.vertices {
+                            let key = (
+                                (v.pos.x * quant) as i64,
+                                (v.pos.y * quant) as i64,
+                                (v.pos.z * quant) as i64,
+                            );
+                            if seen.insert(key) {
+                                let c =
+                                    Vector3::new(v.pos.x as f32, v.pos.y as f32, v.pos.z as f32);
+                                add_vertex_sphere(c, r, VERT_COL, &mut faces);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        // ---------- upload / (re-)create VBOs -----------------------------------
+        self.profile.line_vertices = self.vertex_storage.len() / 6;
+        self.profile.face_vertices = faces.len() / 6;
+        if let Some(lines_gpu) = &self.gpu {
+            if let Ok(mut g) = lines_gpu.lock() {
+                unsafe { g.upload_vertices(gl, &self.vertex_storage) };
+            }
+        }
+
+        // Faces VBO is present only while “faces” is checked
+        let need_tris = !faces.is_empty();
+        if need_tris {
+            let faces_gpu = self.gpu_faces.get_or_insert_with(|| {
+                Arc::new(Mutex::new(unsafe { renderer::GpuLines::new(gl) }))
+            });
+            if let Ok(mut g) = faces_gpu.lock() {
+                unsafe { g.upload_vertices(gl, &faces) };
+            }
+        } else {
+            self.gpu_faces = None;
+        }
+    }
+}
+
+impl AluminaApp {
+    /// Loaded models, their placement and per-model overrides.
+    fn models_pane(&mut self, ui: &mut egui::Ui) {
+        if self.flat_part.is_some() {
+            ui.horizontal(|ui| {
+                ui.label("Flat part (sketch)");
+                if ui.button("x").on_hover_text("Remove the flat part and slice the models again").clicked() {
+                    self.flat_part = None;
+                    self.refresh_slice();
+                }
+            });
+            if self.flat_source().is_none() {
+                ui.weak("Only cut with the laser, plasma or drill");
+            }
+            ui.separator();
+        }
+        ui.label("Loaded models");
+        let mut remove: Option<usize> = None;
+        for (i, m) in self.models.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                if ui
+                    .selectable_label(self.selected_model == Some(i), &m.name)
+                    .clicked()
+                {
+                    self.selected_model = Some(i);
+                }
+                let name = &m.name;
+                if ui
+                    .button("x")
+                    .on_hover_text("Remove model")
+                    .widget_info(|| egui::WidgetInfo::labeled(egui::WidgetType::Button, true, format!("Remove {name}")))
+                    .clicked()
+                {
+                    remove = Some(i);
+                }
+            });
+        }
+        ui.horizontal(|ui| {
+            if ui.button("Add…").clicked() {
+                self.pick_model_file();
+            }
+            if ui.button("Export STL").on_hover_text("Download the selected model as shown").clicked() {
+                self.export_selected_stl();
+            }
+            if ui.button("BOM").on_hover_text("Bill of materials of the models and graph exports").clicked() {
+                self.bom.open();
+            }
+        });
+        if let Some(idx) = remove {
+            self.guarded(Confirm::RemoveModel(idx));
+        }
+        self.model_history_ui(ui);
+
+        // placement before this frame's edits, for undo
+        let placed = self.selected_model.and_then(|i| self.models.get(i).map(|m| (i, model_history::Placement::of(m))));
+
+        // ────────────── Scale Controls ──────────────
+        ui.separator();
+        let scaled = ui.collapsing("Model scale", |ui| {
+            // --- 1. borrow models[idx] once --------------------
+            if let Some(m) = self.sel_mut() {
+                // Track whether any DragValue changed
+                let mut changed = false;
+
+                ui.horizontal(|ui| {
+                    let label = ui.label("X:");
+                    changed |= ui
+                        .add(
+                            egui::DragValue::new(&mut m.scale.x)
+                                .speed(0.01)
+                                .range(0.01..=100.0),
+                        )
+                        .labelled_by(label.id)
+                        .changed();
+                });
+                ui.horizontal(|ui| {
+                    let label = ui.label("Y:");
+                    changed |= ui
+                        .add(
+                            egui::DragValue::new(&mut m.scale.y)
+                                .speed(0.01)
+                                .range(0.01..=100.0),
+                        )
+                        .labelled_by(label.id)
+                        .changed();
+                });
+                ui.horizontal(|ui| {
+                    let label = ui.label("Z:");
+                    changed |= ui
+                        .add(
+                            egui::DragValue::new(&mut m.scale.z)
+                                .speed(0.01)
+                                .range(0.01..=100.0),
+                        )
+                        .labelled_by(label.id)
+                        .changed();
+                });
+
+                if ui.button("Reset scale").clicked() {
+                    m.scale = Vector3::new(1.0, 1.0, 1.0);
+                    changed = true;
+                }
+
+                // Invalidate *through the same mutable borrow*.
+                if changed {
+                    m.applied_scale = INVALID_SCALE;
+                }
+                changed
+            } else {
+                ui.label("No model selected");
+                false
+            }
+            // --- m is dropped here; safe to touch self again if you need to ---
+        });
+
+        // ────────────── Position Controls ──────────────
+        ui.separator();
+        let moved = ui.collapsing("Model position", |ui| {
+            if let Some(m) = self.sel_mut() {
+                let mut changed = false;
+                let mut old_base = None;
+
+                if ui.button("Float (Z = 0)").clicked() {
+                    m.offset = Vector3::zeros();
+                    let floated = m.base.clone().float();
+                    old_base = Some(std::mem::replace(&mut m.base, floated));
+                    changed = true;
+                }
+                if ui.button("Center").clicked() {
+                    m.offset = Vector3::zeros();
+                    let centered = m.base.clone().center();
+                    old_base = Some(std::mem::replace(&mut m.base, centered));
+                    changed = true;
+                }
+
+                ui.horizontal(|ui| {
+                    let label = ui.label("X:");
+                    changed |= ui
+                        .add(egui::DragValue::new(&mut m.offset.x).speed(1.0))
+                        .labelled_by(label.id)
+                        .changed();
+                });
+                ui.horizontal(|ui| {
+                    let label = ui.label("Y:");
+                    changed |= ui
+                        .add(egui::DragValue::new(&mut m.offset.y).speed(1.0))
+                        .labelled_by(label.id)
+                        .changed();
+                });
+                ui.horizontal(|ui| {
+                    let label = ui.label("Z:");
+                    changed |= ui
+                        .add(egui::DragValue::new(&mut m.offset.z).speed(1.0))
+                        .labelled_by(label.id)
+                        .changed();
+                });
+
+                if ui.button("Reset position").clicked() {
+                    m.offset = Vector3::zeros();
+                    changed = true;
+                }
+
+                if changed {
+                    // Same trick: mark dirty without re-borrowing self.
+                    m.applied_offset = Vector3::repeat(f32::NAN);
+                }
+                (changed, old_base)
+            } else {
+                ui.label("No model selected");
+                (false, None)
+            }
+        });
+        if let Some((index, before)) = placed {
+            let scaled = scaled.body_returned.unwrap_or(false);
+            let (moved, old_base) = moved.body_returned.unwrap_or((false, None));
+            match old_base {
+                Some(old_base) => self.model_rebased(index, before, old_base),
+                None if scaled || moved => self.model_edited(index, before),
+                None => {}
+            }
+        }
+
+        ui.separator();
+        ui.collapsing("Model slicing overrides", |ui| {
+            let (perimeters, infill) = (self.perimeters, self.infill_settings.clone());
+            if let Some(m) = self.sel_mut() {
+                let o = &mut m.overrides;
+                override_value(ui, "Perimeters", &mut o.perimeters, perimeters, 0..=10);
+                override_value(ui, "Top layers", &mut o.top_layers, infill.top_layers, 0..=50);
+                override_value(ui, "Bottom layers", &mut o.bottom_layers, infill.bottom_layers, 0..=50);
+                override_value(ui, "Infill %", &mut o.density, infill.density, 0.0..=100.0);
+                if !o.is_empty() && ui.button("Use global settings").clicked() {
+                    *o = slicer::SliceOverrides::default();
+                }
+            } else {
+                ui.label("No model selected");
+            }
+        });
+
+        ui.separator();
+        ui.collapsing("Stock", |ui| self.stock_ui(ui));
+        ui.collapsing("Fixtures", |ui| self.fixtures_ui(ui));
+        if ui.button("send").clicked(){
+            // existing firmware case matches "g0"
+            self.send_motion("g0");
+        }
+        if ui.button("toggle").clicked() {
+            // Example: toggle wireframe state when this button is pressed
+            self.wireframe = !self.wireframe;
+        }
+    }
+
+    /// Camera snaps, display toggles and the work area.
+    fn view_pane(&mut self, ui: &mut egui::Ui) {
+        ui.label("Snap view");
+        ui.horizontal_wrapped(|ui| {
+            for snap in Snap::ALL {
+                if ui.button(snap.name()).clicked() {
+                    self.rotation = snap.rotation();
+                }
+            }
+        });
+
+        ui.checkbox(&mut self.quad_view, "Quad view")
+            .on_hover_text("Top, front and right orthographic views beside the perspective view");
+        ui.checkbox(&mut self.show_profiler, "Profiler")
+            .on_hover_text("Frame time, buffer sizes and triangle counts");
+        if ui.button("Reset layout").on_hover_text("Put every panel back in its default place").clicked() {
+            self.reset_layout();
+        }
+
+        ui.separator();
+        ui.checkbox(&mut self.edges, "edges");
+        ui.checkbox(&mut self.faces, "faces");
+        ui.checkbox(&mut self.normals, "normals");
+        ui.checkbox(&mut self.vertices, "vertices");
+        ui.checkbox(&mut self.workarea, "Work area");
+
+        ui.separator();
+        ui.collapsing("Work area (mm)", |ui| {
+            ui.horizontal(|ui| {
+                let label = ui.label("X:");
+                ui.add(egui::DragValue::new(&mut self.work_size.x).speed(1.0)).labelled_by(label.id);
+            });
+            ui.horizontal(|ui| {
+                let label = ui.label("Y:");
+                ui.add(egui::DragValue::new(&mut self.work_size.y).speed(1.0)).labelled_by(label.id);
+            });
+            ui.horizontal(|ui| {
+                let label = ui.label("Z:");
+                ui.add(egui::DragValue::new(&mut self.work_size.z).speed(1.0)).labelled_by(label.id);
+            });
+        });
+    }
+
+    /// Tool selection and the selected tool's settings.
+    fn tool_pane(&mut self, ui: &mut egui::Ui) {
+        // ── tool selector ──
+        ui.horizontal(|ui| {
+            ui.label("Tool:");
+            egui::ComboBox::from_id_salt("tool_select")
+                .selected_text(self.selected_tool.to_string())
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(
+                        &mut self.selected_tool,
+                        Tool::Laser,
+                        "Laser",
+                    );
+                    ui.selectable_value(
+                        &mut self.selected_tool,
+                        Tool::Plasma,
+                        "Plasma",
+                    );
+                    ui.selectable_value(
+                        &mut self.selected_tool,
+                        Tool::Extruder,
+                        "Extruder",
+                    );
+                    ui.selectable_value(
+                        &mut self.selected_tool,
+                        Tool::Endmill,
+                        "Endmill",
+                    );
+                    ui.selectable_value(
+                        &mut self.selected_tool,
+                        Tool::Drill,
+                        "Drill",
+                    );
+                    ui.selectable_value(
+                        &mut self.selected_tool,
+                        Tool::DlpLcd,
+                        "DLP / LCD",
+                    );
+                });
+        });
+
+        self.presets_ui(ui);
+        ui.separator();
+
+        // ── tool-specific widgets ──
+        match self.selected_tool {
+            Tool::Laser => {
+                ui.horizontal(|ui| {
+                    let label = ui.label("Kerf (mm):");
+                    ui.add(
+                        egui::DragValue::new(&mut self.kerf)
+                            .speed(0.01)
+                            .range(0.0..=5.0),
+                    )
+                    .labelled_by(label.id);
+                });
+                laser_settings_ui(ui, &mut self.laser);
+                common_line_ui(ui, &mut self.common_line, self.cut_paths.as_ref());
+                cut_order_ui(ui, &mut self.optimize_order, self.cut_plan.as_ref());
+                self.stock_fit_ui(ui);
+            }
+            Tool::Plasma => {
+                ui.checkbox(&mut self.touch_off, "Touch off")
+                    .on_hover_text("Probe the plate before every pierce and cut at heights above it");
+                plasma_settings_ui(ui, &mut self.plasma, self.touch_off);
+                common_line_ui(ui, &mut self.common_line, self.cut_paths.as_ref());
+                cut_order_ui(ui, &mut self.optimize_order, self.cut_plan.as_ref());
+                self.stock_fit_ui(ui);
+            }
+            Tool::Extruder => {
+                ui.horizontal(|ui| {
+                    let label = ui.label("Perimeters:");
+                    ui.add(
+                        egui::DragValue::new(&mut self.perimeters)
+                            .speed(1)
+                            .range(0..=10),
+                    )
+                    .labelled_by(label.id);
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Infill type:");
+                    egui::ComboBox::from_id_salt("infill_type")
+                        .selected_text(self.infill_type.to_string())
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(
+                                &mut self.infill_type,
+                                InfillType::Linear,
+                                "Linear",
+                            );
+                            ui.selectable_value(
+                                &mut self.infill_type,
+                                InfillType::Gyroid,
+                                "Gyroid",
+                            );
+                            ui.selectable_value(
+                                &mut self.infill_type,
+                                InfillType::SchwarzP,
+                                "Schwarz P",
+                            );
+                            ui.selectable_value(
+                                &mut self.infill_type,
+                                InfillType::SchwarzD,
+                                "Schwarz D",
+                            );
+                        });
+                });
+                ui.horizontal(|ui| {
+                    let label = ui.label("Line width (mm):");
+                    ui.add(
+                        egui::DragValue::new(&mut self.line_width)
+                            .speed(0.01)
+                            .range(0.1..=5.0),
+                    )
+                    .labelled_by(label.id);
+                });
+                infill_settings_ui(ui, &mut self.infill_settings);
+                seam_settings_ui(ui, &mut self.seam);
+                ui.collapsing("Bed adhesion", |ui| {
+                    adhesion_settings_ui(ui, &mut self.adhesion);
+                });
+                ui.collapsing("Printing", |ui| {
+                    extruder_settings_ui(ui, &mut self.extruder);
+                });
+                ui.collapsing("Retraction and travel", |ui| {
+                    retraction_settings_ui(ui, &mut self.retraction, self.print_preview.as_ref().map(|l| &l.moves));
+                });
+                ui.collapsing("Cooling fan", |ui| {
+                    let layer = usize::try_from(self.current_layer).unwrap_or(0);
+                    fan_settings_ui(ui, &mut self.fan, layer);
+                });
+                ui.collapsing("Pause at Z", |ui| {
+                    let top = self
+                        .layer_plan
+                        .as_ref()
+                        .and_then(|plan| plan.get(usize::try_from(self.current_layer).ok()?))
+                        .map(slicer::Layer::top);
+                    pause_heights_ui(ui, &mut self.pause_heights, top);
+                });
+                ui.collapsing("Supports", |ui| {
+                    support_settings_ui(ui, &mut self.support_settings, self.supports.as_ref().map(|(_, s)| s));
+                });
+            }
+            Tool::Endmill => {
+                if mill_ops_ui(ui, &mut self.mill_ops, self.mill_results.as_deref()) {
+                    self.compute_milling();
+                }
+                let passes = self.cam_paths.as_ref().map(|(p, _)| p.len());
+                let compute = ui.collapsing("2.5-D profile / pocket", |ui| cam_settings_ui(ui, &mut self.cam, passes)).body_returned;
+                if compute == Some(true) {
+                    self.compute_contours();
+                }
+                ui.collapsing("Spindle and feeds", |ui| {
+                    mill_settings_ui(ui, &mut self.mill);
+                });
+                ui.collapsing("Holder", |ui| {
+                    if holder_ui(ui, &mut self.holder) {
+                        self.mill_sim = None;
+                    }
+                });
+                ui.collapsing("Simulation", |ui| self.mill_simulation_ui(ui));
+                ui.horizontal(|ui| {
+                    let label = ui.label("Endmill width (mm):");
+                    ui.add(
+                        egui::DragValue::new(&mut self.endmill_width)
+                            .speed(0.1)
+                            .range(0.1..=100.0),
+                    )
+                    .labelled_by(label.id);
+                });
+                ui.horizontal(|ui| {
+                    let label = ui.label("Endmill length (mm):");
+                    ui.add(
+                        egui::DragValue::new(&mut self.endmill_length)
+                            .speed(0.1)
+                            .range(1.0..=300.0),
+                    )
+                    .labelled_by(label.id);
+                });
+            }
+            Tool::Drill => {
+                cut_order_ui(ui, &mut self.optimize_order, self.cut_plan.as_ref());
+                ui.horizontal(|ui| {
+                    let label = ui.label("Drill width (mm):");
+                    ui.add(
+                        egui::DragValue::new(&mut self.drill_width)
+                            .speed(0.1)
+                            .range(0.1..=100.0),
+                    )
+                    .labelled_by(label.id);
+                });
+                ui.horizontal(|ui| {
+                    let label = ui.label("Drill length (mm):");
+                    ui.add(
+                        egui::DragValue::new(&mut self.drill_length)
+                            .speed(0.1)
+                            .range(1.0..=300.0),
+                    )
+                    .labelled_by(label.id);
+                });
+                if let Some((plan, _)) = &self.cut_plan {
+                    let holes = plan.ops.iter().filter(|op| matches!(op, cutting::CutOp::Drill(_))).count();
+                    ui.small(format!("{holes} hole(s) match the drill width"));
+                }
+                ui.collapsing("Drilling cycle", |ui| {
+                    drill_settings_ui(ui, &mut self.drill);
+                });
+                self.stock_fit_ui(ui);
+            }
+            Tool::DlpLcd => {
+                ui.horizontal(|ui| {
+                    let label = ui.label("Pixels wide:");
+                    ui.add(
+                        egui::DragValue::new(&mut self.pixels_wide)
+                            .speed(1)
+                            .range(1..=8192),
+                    )
+                    .labelled_by(label.id);
+                });
+                ui.horizontal(|ui| {
+                    let label = ui.label("Pixels tall:");
+                    ui.add(
+                        egui::DragValue::new(&mut self.pixels_tall)
+                            .speed(1)
+                            .range(1..=8192),
+                    )
+                    .labelled_by(label.id);
+                });
+                ui.horizontal(|ui| {
+                    let label = ui.label("Layer delay (s):");
+                    ui.add(
+                        egui::DragValue::new(&mut self.layer_delay)
+                            .speed(0.1)
+                            .range(0.0..=60.0),
+                    )
+                    .labelled_by(label.id);
+                });
+                ui.horizontal(|ui| {
+                    let label = ui.label("Peel distance (mm):");
+                    ui.add(
+                        egui::DragValue::new(&mut self.peel_distance)
+                            .speed(0.1)
+                            .range(0.0..=100.0),
+                    )
+                    .labelled_by(label.id);
+                });
+                ui.collapsing("Exposure and lift", |ui| {
+                    resin_settings_ui(ui, &mut self.resin, self.peel_distance);
+                });
+                ui.collapsing("Hollowing", |ui| {
+                    hollow_settings_ui(ui, &mut self.resin.hollow);
+                });
+                let trapped = ui.collapsing("Trapped volumes", |ui| trapped_volumes_ui(ui, self.trapped.as_deref())).body_returned;
+                match trapped {
+                    Some(TrappedAction::Check) => self.find_trapped_volumes(),
+                    Some(TrappedAction::Show(layer)) => {
+                        self.current_layer = i32::try_from(layer).unwrap_or(0);
+                        self.refresh_slice();
+                    }
+                    Some(TrappedAction::None) | None => {}
+                }
+                ui.collapsing("Direct projection", |ui| self.projector_ui(ui));
+                ui.collapsing("Supports", |ui| {
+                    support_settings_ui(ui, &mut self.support_settings, self.supports.as_ref().map(|(_, s)| s));
+                });
+                if ui
+                    .add_enabled(!self.models.is_empty(), egui::Button::new("Export job (run.gcode)"))
+                    .on_hover_text("Layer commands of the resin job, with each layer's exposure, lift and pauses")
+                    .clicked()
+                {
+                    let plan = self.layer_plan().to_vec();
+                    let text = engine::resin_gcode(&plan, &self.resin, self.peel_distance, self.layer_delay);
+                    download_bytes("run.gcode", text.as_bytes());
+                }
+                self.mask_export_ui(ui);
+            }
+        }
+    }
+
+    /// Layer stack and slice preview.
+    fn slicing_pane(&mut self, ui: &mut egui::Ui) {
+        let before = self.layer_settings.clone();
+        ui.horizontal(|ui| {
+            let label = ui.label("Layer height (mm):");
+            ui.add(
+                egui::DragValue::new(&mut self.layer_settings.base_height)
+                    .speed(0.01)
+                    .range(0.01..=10.0),
+            )
+            .labelled_by(label.id);
+        });
+        ui.collapsing("Variable layer height", |ui| {
+            slicer_settings_ui(ui, &mut self.layer_settings);
+        });
+        ui.horizontal(|ui| {
+            let label = ui.label("Simplify contours (mm):");
+            let changed = ui
+                .add(egui::DragValue::new(&mut self.simplify_tolerance).speed(0.001).range(0.0..=1.0).max_decimals(3))
+                .labelled_by(label.id)
+                .on_hover_text("Drop slice points closer than this to a straight line; 0 keeps every point")
+                .changed();
+            if changed {
+                self.refresh_slice();
+            }
+        });
+        if let Some(stats) = self.simplify_stats.filter(|s| s.before > 0 && self.sliced_layer.is_some()) {
+            ui.small(format!(
+                "{} of {} points removed ({:.0} %)",
+                stats.removed(),
+                stats.before,
+                100.0 * stats.removed() as f64 / stats.before as f64
+            ));
+        }
+        if self.layer_settings != before {
+            self.invalidate_layers();
+            self.refresh_slice();
+        }
+
+        let plan_len = self.layer_plan().len();
+        ui.horizontal(|ui| {
+            let max_layers = i32::try_from(plan_len).unwrap_or(i32::MAX).saturating_sub(1).max(0);
+            let prev = self.current_layer;
+            let label = ui.label("Current layer:");
+            ui.add(
+                egui::DragValue::new(&mut self.current_layer)
+                    .range(0..=max_layers)
+                    .speed(1),
+            )
+            .labelled_by(label.id);
+            self.current_layer = self.current_layer.min(max_layers);
+            if self.current_layer != prev {
+                self.refresh_slice();
+            }
+        });
+        let pauses: Vec<usize> = if self.selected_tool == Tool::Extruder {
+            let plan = self.layer_plan.as_deref().unwrap_or_default();
+            self.pause_heights.iter().filter_map(|&h| plan.iter().position(|l| l.top() > h + 1e-4)).collect()
+        } else if self.selected_tool == Tool::DlpLcd {
+            self.resin.pauses(plan_len)
+        } else {
+            Vec::new()
+        };
+        if plan_len > 1 {
+            let mut layer = usize::try_from(self.current_layer).unwrap_or(0);
+            if layer_strip(ui, plan_len, &mut layer, &pauses) {
+                self.current_layer = i32::try_from(layer).unwrap_or(0);
+                self.refresh_slice();
+            }
+        }
+        let index = usize::try_from(self.current_layer).unwrap_or(0);
+        if let Some(layer) = self.layer_plan().get(index).copied() {
+            ui.small(format!(
+                "{plan_len} layers · z {:.2}–{:.2} mm · h {:.3} mm",
+                layer.z,
+                layer.top(),
+                layer.height
+            ));
+        }
+        if pauses.contains(&index) {
+            ui.small("⏸ The job pauses before this layer to insert hardware");
+        }
+        ui.horizontal(|ui| {
+            if ui.checkbox(&mut self.show_slice, "slice").changed() {
+                self.refresh_slice();
+            }
+            let slice = self.sliced_layer.as_ref().filter(|_| self.show_slice && !self.custom_plane);
+            let name = if self.flat_source().is_some() { "part".to_owned() } else { format!("layer-{}", index + 1) };
+            if ui.add_enabled(slice.is_some(), egui::Button::new("SVG")).on_hover_text("Download the slice, 1:1 in mm").clicked() {
+                if let Some(slice) = slice {
+                    download_bytes(&format!("{name}.svg"), drawing::svg(&engine::cut_paths(slice), &[]).as_bytes());
+                }
+            }
+            if ui.add_enabled(slice.is_some(), egui::Button::new("DXF")).on_hover_text("Download the slice in mm").clicked() {
+                if let Some(slice) = slice {
+                    download_bytes(&format!("{name}.dxf"), drawing::dxf(&engine::cut_paths(slice), &[]).as_bytes());
+                }
+            }
+            ui.add_enabled_ui(slice.is_some(), |ui| {
+                ui.menu_button("Print layout", |ui| {
+                    for paper in [drawing::Paper::A4, drawing::Paper::Letter] {
+                        if ui.button(format!("{paper} pages")).on_hover_text("1:1 PDF tiled over as many pages as needed").clicked() {
+                            if let Some(slice) = slice {
+                                download_bytes(&format!("{name}-print.pdf"), &drawing::print_pdf(&engine::cut_paths(slice), paper));
+                            }
+                            ui.close_menu();
+                        }
+                    }
+                });
+            });
+        });
+        if self.show_slice && self.selected_tool == Tool::Extruder {
+            self.feature_legend_ui(ui);
+        }
+        ui.collapsing("Layer statistics", |ui| self.layer_stats_ui(ui));
+        ui.collapsing("Slice plane", |ui| {
+            let before = (self.custom_plane, self.plane_normal, self.plane_offset);
+            ui.checkbox(&mut self.custom_plane, "Custom plane (instead of layers)");
+            ui.add_enabled_ui(self.custom_plane, |ui| {
+                ui.horizontal(|ui| {
+                    let label = ui.label("Normal:");
+                    ui.add(egui::DragValue::new(&mut self.plane_normal.x).speed(0.01).prefix("x ")).labelled_by(label.id);
+                    ui.add(egui::DragValue::new(&mut self.plane_normal.y).speed(0.01).prefix("y "));
+                    ui.add(egui::DragValue::new(&mut self.plane_normal.z).speed(0.01).prefix("z "));
+                });
+                ui.horizontal(|ui| {
+                    for (label, n) in [("X", Vector3::x()), ("Y", Vector3::y()), ("Z", Vector3::z())] {
+                        if ui.small_button(label).clicked() {
+                            self.plane_normal = n;
+                        }
+                    }
+                });
+                ui.horizontal(|ui| {
+                    let label = ui.label("Offset (mm):");
+                    ui.add(egui::DragValue::new(&mut self.plane_offset).speed(0.5)).labelled_by(label.id);
+                });
+            });
+            if (self.custom_plane, self.plane_normal, self.plane_offset) != before {
+                self.refresh_slice();
             }
+        });
+    }
 
-            // === Polygon normals ========================================================
-            if self.normals {
-                const NORMAL_COL: [f32; 3] = [1.0, 0.0, 0.0]; // red
-                let normal_len = (self.work_size.norm() * 0.04) as f32; // ≈ 4 % of diag
+    /// 3-D view (or quad view) of the scene.
+    fn viewport_pane(&mut self, ui: &mut egui::Ui, frame: &eframe::Frame) {
+        ui.set_min_size(ui.available_size());
+        let (full, _) = ui.allocate_exact_size(ui.available_size(), egui::Sense::hover());
 
-                for model_entry in &self.models {
-                    let model = &model_entry.mesh;
-                    for p in &model.polygons {
-                        // polygon centroid
-                        let mut c = Vector3::zeros();
-                        for v in &p.vertices {
-                            c += Vector3::new(v.pos.x as f32, v.pos.y as f32, v.pos.z as f32);
-                        }
-                        c /= p.vertices.len() as f32;
+        // ------------------------------------------------------------------
+        // Ask egui for the GL context once per frame
+        // ------------------------------------------------------------------
+        if let Some(gl) = frame.gl() {
+            // ── 1) create once ─────────────────────────────────────────────
+            if self.gpu.is_none() {
+                self.gpu =
+                    Some(Arc::new(Mutex::new(unsafe { renderer::GpuLines::new(gl) })));
+            }
 
-                        let n = Vector3::<f32>::new(
-                            p.plane.normal().x as f32,
-                            p.plane.normal().y as f32,
-                            p.plane.normal().z as f32,
-                        )
-                        .normalize()
-                            * normal_len;
+            // ── 2) keep vertex buffer in sync (shared by every pane) ──────
+            let started = now_ms();
+            unsafe { self.sync_buffers(gl) };
+            self.profile.sync_ms = now_ms() - started;
+        }
 
-                        self.vertex_storage.extend_from_slice(&[
-                            c.x,
-                            c.y,
-                            c.z,
-                            NORMAL_COL[0],
-                            NORMAL_COL[1],
-                            NORMAL_COL[2],
-                            c.x + n.x,
-                            c.y + n.y,
-                            c.z + n.z,
-                            NORMAL_COL[0],
-                            NORMAL_COL[1],
-                            NORMAL_COL[2],
-                        ]);
-                    }
-                }
+        if self.quad_view {
+            // top-left, top-right, bottom-left: orthographic; bottom-right: perspective
+            let half = full.size() * 0.5;
+            let cell = |col: f32, row: f32| {
+                egui::Rect::from_min_size(full.min + egui::vec2(col * half.x, row * half.y), half).shrink(1.0)
+            };
+            for (k, rect) in [cell(0.0, 0.0), cell(1.0, 0.0), cell(0.0, 1.0)].into_iter().enumerate() {
+                self.ortho_pane(ui, k, rect);
             }
+            self.perspective_pane(ui, cell(1.0, 1.0));
+            let stroke = ui.visuals().widgets.noninteractive.bg_stroke;
+            ui.painter().vline(full.center().x, full.y_range(), stroke);
+            ui.painter().hline(full.x_range(), full.center().y, stroke);
+        } else {
+            self.perspective_pane(ui, full);
+        }
 
-            // === Vertex spheres =========================================================
-            if self.vertices {
-                const VERT_COL: [f32; 3] = [1.0, 1.0, 0.0]; // yellow
-                let r = (self.work_size.norm() * 0.005) as f32; // ≈ 0.5 % of diag
+        if self.show_profiler {
+            let models: Vec<(&str, usize)> = self
+                .models
+                .iter()
+                .map(|m| (m.name.as_str(), profiler::triangle_count(&m.mesh)))
+                .collect();
+            self.profile.show(ui, full, &models);
+            ui.ctx().request_repaint();
+        }
+    }
 
-                // de-dupe identical vertices so we don’t draw the same sphere many times
-                let mut seen: HashSet<(i64, i64, i64)> = HashSet::new();
-                let quant = 1_000_000.0; // 1 µm grid
+    /// Controller diagnostics: Wi-Fi, pins, health, capture and scripts.
+    fn diag_controls_pane(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            if ui.button("Scan Wi-Fi").clicked() {
+                send_queue_command("scan_wifi");
+            }
+            if ui.button("Set Wi-Fi").clicked() {
+                // current firmware just logs;
+                send_queue_command("set_wifi");
+            }
+        });
+        ui.separator();
+        ui.checkbox(&mut self.diag_poll,"Poll");
+        if ui.checkbox(&mut self.diag_led,"Status LED").changed(){
+            if self.diag_led { send_queue_command("status_on"); }
+            else { send_queue_command("status_off"); }
+        }
+        ui.separator();
+        ui.label("GPIO pins");
+        for pin in &self.machine.pins {
+            let on = self.diag_pin_on.entry(pin.name.clone()).or_insert(false);
+            match (pin.direction, pin.kind) {
+                (machine::PinDirection::Output, machine::PinKind::Digital) => {
+                    if ui.checkbox(on, &pin.name).changed() {
+                        send_queue_command(pin.level_command(*on));
+                    }
+                }
+                (machine::PinDirection::Output, machine::PinKind::Analog) => {
+                    // on writes the level, off writes 0; the level is sent as it changes while on
+                    ui.horizontal(|ui| {
+                        let switched = ui.checkbox(on, &pin.name).changed();
+                        let level = self.diag_pin_level.entry(pin.name.clone()).or_insert(0);
+                        let changed = ui.add(egui::DragValue::new(level).range(0..=1023)).changed();
+                        if switched || (changed && *on) {
+                            send_queue_command(pin.write_command(if *on { *level } else { 0 }));
+                        }
+                    });
+                }
+                // inputs are only sampled; the checkbox selects them for plotting
+                (machine::PinDirection::Input, _) => {
+                    ui.checkbox(on, format!("{} (in)", pin.name));
+                }
+            }
+        }
 
-                for model_entry in &self.models {
-                    let model = &model_entry.mesh;
-                    for p in &model.polygons {
-                        for v in &p.vertices {
-                            let key = (
-                                (v.pos.x * quant) as i64,
-                                (v.pos.y * quant) as i64,
-                                (v.pos.z * quant) as i64,
-                            );
-                            if seen.insert(key) {
-                                let c =
-                                    Vector3::new(v.pos.x as f32, v.pos.y as f32, v.pos.z as f32);
-                                add_vertex_sphere(c, r, VERT_COL, &mut faces);
+        ui.collapsing("Pin map", |ui| {
+            let mut changed = false;
+            let mut remove: Option<usize> = None;
+            egui::Grid::new("pin_map").num_columns(5).show(ui, |ui| {
+                ui.label("name");
+                ui.label("prefix");
+                ui.label("dir");
+                ui.label("type");
+                ui.end_row();
+                for (i, pin) in self.machine.pins.iter_mut().enumerate() {
+                    changed |= ui
+                        .add(egui::TextEdit::singleline(&mut pin.name).desired_width(40.0))
+                        .changed();
+                    changed |= ui
+                        .add(egui::TextEdit::singleline(&mut pin.prefix).desired_width(40.0))
+                        .changed();
+                    egui::ComboBox::from_id_salt(("pin_dir", i))
+                        .selected_text(pin.direction.to_string())
+                        .width(48.0)
+                        .show_ui(ui, |ui| {
+                            for d in [machine::PinDirection::Input, machine::PinDirection::Output] {
+                                changed |= ui.selectable_value(&mut pin.direction, d, d.to_string()).changed();
                             }
-                        }
+                        });
+                    egui::ComboBox::from_id_salt(("pin_kind", i))
+                        .selected_text(pin.kind.to_string())
+                        .width(60.0)
+                        .show_ui(ui, |ui| {
+                            for k in [machine::PinKind::Digital, machine::PinKind::Analog] {
+                                changed |= ui.selectable_value(&mut pin.kind, k, k.to_string()).changed();
+                            }
+                        });
+                    let name = &pin.name;
+                    if ui
+                        .button("x")
+                        .on_hover_text("Remove pin")
+                        .widget_info(|| egui::WidgetInfo::labeled(egui::WidgetType::Button, true, format!("Remove pin {name}")))
+                        .clicked()
+                    {
+                        remove = Some(i);
                     }
+                    ui.end_row();
                 }
+            });
+            if let Some(i) = remove {
+                self.machine.pins.remove(i);
+                changed = true;
             }
-        }
+            ui.horizontal(|ui| {
+                if ui.button("Add pin").clicked() {
+                    let name = format!("P{}", self.machine.pins.len());
+                    self.machine.pins.push(machine::PinDef::digital_out(&name));
+                    changed = true;
+                }
+                if ui.button("Reset").clicked() {
+                    self.machine.pins = machine::MachineProfile::default().pins;
+                    changed = true;
+                }
+            });
+            if changed {
+                self.machine.save();
+            }
+        });
 
-        // ---------- upload / (re-)create VBOs -----------------------------------
-        if let Some(lines_gpu) = &self.gpu {
-            if let Ok(mut g) = lines_gpu.lock() {
-                unsafe { g.upload_vertices(gl, &self.vertex_storage) };
+        ui.separator();
+        ui.horizontal(|ui| {
+            let busy = self.diag_scan_reply.is_some();
+            if ui.add_enabled(!busy, egui::Button::new("Scan I²C / SPI")).clicked() {
+                self.diag_log("bus scan requested");
+                self.diag_scan_reply = Some(net::spawn(net::Endpoint::Queue("bus_scan".into())));
+            }
+            if busy {
+                ui.spinner();
+            }
+        });
+        if let Some(scan) = &self.diag_last_scan {
+            for a in &scan.i2c {
+                ui.small(format!(
+                    "0x{a:02X} {}",
+                    diagnostics::known_i2c_device(*a).unwrap_or("?")
+                ));
+            }
+            for d in &scan.spi {
+                ui.small(format!("SPI {d}"));
             }
         }
 
-        // Faces VBO is present only while “faces” is checked
-        let need_tris = !faces.is_empty();
-        if need_tris {
-            let faces_gpu = self.gpu_faces.get_or_insert_with(|| {
-                Arc::new(Mutex::new(unsafe { renderer::GpuLines::new(gl) }))
+        ui.separator();
+        ui.collapsing("Telemetry", |ui| {
+            let t = &mut self.diag_telemetry;
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut t.enabled, "Stream");
+                let label = ui.label("Path:");
+                ui.add_enabled(!t.enabled, egui::TextEdit::singleline(&mut t.path).desired_width(80.0)).labelled_by(label.id);
             });
-            if let Ok(mut g) = faces_gpu.lock() {
-                unsafe { g.upload_vertices(gl, &faces) };
+            if let Some(state) = t.state() {
+                ui.small(format!("WebSocket {state}"));
             }
-        } else {
-            self.gpu_faces = None;
-        }
-    }
-}
-
-impl eframe::App for AluminaApp {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        egui::TopBottomPanel::top("tab_bar").show(ctx, |ui| {
+            // series selector: ticked series are plotted alongside the pins
+            for name in &t.series {
+                let on = self.diag_pin_on.entry(name.clone()).or_default();
+                ui.checkbox(on, name);
+            }
+        });
+        ui.collapsing("Connection health", |ui| {
+            let h = &mut self.diag_health;
+            ui.checkbox(&mut h.enabled, "Monitor");
             ui.horizontal(|ui| {
-                ui.selectable_value(&mut self.selected_tab, Tab::Diagnostics, "Diagnostics");
-                ui.selectable_value(&mut self.selected_tab, Tab::Design, "Design");
-                ui.selectable_value(&mut self.selected_tab, Tab::Control, "Control");
+                let label = ui.label("Ping:");
+                ui.add(egui::TextEdit::singleline(&mut h.path).desired_width(80.0)).labelled_by(label.id);
             });
+            ui.horizontal(|ui| {
+                let label = ui.label("Every (ms):");
+                ui.add(egui::DragValue::new(&mut h.interval_ms).range(100.0..=60_000.0)).labelled_by(label.id);
+            });
+            ui.horizontal(|ui| {
+                let label = ui.label("Warn RTT (ms):");
+                ui.add(egui::DragValue::new(&mut h.warn_latency_ms).range(1.0..=10_000.0)).labelled_by(label.id);
+            });
+            ui.horizontal(|ui| {
+                let label = ui.label("Warn loss (%):");
+                ui.add(egui::DragValue::new(&mut h.warn_loss_pct).range(0.0..=100.0)).labelled_by(label.id);
+            });
+            let st = h.stats();
+            let col = if h.is_degraded() {
+                ui.visuals().warn_fg_color
+            } else {
+                ui.visuals().text_color()
+            };
+            ui.colored_label(
+                col,
+                format!(
+                    "RTT {:.0} ms (max {:.0}), loss {:.1} % of {}",
+                    st.mean_ms, st.max_ms, st.loss_pct, st.sent
+                ),
+            );
+            Plot::new("rtt_plot")
+                .height(100.0)
+                .allow_scroll(false)
+                .show(ui, |plot_ui| {
+                    plot_ui.line(Line::new(PlotPoints::from(h.rtt_points())).name("RTT (ms)"));
+                    plot_ui.points(
+                        Points::new(PlotPoints::from(h.lost_points()))
+                            .radius(3.0)
+                            .color(egui::Color32::RED)
+                            .name("lost"),
+                    );
+                    plot_ui.hline(HLine::new(h.warn_latency_ms).name("warn"));
+                });
+            if ui.button("Reset stats").clicked() {
+                h.clear();
+            }
         });
 
-        match self.selected_tab {
-            Tab::Control => {
-                // ------------------------------------------------------------------
-                // Sidebar
-                // ------------------------------------------------------------------
-                egui::SidePanel::left("side_panel")
-                    .resizable(false)
-                    .min_width(140.0)
-                    .show(ctx, |ui| {
-                        ui.heading("Control");
-                        ui.separator();
-
-                        ui.label("Loaded models");
-                        let mut remove: Option<usize> = None;
-                        for (i, m) in self.models.iter_mut().enumerate() {
-                            ui.horizontal(|ui| {
-                                if ui
-                                    .selectable_label(self.selected_model == Some(i), &m.name)
-                                    .clicked()
-                                {
-                                    self.selected_model = Some(i);
-                                }
-                                if ui.button("x").clicked() {
-                                    remove = Some(i);
-                                }
-                            });
+        ui.separator();
+        ui.collapsing("Trigger capture", |ui| {
+            let cap = &mut self.diag_capture;
+            ui.horizontal(|ui| {
+                ui.label("Source:");
+                egui::ComboBox::from_id_salt("capture_source")
+                    .selected_text(cap.source.clone())
+                    .show_ui(ui, |ui| {
+                        for pin in &self.machine.pins {
+                            ui.selectable_value(&mut cap.source, pin.name.clone(), &pin.name);
                         }
-                        if ui.button("Add…").clicked() {
-                            self.selected_model = None; // -> add after file dialog
-                            spawn_file_picker(
-                                Arc::clone(&self.model_data),
-                                "Model mesh (stl,dxf)",
-                                &["stl", "dxf", "obj", "ply", "amf"],
-                            );
+                    });
+            });
+            ui.horizontal(|ui| {
+                ui.label("Edge:");
+                egui::ComboBox::from_id_salt("capture_edge")
+                    .selected_text(cap.edge.to_string())
+                    .show_ui(ui, |ui| {
+                        for e in [
+                            diagnostics::TriggerEdge::Rising,
+                            diagnostics::TriggerEdge::Falling,
+                            diagnostics::TriggerEdge::Either,
+                        ] {
+                            ui.selectable_value(&mut cap.edge, e, e.to_string());
                         }
-                        if let Some(idx) = remove {
-                            self.models.remove(idx);
-                            self.clamp_selection();
+                    });
+            });
+            ui.horizontal(|ui| {
+                let label = ui.label("Threshold:");
+                ui.add(egui::DragValue::new(&mut cap.threshold).speed(0.01)).labelled_by(label.id);
+            });
+            ui.horizontal(|ui| {
+                let label = ui.label("Pre / post:");
+                ui.add(egui::DragValue::new(&mut cap.pre_samples).range(0..=1000)).labelled_by(label.id);
+                ui.add(egui::DragValue::new(&mut cap.post_samples).range(0..=1000));
+            });
+            ui.horizontal(|ui| {
+                use diagnostics::CaptureState;
+                match cap.state() {
+                    CaptureState::Idle => {
+                        if ui.button("Arm").clicked() {
+                            cap.arm();
                         }
+                    }
+                    CaptureState::Armed | CaptureState::Triggered => {
+                        ui.spinner();
+                        ui.label(if cap.state() == CaptureState::Armed { "armed" } else { "recording" });
+                        if ui.button("Cancel").clicked() {
+                            cap.release();
+                        }
+                    }
+                    CaptureState::Frozen => {
+                        if ui.button("Re-arm").clicked() {
+                            cap.arm();
+                        }
+                        if ui.button("Release").clicked() {
+                            cap.release();
+                        }
+                    }
+                }
+            });
+            if cap.state() != diagnostics::CaptureState::Idle && !self.diag_poll {
+                ui.small("Enable Poll to feed the trigger.");
+            }
+        });
 
-                        ui.separator();
-                        ui.label("Snap view");
-                        ui.horizontal_wrapped(|ui| {
-                            let pitch =
-                                UnitQuaternion::from_axis_angle(&Vector3::x_axis(), -FRAC_PI_2); //  -90° about X  (Z-up ➜ Y-up)
-                            if ui.button("Front").clicked() {
-                                self.rotation = pitch;
-                            } //  -90° about X
-                            if ui.button("Back").clicked() {
-                                self.rotation =
-                                    pitch * UnitQuaternion::from_axis_angle(&Vector3::z_axis(), PI);
-                            } // 180° roll
-                            if ui.button("Left").clicked() {
-                                self.rotation =
-                                    UnitQuaternion::from_axis_angle(&Vector3::y_axis(), FRAC_PI_2)
-                                        * pitch;
-                            } // +90° yaw
-                            if ui.button("Right").clicked() {
-                                self.rotation =
-                                    UnitQuaternion::from_axis_angle(&Vector3::y_axis(), -FRAC_PI_2)
-                                        * pitch;
-                            } // –90° yaw
-                            if ui.button("Top").clicked() {
-                                self.rotation = UnitQuaternion::identity();
-                            } // no change
-                            if ui.button("Bottom").clicked() {
-                                self.rotation =
-                                    UnitQuaternion::from_axis_angle(&Vector3::x_axis(), PI);
-                            } // look from below
-                        });
-
-                        ui.separator();
-                        ui.checkbox(&mut self.edges, "edges");
-                        ui.checkbox(&mut self.faces, "faces");
-                        ui.checkbox(&mut self.normals, "normals");
-                        ui.checkbox(&mut self.vertices, "vertices");
-                        ui.checkbox(&mut self.workarea, "Work area");
-
-                        // ────────────── Scale Controls ──────────────
-                        ui.separator();
-                        ui.collapsing("Model scale", |ui| {
-                            // --- 1. borrow models[idx] once --------------------
-                            if let Some(m) = self.sel_mut() {
-                                // Track whether any DragValue changed
-                                let mut changed = false;
-
-                                ui.horizontal(|ui| {
-                                    ui.label("X:");
-                                    changed |= ui
-                                        .add(
-                                            egui::DragValue::new(&mut m.scale.x)
-                                                .speed(0.01)
-                                                .range(0.01..=100.0),
-                                        )
-                                        .changed();
-                                });
-                                ui.horizontal(|ui| {
-                                    ui.label("Y:");
-                                    changed |= ui
-                                        .add(
-                                            egui::DragValue::new(&mut m.scale.y)
-                                                .speed(0.01)
-                                                .range(0.01..=100.0),
-                                        )
-                                        .changed();
-                                });
-                                ui.horizontal(|ui| {
-                                    ui.label("Z:");
-                                    changed |= ui
-                                        .add(
-                                            egui::DragValue::new(&mut m.scale.z)
-                                                .speed(0.01)
-                                                .range(0.01..=100.0),
-                                        )
-                                        .changed();
-                                });
-
-                                if ui.button("Reset scale").clicked() {
-                                    m.scale = Vector3::new(1.0, 1.0, 1.0);
-                                    changed = true;
-                                }
+        ui.separator();
+        ui.collapsing("Test script", |ui| {
+            ui.add(
+                egui::TextEdit::multiline(&mut self.diag_script)
+                    .code_editor()
+                    .desired_rows(8)
+                    .desired_width(f32::INFINITY),
+            );
+            let running = self.diag_runner.is_running();
+            ui.horizontal(|ui| {
+                if ui.add_enabled(!running, egui::Button::new("Run")).clicked() {
+                    match diagnostics::parse_script(&self.diag_script) {
+                        Ok(steps) => {
+                            self.diag_log(format!("script started ({} steps)", steps.len()));
+                            self.diag_runner.start(steps);
+                        }
+                        Err(e) => self.diag_log(format!("script error: {e}")),
+                    }
+                }
+                if ui.add_enabled(running, egui::Button::new("Stop")).clicked() {
+                    self.diag_runner.stop();
+                    self.diag_log("script aborted");
+                }
+            });
+        });
+    }
 
-                                // Invalidate *through the same mutable borrow*.
-                                if changed {
-                                    m.applied_scale = INVALID_SCALE;
-                                }
-                            } else {
-                                ui.label("No model selected");
-                            }
-                            // --- m is dropped here; safe to touch self again if you need to ---
-                        });
+    /// Live (or captured) pin values.
+    fn diag_plot_pane(&mut self, ui: &mut egui::Ui) {
+        Plot::new("diag_plot")
+            .width(ui.available_width())
+            .height(ui.available_height())
+            .show(ui, |plot_ui| {
+                // A frozen capture replaces the live view until released
+                if let Some((frozen, t0)) = self.diag_capture.frozen() {
+                    for (name, series) in frozen {
+                        if self.is_pin_checked(name) || *name == self.diag_capture.source {
+                            plot_ui.line(Line::new(PlotPoints::from(series.clone())).name(name.clone()));
+                        }
+                    }
+                    plot_ui.vline(VLine::new(t0).name("trigger"));
+                    plot_ui.hline(HLine::new(self.diag_capture.threshold).name("threshold"));
+                    return;
+                }
+                // Draw a series per *checked* pin that has data
+                for (name, series) in &self.diag_series {
+                    if self.is_pin_checked(name) && !series.is_empty() {
+                        let points = PlotPoints::from(series.clone());
+                        plot_ui.line(Line::new(points).name(name.clone()));
+                    }
+                }
+            });
+    }
 
-                        // ────────────── Position Controls ──────────────
-                        ui.separator();
-                        ui.collapsing("Model position", |ui| {
-                            if let Some(m) = self.sel_mut() {
-                                let mut changed = false;
-
-                                if ui.button("Float (Z = 0)").clicked() {
-                                    m.offset = Vector3::zeros();
-                                    m.base = m.base.clone().float();
-                                    changed = true;
-                                }
-                                if ui.button("Center").clicked() {
-                                    m.offset = Vector3::zeros();
-                                    m.base = m.base.clone().center();
-                                    changed = true;
-                                }
+    /// Diagnostic log.
+    fn diag_console_pane(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.heading("Console");
+            if ui.button("Clear").clicked() {
+                self.diag_console.clear();
+                self.diag_series.clear();
+            }
+            if ui.button("Refresh queue").clicked() {
+                execute(async {
+                    match net::fetch(&net::Endpoint::Get("/queue".into())).await {
+                        Ok(s) => log::info!("/queue: {}", s),
+                        Err(e) => log::error!("GET /queue failed: {e}"),
+                    }
+                });
+            }
+        });
 
-                                ui.horizontal(|ui| {
-                                    ui.label("X:");
-                                    changed |= ui
-                                        .add(egui::DragValue::new(&mut m.offset.x).speed(1.0))
-                                        .changed();
-                                });
-                                ui.horizontal(|ui| {
-                                    ui.label("Y:");
-                                    changed |= ui
-                                        .add(egui::DragValue::new(&mut m.offset.y).speed(1.0))
-                                        .changed();
-                                });
-                                ui.horizontal(|ui| {
-                                    ui.label("Z:");
-                                    changed |= ui
-                                        .add(egui::DragValue::new(&mut m.offset.z).speed(1.0))
-                                        .changed();
-                                });
-
-                                if ui.button("Reset position").clicked() {
-                                    m.offset = Vector3::zeros();
-                                    changed = true;
-                                }
+        // Make the log fill the remainder of this half
+        egui::ScrollArea::vertical()
+            .stick_to_bottom(true)
+            .show(ui, |ui| {
+                let te = egui::TextEdit::multiline(&mut self.diag_console)
+                    .desired_width(f32::INFINITY)
+                    .interactive(false);
+                ui.add_sized(ui.available_size(), te);
+            });
+    }
+}
 
-                                if changed {
-                                    // Same trick: mark dirty without re-borrowing self.
-                                    m.applied_offset = Vector3::repeat(f32::NAN);
-                                }
-                            } else {
-                                ui.label("No model selected");
-                            }
-                        });
+impl eframe::App for AluminaApp {
+    fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+        self.profile.frame(now_ms());
+        self.tick_machine(ctx);
 
-                        ui.separator();
-                        ui.collapsing("Work area (mm)", |ui| {
-                            ui.horizontal(|ui| {
-                                ui.label("X:");
-                                ui.add(egui::DragValue::new(&mut self.work_size.x).speed(1.0));
-                            });
-                            ui.horizontal(|ui| {
-                                ui.label("Y:");
-                                ui.add(egui::DragValue::new(&mut self.work_size.y).speed(1.0));
-                            });
-                            ui.horizontal(|ui| {
-                                ui.label("Z:");
-                                ui.add(egui::DragValue::new(&mut self.work_size.z).speed(1.0));
-                            });
-                        });
+        // Bus scan reply
+        let scan_done = self
+            .diag_scan_reply
+            .as_ref()
+            .and_then(|slot| slot.lock().unwrap().take());
+        if let Some(result) = scan_done {
+            self.diag_scan_reply = None;
+            match result.and_then(|body| diagnostics::parse_bus_scan(&body)) {
+                Ok(scan) => {
+                    for line in scan.report() {
+                        self.diag_log(line);
+                    }
+                    self.diag_last_scan = Some(scan);
+                }
+                Err(e) => toasts::error("Bus scan failed", Some(e)),
+            }
+        } else if self.diag_scan_reply.is_some() {
+            ctx.request_repaint_after(std::time::Duration::from_millis(100));
+        }
 
-                        ui.separator();
-                        ui.collapsing("Tool settings", |ui| {
-                            // ── tool selector ──
-                            ui.horizontal(|ui| {
-                                ui.label("Tool:");
-                                egui::ComboBox::from_id_salt("tool_select")
-                                    .selected_text(self.selected_tool.to_string())
-                                    .show_ui(ui, |ui| {
-                                        ui.selectable_value(
-                                            &mut self.selected_tool,
-                                            Tool::Laser,
-                                            "Laser",
-                                        );
-                                        ui.selectable_value(
-                                            &mut self.selected_tool,
-                                            Tool::Plasma,
-                                            "Plasma",
-                                        );
-                                        ui.selectable_value(
-                                            &mut self.selected_tool,
-                                            Tool::Extruder,
-                                            "Extruder",
-                                        );
-                                        ui.selectable_value(
-                                            &mut self.selected_tool,
-                                            Tool::Endmill,
-                                            "Endmill",
-                                        );
-                                        ui.selectable_value(
-                                            &mut self.selected_tool,
-                                            Tool::Drill,
-                                            "Drill",
-                                        );
-                                        ui.selectable_value(
-                                            &mut self.selected_tool,
-                                            Tool::DlpLcd,
-                                            "DLP / LCD",
-                                        );
-                                    });
-                            });
-
-                            // ── tool-specific widgets ──
-                            match self.selected_tool {
-                                Tool::Laser => {
-                                    ui.horizontal(|ui| {
-                                        ui.label("Kerf (mm):");
-                                        ui.add(
-                                            egui::DragValue::new(&mut self.kerf)
-                                                .speed(0.01)
-                                                .range(0.0..=5.0),
-                                        );
-                                    });
-                                }
-                                Tool::Plasma => {
-                                    ui.checkbox(&mut self.touch_off, "Touch off");
-                                }
-                                Tool::Extruder => {
-                                    ui.horizontal(|ui| {
-                                        ui.label("Perimeters:");
-                                        ui.add(
-                                            egui::DragValue::new(&mut self.perimeters)
-                                                .speed(1)
-                                                .range(0..=10),
-                                        );
-                                    });
-                                    ui.horizontal(|ui| {
-                                        ui.label("Infill type:");
-                                        egui::ComboBox::from_id_salt("infill_type")
-                                            .selected_text(self.infill_type.to_string())
-                                            .show_ui(ui, |ui| {
-                                                ui.selectable_value(
-                                                    &mut self.infill_type,
-                                                    InfillType::Linear,
-                                                    "Linear",
-                                                );
-                                                ui.selectable_value(
-                                                    &mut self.infill_type,
-                                                    InfillType::Gyroid,
-                                                    "Gyroid",
-                                                );
-                                                ui.selectable_value(
-                                                    &mut self.infill_type,
-                                                    InfillType::SchwarzP,
-                                                    "Schwarz P",
-                                                );
-                                                ui.selectable_value(
-                                                    &mut self.infill_type,
-                                                    InfillType::SchwarzD,
-                                                    "Schwarz D",
-                                                );
-                                            });
-                                    });
-                                }
-                                Tool::Endmill => {
-                                    ui.horizontal(|ui| {
-                                        ui.label("Endmill width (mm):");
-                                        ui.add(
-                                            egui::DragValue::new(&mut self.endmill_width)
-                                                .speed(0.1)
-                                                .range(0.1..=100.0),
-                                        );
-                                    });
-                                    ui.horizontal(|ui| {
-                                        ui.label("Endmill length (mm):");
-                                        ui.add(
-                                            egui::DragValue::new(&mut self.endmill_length)
-                                                .speed(0.1)
-                                                .range(1.0..=300.0),
-                                        );
-                                    });
-                                }
-                                Tool::Drill => {
-                                    ui.horizontal(|ui| {
-                                        ui.label("Drill width (mm):");
-                                        ui.add(
-                                            egui::DragValue::new(&mut self.drill_width)
-                                                .speed(0.1)
-                                                .range(0.1..=100.0),
-                                        );
-                                    });
-                                    ui.horizontal(|ui| {
-                                        ui.label("Drill length (mm):");
-                                        ui.add(
-                                            egui::DragValue::new(&mut self.drill_length)
-                                                .speed(0.1)
-                                                .range(1.0..=300.0),
-                                        );
-                                    });
-                                }
-                                Tool::DlpLcd => {
-                                    ui.horizontal(|ui| {
-                                        ui.label("Pixels wide:");
-                                        ui.add(
-                                            egui::DragValue::new(&mut self.pixels_wide)
-                                                .speed(1)
-                                                .range(1..=8192),
-                                        );
-                                    });
-                                    ui.horizontal(|ui| {
-                                        ui.label("Pixels tall:");
-                                        ui.add(
-                                            egui::DragValue::new(&mut self.pixels_tall)
-                                                .speed(1)
-                                                .range(1..=8192),
-                                        );
-                                    });
-                                    ui.horizontal(|ui| {
-                                        ui.label("Layer delay (s):");
-                                        ui.add(
-                                            egui::DragValue::new(&mut self.layer_delay)
-                                                .speed(0.1)
-                                                .range(0.0..=60.0),
-                                        );
-                                    });
-                                    ui.horizontal(|ui| {
-                                        ui.label("Peel distance (mm):");
-                                        ui.add(
-                                            egui::DragValue::new(&mut self.peel_distance)
-                                                .speed(0.1)
-                                                .range(0.0..=100.0),
-                                        );
-                                    });
-                                }
-                            }
-                        });
+        // Connection health pings (a last outstanding ping is still collected after disabling)
+        if let Some(line) = self.diag_health.tick(now_ms()) {
+            if self.diag_health.is_degraded() {
+                log::warn!("{line}");
+                toasts::warn("Controller link degraded", Some(line));
+            } else {
+                self.diag_log(line);
+            }
+        }
+        if self.diag_health.enabled {
+            ctx.request_repaint_after(std::time::Duration::from_millis(100));
+        }
 
-                        ui.separator();
-                        ui.horizontal(|ui| {
-                            ui.label("Layer height (mm):");
-                            ui.add(
-                                egui::DragValue::new(&mut self.layer_height)
-                                    .speed(0.01)
-                                    .range(0.01..=10.0),
-                            );
-                        });
+        // Telemetry samples go straight into the plot series
+        let (samples, line) = self.diag_telemetry.tick(now_ms() / 1000.0);
+        if let Some(line) = line {
+            self.diag_log(line);
+        }
+        for (t, values) in samples {
+            for (name, v) in values {
+                self.diag_pin_on.entry(name.clone()).or_insert(true);
+                self.diag_push_point_named(&name, t, v);
+            }
+        }
+        if self.diag_telemetry.enabled {
+            ctx.request_repaint_after(std::time::Duration::from_millis(100));
+        }
 
-                        ui.horizontal(|ui| {
-                            let max_layers = (self.work_size.z / self.layer_height).floor() as i32;
-                            let prev = self.current_layer;
-                            ui.label("Current layer:");
-                            ui.add(
-                                egui::DragValue::new(&mut self.current_layer)
-                                    .range(0..=max_layers)
-                                    .speed(1),
-                            );
-                            if self.current_layer != prev {
-                                self.refresh_slice();
-                            }
-                        });
-                        if ui.checkbox(&mut self.show_slice, "slice").changed() {
-                            self.refresh_slice();
-                        }
+        // Test scripts keep running regardless of the visible tab
+        if self.diag_runner.is_running() {
+            for line in self.diag_runner.tick(now_ms()) {
+                self.diag_log(line);
+            }
+            ctx.request_repaint_after(std::time::Duration::from_millis(50));
+        }
 
-                        ui.separator();
-                        if ui.button("load workpiece").clicked() {
-                            spawn_file_picker(
-                                Arc::clone(&self.workpiece_data),
-                                "Workpiece mesh (stl,dxf)",
-                                &["stl", "dxf"],
-                            );
-                        }
-                        if ui.button("send").clicked(){
-							// existing firmware case matches "g0"
-							send_queue_command("g0");
-						}
-                        if ui.button("toggle").clicked() {
-                            // Example: toggle wireframe state when this button is pressed
-                            self.wireframe = !self.wireframe;
-                        }
-                    });
+        egui::TopBottomPanel::top("tab_bar").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.selectable_value(&mut self.selected_tab, Tab::Diagnostics, "Diagnostics");
+                ui.selectable_value(&mut self.selected_tab, Tab::Design, "Design");
+                ui.selectable_value(&mut self.selected_tab, Tab::Control, "Control");
+                ui.separator();
+                ui.toggle_value(&mut self.show_plugins, "Plugins");
+                if ui.button("⌘ Commands").on_hover_text("Command palette (Ctrl+K)").clicked() {
+                    self.palette.toggle();
+                }
+                ui.separator();
+                if ui.button("Save project").on_hover_text("Models, design graph, work area and tool settings as one .alumina file").clicked() {
+                    self.save_project();
+                }
+                if ui.button("Open project…").clicked() {
+                    spawn_file_picker(Arc::clone(&self.project_data), "Alumina project (alumina)", &[project::EXTENSION]);
+                }
+                ui.separator();
+                if ui.button("Export settings").on_hover_text("Machine profile, tool, slicer and view settings as JSON").clicked() {
+                    self.export_settings();
+                }
+                if ui.button("Import settings…").clicked() {
+                    spawn_file_picker(Arc::clone(&self.settings_data), "Settings (json)", &["json"]);
+                }
+            });
+        });
+
+        egui::TopBottomPanel::top("workspace_bar").show(ctx, |ui| self.workspace_bar(ui));
+
+        self.track_dirty();
+        self.tick_autosave();
+        self.tick_app_settings();
+        self.session_dialog(ctx);
+        self.wizard_dialog(ctx);
+        self.alignment_window(ctx);
+        self.tick_projector(ctx);
+        self.bom_window(ctx);
+        self.command_palette(ctx);
+        self.tick_model_history(ctx);
+        if ctx.input(|i| i.viewport().close_requested()) && !self.quit_confirmed {
+            if self.unsaved_anywhere() {
+                ctx.send_viewport_cmd(egui::ViewportCommand::CancelClose);
+            }
+            self.guarded(Confirm::Quit);
+        }
+        self.confirm_dialog(ctx);
+        if self.quit_confirmed {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+        }
+        for line in self.toasts.collect(now_ms()) {
+            self.diag_log(line);
+        }
+        if self.toasts.show(ctx, now_ms()) {
+            self.selected_tab = Tab::Diagnostics;
+        }
+        if let Some(bytes) = self.plugin_data.lock().unwrap().take() {
+            plugins::load(bytes);
+        }
+        let settings = self.settings_data.lock().unwrap().take();
+        if let Some(bytes) = settings {
+            self.import_settings(&bytes);
+        }
+        let graph = self.graph_data.lock().unwrap().take();
+        if let Some(bytes) = graph {
+            self.open_graph(&bytes);
+        }
+        let project = self.project_data.lock().unwrap().take();
+        if let Some(bytes) = project {
+            self.open_project(&bytes);
+        }
+        let mut show_plugins = self.show_plugins;
+        egui::Window::new("Plugins").open(&mut show_plugins).show(ctx, |ui| {
+            let selected = self.selected_model.and_then(|i| self.models.get(i)).map(|m| &m.mesh);
+            if plugins::manager_ui(ui, selected) {
+                spawn_file_picker(Arc::clone(&self.plugin_data), "Plugin (wasm)", &["wasm"]);
+            }
+        });
+        self.show_plugins = show_plugins;
 
+        match self.selected_tab {
+            Tab::Control => {
                 // ── workpiece ────────────────────────────────────────────────
                 let workpiece_bytes_opt = {
                     let mut guard = self.workpiece_data.lock().unwrap();
                     guard.take()
                 };
                 if let Some(bytes) = workpiece_bytes_opt {
-                    if let Some(mesh) = load_mesh_from_bytes(&bytes) {
-                        self.add_model(mesh.float(), "workpiece".into());
-                        log::info!("[alumina] workpiece loaded ({} bytes)", bytes.len());
-                    } else {
-                        log::error!("Could not parse workpiece file");
-                    }
+                    self.load_stock(&bytes);
                 }
 
                 // ── model ────────────────────────────────────────────────────
@@ -1077,284 +3468,147 @@ impl eframe::App for AluminaApp {
                         log::info!("[alumina] model loaded ({} bytes)", bytes.len());
                     } else {
                         log::error!("Could not parse model file – unsupported or corrupt");
+                        toasts::error("Could not load the model: unsupported or corrupt file", None);
                     }
                 }
 
                 // Apply scaling if the user changed any of the factors -------------
                 self.refresh_models();
                 self.refresh_slice();
+                self.refresh_supports();
 
-                // ------------------------------------------------------------------
-                // Main viewport
-                // ------------------------------------------------------------------
-                egui::CentralPanel::default().show(ctx, |ui| {
-                    ui.set_min_size(ui.available_size());
-                    let (rect, response) =
-                        ui.allocate_exact_size(ui.available_size(), egui::Sense::drag());
-
-                    // ───── Interaction ─────
-                    if response.dragged() {
-                        let delta = response.drag_delta();
-                        let input = ui.input(|i| i.clone());
-                        if input.pointer.primary_down() {
-                            // left‑drag → rotate
-                            let yaw = delta.x * 0.01;
-                            let pitch = delta.y * 0.01;
-                            self.rotation =
-                                UnitQuaternion::from_axis_angle(&Vector3::y_axis(), yaw)
-                                    * UnitQuaternion::from_axis_angle(&Vector3::x_axis(), pitch)
-                                    * self.rotation;
-                        } else if input.pointer.middle_down() {
-                            // middle‑drag → pan
-                            self.translation += -delta;
-                        }
-                    }
-                    
-                    //-------------------------------------------------------------
-					// ✨ 1. Pinch-to-zoom (egui 0.23+ synthesises this for us)
-					//-------------------------------------------------------------
-					let pinch = ui.input(|i| i.zoom_delta());
-					// `zoom_delta` is multiplicative: 1.0 == no change.
-					if (pinch - 1.0).abs() > f32::EPSILON {
-						// >1  → fingers move apart → zoom-in (move camera closer)
-						// <1  → fingers pinch      → zoom-out
-						self.zoom = (self.zoom / pinch).clamp(0.1, 500.0);
-					}
+                self.show_dock(ctx, frame, Tab::Control);
+            }
 
-					//-------------------------------------------------------------
-					// ✨ 2. Two-/three-finger pan
-					//
-					// Track-pads send a “scroll” gesture for multi-finger drags.
-					// We treat that *vector* as a pan.  Mouse-wheel users still
-					// get the existing scroll-to-zoom behaviour because wheels
-					// never generate an X component in practice.
-					//-------------------------------------------------------------
-					//let scroll_vec = ui.input(|i| i.raw_scroll_delta);
-					//if scroll_vec.x.abs() > 0.0 || scroll_vec.y.abs() > 0.0 {
-					//	// For a natural feel, match the finger direction: we invert Y.
-					//	self.translation += egui::vec2(-scroll_vec.x, scroll_vec.y);
-					//}
-
-                    // scroll → zoom
-                    let scroll = ui.input(|i| i.raw_scroll_delta.y);
-                    if scroll.abs() > 0.0 {
-                        self.zoom = (self.zoom * (1.0 + scroll * 0.001)).clamp(0.0, 500.0);
+            Tab::Diagnostics => {
+                // Periodic sampler (~5 Hz)
+                if self.diag_poll {
+                    let now = now_ms();
+                    if now - self.last_poll_ms > 200.0 {
+                        self.last_poll_ms = now;
+                        Self::poll_pins_once(Arc::clone(&self.diag_last_pins));
                     }
-
-                    // ------------------------------------------------------------------
-                    // Ask egui for the GL context once per frame
-                    // ------------------------------------------------------------------
-                    if let Some(gl) = _frame.gl() {
-                        // ── 1) create once ─────────────────────────────────────────────
-                        if self.gpu.is_none() {
-                            self.gpu =
-                                Some(Arc::new(Mutex::new(unsafe { renderer::GpuLines::new(gl) })));
-                        }
-
-                        // ── 2) keep vertex buffer in sync ─────────────────────────────
-                        unsafe { self.sync_buffers(gl) };
-
-                        // ── 3) schedule GL paint right after egui’s own meshes ────────
-                        if let Some(lines_gpu) = &self.gpu {
-                            let lines_gpu = lines_gpu.clone();
-                            let faces_gpu = self.gpu_faces.clone();
-                            let mvp = mvp(self, rect); // copy for the closure
-
-                            let callback = egui_glow::CallbackFn::new(move |_info, painter| {
-                                let gl = painter.gl();
-                                unsafe {
-                                    gl.enable(glow::DEPTH_TEST);
-                                    gl.depth_func(glow::LEQUAL);
-                                    gl.clear(glow::DEPTH_BUFFER_BIT);
-
-                                    // draw filled faces first (slight offset keeps outlines crisp)
-                                    if let Some(faces_gpu) = &faces_gpu {
-                                        if let Ok(f) = faces_gpu.lock() {
-                                            gl.enable(glow::POLYGON_OFFSET_FILL);
-                                            gl.polygon_offset(1.0, 1.0);
-                                            f.paint_tris(gl, mvp);
-                                            gl.disable(glow::POLYGON_OFFSET_FILL);
-                                        }
-                                    }
-                                    // then draw outlines
-                                    if let Ok(l) = lines_gpu.lock() {
-                                        l.paint(gl, mvp);
-                                    }
-                                }
-                            });
-
-                            ui.painter().add(egui::PaintCallback {
-                                rect,
-                                callback: Arc::new(callback),
-                            });
+                }
+                // Apply the latest sample to series and console
+                if let Some(pins) = { let mut g = self.diag_last_pins.lock().unwrap(); g.take() } {
+                    let t = now_ms() / 1000.0;
+                    // Only track pins the user has "checked"
+                    let mut line = format!("t={:.02}s ", t);
+                    for (name, val) in pins.iter() {
+                        if self.is_pin_checked(name) {
+                            self.diag_push_point_named(name, t as f64, *val);
+                            line.push_str(&format!(" {}={}", name, *val as i32));
                         }
                     }
-                });
-            }
-
-            Tab::Diagnostics => {
-                egui::SidePanel::left("diag_side")
-                    .resizable(false)
-                    .min_width(140.0)
-                    .show(ctx, |ui| {
-                        ui.heading("Diagnostics");
-                        ui.separator();
-                        ui.horizontal(|ui| {
-							if ui.button("Scan Wi-Fi").clicked() {
-								send_queue_command("scan_wifi");
-							}
-							if ui.button("Set Wi-Fi").clicked() {
-								// current firmware just logs;
-								send_queue_command("set_wifi");
-							}
-						});
-                        ui.separator();
-                        ui.checkbox(&mut self.diag_poll,"Poll");
-						if ui.checkbox(&mut self.diag_led,"Status LED").changed(){
-							if self.diag_led { send_queue_command("status_on"); }
-							else { send_queue_command("status_off"); }
-						}
-						ui.separator();
-						ui.label("GPIO pins");
-						let mut gpio_row = |label: &str,
-											state: &mut bool,
-											high: &'static str,
-											low:  &'static str,
-											ui: &mut egui::Ui| {
-							if ui.checkbox(state, label).changed() {
-								if *state { send_queue_command(high); }
-								else      { send_queue_command(low);  }
-							}
-						};
-						gpio_row("D0",&mut self.diag_d0,"d0_high","d0_low",ui);
-						gpio_row("D1",&mut self.diag_d1,"d1_high","d1_low",ui);
-						gpio_row("D2",&mut self.diag_d2,"d2_high","d2_low",ui);
-						gpio_row("D3",&mut self.diag_d3,"d3_high","d3_low",ui);
-						gpio_row("D4",&mut self.diag_d4,"d4_high","d4_low",ui);
-						gpio_row("D5",&mut self.diag_d5,"d5_high","d5_low",ui);
-						gpio_row("D6",&mut self.diag_d6,"d6_high","d6_low",ui);
-						gpio_row("D7",&mut self.diag_d7,"d7_high","d7_low",ui);
-						gpio_row("D9",&mut self.diag_d9,"d9_high","d9_low",ui);
-						gpio_row("D11",&mut self.diag_d11,"d11_high","d11_low",ui);
-						gpio_row("D12",&mut self.diag_d12,"d12_high","d12_low",ui);
-						gpio_row("D13",&mut self.diag_d13,"d13_high","d13_low",ui);
-					});
+                    if line.trim() != "t=0.00s" {
+                        self.diag_log(line);
+                    }
+                    if self.diag_capture.push(t, &pins) {
+                        self.diag_log(format!("trigger fired on {} at t={t:.02}s", self.diag_capture.source));
+                    }
+                }
 
-                egui::CentralPanel::default().show(ctx, |ui| {
-					// Periodic sampler (~5 Hz)
-					if self.diag_poll {
-						if let Some(perf) = web_sys::window().and_then(|w| w.performance()) {
-							let now = perf.now();
-							if now - self.last_poll_ms > 200.0 {
-								self.last_poll_ms = now;
-								Self::poll_pins_once(Arc::clone(&self.diag_last_pins));
-							}
-						}
-					}
-					// Apply the latest sample to series and console
-					if let Some(pins) = { let mut g = self.diag_last_pins.lock().unwrap(); g.take() } {
-						let t = (web_sys::window().and_then(|w| w.performance()).map(|p| p.now()).unwrap_or(0.0)) / 1000.0;
-						// Only track pins the user has "checked"
-						let mut line = format!("t={:.02}s ", t);
-						for (name, val) in pins.iter() {
-							if self.is_pin_checked(name) {
-								self.diag_push_point_named(name, t as f64, *val);
-								line.push_str(&format!(" {}={}", name, *val as i32));
-							}
-						}
-						if line.trim() != "t=0.00s" {
-							self.diag_log(line);
-						}
-					}
-					
-					// Split the available space into two equal vertical regions
-					let total = ui.available_size();
-					let half_h = total.y / 2.0;
-
-					// ─────────────── Top half: graph ───────────────
-					ui.allocate_ui(egui::vec2(total.x, half_h), |ui| {
-						ui.heading("Graph");
-						ui.add_space(4.0);
-						Plot::new("diag_plot")
-							.width(ui.available_width())
-							.height(ui.available_height())
-							.show(ui, |plot_ui| {
-								// Draw a series per *checked* pin that has data
-								for (name, series) in &self.diag_series {
-									if self.is_pin_checked(name) && !series.is_empty() {
-										let points = PlotPoints::from(series.clone());
-										plot_ui.line(Line::new(points).name(name.clone()));
-									}
-								}
-							});
-					});
-
-					ui.add_space(4.0); // optional tiny gap between halves
-
-					// ───────────── Bottom half: console ─────────────
-					ui.allocate_ui(egui::vec2(total.x, half_h), |ui| {
-						ui.horizontal(|ui| {
-							ui.heading("Console");
-							if ui.button("Clear").clicked() {
-								self.diag_console.clear();
-								self.diag_series.clear();
-							}
-							if ui.button("Refresh queue").clicked() {
-								execute(async {
-									match http_get_text("/queue").await {
-										Ok(s) => log::info!("/queue: {}", s),
-										Err(e) => log::error!("GET /queue failed: {:?}", e),
-									}
-								});
-							}
-						});
-
-						// Make the log fill the remainder of this half
-						egui::ScrollArea::vertical()
-							.stick_to_bottom(true)
-							.show(ui, |ui| {
-								let te = egui::TextEdit::multiline(&mut self.diag_console)
-									.desired_width(f32::INFINITY)
-									.interactive(false);
-								ui.add_sized(ui.available_size(), te);
-							});
-					});
-				});
+                self.show_dock(ctx, frame, Tab::Diagnostics);
             }
 
             Tab::Design => {
                 egui::SidePanel::left("design_side")
-                    .resizable(false)
+                    .resizable(true)
                     .min_width(140.0)
                     .show(ctx, |ui| {
                         ui.heading("Design");
                         ui.separator();
+                        self.node_search_ui(ui);
+                        ui.separator();
+                        self.arrange_nodes_ui(ui);
+                        ui.separator();
                         if ui.button("Clear graph").clicked() {
-                            self.design_state = GraphEditorState::default();
+                            self.guarded(Confirm::ClearGraph);
                         }
                         if ui.button("Apply to model").clicked() {
                             let roots = design_graph::graph_roots(&self.design_state.graph);
-                            log::warn!("roots: {:#?}", roots);
-                            if roots.is_empty() {
+                            let exports = design_graph::mesh_exports(&self.design_state.graph);
+                            if roots.is_empty() && exports.is_empty() {
                                 log::warn!("Apply to model: No root nodes found in the graph.");
+                                toasts::warn("The graph has no output to apply", None);
                             }
+                            let mut eval_ms = 0.0;
+                            let mut report = design_graph::EvalReport {
+                                trace: self.eval_trace.enabled.then(Vec::new),
+                                ..Default::default()
+                            };
                             for root_out in roots {
-                                match design_graph::evaluate(&self.design_state.graph, root_out) {
+                                let started = now_ms();
+                                let result = design_graph::evaluate_reported(&self.design_state.graph, root_out, &mut report);
+                                eval_ms += now_ms() - started;
+                                match result {
                                     Ok(mesh) => self.add_model(mesh.float(), "graph".into()),
-                                    Err(e) => log::error!(
-                                        "Graph eval failed for root {:?}: {e}",
-                                        root_out
+                                    Err(e) => {
+                                        log::error!("Graph eval failed for root {:?}: {e}", root_out);
+                                        toasts::error("Graph evaluation failed", Some(e.to_string()));
+                                    }
+                                }
+                            }
+                            self.graph_exports.clear();
+                            for node in exports {
+                                match design_graph::evaluate_export(&self.design_state.graph, node) {
+                                    Ok(file) => self.graph_exports.push(file),
+                                    Err(e) => toasts::error(
+                                        format!("Export “{}” failed", self.design_state.graph[node].label),
+                                        Some(e.to_string()),
                                     ),
                                 }
                             }
+                            self.profile.eval_ms = Some(eval_ms);
+                            for (id, node) in self.design_state.graph.nodes.iter_mut() {
+                                node.user_data.eval_ms = report.times.get(&id).copied();
+                            }
+                            if let Some(trace) = report.trace {
+                                self.eval_trace.set(trace);
+                            }
+                            self.checkpoint_graph();
                         }
-                        if ui.button("Save .graph").clicked() {
-                            // serialise self.design_state.graph and trigger download …
+                        if ui
+                            .button("Send sketch to cutting")
+                            .on_hover_text("Cut the graph's 2-D output as a flat part, without extruding or slicing")
+                            .clicked()
+                        {
+                            self.send_sketch_to_cutting();
+                        }
+                        if ui.button("Copy share link").on_hover_text("Encode the graph in the page address").clicked() {
+                            match design_graph::SavedGraph::from_state(&self.design_state).and_then(|g| g.to_link()) {
+                                Ok(link) => {
+                                    if let Some(href) = platform::share_url(&format!("#graph={link}")) {
+                                        ui.ctx().copy_text(href);
+                                        toasts::info("Link copied to the clipboard");
+                                    }
+                                }
+                                Err(e) => toasts::error("The graph could not be encoded", Some(e.to_string())),
+                            }
                         }
+                        ui.horizontal(|ui| {
+                            if ui.button("Save .graph").on_hover_text("Download the graph as a file").clicked() {
+                                self.save_graph();
+                            }
+                            if ui.button("Load .graph…").on_hover_text("Replace the graph with one from a file").clicked() {
+                                spawn_file_picker(Arc::clone(&self.graph_data), "Design graph (graph)", &["graph", "json"]);
+                            }
+                        });
+                        ui.separator();
+                        self.graph_exports_ui(ui);
+                        ui.collapsing("History", |ui| self.graph_history_ui(ui));
+                        ui.collapsing("Evaluation trace", |ui| self.eval_trace_ui(ui));
                     });
 
+                egui::SidePanel::right("sketch_preview")
+                    .resizable(true)
+                    .default_width(280.0)
+                    .min_width(160.0)
+                    .show(ctx, |ui| self.sketch_preview_ui(ui));
+
                 egui::CentralPanel::default().show(ctx, |ui| {
 					ui.set_min_size(ui.available_size());
+					self.graph_rect = ui.max_rect();
 
 					// Check if the graph is empty before drawing (no nodes/ports yet)
 					let graph_is_empty =
@@ -1365,11 +3619,17 @@ impl eframe::App for AluminaApp {
 						AllTemplates,
 						&mut self.design_user_state,
 						Vec::<egui_node_graph2::NodeResponse<
-							design_graph::EmptyUserResponse,
+							design_graph::GraphResponse,
 							design_graph::NodeData,
 						>>::new(),
 					);
-					_ = resp;
+					for response in resp.node_responses {
+						if let egui_node_graph2::NodeResponse::User(design_graph::GraphResponse::ToggleBypass(id)) = response {
+							if let Some(node) = self.design_state.graph.nodes.get_mut(id) {
+								node.user_data.bypass = !node.user_data.bypass;
+							}
+						}
+					}
 
 					// Overlay hint when no nodes are present
 					if graph_is_empty {
@@ -1389,6 +3649,725 @@ impl eframe::App for AluminaApp {
     }
 }
 
+/// Where perimeter loops start: the seam strategy, and for a painted seam
+/// the bed X/Y of the line's two ends, which loops start nearest to.
+fn seam_settings_ui(ui: &mut egui::Ui, seam: &mut slicer::SeamSettings) {
+    use slicer::SeamStrategy;
+    ui.horizontal(|ui| {
+        ui.label("Seam:");
+        egui::ComboBox::from_id_salt("seam_strategy")
+            .selected_text(seam.strategy.to_string())
+            .show_ui(ui, |ui| {
+                for s in [SeamStrategy::Nearest, SeamStrategy::Aligned, SeamStrategy::Random, SeamStrategy::Painted] {
+                    ui.selectable_value(&mut seam.strategy, s, s.to_string());
+                }
+            });
+    });
+    if seam.strategy == SeamStrategy::Painted {
+        for (label, p) in ["From", "To"].into_iter().zip(&mut seam.painted) {
+            ui.horizontal(|ui| {
+                ui.label(label);
+                ui.add(egui::DragValue::new(&mut p[0]).speed(0.5).prefix("X "));
+                ui.add(egui::DragValue::new(&mut p[1]).speed(0.5).prefix("Y "));
+            });
+        }
+    }
+}
+
+/// Checkbox to override an inherited setting, with its value beside it.
+fn override_value<N: egui::emath::Numeric + std::fmt::Display>(
+    ui: &mut egui::Ui,
+    label: &str,
+    value: &mut Option<N>,
+    inherited: N,
+    range: std::ops::RangeInclusive<N>,
+) {
+    ui.horizontal(|ui| {
+        let mut on = value.is_some();
+        if ui.checkbox(&mut on, label).changed() {
+            *value = on.then_some(inherited);
+        }
+        match value {
+            Some(v) => {
+                ui.add(egui::DragValue::new(v).range(range));
+            }
+            None => {
+                ui.weak(format!("{inherited}"));
+            }
+        }
+    });
+}
+
+/// Support style and parameters, plus a summary of the generated regions.
+fn support_settings_ui(ui: &mut egui::Ui, s: &mut support::SupportSettings, generated: Option<&support::Supports>) {
+    use support::SupportStyle;
+    ui.checkbox(&mut s.enabled, "Generate supports");
+    ui.add_enabled_ui(s.enabled, |ui| {
+        egui::Grid::new("support_settings").num_columns(2).show(ui, |ui| {
+            ui.label("Style:");
+            egui::ComboBox::from_id_salt("support_style")
+                .selected_text(s.style.to_string())
+                .show_ui(ui, |ui| {
+                    for style in [SupportStyle::Grid, SupportStyle::Tree] {
+                        ui.selectable_value(&mut s.style, style, style.to_string());
+                    }
+                });
+            ui.end_row();
+            let label = ui.label("Overhang angle:");
+            ui.add(egui::Slider::new(&mut s.overhang_angle, 10.0..=89.0).suffix("°")).labelled_by(label.id);
+            ui.end_row();
+            let label = ui.label("Density:");
+            ui.add(egui::Slider::new(&mut s.density, 1.0..=100.0).suffix(" %")).labelled_by(label.id);
+            ui.end_row();
+            let label = ui.label("Interface layers:");
+            ui.add(egui::DragValue::new(&mut s.interface_layers).range(0..=10)).labelled_by(label.id);
+            ui.end_row();
+            let label = ui.label("Z gap (mm):");
+            ui.add(egui::DragValue::new(&mut s.z_gap).speed(0.01).range(0.0..=5.0)).labelled_by(label.id);
+            ui.end_row();
+        });
+    });
+    if let Some(g) = generated {
+        let off = g.regions.iter().filter(|r| !r.enabled).count();
+        ui.small(format!("{} regions ({off} off) · click one in the viewport to toggle it", g.regions.len()));
+    }
+}
+
+/// Heights the extruder job pauses at; `current` is the top of the layer
+/// shown, offered as the next height.
+fn pause_heights_ui(ui: &mut egui::Ui, heights: &mut Vec<f32>, current: Option<f32>) {
+    ui.label("Stops before the first layer above each height, to embed nuts or magnets:");
+    let mut remove = None;
+    for (i, h) in heights.iter_mut().enumerate() {
+        ui.horizontal(|ui| {
+            ui.add(egui::DragValue::new(h).speed(0.05).range(0.0..=1000.0).prefix("Z ").suffix(" mm"));
+            if ui.small_button("✖").on_hover_text("Remove this pause").clicked() {
+                remove = Some(i);
+            }
+        });
+    }
+    if let Some(i) = remove {
+        heights.remove(i);
+    }
+    let next = current.unwrap_or_else(|| heights.last().map_or(5.0, |h| h + 5.0));
+    if ui.button("Add pause").on_hover_text(format!("Pause above Z {next:.2} mm (the current layer)")).clicked() {
+        heights.push(next);
+        heights.sort_by(f32::total_cmp);
+    }
+    if !heights.is_empty() {
+        ui.weak("Inserted into extruder jobs as they are loaded");
+    }
+}
+
+/// Base exposure, lift speed and per-layer-range overrides of resin jobs;
+/// `lift` is the peel distance new ranges start from.
+fn resin_settings_ui(ui: &mut egui::Ui, r: &mut resin::ResinSettings, lift: f32) {
+    egui::Grid::new("resin_settings").num_columns(2).show(ui, |ui| {
+        let label = ui.label("Exposure (s):");
+        ui.add(egui::DragValue::new(&mut r.exposure).speed(0.1).range(0.1..=600.0)).labelled_by(label.id);
+        ui.end_row();
+        let label = ui.label("Lift speed (mm/min):");
+        ui.add(egui::DragValue::new(&mut r.lift_speed).speed(1.0).range(1.0..=1000.0)).labelled_by(label.id);
+        ui.end_row();
+    });
+
+    ui.label("Layer ranges (first match wins):");
+    let mut remove = None;
+    egui::Grid::new("resin_ranges").num_columns(6).show(ui, |ui| {
+        for (i, range) in r.ranges.iter_mut().enumerate() {
+            ui.add(egui::DragValue::new(&mut range.first).speed(1).prefix("layers "));
+            ui.add(egui::DragValue::new(&mut range.last).speed(1).range(range.first..=u32::MAX).prefix("to "));
+            ui.add(egui::DragValue::new(&mut range.exposure).speed(0.1).range(0.1..=600.0).suffix(" s"));
+            ui.add(egui::DragValue::new(&mut range.lift).speed(0.1).range(0.0..=100.0).prefix("lift ").suffix(" mm"));
+            ui.checkbox(&mut range.pause, "pause").on_hover_text("Stop before the first layer of the range");
+            if ui.small_button("✖").clicked() {
+                remove = Some(i);
+            }
+            ui.end_row();
+        }
+    });
+    if let Some(i) = remove {
+        r.ranges.remove(i);
+    }
+    if ui.button("Add range").clicked() {
+        let first = r.ranges.last().map_or(0, |range| range.last + 1);
+        r.ranges.push(resin::ResinRange {
+            first,
+            last: first + 9,
+            exposure: r.exposure,
+            lift,
+            pause: false,
+        });
+    }
+}
+
+/// Wall and cavity lattice of hollowed resin parts.
+fn hollow_settings_ui(ui: &mut egui::Ui, h: &mut resin::HollowSettings) {
+    ui.checkbox(&mut h.enabled, "Hollow the parts").on_hover_text("Saves resin; add drain holes to the model so the cavity can empty");
+    ui.add_enabled_ui(h.enabled, |ui| {
+        egui::Grid::new("hollow_settings").num_columns(2).show(ui, |ui| {
+            let label = ui.label("Wall (mm):");
+            ui.add(egui::DragValue::new(&mut h.wall).speed(0.1).range(0.3..=20.0))
+                .labelled_by(label.id)
+                .on_hover_text("Sides, floor and roof");
+            ui.end_row();
+            ui.checkbox(&mut h.lattice, "Lattice");
+            egui::ComboBox::from_id_salt("hollow_lattice")
+                .selected_text(h.pattern.to_string())
+                .show_ui(ui, |ui| {
+                    for pattern in [InfillType::Gyroid, InfillType::SchwarzP, InfillType::SchwarzD] {
+                        ui.selectable_value(&mut h.pattern, pattern, pattern.to_string());
+                    }
+                });
+            ui.end_row();
+            let label = ui.label("Cell (mm):");
+            ui.add_enabled(h.lattice, egui::DragValue::new(&mut h.cell).speed(0.1).range(1.0..=100.0)).labelled_by(label.id);
+            ui.end_row();
+            let label = ui.label("Sheet (mm):");
+            ui.add_enabled(h.lattice, egui::DragValue::new(&mut h.thickness).speed(0.05).range(0.1..=10.0))
+                .labelled_by(label.id)
+                .on_hover_text("Thickness of the lattice walls; thin sheets in large cells keep it light");
+            ui.end_row();
+        });
+    });
+}
+
+enum TrappedAction {
+    None,
+    Check,
+    /// Jump to this layer.
+    Show(usize),
+}
+
+/// Trapped volumes found so far, with their suggested drain holes.
+fn trapped_volumes_ui(ui: &mut egui::Ui, volumes: Option<&[engine::TrappedVolume]>) -> TrappedAction {
+    let mut action = TrappedAction::None;
+    if ui
+        .button("Check layers")
+        .on_hover_text("Find voids sealed at the bottom, which pull on the vat film like suction cups")
+        .clicked()
+    {
+        action = TrappedAction::Check;
+    }
+    match volumes {
+        None => {}
+        Some([]) => {
+            ui.weak("No trapped volumes");
+        }
+        Some(volumes) => {
+            egui::Grid::new("trapped_volumes").num_columns(4).striped(true).show(ui, |ui| {
+                for h in ["Layers", "Volume", "Kind", "Drain hole at"] {
+                    ui.strong(h);
+                }
+                ui.end_row();
+                for v in volumes {
+                    if ui.link(format!("{}–{}", v.first + 1, v.last + 1)).on_hover_text("Show the first layer").clicked() {
+                        action = TrappedAction::Show(v.first);
+                    }
+                    ui.label(format!("{:.2} cm³", v.volume / 1000.0));
+                    if v.sealed_roof {
+                        ui.colored_label(ui.visuals().error_fg_color, "closed")
+                            .on_hover_text("Sealed all round: suction while printing, and resin stays inside");
+                    } else {
+                        ui.colored_label(ui.visuals().warn_fg_color, "cup").on_hover_text("Sealed at the bottom: suction until it opens");
+                    }
+                    ui.label(format!("X {:.1} Y {:.1} Z {:.1}", v.drain.x, v.drain.y, v.drain_z))
+                        .on_hover_text("Through the floor of the volume; 2 mm or more across");
+                    ui.end_row();
+                }
+            });
+        }
+    }
+    action
+}
+
+/// Clickable bar over all layers with a tick at each pause; returns `true`
+/// when `current` was moved.
+fn layer_strip(ui: &mut egui::Ui, len: usize, current: &mut usize, pauses: &[usize]) -> bool {
+    let (rect, response) = ui.allocate_exact_size(egui::vec2(ui.available_width(), 14.0), egui::Sense::click_and_drag());
+    let x_of = |i: usize| rect.left() + rect.width() * (i as f32 + 0.5) / len as f32;
+    let painter = ui.painter_at(rect);
+    let visuals = ui.visuals();
+    painter.rect_filled(rect.shrink2(egui::vec2(0.0, 4.0)), 2.0, visuals.extreme_bg_color);
+    for &p in pauses {
+        let x = x_of(p);
+        painter.line_segment([egui::pos2(x, rect.top()), egui::pos2(x, rect.bottom())], (2.0, visuals.warn_fg_color));
+    }
+    painter.circle_filled(egui::pos2(x_of(*current), rect.center().y), 5.0, visuals.selection.bg_fill);
+    let response = response.on_hover_text("Layers; orange ticks are pauses");
+    if let Some(pos) = response.interact_pointer_pos() {
+        let i = (((pos.x - rect.left()) / rect.width()) * len as f32).floor().clamp(0.0, len as f32 - 1.0) as usize;
+        if i != *current {
+            *current = i;
+            return true;
+        }
+    }
+    false
+}
+
+/// Common-line toggle and what it saved on the current slice.
+fn common_line_ui(ui: &mut egui::Ui, on: &mut bool, merged: Option<&(Vec<LineString<f64>>, cutting::MergeStats)>) {
+    ui.checkbox(on, "Common-line cutting").on_hover_text("Cut edges shared by nested parts only once");
+    if let Some((paths, stats)) = merged {
+        ui.small(format!(
+            "{} paths · {:.0} of {:.0} mm ({} shared segments, {:.0} mm saved)",
+            paths.len(),
+            stats.length_after,
+            stats.length_before,
+            stats.shared_segments,
+            stats.saved()
+        ));
+    }
+}
+
+/// Operation list for stock-aware milling; returns `true` to (re)compute.
+fn mill_ops_ui(ui: &mut egui::Ui, ops: &mut Vec<milling::Operation>, results: Option<&[milling::OperationResult]>) -> bool {
+    ui.label("Operations (each machines only rest material):");
+    let mut remove = None;
+    egui::Grid::new("mill_ops").num_columns(5).show(ui, |ui| {
+        for (i, op) in ops.iter_mut().enumerate() {
+            ui.label(format!("{}.", i + 1));
+            ui.add(egui::DragValue::new(&mut op.tool_diameter).speed(0.1).range(0.1..=50.0).prefix("⌀ "));
+            ui.add(egui::DragValue::new(&mut op.stepdown).speed(0.05).range(0.01..=20.0).prefix("↓ "));
+            ui.add(egui::DragValue::new(&mut op.stepover).speed(0.01).range(0.05..=1.0).prefix("→ "));
+            if ui.small_button("✖").clicked() {
+                remove = Some(i);
+            }
+            ui.end_row();
+            if let Some(r) = results.and_then(|r| r.get(i)) {
+                ui.label("");
+                ui.small(format!("{} paths · {:.0} mm³ left", r.paths.len(), r.rest_volume));
+                ui.end_row();
+            }
+        }
+    });
+    if let Some(i) = remove {
+        ops.remove(i);
+    }
+    ui.horizontal(|ui| {
+        if ui.button("Add operation").clicked() {
+            let last = ops.last().cloned().unwrap_or(milling::Operation {
+                tool_diameter: 3.0,
+                stepdown: 0.5,
+                stepover: 0.4,
+            });
+            ops.push(milling::Operation {
+                tool_diameter: last.tool_diameter * 0.5,
+                ..last
+            });
+        }
+        ui.button("Compute toolpaths").on_hover_text("The stock (Models → Stock) is the raw material").clicked()
+    })
+    .inner
+}
+
+/// Profile / pocket parameters of the current layer's outline; returns
+/// `true` to (re)compute. `passes` counts the paths computed last.
+fn cam_settings_ui(ui: &mut egui::Ui, c: &mut cam::CamSettings, passes: Option<usize>) -> bool {
+    egui::Grid::new("cam_settings").num_columns(2).show(ui, |ui| {
+        let label = ui.label("Strategy:");
+        egui::ComboBox::from_id_salt("cam_strategy")
+            .selected_text(c.strategy.to_string())
+            .show_ui(ui, |ui| {
+                for s in cam::Strategy::ALL {
+                    ui.selectable_value(&mut c.strategy, s, s.to_string());
+                }
+            })
+            .response
+            .labelled_by(label.id);
+        ui.end_row();
+        if c.strategy == cam::Strategy::VCarve {
+            let label = ui.label("Bit angle (°):");
+            ui.add(egui::DragValue::new(&mut c.v_angle).speed(0.5).range(10.0..=170.0))
+                .labelled_by(label.id)
+                .on_hover_text("Included angle of the V-bit's tip");
+        } else if c.strategy == cam::Strategy::Pocket {
+            let label = ui.label("Pattern:");
+            egui::ComboBox::from_id_salt("cam_pattern")
+                .selected_text(c.pattern.to_string())
+                .show_ui(ui, |ui| {
+                    for p in [cam::PocketPattern::Zigzag, cam::PocketPattern::Spiral] {
+                        ui.selectable_value(&mut c.pattern, p, p.to_string());
+                    }
+                })
+                .response
+                .labelled_by(label.id);
+        } else {
+            let label = ui.label("Passes:");
+            ui.add(egui::DragValue::new(&mut c.profile_passes).speed(0.1).range(1..=20))
+                .labelled_by(label.id)
+                .on_hover_text("Roughing passes outside the final one, a stepover apart");
+        }
+        ui.end_row();
+        let label = ui.label("Stepover:");
+        ui.add(egui::DragValue::new(&mut c.stepover).speed(0.01).range(0.05..=1.0))
+            .labelled_by(label.id)
+            .on_hover_text("Fraction of the endmill width");
+        ui.end_row();
+        let label = ui.label(if c.strategy == cam::Strategy::VCarve { "Max depth (mm):" } else { "Depth (mm):" });
+        ui.add(egui::DragValue::new(&mut c.depth).speed(0.1).range(0.0..=200.0)).labelled_by(label.id);
+        ui.end_row();
+        let label = ui.label("Depth per pass (mm):");
+        ui.add(egui::DragValue::new(&mut c.depth_per_pass).speed(0.05).range(0.01..=50.0)).labelled_by(label.id);
+        ui.end_row();
+        let label = ui.label("Entry:");
+        egui::ComboBox::from_id_salt("cam_entry")
+            .selected_text(c.entry.to_string())
+            .show_ui(ui, |ui| {
+                for e in cam::Entry::ALL {
+                    ui.selectable_value(&mut c.entry, e, e.to_string());
+                }
+            })
+            .response
+            .labelled_by(label.id)
+            .on_hover_text("How each pass goes down into the material");
+        ui.end_row();
+        if c.entry != cam::Entry::Straight {
+            let label = ui.label("Ramp angle (°):");
+            ui.add(egui::DragValue::new(&mut c.ramp_angle).speed(0.1).range(0.5..=45.0)).labelled_by(label.id);
+            ui.end_row();
+        }
+        if c.entry == cam::Entry::Helix {
+            let label = ui.label("Helix diameter (mm):");
+            ui.add(egui::DragValue::new(&mut c.helix_diameter).speed(0.05).range(0.1..=100.0))
+                .labelled_by(label.id)
+                .on_hover_text("Of the circle the tool centre follows, beside each pass's start");
+            ui.end_row();
+        }
+    });
+    if let Some(n) = passes {
+        ui.small(format!("{n} paths"));
+    }
+    ui.button("Compute passes").on_hover_text("Around the current layer's outline, down from the top of the stock").clicked()
+}
+
+/// Spindle speed, feeds and clearance of endmill jobs.
+fn mill_settings_ui(ui: &mut egui::Ui, m: &mut gcode::MillSettings) {
+    egui::Grid::new("mill_settings").num_columns(2).show(ui, |ui| {
+        let label = ui.label("Spindle (%):");
+        ui.add(egui::DragValue::new(&mut m.spindle_pct).speed(1.0).range(0.0..=100.0)).labelled_by(label.id);
+        ui.end_row();
+        let label = ui.label("Feed (mm/min):");
+        ui.add(egui::DragValue::new(&mut m.feed).speed(10.0).range(1.0..=20000.0)).labelled_by(label.id);
+        ui.end_row();
+        let label = ui.label("Plunge (mm/min):");
+        ui.add(egui::DragValue::new(&mut m.plunge_feed).speed(10.0).range(1.0..=5000.0)).labelled_by(label.id);
+        ui.end_row();
+        let label = ui.label("Rapid (mm/min):");
+        ui.add(egui::DragValue::new(&mut m.travel_feed).speed(10.0).range(1.0..=20000.0)).labelled_by(label.id);
+        ui.end_row();
+        let label = ui.label("Clearance (mm):");
+        ui.add(egui::DragValue::new(&mut m.clearance).speed(0.1).range(0.5..=100.0))
+            .labelled_by(label.id)
+            .on_hover_text("Above the top of the material, for rapids");
+        ui.end_row();
+    });
+}
+
+/// Holder the simulation checks against the stock; true when changed.
+fn holder_ui(ui: &mut egui::Ui, holder: &mut collision::Holder) -> bool {
+    let before = holder.clone();
+    egui::Grid::new("holder").num_columns(2).show(ui, |ui| {
+        let label = ui.label("Holder diameter (mm):");
+        ui.add(egui::DragValue::new(&mut holder.diameter).speed(0.5).range(1.0..=200.0)).labelled_by(label.id);
+        ui.end_row();
+        let label = ui.label("Holder length (mm):");
+        ui.add(egui::DragValue::new(&mut holder.length).speed(0.5).range(1.0..=300.0))
+            .labelled_by(label.id)
+            .on_hover_text("The endmill length is its stick-out below the holder");
+        ui.end_row();
+    });
+    *holder != before
+}
+
+/// Depths, pecking and feeds of drill jobs.
+fn drill_settings_ui(ui: &mut egui::Ui, d: &mut gcode::DrillSettings) {
+    egui::Grid::new("drill_settings").num_columns(2).show(ui, |ui| {
+        let label = ui.label("Depth (mm):");
+        ui.add(egui::DragValue::new(&mut d.depth).speed(0.1).range(0.1..=300.0))
+            .labelled_by(label.id)
+            .on_hover_text("Below the top of the material");
+        ui.end_row();
+        let label = ui.label("Peck (mm):");
+        ui.add(egui::DragValue::new(&mut d.peck).speed(0.1).range(0.0..=100.0))
+            .labelled_by(label.id)
+            .on_hover_text("Depth of each peck (G83); 0 drills in one plunge (G81)");
+        ui.end_row();
+        let label = ui.label("Retract (mm):");
+        ui.add(egui::DragValue::new(&mut d.retract).speed(0.1).range(0.1..=50.0))
+            .labelled_by(label.id)
+            .on_hover_text("R plane above the material, between pecks");
+        ui.end_row();
+        let label = ui.label("Clearance (mm):");
+        ui.add(egui::DragValue::new(&mut d.clearance).speed(0.1).range(0.5..=100.0))
+            .labelled_by(label.id)
+            .on_hover_text("Above the top of the material, for rapids between holes");
+        ui.end_row();
+        let label = ui.label("Feed (mm/min):");
+        ui.add(egui::DragValue::new(&mut d.feed).speed(10.0).range(1.0..=5000.0)).labelled_by(label.id);
+        ui.end_row();
+        let label = ui.label("Rapid (mm/min):");
+        ui.add(egui::DragValue::new(&mut d.travel_feed).speed(10.0).range(1.0..=20000.0)).labelled_by(label.id);
+        ui.end_row();
+        let label = ui.label("Spindle (%):");
+        ui.add(egui::DragValue::new(&mut d.spindle_pct).speed(1.0).range(0.0..=100.0)).labelled_by(label.id);
+        ui.end_row();
+        let label = ui.label("Hole tolerance (mm):");
+        ui.add(egui::DragValue::new(&mut d.tolerance).speed(0.01).range(0.01..=5.0))
+            .labelled_by(label.id)
+            .on_hover_text("How far a round hole's diameter and roundness may be off the drill width");
+        ui.end_row();
+    });
+    ui.checkbox(&mut d.expand, "Write cycles as plain moves")
+        .on_hover_text("For controllers without G81 / G83");
+}
+
+/// Cut-order toggle and the rapid travel it saves.
+fn cut_order_ui(ui: &mut egui::Ui, on: &mut bool, plan: Option<&(cutting::CutPlan, f64)>) {
+    ui.checkbox(on, "Optimize cut order").on_hover_text("Shortest rapids; inner contours before outer ones");
+    if let Some((plan, before)) = plan {
+        ui.small(format!(
+            "{} operations · rapids {:.0} mm (was {before:.0} mm)",
+            plan.ops.len(),
+            plan.rapid_length()
+        ));
+    }
+}
+
+/// Skirt / brim / raft parameters.
+fn adhesion_settings_ui(ui: &mut egui::Ui, a: &mut slicer::AdhesionSettings) {
+    egui::Grid::new("adhesion").num_columns(2).show(ui, |ui| {
+        let label = ui.label("Skirt loops:");
+        ui.add(egui::DragValue::new(&mut a.skirt_loops).range(0..=20)).labelled_by(label.id);
+        ui.end_row();
+        let label = ui.label("Skirt distance (mm):");
+        ui.add(egui::DragValue::new(&mut a.skirt_distance).speed(0.1).range(0.0..=50.0)).labelled_by(label.id);
+        ui.end_row();
+        let label = ui.label("Brim width (mm):");
+        ui.add(egui::DragValue::new(&mut a.brim_width).speed(0.1).range(0.0..=50.0)).labelled_by(label.id);
+        ui.end_row();
+        let label = ui.label("Raft layers:");
+        ui.add(egui::DragValue::new(&mut a.raft_layers).range(0..=10)).labelled_by(label.id);
+        ui.end_row();
+        let label = ui.label("Raft margin (mm):");
+        ui.add(egui::DragValue::new(&mut a.raft_margin).speed(0.1).range(0.0..=50.0)).labelled_by(label.id);
+        ui.end_row();
+    });
+}
+
+/// Part-cooling schedule; `layer` is the inspected layer, whose speed is shown.
+fn fan_settings_ui(ui: &mut egui::Ui, f: &mut slicer::FanSettings, layer: usize) {
+    egui::Grid::new("fan_settings").num_columns(2).show(ui, |ui| {
+        let label = ui.label("Fan speed:");
+        ui.add(egui::Slider::new(&mut f.speed, 0.0..=100.0).suffix(" %")).labelled_by(label.id);
+        ui.end_row();
+        let label = ui.label("Off for first layers:");
+        ui.add(egui::DragValue::new(&mut f.off_layers).range(0..=20)).labelled_by(label.id);
+        ui.end_row();
+        let label = ui.label("Ramp-up layers:");
+        ui.add(egui::DragValue::new(&mut f.ramp_layers).range(0..=20)).labelled_by(label.id);
+        ui.end_row();
+        let label = ui.label("Bridge fan:");
+        ui.add(egui::Slider::new(&mut f.bridge_speed, 0.0..=100.0).suffix(" %")).labelled_by(label.id);
+        ui.end_row();
+    });
+    ui.small(format!("Layer {}: fan at {:.0} %", layer + 1, f.layer_speed(layer)));
+    ui.checkbox(&mut f.apply_to_jobs, "Apply to loaded jobs")
+        .on_hover_text("Replace the fan commands of extruder G-code with this schedule as it is loaded");
+}
+
+/// Power and feeds of laser jobs.
+fn laser_settings_ui(ui: &mut egui::Ui, l: &mut gcode::LaserSettings) {
+    egui::Grid::new("laser_settings").num_columns(2).show(ui, |ui| {
+        let label = ui.label("Power:");
+        ui.add(egui::Slider::new(&mut l.power_pct, 0.0..=100.0).suffix(" %")).labelled_by(label.id);
+        ui.end_row();
+        let label = ui.label("Cut feed (mm/min):");
+        ui.add(egui::DragValue::new(&mut l.cut_feed).speed(10.0).range(1.0..=20_000.0)).labelled_by(label.id);
+        ui.end_row();
+        let label = ui.label("Travel feed (mm/min):");
+        ui.add(egui::DragValue::new(&mut l.travel_feed).speed(50.0).range(1.0..=50_000.0)).labelled_by(label.id);
+        ui.end_row();
+        let label = ui.label("Passes:");
+        ui.add(egui::DragValue::new(&mut l.passes).speed(0.1).range(1..=50))
+            .labelled_by(label.id)
+            .on_hover_text("Cut every layer this many times, for material too thick for one pass");
+        ui.end_row();
+        if l.passes > 1 {
+            let label = ui.label("Step-down per pass (mm):");
+            ui.add(egui::DragValue::new(&mut l.z_step).speed(0.05).range(0.0..=20.0))
+                .labelled_by(label.id)
+                .on_hover_text("Lowers the head (and focus) before each pass after the first");
+            ui.end_row();
+        }
+    });
+}
+
+/// Kerf, pierce cycle, touch-off and torch height control of plasma jobs.
+fn plasma_settings_ui(ui: &mut egui::Ui, p: &mut gcode::PlasmaSettings, touch_off: bool) {
+    egui::Grid::new("plasma_settings").num_columns(2).show(ui, |ui| {
+        let label = ui.label("Kerf (mm):");
+        ui.add(egui::DragValue::new(&mut p.kerf).speed(0.05).range(0.0..=10.0)).labelled_by(label.id);
+        ui.end_row();
+        let label = ui.label("Cut feed (mm/min):");
+        ui.add(egui::DragValue::new(&mut p.cut_feed).speed(10.0).range(1.0..=20_000.0)).labelled_by(label.id);
+        ui.end_row();
+        let label = ui.label("Travel feed (mm/min):");
+        ui.add(egui::DragValue::new(&mut p.travel_feed).speed(50.0).range(1.0..=50_000.0)).labelled_by(label.id);
+        ui.end_row();
+        let label = ui.label("Pierce height (mm):");
+        ui.add(egui::DragValue::new(&mut p.pierce_height).speed(0.1).range(0.0..=20.0)).labelled_by(label.id);
+        ui.end_row();
+        let label = ui.label("Pierce delay (ms):");
+        ui.add(egui::DragValue::new(&mut p.pierce_delay_ms).speed(10.0).range(0.0..=10_000.0)).labelled_by(label.id);
+        ui.end_row();
+        let label = ui.label("Cut height (mm):");
+        ui.add(egui::DragValue::new(&mut p.cut_height).speed(0.1).range(0.0..=20.0)).labelled_by(label.id);
+        ui.end_row();
+        let label = ui.label("Safe height (mm):");
+        ui.add(egui::DragValue::new(&mut p.safe_height).speed(0.5).range(0.0..=200.0)).labelled_by(label.id);
+        ui.end_row();
+    });
+    if touch_off {
+        ui.collapsing("Touch-off", |ui| {
+            egui::Grid::new("plasma_touch_off").num_columns(2).show(ui, |ui| {
+                let label = ui.label("Probe feed (mm/min):");
+                ui.add(egui::DragValue::new(&mut p.probe_feed).speed(10.0).range(1.0..=5000.0)).labelled_by(label.id);
+                ui.end_row();
+                let label = ui.label("Probe depth (mm):");
+                ui.add(egui::DragValue::new(&mut p.probe_depth).speed(0.5).range(1.0..=200.0))
+                    .labelled_by(label.id)
+                    .on_hover_text("Furthest the torch probes down from the safe height");
+                ui.end_row();
+                let label = ui.label("Switch offset (mm):");
+                ui.add(egui::DragValue::new(&mut p.switch_offset).speed(0.05).range(0.0..=10.0))
+                    .labelled_by(label.id)
+                    .on_hover_text("Floating head travel from touching the plate to tripping the switch");
+                ui.end_row();
+            });
+        });
+    }
+    ui.checkbox(&mut p.thc, "Torch height control");
+    ui.add_enabled_ui(p.thc, |ui| {
+        ui.horizontal(|ui| {
+            let label = ui.label("On:");
+            ui.add(egui::TextEdit::singleline(&mut p.thc_on).desired_width(70.0)).labelled_by(label.id);
+            let label = ui.label("Off:");
+            ui.add(egui::TextEdit::singleline(&mut p.thc_off).desired_width(70.0)).labelled_by(label.id);
+        });
+    });
+}
+
+/// Temperatures, filament and speeds of extruder jobs.
+fn extruder_settings_ui(ui: &mut egui::Ui, e: &mut gcode::ExtruderSettings) {
+    egui::Grid::new("extruder_settings").num_columns(2).show(ui, |ui| {
+        let label = ui.label("Nozzle (°C):");
+        ui.add(egui::DragValue::new(&mut e.nozzle_temp).speed(1.0).range(0.0..=450.0)).labelled_by(label.id);
+        ui.end_row();
+        let label = ui.label("Bed (°C):");
+        ui.add(egui::DragValue::new(&mut e.bed_temp).speed(1.0).range(0.0..=150.0)).labelled_by(label.id);
+        ui.end_row();
+        let label = ui.label("Filament (mm):");
+        ui.add(egui::DragValue::new(&mut e.filament_diameter).speed(0.01).range(1.0..=3.0)).labelled_by(label.id);
+        ui.end_row();
+        let label = ui.label("Flow:");
+        ui.add(egui::DragValue::new(&mut e.flow).speed(0.01).range(0.5..=2.0))
+            .labelled_by(label.id)
+            .on_hover_text("Multiplies every extrusion amount");
+        ui.end_row();
+        let label = ui.label("Print speed (mm/s):");
+        ui.add(egui::DragValue::new(&mut e.print_speed).speed(1.0).range(1.0..=500.0)).labelled_by(label.id);
+        ui.end_row();
+        let label = ui.label("First layer (mm/s):");
+        ui.add(egui::DragValue::new(&mut e.first_layer_speed).speed(1.0).range(1.0..=500.0)).labelled_by(label.id);
+        ui.end_row();
+        let label = ui.label("Travel speed (mm/s):");
+        ui.add(egui::DragValue::new(&mut e.travel_speed).speed(1.0).range(1.0..=1000.0)).labelled_by(label.id);
+        ui.end_row();
+    });
+}
+
+/// Retraction parameters and the travels they produce on the current layer.
+fn retraction_settings_ui(ui: &mut egui::Ui, r: &mut slicer::RetractionSettings, moves: Option<&slicer::LayerMoves>) {
+    egui::Grid::new("retraction").num_columns(2).show(ui, |ui| {
+        let label = ui.label("Length (mm):");
+        ui.add(egui::DragValue::new(&mut r.length).speed(0.05).range(0.0..=20.0)).labelled_by(label.id);
+        ui.end_row();
+        let label = ui.label("Speed (mm/s):");
+        ui.add(egui::DragValue::new(&mut r.speed).speed(1.0).range(1.0..=200.0)).labelled_by(label.id);
+        ui.end_row();
+        let label = ui.label("Z-hop (mm):");
+        ui.add(egui::DragValue::new(&mut r.z_hop).speed(0.05).range(0.0..=5.0)).labelled_by(label.id);
+        ui.end_row();
+        let label = ui.label("Minimum travel (mm):");
+        ui.add(egui::DragValue::new(&mut r.min_travel).speed(0.1).range(0.0..=50.0))
+            .labelled_by(label.id)
+            .on_hover_text("Shorter travels are made without retracting");
+        ui.end_row();
+    });
+    if let Some(moves) = moves {
+        ui.small(format!("This layer: {} travels, {} retracted", moves.travels.len(), moves.retractions()));
+    }
+}
+
+/// Solid layer counts and sparse density.
+fn infill_settings_ui(ui: &mut egui::Ui, f: &mut slicer::InfillSettings) {
+    egui::Grid::new("infill_settings").num_columns(2).show(ui, |ui| {
+        let label = ui.label("Top solid layers:");
+        ui.add(egui::DragValue::new(&mut f.top_layers).range(0..=50)).labelled_by(label.id);
+        ui.end_row();
+        let label = ui.label("Bottom solid layers:");
+        ui.add(egui::DragValue::new(&mut f.bottom_layers).range(0..=50)).labelled_by(label.id);
+        ui.end_row();
+        let label = ui.label("Infill density:");
+        ui.add(egui::Slider::new(&mut f.density, 0.0..=100.0).suffix(" %")).labelled_by(label.id);
+        ui.end_row();
+    });
+}
+
+/// Layer-height ranges and adaptive-height controls.
+fn slicer_settings_ui(ui: &mut egui::Ui, settings: &mut slicer::LayerSettings) {
+    ui.checkbox(&mut settings.adaptive, "Adapt to surface slope");
+    if settings.adaptive {
+        egui::Grid::new("adaptive_layers").num_columns(2).show(ui, |ui| {
+            let label = ui.label("Min height (mm):");
+            ui.add(egui::DragValue::new(&mut settings.min_height).speed(0.01).range(0.01..=10.0)).labelled_by(label.id);
+            ui.end_row();
+            let label = ui.label("Max height (mm):");
+            ui.add(egui::DragValue::new(&mut settings.max_height).speed(0.01).range(0.01..=10.0)).labelled_by(label.id);
+            ui.end_row();
+            let label = ui.label("Max cusp (mm):");
+            ui.add(egui::DragValue::new(&mut settings.max_cusp).speed(0.005).range(0.001..=1.0)).labelled_by(label.id);
+            ui.end_row();
+        });
+    }
+
+    ui.label("Height ranges (first match wins):");
+    let mut remove = None;
+    egui::Grid::new("layer_ranges").num_columns(4).show(ui, |ui| {
+        for (i, r) in settings.ranges.iter_mut().enumerate() {
+            ui.add(egui::DragValue::new(&mut r.z_min).speed(0.1).prefix("from "));
+            ui.add(egui::DragValue::new(&mut r.z_max).speed(0.1).prefix("to "));
+            ui.add(egui::DragValue::new(&mut r.height).speed(0.01).range(0.01..=10.0).prefix("h "));
+            if ui.small_button("✖").clicked() {
+                remove = Some(i);
+            }
+            ui.end_row();
+        }
+    });
+    if let Some(i) = remove {
+        settings.ranges.remove(i);
+    }
+    if ui.button("Add range").clicked() {
+        let z = settings.ranges.last().map_or(0.0, |r| r.z_max);
+        settings.ranges.push(slicer::LayerRange {
+            z_min: z,
+            z_max: z + 10.0,
+            height: settings.min_height,
+        });
+    }
+}
+
 /// Build an MVP matrix that always keeps the entire model in front of the camera.
 ///
 /// * `zoom` is interpreted as a dolly factor: 1 = default distance, 2 = half the distance, etc.
@@ -1421,6 +4400,37 @@ fn mvp(app: &AluminaApp, rect: egui::Rect) -> Matrix4<f32> {
     proj * view * model
 }
 
+/// Orthographic MVP for a quad-view pane; `zoom` 1 fits the work area.
+fn ortho_mvp(work_size: Vector3<f32>, view: &OrthoView, rect: egui::Rect) -> Matrix4<f32> {
+    let radius = work_size.norm() * 0.5;
+    let half_h = radius / view.zoom;
+    let half_w = half_h * rect.width() / rect.height();
+    let proj = Orthographic3::new(-half_w, half_w, -half_h, half_h, 0.1, 10_000.0).to_homogeneous();
+    let eye = Point3::new(0.0, 0.0, radius * 3.0);
+    let look = nalgebra::Isometry3::look_at_rh(&eye, &Point3::origin(), &Vector3::new(0.0, 1.0, 0.0)).to_homogeneous();
+
+    let pixels_per_world = rect.height() / (half_h * 2.0);
+    let pan = Vector3::new(
+        -view.translation.x / pixels_per_world,
+        view.translation.y / pixels_per_world,
+        0.0,
+    );
+    let model = Translation3::from(pan).to_homogeneous() * view.rotation.to_homogeneous();
+
+    proj * look * model
+}
+
+/// Small caption in the top-left corner of a viewport pane.
+fn pane_label(ui: &egui::Ui, rect: egui::Rect, text: &str) {
+    ui.painter().text(
+        rect.left_top() + egui::vec2(6.0, 4.0),
+        egui::Align2::LEFT_TOP,
+        text,
+        egui::FontId::proportional(13.0),
+        ui.visuals().weak_text_color(),
+    );
+}
+
 /// Pushes a tiny icosahedron (≈ sphere) into `out`, centred on `c`.
 fn add_vertex_sphere(c: Vector3<f32>, r: f32, col: [f32; 3], out: &mut Vec<f32>) {
     // golden-ratio icosahedron (12 verts, 20 tris)
@@ -1470,120 +4480,41 @@ fn add_vertex_sphere(c: Vector3<f32>, r: f32, col: [f32; 3], out: &mut Vec<f32>)
     }
 }
 
-fn spawn_file_picker(
-    target: Arc<Mutex<Option<Vec<u8>>>>,
-    _filter_name: &'static str,
-    exts: &'static [&'static str],
-) {
-    // 100 % non-blocking: the async task lives in the browser’s micro-task queue
-    execute(async move {
-        // ---1) build an <input type="file"> on the fly --------------------
-        let document = window()
-            .expect("no window")
-            .document()
-            .expect("no document");
-        let input: HtmlInputElement = document
-            .create_element("input")
-            .unwrap()
-            .dyn_into()
-            .unwrap();
-        input.set_type("file");
-
-        // Accept filter (".stl,.dxf", etc.)
-        let accept = exts
-            .iter()
-            .map(|e| format!(".{e}"))
-            .collect::<Vec<_>>()
-            .join(",");
-        input.set_accept(&accept);
-
-        input.style().set_property("display", "none").unwrap(); // invisible
-        document.body().unwrap().append_child(&input).unwrap();
-
-        // ---2) turn the "change" event into a Future -----------------------
-        let (tx, rx) = oneshot::channel::<()>();
-
-        // Wrap the Sender so we can *move* it exactly once inside an FnMut closure
-        let tx_cell = Rc::new(RefCell::new(Some(tx)));
-        let tx_handle = Rc::clone(&tx_cell);
-
-        let closure = Closure::<dyn FnMut(Event)>::wrap(Box::new(move |_e| {
-            if let Some(sender) = tx_handle.borrow_mut().take() {
-                let _ = sender.send(()); // 2nd call → already None → no-op
+/// Design graph shared through the page address (`#graph=…`), if any.
+fn graph_from_location() -> Option<design_graph::EditorState> {
+    let hash = platform::location_hash()?;
+    let link = hash.strip_prefix("#graph=")?;
+    match design_graph::SavedGraph::from_link(link) {
+        Ok(saved) => {
+            let (state, missing) = saved.into_state();
+            if missing > 0 {
+                toasts::warn(
+                    format!("{missing} plugin node(s) of the shared design are unavailable"),
+                    Some("load the plugins it uses, then open the link again".into()),
+                );
             }
-        }));
-        input
-            .add_event_listener_with_callback("change", closure.as_ref().unchecked_ref())
-            .unwrap();
-        closure.forget(); // leak => stays alive for the element’s lifetime
-
-        input.click(); // **opens** the browser dialog
-        rx.await.ok(); // wait until the user picked a file
-
-        // ---3) extract bytes with File::arrayBuffer -----------------------
-        let files = input.files().unwrap();
-        if files.length() == 0 {
-            return;
+            Some(state)
         }
-        let file = files.get(0).unwrap();
-
-        let buf_promise = file.array_buffer();
-        let js_buf = JsFuture::from(buf_promise).await.unwrap();
-        let u8_array = Uint8Array::new(&js_buf);
-        let mut bytes = vec![0u8; u8_array.length() as usize];
-        u8_array.copy_to(&mut bytes);
-
-        *target.lock().unwrap() = Some(bytes); // hand off to the egui thread
-    });
-}
-
-/// POST a simple text command to the firmware `/queue` endpoint.
-fn send_queue_command(cmd:&'static str){
-    execute(async move{
-        use wasm_bindgen::prelude::*;
-        use wasm_bindgen::JsCast;
-        use wasm_bindgen_futures::JsFuture;
-        use web_sys::{Request,RequestInit,Window,Response};
-        let window:Window=web_sys::window().expect("no window");
-        let mut opts=RequestInit::new();
-        opts.set_method("POST");
-        opts.set_body(&JsValue::from_str(cmd));
-        let request=Request::new_with_str_and_init("/queue",&opts).unwrap();
-        request.headers().set("Accept","text/plain").ok();
-        request.headers().set("Content-Type","text/plain").ok();
-        let resp_value=JsFuture::from(window.fetch_with_request(&request)).await;
-        if let Ok(val)=resp_value{
-            let _resp:Response=val.dyn_into().unwrap();
+        Err(e) => {
+            toasts::error("The shared design link could not be read", Some(e.to_string()));
+            None
         }
-    });
+    }
 }
 
-/// GET a text endpoint and return the body as String.
-async fn http_get_text(path: &str) -> Result<String, JsValue> {
-    use wasm_bindgen::JsCast;
-    use wasm_bindgen_futures::JsFuture;
-    let window = web_sys::window().ok_or(JsValue::from_str("no window"))?;
-    let resp_val = JsFuture::from(window.fetch_with_str(path)).await?;
-    let resp: web_sys::Response = resp_val.dyn_into()?;
-    let text = JsFuture::from(resp.text()?).await?;
-    text.as_string().ok_or(JsValue::from_str("no text"))
+/// Queue a command on the firmware; failures are raised as toasts.
+fn send_queue_command(cmd: impl Into<String>) {
+    net::send(net::Endpoint::Queue(cmd.into()));
 }
 
 fn load_mesh_from_bytes(bytes: &[u8]) -> Option<Mesh<()>> {
-    if let Ok(m) = Mesh::<()>::from_stl(bytes, None) {
-        return Some(m);
-    }
-
-    if let Ok(m) = Mesh::<()>::from_dxf(bytes, None) {
-        return Some(m);
-    }
-
-    None
+    engine::load_mesh(bytes).or_else(|| plugins::import_mesh(bytes))
 }
 
+#[cfg(target_arch = "wasm32")]
 #[wasm_bindgen(start)]
 pub async fn start() -> Result<(), JsValue> {
-    console_log::init_with_level(Level::Debug).expect("failed to init logger");
+    console_log::init_with_level(log::Level::Debug).expect("failed to init logger");
 
 	// Optionally fetch the Google Fonts index at startup (or on first use).
 	// Replace with your real API key (read-only metadata).
@@ -1604,7 +4535,7 @@ pub async fn start() -> Result<(), JsValue> {
     let canvas = document
         .get_element_by_id("alumina_canvas")
         .expect("canvas not found")
-        .dyn_into::<HtmlCanvasElement>()?; // ← cast
+        .dyn_into::<web_sys::HtmlCanvasElement>()?; // ← cast
 
     // Pass the element instead of the id
     eframe::WebRunner::new()
@@ -1618,6 +4549,20 @@ pub async fn start() -> Result<(), JsValue> {
     Ok(())
 }
 
-fn execute<F: Future<Output = ()> + 'static>(f: F) {
-    wasm_bindgen_futures::spawn_local(f);
+/// Desktop entry point (see `src/main.rs`).
+#[cfg(not(target_arch = "wasm32"))]
+pub fn run_native() -> eframe::Result {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+
+    let native_options = eframe::NativeOptions {
+        viewport: egui::ViewportBuilder::default()
+            .with_title(control::PAGE_TITLE)
+            .with_inner_size([1280.0, 800.0]),
+        ..Default::default()
+    };
+    eframe::run_native(
+        control::PAGE_TITLE,
+        native_options,
+        Box::new(|cc| Ok(Box::new(AluminaApp::new(cc)))),
+    )
 }
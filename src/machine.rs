@@ -0,0 +1,839 @@
+//! Machine profile: everything that differs between controller boards and
+//! machines (pinout, and later travel limits, outputs, connection settings).
+//!
+//! The active profile is persisted as JSON (`localStorage`, or the config
+//! directory on the desktop) so a board only has to be described once.
+
+use serde::{Deserialize, Serialize};
+
+use crate::platform::storage;
+
+const LS_KEY: &str = "alumina.machine";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PinDirection {
+    Input,
+    Output,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PinKind {
+    Digital,
+    Analog,
+}
+
+impl std::fmt::Display for PinDirection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            PinDirection::Input => "in",
+            PinDirection::Output => "out",
+        })
+    }
+}
+
+impl std::fmt::Display for PinKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            PinKind::Digital => "digital",
+            PinKind::Analog => "analog",
+        })
+    }
+}
+
+/// One user-visible GPIO pin.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PinDef {
+    /// Label shown in the UI and key reported by `/pins` (e.g. "D13").
+    pub name: String,
+    /// Firmware command prefix: digital outputs send `{prefix}_high` /
+    /// `{prefix}_low`, analog outputs send `{prefix}_write {value}`.
+    pub prefix: String,
+    pub direction: PinDirection,
+    pub kind: PinKind,
+}
+
+impl PinDef {
+    pub fn digital_out(name: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            prefix: name.to_ascii_lowercase(),
+            direction: PinDirection::Output,
+            kind: PinKind::Digital,
+        }
+    }
+
+    /// Command that drives a digital output high or low.
+    pub fn level_command(&self, high: bool) -> String {
+        format!("{}_{}", self.prefix, if high { "high" } else { "low" })
+    }
+
+    /// Command that writes an analog (PWM/DAC) value.
+    pub fn write_command(&self, value: u16) -> String {
+        format!("{}_write {value}", self.prefix)
+    }
+}
+
+/// Command dialect spoken by the controller.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Firmware {
+    /// Alumina firmware: plain-word commands (`status_on`, `feed_override 120`, …).
+    #[default]
+    Alumina,
+    Marlin,
+    Grbl,
+}
+
+impl std::fmt::Display for Firmware {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Firmware::Alumina => "Alumina",
+            Firmware::Marlin => "Marlin",
+            Firmware::Grbl => "GRBL",
+        })
+    }
+}
+
+/// Live, percentage-based adjustments applied to a running job.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Override {
+    Feed,
+    Power,
+    Flow,
+}
+
+impl Firmware {
+    /// What sets `which` to `percent` of the programmed value, in the order
+    /// to send it, or `None` if the dialect has no override for it.
+    pub fn override_command(self, which: Override, percent: u32) -> Option<Vec<crate::net::Endpoint>> {
+        use crate::net::Endpoint;
+        let line = match (self, which) {
+            (Firmware::Alumina, Override::Feed) => format!("feed_override {percent}"),
+            (Firmware::Alumina, Override::Power) => format!("power_override {percent}"),
+            (Firmware::Alumina, Override::Flow) => format!("flow_override {percent}"),
+            (Firmware::Marlin, Override::Feed) => format!("M220 S{percent}"),
+            (Firmware::Marlin, Override::Flow) => format!("M221 S{percent}"),
+            (Firmware::Grbl, Override::Feed) => return Some(grbl_override(0x90, percent)),
+            (Firmware::Grbl, Override::Power) => return Some(grbl_override(0x99, percent)),
+            // Marlin has no spindle/laser override, GRBL no flow
+            (Firmware::Marlin, Override::Power) | (Firmware::Grbl, Override::Flow) => return None,
+        };
+        Some(vec![Endpoint::Queue(line)])
+    }
+}
+
+/// GRBL overrides are real-time bytes that only step the value: `reset`
+/// back to 100 %, then ±10 % and ±1 % steps (the next four bytes) up or
+/// down to `percent`, which GRBL keeps within 10…200 %.
+fn grbl_override(reset: u8, percent: u32) -> Vec<crate::net::Endpoint> {
+    let delta = i64::from(percent.clamp(10, 200)) - 100;
+    let (coarse, fine) = if delta >= 0 { (reset + 1, reset + 3) } else { (reset + 2, reset + 4) };
+    let (tens, ones) = ((delta.abs() / 10) as usize, (delta.abs() % 10) as usize);
+    std::iter::once(reset)
+        .chain(std::iter::repeat_n(coarse, tens))
+        .chain(std::iter::repeat_n(fine, ones))
+        .map(crate::net::Endpoint::Realtime)
+        .collect()
+}
+
+/// What to do with a move that would leave the travel envelope.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SoftLimits {
+    Off,
+    /// Drop the move and warn.
+    #[default]
+    Refuse,
+    /// Shorten the move to the envelope and warn.
+    Clamp,
+}
+
+impl std::fmt::Display for SoftLimits {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            SoftLimits::Off => "off",
+            SoftLimits::Refuse => "refuse",
+            SoftLimits::Clamp => "clamp",
+        })
+    }
+}
+
+impl Firmware {
+    /// Homing command for one axis (0 = X, 1 = Y, 2 = Z) or all axes.
+    pub fn home_command(self, axis: Option<usize>) -> String {
+        let letter = axis.map(|i| ['X', 'Y', 'Z'][i]);
+        match (self, letter) {
+            (Firmware::Alumina, Some(l)) => format!("home {}", l.to_ascii_lowercase()),
+            (Firmware::Alumina, None) => "home".to_owned(),
+            (Firmware::Marlin, Some(l)) => format!("G28 {l}"),
+            (Firmware::Marlin, None) => "G28".to_owned(),
+            // single-axis homing needs HOMING_SINGLE_AXIS_COMMANDS (default in grblHAL)
+            (Firmware::Grbl, Some(l)) => format!("$H{l}"),
+            (Firmware::Grbl, None) => "$H".to_owned(),
+        }
+    }
+}
+
+impl Firmware {
+    /// Switch the spindle / laser / torch on at `percent` of full power.
+    pub fn tool_on_command(self, percent: f32) -> String {
+        let p = f64::from(percent.clamp(0.0, 100.0)) / 100.0;
+        match self {
+            Firmware::Alumina => format!("tool_on {:.0}", p * 100.0),
+            // Marlin cutter power is 0–255 by default, GRBL spindle max ($30) 1000
+            Firmware::Marlin => format!("M3 S{:.0}", p * 255.0),
+            Firmware::Grbl => format!("M3 S{:.0}", p * 1000.0),
+        }
+    }
+
+    pub fn tool_off_command(self) -> String {
+        match self {
+            Firmware::Alumina => "tool_off".to_owned(),
+            Firmware::Marlin | Firmware::Grbl => "M5".to_owned(),
+        }
+    }
+
+    /// Stop motion as soon as possible without losing position.
+    /// `None` for Marlin, which has no hold for a host stream (`M25` only
+    /// pauses SD prints): not sending the next line is the hold.
+    pub fn feed_hold(self) -> Option<crate::net::Endpoint> {
+        use crate::net::Endpoint;
+        match self {
+            Firmware::Alumina => Some(Endpoint::Queue("feed_hold".to_owned())),
+            Firmware::Marlin => None,
+            // real-time `!`, acted on ahead of the planner buffer
+            Firmware::Grbl => Some(Endpoint::Realtime(b'!')),
+        }
+    }
+
+    /// Stop a jog in progress and drop the rest of it: GRBL's jog-cancel
+    /// real-time byte, Marlin's quick stop.
+    pub fn jog_cancel(self) -> crate::net::Endpoint {
+        use crate::net::Endpoint;
+        match self {
+            Firmware::Alumina => Endpoint::Queue("jog_cancel".to_owned()),
+            Firmware::Marlin => Endpoint::Queue("M410".to_owned()),
+            Firmware::Grbl => Endpoint::Realtime(0x85),
+        }
+    }
+
+    /// Carry on after [`Self::feed_hold`]; `None` where streaming on is enough.
+    pub fn cycle_start(self) -> Option<crate::net::Endpoint> {
+        use crate::net::Endpoint;
+        match self {
+            Firmware::Alumina => Some(Endpoint::Queue("cycle_start".to_owned())),
+            Firmware::Marlin => None,
+            Firmware::Grbl => Some(Endpoint::Realtime(b'~')),
+        }
+    }
+
+    /// Whether `line` switches the tool on (`Some(true)`) or off.
+    pub fn tool_switch(self, line: &str) -> Option<bool> {
+        let line = line.trim();
+        if self == Firmware::Alumina {
+            return if line.starts_with("tool_on") {
+                Some(true)
+            } else if line.starts_with("tool_off") {
+                Some(false)
+            } else {
+                None
+            };
+        }
+        gcode_words(line).iter().find_map(|&(c, v)| match (c, v as i32) {
+            ('M', 3 | 4) => Some(true),
+            ('M', 5) => Some(false),
+            _ => None,
+        })
+    }
+
+    /// Wait `ms` milliseconds before the next command.
+    pub fn dwell_command(self, ms: f64) -> String {
+        match self {
+            // GRBL reads G4 P as seconds, Marlin as milliseconds
+            Firmware::Grbl => format!("G4 P{:.3}", ms / 1000.0),
+            Firmware::Alumina | Firmware::Marlin => format!("G4 P{ms:.0}"),
+        }
+    }
+
+    /// Set the part-cooling fan to `percent`, or `None` if the dialect has no
+    /// fan command.
+    pub fn fan_command(self, percent: f32) -> Option<String> {
+        let p = percent.clamp(0.0, 100.0);
+        match self {
+            Firmware::Alumina => Some(format!("fan {p:.0}")),
+            Firmware::Marlin if p <= 0.0 => Some("M107".to_owned()),
+            // Marlin fan PWM is 0–255
+            Firmware::Marlin => Some(format!("M106 S{:.0}", f64::from(p) * 2.55)),
+            // GRBL has no fan; builds that wire one to coolant use M7/M8 (Outputs)
+            Firmware::Grbl => None,
+        }
+    }
+}
+
+/// How an auxiliary output (coolant, air assist, exhaust, …) is switched.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum AuxControl {
+    /// Explicit commands, e.g. `M8` / `M9`.
+    Gcode { on: String, off: String },
+    /// A digital output from the pin map, driven with its level commands.
+    Pin(String),
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AuxOutput {
+    pub name: String,
+    pub control: AuxControl,
+}
+
+impl AuxOutput {
+    fn gcode(name: &str, on: &str, off: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            control: AuxControl::Gcode {
+                on: on.to_owned(),
+                off: off.to_owned(),
+            },
+        }
+    }
+
+    /// The G-code switching this output on, if it is switched by G-code.
+    pub fn on_command(&self) -> Option<&str> {
+        match &self.control {
+            AuxControl::Gcode { on, .. } if !on.trim().is_empty() => Some(on.trim()),
+            _ => None,
+        }
+    }
+
+    /// Command switching this output, resolved against the profile's pin map.
+    pub fn command(&self, profile: &MachineProfile, on: bool) -> Option<String> {
+        match &self.control {
+            AuxControl::Gcode { on: c, .. } if on => Some(c.clone()),
+            AuxControl::Gcode { off: c, .. } => Some(c.clone()),
+            AuxControl::Pin(name) => profile.pin(name).map(|p| p.level_command(on)),
+        }
+    }
+}
+
+impl Firmware {
+    /// Babystep (live Z nudge without changing coordinates), or `None` if unsupported.
+    pub fn babystep_command(self, dz: f64) -> Option<String> {
+        match self {
+            Firmware::Alumina => Some(format!("babystep {dz:.3}")),
+            Firmware::Marlin => Some(format!("M290 Z{dz:.3}")),
+            Firmware::Grbl => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MachineProfile {
+    pub name: String,
+    pub firmware: Firmware,
+    pub pins: Vec<PinDef>,
+    /// Travel envelope in machine coordinates (mm), X/Y/Z.
+    pub travel_min: [f64; 3],
+    pub travel_max: [f64; 3],
+    pub soft_limits: SoftLimits,
+    /// Refuse moves on axes that have not been homed since connecting.
+    pub require_homing: bool,
+    pub aux_outputs: Vec<AuxOutput>,
+    /// Z offset (mm) added to generated toolpaths; babystepping can bake into it.
+    pub z_offset: f64,
+    pub connection: Connection,
+}
+
+impl Default for MachineProfile {
+    /// The Alumina reference board (Arduino-style D0–D13 header).
+    fn default() -> Self {
+        Self {
+            name: "Alumina".to_owned(),
+            firmware: Firmware::Alumina,
+            pins: ["D0", "D1", "D2", "D3", "D4", "D5", "D6", "D7", "D9", "D11", "D12", "D13"]
+                .into_iter()
+                .map(PinDef::digital_out)
+                .collect(),
+            travel_min: [0.0; 3],
+            travel_max: [200.0; 3],
+            soft_limits: SoftLimits::Refuse,
+            require_homing: false,
+            aux_outputs: vec![
+                AuxOutput::gcode("Mist coolant", "M7", "M9"),
+                AuxOutput::gcode("Flood coolant", "M8", "M9"),
+                AuxOutput {
+                    name: "Air assist".to_owned(),
+                    control: AuxControl::Pin("D6".to_owned()),
+                },
+                AuxOutput {
+                    name: "Exhaust".to_owned(),
+                    control: AuxControl::Pin("D7".to_owned()),
+                },
+            ],
+            z_offset: 0.0,
+            connection: Connection::default(),
+        }
+    }
+}
+
+impl MachineProfile {
+    /// Restore the saved profile, falling back to the default board.
+    pub fn load() -> Self {
+        storage()
+            .and_then(|s| s.get_item(LS_KEY))
+            .and_then(|json| match serde_json::from_str(&json) {
+                Ok(p) => Some(p),
+                Err(e) => {
+                    log::error!("stored machine profile is invalid: {e}");
+                    crate::toasts::warn("Saved machine profile was invalid; using defaults", Some(e.to_string()));
+                    None
+                }
+            })
+            .unwrap_or_default()
+    }
+
+    /// Whether a profile has been saved, i.e. this is not the first run.
+    pub fn is_saved() -> bool {
+        storage().is_some_and(|s| s.get_item(LS_KEY).is_some())
+    }
+
+    pub fn save(&self) {
+        let Some(store) = storage() else { return };
+        match serde_json::to_string(self) {
+            Ok(json) => {
+                if let Err(e) = store.set_item(LS_KEY, &json) {
+                    log::error!("saving machine profile failed: {e}");
+                    crate::toasts::error("Machine profile could not be saved", None);
+                }
+            }
+            Err(e) => log::error!("serialising machine profile failed: {e}"),
+        }
+    }
+
+    pub fn pin(&self, name: &str) -> Option<&PinDef> {
+        self.pins.iter().find(|p| p.name == name)
+    }
+}
+
+// ---------- connection ------------------------------------------------------------------------------
+
+/// `http`/`https`; WebSocket channels use the matching `ws`/`wss`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Scheme {
+    #[default]
+    Http,
+    Https,
+}
+
+impl std::fmt::Display for Scheme {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Scheme::Http => "http",
+            Scheme::Https => "https",
+        })
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum Auth {
+    #[default]
+    None,
+    /// `Authorization: Bearer <token>`.
+    Token(String),
+    /// `Authorization: Basic …`.
+    Basic { user: String, password: String },
+}
+
+impl Auth {
+    /// Value of the `Authorization` header, if any.
+    pub fn header(&self) -> Option<String> {
+        use base64::Engine as _;
+        match self {
+            Auth::None => None,
+            Auth::Token(t) if t.trim().is_empty() => None,
+            Auth::Token(t) => Some(format!("Bearer {}", t.trim())),
+            Auth::Basic { user, password } => Some(format!(
+                "Basic {}",
+                base64::engine::general_purpose::STANDARD.encode(format!("{user}:{password}"))
+            )),
+        }
+    }
+}
+
+/// How to reach the controller over the network.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Connection {
+    pub scheme: Scheme,
+    /// Host name or address; empty for the default (the page origin in the
+    /// browser, `ALUMINA_URL` on the desktop).
+    pub host: String,
+    /// `None` for the scheme's default port.
+    pub port: Option<u16>,
+    /// Path every endpoint sits under (`/api` for `/api/queue`), when the
+    /// firmware is served behind a proxy or alongside other pages.
+    pub base_path: String,
+    pub auth: Auth,
+}
+
+impl Connection {
+    /// Base URL, or `None` for the default.
+    pub fn base_url(&self) -> Option<String> {
+        let host = self.host.trim();
+        if host.is_empty() {
+            return None;
+        }
+        Some(match self.port {
+            Some(port) => format!("{}://{host}:{port}", self.scheme),
+            None => format!("{}://{host}", self.scheme),
+        })
+    }
+
+    /// `base_path` as a prefix: a leading slash and no trailing one, or
+    /// empty.
+    pub fn path_prefix(&self) -> String {
+        let path = self.base_path.trim().trim_matches('/');
+        if path.is_empty() { String::new() } else { format!("/{path}") }
+    }
+
+    /// Point at `url` (`scheme://host[:port][/…]`), keeping the credentials
+    /// and base path.
+    /// An empty `url` selects the default.
+    pub fn set_url(&mut self, url: &str) {
+        let (scheme, rest) = match url.split_once("://") {
+            Some(("https", rest)) => (Scheme::Https, rest),
+            Some((_, rest)) => (Scheme::Http, rest),
+            None => (Scheme::Http, url),
+        };
+        let authority = rest.split('/').next().unwrap_or_default();
+        // a bare IPv6 address has more colons; leave it whole
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((h, p)) if !h.contains(':') => p.parse().map_or((authority, None), |port| (h, Some(port))),
+            _ => (authority, None),
+        };
+        self.scheme = scheme;
+        self.host = host.to_owned();
+        self.port = port;
+    }
+}
+
+// ---------- soft limits -----------------------------------------------------------------------------
+
+/// Outcome of checking one outgoing line against the travel envelope.
+#[derive(Clone, Debug, PartialEq)]
+pub enum LimitCheck {
+    /// Within limits (or not a move); send as is.
+    Pass(String),
+    /// Rewritten to stay inside the envelope; the second field explains why.
+    Clamped(String, String),
+    /// Must not be sent.
+    Refused(String),
+}
+
+/// Split a G-code line into `(letter, value)` words, dropping comments as
+/// [`crate::job::strip_comment`] does for the line that is streamed.
+pub fn gcode_words(line: &str) -> Vec<(char, f64)> {
+    let code = crate::job::strip_comment(line);
+
+    let mut words = Vec::new();
+    let mut chars = code.chars().peekable();
+    while let Some(c) = chars.next() {
+        if !c.is_ascii_alphabetic() {
+            continue;
+        }
+        let mut num = String::new();
+        while let Some(&d) = chars.peek() {
+            if d.is_ascii_digit() || d == '.' || d == '-' || d == '+' || d == ' ' {
+                if d != ' ' {
+                    num.push(d);
+                }
+                chars.next();
+            } else {
+                break;
+            }
+        }
+        if let Ok(v) = num.parse() {
+            words.push((c.to_ascii_uppercase(), v));
+        }
+    }
+    words
+}
+
+/// Join `(letter, value)` words back into a line.
+pub fn format_words(words: &[(char, f64)]) -> String {
+    words
+        .iter()
+        .map(|(c, v)| {
+            if matches!(c, 'G' | 'M' | 'T' | 'N') && v.fract() == 0.0 {
+                format!("{c}{v:.0}")
+            } else {
+                let s = format!("{v:.3}");
+                let s = s.trim_end_matches('0').trim_end_matches('.');
+                format!("{c}{s}")
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Follows the modal state of outgoing G-code (G90/G91, last position) so
+/// each move's target can be validated before it is sent.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MotionTracker {
+    pub absolute: bool,
+    /// Last commanded position in machine coordinates.
+    pub pos: [f64; 3],
+    /// Shift a `G92` put on the coordinates programs move in: machine
+    /// position minus programmed position.
+    pub offset: [f64; 3],
+}
+
+impl Default for MotionTracker {
+    fn default() -> Self {
+        Self {
+            absolute: true,
+            pos: [0.0; 3],
+            offset: [0.0; 3],
+        }
+    }
+}
+
+impl MachineProfile {
+    /// Travel range of axis `i`, lowest first however the limits were
+    /// entered; unbounded if either limit is not a number.
+    pub fn travel(&self, i: usize) -> (f64, f64) {
+        let (a, b) = (self.travel_min[i], self.travel_max[i]);
+        if a.is_nan() || b.is_nan() { (f64::NEG_INFINITY, f64::INFINITY) } else { (a.min(b), a.max(b)) }
+    }
+}
+
+/// Points of an XY arc (`G2` when `clockwise`, else `G3`) from `from` to
+/// `to` where it reaches furthest along ±X and ±Y, centre given by `I`/`J`
+/// (relative to `from`) or `R` in `words`. Endpoints are not included.
+fn arc_extremes(from: [f64; 3], to: [f64; 3], words: &[(char, f64)], clockwise: bool) -> Vec<[f64; 2]> {
+    use std::f64::consts::{FRAC_PI_2, TAU};
+    let word = |l: char| words.iter().find(|(c, _)| *c == l).map(|&(_, v)| v);
+    let centre = match (word('I'), word('J'), word('R')) {
+        (None, None, Some(r)) => {
+            let (dx, dy) = (to[0] - from[0], to[1] - from[1]);
+            let d = dx.hypot(dy);
+            if d == 0.0 {
+                return Vec::new();
+            }
+            // G3 with positive R has its centre left of the chord, G2 right
+            let h = (r * r - d * d / 4.0).max(0.0).sqrt();
+            let side = if clockwise == (r > 0.0) { -1.0 } else { 1.0 };
+            [(from[0] + to[0]) / 2.0 - dy / d * h * side, (from[1] + to[1]) / 2.0 + dx / d * h * side]
+        }
+        (i, j, _) => [from[0] + i.unwrap_or(0.0), from[1] + j.unwrap_or(0.0)],
+    };
+    let r = (from[0] - centre[0]).hypot(from[1] - centre[1]);
+    let a0 = (from[1] - centre[1]).atan2(from[0] - centre[0]);
+    let a1 = (to[1] - centre[1]).atan2(to[0] - centre[0]);
+    let mut span = if clockwise { a0 - a1 } else { a1 - a0 }.rem_euclid(TAU);
+    if span < 1e-9 {
+        span = TAU;
+    }
+    (0..4)
+        .map(|k| f64::from(k) * FRAC_PI_2)
+        .filter(|&t| if clockwise { a0 - t } else { t - a0 }.rem_euclid(TAU) <= span)
+        .map(|t| [centre[0] + r * t.cos(), centre[1] + r * t.sin()])
+        .collect()
+}
+
+impl MotionTracker {
+    pub fn check(&mut self, line: &str, profile: &MachineProfile) -> LimitCheck {
+        let words = gcode_words(line);
+        let axis = |c: char| match c {
+            'X' => Some(0),
+            'Y' => Some(1),
+            'Z' => Some(2),
+            _ => None,
+        };
+        let mut arc = None;
+        for &(c, v) in &words {
+            match (c, v as i32) {
+                ('G', 90) => self.absolute = true,
+                ('G', 91) => self.absolute = false,
+                ('G', 2) => arc = Some(true),
+                ('G', 3) => arc = Some(false),
+                ('G', 92) if (v - 92.0).abs() > 1e-6 => {
+                    // G92.1 (and G92.2, which suspends it) drop the shift
+                    self.offset = [0.0; 3];
+                    return LimitCheck::Pass(line.to_owned());
+                }
+                ('G', 92) => {
+                    // the tool stays put; the named axes get new coordinates
+                    for &(c, v) in &words {
+                        if let Some(i) = axis(c) {
+                            self.offset[i] = self.pos[i] - v;
+                        }
+                    }
+                    return LimitCheck::Pass(line.to_owned());
+                }
+                // homing / machine coordinates: trust the firmware, don't track
+                ('G', 28 | 53) => return LimitCheck::Pass(line.to_owned()),
+                _ => {}
+            }
+        }
+        if !words.iter().any(|(c, _)| axis(*c).is_some()) {
+            return LimitCheck::Pass(line.to_owned());
+        }
+
+        let mut target = self.pos;
+        for &(c, v) in &words {
+            if let Some(i) = axis(c) {
+                target[i] = if self.absolute { v + self.offset[i] } else { target[i] + v };
+            }
+        }
+
+        let out = |i: usize, v: f64| {
+            let (lo, hi) = profile.travel(i);
+            (v < lo || v > hi).then(|| format!("{}={v:.3} not in [{lo}, {hi}]", ['X', 'Y', 'Z'][i]))
+        };
+        let mut outside: Vec<String> = (0..3).filter_map(|i| out(i, target[i])).collect();
+        if let (Some(clockwise), true) = (arc, outside.is_empty()) {
+            // the arc may bulge past the limits between its endpoints
+            for p in arc_extremes(self.pos, target, &words, clockwise) {
+                outside.extend((0..2).filter_map(|i| out(i, p[i]).map(|why| format!("arc reaches {why}"))));
+            }
+        }
+        if outside.is_empty() || profile.soft_limits == SoftLimits::Off {
+            self.pos = target;
+            return LimitCheck::Pass(line.to_owned());
+        }
+        let why = outside.join(", ");
+        if profile.soft_limits == SoftLimits::Refuse || arc.is_some() {
+            return LimitCheck::Refused(format!("`{}` refused: {why}", line.trim()));
+        }
+
+        let mut clamped = target;
+        for i in 0..3 {
+            let (lo, hi) = profile.travel(i);
+            clamped[i] = clamped[i].clamp(lo, hi);
+        }
+        let rewritten: Vec<(char, f64)> = words
+            .iter()
+            .map(|&(c, v)| match axis(c) {
+                Some(i) if self.absolute => (c, clamped[i] - self.offset[i]),
+                Some(i) => (c, clamped[i] - self.pos[i]),
+                None => (c, v),
+            })
+            .collect();
+        self.pos = clamped;
+        LimitCheck::Clamped(format_words(&rewritten), format!("`{}` clamped: {why}", line.trim()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn near(a: [f64; 2], b: [f64; 2]) -> bool {
+        (a[0] - b[0]).abs() < 1e-9 && (a[1] - b[1]).abs() < 1e-9
+    }
+
+    fn tracker_at(pos: [f64; 3]) -> MotionTracker {
+        MotionTracker { pos, ..MotionTracker::default() }
+    }
+
+    fn bytes(endpoints: Option<Vec<crate::net::Endpoint>>) -> Vec<u8> {
+        endpoints
+            .unwrap_or_default()
+            .into_iter()
+            .map(|e| match e {
+                crate::net::Endpoint::Realtime(b) => b,
+                other => panic!("not a real-time byte: {other}"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn grbl_override_resets_then_steps_tens_before_ones() {
+        let fw = Firmware::Grbl;
+        assert_eq!(bytes(fw.override_command(Override::Feed, 100)), [0x90]);
+        assert_eq!(bytes(fw.override_command(Override::Feed, 123)), [0x90, 0x91, 0x91, 0x93, 0x93, 0x93]);
+        assert_eq!(bytes(fw.override_command(Override::Feed, 88)), [0x90, 0x92, 0x94, 0x94]);
+        assert_eq!(bytes(fw.override_command(Override::Power, 111)), [0x99, 0x9A, 0x9C]);
+        assert_eq!(bytes(fw.override_command(Override::Power, 90)), [0x99, 0x9B]);
+        assert!(fw.override_command(Override::Flow, 100).is_none());
+    }
+
+    #[test]
+    fn grbl_override_clamps_to_10_to_200_percent() {
+        let fw = Firmware::Grbl;
+        for (which, reset) in [(Override::Feed, 0x90), (Override::Power, 0x99)] {
+            let up = bytes(fw.override_command(which, 500));
+            assert_eq!(up.len(), 11);
+            assert!(up[0] == reset && up[1..].iter().all(|&b| b == reset + 1));
+            let down = bytes(fw.override_command(which, 0));
+            assert_eq!(down.len(), 10);
+            assert!(down[0] == reset && down[1..].iter().all(|&b| b == reset + 2));
+        }
+    }
+
+    #[test]
+    fn check_tracks_linear_moves_and_refuses_past_the_limits() {
+        let profile = MachineProfile::default();
+        let mut t = MotionTracker::default();
+        assert_eq!(t.check("G1 X10 Y20", &profile), LimitCheck::Pass("G1 X10 Y20".to_owned()));
+        assert_eq!(t.pos, [10.0, 20.0, 0.0]);
+        assert!(matches!(t.check("G0 X250", &profile), LimitCheck::Refused(_)));
+        assert_eq!(t.pos, [10.0, 20.0, 0.0]);
+
+        let clamp = MachineProfile { soft_limits: SoftLimits::Clamp, ..MachineProfile::default() };
+        assert!(matches!(t.check("G1 X250 Y5", &clamp), LimitCheck::Clamped(line, _) if line == "G1 X200 Y5"));
+        assert_eq!(t.pos, [200.0, 5.0, 0.0]);
+    }
+
+    #[test]
+    fn check_follows_relative_mode_and_g92() {
+        let profile = MachineProfile { soft_limits: SoftLimits::Clamp, ..MachineProfile::default() };
+        let mut t = tracker_at([10.0, 10.0, 0.0]);
+        t.check("G91", &profile);
+        t.check("G1 X5 Z2", &profile);
+        assert_eq!(t.pos, [15.0, 10.0, 2.0]);
+        assert!(matches!(t.check("G1 X-20", &profile), LimitCheck::Clamped(line, _) if line == "G1 X-15"));
+        assert_eq!(t.pos, [0.0, 10.0, 2.0]);
+
+        t.check("G90", &profile);
+        t.check("G92 X0 Y0", &profile);
+        t.check("G1 X100", &profile);
+        assert_eq!(t.pos, [100.0, 10.0, 2.0]);
+        // clamped in the shifted coordinates the program moves in
+        t.check("G92 X50", &profile);
+        assert!(matches!(t.check("G1 X190", &profile), LimitCheck::Clamped(line, _) if line == "G1 X150"));
+        t.check("G92.1", &profile);
+        t.check("G1 X20", &profile);
+        assert_eq!(t.pos, [20.0, 10.0, 2.0]);
+    }
+
+    #[test]
+    fn check_refuses_arcs_bulging_past_the_limits() {
+        let profile = MachineProfile::default();
+        // half circles from (10,100) to (10,60) about (10,80): G2 bulges to X=30, G3 to X=-10
+        let mut t = tracker_at([10.0, 100.0, 0.0]);
+        assert!(matches!(t.check("G2 X10 Y60 J-20", &profile), LimitCheck::Pass(_)));
+        let mut t = tracker_at([10.0, 100.0, 0.0]);
+        assert!(matches!(t.check("G3 X10 Y60 J-20", &profile), LimitCheck::Refused(why) if why.contains("arc reaches X=-10")));
+        assert_eq!(t.pos, [10.0, 100.0, 0.0]);
+    }
+
+    #[test]
+    fn arc_extremes_follow_the_direction_of_travel() {
+        // quarter circles from (1,0) to (0,1) about the origin
+        let (from, to) = ([1.0, 0.0, 0.0], [0.0, 1.0, 0.0]);
+        let words = [('I', -1.0), ('J', 0.0)];
+        let ccw = arc_extremes(from, to, &words, false);
+        assert!(!ccw.iter().any(|&p| near(p, [-1.0, 0.0]) || near(p, [0.0, -1.0])));
+        let cw = arc_extremes(from, to, &words, true);
+        assert!(cw.iter().any(|&p| near(p, [-1.0, 0.0])) && cw.iter().any(|&p| near(p, [0.0, -1.0])));
+    }
+
+    #[test]
+    fn arc_extremes_find_the_centre_from_r() {
+        // half circles from (0,0) to (2,0): G3 R1 runs below the chord, G2 R1 above
+        let (from, to) = ([0.0, 0.0, 0.0], [2.0, 0.0, 0.0]);
+        let g3 = arc_extremes(from, to, &[('R', 1.0)], false);
+        assert!(g3.iter().any(|&p| near(p, [1.0, -1.0])) && !g3.iter().any(|&p| near(p, [1.0, 1.0])));
+        let g2 = arc_extremes(from, to, &[('R', 1.0)], true);
+        assert!(g2.iter().any(|&p| near(p, [1.0, 1.0])) && !g2.iter().any(|&p| near(p, [1.0, -1.0])));
+    }
+}
@@ -0,0 +1,9 @@
+//! Desktop binary. The web build starts from `alumina_ui::start` instead.
+
+#[cfg(not(target_arch = "wasm32"))]
+fn main() -> eframe::Result {
+    alumina_ui::run_native()
+}
+
+#[cfg(target_arch = "wasm32")]
+fn main() {}
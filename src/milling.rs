@@ -0,0 +1,373 @@
+//! Stock-aware 2.5-D milling on height maps.
+//!
+//! Stock and part are rasterised into height maps over the same grid. Each
+//! operation clears level by level in zig-zag rows, never letting the flat
+//! endmill below the part surface under its footprint, and only where it
+//! would actually remove material. The stock map is updated after every
+//! operation, so a following operation with a smaller tool only visits the
+//! rest material its predecessors could not reach.
+//!
+//! A program can also be simulated on the same grid: the endmill is swept
+//! along every move, lowering the stock under its footprint to its tip, and
+//! the result is compared with the part for gouges and uncut material. The
+//! same sweep flags moves where the holder or a clamp gets in the way (see
+//! [`crate::collision`]).
+
+use csgrs::mesh::Mesh;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    collision::{Collision, Fixture, Hit, ToolAssembly, fixture_hit},
+    machine::gcode_words,
+};
+
+/// Z values on a regular XY grid; `f32::NEG_INFINITY` where empty.
+#[derive(Clone, Debug)]
+pub struct HeightMap {
+    pub origin: [f64; 2],
+    pub cell: f64,
+    pub nx: usize,
+    pub ny: usize,
+    pub z: Vec<f32>,
+}
+
+impl HeightMap {
+    pub fn new(origin: [f64; 2], cell: f64, nx: usize, ny: usize, fill: f32) -> Self {
+        Self {
+            origin,
+            cell,
+            nx,
+            ny,
+            z: vec![fill; nx * ny],
+        }
+    }
+
+    /// Grid covering `lo..hi` with at most `max_cells` along either side.
+    pub fn covering(lo: [f64; 2], hi: [f64; 2], cell: f64, max_cells: usize, fill: f32) -> Self {
+        let span = (hi[0] - lo[0]).max(hi[1] - lo[1]).max(1e-3);
+        let cell = cell.max(span / max_cells as f64);
+        let nx = ((hi[0] - lo[0]) / cell).ceil() as usize + 1;
+        let ny = ((hi[1] - lo[1]) / cell).ceil() as usize + 1;
+        Self::new(lo, cell, nx, ny, fill)
+    }
+
+    pub fn xy(&self, i: usize, j: usize) -> [f64; 2] {
+        [self.origin[0] + i as f64 * self.cell, self.origin[1] + j as f64 * self.cell]
+    }
+
+    /// Raise cells to the highest surface of `mesh` above them.
+    pub fn raise_to(&mut self, mesh: &Mesh<()>) {
+        for p in &mesh.polygons {
+            let v: Vec<[f64; 3]> = p.vertices.iter().map(|v| [v.pos.x, v.pos.y, v.pos.z]).collect();
+            for k in 1..v.len().saturating_sub(1) {
+                self.raster_triangle([v[0], v[k], v[k + 1]]);
+            }
+        }
+    }
+
+    fn raster_triangle(&mut self, t: [[f64; 3]; 3]) {
+        let [a, b, c] = t;
+        let det = (b[1] - c[1]) * (a[0] - c[0]) + (c[0] - b[0]) * (a[1] - c[1]);
+        if det.abs() < 1e-12 {
+            return; // vertical facet: covered by its neighbours
+        }
+        let min_x = a[0].min(b[0]).min(c[0]);
+        let max_x = a[0].max(b[0]).max(c[0]);
+        let min_y = a[1].min(b[1]).min(c[1]);
+        let max_y = a[1].max(b[1]).max(c[1]);
+        let to_cell = |v: f64, o: f64| (v - o) / self.cell;
+        let i0 = to_cell(min_x, self.origin[0]).ceil().max(0.0) as usize;
+        let j0 = to_cell(min_y, self.origin[1]).ceil().max(0.0) as usize;
+        let i1 = to_cell(max_x, self.origin[0]).floor().min(self.nx as f64 - 1.0);
+        let j1 = to_cell(max_y, self.origin[1]).floor().min(self.ny as f64 - 1.0);
+        if i1 < 0.0 || j1 < 0.0 {
+            return;
+        }
+        for j in j0..=j1 as usize {
+            for i in i0..=i1 as usize {
+                let [x, y] = self.xy(i, j);
+                let l1 = ((b[1] - c[1]) * (x - c[0]) + (c[0] - b[0]) * (y - c[1])) / det;
+                let l2 = ((c[1] - a[1]) * (x - c[0]) + (a[0] - c[0]) * (y - c[1])) / det;
+                let l3 = 1.0 - l1 - l2;
+                if l1 >= -1e-9 && l2 >= -1e-9 && l3 >= -1e-9 {
+                    let z = (l1 * a[2] + l2 * b[2] + l3 * c[2]) as f32;
+                    let cell = &mut self.z[j * self.nx + i];
+                    *cell = cell.max(z);
+                }
+            }
+        }
+    }
+
+    /// Cell offsets inside a disc of `radius`.
+    fn disc(&self, radius: f64) -> Vec<(isize, isize)> {
+        let r = (radius / self.cell).floor() as isize;
+        let r2 = (radius / self.cell).powi(2);
+        (-r..=r)
+            .flat_map(|dj| (-r..=r).map(move |di| (di, dj)))
+            .filter(|&(di, dj)| ((di * di + dj * dj) as f64) <= r2 + 1e-9)
+            .collect()
+    }
+
+    fn neighbours<'a>(&'a self, i: usize, j: usize, disc: &'a [(isize, isize)]) -> impl Iterator<Item = usize> + 'a {
+        disc.iter().filter_map(move |&(di, dj)| {
+            let (x, y) = (i as isize + di, j as isize + dj);
+            (x >= 0 && y >= 0 && (x as usize) < self.nx && (y as usize) < self.ny).then(|| y as usize * self.nx + x as usize)
+        })
+    }
+
+    /// Raise cells under `fixture` to its top, so no operation cuts there.
+    pub fn raise_fixture(&mut self, fixture: &Fixture) {
+        let top = fixture.top() as f32;
+        for j in 0..self.ny {
+            for i in 0..self.nx {
+                let [x, y] = self.xy(i, j);
+                if fixture.distance(x, y) <= 0.0 {
+                    let cell = &mut self.z[j * self.nx + i];
+                    *cell = cell.max(top);
+                }
+            }
+        }
+    }
+
+    /// Volume (mm³) of `self` above `floor`.
+    pub fn volume_above(&self, floor: &HeightMap) -> f64 {
+        let area = self.cell * self.cell;
+        self.z
+            .iter()
+            .zip(&floor.z)
+            .map(|(&s, &f)| f64::from((s - f).max(0.0)) * area)
+            .filter(|v| v.is_finite())
+            .sum()
+    }
+}
+
+/// One roughing pass with a flat endmill.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Operation {
+    pub tool_diameter: f64,
+    /// Depth per level (mm).
+    pub stepdown: f64,
+    /// Row spacing as a fraction of the tool diameter.
+    pub stepover: f64,
+}
+
+/// Result of one operation: tool-centre paths and the stock left behind.
+#[derive(Clone, Debug)]
+pub struct OperationResult {
+    pub paths: Vec<Vec<[f64; 3]>>,
+    pub remaining: HeightMap,
+    /// Material still above the part after this operation (mm³).
+    pub rest_volume: f64,
+}
+
+/// Material thinner than this is not worth another pass (mm).
+const MIN_REST: f32 = 0.05;
+
+/// Clear `stock` down toward `part` with `op`. `stock` is the material left
+/// by earlier operations, so only rest material is machined. `part` must be
+/// finite everywhere (fill empty cells with the stock bottom).
+pub fn run_operation(stock: &HeightMap, part: &HeightMap, op: &Operation) -> OperationResult {
+    let mut remaining = stock.clone();
+    let radius = op.tool_diameter * 0.5;
+    let disc = stock.disc(radius);
+
+    // lowest the tool centre may go anywhere: highest part surface under its footprint
+    let floor: Vec<f32> = (0..stock.ny)
+        .flat_map(|j| (0..stock.nx).map(move |i| (i, j)))
+        .map(|(i, j)| stock.neighbours(i, j, &disc).map(|k| part.z[k]).fold(f32::NEG_INFINITY, f32::max))
+        .collect();
+
+    let top = stock.z.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    let bottom = floor.iter().copied().filter(|z| z.is_finite()).fold(f32::INFINITY, f32::min);
+    let row_step = ((op.tool_diameter * op.stepover.clamp(0.05, 1.0)) / stock.cell).round().max(1.0) as usize;
+
+    let mut paths = Vec::new();
+    if !top.is_finite() || !bottom.is_finite() {
+        return OperationResult { rest_volume: remaining.volume_above(part), paths, remaining };
+    }
+    let mut level = top;
+    loop {
+        level = (level - op.stepdown.max(0.01) as f32).max(bottom);
+        for (row, j) in (0..stock.ny).step_by(row_step).enumerate() {
+            let cols: Vec<usize> = if row % 2 == 0 { (0..stock.nx).collect() } else { (0..stock.nx).rev().collect() };
+            let mut run: Vec<[f64; 3]> = Vec::new();
+            for i in cols {
+                let f = floor[j * stock.nx + i];
+                let depth = level.max(f);
+                if remaining.neighbours(i, j, &disc).any(|k| remaining.z[k] > depth + MIN_REST) {
+                    for k in stock.neighbours(i, j, &disc) {
+                        remaining.z[k] = remaining.z[k].min(depth);
+                    }
+                    let [x, y] = stock.xy(i, j);
+                    run.push([x, y, f64::from(depth)]);
+                } else if !run.is_empty() {
+                    paths.push(std::mem::take(&mut run));
+                }
+            }
+            if !run.is_empty() {
+                paths.push(run);
+            }
+        }
+        if level <= bottom {
+            break;
+        }
+    }
+    OperationResult {
+        rest_volume: remaining.volume_above(part),
+        paths,
+        remaining,
+    }
+}
+
+/// Run operations in sequence, each on the stock the previous one left.
+pub fn run_sequence(stock: &HeightMap, part: &HeightMap, ops: &[Operation]) -> Vec<OperationResult> {
+    let mut current = stock.clone();
+    let mut out = Vec::new();
+    for op in ops {
+        let r = run_operation(&current, part, op);
+        current = r.remaining.clone();
+        out.push(r);
+    }
+    out
+}
+
+/// Tool-tip positions of a program's G0 – G3 moves, in mm and absolute
+/// coordinates, from the first one that sets every axis. Arcs are taken as
+/// straight moves to their end point.
+pub fn program_moves(text: &str) -> Vec<[f64; 3]> {
+    let mut pos: [Option<f64>; 3] = [None; 3];
+    let (mut absolute, mut scale) = (true, 1.0);
+    let mut moving = false;
+    let mut out = Vec::new();
+    for line in text.lines() {
+        let words = gcode_words(line);
+        let mut axes = false;
+        for &(c, v) in &words {
+            match (c, v as i32) {
+                ('G', 0..=3) => moving = true,
+                ('G', 20) => scale = 25.4,
+                ('G', 21) => scale = 1.0,
+                ('G', 90) => absolute = true,
+                ('G', 91) => absolute = false,
+                ('G', 28 | 80..=89) => moving = false,
+                _ => axes |= "XYZ".contains(c),
+            }
+        }
+        if !moving || !axes {
+            continue;
+        }
+        for &(c, v) in &words {
+            if let Some(i) = "XYZ".find(c) {
+                pos[i] = Some(if absolute { v * scale } else { pos[i].unwrap_or(0.0) + v * scale });
+            }
+        }
+        if let [Some(x), Some(y), Some(z)] = pos {
+            out.push([x, y, z]);
+        }
+    }
+    out
+}
+
+/// A flat endmill cutting a program into the stock, a few moves at a time.
+#[derive(Clone, Debug)]
+pub struct Simulation {
+    /// Stock as cut so far, and the part it should become.
+    pub stock: HeightMap,
+    pub part: HeightMap,
+    moves: Vec<[f64; 3]>,
+    tool: ToolAssembly,
+    fixtures: Vec<Fixture>,
+    disc: Vec<(isize, isize)>,
+    holder_disc: Vec<(isize, isize)>,
+    /// Moves cut so far.
+    done: usize,
+    /// Moves that hit something, in program order.
+    pub collisions: Vec<Collision>,
+}
+
+/// Cut deeper than the part by more than this counts as a gouge, and stock
+/// left higher as uncut (mm).
+pub const SIM_TOLERANCE: f32 = 0.05;
+
+impl Simulation {
+    pub fn new(stock: HeightMap, part: HeightMap, moves: Vec<[f64; 3]>, tool: ToolAssembly, fixtures: Vec<Fixture>) -> Self {
+        let disc = stock.disc(tool.diameter * 0.5);
+        let holder_disc = stock.disc(tool.holder.diameter.max(tool.diameter) * 0.5);
+        Self { stock, part, moves, tool, fixtures, disc, holder_disc, done: 0, collisions: Vec::new() }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.done + 1 >= self.moves.len()
+    }
+
+    pub fn progress(&self) -> f32 {
+        self.done as f32 / self.moves.len().saturating_sub(1).max(1) as f32
+    }
+
+    /// Sweep the tool along the next `count` moves.
+    pub fn advance(&mut self, count: usize) {
+        let end = (self.done + count).min(self.moves.len().saturating_sub(1));
+        for n in self.done..end {
+            let (a, b) = (self.moves[n], self.moves[n + 1]);
+            let len = ((b[0] - a[0]).powi(2) + (b[1] - a[1]).powi(2)).sqrt();
+            let steps = ((len / (self.stock.cell * 0.5)).ceil() as usize).max(1);
+            for k in 0..=steps {
+                let t = k as f64 / steps as f64;
+                let p: [f64; 3] = std::array::from_fn(|i| a[i] + (b[i] - a[i]) * t);
+                let flagged = self.collisions.last().is_some_and(|c| c.index == n + 1);
+                if let Some(hit) = if flagged { None } else { self.collision(p) } {
+                    self.collisions.push(Collision { index: n + 1, at: p, hit });
+                }
+                self.plunge(p);
+            }
+        }
+        self.done = end;
+    }
+
+    /// Grid cell under `p`, which may be off the grid.
+    fn cell_at(&self, p: [f64; 3]) -> (isize, isize) {
+        (
+            ((p[0] - self.stock.origin[0]) / self.stock.cell).round() as isize,
+            ((p[1] - self.stock.origin[1]) / self.stock.cell).round() as isize,
+        )
+    }
+
+    /// What the assembly runs into with its tip at `p`, before cutting there.
+    fn collision(&self, p: [f64; 3]) -> Option<Hit> {
+        if let Some(k) = fixture_hit(&self.tool, &self.fixtures, p) {
+            return Some(Hit::Fixture(k));
+        }
+        let face = (p[2] + self.tool.length) as f32 + SIM_TOLERANCE;
+        let (i, j) = self.cell_at(p);
+        let (nx, ny) = (self.stock.nx as isize, self.stock.ny as isize);
+        self.holder_disc
+            .iter()
+            .map(|&(di, dj)| (i + di, j + dj))
+            .filter(|(x, y)| (0..nx).contains(x) && (0..ny).contains(y))
+            .any(|(x, y)| self.stock.z[(y * nx + x) as usize] > face)
+            .then_some(Hit::Stock)
+    }
+
+    /// Lower the stock under the tool's footprint at `p` to its tip.
+    fn plunge(&mut self, p: [f64; 3]) {
+        let (i, j) = self.cell_at(p);
+        let (nx, ny) = (self.stock.nx as isize, self.stock.ny as isize);
+        for &(di, dj) in &self.disc {
+            let (x, y) = (i + di, j + dj);
+            if (0..nx).contains(&x) && (0..ny).contains(&y) {
+                let cell = &mut self.stock.z[(y * nx + x) as usize];
+                *cell = cell.min(p[2] as f32);
+            }
+        }
+    }
+
+    /// Part volume cut away (mm³).
+    pub fn gouge_volume(&self) -> f64 {
+        self.part.volume_above(&self.stock)
+    }
+
+    /// Material left above the part (mm³).
+    pub fn rest_volume(&self) -> f64 {
+        self.stock.volume_above(&self.part)
+    }
+}
@@ -0,0 +1,357 @@
+//! Undo and redo of changes to the model list: models added, removed and
+//! replaced by a newly loaded mesh, and their scale and position, including
+//! Float and Center.
+//!
+//! Each change is kept as a command carrying what it takes to reverse and
+//! replay it. Dragging a scale or position field is one command, closed
+//! when the drag ends. Ctrl+Z undoes and Ctrl+Shift+Z redoes, unless a
+//! text field has the keyboard.
+
+use csgrs::mesh::Mesh;
+use eframe::egui;
+use nalgebra::Vector3;
+
+use crate::{AluminaApp, ModelEntry};
+
+/// Oldest commands are dropped past this many.
+const MAX_COMMANDS: usize = 100;
+
+/// Scale and offset of a model, and its base mesh where Float or Center
+/// changed it.
+#[derive(Clone)]
+pub(crate) struct Placement {
+    scale: Vector3<f32>,
+    offset: Vector3<f32>,
+    base: Option<Mesh<()>>,
+}
+
+impl Placement {
+    pub(crate) fn of(m: &ModelEntry) -> Self {
+        Self { scale: m.scale, offset: m.offset, base: None }
+    }
+
+    fn with_base(m: &ModelEntry) -> Self {
+        Self { base: Some(m.base.clone()), ..Self::of(m) }
+    }
+
+    fn apply(&self, m: &mut ModelEntry) {
+        m.scale = self.scale;
+        m.offset = self.offset;
+        if let Some(base) = &self.base {
+            m.base = base.clone();
+        }
+        // rebuilt by the next refresh
+        m.applied_offset = Vector3::repeat(f32::NAN);
+    }
+}
+
+enum Command {
+    Add { index: usize, entry: ModelEntry },
+    Remove { index: usize, entry: ModelEntry },
+    Place { index: usize, name: String, before: Placement, after: Placement },
+    Replace { index: usize, before: ModelEntry, after: ModelEntry },
+}
+
+impl Command {
+    fn describe(&self) -> String {
+        match self {
+            Command::Add { entry, .. } => format!("add “{}”", entry.name),
+            Command::Remove { entry, .. } => format!("remove “{}”", entry.name),
+            Command::Place { name, .. } => format!("move or scale “{name}”"),
+            Command::Replace { before, .. } => format!("replace “{}”", before.name),
+        }
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct ModelHistory {
+    undo: Vec<Command>,
+    redo: Vec<Command>,
+    /// Model being dragged, and its placement when the drag began.
+    editing: Option<(usize, Placement)>,
+}
+
+impl ModelHistory {
+    fn record(&mut self, command: Command) {
+        self.undo.push(command);
+        self.redo.clear();
+        if self.undo.len() > MAX_COMMANDS {
+            self.undo.remove(0);
+        }
+    }
+
+    /// Forget everything, e.g. when a session replaces the models.
+    pub(crate) fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    pub(crate) fn undo_label(&self) -> Option<String> {
+        self.undo.last().map(Command::describe)
+    }
+
+    pub(crate) fn redo_label(&self) -> Option<String> {
+        self.redo.last().map(Command::describe)
+    }
+
+    /// Reverse the last command on `models`: what it was, and the model to
+    /// select afterwards, if any.
+    fn undo(&mut self, models: &mut Vec<ModelEntry>) -> Option<(String, Option<usize>)> {
+        let command = self.undo.pop()?;
+        let selected = match &command {
+            Command::Add { index, .. } => {
+                if *index < models.len() {
+                    models.remove(*index);
+                }
+                None
+            }
+            Command::Remove { index, entry } => Some(insert(models, *index, entry.clone())),
+            Command::Place { index, before, .. } => {
+                if let Some(m) = models.get_mut(*index) {
+                    before.apply(m);
+                }
+                Some(*index)
+            }
+            Command::Replace { index, before, .. } => {
+                if let Some(m) = models.get_mut(*index) {
+                    *m = before.clone();
+                }
+                Some(*index)
+            }
+        };
+        let what = command.describe();
+        self.redo.push(command);
+        Some((what, selected))
+    }
+
+    /// Replay the last undone command on `models`, as [`Self::undo`].
+    fn redo(&mut self, models: &mut Vec<ModelEntry>) -> Option<(String, Option<usize>)> {
+        let command = self.redo.pop()?;
+        let selected = match &command {
+            Command::Add { index, entry } => Some(insert(models, *index, entry.clone())),
+            Command::Remove { index, .. } => {
+                if *index < models.len() {
+                    models.remove(*index);
+                }
+                None
+            }
+            Command::Place { index, after, .. } => {
+                if let Some(m) = models.get_mut(*index) {
+                    after.apply(m);
+                }
+                Some(*index)
+            }
+            Command::Replace { index, after, .. } => {
+                if let Some(m) = models.get_mut(*index) {
+                    *m = after.clone();
+                }
+                Some(*index)
+            }
+        };
+        let what = command.describe();
+        self.undo.push(command);
+        Some((what, selected))
+    }
+}
+
+/// Put `entry` back at `index`, or at the end if the list got shorter.
+fn insert(models: &mut Vec<ModelEntry>, index: usize, entry: ModelEntry) -> usize {
+    let index = index.min(models.len());
+    models.insert(index, entry);
+    index
+}
+
+impl AluminaApp {
+    pub(crate) fn record_model_added(&mut self, index: usize) {
+        self.finish_model_edit();
+        if let Some(entry) = self.models.get(index) {
+            let entry = entry.clone();
+            self.model_history.record(Command::Add { index, entry });
+        }
+    }
+
+    pub(crate) fn record_model_removed(&mut self, index: usize, entry: ModelEntry) {
+        self.finish_model_edit();
+        self.model_history.record(Command::Remove { index, entry });
+    }
+
+    /// The selected model's scale or position was edited from `before`;
+    /// recorded once the edit is over.
+    pub(crate) fn model_edited(&mut self, index: usize, before: Placement) {
+        if self.model_history.editing.as_ref().is_some_and(|(i, _)| *i != index) {
+            self.finish_model_edit();
+        }
+        if self.model_history.editing.is_none() {
+            self.model_history.editing = Some((index, before));
+        }
+    }
+
+    /// Model `index` was `before` until a new mesh replaced it.
+    pub(crate) fn record_model_replaced(&mut self, index: usize, before: ModelEntry) {
+        self.finish_model_edit();
+        if let Some(after) = self.models.get(index) {
+            let after = after.clone();
+            self.model_history.record(Command::Replace { index, before, after });
+        }
+    }
+
+    /// Float or Center moved model `index`'s base mesh away from `old_base`.
+    pub(crate) fn model_rebased(&mut self, index: usize, before: Placement, old_base: Mesh<()>) {
+        self.finish_model_edit();
+        let Some(m) = self.models.get(index) else { return };
+        let before = Placement { base: Some(old_base), ..before };
+        let command = Command::Place { index, name: m.name.clone(), before, after: Placement::with_base(m) };
+        self.model_history.record(command);
+    }
+
+    /// Close the edit in progress as one command.
+    fn finish_model_edit(&mut self) {
+        let Some((index, before)) = self.model_history.editing.take() else { return };
+        let Some(m) = self.models.get(index) else { return };
+        let after = Placement::of(m);
+        if after.scale != before.scale || after.offset != before.offset {
+            let command = Command::Place { index, name: m.name.clone(), before, after };
+            self.model_history.record(command);
+        }
+    }
+
+    pub(crate) fn undo_model(&mut self) {
+        self.finish_model_edit();
+        let Some((what, selected)) = self.model_history.undo(&mut self.models) else { return };
+        self.diag_log(format!("undo {what}"));
+        self.models_restored(selected);
+    }
+
+    pub(crate) fn redo_model(&mut self) {
+        self.finish_model_edit();
+        let Some((what, selected)) = self.model_history.redo(&mut self.models) else { return };
+        self.diag_log(format!("redo {what}"));
+        self.models_restored(selected);
+    }
+
+    fn models_restored(&mut self, selected: Option<usize>) {
+        if selected.is_some() {
+            self.selected_model = selected;
+        }
+        self.clamp_selection();
+        self.dirty.models = true;
+        self.refresh_models();
+        self.invalidate_layers();
+        self.refresh_slice();
+    }
+
+    /// Keyboard shortcuts, and closing an edit once its drag has ended.
+    pub(crate) fn tick_model_history(&mut self, ctx: &egui::Context) {
+        if ctx.dragged_id().is_none() {
+            self.finish_model_edit();
+        }
+        if ctx.wants_keyboard_input() {
+            return;
+        }
+        let (redo, undo) = ctx.input_mut(|i| {
+            let redo = i.consume_key(egui::Modifiers::COMMAND | egui::Modifiers::SHIFT, egui::Key::Z);
+            (redo, i.consume_key(egui::Modifiers::COMMAND, egui::Key::Z))
+        });
+        if redo {
+            self.redo_model();
+        } else if undo {
+            self.undo_model();
+        }
+    }
+
+    /// Undo / Redo buttons for the models panel.
+    pub(crate) fn model_history_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            let undo = self.model_history.undo_label();
+            let redo = self.model_history.redo_label();
+            let undo_button = ui.add_enabled(undo.is_some(), egui::Button::new("⟲ Undo"));
+            if undo_button.on_hover_text(format!("Undo {} (Ctrl+Z)", undo.unwrap_or_default())).clicked() {
+                self.undo_model();
+            }
+            let redo_button = ui.add_enabled(redo.is_some(), egui::Button::new("⟳ Redo"));
+            if redo_button.on_hover_text(format!("Redo {} (Ctrl+Shift+Z)", redo.unwrap_or_default())).clicked() {
+                self.redo_model();
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use csgrs::traits::CSG;
+
+    use super::*;
+
+    fn entry(name: &str) -> ModelEntry {
+        ModelEntry::new(name, Mesh::new())
+    }
+
+    fn names(models: &[ModelEntry]) -> Vec<&str> {
+        models.iter().map(|m| m.name.as_str()).collect()
+    }
+
+    #[test]
+    fn undo_and_redo_add_and_remove() {
+        let mut history = ModelHistory::default();
+        let mut models = vec![entry("a")];
+        models.push(entry("b"));
+        history.record(Command::Add { index: 1, entry: entry("b") });
+        let removed = models.remove(0);
+        history.record(Command::Remove { index: 0, entry: removed });
+        assert_eq!(names(&models), ["b"]);
+
+        assert_eq!(history.undo(&mut models).map(|(_, s)| s), Some(Some(0)));
+        assert_eq!(names(&models), ["a", "b"]);
+        assert_eq!(history.undo(&mut models).map(|(_, s)| s), Some(None));
+        assert_eq!(names(&models), ["a"]);
+        assert!(history.undo(&mut models).is_none());
+
+        history.redo(&mut models);
+        history.redo(&mut models);
+        assert_eq!(names(&models), ["b"]);
+        assert!(history.redo(&mut models).is_none());
+    }
+
+    #[test]
+    fn undo_restores_placement_and_replaced_entry() {
+        let mut history = ModelHistory::default();
+        let mut models = vec![entry("a")];
+        let before = Placement::of(&models[0]);
+        models[0].offset = Vector3::new(5.0, 0.0, 0.0);
+        let after = Placement::of(&models[0]);
+        history.record(Command::Place { index: 0, name: "a".into(), before, after });
+        let old = models[0].clone();
+        models[0] = entry("b");
+        history.record(Command::Replace { index: 0, before: old, after: models[0].clone() });
+        assert_eq!(history.undo_label().as_deref(), Some("replace “a”"));
+
+        history.undo(&mut models);
+        assert_eq!(names(&models), ["a"]);
+        assert_eq!(models[0].offset.x, 5.0);
+        history.undo(&mut models);
+        assert_eq!(models[0].offset.x, 0.0);
+
+        history.redo(&mut models);
+        history.redo(&mut models);
+        assert_eq!(names(&models), ["b"]);
+    }
+
+    #[test]
+    fn recording_drops_redo_and_oldest() {
+        let mut history = ModelHistory::default();
+        let mut models = Vec::new();
+        for i in 0..=MAX_COMMANDS {
+            models.push(entry(&i.to_string()));
+            history.record(Command::Add { index: i, entry: models[i].clone() });
+        }
+        assert_eq!(history.undo.len(), MAX_COMMANDS);
+        assert_eq!(history.undo.first().map(Command::describe).as_deref(), Some("add “1”"));
+
+        history.undo(&mut models);
+        assert!(history.redo_label().is_some());
+        history.record(Command::Add { index: 0, entry: entry("new") });
+        assert!(history.redo_label().is_none());
+
+        history.clear();
+        assert!(history.undo_label().is_none());
+    }
+}
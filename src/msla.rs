@@ -0,0 +1,355 @@
+//! MSLA printer job files, for printers that read their jobs from a USB
+//! stick rather than taking G-code.
+//!
+//! - `.ctb` and `.cbddlp` are ChiTuBox's binary containers, written as
+//!   version 2 (unencrypted): a header with the bottom-layer overrides,
+//!   two RLE previews, the print parameters, a table with each layer's
+//!   height and exposure, then every layer's RLE image. `.ctb` keeps the
+//!   greyscale of the masks; `.cbddlp` stores one bit per pixel.
+//! - `.sl1` is Prusa's: a ZIP of PNG layers and a `config.ini`. It has one
+//!   layer height and two exposures (bottom and normal), so per-range
+//!   exposures and variable layer heights are lost.
+//!
+//! Layer images are greyscale, one byte per pixel, rows top to bottom.
+
+use crate::{
+    archive::{Zip, png_grey},
+    resin::ResinSettings,
+    slicer::Layer,
+};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Format {
+    /// `n.png` per layer, with a manifest and `run.gcode`.
+    #[default]
+    PngZip,
+    Ctb,
+    Cbddlp,
+    Sl1,
+}
+
+impl Format {
+    pub const ALL: [Format; 4] = [Format::PngZip, Format::Ctb, Format::Cbddlp, Format::Sl1];
+
+    pub fn file_name(self) -> &'static str {
+        match self {
+            Format::PngZip => "layers.zip",
+            Format::Ctb => "job.ctb",
+            Format::Cbddlp => "job.cbddlp",
+            Format::Sl1 => "job.sl1",
+        }
+    }
+}
+
+impl std::fmt::Display for Format {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Format::PngZip => "PNG layers (.zip)",
+            Format::Ctb => "ChiTuBox (.ctb)",
+            Format::Cbddlp => "ChiTuBox mono (.cbddlp)",
+            Format::Sl1 => "Prusa SL1 (.sl1)",
+        })
+    }
+}
+
+/// Everything about a resin job the containers record besides its images.
+pub struct Job<'a> {
+    pub layers: &'a [Layer],
+    pub settings: &'a ResinSettings,
+    /// Lift outside every range (mm) and rest before each exposure (s).
+    pub lift: f32,
+    pub delay: f32,
+    /// Image size (pixels) and the build volume it covers (mm).
+    pub resolution: [usize; 2],
+    pub bed: [f32; 3],
+}
+
+/* ------------------------------------------------------------------------- */
+/*  Layer images                                                             */
+/* ------------------------------------------------------------------------- */
+
+/// `.ctb` layer image: runs of 7-bit grey, a run's length following its
+/// value (top bit set) in one to four bytes.
+pub fn rle_ctb(grey: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < grey.len() {
+        let code = grey[i] >> 1;
+        let mut run = 1;
+        while i + run < grey.len() && grey[i + run] >> 1 == code && run < 0x0FFF_FFFF {
+            run += 1;
+        }
+        if run == 1 {
+            out.push(code);
+        } else {
+            out.push(code | 0x80);
+            let r = run as u32;
+            if r <= 0x7F {
+                out.push(r as u8);
+            } else if r <= 0x3FFF {
+                out.extend([(r >> 8) as u8 | 0x80, r as u8]);
+            } else if r <= 0x1F_FFFF {
+                out.extend([(r >> 16) as u8 | 0xC0, (r >> 8) as u8, r as u8]);
+            } else {
+                out.extend([(r >> 24) as u8 | 0xE0, (r >> 16) as u8, (r >> 8) as u8, r as u8]);
+            }
+        }
+        i += run;
+    }
+    out
+}
+
+/// `.cbddlp` layer image: one byte per run, the top bit lit or dark and the
+/// rest the length (up to 125).
+pub fn rle_cbddlp(grey: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < grey.len() {
+        let lit = grey[i] >= 0x80;
+        let mut run = 1;
+        while i + run < grey.len() && (grey[i + run] >= 0x80) == lit && run < 0x7D {
+            run += 1;
+        }
+        out.push(run as u8 | if lit { 0x80 } else { 0 });
+        i += run;
+    }
+    out
+}
+
+/// Preview image: RGB 5-5-5 values, a run flagged by bit 5 and followed by
+/// its length (minus one) with 0x3000 set.
+fn rle_preview(grey: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < grey.len() {
+        let mut run = 1;
+        while i + run < grey.len() && grey[i + run] == grey[i] && run < 0x1000 {
+            run += 1;
+        }
+        let c = u16::from(grey[i] >> 3);
+        let colour = (c << 11) | (c << 6) | c;
+        if run == 1 {
+            out.extend(colour.to_le_bytes());
+        } else {
+            out.extend((colour | 0x20).to_le_bytes());
+            out.extend((0x3000 | (run as u16 - 1)).to_le_bytes());
+        }
+        i += run;
+    }
+    out
+}
+
+/// `image` (`size` pixels) scaled to fit `w` × `h`: lit pixels light on a
+/// dark ground, for previews.
+fn thumbnail(image: &[u8], size: [usize; 2], w: usize, h: usize) -> Vec<u8> {
+    let [iw, ih] = size.map(|v| v.max(1));
+    (0..h)
+        .flat_map(|y| (0..w).map(move |x| (x, y)))
+        .map(|(x, y)| if image.get((y * ih / h) * iw + x * iw / w).is_some_and(|&v| v >= 0x80) { 0xE0 } else { 0x30 })
+        .collect()
+}
+
+/* ------------------------------------------------------------------------- */
+/*  Containers                                                               */
+/* ------------------------------------------------------------------------- */
+
+fn u32_at(out: &mut [u8], at: usize, v: u32) {
+    out[at..at + 4].copy_from_slice(&v.to_le_bytes());
+}
+
+fn len32(v: usize) -> u32 {
+    u32::try_from(v).unwrap_or(u32::MAX)
+}
+
+/// `.ctb` (or `.cbddlp` when `mono`) file of `job`. `images` are the
+/// layers' images encoded with [`rle_ctb`] (or [`rle_cbddlp`]); `preview`
+/// is the plain image the thumbnails are drawn from.
+pub fn chitu_file(job: &Job<'_>, images: &[Vec<u8>], preview: &[u8], mono: bool) -> Vec<u8> {
+    const HEADER: usize = 112;
+    let s = job.settings;
+    let bottom = s.bottom_layers().min(len32(job.layers.len()));
+    let first = s.layer(0, job.lift);
+    let normal = s.layer(usize::try_from(bottom).unwrap_or(0), job.lift);
+    let height = job.layers.first().map_or(0.05, |l| l.height);
+    let speed = s.lift_speed.max(1.0);
+    let f = |v: f32| v.to_le_bytes();
+
+    let mut out = vec![0u8; HEADER];
+
+    // previews: resolution, image address and length, four spare words
+    let mut previews = [0usize; 2];
+    for (k, (w, h)) in [(400, 300), (200, 125)].into_iter().enumerate() {
+        previews[k] = out.len();
+        let image = rle_preview(&thumbnail(preview, job.resolution, w, h));
+        for v in [len32(w), len32(h), len32(out.len() + 32), len32(image.len()), 0, 0, 0, 0] {
+            out.extend(v.to_le_bytes());
+        }
+        out.extend(image);
+    }
+
+    let parameters = out.len();
+    for v in [first.lift, speed, normal.lift, speed, speed, 0.0, 0.0, 0.0, job.delay, job.delay] {
+        out.extend(f(v));
+    }
+    out.extend(bottom.to_le_bytes());
+    out.extend([0; 16]);
+    let parameters_len = out.len() - parameters;
+
+    // slicer info: second-stage lifts and rests (unused), the machine name,
+    // anti-aliasing and per-layer settings flags, version
+    let slicer = out.len();
+    let name = b"Alumina";
+    out.extend([0; 28]);
+    out.extend(len32(slicer + 76).to_le_bytes());
+    out.extend(len32(name.len()).to_le_bytes());
+    let per_layer = s.ranges.iter().any(|r| r.first > 0);
+    out.extend([0x07, 0, 0, if per_layer { 0x50 } else { 0x40 }]);
+    out.extend(0u32.to_le_bytes());
+    out.extend(1u32.to_le_bytes());
+    out.extend(0x0106_0300u32.to_le_bytes());
+    out.extend([0; 24]);
+    let slicer_len = out.len() - slicer;
+    out.extend_from_slice(name);
+
+    // layer table: height, exposure, light-off delay, image address and
+    // length, page, table size, two spare words
+    let table = out.len();
+    let mut address = table + 36 * job.layers.len();
+    for (i, (layer, image)) in job.layers.iter().zip(images).enumerate() {
+        let p = s.layer(i, job.lift);
+        out.extend(f(layer.top()));
+        out.extend(f(p.exposure));
+        out.extend(f(job.delay));
+        for v in [len32(address), len32(image.len()), 0, 36, 0, 0] {
+            out.extend(v.to_le_bytes());
+        }
+        address += image.len();
+    }
+    for image in images {
+        out.extend_from_slice(image);
+    }
+
+    // header: bed size, two spare words, height, exposures, light-off delay
+    out[0..4].copy_from_slice(&(if mono { 0x12FD_0019u32 } else { 0x12FD_0086u32 }).to_le_bytes());
+    u32_at(&mut out, 4, 2);
+    for (k, v) in [job.bed[0], job.bed[1], job.bed[2], 0.0, 0.0, job.layers.last().map_or(0.0, Layer::top), height, normal.exposure, first.exposure, job.delay]
+        .into_iter()
+        .enumerate()
+    {
+        out[8 + 4 * k..12 + 4 * k].copy_from_slice(&f(v));
+    }
+    let time = s.print_time(job.layers, job.lift, job.delay);
+    let words = [
+        bottom,
+        len32(job.resolution[0]),
+        len32(job.resolution[1]),
+        len32(previews[0]),
+        len32(table),
+        len32(job.layers.len()),
+        len32(previews[1]),
+        time as u32,
+        0,
+        len32(parameters),
+        len32(parameters_len),
+        1,
+    ];
+    for (k, v) in words.into_iter().enumerate() {
+        u32_at(&mut out, 48 + 4 * k, v);
+    }
+    // light PWM (bottom and normal), no encryption
+    out[96..100].copy_from_slice(&[255, 0, 255, 0]);
+    u32_at(&mut out, 100, 0);
+    u32_at(&mut out, 104, len32(slicer));
+    u32_at(&mut out, 108, len32(slicer_len));
+    out
+}
+
+/// Name of layer `i`'s image in an `.sl1`.
+pub fn sl1_layer_name(i: usize) -> String {
+    format!("alumina{i:05}.png")
+}
+
+/// `config.ini` of an `.sl1` holding `job`'s layers.
+pub fn sl1_config(job: &Job<'_>) -> String {
+    let s = job.settings;
+    let bottom = s.bottom_layers().min(len32(job.layers.len()));
+    let normal = s.layer(usize::try_from(bottom).unwrap_or(0), job.lift);
+    [
+        "action = print".to_owned(),
+        "jobDir = alumina".to_owned(),
+        format!("expTime = {:.3}", normal.exposure),
+        format!("expTimeFirst = {:.3}", s.layer(0, job.lift).exposure),
+        format!("layerHeight = {:.4}", job.layers.first().map_or(0.05, |l| l.height)),
+        "materialName = Alumina".to_owned(),
+        format!("numFade = {bottom}"),
+        format!("numFast = {}", job.layers.len()),
+        "numSlow = 0".to_owned(),
+        "printProfile = Alumina".to_owned(),
+        format!("printTime = {:.0}", s.print_time(job.layers, job.lift, job.delay)),
+        "printerModel = SL1".to_owned(),
+        "printerProfile = Alumina".to_owned(),
+        "printerVariant = default".to_owned(),
+        "prusaSlicerVersion = Alumina".to_owned(),
+        "usedMaterial = 0".to_owned(),
+    ]
+    .join("\n")
+        + "\n"
+}
+
+/// Add layer `i`'s image to an `.sl1` being built.
+pub fn sl1_add_layer(zip: &mut Zip, i: usize, size: [usize; 2], grey: &[u8]) {
+    zip.add(&sl1_layer_name(i), &png_grey(size[0], size[1], grey));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn u32_from(out: &[u8], at: usize) -> u32 {
+        u32::from_le_bytes(out[at..at + 4].try_into().unwrap())
+    }
+
+    fn f32_from(out: &[u8], at: usize) -> f32 {
+        f32::from_le_bytes(out[at..at + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn rle_ctb_runs() {
+        assert_eq!(rle_ctb(&[0xFF]), [0x7F]);
+        assert_eq!(rle_ctb(&[0xFF, 0xFE, 0xFF]), [0xFF, 3]);
+        assert_eq!(rle_ctb(&[0, 0, 0xFF]), [0x80, 2, 0x7F]);
+        // lengths past 0x7F take two bytes, past 0x3FFF three
+        assert_eq!(rle_ctb(&[0; 200]), [0x80, 0x80, 200]);
+        assert_eq!(rle_ctb(&vec![0; 0x4000]), [0x80, 0xC0, 0x40, 0x00]);
+        assert!(rle_ctb(&[]).is_empty());
+    }
+
+    #[test]
+    fn chitu_file_header_and_layer_table() {
+        let layers: Vec<Layer> = (0..6).map(|i| Layer { z: i as f32 * 0.05, height: 0.05 }).collect();
+        let settings = ResinSettings::default();
+        let job = Job { layers: &layers, settings: &settings, lift: 5.0, delay: 1.0, resolution: [4, 2], bed: [80.0, 40.0, 100.0] };
+        let images: Vec<Vec<u8>> = (0..6u8).map(|i| rle_ctb(&[i * 40; 8])).collect();
+        let out = chitu_file(&job, &images, &[0xFF; 8], false);
+
+        assert_eq!(u32_from(&out, 0), 0x12FD_0086);
+        assert_eq!(u32_from(&out, 4), 2);
+        assert_eq!(f32_from(&out, 8), 80.0);
+        // normal and bottom exposure, bottom layer count, resolution, layer count
+        assert_eq!(f32_from(&out, 36), 8.0);
+        assert_eq!(f32_from(&out, 40), 40.0);
+        assert_eq!(u32_from(&out, 48), 4);
+        assert_eq!([u32_from(&out, 52), u32_from(&out, 56)], [4, 2]);
+        assert_eq!(u32_from(&out, 68), 6);
+
+        let table = u32_from(&out, 64) as usize;
+        for (i, image) in images.iter().enumerate() {
+            let entry = table + 36 * i;
+            assert!((f32_from(&out, entry) - layers[i].top()).abs() < 1e-6);
+            assert_eq!(f32_from(&out, entry + 4), if i < 4 { 40.0 } else { 8.0 });
+            let (address, len) = (u32_from(&out, entry + 12) as usize, u32_from(&out, entry + 16) as usize);
+            assert_eq!(&out[address..address + len], image.as_slice());
+        }
+        assert_eq!(u32_from(&chitu_file(&job, &images, &[], true), 0), 0x12FD_0019);
+    }
+}
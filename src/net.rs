@@ -0,0 +1,1007 @@
+//! HTTP client for the controller firmware.
+//!
+//! Every request goes through [`fetch`], which applies a per-attempt timeout
+//! and retries idempotent requests with a short back-off. Requests are
+//! addressed by the one [`FirmwareClient`], set up from the machine
+//! profile's connection settings: the host (typed in or picked from the
+//! discovered controllers) or, by default, the page origin in the browser
+//! (the UI is served by the controller) and `ALUMINA_URL` on the desktop,
+//! then an optional base path every endpoint sits under. An API token or
+//! basic-auth credentials are sent along when configured.
+//!
+//! Queued commands can go down a serial link instead, to a GRBL or Marlin
+//! board plugged in over USB: a serial port on the desktop, WebSerial in
+//! the browser (Chrome and Edge). While a link is open [`Endpoint::Queue`]
+//! uses it and everything else still goes over HTTP; [`link_ui`] opens
+//! and closes it. GRBL real-time bytes ([`Endpoint::Realtime`]) are
+//! written to the link straight away, ahead of any exchange in progress,
+//! and get no reply.
+//!
+//! Callers that care about the reply use [`spawn`] and poll the returned
+//! [`Pending`] slot from the UI loop; fire-and-forget commands use [`send`],
+//! whose failures are raised as error toasts instead of vanishing.
+//!
+//! Streams the firmware pushes (telemetry) are read from a WebSocket on the
+//! same host through a [`Feed`], which reconnects on its own.
+
+use std::{
+    fmt,
+    sync::{Arc, Mutex},
+};
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) use web::link_ui;
+#[cfg(target_arch = "wasm32")]
+use web::{attempt, sleep, watch};
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) use native::link_ui;
+#[cfg(not(target_arch = "wasm32"))]
+use native::{attempt, sleep, watch};
+
+/// Where firmware traffic goes and the credentials it carries. Requests,
+/// serial routing and feeds all address the controller through the one
+/// set with [`configure`]; other controllers are only probed, with
+/// [`fetch_from`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct FirmwareClient {
+    /// `scheme://host[:port]`; `None` for [`default_url`].
+    host: Option<String>,
+    /// Prefix of every endpoint path (`/api`), empty for none.
+    base_path: String,
+    /// `Authorization` header.
+    auth: Option<String>,
+}
+
+static CLIENT: Mutex<FirmwareClient> = Mutex::new(FirmwareClient { host: None, base_path: String::new(), auth: None });
+
+impl FirmwareClient {
+    fn new(connection: &crate::machine::Connection) -> Self {
+        Self { host: connection.base_url(), base_path: connection.path_prefix(), auth: connection.auth.header() }
+    }
+
+    /// The client configured last.
+    pub(crate) fn current() -> Self {
+        CLIENT.lock().unwrap().clone()
+    }
+
+    /// Address of the controller itself, without the base path.
+    pub(crate) fn host_url(&self) -> String {
+        self.host.clone().unwrap_or_else(default_url)
+    }
+
+    /// Address endpoint paths are appended to (empty: relative to the page).
+    pub(crate) fn base_url(&self) -> String {
+        format!("{}{}", self.host_url().trim_end_matches('/'), self.base_path)
+    }
+
+    /// Credentials go only to this controller, never to probed ones.
+    fn auth_for(&self, base: &str) -> Option<String> {
+        self.auth.clone().filter(|_| base == self.base_url())
+    }
+
+    /// Perform `endpoint` under `policy`.
+    pub(crate) async fn fetch(&self, endpoint: &Endpoint, policy: Policy) -> Result<String, NetError> {
+        fetch_from(&self.base_url(), endpoint, policy).await
+    }
+}
+
+/// Where requests go when the profile names no host: the page origin in
+/// the browser, `ALUMINA_URL` (default `http://alumina.local`) on the desktop.
+pub(crate) fn default_url() -> String {
+    #[cfg(target_arch = "wasm32")]
+    return String::new();
+    #[cfg(not(target_arch = "wasm32"))]
+    return std::env::var("ALUMINA_URL").unwrap_or_else(|_| "http://alumina.local".into());
+}
+
+/// Address requests are sent to (empty: relative to the page).
+pub(crate) fn base_url() -> String {
+    CLIENT.lock().unwrap().base_url()
+}
+
+/// Send further requests as `connection` describes.
+pub(crate) fn configure(connection: &crate::machine::Connection) {
+    *CLIENT.lock().unwrap() = FirmwareClient::new(connection);
+}
+
+fn auth_for(base: &str) -> Option<String> {
+    CLIENT.lock().unwrap().auth_for(base)
+}
+
+/// Slot an in-flight HTTP request writes its outcome into.
+pub(crate) type Pending = Arc<Mutex<Option<Result<String, String>>>>;
+
+/// Firmware endpoints.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Endpoint {
+    /// POST a command line to `/queue`; the body is the firmware's reply.
+    Queue(String),
+    /// GET `/position` (Alumina firmware).
+    Position,
+    /// GET any other path (diagnostic scripts, link monitor).
+    Get(String),
+    /// A GRBL real-time byte (feed hold, jog cancel, overrides), acted on
+    /// ahead of the queue; POSTed to `/realtime` as two hex digits.
+    Realtime(u8),
+}
+
+impl Endpoint {
+    fn path(&self) -> &str {
+        match self {
+            Endpoint::Queue(_) => "/queue",
+            Endpoint::Position => "/position",
+            Endpoint::Get(p) => p,
+            Endpoint::Realtime(_) => "/realtime",
+        }
+    }
+
+    fn body(&self) -> Option<std::borrow::Cow<'_, str>> {
+        match self {
+            Endpoint::Queue(cmd) => Some(cmd.into()),
+            Endpoint::Realtime(byte) => Some(format!("{byte:02x}").into()),
+            Endpoint::Position | Endpoint::Get(_) => None,
+        }
+    }
+
+    /// Timeout and retry count suited to the endpoint. Queued commands are
+    /// never retried: a lost reply does not mean the move was not executed.
+    /// Heating and homing get [`SLOW_COMMAND_MS`] to answer.
+    pub fn policy(&self) -> Policy {
+        match self {
+            Endpoint::Queue(cmd) if is_slow(cmd) => Policy { timeout_ms: SLOW_COMMAND_MS, retries: 0, ..Policy::default() },
+            Endpoint::Queue(_) | Endpoint::Realtime(_) => Policy { retries: 0, ..Policy::default() },
+            Endpoint::Position | Endpoint::Get(_) => Policy::default(),
+        }
+    }
+}
+
+impl fmt::Display for Endpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Endpoint::Queue(cmd) => write!(f, "POST /queue {cmd:?}"),
+            Endpoint::Realtime(byte) => write!(f, "real-time {byte:#04x}"),
+            Endpoint::Position | Endpoint::Get(_) => write!(f, "GET {}", self.path()),
+        }
+    }
+}
+
+/// Time allowed for commands that answer only when done (ms).
+const SLOW_COMMAND_MS: i32 = 600_000;
+
+/// Whether `cmd` answers only once a wait for heat or a homing or probing
+/// run is over: `M109`, `M190`, `G28`, `G29`, `$H` and Alumina's `home`.
+fn is_slow(cmd: &str) -> bool {
+    let cmd = cmd.trim_start();
+    cmd.starts_with("$H")
+        || cmd.starts_with("home")
+        || crate::machine::gcode_words(cmd).iter().any(|&(c, v)| matches!((c, v as i32), ('M', 109 | 190) | ('G', 28 | 29)))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Policy {
+    pub timeout_ms: i32,
+    /// Extra attempts after the first one fails.
+    pub retries: u32,
+    /// Delay before the first retry; doubled for every further one.
+    pub backoff_ms: i32,
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Self {
+            timeout_ms: 5000,
+            retries: 2,
+            backoff_ms: 250,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum NetError {
+    Timeout,
+    /// Non-2xx reply: status and body.
+    Http(u16, String),
+    /// The request could not be made (controller unreachable, CORS, …).
+    Network(String),
+    /// The firmware answered `error…` on the serial link.
+    Rejected(String),
+}
+
+impl NetError {
+    fn retryable(&self) -> bool {
+        match self {
+            NetError::Timeout | NetError::Network(_) => true,
+            NetError::Http(status, _) => *status >= 500,
+            NetError::Rejected(_) => false,
+        }
+    }
+}
+
+impl fmt::Display for NetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NetError::Timeout => write!(f, "timed out"),
+            NetError::Http(status, body) if body.trim().is_empty() => write!(f, "HTTP {status}"),
+            NetError::Http(status, body) => write!(f, "HTTP {status}: {}", body.trim()),
+            NetError::Network(e) => write!(f, "network error: {e}"),
+            NetError::Rejected(reply) => write!(f, "rejected: {reply}"),
+        }
+    }
+}
+
+/// Add one line of a serial reply to `reply`; returns the outcome once the
+/// firmware's `ok` / `error` arrives. A GRBL `<…>` status report (the
+/// answer to `?`) is kept in the reply but ends nothing: the `ok` for the
+/// rest of the line still follows. While `late` is above zero, answers
+/// still owed to commands that timed out are taken off it instead of
+/// ending this one.
+fn serial_reply(reply: &mut String, line: &str, late: &mut usize) -> Option<Result<String, NetError>> {
+    let line = line.trim();
+    if line.is_empty() {
+        return None;
+    }
+    let ok = line.starts_with("ok");
+    let failed = line.starts_with("error") || line.starts_with("ALARM");
+    if (ok || failed) && *late > 0 {
+        log::debug!("[net] late serial reply {line:?} dropped");
+        *late -= 1;
+        reply.clear();
+        return None;
+    }
+    if !reply.is_empty() {
+        reply.push('\n');
+    }
+    reply.push_str(line);
+    if ok {
+        return Some(Ok(std::mem::take(reply)));
+    }
+    if failed {
+        return Some(Err(NetError::Rejected(std::mem::take(reply))));
+    }
+    None
+}
+
+/// Marlin's keep-alive while it works on a long command: the reply is
+/// coming, so the wait for it starts over.
+fn is_busy(line: &str) -> bool {
+    line.trim_start().starts_with("echo:busy")
+}
+
+/// Perform `endpoint` under `policy`, through the configured client.
+pub async fn fetch_with(endpoint: &Endpoint, policy: Policy) -> Result<String, NetError> {
+    FirmwareClient::current().fetch(endpoint, policy).await
+}
+
+/// Perform `endpoint` on the controller at `base` rather than the chosen one.
+pub async fn fetch_from(base: &str, endpoint: &Endpoint, policy: Policy) -> Result<String, NetError> {
+    let mut delay = policy.backoff_ms;
+    let mut tries_left = policy.retries;
+    loop {
+        match attempt(base, endpoint, policy.timeout_ms).await {
+            Err(e) if tries_left > 0 && e.retryable() => {
+                log::debug!("[net] {endpoint}: {e}, retrying in {delay} ms");
+                tries_left -= 1;
+                sleep(delay).await;
+                delay = delay.saturating_mul(2);
+            }
+            other => return other,
+        }
+    }
+}
+
+/// Perform `endpoint` under its default policy.
+pub async fn fetch(endpoint: &Endpoint) -> Result<String, NetError> {
+    fetch_with(endpoint, endpoint.policy()).await
+}
+
+/// Run `endpoint` on the browser executor and hand the result back through a slot.
+pub(crate) fn spawn(endpoint: Endpoint) -> Pending {
+    let policy = endpoint.policy();
+    spawn_with(endpoint, policy)
+}
+
+pub(crate) fn spawn_with(endpoint: Endpoint, policy: Policy) -> Pending {
+    let slot: Pending = Arc::new(Mutex::new(None));
+    let target = Arc::clone(&slot);
+    crate::execute(async move {
+        let result = fetch_with(&endpoint, policy).await.map_err(|e| e.to_string());
+        *target.lock().unwrap() = Some(result);
+    });
+    slot
+}
+
+/// Fire-and-forget: failures are raised as error toasts.
+pub(crate) fn send(endpoint: Endpoint) {
+    crate::execute(async move {
+        if let Err(e) = fetch(&endpoint).await {
+            log::error!("[net] {endpoint}: {e}");
+            crate::toasts::error("Controller did not accept a command", Some(format!("{endpoint}: {e}")));
+        }
+    });
+}
+
+/// [`send`] each of `endpoints`, one after the other.
+pub(crate) fn send_all(endpoints: Vec<Endpoint>) {
+    crate::execute(async move {
+        for endpoint in endpoints {
+            if let Err(e) = fetch(&endpoint).await {
+                log::error!("[net] {endpoint}: {e}");
+                crate::toasts::error("Controller did not accept a command", Some(format!("{endpoint}: {e}")));
+                return;
+            }
+        }
+    });
+}
+
+/// Delay before reopening a dropped feed, doubling up to the maximum (ms).
+const FEED_RETRY_MS: (i32, i32) = (500, 10_000);
+
+/// Frames a feed holds before dropping the oldest, should nobody read it.
+const FEED_MAX_FRAMES: usize = 5_000;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) enum FeedState {
+    #[default]
+    Connecting,
+    Open,
+    /// Closed; reopening after a back-off.
+    Retrying,
+}
+
+impl fmt::Display for FeedState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            FeedState::Connecting => "connecting…",
+            FeedState::Open => "connected",
+            FeedState::Retrying => "disconnected, retrying…",
+        })
+    }
+}
+
+#[derive(Default)]
+struct FeedShared {
+    /// Frames with the connection they came over.
+    frames: std::collections::VecDeque<(u32, String)>,
+    state: FeedState,
+    dropped: bool,
+    /// Counts the times the socket was (re)opened.
+    connection: u32,
+}
+
+impl FeedShared {
+    fn push(&mut self, frame: String) {
+        if self.frames.len() >= FEED_MAX_FRAMES {
+            self.frames.pop_front();
+        }
+        self.frames.push_back((self.connection, frame));
+    }
+}
+
+/// Text frames from a WebSocket on the controller. The socket is kept open,
+/// and reopened whenever it closes, until the feed is dropped.
+pub(crate) struct Feed {
+    shared: Arc<Mutex<FeedShared>>,
+}
+
+impl Feed {
+    pub(crate) fn state(&self) -> FeedState {
+        self.shared.lock().unwrap().state
+    }
+
+    /// Frames received since the last call, oldest first, each with the
+    /// number of the connection it came over: the far end may have
+    /// restarted in between.
+    pub(crate) fn take(&self) -> Vec<(u32, String)> {
+        self.shared.lock().unwrap().frames.drain(..).collect()
+    }
+}
+
+impl Drop for Feed {
+    fn drop(&mut self) {
+        self.shared.lock().unwrap().dropped = true;
+    }
+}
+
+/// `ws://` (or `wss://`) address of `path` on the controller; relative to
+/// the page when requests are.
+fn ws_url(path: &str) -> String {
+    let base = base_url();
+    let base = match (base.strip_prefix("https://"), base.strip_prefix("http://")) {
+        (Some(rest), _) => format!("wss://{rest}"),
+        (_, Some(rest)) => format!("ws://{rest}"),
+        _ => base,
+    };
+    format!("{}/{}", base.trim_end_matches('/'), path.trim_start_matches('/'))
+}
+
+/// Subscribe to the WebSocket at `path` on the controller.
+pub(crate) fn subscribe(path: &str) -> Feed {
+    let shared = Arc::new(Mutex::new(FeedShared::default()));
+    let feed = Arc::clone(&shared);
+    let url = ws_url(path);
+    crate::execute(async move {
+        let mut delay = FEED_RETRY_MS.0;
+        while !feed.lock().unwrap().dropped {
+            {
+                let mut f = feed.lock().unwrap();
+                f.state = FeedState::Connecting;
+                f.connection = f.connection.wrapping_add(1);
+            }
+            if watch(&url, &feed).await {
+                delay = FEED_RETRY_MS.0;
+            }
+            if feed.lock().unwrap().dropped {
+                break;
+            }
+            feed.lock().unwrap().state = FeedState::Retrying;
+            log::debug!("[net] feed {url} closed, reopening in {delay} ms");
+            sleep(delay).await;
+            delay = delay.saturating_mul(2).min(FEED_RETRY_MS.1);
+        }
+    });
+    Feed { shared }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod web {
+    use super::{Endpoint, FeedShared, FeedState, NetError, auth_for, base_url, is_busy, serial_reply};
+    use js_sys::{Array, Object, Promise, Reflect, Uint8Array};
+    use std::{
+        cell::{Cell, RefCell},
+        rc::Rc,
+    };
+    use wasm_bindgen::{JsCast, prelude::*};
+    use wasm_bindgen_futures::JsFuture;
+    use std::sync::{Arc, Mutex};
+    use web_sys::{AbortController, MessageEvent, RequestInit, Response, WebSocket};
+
+    /// Open WebSerial port. The API is reached through `Reflect`, as
+    /// `web-sys` only has it behind its unstable-API flag.
+    struct WebSerial {
+        name: String,
+        port: JsValue,
+        writer: JsValue,
+        reader: JsValue,
+        /// Received text not yet split into lines.
+        received: RefCell<String>,
+        /// A read that lost the race against a timeout; the next exchange
+        /// picks it up so no data is dropped.
+        pending_read: RefCell<Option<Promise>>,
+        /// One exchange at a time: replies would interleave otherwise.
+        busy: Cell<bool>,
+        /// Answers still owed to commands that timed out.
+        late: Cell<usize>,
+    }
+
+    thread_local! {
+        static SERIAL: RefCell<Option<Rc<WebSerial>>> = const { RefCell::new(None) };
+    }
+
+    fn serial() -> Option<Rc<WebSerial>> {
+        SERIAL.with(|s| s.borrow().clone())
+    }
+
+    fn call(target: &JsValue, method: &str, args: &[JsValue]) -> Result<JsValue, JsValue> {
+        let f: js_sys::Function = Reflect::get(target, &method.into())?.dyn_into()?;
+        f.apply(target, &args.iter().collect::<Array>())
+    }
+
+    async fn call_async(target: &JsValue, method: &str, args: &[JsValue]) -> Result<JsValue, JsValue> {
+        let promise: Promise = call(target, method, args)?.dyn_into()?;
+        JsFuture::from(promise).await
+    }
+
+    /// Ask the user for a port and open it at `baud`.
+    async fn open_serial(baud: u32) -> Result<WebSerial, JsValue> {
+        let navigator = web_sys::window().ok_or_else(|| JsValue::from_str("no window"))?.navigator();
+        let api = Reflect::get(&navigator, &"serial".into())?;
+        if api.is_undefined() {
+            return Err("this browser has no WebSerial; use Chrome or Edge".into());
+        }
+        let port = call_async(&api, "requestPort", &[]).await?;
+        let options = Object::new();
+        Reflect::set(&options, &"baudRate".into(), &baud.into())?;
+        call_async(&port, "open", &[options.into()]).await?;
+        let writer = call(&Reflect::get(&port, &"writable".into())?, "getWriter", &[])?;
+        let reader = call(&Reflect::get(&port, &"readable".into())?, "getReader", &[])?;
+        let info = call(&port, "getInfo", &[])?;
+        let id = |key: &str| Reflect::get(&info, &key.into()).ok().and_then(|v| v.as_f64());
+        let name = match (id("usbVendorId"), id("usbProductId")) {
+            (Some(v), Some(p)) => format!("USB {:04x}:{:04x}", v as u32, p as u32),
+            _ => "Serial port".to_owned(),
+        };
+        Ok(WebSerial {
+            name,
+            port,
+            writer,
+            reader,
+            received: RefCell::new(String::new()),
+            pending_read: RefCell::new(None),
+            busy: Cell::new(false),
+            late: Cell::new(0),
+        })
+    }
+
+    fn connect_serial(baud: u32) {
+        wasm_bindgen_futures::spawn_local(async move {
+            match open_serial(baud).await {
+                Ok(link) => {
+                    log::info!("[net] serial link {} @ {baud}", link.name);
+                    SERIAL.with(|s| *s.borrow_mut() = Some(Rc::new(link)));
+                }
+                Err(e) => {
+                    log::error!("[net] opening a serial port failed: {}", js_message(&e));
+                    crate::toasts::error("The serial port could not be opened", Some(js_message(&e)));
+                }
+            }
+        });
+    }
+
+    fn disconnect_serial() {
+        let Some(link) = SERIAL.with(|s| s.borrow_mut().take()) else { return };
+        wasm_bindgen_futures::spawn_local(async move {
+            call_async(&link.reader, "cancel", &[]).await.ok();
+            call(&link.reader, "releaseLock", &[]).ok();
+            call(&link.writer, "releaseLock", &[]).ok();
+            if let Err(e) = call_async(&link.port, "close", &[]).await {
+                log::warn!("[net] closing {} failed: {}", link.name, js_message(&e));
+            }
+        });
+    }
+
+    /// Next complete line received, if any.
+    fn take_line(link: &WebSerial) -> Option<String> {
+        let mut received = link.received.borrow_mut();
+        let end = received.find('\n')?;
+        let line: String = received.drain(..=end).collect();
+        Some(line)
+    }
+
+    /// Write `cmd` and collect reply lines up to the firmware's answer.
+    async fn serial_command(link: &WebSerial, cmd: &str, timeout_ms: i32) -> Result<String, NetError> {
+        let result = exchange(link, cmd, timeout_ms).await;
+        if result == Err(NetError::Timeout) {
+            // its answer may still come; it must not end the next exchange
+            link.late.set(link.late.get() + 1);
+        }
+        result
+    }
+
+    async fn exchange(link: &WebSerial, cmd: &str, timeout_ms: i32) -> Result<String, NetError> {
+        let net = |e: JsValue| NetError::Network(js_message(&e));
+        let bytes = Uint8Array::from(format!("{}\n", cmd.trim()).as_bytes());
+        call_async(&link.writer, "write", &[bytes.into()]).await.map_err(net)?;
+        let mut deadline = js_sys::Date::now() + f64::from(timeout_ms);
+        let mut reply = String::new();
+        loop {
+            while let Some(line) = take_line(link) {
+                if is_busy(&line) {
+                    deadline = js_sys::Date::now() + f64::from(timeout_ms);
+                }
+                let mut late = link.late.get();
+                let done = serial_reply(&mut reply, &line, &mut late);
+                link.late.set(late);
+                if let Some(done) = done {
+                    return done;
+                }
+            }
+            let left = deadline - js_sys::Date::now();
+            if left <= 0.0 {
+                return Err(NetError::Timeout);
+            }
+            let read = match link.pending_read.borrow_mut().take() {
+                Some(read) => read,
+                None => call(&link.reader, "read", &[]).map_err(net)?.dyn_into().map_err(net)?,
+            };
+            let timer = Promise::new(&mut |resolve, _| {
+                if let Some(w) = web_sys::window() {
+                    w.set_timeout_with_callback_and_timeout_and_arguments_0(&resolve, left as i32).ok();
+                }
+            });
+            let chunk = JsFuture::from(Promise::race(&Array::of2(&read, &timer))).await.map_err(net)?;
+            if chunk.is_undefined() {
+                *link.pending_read.borrow_mut() = Some(read);
+                return Err(NetError::Timeout);
+            }
+            if Reflect::get(&chunk, &"done".into()).ok().and_then(|d| d.as_bool()).unwrap_or(false) {
+                return Err(NetError::Network("serial link closed".into()));
+            }
+            if let Ok(value) = Reflect::get(&chunk, &"value".into()).and_then(|v| v.dyn_into::<Uint8Array>()) {
+                link.received.borrow_mut().push_str(&String::from_utf8_lossy(&value.to_vec()));
+            }
+        }
+    }
+
+    /// Machine panel row connecting a USB board over WebSerial, or showing
+    /// the one connected.
+    pub(crate) fn link_ui(ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Link:");
+            if let Some(link) = serial() {
+                ui.label(format!("{} (WebSerial)", link.name));
+                if ui.button("Disconnect").on_hover_text("Send commands over the network again").clicked() {
+                    disconnect_serial();
+                }
+                return;
+            }
+            let base = base_url();
+            ui.label(if base.is_empty() { "this page's controller".to_owned() } else { base });
+            let baud_id = egui::Id::new("link_baud");
+            let mut baud: u32 = ui.data_mut(|d| *d.get_persisted_mut_or(baud_id, 115_200));
+            egui::ComboBox::from_id_salt("link_baud_select")
+                .selected_text(baud.to_string())
+                .width(80.0)
+                .show_ui(ui, |ui| {
+                    for rate in [9_600, 57_600, 115_200, 250_000] {
+                        ui.selectable_value(&mut baud, rate, rate.to_string());
+                    }
+                });
+            ui.data_mut(|d| d.insert_persisted(baud_id, baud));
+            if ui.button("Connect USB…").on_hover_text("Stream commands to a GRBL or Marlin board plugged into this computer").clicked() {
+                connect_serial(baud);
+            }
+        });
+    }
+
+    fn js_message(e: &JsValue) -> String {
+        e.as_string()
+            .or_else(|| js_sys::Reflect::get(e, &"message".into()).ok().and_then(|m| m.as_string()))
+            .unwrap_or_else(|| format!("{e:?}"))
+    }
+
+    pub(super) async fn sleep(ms: i32) {
+        let promise = js_sys::Promise::new(&mut |resolve, _| {
+            if let Some(w) = web_sys::window() {
+                w.set_timeout_with_callback_and_timeout_and_arguments_0(&resolve, ms).ok();
+            }
+        });
+        JsFuture::from(promise).await.ok();
+    }
+
+    /// Hold one WebSocket to `url` open until it closes or the feed is
+    /// dropped; true if it opened at all.
+    pub(super) async fn watch(url: &str, feed: &Arc<Mutex<FeedShared>>) -> bool {
+        let url = match url.strip_prefix('/') {
+            // the page's own host, over the page's scheme
+            Some(path) => match web_sys::window().map(|w| w.location()) {
+                Some(loc) => {
+                    let scheme = if loc.protocol().is_ok_and(|p| p == "https:") { "wss" } else { "ws" };
+                    format!("{scheme}://{}/{path}", loc.host().unwrap_or_default())
+                }
+                None => return false,
+            },
+            None => url.to_owned(),
+        };
+        let socket = match WebSocket::new(&url) {
+            Ok(s) => s,
+            Err(e) => {
+                log::warn!("[net] feed {url}: {}", js_message(&e));
+                return false;
+            }
+        };
+        let (opened, closed) = (Rc::new(Cell::new(false)), Rc::new(Cell::new(false)));
+        let on_open = {
+            let (opened, feed) = (Rc::clone(&opened), Arc::clone(feed));
+            Closure::<dyn FnMut()>::new(move || {
+                opened.set(true);
+                feed.lock().unwrap().state = FeedState::Open;
+            })
+        };
+        let on_message = {
+            let feed = Arc::clone(feed);
+            Closure::<dyn FnMut(MessageEvent)>::new(move |e: MessageEvent| {
+                if let Some(text) = e.data().as_string() {
+                    feed.lock().unwrap().push(text);
+                }
+            })
+        };
+        let on_close = {
+            let closed = Rc::clone(&closed);
+            Closure::<dyn FnMut()>::new(move || closed.set(true))
+        };
+        socket.set_onopen(Some(on_open.as_ref().unchecked_ref()));
+        socket.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+        socket.set_onclose(Some(on_close.as_ref().unchecked_ref()));
+        while !closed.get() {
+            sleep(250).await;
+            if feed.lock().unwrap().dropped {
+                socket.close().ok();
+                break;
+            }
+        }
+        socket.set_onopen(None);
+        socket.set_onmessage(None);
+        socket.set_onclose(None);
+        opened.get()
+    }
+
+    /// One attempt, aborted after `timeout_ms`.
+    pub(super) async fn attempt(base: &str, endpoint: &Endpoint, timeout_ms: i32) -> Result<String, NetError> {
+        if let (Endpoint::Realtime(byte), true, Some(link)) = (endpoint, base == base_url(), serial()) {
+            // not behind `busy`: it must overtake the exchange in progress
+            let bytes = Uint8Array::from(&[*byte][..]);
+            call_async(&link.writer, "write", &[bytes.into()]).await.map_err(|e| NetError::Network(js_message(&e)))?;
+            return Ok(String::new());
+        }
+        if let (Endpoint::Queue(cmd), true) = (endpoint, base == base_url()) {
+            if let Some(link) = serial() {
+                while link.busy.get() {
+                    sleep(5).await;
+                }
+                link.busy.set(true);
+                let result = serial_command(&link, cmd, timeout_ms).await;
+                link.busy.set(false);
+                return result;
+            }
+        }
+        let window = web_sys::window().ok_or_else(|| NetError::Network("no window".into()))?;
+        let abort = AbortController::new().map_err(|e| NetError::Network(js_message(&e)))?;
+        let opts = RequestInit::new();
+        opts.set_signal(Some(&abort.signal()));
+        if let Some(body) = endpoint.body() {
+            opts.set_method("POST");
+            opts.set_body(&JsValue::from_str(&body));
+        }
+        let url = format!("{}{}", base.trim_end_matches('/'), endpoint.path());
+        let request = web_sys::Request::new_with_str_and_init(&url, &opts)
+            .map_err(|e| NetError::Network(js_message(&e)))?;
+        request.headers().set("Accept", "text/plain").ok();
+        if let Some(auth) = auth_for(base) {
+            request.headers().set("Authorization", &auth).ok();
+        }
+        if endpoint.body().is_some() {
+            request.headers().set("Content-Type", "text/plain").ok();
+        }
+
+        let on_timeout = {
+            let abort = abort.clone();
+            Closure::once_into_js(move || abort.abort())
+        };
+        let timer = window
+            .set_timeout_with_callback_and_timeout_and_arguments_0(on_timeout.unchecked_ref(), timeout_ms)
+            .ok();
+        let result = async {
+            let resp: Response = JsFuture::from(window.fetch_with_request(&request))
+                .await
+                .map_err(|e| {
+                    if abort.signal().aborted() {
+                        NetError::Timeout
+                    } else {
+                        NetError::Network(js_message(&e))
+                    }
+                })?
+                .dyn_into()
+                .map_err(|e| NetError::Network(js_message(&e)))?;
+            let text = match resp.text() {
+                Ok(p) => JsFuture::from(p).await.ok().and_then(|t| t.as_string()).unwrap_or_default(),
+                Err(_) => String::new(),
+            };
+            if resp.ok() { Ok(text) } else { Err(NetError::Http(resp.status(), text)) }
+        }
+        .await;
+        if let Some(id) = timer {
+            window.clear_timeout_with_handle(id);
+        }
+        result
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[allow(clippy::unused_async)] // same signatures as the browser transport
+mod native {
+    use super::{Endpoint, FeedShared, FeedState, NetError, auth_for, base_url, is_busy, serial_reply};
+    use std::{
+        io::{BufRead, BufReader, Write},
+        sync::{Arc, Mutex},
+        time::{Duration, Instant},
+    };
+
+    /// Open serial link; while set, queued commands go here instead of HTTP.
+    /// The outer lock is only held briefly so the UI never waits on a reply.
+    static SERIAL: Mutex<Option<Link>> = Mutex::new(None);
+
+    /// Open port, read line by line.
+    struct SerialPort {
+        reader: BufReader<Box<dyn serialport::SerialPort>>,
+        /// Answers still owed to commands that timed out.
+        late: usize,
+    }
+    /// Port name, the port, and a second handle on it for real-time bytes,
+    /// which must not wait for the exchange holding the first.
+    type Link = (String, Arc<Mutex<SerialPort>>, Arc<Mutex<Box<dyn serialport::SerialPort>>>);
+
+    pub(super) async fn sleep(ms: i32) {
+        std::thread::sleep(Duration::from_millis(u64::try_from(ms).unwrap_or(0)));
+    }
+
+    /// Hold one WebSocket to `url` open until it closes or the feed is
+    /// dropped; true if it opened at all. Plain `ws://` only.
+    pub(super) async fn watch(url: &str, feed: &Arc<Mutex<FeedShared>>) -> bool {
+        let mut socket = match tungstenite::connect(url) {
+            Ok((socket, _)) => socket,
+            Err(e) => {
+                log::warn!("[net] feed {url}: {e}");
+                return false;
+            }
+        };
+        feed.lock().unwrap().state = FeedState::Open;
+        // a read timeout lets the loop notice the feed being dropped
+        if let tungstenite::stream::MaybeTlsStream::Plain(stream) = socket.get_mut() {
+            stream.set_read_timeout(Some(Duration::from_millis(250))).ok();
+        }
+        loop {
+            if feed.lock().unwrap().dropped {
+                socket.close(None).ok();
+                return true;
+            }
+            match socket.read() {
+                Ok(tungstenite::Message::Text(text)) => feed.lock().unwrap().push(text),
+                Ok(_) => {}
+                Err(tungstenite::Error::Io(e)) if matches!(e.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut) => {}
+                Err(_) => return true,
+            }
+        }
+    }
+
+    /// One attempt. Runs on its own thread (see `platform::execute`), so blocking is fine.
+    pub(super) async fn attempt(base: &str, endpoint: &Endpoint, timeout_ms: i32) -> Result<String, NetError> {
+        let timeout = Duration::from_millis(u64::try_from(timeout_ms).unwrap_or(0));
+        if let (Endpoint::Realtime(byte), true) = (endpoint, base == base_url()) {
+            let port = SERIAL.lock().unwrap().as_ref().map(|(_, _, rt)| Arc::clone(rt));
+            if let Some(port) = port {
+                port.lock().unwrap().write_all(&[*byte]).map_err(|e| NetError::Network(e.to_string()))?;
+                return Ok(String::new());
+            }
+        }
+        if let (Endpoint::Queue(cmd), true) = (endpoint, base == base_url()) {
+            let port = SERIAL.lock().unwrap().as_ref().map(|(_, p, _)| Arc::clone(p));
+            if let Some(port) = port {
+                return serial_command(&mut port.lock().unwrap(), cmd, timeout);
+            }
+        }
+
+        let agent = ureq::AgentBuilder::new().timeout(timeout).build();
+        let url = format!("{}{}", base.trim_end_matches('/'), endpoint.path());
+        let mut request = match endpoint.body() {
+            Some(_) => agent.post(&url).set("Content-Type", "text/plain"),
+            None => agent.get(&url),
+        }
+        .set("Accept", "text/plain");
+        if let Some(auth) = auth_for(base) {
+            request = request.set("Authorization", &auth);
+        }
+        let result = match endpoint.body() {
+            Some(body) => request.send_string(&body),
+            None => request.call(),
+        };
+        match result {
+            Ok(resp) => resp.into_string().map_err(|e| NetError::Network(e.to_string())),
+            Err(ureq::Error::Status(status, resp)) => Err(NetError::Http(status, resp.into_string().unwrap_or_default())),
+            Err(ureq::Error::Transport(t)) => {
+                let timed_out = std::error::Error::source(&t)
+                    .and_then(|s| s.downcast_ref::<std::io::Error>())
+                    .is_some_and(|e| matches!(e.kind(), std::io::ErrorKind::TimedOut | std::io::ErrorKind::WouldBlock));
+                Err(if timed_out { NetError::Timeout } else { NetError::Network(t.to_string()) })
+            }
+        }
+    }
+
+    /// Write `cmd` and collect reply lines up to the firmware's answer.
+    fn serial_command(port: &mut SerialPort, cmd: &str, timeout: Duration) -> Result<String, NetError> {
+        let result = exchange(port, cmd, timeout);
+        if result == Err(NetError::Timeout) {
+            // its answer may still come; it must not end the next exchange
+            port.late += 1;
+        }
+        result
+    }
+
+    fn exchange(port: &mut SerialPort, cmd: &str, timeout: Duration) -> Result<String, NetError> {
+        let io = |e: std::io::Error| match e.kind() {
+            std::io::ErrorKind::TimedOut => NetError::Timeout,
+            _ => NetError::Network(e.to_string()),
+        };
+        port.reader.get_mut().write_all(format!("{}\n", cmd.trim()).as_bytes()).map_err(io)?;
+        let mut deadline = Instant::now() + timeout;
+        let mut reply = String::new();
+        loop {
+            let left = deadline.saturating_duration_since(Instant::now());
+            if left.is_zero() {
+                return Err(NetError::Timeout);
+            }
+            port.reader.get_mut().set_timeout(left).map_err(|e| NetError::Network(e.to_string()))?;
+            let mut line = String::new();
+            if port.reader.read_line(&mut line).map_err(io)? == 0 {
+                return Err(NetError::Network("serial link closed".into()));
+            }
+            if is_busy(&line) {
+                deadline = Instant::now() + timeout;
+            }
+            if let Some(done) = serial_reply(&mut reply, &line, &mut port.late) {
+                return done;
+            }
+        }
+    }
+
+    /// Machine panel row choosing between the HTTP controller and a serial port.
+    pub(crate) fn link_ui(ui: &mut egui::Ui) {
+        let mut serial = SERIAL.lock().unwrap();
+        ui.horizontal(|ui| {
+            ui.label("Link:");
+            let current = serial.as_ref().map_or_else(base_url, |(name, _, _)| name.clone());
+            let mut open: Option<Option<String>> = None;
+            egui::ComboBox::from_id_salt("link_select")
+                .selected_text(current)
+                .show_ui(ui, |ui| {
+                    if ui.selectable_label(serial.is_none(), base_url()).clicked() {
+                        open = Some(None);
+                    }
+                    for port in serialport::available_ports().unwrap_or_default() {
+                        let selected = serial.as_ref().is_some_and(|(name, _, _)| *name == port.port_name);
+                        if ui.selectable_label(selected, &port.port_name).clicked() {
+                            open = Some(Some(port.port_name));
+                        }
+                    }
+                });
+            let baud_id = egui::Id::new("link_baud");
+            let mut baud: u32 = ui.data_mut(|d| *d.get_persisted_mut_or(baud_id, 115_200));
+            egui::ComboBox::from_id_salt("link_baud_select")
+                .selected_text(baud.to_string())
+                .width(80.0)
+                .show_ui(ui, |ui| {
+                    for rate in [9_600, 57_600, 115_200, 250_000] {
+                        ui.selectable_value(&mut baud, rate, rate.to_string());
+                    }
+                });
+            ui.data_mut(|d| d.insert_persisted(baud_id, baud));
+
+            match open {
+                Some(None) => *serial = None,
+                Some(Some(name)) => match serialport::new(&name, baud).open().and_then(|port| Ok((port.try_clone()?, port))) {
+                    Ok((realtime, port)) => {
+                        log::info!("[net] serial link {name} @ {baud}");
+                        let port = SerialPort { reader: BufReader::new(port), late: 0 };
+                        *serial = Some((name, Arc::new(Mutex::new(port)), Arc::new(Mutex::new(realtime))));
+                    }
+                    Err(e) => {
+                        log::error!("[net] opening {name} failed: {e}");
+                        crate::toasts::error(format!("{name} could not be opened"), Some(e.to_string()));
+                    }
+                },
+                None => {}
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed(lines: &[&str], late: &mut usize) -> Vec<Result<String, NetError>> {
+        let mut reply = String::new();
+        lines.iter().filter_map(|line| serial_reply(&mut reply, line, late)).collect()
+    }
+
+    #[test]
+    fn serial_reply_keeps_status_report_until_ok() {
+        let mut late = 0;
+        let status = "<Idle|MPos:1.000,2.000,3.000|FS:0,0>";
+        assert_eq!(feed(&[status, "ok"], &mut late), [Ok(format!("{status}\nok"))]);
+    }
+
+    #[test]
+    fn serial_reply_acks_each_line_once() {
+        let mut late = 0;
+        let done = feed(&["<Idle|MPos:0.000,0.000,0.000>", "ok", "ok", "error:20"], &mut late);
+        assert_eq!(done, [Ok("<Idle|MPos:0.000,0.000,0.000>\nok".to_owned()), Ok("ok".to_owned()), Err(NetError::Rejected("error:20".to_owned()))]);
+    }
+
+    #[test]
+    fn serial_reply_status_report_does_not_settle_a_late_answer() {
+        let mut late = 1;
+        assert_eq!(feed(&["<Run|MPos:0.000,0.000,0.000>", "ok", "echo:G1", "ok"], &mut late), [Ok("echo:G1\nok".to_owned())]);
+        assert_eq!(late, 0);
+    }
+}
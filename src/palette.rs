@@ -0,0 +1,250 @@
+//! Command palette (Ctrl+K, ⌘K on macOS): every app action by name,
+//! narrowed by a fuzzy search as you type.
+
+use egui_node_graph2::{NodeTemplateIter, NodeTemplateTrait};
+
+use crate::{AluminaApp, Snap, Tab, Tool, design_graph, spawn_file_picker};
+
+/// Rows shown at once.
+const MAX_ROWS: usize = 12;
+
+#[derive(Clone, Copy)]
+enum Action {
+    Tab(Tab),
+    Snap(Snap),
+    Toggle(fn(&mut AluminaApp) -> &mut bool),
+    Tool(Tool),
+    AddModel,
+    UndoModel,
+    RedoModel,
+    ExportStl,
+    ExportSettings,
+    ImportSettings,
+    SaveProject,
+    OpenProject,
+    NewWorkspace,
+    SaveWorkspace,
+    OpenWorkspace,
+    SetupWizard,
+    AlignWorkpiece,
+    HomeAll,
+    ResetLayout,
+    AutoLayoutGraph,
+    AddNode(design_graph::Template),
+}
+
+struct Command {
+    label: String,
+    action: Action,
+}
+
+#[derive(Default)]
+pub(crate) struct Palette {
+    open: bool,
+    query: String,
+    selected: usize,
+}
+
+impl Palette {
+    pub(crate) fn toggle(&mut self) {
+        *self = Palette { open: !self.open, ..Palette::default() };
+    }
+}
+
+/// Score `label` against `query`: every query character must appear in
+/// order. Consecutive matches and matches at word starts score higher.
+fn fuzzy_score(query: &str, label: &str) -> Option<i32> {
+    if query.trim().is_empty() {
+        return Some(0);
+    }
+    let mut score = 0;
+    let mut chars = label.char_indices();
+    let mut last: Option<usize> = None;
+    for q in query.chars().filter(|c| !c.is_whitespace()).flat_map(char::to_lowercase) {
+        let (i, _) = chars.find(|(_, c)| c.to_lowercase().eq(std::iter::once(q)))?;
+        let word_start = i == 0 || label[..i].ends_with([' ', ':', '(', '/']);
+        score += 1 + if word_start { 8 } else { 0 } + if last.is_some_and(|l| l + 1 == i) { 5 } else { 0 };
+        last = Some(i);
+    }
+    // prefer shorter labels among equal matches
+    Some(score * 4 - i32::try_from(label.len()).unwrap_or(i32::MAX) / 8)
+}
+
+fn toggle(label: &str, on: bool) -> String {
+    format!("{label}: turn {}", if on { "off" } else { "on" })
+}
+
+impl AluminaApp {
+    fn commands(&mut self) -> Vec<Command> {
+        let mut out = Vec::new();
+        let mut push = |label: String, action| out.push(Command { label, action });
+        for (tab, name) in [(Tab::Design, "Design"), (Tab::Control, "Control"), (Tab::Diagnostics, "Diagnostics")] {
+            push(format!("Go to {name} tab"), Action::Tab(tab));
+        }
+        for snap in Snap::ALL {
+            push(format!("Snap view: {}", snap.name()), Action::Snap(snap));
+        }
+        let toggles: [(&str, fn(&mut AluminaApp) -> &mut bool); 8] = [
+            ("Quad view", |a| &mut a.quad_view),
+            ("Profiler", |a| &mut a.show_profiler),
+            ("Show edges", |a| &mut a.edges),
+            ("Show faces", |a| &mut a.faces),
+            ("Show normals", |a| &mut a.normals),
+            ("Show vertices", |a| &mut a.vertices),
+            ("Show work area", |a| &mut a.workarea),
+            ("Poll machine position", |a| &mut a.dro_poll),
+        ];
+        for (label, field) in toggles {
+            push(toggle(label, *field(self)), Action::Toggle(field));
+        }
+        for (tool, name) in [
+            (Tool::Laser, "Laser"),
+            (Tool::Plasma, "Plasma"),
+            (Tool::Extruder, "Extruder"),
+            (Tool::Endmill, "Endmill"),
+            (Tool::Drill, "Drill"),
+            (Tool::DlpLcd, "DLP / LCD"),
+        ] {
+            push(format!("Select tool: {name}"), Action::Tool(tool));
+        }
+        push("Add model…".into(), Action::AddModel);
+        if let Some(what) = self.model_history.undo_label() {
+            push(format!("Undo {what}"), Action::UndoModel);
+        }
+        if let Some(what) = self.model_history.redo_label() {
+            push(format!("Redo {what}"), Action::RedoModel);
+        }
+        push("Export selected model as STL".into(), Action::ExportStl);
+        push("Export settings".into(), Action::ExportSettings);
+        push("Import settings…".into(), Action::ImportSettings);
+        push("Save project".into(), Action::SaveProject);
+        push("Open project…".into(), Action::OpenProject);
+        push("New workspace".into(), Action::NewWorkspace);
+        push("Save workspace".into(), Action::SaveWorkspace);
+        push("Open workspace…".into(), Action::OpenWorkspace);
+        push("Connect machine: setup wizard".into(), Action::SetupWizard);
+        push("Align workpiece with the camera".into(), Action::AlignWorkpiece);
+        push("Home all axes".into(), Action::HomeAll);
+        push("Reset panel layout".into(), Action::ResetLayout);
+        push("Auto-layout design graph".into(), Action::AutoLayoutGraph);
+        let mut user = design_graph::UserState;
+        for template in design_graph::AllTemplates.all_kinds() {
+            push(format!("Add node: {}", template.node_finder_label(&mut user)), Action::AddNode(template));
+        }
+        out
+    }
+
+    fn run(&mut self, action: Action) {
+        match action {
+            Action::Tab(tab) => self.selected_tab = tab,
+            Action::Snap(snap) => self.rotation = snap.rotation(),
+            Action::Toggle(field) => {
+                let value = field(self);
+                *value = !*value;
+            }
+            Action::Tool(tool) => {
+                self.selected_tool = tool;
+                self.refresh_slice();
+            }
+            Action::AddModel => self.pick_model_file(),
+            Action::UndoModel => self.undo_model(),
+            Action::RedoModel => self.redo_model(),
+            Action::ExportStl => self.export_selected_stl(),
+            Action::ExportSettings => self.export_settings(),
+            Action::ImportSettings => {
+                spawn_file_picker(std::sync::Arc::clone(&self.settings_data), "Settings (json)", &["json"]);
+            }
+            Action::SaveProject => self.save_project(),
+            Action::OpenProject => {
+                spawn_file_picker(std::sync::Arc::clone(&self.project_data), "Alumina project (alumina)", &[crate::project::EXTENSION]);
+            }
+            Action::NewWorkspace => self.new_workspace(None),
+            Action::SaveWorkspace => self.save_workspace(),
+            Action::OpenWorkspace => self.pick_workspace_file(),
+            Action::SetupWizard => self.open_wizard(),
+            Action::AlignWorkpiece => self.alignment.open = true,
+            Action::HomeAll => self.start_homing(None),
+            Action::ResetLayout => self.reset_layout(),
+            Action::AutoLayoutGraph => {
+                self.selected_tab = Tab::Design;
+                design_graph::auto_layout(&mut self.design_state);
+            }
+            Action::AddNode(template) => {
+                self.selected_tab = Tab::Design;
+                let pos = (egui::vec2(300.0, 200.0) - self.design_state.pan_zoom.pan).to_pos2();
+                design_graph::add_node(&mut self.design_state, template, pos);
+            }
+        }
+    }
+
+    /// Open on Ctrl+K and show the palette while open.
+    pub(crate) fn command_palette(&mut self, ctx: &egui::Context) {
+        if ctx.input_mut(|i| i.consume_key(egui::Modifiers::COMMAND, egui::Key::K)) {
+            self.palette.toggle();
+        }
+        if !self.palette.open {
+            return;
+        }
+
+        let commands = self.commands();
+        let mut matches: Vec<(i32, usize)> = commands
+            .iter()
+            .enumerate()
+            .filter_map(|(i, c)| fuzzy_score(&self.palette.query, &c.label).map(|s| (s, i)))
+            .collect();
+        matches.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+
+        let (up, down, enter, escape) = ctx.input_mut(|i| {
+            (
+                i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowUp),
+                i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowDown),
+                i.consume_key(egui::Modifiers::NONE, egui::Key::Enter),
+                i.consume_key(egui::Modifiers::NONE, egui::Key::Escape),
+            )
+        });
+        let p = &mut self.palette;
+        if down {
+            p.selected = (p.selected + 1).min(matches.len().saturating_sub(1));
+        }
+        if up {
+            p.selected = p.selected.saturating_sub(1);
+        }
+        p.selected = p.selected.min(matches.len().saturating_sub(1));
+        let mut chosen = enter.then(|| matches.get(p.selected).map(|&(_, i)| i)).flatten();
+
+        egui::Window::new("Command palette")
+            .title_bar(false)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_TOP, egui::vec2(0.0, 60.0))
+            .fixed_size(egui::vec2(420.0, 0.0))
+            .show(ctx, |ui| {
+                let edit = ui.add(
+                    egui::TextEdit::singleline(&mut p.query)
+                        .hint_text("Type a command…")
+                        .desired_width(f32::INFINITY),
+                );
+                edit.request_focus();
+                if edit.changed() {
+                    p.selected = 0;
+                }
+                ui.separator();
+                if matches.is_empty() {
+                    ui.weak("No matching command");
+                }
+                let first = p.selected.saturating_sub(MAX_ROWS - 1);
+                for (row, &(_, i)) in matches.iter().enumerate().skip(first).take(MAX_ROWS) {
+                    if ui.selectable_label(row == p.selected, &commands[i].label).clicked() {
+                        chosen = Some(i);
+                    }
+                }
+            });
+
+        if escape || chosen.is_some() {
+            self.palette.open = false;
+        }
+        if let Some(i) = chosen {
+            self.run(commands[i].action);
+        }
+    }
+}
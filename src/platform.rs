@@ -0,0 +1,642 @@
+//! Browser and desktop implementations of the few services the app needs
+//! from its host: clocks, a task executor, key/value and blob storage, file
+//! dialogs, the window title, the address a design can be shared through,
+//! the webcam and a second window for projecting resin masks.
+//!
+//! Everything else is target-independent; code outside this module (and
+//! the transport half of [`crate::net`]) should not need `cfg(target_arch)`.
+
+use std::{
+    cell::Cell,
+    future::Future,
+    rc::Rc,
+    sync::{Arc, Mutex},
+};
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) use web::*;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) use native::*;
+
+/// Key/value store that survives restarts (`localStorage` in the browser,
+/// one file per key in the config directory on the desktop).
+pub(crate) struct Storage {
+    #[cfg(target_arch = "wasm32")]
+    inner: web_sys::Storage,
+    #[cfg(not(target_arch = "wasm32"))]
+    dir: std::path::PathBuf,
+}
+
+#[cfg(target_arch = "wasm32")]
+mod web {
+    use super::*;
+    use futures_channel::oneshot;
+    use js_sys::Uint8Array;
+    use std::cell::RefCell;
+    use wasm_bindgen::{JsCast, prelude::*};
+    use wasm_bindgen_futures::JsFuture;
+    use web_sys::{Event, HtmlInputElement, window};
+
+    /// Milliseconds since page load (`performance.now()`).
+    pub(crate) fn now_ms() -> f64 {
+        window().and_then(|w| w.performance()).map_or(0.0, |p| p.now())
+    }
+
+    /// Wall-clock time, milliseconds since the Unix epoch.
+    pub(crate) fn unix_ms() -> f64 {
+        js_sys::Date::now()
+    }
+
+    /// Run `f` on the browser's micro-task queue.
+    pub(crate) fn execute<F: Future<Output = ()> + 'static>(f: F) {
+        wasm_bindgen_futures::spawn_local(f);
+    }
+
+    pub(crate) fn storage() -> Option<Storage> {
+        let inner = window()?.local_storage().ok().flatten()?;
+        Some(Storage { inner })
+    }
+
+    impl Storage {
+        pub(crate) fn get_item(&self, key: &str) -> Option<String> {
+            self.inner.get_item(key).ok().flatten()
+        }
+
+        /// Fails when the quota is exceeded.
+        pub(crate) fn set_item(&self, key: &str, value: &str) -> Result<(), String> {
+            self.inner.set_item(key, value).map_err(|e| format!("{e:?}"))
+        }
+
+        pub(crate) fn remove_item(&self, key: &str) {
+            let _ = self.inner.remove_item(key);
+        }
+
+        pub(crate) fn keys(&self) -> Vec<String> {
+            let len = self.inner.length().unwrap_or(0);
+            (0..len).filter_map(|i| self.inner.key(i).ok().flatten()).collect()
+        }
+    }
+
+    /// Let the user pick a file; its bytes land in `target`.
+    pub(crate) fn spawn_file_picker(
+        target: Arc<Mutex<Option<Vec<u8>>>>,
+        _filter_name: &'static str,
+        exts: &[impl AsRef<str>],
+    ) {
+        // Accept filter (".stl,.dxf", etc.)
+        let accept = exts
+            .iter()
+            .map(|e| format!(".{}", e.as_ref()))
+            .collect::<Vec<_>>()
+            .join(",");
+
+        // 100 % non-blocking: the async task lives in the browser’s micro-task queue
+        execute(async move {
+            // ---1) build an <input type="file"> on the fly --------------------
+            let document = window()
+                .expect("no window")
+                .document()
+                .expect("no document");
+            let input: HtmlInputElement = document
+                .create_element("input")
+                .unwrap()
+                .dyn_into()
+                .unwrap();
+            input.set_type("file");
+            input.set_accept(&accept);
+
+            input.style().set_property("display", "none").unwrap(); // invisible
+            document.body().unwrap().append_child(&input).unwrap();
+
+            // ---2) turn the "change" event into a Future -----------------------
+            let (tx, rx) = oneshot::channel::<()>();
+
+            // Wrap the Sender so we can *move* it exactly once inside an FnMut closure
+            let tx_cell = Rc::new(RefCell::new(Some(tx)));
+            let tx_handle = Rc::clone(&tx_cell);
+
+            let closure = Closure::<dyn FnMut(Event)>::wrap(Box::new(move |_e| {
+                if let Some(sender) = tx_handle.borrow_mut().take() {
+                    let _ = sender.send(()); // 2nd call → already None → no-op
+                }
+            }));
+            input
+                .add_event_listener_with_callback("change", closure.as_ref().unchecked_ref())
+                .unwrap();
+            closure.forget(); // leak => stays alive for the element’s lifetime
+
+            input.click(); // **opens** the browser dialog
+            rx.await.ok(); // wait until the user picked a file
+
+            // ---3) extract bytes with File::arrayBuffer -----------------------
+            let files = input.files().unwrap();
+            if files.length() == 0 {
+                return;
+            }
+            let file = files.get(0).unwrap();
+
+            let buf_promise = file.array_buffer();
+            let js_buf = JsFuture::from(buf_promise).await.unwrap();
+            let u8_array = Uint8Array::new(&js_buf);
+            let mut bytes = vec![0u8; u8_array.length() as usize];
+            u8_array.copy_to(&mut bytes);
+
+            *target.lock().unwrap() = Some(bytes); // hand off to the egui thread
+        });
+    }
+
+    /// Hand `bytes` to the browser as a file download.
+    pub(crate) fn download_bytes(filename: &str, bytes: &[u8]) {
+        let parts = js_sys::Array::of1(&Uint8Array::from(bytes));
+        let Ok(blob) = web_sys::Blob::new_with_u8_array_sequence(&parts) else { return };
+        let Ok(url) = web_sys::Url::create_object_url_with_blob(&blob) else { return };
+        if let Some(a) = window()
+            .and_then(|w| w.document())
+            .and_then(|d| d.create_element("a").ok())
+            .and_then(|e| e.dyn_into::<web_sys::HtmlAnchorElement>().ok())
+        {
+            a.set_href(&url);
+            a.set_download(filename);
+            a.click();
+        }
+        web_sys::Url::revoke_object_url(&url).ok();
+    }
+
+    /// Have the browser confirm leaving the page while the returned flag is set.
+    pub(crate) fn install_unload_guard() -> Rc<Cell<bool>> {
+        let guard = Rc::new(Cell::new(false));
+        let flag = Rc::clone(&guard);
+        let handler = Closure::<dyn FnMut(web_sys::BeforeUnloadEvent)>::new(move |e: web_sys::BeforeUnloadEvent| {
+            if flag.get() {
+                e.prevent_default();
+                e.set_return_value("You have unsaved changes.");
+            }
+        });
+        if let Some(w) = window() {
+            w.add_event_listener_with_callback("beforeunload", handler.as_ref().unchecked_ref())
+                .ok();
+        }
+        handler.forget(); // lives as long as the page
+        guard
+    }
+
+    pub(crate) fn set_title(_ctx: &egui::Context, title: &str) {
+        if let Some(doc) = window().and_then(|w| w.document()) {
+            doc.set_title(title);
+        }
+    }
+
+    /// Fragment of the page address, including the leading `#`.
+    pub(crate) fn location_hash() -> Option<String> {
+        window()?.location().hash().ok()
+    }
+
+    /// Put `fragment` into the page address and return the full link.
+    pub(crate) fn share_url(fragment: &str) -> Option<String> {
+        let location = window()?.location();
+        location.set_hash(fragment).ok()?;
+        location.href().ok()
+    }
+
+    /* --------------------------------------------------------------------- */
+    /*  Camera (getUserMedia)                                                */
+    /* --------------------------------------------------------------------- */
+
+    /// Live webcam stream, grabbed frame by frame through an off-screen canvas.
+    pub(crate) struct Camera {
+        stream: web_sys::MediaStream,
+        video: web_sys::HtmlVideoElement,
+        canvas: web_sys::HtmlCanvasElement,
+    }
+
+    /// Ask for the webcam; the opened stream (or why not) lands in `target`.
+    pub(crate) fn open_camera(target: Rc<RefCell<Option<Result<Camera, String>>>>) {
+        execute(async move {
+            let result = async {
+                let window = window().ok_or("no window")?;
+                let devices = window.navigator().media_devices().map_err(js_err)?;
+                let constraints = web_sys::MediaStreamConstraints::new();
+                constraints.set_video(&JsValue::TRUE);
+                let promise = devices.get_user_media_with_constraints(&constraints).map_err(js_err)?;
+                let stream: web_sys::MediaStream =
+                    JsFuture::from(promise).await.map_err(js_err)?.dyn_into().map_err(js_err)?;
+
+                let document = window.document().ok_or("no document")?;
+                let video: web_sys::HtmlVideoElement =
+                    document.create_element("video").map_err(js_err)?.dyn_into().map_err(js_err)?;
+                video.set_muted(true);
+                video.set_attribute("playsinline", "").ok();
+                video.set_src_object(Some(&stream));
+                video.play().map_err(js_err)?;
+                let canvas: web_sys::HtmlCanvasElement =
+                    document.create_element("canvas").map_err(js_err)?.dyn_into().map_err(js_err)?;
+                Ok::<_, String>(Camera { stream, video, canvas })
+            }
+            .await;
+            *target.borrow_mut() = Some(result);
+        });
+    }
+
+    impl Camera {
+        /// The current frame, or `None` until the stream has started.
+        pub(crate) fn frame(&self) -> Option<egui::ColorImage> {
+            let (w, h) = (self.video.video_width(), self.video.video_height());
+            if w == 0 || h == 0 {
+                return None;
+            }
+            self.canvas.set_width(w);
+            self.canvas.set_height(h);
+            let ctx: web_sys::CanvasRenderingContext2d = self.canvas.get_context("2d").ok()??.dyn_into().ok()?;
+            ctx.draw_image_with_html_video_element(&self.video, 0.0, 0.0).ok()?;
+            let data = ctx.get_image_data(0.0, 0.0, f64::from(w), f64::from(h)).ok()?;
+            Some(egui::ColorImage::from_rgba_unmultiplied([w as usize, h as usize], &data.data().0))
+        }
+    }
+
+    impl Drop for Camera {
+        fn drop(&mut self) {
+            for track in self.stream.get_tracks().iter() {
+                if let Ok(track) = track.dyn_into::<web_sys::MediaStreamTrack>() {
+                    track.stop();
+                }
+            }
+        }
+    }
+
+    /* --------------------------------------------------------------------- */
+    /*  Projector window (a popup drawn through a canvas)                    */
+    /* --------------------------------------------------------------------- */
+
+    /// Popup window showing one image scaled to fill it, for a projector or
+    /// LCD set up as a second screen. Clicking it goes full screen.
+    pub(crate) struct ProjectorWindow {
+        window: web_sys::Window,
+        canvas: web_sys::HtmlCanvasElement,
+    }
+
+    pub(crate) fn open_projector() -> Result<ProjectorWindow, String> {
+        let popup = window()
+            .ok_or("no window")?
+            .open_with_url_and_target_and_features("", "alumina-projector", "popup,width=800,height=600")
+            .map_err(js_err)?
+            .ok_or("the browser blocked the popup")?;
+        let document = popup.document().ok_or("no document")?;
+        document.set_title("Alumina projector");
+        let body = document.body().ok_or("no body")?;
+        body.set_attribute("style", "margin:0;background:#000;overflow:hidden").map_err(js_err)?;
+        let canvas: web_sys::HtmlCanvasElement = document.create_element("canvas").map_err(js_err)?.dyn_into().map_err(js_err)?;
+        canvas
+            .set_attribute("style", "display:block;width:100vw;height:100vh;object-fit:contain;image-rendering:pixelated")
+            .map_err(js_err)?;
+        let target = canvas.clone();
+        let fullscreen = Closure::<dyn FnMut()>::new(move || {
+            target.request_fullscreen().ok();
+        });
+        canvas.add_event_listener_with_callback("click", fullscreen.as_ref().unchecked_ref()).map_err(js_err)?;
+        fullscreen.forget(); // lives as long as the popup
+        body.append_child(&canvas).map_err(js_err)?;
+        Ok(ProjectorWindow { window: popup, canvas })
+    }
+
+    impl ProjectorWindow {
+        pub(crate) fn is_open(&self) -> bool {
+            !self.window.closed().unwrap_or(true)
+        }
+
+        pub(crate) fn show(&mut self, image: &egui::ColorImage) {
+            let [w, h] = image.size.map(|v| v as u32);
+            self.canvas.set_width(w);
+            self.canvas.set_height(h);
+            let Some(ctx) = self
+                .canvas
+                .get_context("2d")
+                .ok()
+                .flatten()
+                .and_then(|c| c.dyn_into::<web_sys::CanvasRenderingContext2d>().ok())
+            else {
+                return;
+            };
+            let rgba: Vec<u8> = image.pixels.iter().flat_map(|c| c.to_array()).collect();
+            if let Ok(data) = web_sys::ImageData::new_with_u8_clamped_array_and_sh(wasm_bindgen::Clamped(&rgba), w, h) {
+                ctx.put_image_data(&data, 0.0, 0.0).ok();
+            }
+        }
+
+        /// Nothing to do each frame: the popup keeps what it was shown.
+        pub(crate) fn present(&mut self, _ctx: &egui::Context) {}
+
+        pub(crate) fn close(&mut self) {
+            self.window.close().ok();
+        }
+    }
+
+    /* --------------------------------------------------------------------- */
+    /*  Blob store (IndexedDB)                                               */
+    /* --------------------------------------------------------------------- */
+
+    const IDB_NAME: &str = "alumina";
+    const IDB_STORE: &str = "blobs";
+
+    fn js_err(e: JsValue) -> String {
+        e.as_string().unwrap_or_else(|| format!("{e:?}"))
+    }
+
+    /// Resolve once `req` succeeds; its `result` is then ready.
+    async fn settle(req: &web_sys::IdbRequest) -> Result<JsValue, String> {
+        let promise = js_sys::Promise::new(&mut |resolve, reject| {
+            req.set_onsuccess(Some(&resolve));
+            req.set_onerror(Some(&reject));
+        });
+        JsFuture::from(promise).await.map_err(|_| "IndexedDB request failed".to_owned())?;
+        req.result().map_err(js_err)
+    }
+
+    async fn open_db() -> Result<web_sys::IdbDatabase, String> {
+        let factory = window()
+            .and_then(|w| w.indexed_db().ok().flatten())
+            .ok_or("IndexedDB is not available")?;
+        let req = factory.open_with_u32(IDB_NAME, 1).map_err(js_err)?;
+        let upgrade = {
+            let req = req.clone();
+            Closure::once_into_js(move || {
+                if let Some(db) = req.result().ok().and_then(|r| r.dyn_into::<web_sys::IdbDatabase>().ok()) {
+                    db.create_object_store(IDB_STORE).ok();
+                }
+            })
+        };
+        req.set_onupgradeneeded(Some(upgrade.unchecked_ref()));
+        settle(&req).await?.dyn_into().map_err(js_err)
+    }
+
+    async fn store(mode: web_sys::IdbTransactionMode) -> Result<web_sys::IdbObjectStore, String> {
+        let db = open_db().await?;
+        db.transaction_with_str_and_mode(IDB_STORE, mode)
+            .and_then(|tx| tx.object_store(IDB_STORE))
+            .map_err(js_err)
+    }
+
+    /// Store `bytes` under `key`; unlike `localStorage` there is room for meshes.
+    pub(crate) async fn blob_put(key: &str, bytes: Vec<u8>) -> Result<(), String> {
+        let store = store(web_sys::IdbTransactionMode::Readwrite).await?;
+        let req = store
+            .put_with_key(&Uint8Array::from(bytes.as_slice()), &JsValue::from_str(key))
+            .map_err(js_err)?;
+        settle(&req).await.map(|_| ())
+    }
+
+    pub(crate) async fn blob_get(key: &str) -> Result<Option<Vec<u8>>, String> {
+        let store = store(web_sys::IdbTransactionMode::Readonly).await?;
+        let req = store.get(&JsValue::from_str(key)).map_err(js_err)?;
+        let value = settle(&req).await?;
+        Ok(value.dyn_into::<Uint8Array>().ok().map(|a| a.to_vec()))
+    }
+
+    pub(crate) async fn blob_delete(key: &str) -> Result<(), String> {
+        let store = store(web_sys::IdbTransactionMode::Readwrite).await?;
+        let req = store.delete(&JsValue::from_str(key)).map_err(js_err)?;
+        settle(&req).await.map(|_| ())
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod native {
+    use super::*;
+    use std::{path::PathBuf, sync::OnceLock, time::Instant};
+
+    /// Milliseconds since start-up.
+    pub(crate) fn now_ms() -> f64 {
+        static START: OnceLock<Instant> = OnceLock::new();
+        START.get_or_init(Instant::now).elapsed().as_secs_f64() * 1000.0
+    }
+
+    /// Wall-clock time, milliseconds since the Unix epoch.
+    pub(crate) fn unix_ms() -> f64 {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0.0, |d| d.as_secs_f64() * 1000.0)
+    }
+
+    /// Run `f` to completion on its own thread. Tasks are few and mostly
+    /// wait on I/O, so a thread each is simpler than an async runtime.
+    pub(crate) fn execute<F: Future<Output = ()> + Send + 'static>(f: F) {
+        std::thread::spawn(move || pollster::block_on(f));
+    }
+
+    fn config_dir() -> Option<PathBuf> {
+        Some(dirs::config_dir()?.join("alumina"))
+    }
+
+    pub(crate) fn storage() -> Option<Storage> {
+        let dir = config_dir()?;
+        std::fs::create_dir_all(&dir).ok()?;
+        Some(Storage { dir })
+    }
+
+    impl Storage {
+        /// Keys are file names, with anything but `[A-Za-z0-9.-]` %-escaped.
+        fn path(&self, key: &str) -> PathBuf {
+            let mut name = String::with_capacity(key.len());
+            for b in key.bytes() {
+                if b.is_ascii_alphanumeric() || b == b'.' || b == b'-' {
+                    name.push(char::from(b));
+                } else {
+                    name.push_str(&format!("%{b:02X}"));
+                }
+            }
+            self.dir.join(name)
+        }
+
+        fn key(name: &str) -> Option<String> {
+            let mut bytes = Vec::with_capacity(name.len());
+            let mut rest = name.as_bytes();
+            while let Some((&b, tail)) = rest.split_first() {
+                if b == b'%' {
+                    let hex = std::str::from_utf8(tail.get(..2)?).ok()?;
+                    bytes.push(u8::from_str_radix(hex, 16).ok()?);
+                    rest = &tail[2..];
+                } else {
+                    bytes.push(b);
+                    rest = tail;
+                }
+            }
+            String::from_utf8(bytes).ok()
+        }
+
+        pub(crate) fn get_item(&self, key: &str) -> Option<String> {
+            std::fs::read_to_string(self.path(key)).ok()
+        }
+
+        pub(crate) fn set_item(&self, key: &str, value: &str) -> Result<(), String> {
+            std::fs::write(self.path(key), value).map_err(|e| e.to_string())
+        }
+
+        pub(crate) fn remove_item(&self, key: &str) {
+            let _ = std::fs::remove_file(self.path(key));
+        }
+
+        pub(crate) fn keys(&self) -> Vec<String> {
+            let Ok(entries) = std::fs::read_dir(&self.dir) else { return Vec::new() };
+            entries
+                .filter_map(|e| Self::key(e.ok()?.file_name().to_str()?))
+                .collect()
+        }
+    }
+
+    /// Let the user pick a file; its bytes land in `target`.
+    pub(crate) fn spawn_file_picker(
+        target: Arc<Mutex<Option<Vec<u8>>>>,
+        filter_name: &'static str,
+        exts: &[impl AsRef<str>],
+    ) {
+        let exts: Vec<String> = exts.iter().map(|e| e.as_ref().to_owned()).collect();
+        execute(async move {
+            let Some(file) = rfd::AsyncFileDialog::new()
+                .add_filter(filter_name, &exts)
+                .pick_file()
+                .await
+            else {
+                return;
+            };
+            *target.lock().unwrap() = Some(file.read().await);
+        });
+    }
+
+    /// Ask where to save `bytes` and write them there.
+    pub(crate) fn download_bytes(filename: &str, bytes: &[u8]) {
+        let filename = filename.to_owned();
+        let bytes = bytes.to_vec();
+        execute(async move {
+            let Some(file) = rfd::AsyncFileDialog::new().set_file_name(&filename).save_file().await else {
+                return;
+            };
+            if let Err(e) = file.write(&bytes).await {
+                log::error!("[alumina] writing {filename} failed: {e}");
+                crate::toasts::error(format!("{filename} could not be saved"), Some(e.to_string()));
+            }
+        });
+    }
+
+    /// The flag is checked when the window is asked to close (see `AluminaApp::update`).
+    pub(crate) fn install_unload_guard() -> Rc<Cell<bool>> {
+        Rc::new(Cell::new(false))
+    }
+
+    pub(crate) fn set_title(ctx: &egui::Context, title: &str) {
+        ctx.send_viewport_cmd(egui::ViewportCommand::Title(title.to_owned()));
+    }
+
+    /// Fragment of a share link passed on the command line, if any.
+    pub(crate) fn location_hash() -> Option<String> {
+        std::env::args().skip(1).find_map(|a| a.find('#').map(|i| a[i..].to_owned()))
+    }
+
+    /// Link to the web app served by the controller, opening `fragment`.
+    pub(crate) fn share_url(fragment: &str) -> Option<String> {
+        Some(format!("{}/{fragment}", crate::net::FirmwareClient::current().host_url().trim_end_matches('/')))
+    }
+
+    /// Webcam capture is browser-only for now.
+    pub(crate) struct Camera;
+
+    pub(crate) fn open_camera(target: Rc<std::cell::RefCell<Option<Result<Camera, String>>>>) {
+        *target.borrow_mut() = Some(Err("Camera capture is only available in the browser build".to_owned()));
+    }
+
+    impl Camera {
+        pub(crate) fn frame(&self) -> Option<egui::ColorImage> {
+            None
+        }
+    }
+
+    /* --------------------------------------------------------------------- */
+    /*  Projector window (a second viewport)                                 */
+    /* --------------------------------------------------------------------- */
+
+    /// Second window showing one image scaled to fill it, for a projector or
+    /// LCD set up as a second screen. F11 toggles full screen.
+    pub(crate) struct ProjectorWindow {
+        image: Option<egui::ColorImage>,
+        texture: Option<egui::TextureHandle>,
+        open: bool,
+    }
+
+    #[allow(clippy::unnecessary_wraps)] // same signature as the browser window
+    pub(crate) fn open_projector() -> Result<ProjectorWindow, String> {
+        Ok(ProjectorWindow { image: None, texture: None, open: true })
+    }
+
+    impl ProjectorWindow {
+        pub(crate) fn is_open(&self) -> bool {
+            self.open
+        }
+
+        pub(crate) fn show(&mut self, image: &egui::ColorImage) {
+            self.image = Some(image.clone());
+        }
+
+        /// Draw the window; it is an immediate viewport, so this runs every frame.
+        pub(crate) fn present(&mut self, ctx: &egui::Context) {
+            if !self.open {
+                return;
+            }
+            if let Some(image) = self.image.take() {
+                match &mut self.texture {
+                    Some(t) => t.set(image, egui::TextureOptions::NEAREST),
+                    None => self.texture = Some(ctx.load_texture("projector_mask", image, egui::TextureOptions::NEAREST)),
+                }
+            }
+            let texture = self.texture.clone();
+            let builder = egui::ViewportBuilder::default().with_title("Alumina projector").with_inner_size([800.0, 600.0]);
+            let mut open = true;
+            ctx.show_viewport_immediate(egui::ViewportId::from_hash_of("alumina_projector"), builder, |ctx, _| {
+                egui::CentralPanel::default().frame(egui::Frame::none().fill(egui::Color32::BLACK)).show(ctx, |ui| {
+                    if let Some(t) = &texture {
+                        ui.centered_and_justified(|ui| ui.add(egui::Image::new(t).fit_to_exact_size(ui.available_size())));
+                    }
+                });
+                if ctx.input(|i| i.key_pressed(egui::Key::F11)) {
+                    let full = ctx.input(|i| i.viewport().fullscreen.unwrap_or(false));
+                    ctx.send_viewport_cmd(egui::ViewportCommand::Fullscreen(!full));
+                }
+                open = !ctx.input(|i| i.viewport().close_requested());
+            });
+            self.open = open;
+        }
+
+        pub(crate) fn close(&mut self) {
+            self.open = false;
+        }
+    }
+
+    /* --------------------------------------------------------------------- */
+    /*  Blob store (files next to the key/value store)                       */
+    /* --------------------------------------------------------------------- */
+
+    fn blob_path(key: &str) -> Result<PathBuf, String> {
+        let store = storage().ok_or("no config directory")?;
+        Ok(store.path(&format!("blob.{key}")))
+    }
+
+    #[allow(clippy::unused_async)] // same signatures as the browser store
+    pub(crate) async fn blob_put(key: &str, bytes: Vec<u8>) -> Result<(), String> {
+        std::fs::write(blob_path(key)?, bytes).map_err(|e| e.to_string())
+    }
+
+    #[allow(clippy::unused_async)]
+    pub(crate) async fn blob_get(key: &str) -> Result<Option<Vec<u8>>, String> {
+        match std::fs::read(blob_path(key)?) {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    #[allow(clippy::unused_async)]
+    pub(crate) async fn blob_delete(key: &str) -> Result<(), String> {
+        match std::fs::remove_file(blob_path(key)?) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.to_string()),
+            _ => Ok(()),
+        }
+    }
+}
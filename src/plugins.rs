@@ -0,0 +1,687 @@
+//! Runtime plugins loaded from user-supplied WebAssembly modules.
+//!
+//! A plugin is a plain WASM module (no wasm-bindgen glue) exporting
+//!
+//! * `memory`
+//! * `alumina_alloc(len: i32) -> i32` – buffer the host copies arguments into
+//! * `alumina_manifest() -> i32` – JSON [`Manifest`] naming what it provides
+//! * `alumina_call(name_ptr, name_len, in_ptr, in_len: i32) -> i32` – run the
+//!   entry point `name` on the input bytes
+//!
+//! Results are a pointer to a little-endian `u32` length followed by that many
+//! bytes. A null pointer means failure, described by the optional
+//! `alumina_error() -> i32` in the same format. The host imports
+//! `env.alumina_log(ptr, len: i32)` for messages.
+//!
+//! Entry points by kind:
+//!
+//! * graph node – `{"inputs": {name: value}}` in, the output value out (JSON;
+//!   meshes are `{"polygons": [[[x,y,z], …], …]}`, sketches are
+//!   `{"polygons": [{"exterior": [[x,y], …], "holes": [[[x,y], …]]}]}`)
+//! * importer – file bytes in, mesh JSON out
+//! * exporter – mesh JSON in, file bytes out
+//! * post-processor – G-code text in, G-code text out
+//!
+//! Modules are kept in `localStorage` (base64) with their enabled flag.
+//! They are instantiated through the browser's `WebAssembly` API, so the
+//! desktop build lists no plugins and refuses to load any.
+
+use std::cell::RefCell;
+#[cfg(target_arch = "wasm32")]
+use std::rc::Rc;
+
+use base64::Engine;
+use csgrs::{
+    mesh::{Mesh, polygon::Polygon as Facet, vertex::Vertex},
+    sketch::Sketch,
+};
+use geo::{Geometry, GeometryCollection, LineString, MultiPolygon, Polygon};
+#[cfg(target_arch = "wasm32")]
+use js_sys::{Array, Function, Object, Reflect, Uint8Array, WebAssembly};
+use nalgebra::{Point3, Vector2, Vector3};
+use serde::{Deserialize, Serialize};
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::{JsCast, prelude::*};
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen_futures::JsFuture;
+
+use crate::design_graph::{DType, DValue};
+
+const STORAGE_KEY: &str = "alumina.plugins";
+
+/* ------------------------------------------------------------------------- */
+/*  Manifest                                                                 */
+/* ------------------------------------------------------------------------- */
+
+/// What a plugin provides, as returned by `alumina_manifest`.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct Manifest {
+    pub name: String,
+    #[serde(default)]
+    pub version: String,
+    #[serde(default)]
+    pub nodes: Vec<NodeSpec>,
+    #[serde(default)]
+    pub importers: Vec<Importer>,
+    #[serde(default)]
+    pub exporters: Vec<Exporter>,
+    #[serde(default)]
+    pub post_processors: Vec<PostProcessor>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PortType {
+    Mesh,
+    Sketch,
+    Scalar,
+    Vec2,
+    Vec3,
+    Text,
+}
+
+impl PortType {
+    pub fn dtype(self) -> DType {
+        match self {
+            PortType::Mesh => DType::Mesh,
+            PortType::Sketch => DType::Sketch,
+            PortType::Scalar => DType::Scalar,
+            PortType::Vec2 => DType::Vec2,
+            PortType::Vec3 => DType::Vec3,
+            PortType::Text => DType::Text,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct PortSpec {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ty: PortType,
+    /// Constant used until something is connected (scalar, `[x,y]`, `[x,y,z]` or text).
+    #[serde(default)]
+    pub default: serde_json::Value,
+}
+
+/// A graph node template.
+#[derive(Clone, Debug, Deserialize)]
+pub struct NodeSpec {
+    pub entry: String,
+    pub label: String,
+    #[serde(default)]
+    pub inputs: Vec<PortSpec>,
+    pub output: PortType,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Importer {
+    pub entry: String,
+    /// File extensions without the dot.
+    pub extensions: Vec<String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Exporter {
+    pub entry: String,
+    pub label: String,
+    pub extension: String,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct PostProcessor {
+    pub entry: String,
+    pub label: String,
+}
+
+/* ------------------------------------------------------------------------- */
+/*  Instances                                                                */
+/* ------------------------------------------------------------------------- */
+
+#[cfg(target_arch = "wasm32")]
+fn js_err(e: JsValue) -> anyhow::Error {
+    anyhow::anyhow!("{}", e.as_string().unwrap_or_else(|| format!("{e:?}")))
+}
+
+/// An instantiated module and its linear memory.
+#[cfg(target_arch = "wasm32")]
+struct Instance {
+    exports: Object,
+    memory: WebAssembly::Memory,
+}
+
+#[cfg(target_arch = "wasm32")]
+impl Instance {
+    async fn new(name: &str, wasm: &[u8]) -> anyhow::Result<Self> {
+        // the log import needs the memory, which only exists once instantiated
+        let memory_cell: Rc<RefCell<Option<WebAssembly::Memory>>> = Rc::default();
+        let log = {
+            let memory_cell = Rc::clone(&memory_cell);
+            let name = name.to_owned();
+            Closure::<dyn Fn(u32, u32)>::new(move |ptr: u32, len: u32| {
+                if let Some(memory) = memory_cell.borrow().as_ref() {
+                    let bytes = Uint8Array::new(&memory.buffer()).subarray(ptr, ptr + len).to_vec();
+                    log::info!("[plugin {name}] {}", String::from_utf8_lossy(&bytes));
+                }
+            })
+        };
+        let env = Object::new();
+        Reflect::set(&env, &"alumina_log".into(), log.as_ref()).map_err(js_err)?;
+        log.forget(); // lives as long as the instance
+        let imports = Object::new();
+        Reflect::set(&imports, &"env".into(), &env).map_err(js_err)?;
+
+        let result = JsFuture::from(WebAssembly::instantiate_buffer(wasm, &imports))
+            .await
+            .map_err(js_err)?;
+        let instance: WebAssembly::Instance = Reflect::get(&result, &"instance".into())
+            .map_err(js_err)?
+            .dyn_into()
+            .map_err(js_err)?;
+        let exports = instance.exports();
+        let memory: WebAssembly::Memory = Reflect::get(&exports, &"memory".into())
+            .map_err(js_err)?
+            .dyn_into()
+            .map_err(|_| anyhow::anyhow!("module does not export its memory"))?;
+        *memory_cell.borrow_mut() = Some(memory.clone());
+        Ok(Self { exports, memory })
+    }
+
+    fn func(&self, name: &str) -> anyhow::Result<Function> {
+        Reflect::get(&self.exports, &name.into())
+            .map_err(js_err)?
+            .dyn_into()
+            .map_err(|_| anyhow::anyhow!("module does not export `{name}`"))
+    }
+
+    /// Copy `bytes` into plugin memory; returns the pointer.
+    fn write(&self, bytes: &[u8]) -> anyhow::Result<u32> {
+        let len = u32::try_from(bytes.len())?;
+        let ptr = self.func("alumina_alloc")?.call1(&JsValue::NULL, &len.into()).map_err(js_err)?;
+        let ptr = ptr.as_f64().ok_or_else(|| anyhow::anyhow!("alumina_alloc returned no pointer"))? as u32;
+        // fetch the view after alloc: growing memory detaches old buffers
+        Uint8Array::new(&self.memory.buffer()).set(&Uint8Array::from(bytes), ptr);
+        Ok(ptr)
+    }
+
+    /// Read a length-prefixed result.
+    fn read(&self, ret: &JsValue) -> Option<Vec<u8>> {
+        let ptr = ret.as_f64()? as u32;
+        if ptr == 0 {
+            return None;
+        }
+        let view = Uint8Array::new(&self.memory.buffer());
+        let mut len = [0u8; 4];
+        view.subarray(ptr, ptr + 4).copy_to(&mut len);
+        let start = ptr + 4;
+        Some(view.subarray(start, start + u32::from_le_bytes(len)).to_vec())
+    }
+
+    fn fail(&self, what: &str) -> anyhow::Error {
+        let detail = self
+            .func("alumina_error")
+            .ok()
+            .and_then(|f| f.call0(&JsValue::NULL).ok())
+            .and_then(|r| self.read(&r));
+        match detail {
+            Some(msg) => anyhow::anyhow!("{what}: {}", String::from_utf8_lossy(&msg)),
+            None => anyhow::anyhow!("{what} failed"),
+        }
+    }
+
+    fn manifest(&self) -> anyhow::Result<Manifest> {
+        let ret = self.func("alumina_manifest")?.call0(&JsValue::NULL).map_err(js_err)?;
+        let bytes = self.read(&ret).ok_or_else(|| self.fail("alumina_manifest"))?;
+        Ok(serde_json::from_slice(&bytes)?)
+    }
+
+    fn call(&self, entry: &str, input: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let name_ptr = self.write(entry.as_bytes())?;
+        let in_ptr = self.write(input)?;
+        let args = Array::of4(
+            &name_ptr.into(),
+            &(entry.len() as u32).into(),
+            &in_ptr.into(),
+            &(input.len() as u32).into(),
+        );
+        let ret = self.func("alumina_call")?.apply(&JsValue::NULL, &args).map_err(js_err)?;
+        self.read(&ret).ok_or_else(|| self.fail(entry))
+    }
+}
+
+/// There is no WebAssembly host on the desktop, so no instances either.
+#[cfg(not(target_arch = "wasm32"))]
+enum Instance {}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Instance {
+    fn call(&self, _entry: &str, _input: &[u8]) -> anyhow::Result<Vec<u8>> {
+        match *self {}
+    }
+}
+
+/* ------------------------------------------------------------------------- */
+/*  Registry                                                                 */
+/* ------------------------------------------------------------------------- */
+
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))] // only built by `instantiate`
+pub struct Plugin {
+    pub manifest: Manifest,
+    pub enabled: bool,
+    wasm: Vec<u8>,
+    instance: Option<Instance>,
+    /// Why the module could not be instantiated.
+    pub error: Option<String>,
+}
+
+/// Loaded plugins plus the graph node templates they registered. Node
+/// templates are referred to by slot so `Template` stays `Copy`; slots are
+/// never reused, and a plugin replaced by a newer build keeps its slots.
+#[derive(Default)]
+pub struct Registry {
+    pub plugins: Vec<Plugin>,
+    nodes: Vec<(String, NodeSpec)>,
+}
+
+thread_local! {
+    static REGISTRY: RefCell<Registry> = RefCell::default();
+}
+
+fn with<R>(f: impl FnOnce(&mut Registry) -> R) -> R {
+    REGISTRY.with(|r| f(&mut r.borrow_mut()))
+}
+
+impl Registry {
+    #[cfg(target_arch = "wasm32")]
+    fn add(&mut self, plugin: Plugin) {
+        let name = plugin.manifest.name.clone();
+        for spec in &plugin.manifest.nodes {
+            match self.nodes.iter_mut().find(|(p, s)| *p == name && s.entry == spec.entry) {
+                Some(slot) => slot.1 = spec.clone(),
+                None => self.nodes.push((name.clone(), spec.clone())),
+            }
+        }
+        match self.plugins.iter_mut().find(|p| p.manifest.name == name) {
+            Some(existing) => *existing = plugin,
+            None => self.plugins.push(plugin),
+        }
+    }
+
+    fn enabled(&self) -> impl Iterator<Item = (&Plugin, &Instance)> {
+        self.plugins
+            .iter()
+            .filter(|p| p.enabled)
+            .filter_map(|p| p.instance.as_ref().map(|i| (p, i)))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Stored {
+    name: String,
+    enabled: bool,
+    wasm: String,
+}
+
+fn persist() {
+    let Some(storage) = crate::platform::storage() else { return };
+    let stored: Vec<Stored> = with(|r| {
+        r.plugins
+            .iter()
+            .map(|p| Stored {
+                name: p.manifest.name.clone(),
+                enabled: p.enabled,
+                wasm: base64::engine::general_purpose::STANDARD.encode(&p.wasm),
+            })
+            .collect()
+    });
+    if let Ok(json) = serde_json::to_string(&stored) {
+        if storage.set_item(STORAGE_KEY, &json).is_err() {
+            log::error!("[alumina] plugins too large to persist");
+            crate::toasts::warn("Plugins could not be saved for the next session", Some("browser storage is full".into()));
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+async fn instantiate(name: String, wasm: Vec<u8>, enabled: bool) -> anyhow::Result<String> {
+    let loaded = async {
+        let instance = Instance::new(&name, &wasm).await?;
+        let manifest = instance.manifest()?;
+        anyhow::Ok((instance, manifest))
+    }
+    .await;
+    let (plugin, result) = match loaded {
+        Ok((instance, manifest)) => {
+            let name = manifest.name.clone();
+            (Plugin { manifest, enabled, wasm, instance: Some(instance), error: None }, Ok(name))
+        }
+        Err(e) => {
+            let manifest = Manifest { name, ..Manifest::default() };
+            let plugin = Plugin { manifest, enabled: false, wasm, instance: None, error: Some(e.to_string()) };
+            (plugin, Err(e))
+        }
+    };
+    with(|r| r.add(plugin));
+    result
+}
+
+/// Instantiate a user-picked module and remember it.
+#[cfg(target_arch = "wasm32")]
+pub fn load(wasm: Vec<u8>) {
+    crate::execute(async move {
+        match instantiate("(unnamed)".into(), wasm, true).await {
+            Ok(name) => {
+                log::info!("[alumina] plugin `{name}` loaded");
+                persist();
+            }
+            Err(e) => {
+                log::error!("[alumina] plugin rejected: {e}");
+                crate::toasts::error("Plugin could not be loaded", Some(e.to_string()));
+            }
+        }
+    });
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load(_wasm: Vec<u8>) {
+    crate::toasts::error("Plugins are only available in the web build", None);
+}
+
+/// Re-instantiate the modules saved in `localStorage`.
+#[cfg(target_arch = "wasm32")]
+pub fn restore() {
+    let Some(json) = crate::platform::storage().and_then(|s| s.get_item(STORAGE_KEY)) else {
+        return;
+    };
+    let stored: Vec<Stored> = serde_json::from_str(&json).unwrap_or_default();
+    for s in stored {
+        let Ok(wasm) = base64::engine::general_purpose::STANDARD.decode(&s.wasm) else { continue };
+        crate::execute(async move {
+            if let Err(e) = instantiate(s.name.clone(), wasm, s.enabled).await {
+                log::error!("[alumina] plugin `{}` failed to load: {e}", s.name);
+                crate::toasts::error(format!("Plugin `{}` could not be loaded", s.name), Some(e.to_string()));
+            }
+        });
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn restore() {}
+
+/* ------------------------------------------------------------------------- */
+/*  Extension points                                                         */
+/* ------------------------------------------------------------------------- */
+
+/// Node template slots of enabled plugins.
+pub fn node_slots() -> Vec<u32> {
+    with(|r| {
+        (0..r.nodes.len())
+            .filter(|&i| r.enabled().any(|(p, _)| p.manifest.name == r.nodes[i].0))
+            .filter_map(|i| u32::try_from(i).ok())
+            .collect()
+    })
+}
+
+/// Stable name of a node slot: (plugin, entry point).
+pub fn slot_key(slot: u32) -> Option<(String, String)> {
+    with(|r| r.nodes.get(slot as usize).map(|(p, s)| (p.clone(), s.entry.clone())))
+}
+
+/// Slot of a node registered by `plugin` under `entry`.
+pub fn slot_for(plugin: &str, entry: &str) -> Option<u32> {
+    with(|r| {
+        let i = r.nodes.iter().position(|(p, s)| p == plugin && s.entry == entry)?;
+        u32::try_from(i).ok()
+    })
+}
+
+pub fn node_spec(slot: u32) -> Option<NodeSpec> {
+    with(|r| r.nodes.get(slot as usize).map(|(_, s)| s.clone()))
+}
+
+/// Evaluate a plugin node on its (already evaluated) inputs.
+pub fn eval_node(slot: u32, inputs: &[(String, DValue)]) -> anyhow::Result<DValue> {
+    with(|r| {
+        let (plugin, spec) = r.nodes.get(slot as usize).ok_or_else(|| anyhow::anyhow!("unknown plugin node"))?;
+        let (_, instance) = r
+            .enabled()
+            .find(|(p, _)| p.manifest.name == *plugin)
+            .ok_or_else(|| anyhow::anyhow!("plugin `{plugin}` is not loaded or disabled"))?;
+        let inputs: serde_json::Map<String, serde_json::Value> =
+            inputs.iter().map(|(name, v)| (name.clone(), value_to_json(v))).collect();
+        let out = instance.call(&spec.entry, serde_json::json!({ "inputs": inputs }).to_string().as_bytes())?;
+        value_from_json(spec.output, serde_json::from_slice(&out)?)
+    })
+}
+
+/// `base` plus every extension an enabled importer accepts.
+pub fn import_extensions(base: &[&str]) -> Vec<String> {
+    let mut exts: Vec<String> = base.iter().map(|&e| e.to_owned()).collect();
+    with(|r| {
+        for (p, _) in r.enabled() {
+            exts.extend(p.manifest.importers.iter().flat_map(|i| i.extensions.iter().cloned()));
+        }
+    });
+    exts.sort();
+    exts.dedup();
+    exts
+}
+
+/// Try every enabled importer on `bytes`; the first that succeeds wins.
+pub fn import_mesh(bytes: &[u8]) -> Option<Mesh<()>> {
+    with(|r| {
+        for (p, instance) in r.enabled() {
+            for imp in &p.manifest.importers {
+                match instance
+                    .call(&imp.entry, bytes)
+                    .and_then(|out| Ok(serde_json::from_slice::<MeshJson>(&out)?))
+                {
+                    Ok(mesh) => return Some(mesh.into_mesh()),
+                    Err(e) => log::debug!("[plugin {}] {}: {e}", p.manifest.name, imp.entry),
+                }
+            }
+        }
+        None
+    })
+}
+
+/// Run `gcode` through every enabled post-processor in load order.
+pub fn post_process(gcode: String) -> anyhow::Result<String> {
+    with(|r| {
+        let mut text = gcode;
+        for (p, instance) in r.enabled() {
+            for pp in &p.manifest.post_processors {
+                let out = instance.call(&pp.entry, text.as_bytes())?;
+                text = String::from_utf8(out)
+                    .map_err(|_| anyhow::anyhow!("{} returned invalid UTF-8", pp.label))?;
+            }
+        }
+        Ok(text)
+    })
+}
+
+fn export(plugin: &str, entry: &str, mesh: &Mesh<()>) -> anyhow::Result<Vec<u8>> {
+    with(|r| {
+        let (_, instance) = r
+            .enabled()
+            .find(|(p, _)| p.manifest.name == plugin)
+            .ok_or_else(|| anyhow::anyhow!("plugin `{plugin}` is disabled"))?;
+        instance.call(entry, serde_json::to_string(&MeshJson::from_mesh(mesh))?.as_bytes())
+    })
+}
+
+/* ------------------------------------------------------------------------- */
+/*  Value encoding                                                           */
+/* ------------------------------------------------------------------------- */
+
+#[derive(Serialize, Deserialize)]
+struct MeshJson {
+    polygons: Vec<Vec<[f64; 3]>>,
+}
+
+impl MeshJson {
+    fn from_mesh(mesh: &Mesh<()>) -> Self {
+        let polygons = mesh
+            .polygons
+            .iter()
+            .map(|p| p.vertices.iter().map(|v| [v.pos.x, v.pos.y, v.pos.z]).collect())
+            .collect();
+        Self { polygons }
+    }
+
+    fn into_mesh(self) -> Mesh<()> {
+        let facets: Vec<Facet<()>> = self
+            .polygons
+            .into_iter()
+            .filter(|p| p.len() >= 3)
+            .map(|p| {
+                let pts: Vec<Point3<f64>> = p.iter().map(|&[x, y, z]| Point3::new(x, y, z)).collect();
+                let n = (pts[1] - pts[0])
+                    .cross(&(pts[2] - pts[0]))
+                    .try_normalize(1e-12)
+                    .unwrap_or_else(Vector3::z);
+                Facet::new(pts.into_iter().map(|p| Vertex::new(p, n)).collect(), None)
+            })
+            .collect();
+        Mesh::from_polygons(&facets, None)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct RegionJson {
+    exterior: Vec<[f64; 2]>,
+    #[serde(default)]
+    holes: Vec<Vec<[f64; 2]>>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SketchJson {
+    polygons: Vec<RegionJson>,
+}
+
+fn ring(ls: &LineString<f64>) -> Vec<[f64; 2]> {
+    ls.0.iter().map(|c| [c.x, c.y]).collect()
+}
+
+impl SketchJson {
+    fn from_sketch(sketch: &Sketch<()>) -> Self {
+        let region = |p: &Polygon<f64>| RegionJson {
+            exterior: ring(p.exterior()),
+            holes: p.interiors().iter().map(ring).collect(),
+        };
+        let mut polygons = Vec::new();
+        for g in &sketch.geometry.0 {
+            match g {
+                Geometry::Polygon(p) => polygons.push(region(p)),
+                Geometry::MultiPolygon(mp) => polygons.extend(mp.0.iter().map(region)),
+                _ => {}
+            }
+        }
+        Self { polygons }
+    }
+
+    fn into_sketch(self) -> Sketch<()> {
+        let line = |r: Vec<[f64; 2]>| LineString::from(r.into_iter().map(|[x, y]| (x, y)).collect::<Vec<_>>());
+        let polys: Vec<Polygon<f64>> = self
+            .polygons
+            .into_iter()
+            .map(|r| Polygon::new(line(r.exterior), r.holes.into_iter().map(line).collect()))
+            .collect();
+        Sketch::from_geo(GeometryCollection(vec![Geometry::MultiPolygon(MultiPolygon(polys))]), None)
+    }
+}
+
+fn value_to_json(v: &DValue) -> serde_json::Value {
+    match v {
+        DValue::Scalar(x) => serde_json::json!(x),
+        DValue::Vec2(v) => serde_json::json!([v.x, v.y]),
+        DValue::Vec3(v) => serde_json::json!([v.x, v.y, v.z]),
+        DValue::Text(s) => serde_json::json!(s),
+        DValue::Mesh(m) => serde_json::to_value(MeshJson::from_mesh(m)).unwrap_or_default(),
+        DValue::Sketch(s) => serde_json::to_value(SketchJson::from_sketch(s)).unwrap_or_default(),
+        // column-major, as nalgebra stores it
+        DValue::Transform(m) => serde_json::json!(m.as_slice()),
+    }
+}
+
+fn value_from_json(ty: PortType, v: serde_json::Value) -> anyhow::Result<DValue> {
+    Ok(match ty {
+        PortType::Scalar => DValue::Scalar(serde_json::from_value(v)?),
+        PortType::Vec2 => {
+            let [x, y]: [f64; 2] = serde_json::from_value(v)?;
+            DValue::Vec2(Vector2::new(x, y))
+        }
+        PortType::Vec3 => {
+            let [x, y, z]: [f64; 3] = serde_json::from_value(v)?;
+            DValue::Vec3(Vector3::new(x, y, z))
+        }
+        PortType::Text => DValue::Text(serde_json::from_value(v)?),
+        PortType::Mesh => DValue::Mesh(serde_json::from_value::<MeshJson>(v)?.into_mesh()),
+        PortType::Sketch => DValue::Sketch(serde_json::from_value::<SketchJson>(v)?.into_sketch()),
+    })
+}
+
+/* ------------------------------------------------------------------------- */
+/*  Manager panel                                                            */
+/* ------------------------------------------------------------------------- */
+
+/// Plugin list with enable / remove, and exporters for `selected`. Returns
+/// `true` when the user asked to load another module.
+pub fn manager_ui(ui: &mut egui::Ui, selected: Option<&Mesh<()>>) -> bool {
+    let mut changed = false;
+    let mut remove = None;
+    let mut export_with: Option<(String, Exporter)> = None;
+    with(|r| {
+        if r.plugins.is_empty() {
+            ui.weak("No plugins loaded.");
+        }
+        for (i, p) in r.plugins.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                changed |= ui.add_enabled(p.instance.is_some(), egui::Checkbox::new(&mut p.enabled, "")).changed();
+                ui.strong(p.manifest.name.as_str());
+                ui.weak(p.manifest.version.as_str());
+                if ui.small_button("✖").on_hover_text("Remove plugin").clicked() {
+                    remove = Some(i);
+                }
+            });
+            if let Some(e) = &p.error {
+                ui.colored_label(egui::Color32::LIGHT_RED, e.as_str());
+                continue;
+            }
+            let m = &p.manifest;
+            ui.small(format!(
+                "{} nodes · {} importers · {} exporters · {} post-processors",
+                m.nodes.len(),
+                m.importers.len(),
+                m.exporters.len(),
+                m.post_processors.len()
+            ));
+            if p.enabled {
+                for ex in &m.exporters {
+                    let button = egui::Button::new(format!("Export selected model: {}", ex.label));
+                    if ui.add_enabled(selected.is_some(), button).clicked() {
+                        export_with = Some((m.name.clone(), ex.clone()));
+                    }
+                }
+            }
+            ui.separator();
+        }
+        if let Some(i) = remove {
+            let name = r.plugins.remove(i).manifest.name;
+            log::info!("[alumina] plugin `{name}` removed");
+            changed = true;
+        }
+    });
+    if changed {
+        persist();
+    }
+    if let (Some((plugin, ex)), Some(mesh)) = (export_with, selected) {
+        match export(&plugin, &ex.entry, mesh) {
+            Ok(bytes) => crate::download_bytes(&format!("model.{}", ex.extension), &bytes),
+            Err(e) => {
+                log::error!("[plugin {plugin}] export failed: {e}");
+                crate::toasts::error("Export failed", Some(e.to_string()));
+            }
+        }
+    }
+    ui.button("Load plugin (.wasm)…").clicked()
+}
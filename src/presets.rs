@@ -0,0 +1,328 @@
+//! Named presets of a tool's parameters ("3mm plywood laser", "6061
+//! roughing endmill", …), picked from a dropdown above the tool settings.
+//!
+//! A preset holds the parameters of one tool plus the layer settings, which
+//! usually go with the material. Presets are persisted as JSON
+//! (`localStorage`, or the config directory on the desktop) and travel with
+//! the settings export.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{AluminaApp, Tool, cam, collision, gcode, milling, platform::storage, resin, slicer, support, toasts};
+
+const LS_KEY: &str = "alumina.presets";
+
+/// Parameters of one tool, as edited in the Tool panel.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) enum ToolParams {
+    Laser {
+        kerf: f32,
+        common_line: bool,
+        optimize_order: bool,
+        #[serde(default)]
+        laser: gcode::LaserSettings,
+    },
+    Plasma {
+        touch_off: bool,
+        common_line: bool,
+        optimize_order: bool,
+        #[serde(default)]
+        plasma: gcode::PlasmaSettings,
+    },
+    Extruder {
+        perimeters: i32,
+        infill_type: slicer::InfillType,
+        line_width: f64,
+        infill: slicer::InfillSettings,
+        seam: slicer::SeamSettings,
+        adhesion: slicer::AdhesionSettings,
+        supports: support::SupportSettings,
+        #[serde(default)]
+        retraction: slicer::RetractionSettings,
+        #[serde(default)]
+        fan: slicer::FanSettings,
+        #[serde(default)]
+        extruder: gcode::ExtruderSettings,
+    },
+    Endmill {
+        width: f32,
+        length: f32,
+        ops: Vec<milling::Operation>,
+        #[serde(default)]
+        cam: cam::CamSettings,
+        #[serde(default)]
+        mill: gcode::MillSettings,
+        #[serde(default)]
+        holder: collision::Holder,
+    },
+    Drill {
+        optimize_order: bool,
+        width: f32,
+        length: f32,
+        #[serde(default)]
+        cycle: gcode::DrillSettings,
+    },
+    DlpLcd {
+        pixels_wide: i32,
+        pixels_tall: i32,
+        layer_delay: f32,
+        peel_distance: f32,
+        supports: support::SupportSettings,
+        #[serde(default)]
+        resin: resin::ResinSettings,
+    },
+}
+
+impl ToolParams {
+    fn tool(&self) -> Tool {
+        match self {
+            ToolParams::Laser { .. } => Tool::Laser,
+            ToolParams::Plasma { .. } => Tool::Plasma,
+            ToolParams::Extruder { .. } => Tool::Extruder,
+            ToolParams::Endmill { .. } => Tool::Endmill,
+            ToolParams::Drill { .. } => Tool::Drill,
+            ToolParams::DlpLcd { .. } => Tool::DlpLcd,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct Preset {
+    pub(crate) name: String,
+    layers: slicer::LayerSettings,
+    params: ToolParams,
+}
+
+#[derive(Default)]
+pub(crate) struct Presets {
+    list: Vec<Preset>,
+    /// Name typed for the next save.
+    new_name: String,
+}
+
+impl Presets {
+    pub(crate) fn load() -> Self {
+        let list = storage()
+            .and_then(|s| s.get_item(LS_KEY))
+            .and_then(|json| match serde_json::from_str(&json) {
+                Ok(list) => Some(list),
+                Err(e) => {
+                    log::error!("stored tool presets are invalid: {e}");
+                    toasts::warn("Saved tool presets were invalid and were not loaded", Some(e.to_string()));
+                    None
+                }
+            })
+            .unwrap_or_default();
+        Self { list, new_name: String::new() }
+    }
+
+    fn save(&self) {
+        let Some(store) = storage() else { return };
+        match serde_json::to_string(&self.list) {
+            Ok(json) => {
+                if let Err(e) = store.set_item(LS_KEY, &json) {
+                    log::error!("saving tool presets failed: {e}");
+                    toasts::error("Tool presets could not be saved", None);
+                }
+            }
+            Err(e) => log::error!("serialising tool presets failed: {e}"),
+        }
+    }
+
+    pub(crate) fn all(&self) -> &[Preset] {
+        &self.list
+    }
+
+    /// Replace every preset (settings import) and persist the result.
+    pub(crate) fn replace(&mut self, list: Vec<Preset>) {
+        self.list = list;
+        self.save();
+    }
+
+    /// Add `preset`, replacing one of the same tool and name.
+    fn upsert(&mut self, preset: Preset) {
+        let tool = preset.params.tool();
+        match self.list.iter_mut().find(|p| p.params.tool() == tool && p.name == preset.name) {
+            Some(p) => *p = preset,
+            None => self.list.push(preset),
+        }
+        self.save();
+    }
+}
+
+impl AluminaApp {
+    fn tool_params(&self) -> ToolParams {
+        match self.selected_tool {
+            Tool::Laser => ToolParams::Laser {
+                kerf: self.kerf,
+                common_line: self.common_line,
+                optimize_order: self.optimize_order,
+                laser: self.laser.clone(),
+            },
+            Tool::Plasma => ToolParams::Plasma {
+                touch_off: self.touch_off,
+                common_line: self.common_line,
+                optimize_order: self.optimize_order,
+                plasma: self.plasma.clone(),
+            },
+            Tool::Extruder => ToolParams::Extruder {
+                perimeters: self.perimeters,
+                infill_type: self.infill_type,
+                line_width: self.line_width,
+                infill: self.infill_settings.clone(),
+                seam: self.seam.clone(),
+                adhesion: self.adhesion.clone(),
+                supports: self.support_settings.clone(),
+                retraction: self.retraction.clone(),
+                fan: self.fan.clone(),
+                extruder: self.extruder.clone(),
+            },
+            Tool::Endmill => ToolParams::Endmill {
+                width: self.endmill_width,
+                length: self.endmill_length,
+                ops: self.mill_ops.clone(),
+                cam: self.cam.clone(),
+                mill: self.mill.clone(),
+                holder: self.holder.clone(),
+            },
+            Tool::Drill => ToolParams::Drill {
+                optimize_order: self.optimize_order,
+                width: self.drill_width,
+                length: self.drill_length,
+                cycle: self.drill.clone(),
+            },
+            Tool::DlpLcd => ToolParams::DlpLcd {
+                pixels_wide: self.pixels_wide,
+                pixels_tall: self.pixels_tall,
+                layer_delay: self.layer_delay,
+                peel_distance: self.peel_distance,
+                supports: self.support_settings.clone(),
+                resin: self.resin.clone(),
+            },
+        }
+    }
+
+    fn current_preset(&self, name: String) -> Preset {
+        Preset { name, layers: self.layer_settings.clone(), params: self.tool_params() }
+    }
+
+    /// Switch to the preset's tool and load its parameters.
+    fn apply_preset(&mut self, preset: Preset) {
+        self.selected_tool = preset.params.tool();
+        self.layer_settings = preset.layers;
+        match preset.params {
+            ToolParams::Laser { kerf, common_line, optimize_order, laser } => {
+                self.kerf = kerf;
+                self.laser = laser;
+                self.common_line = common_line;
+                self.optimize_order = optimize_order;
+            }
+            ToolParams::Plasma { touch_off, common_line, optimize_order, plasma } => {
+                self.touch_off = touch_off;
+                self.plasma = plasma;
+                self.common_line = common_line;
+                self.optimize_order = optimize_order;
+            }
+            ToolParams::Extruder { perimeters, infill_type, line_width, infill, seam, adhesion, supports, retraction, fan, extruder } => {
+                self.perimeters = perimeters;
+                self.infill_type = infill_type;
+                self.line_width = line_width;
+                self.infill_settings = infill;
+                self.seam = seam;
+                self.adhesion = adhesion;
+                self.support_settings = supports;
+                self.retraction = retraction;
+                self.fan = fan;
+                self.extruder = extruder;
+            }
+            ToolParams::Endmill { width, length, ops, cam, mill, holder } => {
+                self.endmill_width = width;
+                self.endmill_length = length;
+                self.mill_ops = ops;
+                self.cam = cam;
+                self.mill = mill;
+                self.holder = holder;
+                self.mill_results = None;
+            }
+            ToolParams::Drill { optimize_order, width, length, cycle } => {
+                self.optimize_order = optimize_order;
+                self.drill_width = width;
+                self.drill_length = length;
+                self.drill = cycle;
+            }
+            ToolParams::DlpLcd { pixels_wide, pixels_tall, layer_delay, peel_distance, supports, resin } => {
+                self.pixels_wide = pixels_wide;
+                self.pixels_tall = pixels_tall;
+                self.layer_delay = layer_delay;
+                self.peel_distance = peel_distance;
+                self.support_settings = supports;
+                self.resin = resin;
+            }
+        }
+        self.invalidate_layers();
+        self.refresh_slice();
+    }
+
+    /// Preset dropdown plus save / delete for the selected tool.
+    pub(crate) fn presets_ui(&mut self, ui: &mut egui::Ui) {
+        let current = self.current_preset(String::new());
+        let tool = self.selected_tool;
+        // the preset whose values are loaded right now, if any
+        let active = self
+            .presets
+            .list
+            .iter()
+            .find(|p| p.params == current.params && p.layers == current.layers)
+            .map(|p| p.name.clone());
+
+        let mut load = None;
+        ui.horizontal(|ui| {
+            let label = ui.label("Preset:");
+            egui::ComboBox::from_id_salt("tool_preset")
+                .selected_text(active.as_deref().unwrap_or("(custom)"))
+                .show_ui(ui, |ui| {
+                    let mut any = false;
+                    for p in self.presets.list.iter().filter(|p| p.params.tool() == tool) {
+                        any = true;
+                        if ui.selectable_label(active.as_ref() == Some(&p.name), &p.name).clicked() {
+                            load = Some(p.clone());
+                        }
+                    }
+                    if !any {
+                        ui.weak("No presets for this tool yet");
+                    }
+                })
+                .response
+                .labelled_by(label.id);
+            if let Some(name) = &active {
+                if ui.small_button("🗑").on_hover_text(format!("Delete preset “{name}”")).clicked() {
+                    self.presets.list.retain(|p| !(p.params.tool() == tool && &p.name == name));
+                    self.presets.save();
+                }
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::TextEdit::singleline(&mut self.presets.new_name)
+                    .hint_text("e.g. 3mm plywood")
+                    .desired_width(120.0),
+            );
+            let name = self.presets.new_name.trim().to_owned();
+            let exists = self.presets.list.iter().any(|p| p.params.tool() == tool && p.name == name);
+            let button = egui::Button::new(if exists { "Overwrite" } else { "Save preset" });
+            if ui
+                .add_enabled(!name.is_empty(), button)
+                .on_hover_text("Save the current tool and layer settings under this name")
+                .clicked()
+            {
+                self.presets.upsert(Preset { name: name.clone(), ..current });
+                self.presets.new_name.clear();
+                toasts::info(format!("Preset “{name}” saved"));
+            }
+        });
+
+        if let Some(preset) = load {
+            self.apply_preset(preset);
+        }
+    }
+}
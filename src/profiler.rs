@@ -0,0 +1,83 @@
+//! Optional viewport overlay with frame timings and geometry sizes, to tell
+//! a slow GPU upload from a heavy model or an expensive graph.
+
+use std::collections::VecDeque;
+
+/// Frames averaged for the frame time readout.
+const WINDOW: usize = 60;
+
+#[derive(Default)]
+pub struct Profile {
+    last_frame_ms: Option<f64>,
+    frame_ms: VecDeque<f64>,
+    /// Duration of the last `sync_buffers` call.
+    pub sync_ms: f64,
+    /// Vertices uploaded to the line and face buffers.
+    pub line_vertices: usize,
+    pub face_vertices: usize,
+    /// Duration of the last design graph evaluation.
+    pub eval_ms: Option<f64>,
+}
+
+impl Profile {
+    /// Record the start of a frame.
+    pub fn frame(&mut self, now_ms: f64) {
+        if let Some(last) = self.last_frame_ms.replace(now_ms) {
+            if self.frame_ms.len() == WINDOW {
+                self.frame_ms.pop_front();
+            }
+            self.frame_ms.push_back(now_ms - last);
+        }
+    }
+
+    /// Mean and worst frame time over the last [`WINDOW`] frames.
+    fn frame_stats(&self) -> Option<(f64, f64)> {
+        let n = self.frame_ms.len();
+        (n > 0).then(|| {
+            let mean = self.frame_ms.iter().sum::<f64>() / n as f64;
+            (mean, self.frame_ms.iter().copied().fold(0.0, f64::max))
+        })
+    }
+
+    /// Draw the overlay in the top-left corner of `rect`. `models` are
+    /// (name, triangle count) pairs.
+    pub fn show(&self, ui: &egui::Ui, rect: egui::Rect, models: &[(&str, usize)]) {
+        egui::Area::new(egui::Id::new("profiler_overlay"))
+            .fixed_pos(rect.min + egui::vec2(8.0, 8.0))
+            .order(egui::Order::Foreground)
+            .interactable(false)
+            .show(ui.ctx(), |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    egui::Grid::new("profiler_grid").num_columns(2).show(ui, |ui| {
+                        let mut row = |label: &str, value: String| {
+                            ui.label(label);
+                            ui.monospace(value);
+                            ui.end_row();
+                        };
+                        match self.frame_stats() {
+                            Some((mean, worst)) => row(
+                                "frame",
+                                format!("{mean:.1} ms ({:.0} fps), worst {worst:.1} ms", 1000.0 / mean.max(0.001)),
+                            ),
+                            None => row("frame", "–".into()),
+                        }
+                        row("sync_buffers", format!("{:.1} ms", self.sync_ms));
+                        row("line buffer", format!("{} vertices", self.line_vertices));
+                        row("face buffer", format!("{} vertices", self.face_vertices));
+                        row(
+                            "graph eval",
+                            self.eval_ms.map_or_else(|| "–".into(), |ms| format!("{ms:.1} ms")),
+                        );
+                        for (name, tris) in models {
+                            row(name, format!("{tris} triangles"));
+                        }
+                    });
+                });
+            });
+    }
+}
+
+/// Triangles a mesh renders as (its polygons fan-triangulated).
+pub fn triangle_count(mesh: &csgrs::mesh::Mesh<()>) -> usize {
+    mesh.polygons.iter().map(|p| p.vertices.len().saturating_sub(2)).sum()
+}
@@ -0,0 +1,172 @@
+//! `.alumina` project files: everything needed to pick a job up again
+//! exactly where it was left.
+//!
+//! A project is a ZIP (see [`crate::archive`]) holding
+//!
+//! - `project.json`: the format version, workspace name, tool, work area
+//!   and tool settings, and per model its name, scale, offset and slicing
+//!   overrides;
+//! - `models/<n>.stl`: each model's base geometry as binary STL;
+//! - `design.graph`: the design graph, in the `.graph` file form.
+//!
+//! The machine profile and view preferences belong to the computer rather
+//! than the job and stay out; [`crate::settings_file`] carries those.
+//! Projects written by a newer version are refused.
+
+use csgrs::mesh::Mesh;
+use nalgebra::Vector3;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    AluminaApp, ModelEntry, SettingsSnapshot, Tool,
+    archive::{Zip, unzip},
+    design_graph, download_bytes, engine, slicer, toasts,
+};
+
+const VERSION: u32 = 1;
+pub(crate) const EXTENSION: &str = "alumina";
+const MANIFEST: &str = "project.json";
+const GRAPH: &str = "design.graph";
+
+#[derive(Serialize, Deserialize)]
+struct ProjectModel {
+    name: String,
+    /// Entry holding the base geometry.
+    file: String,
+    scale: [f32; 3],
+    offset: [f32; 3],
+    #[serde(default)]
+    overrides: slicer::SliceOverrides,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Manifest {
+    version: u32,
+    #[serde(default)]
+    name: String,
+    tool: Tool,
+    /// Work area X/Y/Z (mm).
+    work_size: [f32; 3],
+    #[serde(default)]
+    common_line: bool,
+    #[serde(default)]
+    optimize_order: bool,
+    models: Vec<ProjectModel>,
+    settings: SettingsSnapshot,
+}
+
+impl AluminaApp {
+    fn project_file(&self) -> anyhow::Result<Vec<u8>> {
+        let mut zip = Zip::default();
+        let mut models = Vec::with_capacity(self.models.len());
+        for (i, m) in self.models.iter().enumerate() {
+            let file = format!("models/{i}.stl");
+            zip.add(&file, &m.base.to_stl_binary(&m.name)?);
+            models.push(ProjectModel {
+                name: m.name.clone(),
+                file,
+                scale: m.scale.into(),
+                offset: m.offset.into(),
+                overrides: m.overrides.clone(),
+            });
+        }
+        let graph = design_graph::SavedGraph::from_state(&self.design_state)?;
+        zip.add(GRAPH, engine::graph_json(&graph)?.as_bytes());
+        let manifest = Manifest {
+            version: VERSION,
+            name: self.workspaces.active_name().to_owned(),
+            tool: self.selected_tool,
+            work_size: self.work_size.into(),
+            common_line: self.common_line,
+            optimize_order: self.optimize_order,
+            models,
+            settings: self.settings_snapshot(),
+        };
+        zip.add(MANIFEST, &serde_json::to_vec_pretty(&manifest)?);
+        Ok(zip.finish())
+    }
+
+    /// Download the active workspace with the tool settings as a project.
+    pub(crate) fn save_project(&mut self) {
+        match self.project_file() {
+            Ok(bytes) => {
+                download_bytes(&format!("{}.{EXTENSION}", self.workspaces.active_name()), &bytes);
+                self.dirty.models = false;
+                self.dirty.graph_saved = None;
+                self.dirty.settings_saved = None;
+            }
+            Err(e) => toasts::error("The project could not be saved", Some(e.to_string())),
+        }
+    }
+
+    /// Open a project file in a new workspace tab, taking over its tool,
+    /// work area and settings.
+    pub(crate) fn open_project(&mut self, bytes: &[u8]) {
+        let read = unzip(bytes).and_then(|entries| {
+            let manifest = entries.iter().find(|(name, _)| name == MANIFEST).ok_or_else(|| anyhow::anyhow!("no {MANIFEST} in the archive"))?;
+            let version = serde_json::from_slice::<serde_json::Value>(&manifest.1)?.get("version").and_then(serde_json::Value::as_u64);
+            if let Some(v) = version.filter(|&v| v > u64::from(VERSION)) {
+                anyhow::bail!("written by a newer version (format {v}, this app reads up to {VERSION})");
+            }
+            let manifest = serde_json::from_slice::<Manifest>(&manifest.1)?;
+            Ok((manifest, entries))
+        });
+        let (manifest, entries) = match read {
+            Ok(read) => read,
+            Err(e) => {
+                log::error!("[project] open failed: {e}");
+                toasts::error("Not a valid project file", Some(e.to_string()));
+                return;
+            }
+        };
+        let entry = |name: &str| entries.iter().find(|(n, _)| n == name).map(|(_, data)| data.as_slice());
+
+        let mut failed = 0;
+        let mut models = Vec::with_capacity(manifest.models.len());
+        for saved in manifest.models {
+            let Some(mesh) = entry(&saved.file).and_then(|stl| Mesh::<()>::from_stl(stl, None).ok()) else {
+                failed += 1;
+                continue;
+            };
+            let mut m = ModelEntry::new(saved.name, mesh);
+            m.scale = Vector3::from(saved.scale);
+            m.offset = Vector3::from(saved.offset);
+            m.overrides = saved.overrides;
+            m.refresh();
+            models.push(m);
+        }
+        let graph = match entry(GRAPH).map(engine::parse_graph) {
+            Some(Ok(graph)) => Some(graph),
+            Some(Err(e)) => {
+                log::error!("[project] unreadable graph: {e}");
+                failed += 1;
+                None
+            }
+            None => None,
+        };
+
+        self.new_workspace((!manifest.name.is_empty()).then_some(manifest.name));
+        self.selected_model = (!models.is_empty()).then_some(0);
+        self.models = models;
+        self.model_history.clear();
+        if let Some(graph) = graph {
+            let (state, missing) = graph.into_state();
+            failed += missing;
+            self.design_state = state;
+        }
+        self.selected_tool = manifest.tool;
+        self.work_size = manifest.work_size.into();
+        self.common_line = manifest.common_line;
+        self.optimize_order = manifest.optimize_order;
+        self.apply_settings(manifest.settings);
+        self.dirty.models = false;
+        self.dirty.graph_saved = None;
+        self.dirty.settings_saved = None;
+
+        if failed > 0 {
+            toasts::warn("Part of the project could not be opened", Some(format!("{failed} model(s), graph or plugin node(s) missing")));
+        } else {
+            toasts::info("Project opened");
+        }
+    }
+}
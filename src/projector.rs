@@ -0,0 +1,427 @@
+//! Direct projection for DLP / LCD machines driven by the UI itself.
+//!
+//! The projector (or the printer's LCD, set up as a second screen) shows a
+//! window of ours with the mask of one layer: white where resin cures,
+//! black elsewhere, the build area stretched over the configured pixels.
+//! A run steps through the layers. For each one it blanks the window, sends
+//! the plate's lift and return to the controller, waits out the move and
+//! the layer delay, then shows the mask for the layer's exposure. Ranges
+//! that pause hold the run until it is resumed.
+//!
+//! Hollowed parts keep their walls lit; inside the cavity only the pixels
+//! on the lattice are.
+//!
+//! The plate must be homed before a run starts; the run only moves Z.
+//!
+//! The same masks can be exported instead, for printers that take their
+//! jobs from a USB stick: as a ZIP of one PNG per layer with a manifest and
+//! the job's `run.gcode`, or in one of the [`msla`] formats. Export draws a
+//! few layers per frame.
+
+use csgrs::sketch::Sketch;
+use egui::{Color32, ColorImage};
+
+use crate::{
+    AluminaApp,
+    archive::{Zip, png_grey},
+    download_bytes, engine, msla,
+    platform::{ProjectorWindow, now_ms, open_projector},
+    resin::HollowSettings,
+    send_queue_command, slicer, toasts,
+};
+
+/// Where a run is within its current layer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Phase {
+    /// Plate moving to the layer and resting; the window is dark.
+    Moving,
+    /// Mask on screen.
+    Exposing,
+    /// Waiting to be resumed before the layer.
+    Paused,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Run {
+    layer: usize,
+    phase: Phase,
+    /// When the phase ends (`now_ms`).
+    until: f64,
+}
+
+/// Mask export in progress: layers before `next` are in `zip` (or, for the
+/// ChiTuBox formats, encoded in `images`) already.
+struct MaskExport {
+    format: msla::Format,
+    next: usize,
+    zip: Zip,
+    images: Vec<Vec<u8>>,
+    /// The layer with the most lit pixels, for previews, and that count.
+    preview: (usize, Vec<u8>),
+}
+
+/// Time spent drawing export masks per frame (ms).
+const EXPORT_BUDGET_MS: f64 = 30.0;
+
+#[derive(Default)]
+pub(crate) struct Projector {
+    window: Option<ProjectorWindow>,
+    run: Option<Run>,
+    export: Option<MaskExport>,
+    format: msla::Format,
+}
+
+/// Mask of `slice` over a `size` pixel image of the build area `area`
+/// (mm, centred on the origin, +Y at the top row): pixels whose centre is
+/// inside the slice (even-odd, so holes stay dark) are white.
+pub(crate) fn mask(slice: &Sketch<()>, size: [usize; 2], area: [f64; 2]) -> ColorImage {
+    let [w, h] = size;
+    let mut image = ColorImage::new(size, Color32::BLACK);
+    let (sx, sy) = (w as f64 / area[0].max(1e-6), h as f64 / area[1].max(1e-6));
+    let rings: Vec<Vec<[f64; 2]>> = slicer::polygons(slice)
+        .iter()
+        .flat_map(|p| std::iter::once(p.exterior()).chain(p.interiors()))
+        .map(|r| r.0.iter().map(|c| [(c.x + area[0] * 0.5) * sx, (area[1] * 0.5 - c.y) * sy]).collect())
+        .collect();
+    for row in 0..h {
+        let y = row as f64 + 0.5;
+        let mut xs: Vec<f64> = rings
+            .iter()
+            .flat_map(|r| r.windows(2))
+            .filter(|e| (e[0][1] <= y) != (e[1][1] <= y))
+            .map(|e| e[0][0] + (y - e[0][1]) * (e[1][0] - e[0][0]) / (e[1][1] - e[0][1]))
+            .collect();
+        xs.sort_by(f64::total_cmp);
+        for span in xs.chunks_exact(2) {
+            // pixel i is lit when its centre i + 0.5 lies in [x0, x1)
+            let from = (span[0] - 0.5).ceil().clamp(0.0, w as f64) as usize;
+            let to = (span[1] - 0.5).ceil().clamp(0.0, w as f64) as usize;
+            image.pixels[row * w + from..row * w + to].fill(Color32::WHITE);
+        }
+    }
+    image
+}
+
+/// Darken the pixels of `image` (a mask over `area`) inside `cavity` that
+/// miss the lattice of `hollow` at height `z`.
+pub(crate) fn hollow_out(image: &mut ColorImage, cavity: &Sketch<()>, hollow: &HollowSettings, z: f64, area: [f64; 2]) {
+    let [w, h] = image.size;
+    let inside = mask(cavity, image.size, area);
+    let (sx, sy) = (w as f64 / area[0].max(1e-6), h as f64 / area[1].max(1e-6));
+    for (n, pixel) in image.pixels.iter_mut().enumerate() {
+        if inside.pixels[n] != Color32::WHITE {
+            continue;
+        }
+        let x = ((n % w) as f64 + 0.5) / sx - area[0] * 0.5;
+        let y = area[1] * 0.5 - ((n / w) as f64 + 0.5) / sy;
+        if !hollow.lattice_at(x, y, z) {
+            *pixel = Color32::BLACK;
+        }
+    }
+}
+
+impl AluminaApp {
+    fn projector_size(&self) -> [usize; 2] {
+        [self.pixels_wide, self.pixels_tall].map(|v| usize::try_from(v).unwrap_or(1).max(1))
+    }
+
+    /// Mask of layer `index` of the current models, hollowed if set.
+    fn layer_mask(&mut self, index: usize) -> ColorImage {
+        let size = self.projector_size();
+        let area = [f64::from(self.work_size.x), f64::from(self.work_size.y)];
+        let Some(layer) = self.layer_plan().get(index).copied() else {
+            return ColorImage::new(size, Color32::BLACK);
+        };
+        let Some(mut slice) = engine::slice_union(self.models.iter().map(|m| &m.mesh), layer.slice_z()) else {
+            return ColorImage::new(size, Color32::BLACK);
+        };
+        if let Some((settings, supports)) = &self.supports {
+            slice = slicer::with_area(&slice, &supports.layer_area(settings, layer.z, layer.height, self.line_width as f32));
+        }
+        let mut image = mask(&slice, size, area);
+        let meshes: Vec<_> = self.models.iter().map(|m| &m.mesh).collect();
+        if let Some(cavity) = engine::resin_cavity(&meshes, layer.slice_z(), &self.resin.hollow) {
+            hollow_out(&mut image, &cavity, &self.resin.hollow, f64::from(layer.slice_z()), area);
+        }
+        image
+    }
+
+    fn project(&mut self, image: &ColorImage) {
+        if let Some(window) = &mut self.projector.window {
+            window.show(image);
+        }
+    }
+
+    fn blank_projector(&mut self) {
+        let dark = ColorImage::new(self.projector_size(), Color32::BLACK);
+        self.project(&dark);
+    }
+
+    /// Send the plate to layer `index` and wait for it; a range that pauses
+    /// there holds the run first unless `resume` is set.
+    fn start_projected_layer(&mut self, index: usize, resume: bool) {
+        let now = now_ms();
+        let Some(layer) = self.layer_plan().get(index).copied() else {
+            self.projector.run = None;
+            return;
+        };
+        let p = self.resin.layer(index, self.peel_distance);
+        if p.pause && !resume {
+            self.projector.run = Some(Run { layer: index, phase: Phase::Paused, until: f64::INFINITY });
+            toasts::info(format!("Projection paused before layer {index}"));
+            return;
+        }
+        let speed = self.resin.lift_speed.max(1.0);
+        let z = layer.top();
+        // the first layer comes down from wherever homing left the plate
+        let travel = if index > 0 && p.lift > 0.0 {
+            send_queue_command(format!("G0 Z{:.3} F{speed:.0}", z + p.lift));
+            2.0 * p.lift
+        } else {
+            z
+        };
+        send_queue_command(format!("G0 Z{z:.3} F{speed:.0}"));
+        let wait = f64::from(travel / speed) * 60_000.0 + f64::from(self.layer_delay) * 1000.0;
+        self.projector.run = Some(Run { layer: index, phase: Phase::Moving, until: now + wait });
+    }
+
+    fn stop_projection(&mut self) {
+        self.projector.run = None;
+        self.blank_projector();
+    }
+
+    /// `manifest.json` of a mask export: the image size and build area, the
+    /// layer delay and peel distance, and every layer's file, height,
+    /// exposure and lift.
+    fn mask_manifest(&mut self) -> String {
+        let [w, h] = self.projector_size();
+        let plan = self.layer_plan().to_vec();
+        let layers: Vec<serde_json::Value> = plan
+            .iter()
+            .enumerate()
+            .map(|(i, layer)| {
+                let p = self.resin.layer(i, self.peel_distance);
+                serde_json::json!({
+                    "file": format!("{}.png", i + 1),
+                    "z": layer.top(),
+                    "height": layer.height,
+                    "exposure": p.exposure,
+                    "lift": p.lift,
+                    "pause": p.pause,
+                })
+            })
+            .collect();
+        let manifest = serde_json::json!({
+            "pixels": [w, h],
+            "area_mm": [self.work_size.x, self.work_size.y],
+            "layer_delay": self.layer_delay,
+            "peel_distance": self.peel_distance,
+            "lift_speed": self.resin.lift_speed,
+            "layers": layers,
+        });
+        serde_json::to_string_pretty(&manifest).unwrap_or_default()
+    }
+
+    /// Draw the next few layers of a mask export, and download the file
+    /// once every layer is in.
+    fn continue_mask_export(&mut self) {
+        let Some(mut export) = self.projector.export.take() else { return };
+        let layers = self.layer_plan().len();
+        let start = now_ms();
+        while export.next < layers && now_ms() - start < EXPORT_BUDGET_MS {
+            let i = export.next;
+            let image = self.layer_mask(i);
+            let grey: Vec<u8> = image.pixels.iter().map(|c| c.r()).collect();
+            match export.format {
+                msla::Format::PngZip => export.zip.add(&format!("{}.png", i + 1), &png_grey(image.size[0], image.size[1], &grey)),
+                msla::Format::Sl1 => msla::sl1_add_layer(&mut export.zip, i, image.size, &grey),
+                msla::Format::Ctb => export.images.push(msla::rle_ctb(&grey)),
+                msla::Format::Cbddlp => export.images.push(msla::rle_cbddlp(&grey)),
+            }
+            let lit = grey.iter().filter(|&&v| v >= 0x80).count();
+            if lit > export.preview.0 {
+                export.preview = (lit, grey);
+            }
+            export.next += 1;
+        }
+        if export.next < layers {
+            self.projector.export = Some(export);
+            return;
+        }
+        let plan = self.layer_plan().to_vec();
+        let manifest = (export.format == msla::Format::PngZip).then(|| self.mask_manifest());
+        let work = self.work_size;
+        let job = msla::Job {
+            layers: &plan,
+            settings: &self.resin,
+            lift: self.peel_distance,
+            delay: self.layer_delay,
+            resolution: self.projector_size(),
+            bed: [work.x, work.y, work.z],
+        };
+        let bytes = match export.format {
+            msla::Format::PngZip => {
+                export.zip.add("manifest.json", manifest.unwrap_or_default().as_bytes());
+                let gcode = engine::resin_gcode(&plan, &self.resin, self.peel_distance, self.layer_delay);
+                export.zip.add("run.gcode", gcode.as_bytes());
+                export.zip.finish()
+            }
+            msla::Format::Sl1 => {
+                export.zip.add("config.ini", msla::sl1_config(&job).as_bytes());
+                export.zip.finish()
+            }
+            msla::Format::Ctb => msla::chitu_file(&job, &export.images, &export.preview.1, false),
+            msla::Format::Cbddlp => msla::chitu_file(&job, &export.images, &export.preview.1, true),
+        };
+        download_bytes(export.format.file_name(), &bytes);
+        toasts::info(format!("Exported {layers} layers as {}", export.format));
+    }
+
+    /// Keep the projector window drawn and the run going; call every frame.
+    pub(crate) fn tick_projector(&mut self, ctx: &egui::Context) {
+        if self.projector.export.is_some() {
+            self.continue_mask_export();
+            ctx.request_repaint();
+        }
+        if let Some(window) = &mut self.projector.window {
+            window.present(ctx);
+            if !window.is_open() {
+                self.projector.window = None;
+                if self.projector.run.take().is_some() {
+                    toasts::warn("Projection stopped: the projector window was closed", None);
+                }
+            }
+        }
+        let Some(Run { layer, phase, until }) = self.projector.run else { return };
+        ctx.request_repaint_after(std::time::Duration::from_millis(20));
+        let now = now_ms();
+        if now < until {
+            return;
+        }
+        match phase {
+            Phase::Moving => {
+                let exposure = self.resin.layer(layer, self.peel_distance).exposure;
+                let image = self.layer_mask(layer);
+                self.project(&image);
+                self.current_layer = i32::try_from(layer).unwrap_or(0);
+                self.refresh_slice();
+                let until = now_ms() + f64::from(exposure) * 1000.0;
+                self.projector.run = Some(Run { layer, phase: Phase::Exposing, until });
+            }
+            Phase::Exposing => {
+                self.blank_projector();
+                let layers = self.layer_plan().len();
+                if layer + 1 < layers {
+                    self.start_projected_layer(layer + 1, false);
+                } else {
+                    self.projector.run = None;
+                    let top = self.layer_plan().last().map_or(0.0, slicer::Layer::top);
+                    send_queue_command(format!("G0 Z{:.3} F{:.0}", top + self.peel_distance.max(5.0), self.resin.lift_speed.max(1.0)));
+                    toasts::info(format!("Projection finished: {layers} layers"));
+                }
+            }
+            Phase::Paused => {}
+        }
+    }
+
+    /// Export the layer masks as PNGs, or show how far the export got.
+    pub(crate) fn mask_export_ui(&mut self, ui: &mut egui::Ui) {
+        let layers = self.layer_plan().len();
+        if let Some(export) = &self.projector.export {
+            ui.horizontal(|ui| {
+                ui.add(egui::ProgressBar::new(export.next as f32 / layers.max(1) as f32).text(format!("{} / {layers} masks", export.next)));
+                if ui.button("Cancel").clicked() {
+                    self.projector.export = None;
+                }
+            });
+            return;
+        }
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_salt("mask_format")
+                .selected_text(self.projector.format.to_string())
+                .show_ui(ui, |ui| {
+                    for format in msla::Format::ALL {
+                        ui.selectable_value(&mut self.projector.format, format, format.to_string());
+                    }
+                });
+            if ui
+                .add_enabled(layers > 0, egui::Button::new("Export layers"))
+                .on_hover_text("Every layer's mask at the projector's resolution over the work area, with the bottom-layer and range exposures")
+                .clicked()
+            {
+                self.projector.export = Some(MaskExport {
+                    format: self.projector.format,
+                    next: 0,
+                    zip: Zip::default(),
+                    images: Vec::new(),
+                    preview: (0, Vec::new()),
+                });
+            }
+        });
+    }
+
+    /// Open / close the projector window, preview masks and run the layers.
+    pub(crate) fn projector_ui(&mut self, ui: &mut egui::Ui) {
+        let index = usize::try_from(self.current_layer).unwrap_or(0);
+        if self.projector.window.is_none() {
+            if ui
+                .button("Open projector window")
+                .on_hover_text("Move it to the projector or LCD and make it full screen (click it, or F11 on the desktop)")
+                .clicked()
+            {
+                match open_projector() {
+                    Ok(window) => {
+                        self.projector.window = Some(window);
+                        self.blank_projector();
+                    }
+                    Err(e) => toasts::error("The projector window could not be opened", Some(e)),
+                }
+            }
+            return;
+        }
+        ui.horizontal(|ui| {
+            let idle = self.projector.run.is_none();
+            if ui.add_enabled(idle, egui::Button::new("Show layer")).on_hover_text("Project the current layer's mask").clicked() {
+                let image = self.layer_mask(index);
+                self.project(&image);
+            }
+            if ui.add_enabled(idle, egui::Button::new("Blank")).clicked() {
+                self.blank_projector();
+            }
+            if ui.button("Close window").clicked() {
+                self.stop_projection();
+                if let Some(mut window) = self.projector.window.take() {
+                    window.close();
+                }
+            }
+        });
+        let layers = self.layer_plan().len();
+        match self.projector.run {
+            None => {
+                if ui
+                    .add_enabled(index < layers, egui::Button::new(format!("Run from layer {index}")))
+                    .on_hover_text("Home Z first; the run only moves the plate between layers")
+                    .clicked()
+                {
+                    self.start_projected_layer(index, false);
+                }
+            }
+            Some(run) => {
+                let left = ((run.until - now_ms()) / 1000.0).max(0.0);
+                ui.label(match run.phase {
+                    Phase::Moving => format!("Layer {} / {layers}: moving, {left:.1} s", run.layer),
+                    Phase::Exposing => format!("Layer {} / {layers}: exposing, {left:.1} s left", run.layer),
+                    Phase::Paused => format!("Paused before layer {} / {layers}", run.layer),
+                });
+                ui.horizontal(|ui| {
+                    if run.phase == Phase::Paused && ui.button("Resume").clicked() {
+                        self.start_projected_layer(run.layer, true);
+                    }
+                    if ui.button("Stop").clicked() {
+                        self.stop_projection();
+                    }
+                });
+            }
+        }
+    }
+}
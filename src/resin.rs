@@ -0,0 +1,208 @@
+//! Resin (DLP / LCD) jobs.
+//!
+//! Every layer is exposed for a set time, then the build plate lifts to
+//! peel it off the vat film and comes back down for the next one. Ranges of
+//! layers can replace the exposure and lift (long burn-in exposures for the
+//! first layers, gentler peels over large cross-sections) and can pause the
+//! job before they start.
+//!
+//! The job file is the `run.gcode` of a ChiTuBox-style archive: each layer
+//! shows its mask image, lifts and returns, rests for the layer delay, then
+//! switches the light on for its exposure.
+//!
+//! Parts can be hollowed to save resin: every layer keeps a wall around
+//! the cavity left inside the part, and the cavity can be filled with a
+//! thin minimal-surface lattice so the shell stays stiff. The lattice is
+//! applied per pixel when the masks are drawn.
+
+use csgrs::{sketch::Sketch, traits::CSG};
+use serde::{Deserialize, Serialize};
+
+use crate::slicer::{self, InfillType, Layer};
+
+/// Exposure and lift for a run of layers, numbered from 0 as in the layer
+/// scrubber; both ends are included.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ResinRange {
+    pub first: u32,
+    pub last: u32,
+    /// Exposure (s) and lift (mm) of these layers.
+    pub exposure: f32,
+    pub lift: f32,
+    /// Stop before the first layer of the range (to clean the vat, top up
+    /// resin or embed an insert).
+    pub pause: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ResinSettings {
+    /// Exposure of layers outside every range (s).
+    pub exposure: f32,
+    /// Plate speed while lifting and returning (mm/min).
+    pub lift_speed: f32,
+    /// Per-layer overrides; the first matching range wins.
+    pub ranges: Vec<ResinRange>,
+    pub hollow: HollowSettings,
+}
+
+impl Default for ResinSettings {
+    fn default() -> Self {
+        Self {
+            exposure: 8.0,
+            lift_speed: 60.0,
+            ranges: vec![ResinRange {
+                first: 0,
+                last: 3,
+                exposure: 40.0,
+                lift: 8.0,
+                pause: false,
+            }],
+            hollow: HollowSettings::default(),
+        }
+    }
+}
+
+/// Hollowing of resin parts, with an optional lattice inside the cavity.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HollowSettings {
+    pub enabled: bool,
+    /// Thickness of the shell on every side, floor and roof included (mm).
+    pub wall: f64,
+    /// Fill the cavity with `pattern` (any but Linear) of cells `cell` mm
+    /// across, its sheets `thickness` mm thick.
+    pub lattice: bool,
+    pub pattern: InfillType,
+    pub cell: f64,
+    pub thickness: f64,
+}
+
+impl Default for HollowSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            wall: 2.0,
+            lattice: true,
+            pattern: InfillType::Gyroid,
+            cell: 8.0,
+            thickness: 0.8,
+        }
+    }
+}
+
+impl HollowSettings {
+    /// Whether the point lies on a lattice sheet; the cavity is left empty
+    /// everywhere else.
+    pub fn lattice_at(&self, x: f64, y: f64, z: f64) -> bool {
+        if !self.lattice {
+            return false;
+        }
+        let k = std::f64::consts::TAU / self.cell.max(0.5);
+        // the surface function changes by about k per mm across a sheet
+        let half = k * self.thickness.max(0.0) * 0.5;
+        self.pattern.surface(k * x, k * y, k * z).is_some_and(|f| f.abs() <= half)
+    }
+}
+
+/// Cavity of a part whose slice is `slice`: everything a wall's thickness
+/// inside it, and inside the `nearby` slices above and below (so the floor
+/// and roof are as thick as the sides). `None` where the layer is all wall.
+pub fn cavity(slice: &Sketch<()>, nearby: &[Sketch<()>], wall: f64) -> Option<Sketch<()>> {
+    let shrink = |s: &Sketch<()>| s.offset(-wall.max(0.01));
+    let cavity = nearby.iter().fold(shrink(slice), |acc, s| acc.intersection(&shrink(s)));
+    (!slicer::polygons(&cavity).is_empty()).then_some(cavity)
+}
+
+/// What one layer of a resin job does.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LayerParams {
+    pub exposure: f32,
+    pub lift: f32,
+    pub pause: bool,
+}
+
+impl ResinSettings {
+    /// Exposure, lift and pause of layer `index`; `lift` is the lift outside
+    /// every range. Only the first layer of a range pauses.
+    pub fn layer(&self, index: usize, lift: f32) -> LayerParams {
+        let i = u32::try_from(index).unwrap_or(u32::MAX);
+        match self.ranges.iter().find(|r| (r.first..=r.last).contains(&i)) {
+            Some(r) => LayerParams {
+                exposure: r.exposure,
+                lift: r.lift,
+                pause: r.pause && r.first == i,
+            },
+            None => LayerParams {
+                exposure: self.exposure,
+                lift,
+                pause: false,
+            },
+        }
+    }
+
+    /// Layers in the range that starts at the first layer, which MSLA
+    /// formats call the bottom layers; zero without one.
+    pub fn bottom_layers(&self) -> u32 {
+        self.ranges.iter().find(|r| r.first == 0).map_or(0, |r| r.last + 1)
+    }
+
+    /// Estimated time of `layers` (s): exposures, lifts and layer delays.
+    pub fn print_time(&self, layers: &[Layer], lift: f32, delay: f32) -> f32 {
+        let speed = self.lift_speed.max(1.0);
+        (0..layers.len())
+            .map(|i| {
+                let p = self.layer(i, lift);
+                p.exposure + delay + 2.0 * p.lift / speed * 60.0
+            })
+            .sum()
+    }
+
+    /// Layers the job stops before.
+    pub fn pauses(&self, layers: usize) -> Vec<usize> {
+        (0..layers).filter(|&i| self.layer(i, 0.0).pause).collect()
+    }
+}
+
+/// `run.gcode` for `layers`: mask `n.png` (from 1) for layer `n - 1`, lifted
+/// by its lift (`lift` outside every range) and rested `delay` seconds
+/// before exposing it.
+pub fn job_gcode(layers: &[Layer], settings: &ResinSettings, lift: f32, delay: f32) -> String {
+    let speed = settings.lift_speed.max(1.0);
+    let mut out = vec![
+        ";Alumina resin job".to_owned(),
+        format!(";totalLayer:{}", layers.len()),
+        "G21 ; mm".to_owned(),
+        "G90 ; absolute".to_owned(),
+        "M17 ; motors on".to_owned(),
+        "M106 S0 ; light off".to_owned(),
+        "G28 Z0".to_owned(),
+    ];
+    let mut total = 0.0;
+    for (i, layer) in layers.iter().enumerate() {
+        let p = settings.layer(i, lift);
+        let z = layer.top();
+        total += p.exposure + delay;
+        out.push(format!(";LAYER_START:{i}"));
+        out.push(format!(";currPos:{z:.3}"));
+        out.push(format!(";exposure:{:.2} lift:{:.2}", p.exposure, p.lift));
+        if p.pause {
+            out.push(format!("M0 ; pause before layer {i}"));
+        }
+        out.push(format!("M6054 \"{}.png\" ; show the mask", i + 1));
+        if i > 0 && p.lift > 0.0 {
+            out.push(format!("G0 Z{:.3} F{speed:.0}", z + p.lift));
+        }
+        out.push(format!("G0 Z{z:.3} F{speed:.0}"));
+        out.push(format!("G4 P{:.0}", delay * 1000.0));
+        out.push("M106 S255 ; light on".to_owned());
+        out.push(format!("G4 P{:.0}", p.exposure * 1000.0));
+        out.push("M106 S0 ; light off".to_owned());
+        out.push(format!(";LAYER_END:{i}"));
+    }
+    let top = layers.last().map_or(0.0, Layer::top);
+    out.push(format!("G0 Z{:.3} F{speed:.0} ; clear of the vat", top + lift.max(5.0)));
+    out.push("M18 ; motors off".to_owned());
+    out.insert(2, format!(";estimatedPrintTime:{total:.0}"));
+    out.join("\n") + "\n"
+}
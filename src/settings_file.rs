@@ -0,0 +1,78 @@
+//! Export and import of the whole setup (machine profile, tool and slicer
+//! settings, tool presets, view preferences) as one JSON file, to back it up
+//! or move it to another browser or computer.
+//!
+//! Keyboard shortcuts are fixed in this version, so there is nothing to
+//! carry for them yet. Files written by a newer version are refused.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{AluminaApp, app_settings::AppSettings, download_bytes, machine, presets::Preset, toasts};
+
+const VERSION: u32 = 1;
+const FILE_NAME: &str = "alumina-settings.json";
+
+#[derive(Serialize, Deserialize)]
+struct SettingsFile {
+    version: u32,
+    machine: machine::MachineProfile,
+    #[serde(flatten)]
+    app: AppSettings,
+    #[serde(default)]
+    presets: Vec<Preset>,
+}
+
+impl AluminaApp {
+    fn settings_file(&self) -> SettingsFile {
+        SettingsFile {
+            version: VERSION,
+            machine: self.machine.clone(),
+            app: self.app_settings(),
+            presets: self.presets.all().to_vec(),
+        }
+    }
+
+    /// Offer the current setup as a download.
+    pub(crate) fn export_settings(&self) {
+        match serde_json::to_vec_pretty(&self.settings_file()) {
+            Ok(json) => download_bytes(FILE_NAME, &json),
+            Err(e) => toasts::error("Settings could not be exported", Some(e.to_string())),
+        }
+    }
+
+    /// Replace the setup with a file picked by the user.
+    pub(crate) fn import_settings(&mut self, bytes: &[u8]) {
+        #[derive(Deserialize)]
+        struct Header {
+            version: u32,
+        }
+        let file = serde_json::from_slice::<Header>(bytes).and_then(|h| {
+            if h.version > VERSION {
+                Err(serde::de::Error::custom(format!(
+                    "written by a newer version (format {}, this app reads up to {VERSION})",
+                    h.version
+                )))
+            } else {
+                serde_json::from_slice::<SettingsFile>(bytes)
+            }
+        });
+        let file = match file {
+            Ok(file) => file,
+            Err(e) => {
+                log::error!("[settings] import failed: {e}");
+                toasts::error("Not a valid settings file", Some(e.to_string()));
+                return;
+            }
+        };
+
+        self.machine = file.machine;
+        self.machine.save();
+        crate::net::configure(&self.machine.connection);
+        self.apply_app_settings(file.app);
+        if !file.presets.is_empty() {
+            self.presets.replace(file.presets);
+        }
+
+        toasts::info("Settings imported");
+    }
+}
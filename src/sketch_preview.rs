@@ -0,0 +1,362 @@
+//! 2-D preview of the sketch produced by the selected design node, drawn
+//! with egui_plot and dimensioned, so a profile can be checked without
+//! extruding it first.
+//!
+//! A selected boolean node shows its A and B operands dashed under the
+//! result instead (meshes seen from above), with a hint when the result is
+//! empty.
+//!
+//! Lengths and diameters can be dimensioned by clicking in the plot; they
+//! are kept on the node and exported with the outlines as DXF or SVG.
+
+use csgrs::{sketch::Sketch, traits::CSG};
+use egui_node_graph2::NodeId;
+use egui_plot::{Legend, Line, LineStyle, Plot, PlotPoint, PlotPoints, PlotUi, Points, Text};
+use geo::{Area, Geometry, LineString};
+
+use crate::{
+    AluminaApp,
+    design_graph::{self, BooleanOp, DValue},
+    download_bytes,
+    drawing::{self, Dimension},
+};
+
+/// Pick radius around the pointer, in screen points.
+const SNAP_PX: f64 = 8.0;
+
+/// The last evaluated preview; re-evaluated when the node or the graph changes.
+#[derive(Default)]
+pub(crate) struct SketchPreview {
+    source: Option<(NodeId, u64)>,
+    result: Option<Result<Sketch<()>, String>>,
+    operands: Option<Result<Operands, String>>,
+    tool: DimensionTool,
+    /// First point of a length being placed.
+    pending: Option<[f64; 2]>,
+}
+
+/// What a click in the preview plot places.
+#[derive(Clone, Copy, Default, PartialEq)]
+enum DimensionTool {
+    #[default]
+    Off,
+    /// Two clicks: from and to, snapped to the nearest vertex.
+    Length,
+    /// One click inside a round loop.
+    Diameter,
+}
+
+enum DimensionEdit {
+    Add(Dimension),
+    Remove(usize),
+    Offset(usize, f64),
+}
+
+/// A boolean node's inputs and output, as top-view sketches.
+struct Operands {
+    a: Sketch<()>,
+    b: Sketch<()>,
+    result: Sketch<()>,
+    /// Why the result is empty, if it is.
+    hint: Option<&'static str>,
+}
+
+/// Sketches as they are, meshes flattened onto XY.
+fn top_view(value: DValue, input: &str) -> Result<Sketch<()>, String> {
+    match value {
+        DValue::Sketch(s) => Ok(s),
+        DValue::Mesh(m) => Ok(m.flatten()),
+        _ => Err(format!("{input} is not connected")),
+    }
+}
+
+/// Lowest and highest corner, in 3-D for meshes.
+fn bounds(value: &DValue) -> Option<([f64; 3], [f64; 3])> {
+    let bb = match value {
+        DValue::Sketch(s) if !s.geometry.0.is_empty() => s.bounding_box(),
+        DValue::Mesh(m) if !m.polygons.is_empty() => m.bounding_box(),
+        _ => return None,
+    };
+    Some(([bb.mins.x, bb.mins.y, bb.mins.z], [bb.maxs.x, bb.maxs.y, bb.maxs.z]))
+}
+
+fn empty_hint(op: BooleanOp, a: &DValue, b: &DValue) -> &'static str {
+    let (Some((a_lo, a_hi)), b_box) = (bounds(a), bounds(b)) else {
+        return "A is empty";
+    };
+    let Some((b_lo, b_hi)) = b_box else {
+        return "B is empty, so A and B share nothing";
+    };
+    let apart = (0..3).any(|i| a_hi[i] < b_lo[i] || b_hi[i] < a_lo[i]);
+    let covers = (0..3).all(|i| b_lo[i] <= a_lo[i] && a_hi[i] <= b_hi[i]);
+    match op {
+        BooleanOp::Intersect if apart => "A and B do not overlap",
+        BooleanOp::Subtract if covers => "B encloses A, so nothing of A is left",
+        _ => "The operation removed everything",
+    }
+}
+
+fn plot_rings(plot_ui: &mut PlotUi, rings: &[(LineString<f64>, bool)], color: egui::Color32, style: LineStyle, name: &str) {
+    for (ring, _) in rings {
+        let pts: Vec<[f64; 2]> = ring.0.iter().map(|c| [c.x, c.y]).collect();
+        plot_ui.line(Line::new(PlotPoints::from(pts)).color(color).style(style).name(name));
+    }
+}
+
+/// The vertex nearest to `p` within `tolerance`, or `p` itself.
+fn snap(rings: &[(LineString<f64>, bool)], p: [f64; 2], tolerance: f64) -> [f64; 2] {
+    rings
+        .iter()
+        .flat_map(|(ring, _)| ring.0.iter())
+        .map(|c| ([c.x, c.y], (c.x - p[0]).hypot(c.y - p[1])))
+        .filter(|&(_, d)| d <= tolerance)
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map_or(p, |(c, _)| c)
+}
+
+/// The smallest loop, taken as a circle, that `p` lies in.
+fn circle_at(rings: &[(LineString<f64>, bool)], p: [f64; 2]) -> Option<([f64; 2], f64)> {
+    rings
+        .iter()
+        .filter_map(|(ring, _)| drawing::fit_circle(ring))
+        .filter(|(c, r)| (p[0] - c[0]).hypot(p[1] - c[1]) <= *r)
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+}
+
+fn rings(sketch: &Sketch<()>) -> Vec<(LineString<f64>, bool)> {
+    let mut out = Vec::new();
+    let mut polygon = |p: &geo::Polygon<f64>| {
+        out.push((p.exterior().clone(), false));
+        out.extend(p.interiors().iter().map(|r| (r.clone(), true)));
+    };
+    for g in &sketch.geometry.0 {
+        match g {
+            Geometry::Polygon(p) => polygon(p),
+            Geometry::MultiPolygon(mp) => mp.0.iter().for_each(&mut polygon),
+            _ => {}
+        }
+    }
+    for g in &sketch.geometry.0 {
+        if let Geometry::LineString(ls) = g {
+            out.push((ls.clone(), false));
+        }
+    }
+    out
+}
+
+impl AluminaApp {
+    /// Right-hand panel of the Design tab.
+    pub(crate) fn sketch_preview_ui(&mut self, ui: &mut egui::Ui) {
+        ui.heading("Sketch preview");
+        ui.separator();
+        let graph = &self.design_state.graph;
+        let node = self.design_state.selected_nodes.last().copied().filter(|&n| graph.nodes.contains_key(n));
+        let Some(node) = node else {
+            ui.weak("Select a node with a sketch output to see its profile here.");
+            return;
+        };
+        if let Some(op) = design_graph::boolean_op(graph, node) {
+            self.boolean_preview_ui(ui, node, op);
+            return;
+        }
+        let Some(&output) = design_graph::sketch_outputs(graph, node).first() else {
+            ui.weak(format!("“{}” has no sketch output.", graph[node].label));
+            return;
+        };
+        let source = (node, design_graph::fingerprint(graph));
+        if self.sketch_preview.source != Some(source) {
+            if self.sketch_preview.source.is_none_or(|(n, _)| n != node) {
+                self.sketch_preview.pending = None;
+            }
+            self.sketch_preview.source = Some(source);
+            self.sketch_preview.result = Some(design_graph::evaluate_sketch(graph, output).map_err(|e| e.to_string()));
+        }
+        let sketch = match &self.sketch_preview.result {
+            Some(Ok(sketch)) => sketch,
+            Some(Err(e)) => {
+                ui.colored_label(ui.visuals().error_fg_color, e);
+                return;
+            }
+            None => return,
+        };
+        ui.label(graph[node].label.as_str());
+
+        let bb = sketch.bounding_box();
+        let (w, h) = (bb.maxs.x - bb.mins.x, bb.maxs.y - bb.mins.y);
+        let area: f64 = crate::slicer::polygons(sketch).iter().map(|p| p.unsigned_area()).sum();
+        let rings = rings(sketch);
+        if rings.is_empty() {
+            ui.weak("The sketch is empty.");
+            return;
+        }
+        ui.small(format!("{w:.2} × {h:.2} mm · area {area:.1} mm² · {} loops", rings.len()));
+
+        let dims = &graph[node].user_data.dimensions;
+        let mut edit = None;
+        ui.horizontal(|ui| {
+            let preview = &mut self.sketch_preview;
+            ui.label("Dimension:");
+            for (tool, name, hover) in [
+                (DimensionTool::Length, "↔ Length", "Click two points; vertices snap"),
+                (DimensionTool::Diameter, "⌀ Diameter", "Click inside a round loop"),
+            ] {
+                if ui.selectable_label(preview.tool == tool, name).on_hover_text(hover).clicked() {
+                    preview.tool = if preview.tool == tool { DimensionTool::Off } else { tool };
+                    preview.pending = None;
+                }
+            }
+            let outlines = || -> Vec<LineString<f64>> { rings.iter().map(|(r, _)| r.clone()).collect() };
+            if ui.button("Export DXF").on_hover_text("Outlines and dimensions on separate layers, in mm").clicked() {
+                download_bytes(&format!("{}.dxf", graph[node].label), drawing::dxf(&outlines(), dims).as_bytes());
+            }
+            if ui.button("Export SVG").on_hover_text("Outlines and dimensions as layers, 1:1 in mm").clicked() {
+                download_bytes(&format!("{}.svg", graph[node].label), drawing::svg(&outlines(), dims).as_bytes());
+            }
+        });
+        for (i, dim) in dims.iter().enumerate() {
+            ui.horizontal(|ui| {
+                ui.monospace(dim.label());
+                if let Dimension::Length { offset, .. } = *dim {
+                    let mut offset = offset;
+                    let drag = egui::DragValue::new(&mut offset).speed(0.25).suffix(" mm offset");
+                    if ui.add(drag).changed() {
+                        edit = Some(DimensionEdit::Offset(i, offset));
+                    }
+                }
+                if ui.small_button("🗑").on_hover_text("Remove this dimension").clicked() {
+                    edit = Some(DimensionEdit::Remove(i));
+                }
+            });
+        }
+
+        let outline = ui.visuals().strong_text_color();
+        let hole = ui.visuals().warn_fg_color;
+        let dim = ui.visuals().weak_text_color();
+        let annotation = egui::Color32::from_rgb(110, 200, 130);
+        let gap = 0.08 * w.max(h).max(1e-3);
+        let pending = self.sketch_preview.pending;
+        let plot = Plot::new("sketch_preview")
+            .data_aspect(1.0)
+            .show_axes([true, true])
+            .label_formatter(|_, p| format!("x {:.2}\ny {:.2}", p.x, p.y))
+            .show(ui, |plot_ui| {
+                for (ring, is_hole) in &rings {
+                    let pts: Vec<[f64; 2]> = ring.0.iter().map(|c| [c.x, c.y]).collect();
+                    plot_ui.line(Line::new(PlotPoints::from(pts)).color(if *is_hole { hole } else { outline }));
+                }
+                // width below, height to the left of the bounding box
+                let y = bb.mins.y - gap;
+                plot_ui.line(Line::new(PlotPoints::from(vec![[bb.mins.x, y], [bb.maxs.x, y]])).color(dim));
+                plot_ui.text(Text::new(PlotPoint::new((bb.mins.x + bb.maxs.x) / 2.0, y - gap * 0.5), format!("{w:.2}")).color(dim));
+                let x = bb.mins.x - gap;
+                plot_ui.line(Line::new(PlotPoints::from(vec![[x, bb.mins.y], [x, bb.maxs.y]])).color(dim));
+                plot_ui.text(Text::new(PlotPoint::new(x - gap * 0.5, (bb.mins.y + bb.maxs.y) / 2.0), format!("{h:.2}")).color(dim));
+
+                for d in dims {
+                    let ann = d.annotation();
+                    for [a, b] in ann.lines {
+                        plot_ui.line(Line::new(PlotPoints::from(vec![a, b])).color(annotation));
+                    }
+                    plot_ui.text(Text::new(PlotPoint::new(ann.at[0], ann.at[1]), ann.text).color(annotation));
+                }
+                if let Some(p) = pending {
+                    plot_ui.points(Points::new(vec![p]).radius(4.0).color(annotation));
+                }
+            });
+
+        let tool = self.sketch_preview.tool;
+        if tool != DimensionTool::Off && plot.response.clicked() {
+            if let Some(pos) = plot.response.interact_pointer_pos() {
+                let at = plot.transform.value_from_position(pos);
+                let p = [at.x, at.y];
+                match tool {
+                    DimensionTool::Length => {
+                        let p = snap(&rings, p, SNAP_PX * plot.transform.dvalue_dpos()[0].abs());
+                        match self.sketch_preview.pending.take() {
+                            None => self.sketch_preview.pending = Some(p),
+                            Some(from) if from != p => {
+                                edit = Some(DimensionEdit::Add(Dimension::Length { from, to: p, offset: 3.0 * drawing::TEXT_HEIGHT }));
+                            }
+                            Some(_) => {}
+                        }
+                    }
+                    DimensionTool::Diameter => {
+                        if let Some((center, radius)) = circle_at(&rings, p) {
+                            edit = Some(DimensionEdit::Add(Dimension::Diameter { center, radius }));
+                        }
+                    }
+                    DimensionTool::Off => {}
+                }
+            }
+        }
+        if let Some(edit) = edit {
+            let dims = &mut self.design_state.graph[node].user_data.dimensions;
+            match edit {
+                DimensionEdit::Add(d) => dims.push(d),
+                DimensionEdit::Remove(i) => {
+                    dims.remove(i);
+                }
+                DimensionEdit::Offset(i, value) => {
+                    if let Some(Dimension::Length { offset, .. }) = dims.get_mut(i) {
+                        *offset = value;
+                    }
+                }
+            }
+        }
+    }
+
+    /// A boolean node's operands dashed under its result.
+    fn boolean_preview_ui(&mut self, ui: &mut egui::Ui, node: NodeId, op: BooleanOp) {
+        let graph = &self.design_state.graph;
+        let source = (node, design_graph::fingerprint(graph));
+        if self.sketch_preview.source != Some(source) {
+            self.sketch_preview.source = Some(source);
+            self.sketch_preview.operands = Some((|| {
+                let a = design_graph::evaluate_input(graph, node, "A").map_err(|e| e.to_string())?;
+                let b = design_graph::evaluate_input(graph, node, "B").map_err(|e| e.to_string())?;
+                let out = graph[node].outputs.first().map(|&(_, id)| id).ok_or("the node has no output")?;
+                let result = design_graph::evaluate_value(graph, out).map_err(|e| e.to_string())?;
+                let empty = bounds(&result).is_none();
+                Ok(Operands {
+                    hint: empty.then(|| empty_hint(op, &a, &b)),
+                    result: top_view(result, "the result")?,
+                    a: top_view(a, "A")?,
+                    b: top_view(b, "B")?,
+                })
+            })());
+        }
+        let ops = match &self.sketch_preview.operands {
+            Some(Ok(ops)) => ops,
+            Some(Err(e)) => {
+                ui.colored_label(ui.visuals().error_fg_color, e);
+                return;
+            }
+            None => return,
+        };
+        let symbol = match op {
+            BooleanOp::Union => "A ∪ B",
+            BooleanOp::Subtract => "A − B",
+            BooleanOp::Intersect => "A ∩ B",
+        };
+        ui.label(format!("{} ({symbol})", graph[node].label));
+        if graph[node].outputs.iter().any(|&(_, id)| graph[id].typ == design_graph::DType::Mesh) {
+            ui.small("Meshes are shown from above.");
+        }
+        if let Some(hint) = ops.hint {
+            ui.colored_label(ui.visuals().warn_fg_color, format!("⚠ Empty result: {hint}"));
+        }
+
+        let (a, b, result) = (rings(&ops.a), rings(&ops.b), rings(&ops.result));
+        let result_color = ui.visuals().strong_text_color();
+        Plot::new("boolean_preview")
+            .data_aspect(1.0)
+            .show_axes([true, true])
+            .legend(Legend::default())
+            .label_formatter(|_, p| format!("x {:.2}\ny {:.2}", p.x, p.y))
+            .show(ui, |plot_ui| {
+                plot_rings(plot_ui, &a, egui::Color32::from_rgb(110, 170, 255), LineStyle::dashed_loose(), "A");
+                plot_rings(plot_ui, &b, egui::Color32::from_rgb(255, 130, 110), LineStyle::dashed_loose(), "B");
+                plot_rings(plot_ui, &result, result_color, LineStyle::Solid, "result");
+            });
+    }
+}
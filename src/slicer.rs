@@ -0,0 +1,1050 @@
+//! Layer planning: the Z heights the models are sliced at.
+//!
+//! Layers are uniform by default. Users can override the height inside Z
+//! ranges, or let it adapt to the surface slope so shallow, detailed regions
+//! get thin layers and vertical walls get thick ones. Every consumer (preview
+//! scrubber, G-code and DLP exports) walks the same `Vec<Layer>`.
+
+use csgrs::{
+    mesh::{Mesh, plane::Plane},
+    sketch::Sketch,
+    traits::CSG,
+};
+use std::collections::HashMap;
+
+use geo::{
+    BooleanOps, ConvexHull, Coord, Geometry, GeometryCollection, LineString, MultiLineString, MultiPoint, MultiPolygon, Polygon, Simplify,
+};
+use nalgebra::{UnitQuaternion, Vector3};
+use serde::{Deserialize, Serialize};
+
+/// One slab of material.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Layer {
+    /// Bottom of the layer (mm).
+    pub z: f32,
+    /// Thickness (mm).
+    pub height: f32,
+}
+
+impl Layer {
+    /// Top of the layer; where the tool sits while depositing / exposing it.
+    pub fn top(&self) -> f32 {
+        self.z + self.height
+    }
+
+    /// Plane the outline is taken from: mid-layer, clear of coplanar faces.
+    pub fn slice_z(&self) -> f32 {
+        self.z + self.height * 0.5
+    }
+}
+
+/// Fixed layer height between two Z levels.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LayerRange {
+    pub z_min: f32,
+    pub z_max: f32,
+    pub height: f32,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LayerSettings {
+    /// Height used outside any range when not adapting.
+    pub base_height: f32,
+    /// Manual overrides; the first matching range wins.
+    pub ranges: Vec<LayerRange>,
+    /// Derive the height from surface slope (ranges still take precedence).
+    pub adaptive: bool,
+    pub min_height: f32,
+    pub max_height: f32,
+    /// Largest allowed stair-step (cusp) height on sloped surfaces (mm).
+    pub max_cusp: f32,
+}
+
+impl Default for LayerSettings {
+    fn default() -> Self {
+        Self {
+            base_height: 0.2,
+            ranges: Vec::new(),
+            adaptive: false,
+            min_height: 0.08,
+            max_height: 0.3,
+            max_cusp: 0.05,
+        }
+    }
+}
+
+/// Z extent and |normal.z| of one facet, for the adaptive planner.
+struct Facet {
+    z_lo: f32,
+    z_hi: f32,
+    nz: f32,
+}
+
+/// Lowest and highest Z over all meshes.
+pub fn z_extent<'a>(meshes: impl IntoIterator<Item = &'a Mesh<()>>) -> Option<(f32, f32)> {
+    let mut out: Option<(f32, f32)> = None;
+    for mesh in meshes {
+        for p in &mesh.polygons {
+            for v in &p.vertices {
+                let z = v.pos.z as f32;
+                out = Some(match out {
+                    Some((lo, hi)) => (lo.min(z), hi.max(z)),
+                    None => (z, z),
+                });
+            }
+        }
+    }
+    out
+}
+
+/// Cut `mesh` with the plane `normal · p = offset` (the Slice node's
+/// convention) and return the outlines as 3-D polylines in world coordinates.
+///
+/// `Mesh::slice` keeps only X/Y of the cut, so the mesh is first rotated
+/// to bring the plane normal onto +Z, cut there, and the result rotated back.
+pub fn slice_oriented(mesh: &Mesh<()>, normal: Vector3<f64>, offset: f64) -> Vec<Vec<Vector3<f32>>> {
+    let len = normal.norm();
+    let (n, offset) = if len > 1e-12 { (normal / len, offset / len) } else { (Vector3::z(), offset) };
+    let to_local = UnitQuaternion::rotation_between(&n, &Vector3::z())
+        // antiparallel: any half turn about a horizontal axis will do
+        .unwrap_or_else(|| UnitQuaternion::from_axis_angle(&Vector3::x_axis(), std::f64::consts::PI));
+    let to_world = to_local.inverse();
+    let sketch = mesh.transform(&to_local.to_homogeneous()).slice(Plane::from_normal(Vector3::z(), offset));
+
+    let lift = |c: &Coord<f64>| {
+        let p = to_world * Vector3::new(c.x, c.y, offset);
+        Vector3::new(p.x as f32, p.y as f32, p.z as f32)
+    };
+    let mut out = Vec::new();
+    for g in &sketch.geometry.0 {
+        match g {
+            Geometry::LineString(ls) => out.push(ls.0.iter().map(lift).collect()),
+            Geometry::Polygon(p) => {
+                for ring in std::iter::once(p.exterior()).chain(p.interiors()) {
+                    out.push(ring.0.iter().map(lift).collect());
+                }
+            }
+            Geometry::MultiPolygon(mp) => {
+                for p in &mp.0 {
+                    for ring in std::iter::once(p.exterior()).chain(p.interiors()) {
+                        out.push(ring.0.iter().map(lift).collect());
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    out
+}
+
+/// Stack layers from `z_start` until `z_end` is covered.
+pub fn plan_layers<'a>(
+    settings: &LayerSettings,
+    meshes: impl IntoIterator<Item = &'a Mesh<()>>,
+    z_start: f32,
+    z_end: f32,
+) -> Vec<Layer> {
+    let facets: Vec<Facet> = if settings.adaptive {
+        meshes
+            .into_iter()
+            .flat_map(|m| &m.polygons)
+            .map(|p| {
+                let (lo, hi) = p.vertices.iter().fold((f32::INFINITY, f32::NEG_INFINITY), |(lo, hi), v| {
+                    (lo.min(v.pos.z as f32), hi.max(v.pos.z as f32))
+                });
+                Facet {
+                    z_lo: lo,
+                    z_hi: hi,
+                    nz: (p.plane.normal().z as f32).abs(),
+                }
+            })
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    // the minimum bounds adaptive heights only; fixed and range heights are taken as set
+    let floor = if settings.adaptive { settings.min_height.max(0.001) } else { 0.001 };
+    let mut layers = Vec::new();
+    let mut z = z_start;
+    while z < z_end - 1e-4 {
+        let h = height_at(settings, &facets, z).max(floor).min(z_end - z).max(1e-3);
+        layers.push(Layer { z, height: h });
+        z += h;
+    }
+    layers
+}
+
+fn height_at(settings: &LayerSettings, facets: &[Facet], z: f32) -> f32 {
+    if let Some(r) = settings.ranges.iter().find(|r| z >= r.z_min && z < r.z_max) {
+        // don't let a thick range layer overshoot into the next region
+        return r.height.min(r.z_max - z).max(settings.min_height.min(r.height));
+    }
+    if !settings.adaptive {
+        return settings.base_height;
+    }
+
+    // cusp = h · |n.z|, so each facet in the slab allows h ≤ cusp / |n.z|
+    let (lo, hi) = (settings.min_height, settings.max_height.max(settings.min_height));
+    let limit = |z0: f32, z1: f32| {
+        facets
+            .iter()
+            .filter(|f| f.z_hi >= z0 && f.z_lo <= z1)
+            .map(|f| if f.nz > 1e-6 { settings.max_cusp / f.nz } else { hi })
+            .fold(hi, f32::min)
+            .clamp(lo, hi)
+    };
+    // second pass: the thinner slab may exclude the facet that limited it
+    let h = limit(z, z + hi);
+    limit(z, z + h).min(h.max(lo) * 1.5)
+}
+
+// ---------- seams -----------------------------------------------------------------------------------
+
+/// Where each closed perimeter loop starts (and ends), i.e. where its seam is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SeamStrategy {
+    /// Vertex closest to where the nozzle already is: shortest travel.
+    Nearest,
+    /// Rear-most vertex, so seams stack into one line up the back.
+    Aligned,
+    /// Scattered, but reproducible for a given layer and loop.
+    Random,
+    /// Vertex closest to a line the user placed on the bed.
+    Painted,
+}
+
+impl std::fmt::Display for SeamStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            SeamStrategy::Nearest => "Nearest",
+            SeamStrategy::Aligned => "Aligned",
+            SeamStrategy::Random => "Random",
+            SeamStrategy::Painted => "Painted line",
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SeamSettings {
+    pub strategy: SeamStrategy,
+    /// End points of the painted seam line (bed XY, mm).
+    pub painted: [[f64; 2]; 2],
+}
+
+impl Default for SeamSettings {
+    fn default() -> Self {
+        Self {
+            strategy: SeamStrategy::Aligned,
+            painted: [[0.0, 100.0], [0.0, -100.0]],
+        }
+    }
+}
+
+/// Index of the vertex a closed `ring` should start at. `nozzle` is the
+/// current tool position; `layer` and `loop_index` seed `Random`.
+pub fn seam_index(ring: &LineString<f64>, settings: &SeamSettings, nozzle: Coord<f64>, layer: usize, loop_index: usize) -> usize {
+    let pts = open_ring(ring);
+    if pts.is_empty() {
+        return 0;
+    }
+    let by_min = |key: &dyn Fn(Coord<f64>) -> f64| {
+        pts.iter()
+            .enumerate()
+            .min_by(|a, b| key(*a.1).total_cmp(&key(*b.1)))
+            .map_or(0, |(i, _)| i)
+    };
+    match settings.strategy {
+        SeamStrategy::Nearest => by_min(&|c| dist2(c, nozzle)),
+        SeamStrategy::Aligned => {
+            let cx = pts.iter().map(|c| c.x).sum::<f64>() / pts.len() as f64;
+            // rear-most; among (near-)ties prefer the one nearest the middle
+            by_min(&|c| -c.y + (c.x - cx).abs() * 1e-3)
+        }
+        SeamStrategy::Random => (splitmix(((layer as u64) << 32) ^ loop_index as u64) % pts.len() as u64) as usize,
+        SeamStrategy::Painted => {
+            let [a, b] = settings.painted.map(|[x, y]| Coord { x, y });
+            by_min(&|c| dist2_to_segment(c, a, b))
+        }
+    }
+}
+
+/// The same closed ring, re-ordered to start (and end) at vertex `i`.
+pub fn start_ring_at(ring: &LineString<f64>, i: usize) -> LineString<f64> {
+    let mut pts = open_ring(ring).to_vec();
+    if pts.is_empty() {
+        return ring.clone();
+    }
+    pts.rotate_left(i % pts.len());
+    pts.push(pts[0]);
+    LineString::new(pts)
+}
+
+/// Ring vertices without the repeated closing point.
+fn open_ring(ring: &LineString<f64>) -> &[Coord<f64>] {
+    match ring.0.as_slice() {
+        [first, rest @ .., last] if first == last => &ring.0[..=rest.len()],
+        all => all,
+    }
+}
+
+fn dist2(a: Coord<f64>, b: Coord<f64>) -> f64 {
+    (a.x - b.x).powi(2) + (a.y - b.y).powi(2)
+}
+
+fn dist2_to_segment(p: Coord<f64>, a: Coord<f64>, b: Coord<f64>) -> f64 {
+    let ab = b - a;
+    let len2 = ab.x * ab.x + ab.y * ab.y;
+    let t = if len2 > 0.0 {
+        (((p.x - a.x) * ab.x + (p.y - a.y) * ab.y) / len2).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    dist2(p, a + ab * t)
+}
+
+fn splitmix(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    x ^ (x >> 31)
+}
+
+// ---------- retraction and travel ------------------------------------------------------------------
+
+/// How the filament is pulled back for travel moves.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetractionSettings {
+    /// Filament pulled back before a travel (mm); zero disables retraction.
+    pub length: f64,
+    /// Retract and prime speed (mm/s).
+    pub speed: f64,
+    /// Nozzle lift while retracted (mm).
+    pub z_hop: f64,
+    /// Travels shorter than this are not retracted (mm).
+    pub min_travel: f64,
+}
+
+impl Default for RetractionSettings {
+    fn default() -> Self {
+        Self {
+            length: 0.8,
+            speed: 35.0,
+            z_hop: 0.2,
+            min_travel: 1.5,
+        }
+    }
+}
+
+/// A non-extruding move between two loops.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Travel {
+    pub from: Coord<f64>,
+    pub to: Coord<f64>,
+    /// Retract (and hop) before the move, prime after it.
+    pub retract: bool,
+}
+
+/// The loops of one layer in print order, each starting at its seam, and
+/// the travels leading to them.
+#[derive(Clone, Debug, Default)]
+pub struct LayerMoves {
+    pub loops: Vec<LineString<f64>>,
+    pub travels: Vec<Travel>,
+    /// Whether each loop is an outer wall, once [`LayerMoves::mark_outer_walls`]
+    /// has run; empty before.
+    pub outer: Vec<bool>,
+}
+
+impl LayerMoves {
+    pub fn retractions(&self) -> usize {
+        self.travels.iter().filter(|t| t.retract).count()
+    }
+
+    /// Tell outer walls from inner ones: the outermost shell runs half a
+    /// line width inside the slice's `outline`, the next ones further in.
+    pub fn mark_outer_walls(&mut self, outline: &[Polygon<f64>], line_width: f64) {
+        let limit = line_width.max(0.05).powi(2);
+        let rings: Vec<&LineString<f64>> = outline.iter().flat_map(|p| std::iter::once(p.exterior()).chain(p.interiors())).collect();
+        self.outer = self
+            .loops
+            .iter()
+            .map(|ring| {
+                let Some(&p) = ring.0.first() else { return false };
+                rings.iter().flat_map(|r| r.0.windows(2)).any(|w| dist2_to_segment(p, w[0], w[1]) < limit)
+            })
+            .collect();
+    }
+}
+
+/// Order the islands of a layer nearest-first from `start`, outline before
+/// holes, and plan the travels between them. Only travels to another island
+/// cross open air, so only those are retracted, and only when longer than
+/// `min_travel`.
+pub fn plan_layer_moves(polys: &[Polygon<f64>], seam: &SeamSettings, retraction: &RetractionSettings, start: Coord<f64>, layer: usize) -> LayerMoves {
+    let mut left: Vec<&Polygon<f64>> = polys.iter().filter(|p| !open_ring(p.exterior()).is_empty()).collect();
+    let mut moves = LayerMoves::default();
+    let mut nozzle = start;
+    while !left.is_empty() {
+        let k = moves.loops.len();
+        let entry = |p: &Polygon<f64>| {
+            let ring = p.exterior();
+            ring.0[seam_index(ring, seam, nozzle, layer, k)]
+        };
+        let next = (0..left.len())
+            .min_by(|&a, &b| dist2(entry(left[a]), nozzle).total_cmp(&dist2(entry(left[b]), nozzle)))
+            .unwrap_or(0);
+        let poly = left.swap_remove(next);
+        for (n, ring) in std::iter::once(poly.exterior()).chain(poly.interiors()).enumerate() {
+            if open_ring(ring).is_empty() {
+                continue;
+            }
+            let ring = start_ring_at(ring, seam_index(ring, seam, nozzle, layer, moves.loops.len()));
+            let to = ring.0[0];
+            let length = dist2(nozzle, to).sqrt();
+            if length > 1e-9 {
+                let retract = n == 0 && retraction.length > 0.0 && length >= retraction.min_travel;
+                moves.travels.push(Travel { from: nozzle, to, retract });
+            }
+            // closed loops end where they start
+            nozzle = to;
+            moves.loops.push(ring);
+        }
+    }
+    moves
+}
+
+// ---------- part cooling ----------------------------------------------------------------------------
+
+/// Part-cooling fan schedule: off for the first layers (bed adhesion), then
+/// ramped up to `speed`, and `bridge_speed` while printing bridges.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FanSettings {
+    /// Normal fan speed (%).
+    pub speed: f32,
+    /// Layers printed with the fan off.
+    pub off_layers: u32,
+    /// Layers over which the fan ramps up to `speed` after those.
+    pub ramp_layers: u32,
+    /// Fan speed while bridging (%).
+    pub bridge_speed: f32,
+    /// Replace the fan commands of loaded extruder jobs with this schedule.
+    pub apply_to_jobs: bool,
+}
+
+impl Default for FanSettings {
+    fn default() -> Self {
+        Self {
+            speed: 100.0,
+            off_layers: 1,
+            ramp_layers: 2,
+            bridge_speed: 100.0,
+            apply_to_jobs: false,
+        }
+    }
+}
+
+impl FanSettings {
+    /// Fan speed (%) for the 0-based `layer`.
+    pub fn layer_speed(&self, layer: usize) -> f32 {
+        let Some(k) = layer.checked_sub(self.off_layers as usize) else {
+            return 0.0;
+        };
+        if k < self.ramp_layers as usize {
+            self.speed * (k + 1) as f32 / (self.ramp_layers + 1) as f32
+        } else {
+            self.speed
+        }
+    }
+}
+
+// ---------- bed adhesion ----------------------------------------------------------------------------
+
+/// Skirt, brim and raft parameters. A count / width of zero disables each.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AdhesionSettings {
+    pub skirt_loops: u32,
+    /// Gap between the part (or brim) and the first skirt loop (mm).
+    pub skirt_distance: f64,
+    pub brim_width: f64,
+    pub raft_layers: u32,
+    /// How far the raft extends past the part outline (mm).
+    pub raft_margin: f64,
+}
+
+impl Default for AdhesionSettings {
+    fn default() -> Self {
+        Self {
+            skirt_loops: 1,
+            skirt_distance: 5.0,
+            brim_width: 0.0,
+            raft_layers: 0,
+            raft_margin: 3.0,
+        }
+    }
+}
+
+/// Extra paths printed before / around the part.
+#[derive(Clone, Debug, Default)]
+pub struct Adhesion {
+    /// Skirt and brim loops, printed on the first part layer.
+    pub first_layer: Vec<LineString<f64>>,
+    /// Raft layers, bottom first, printed below the part.
+    pub raft: Vec<Vec<LineString<f64>>>,
+}
+
+/// Build skirt, brim and raft from the exterior rings of the first layer;
+/// loops and raft lines are spaced by the extrusion width `line_width`.
+pub fn adhesion(settings: &AdhesionSettings, outline: &[LineString<f64>], line_width: f64) -> Adhesion {
+    let mut out = Adhesion::default();
+    let points: Vec<Coord<f64>> = outline.iter().flat_map(|r| r.0.iter().copied()).collect();
+    if points.is_empty() {
+        return out;
+    }
+    let w = line_width.max(0.05);
+
+    // brim: concentric loops hugging each island
+    let brim_loops = (settings.brim_width / w).ceil() as u32;
+    for ring in outline {
+        for k in 0..brim_loops {
+            out.first_layer.push(offset_ring(ring, (f64::from(k) + 0.5) * w));
+        }
+    }
+
+    // skirt and raft follow the convex hull of everything
+    let hull = MultiPoint::from(points).convex_hull().exterior().clone();
+    let brim = f64::from(brim_loops) * w;
+    for k in 0..settings.skirt_loops {
+        let d = brim + settings.skirt_distance + f64::from(k) * w;
+        out.first_layer.push(offset_ring(&hull, d));
+    }
+
+    if settings.raft_layers > 0 {
+        let raft = offset_ring(&hull, settings.raft_margin);
+        for k in 0..settings.raft_layers {
+            // sparse, alternating base; dense top surface for the part to sit on
+            let top = k + 1 == settings.raft_layers;
+            let spacing = if top { w } else { 2.0 * w };
+            let mut paths = vec![raft.clone()];
+            paths.extend(hatch_convex(&raft, spacing, k % 2 == 1));
+            out.raft.push(paths);
+        }
+    }
+    out
+}
+
+/// Signed area; positive for counter-clockwise rings.
+fn ring_area(pts: &[Coord<f64>]) -> f64 {
+    let n = pts.len();
+    (0..n)
+        .map(|i| {
+            let (a, b) = (pts[i], pts[(i + 1) % n]);
+            a.x * b.y - b.x * a.y
+        })
+        .sum::<f64>()
+        * 0.5
+}
+
+/// Grow a closed ring outward by `d` (miter joins, spikes limited).
+/// Concave corners may self-intersect for large `d`; good enough for loops
+/// around the part, which is all this is used for.
+pub fn offset_ring(ring: &LineString<f64>, d: f64) -> LineString<f64> {
+    let mut pts = open_ring(ring).to_vec();
+    let n = pts.len();
+    if n < 3 {
+        return ring.clone();
+    }
+    if ring_area(&pts) < 0.0 {
+        pts.reverse();
+    }
+    let normal = |a: Coord<f64>, b: Coord<f64>| {
+        let (dx, dy) = (b.x - a.x, b.y - a.y);
+        let len = dx.hypot(dy).max(1e-12);
+        Coord { x: dy / len, y: -dx / len }
+    };
+    let mut out: Vec<Coord<f64>> = (0..n)
+        .map(|i| {
+            let prev = pts[(i + n - 1) % n];
+            let (cur, next) = (pts[i], pts[(i + 1) % n]);
+            let (n1, n2) = (normal(prev, cur), normal(cur, next));
+            let denom = (1.0 + n1.x * n2.x + n1.y * n2.y).max(0.25);
+            cur + (n1 + n2) * (d / denom)
+        })
+        .collect();
+    out.push(out[0]);
+    LineString::new(out)
+}
+
+/// Parallel fill lines across a convex ring, alternating direction so they
+/// can be printed back and forth; `vertical` rotates them by 90°.
+fn hatch_convex(ring: &LineString<f64>, spacing: f64, vertical: bool) -> Vec<LineString<f64>> {
+    // work in a frame where the lines run along x
+    let swap = |c: Coord<f64>| if vertical { Coord { x: c.y, y: c.x } } else { c };
+    let pts: Vec<Coord<f64>> = ring.0.iter().map(|&c| swap(c)).collect();
+    let (y0, y1) = pts.iter().fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), c| (lo.min(c.y), hi.max(c.y)));
+    let mut out = Vec::new();
+    let mut y = y0 + spacing * 0.5;
+    while y < y1 {
+        let xs: Vec<f64> = pts
+            .windows(2)
+            .filter(|e| (e[0].y <= y) != (e[1].y <= y))
+            .map(|e| e[0].x + (y - e[0].y) * (e[1].x - e[0].x) / (e[1].y - e[0].y))
+            .collect();
+        let (lo, hi) = xs.iter().fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), &x| (lo.min(x), hi.max(x)));
+        if lo < hi {
+            let (a, b) = if out.len() % 2 == 0 { (lo, hi) } else { (hi, lo) };
+            out.push(LineString::new(vec![swap(Coord { x: a, y }), swap(Coord { x: b, y })]));
+        }
+        y += spacing;
+    }
+    out
+}
+
+// ---------- perimeters ------------------------------------------------------------------------------
+
+/// `count` perimeter shells of a slice, outermost first. Shell `k` runs along
+/// the middle of the `k`-th line width inside the outline, so the outer
+/// wall's edge lands on the model surface. Features too thin for a shell
+/// simply lose it.
+pub fn perimeter_shells(slice: &Sketch<()>, count: i32, line_width: f64) -> Vec<Polygon<f64>> {
+    let w = line_width.max(0.05);
+    (0..count.max(0)).flat_map(|k| polygons(&slice.offset(-(f64::from(k) + 0.5) * w))).collect()
+}
+
+/// The part of a slice inside its `count` perimeter shells, left for infill.
+pub fn inside_perimeters(slice: &Sketch<()>, count: i32, line_width: f64) -> Sketch<()> {
+    if count <= 0 {
+        return slice.clone();
+    }
+    slice.offset(-f64::from(count) * line_width.max(0.05))
+}
+
+// ---------- infill ----------------------------------------------------------------------------------
+
+/// Sparse infill pattern. Linear hatches straight lines; the others are
+/// sections through triply periodic minimal surfaces, whose walls move from
+/// layer to layer and interlock into a stiff 3-D lattice.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum InfillType {
+    Linear,
+    Gyroid,
+    SchwarzP,
+    SchwarzD,
+}
+
+impl std::fmt::Display for InfillType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use InfillType::*;
+        write!(
+            f,
+            "{}",
+            match self {
+                Linear => "Linear",
+                Gyroid => "Gyroid",
+                SchwarzP => "Schwarz P",
+                SchwarzD => "Schwarz D",
+            }
+        )
+    }
+}
+
+impl InfillType {
+    /// The surface's implicit function, periodic over 2π on every axis;
+    /// walls are where it crosses zero. Linear has no surface.
+    pub fn surface(self, x: f64, y: f64, z: f64) -> Option<f64> {
+        let ((sx, cx), (sy, cy), (sz, cz)) = (x.sin_cos(), y.sin_cos(), z.sin_cos());
+        match self {
+            InfillType::Linear => None,
+            InfillType::Gyroid => Some(sx * cy + sy * cz + sz * cx),
+            InfillType::SchwarzP => Some(cx + cy + cz),
+            InfillType::SchwarzD => Some(sx * sy * sz + sx * cy * cz + cx * sy * cz + cx * cy * sz),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InfillSettings {
+    /// Solid layers under every upward-facing surface.
+    pub top_layers: u32,
+    /// Solid layers over every downward-facing surface (and the bed).
+    pub bottom_layers: u32,
+    /// Sparse infill density, percent of solid.
+    pub density: f32,
+}
+
+impl Default for InfillSettings {
+    fn default() -> Self {
+        Self {
+            top_layers: 3,
+            bottom_layers: 3,
+            density: 20.0,
+        }
+    }
+}
+
+/// Per-model replacements for the global extruder settings; `None` inherits.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SliceOverrides {
+    pub perimeters: Option<i32>,
+    pub top_layers: Option<u32>,
+    pub bottom_layers: Option<u32>,
+    pub density: Option<f32>,
+}
+
+impl SliceOverrides {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    pub fn perimeters(&self, global: i32) -> i32 {
+        self.perimeters.unwrap_or(global)
+    }
+
+    pub fn infill(&self, global: &InfillSettings) -> InfillSettings {
+        InfillSettings {
+            top_layers: self.top_layers.unwrap_or(global.top_layers),
+            bottom_layers: self.bottom_layers.unwrap_or(global.bottom_layers),
+            density: self.density.unwrap_or(global.density),
+        }
+    }
+}
+
+/// Fill paths of one layer.
+#[derive(Clone, Debug, Default)]
+pub struct Infill {
+    pub solid: Vec<LineString<f64>>,
+    pub sparse: Vec<LineString<f64>>,
+}
+
+impl Infill {
+    pub fn extend(&mut self, other: Infill) {
+        self.solid.extend(other.solid);
+        self.sparse.extend(other.sparse);
+    }
+}
+
+/// Polygons of a slice.
+pub fn polygons(sketch: &Sketch<()>) -> Vec<Polygon<f64>> {
+    sketch
+        .geometry
+        .0
+        .iter()
+        .flat_map(|g| match g {
+            Geometry::Polygon(p) => vec![p.clone()],
+            Geometry::MultiPolygon(mp) => mp.0.clone(),
+            _ => Vec::new(),
+        })
+        .collect()
+}
+
+/// `slice` with `area` added to it, overlaps merged.
+pub fn with_area(slice: &Sketch<()>, area: &MultiPolygon<f64>) -> Sketch<()> {
+    if area.0.is_empty() {
+        return slice.clone();
+    }
+    let all = MultiPolygon::new(polygons(slice)).union(area);
+    Sketch::from_geo(GeometryCollection(vec![Geometry::MultiPolygon(all)]), None)
+}
+
+/// Contour points before and after [`simplify`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SimplifyStats {
+    pub before: usize,
+    pub after: usize,
+}
+
+impl SimplifyStats {
+    pub fn removed(&self) -> usize {
+        self.before - self.after
+    }
+}
+
+/// Drop contour points that deviate less than `tolerance` (mm) from the
+/// line through their neighbours (Ramer–Douglas–Peucker). Rings that
+/// collapse below a triangle are dropped.
+pub fn simplify(slice: &Sketch<()>, tolerance: f64) -> (Sketch<()>, SimplifyStats) {
+    fn points(g: &Geometry<f64>) -> usize {
+        let poly = |p: &Polygon<f64>| p.exterior().0.len() + p.interiors().iter().map(|r| r.0.len()).sum::<usize>();
+        match g {
+            Geometry::LineString(ls) => ls.0.len(),
+            Geometry::Polygon(p) => poly(p),
+            Geometry::MultiPolygon(mp) => mp.0.iter().map(poly).sum(),
+            _ => 0,
+        }
+    }
+    fn polygon(p: &Polygon<f64>, tolerance: f64) -> Option<Polygon<f64>> {
+        let p = p.simplify(&tolerance);
+        (p.exterior().0.len() >= 4).then(|| {
+            let holes = p.interiors().iter().filter(|r| r.0.len() >= 4).cloned().collect();
+            Polygon::new(p.exterior().clone(), holes)
+        })
+    }
+
+    let before = slice.geometry.0.iter().map(points).sum();
+    if tolerance <= 0.0 {
+        return (slice.clone(), SimplifyStats { before, after: before });
+    }
+    let geometry: Vec<Geometry<f64>> = slice
+        .geometry
+        .0
+        .iter()
+        .filter_map(|g| match g {
+            Geometry::LineString(ls) => Some(Geometry::LineString(ls.simplify(&tolerance))),
+            Geometry::Polygon(p) => polygon(p, tolerance).map(Geometry::Polygon),
+            Geometry::MultiPolygon(mp) => {
+                Some(Geometry::MultiPolygon(MultiPolygon(mp.0.iter().filter_map(|p| polygon(p, tolerance)).collect())))
+            }
+            other => Some(other.clone()),
+        })
+        .collect();
+    let after = geometry.iter().map(points).sum();
+    (Sketch::from_geo(GeometryCollection(geometry), None), SimplifyStats { before, after })
+}
+
+/// Solid and sparse fill for layer `index`, sliced at height `z`. `above` /
+/// `below` are the slices of the next `top_layers` / previous
+/// `bottom_layers` layers, nearest first; fewer than requested means the
+/// stack ends there, so the layer is solid. Areas not covered by every one
+/// of them are skin and get filled solid; the rest gets `pattern`.
+#[allow(clippy::too_many_arguments)]
+pub fn infill(
+    settings: &InfillSettings,
+    pattern: InfillType,
+    line_width: f64,
+    index: usize,
+    z: f64,
+    this: &Sketch<()>,
+    above: &[Sketch<()>],
+    below: &[Sketch<()>],
+) -> Infill {
+    let w = line_width.max(0.05);
+    // alternate ±45° so consecutive layers cross
+    let angle = if index % 2 == 0 { 45.0 } else { -45.0 };
+    let fill = |s: &Sketch<()>, spacing: f64| hatch(&polygons(s), spacing, angle);
+
+    let covered = |slices: &[Sketch<()>], wanted: u32| -> Option<Sketch<()>> {
+        if slices.len() < wanted as usize {
+            return None;
+        }
+        Some(slices.iter().skip(1).fold(slices.first().cloned().unwrap_or_else(|| this.clone()), |acc, s| acc.intersection(s)))
+    };
+    let (Some(up), Some(down)) = (covered(above, settings.top_layers), covered(below, settings.bottom_layers)) else {
+        return Infill {
+            solid: fill(this, w),
+            sparse: Vec::new(),
+        };
+    };
+    let solid = this.difference(&up).union(&this.difference(&down));
+    let sparse = this.difference(&solid);
+    let density = f64::from(settings.density.clamp(0.0, 100.0)) / 100.0;
+    let sparse = if density <= 0.0 {
+        Vec::new()
+    } else if pattern == InfillType::Linear {
+        fill(&sparse, w / density)
+    } else {
+        surface_fill(pattern, &polygons(&sparse), w / density, z)
+    };
+    Infill {
+        solid: fill(&solid, w),
+        sparse,
+    }
+}
+
+/// Most grid cells along either side of the region [`surface_fill`] samples.
+const SURFACE_CELLS: f64 = 400.0;
+
+/// Walls of `pattern` at height `z`, clipped to the polygons: the zero
+/// contour of the surface function, traced with marching squares and joined
+/// into polylines. Walls are about `spacing` apart, like hatch lines.
+fn surface_fill(pattern: InfillType, polys: &[Polygon<f64>], spacing: f64, z: f64) -> Vec<LineString<f64>> {
+    let (mut lo, mut hi) = (Coord { x: f64::INFINITY, y: f64::INFINITY }, Coord { x: f64::NEG_INFINITY, y: f64::NEG_INFINITY });
+    for c in polys.iter().flat_map(|p| &p.exterior().0) {
+        lo = Coord { x: lo.x.min(c.x), y: lo.y.min(c.y) };
+        hi = Coord { x: hi.x.max(c.x), y: hi.y.max(c.y) };
+    }
+    if spacing <= 0.0 || !lo.x.is_finite() {
+        return Vec::new();
+    }
+    // two walls per period; eight samples per period unless the region is huge
+    let k = std::f64::consts::PI / spacing;
+    let step = (spacing / 4.0).max((hi.x - lo.x).max(hi.y - lo.y) / SURFACE_CELLS);
+    let (nx, ny) = (((hi.x - lo.x) / step).ceil() as usize + 1, ((hi.y - lo.y) / step).ceil() as usize + 1);
+    let at = |i: usize, j: usize| Coord { x: lo.x + i as f64 * step, y: lo.y + j as f64 * step };
+    let f = |c: Coord<f64>| pattern.surface(k * c.x, k * c.y, k * z).unwrap_or(0.0);
+    let values: Vec<f64> = (0..=ny).flat_map(|j| (0..=nx).map(move |i| (i, j))).map(|(i, j)| f(at(i, j))).collect();
+    let value = |i: usize, j: usize| values[j * (nx + 1) + i];
+
+    // one or two segments per cell; shared edges give bit-identical endpoints
+    let mut segments: Vec<[Coord<f64>; 2]> = Vec::new();
+    for j in 0..ny {
+        for i in 0..nx {
+            let corners = [(i, j), (i + 1, j), (i + 1, j + 1), (i, j + 1)];
+            let v = corners.map(|(i, j)| value(i, j));
+            // bottom, right, top, left; every edge from its lower corner
+            let edges = [(0, 1), (1, 2), (3, 2), (0, 3)];
+            let hits = edges.map(|(a, b)| {
+                ((v[a] > 0.0) != (v[b] > 0.0)).then(|| {
+                    let (p, q) = (at(corners[a].0, corners[a].1), at(corners[b].0, corners[b].1));
+                    p + (q - p) * (v[a] / (v[a] - v[b]))
+                })
+            });
+            match hits {
+                [Some(b), Some(r), Some(t), Some(l)] => {
+                    // saddle: the centre decides which corners the walls cut off
+                    let centre = f(at(i, j) + Coord { x: step * 0.5, y: step * 0.5 });
+                    if (centre > 0.0) == (v[0] > 0.0) {
+                        segments.extend([[b, r], [t, l]]);
+                    } else {
+                        segments.extend([[l, b], [r, t]]);
+                    }
+                }
+                _ => {
+                    let ends: Vec<Coord<f64>> = hits.into_iter().flatten().collect();
+                    if let [a, b] = ends[..] {
+                        segments.push([a, b]);
+                    }
+                }
+            }
+        }
+    }
+
+    let key = |c: Coord<f64>| (c.x.to_bits(), c.y.to_bits());
+    let mut at_end: HashMap<(u64, u64), Vec<usize>> = HashMap::new();
+    for (n, s) in segments.iter().enumerate() {
+        at_end.entry(key(s[0])).or_default().push(n);
+        at_end.entry(key(s[1])).or_default().push(n);
+    }
+    let mut used = vec![false; segments.len()];
+    let next = |c: Coord<f64>, used: &mut [bool]| -> Option<Coord<f64>> {
+        let n = *at_end.get(&key(c))?.iter().find(|&&n| !used[n])?;
+        used[n] = true;
+        let s = segments[n];
+        Some(if key(s[0]) == key(c) { s[1] } else { s[0] })
+    };
+    let mut lines = Vec::new();
+    for n in 0..segments.len() {
+        if used[n] {
+            continue;
+        }
+        used[n] = true;
+        let mut forward = segments[n].to_vec();
+        while let Some(c) = next(forward[forward.len() - 1], &mut used) {
+            forward.push(c);
+        }
+        let mut line: Vec<Coord<f64>> = Vec::new();
+        while let Some(c) = next(line.last().copied().unwrap_or(forward[0]), &mut used) {
+            line.push(c);
+        }
+        line.reverse();
+        line.extend(forward);
+        lines.push(LineString::new(line));
+    }
+    MultiPolygon(polys.to_vec()).clip(&MultiLineString(lines), false).0
+}
+
+/// Parallel lines at `angle_deg` spaced `spacing` apart, clipped to the
+/// polygons with the even-odd rule (so holes stay empty). Consecutive
+/// lines alternate direction.
+pub fn hatch(polys: &[Polygon<f64>], spacing: f64, angle_deg: f64) -> Vec<LineString<f64>> {
+    let (sin, cos) = angle_deg.to_radians().sin_cos();
+    // rotate into a frame where the lines are horizontal, and back again
+    let to_local = |c: Coord<f64>| Coord { x: c.x * cos + c.y * sin, y: -c.x * sin + c.y * cos };
+    let to_world = |c: Coord<f64>| Coord { x: c.x * cos - c.y * sin, y: c.x * sin + c.y * cos };
+
+    let rings: Vec<Vec<Coord<f64>>> = polys
+        .iter()
+        .flat_map(|p| std::iter::once(p.exterior()).chain(p.interiors()))
+        .map(|r| r.0.iter().map(|&c| to_local(c)).collect())
+        .collect();
+    let (y0, y1) = rings
+        .iter()
+        .flatten()
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), c| (lo.min(c.y), hi.max(c.y)));
+    if spacing <= 0.0 || !y0.is_finite() {
+        return Vec::new();
+    }
+
+    let mut out = Vec::new();
+    let mut flip = false;
+    let mut y = (y0 / spacing).ceil() * spacing;
+    while y < y1 {
+        let mut xs: Vec<f64> = rings
+            .iter()
+            .flat_map(|r| r.windows(2))
+            .filter(|e| (e[0].y <= y) != (e[1].y <= y))
+            .map(|e| e[0].x + (y - e[0].y) * (e[1].x - e[0].x) / (e[1].y - e[0].y))
+            .collect();
+        xs.sort_by(f64::total_cmp);
+        let mut row: Vec<LineString<f64>> = xs
+            .chunks_exact(2)
+            .map(|p| LineString::new(vec![to_world(Coord { x: p[0], y }), to_world(Coord { x: p[1], y })]))
+            .collect();
+        if flip {
+            row.reverse();
+            for ls in &mut row {
+                ls.0.reverse();
+            }
+        }
+        flip = !flip;
+        out.extend(row);
+        y += spacing;
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn heights(layers: &[Layer]) -> Vec<f32> {
+        layers.iter().map(|l| l.height).collect()
+    }
+
+    fn contiguous(layers: &[Layer], z_start: f32, z_end: f32) -> bool {
+        let first = layers.first().is_some_and(|l| l.z == z_start);
+        let stacked = layers.windows(2).all(|w| (w[0].top() - w[1].z).abs() < 1e-5);
+        first && stacked && layers.last().is_some_and(|l| (l.top() - z_end).abs() < 1e-4)
+    }
+
+    #[test]
+    fn plan_layers_fixed_height_trims_the_last_layer() {
+        let settings = LayerSettings { base_height: 0.25, ..LayerSettings::default() };
+        let layers = plan_layers(&settings, [], 0.0, 1.125);
+        assert_eq!(heights(&layers), [0.25, 0.25, 0.25, 0.25, 0.125]);
+        assert!(contiguous(&layers, 0.0, 1.125));
+    }
+
+    #[test]
+    fn plan_layers_ranges_take_their_height_even_below_the_minimum() {
+        let settings = LayerSettings {
+            base_height: 0.25,
+            ranges: vec![LayerRange { z_min: 0.5, z_max: 0.75, height: 0.0625 }],
+            ..LayerSettings::default()
+        };
+        let layers = plan_layers(&settings, [], 0.0, 1.0);
+        assert_eq!(heights(&layers), [0.25, 0.25, 0.0625, 0.0625, 0.0625, 0.0625, 0.25]);
+        assert!(contiguous(&layers, 0.0, 1.0));
+    }
+
+    #[test]
+    fn plan_layers_adaptive_thins_flat_faces_and_thickens_walls() {
+        let settings = LayerSettings { adaptive: true, ..LayerSettings::default() };
+        let cube = Mesh::cube(5.0, None);
+        let (lo, hi) = z_extent([&cube]).unwrap();
+        let layers = plan_layers(&settings, [&cube], lo, hi);
+        assert!(contiguous(&layers, lo, hi));
+        let (min, max) = (settings.min_height, settings.max_height);
+        assert!(layers[..layers.len() - 1].iter().all(|l| l.height >= min - 1e-6 && l.height <= max + 1e-6));
+        // the flat bottom face holds the first layer to the minimum, the walls allow the maximum
+        assert!((layers[0].height - min).abs() < 1e-6);
+        assert!(layers.iter().any(|l| (l.height - max).abs() < 1e-6));
+    }
+}
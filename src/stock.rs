@@ -0,0 +1,270 @@
+//! The raw material a job is cut from: a sheet for the laser, plasma and
+//! drill, or a billet for the endmill.
+//!
+//! Stock is a box on the bed rather than one of the loaded models, so it is
+//! never sliced as a part. Milling clears it down to the parts and never
+//! below its bottom; the 2-D tools check that the parts fit on the sheet.
+//! It is saved with the other job settings, as are the fixtures holding it
+//! (see [`crate::collision`]), which are edited and drawn here too.
+
+use csgrs::{mesh::Mesh, sketch::Sketch, traits::CSG};
+use nalgebra::Vector3;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    AluminaApp, Tool,
+    collision::{Fixture, FixtureKind},
+    load_mesh_from_bytes, spawn_file_picker, toasts,
+};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) enum StockKind {
+    #[default]
+    Sheet,
+    Billet,
+}
+
+impl std::fmt::Display for StockKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            StockKind::Sheet => "Sheet",
+            StockKind::Billet => "Billet",
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) struct Stock {
+    pub material: String,
+    pub kind: StockKind,
+    /// Width (X), length (Y) and thickness (Z) in mm.
+    pub size: Vector3<f32>,
+    /// Front-left-bottom corner on the bed (mm).
+    pub origin: Vector3<f32>,
+}
+
+impl Default for Stock {
+    fn default() -> Self {
+        Self {
+            material: String::new(),
+            kind: StockKind::Sheet,
+            size: Vector3::new(300.0, 200.0, 3.0),
+            origin: Vector3::new(-150.0, -100.0, 0.0),
+        }
+    }
+}
+
+impl Stock {
+    /// A billet just enclosing `mesh` (a workpiece loaded from a file).
+    pub(crate) fn around(mesh: &Mesh<()>) -> Self {
+        let bb = mesh.bounding_box();
+        Self {
+            kind: StockKind::Billet,
+            size: (bb.maxs - bb.mins).cast::<f32>(),
+            origin: bb.mins.coords.cast::<f32>(),
+            ..Self::default()
+        }
+    }
+
+    pub(crate) fn mesh(&self) -> Mesh<()> {
+        Mesh::cuboid(self.size.x.into(), self.size.y.into(), self.size.z.into(), None).translate(
+            self.origin.x.into(),
+            self.origin.y.into(),
+            self.origin.z.into(),
+        )
+    }
+
+    /// Whether `sketch` lies on the stock in XY.
+    pub(crate) fn holds(&self, sketch: &Sketch<()>) -> bool {
+        let bb = sketch.bounding_box();
+        let (lo, hi) = (self.origin, self.origin + self.size);
+        bb.mins.x >= f64::from(lo.x) - 1e-6
+            && bb.mins.y >= f64::from(lo.y) - 1e-6
+            && bb.maxs.x <= f64::from(hi.x) + 1e-6
+            && bb.maxs.y <= f64::from(hi.y) + 1e-6
+    }
+
+    /// The twelve edges of the box as line-list vertices (xyz rgb).
+    pub(crate) fn push_outline(&self, col: [f32; 3], out: &mut Vec<f32>) {
+        push_box(self.origin, self.origin + self.size, col, out);
+    }
+}
+
+/// The twelve edges of the box from `lo` to `hi` as line-list vertices.
+pub(crate) fn push_box(lo: Vector3<f32>, hi: Vector3<f32>, col: [f32; 3], out: &mut Vec<f32>) {
+    let corner = |i: usize| {
+        Vector3::new(
+            if i & 1 == 0 { lo.x } else { hi.x },
+            if i & 2 == 0 { lo.y } else { hi.y },
+            if i & 4 == 0 { lo.z } else { hi.z },
+        )
+    };
+    // corners differing in exactly one bit share an edge
+    for a in 0..8 {
+        for bit in [1, 2, 4] {
+            if a & bit == 0 {
+                let (p, q) = (corner(a), corner(a | bit));
+                out.extend_from_slice(&[p.x, p.y, p.z, col[0], col[1], col[2], q.x, q.y, q.z, col[0], col[1], col[2]]);
+            }
+        }
+    }
+}
+
+/// Outline of a fixture, coloured by kind: a box, or a cylinder for a pin.
+pub(crate) fn push_fixture(fixture: &Fixture, out: &mut Vec<f32>) {
+    let col = match fixture.kind {
+        FixtureKind::Clamp => [0.9, 0.2, 0.2],
+        FixtureKind::ViseJaw => [0.7, 0.3, 0.85],
+        FixtureKind::Pin => [0.2, 0.8, 0.8],
+    };
+    let (lo, hi) = (fixture.min.map(|v| v as f32), fixture.max.map(|v| v as f32));
+    if fixture.kind != FixtureKind::Pin {
+        push_box(Vector3::from(lo), Vector3::from(hi), col, out);
+        return;
+    }
+    let ring = fixture.footprint(0.0);
+    let points: Vec<[f32; 2]> = ring.exterior().0.iter().map(|c| [c.x as f32, c.y as f32]).collect();
+    for (k, w) in points.windows(2).enumerate() {
+        let ([x0, y0], [x1, y1]) = (w[0], w[1]);
+        for z in [lo[2], hi[2]] {
+            out.extend_from_slice(&[x0, y0, z, col[0], col[1], col[2], x1, y1, z, col[0], col[1], col[2]]);
+        }
+        if k % 6 == 0 {
+            out.extend_from_slice(&[x0, y0, lo[2], col[0], col[1], col[2], x0, y0, hi[2], col[0], col[1], col[2]]);
+        }
+    }
+}
+
+impl AluminaApp {
+    /// Stock editor in the Models panel.
+    pub(crate) fn stock_ui(&mut self, ui: &mut egui::Ui) {
+        let before = self.stock.clone();
+        let mut on = self.stock.is_some();
+        ui.checkbox(&mut on, "Cut from stock")
+            .on_hover_text("Raw material: the milling starting block, and the sheet the 2-D parts must fit on");
+        if on != self.stock.is_some() {
+            self.stock = on.then(Stock::default);
+        }
+        if let Some(stock) = &mut self.stock {
+            egui::Grid::new("stock").num_columns(2).show(ui, |ui| {
+                ui.label("Material:");
+                ui.add(egui::TextEdit::singleline(&mut stock.material).hint_text("e.g. 6061 aluminium").desired_width(140.0));
+                ui.end_row();
+
+                ui.label("Form:");
+                ui.horizontal(|ui| {
+                    for kind in [StockKind::Sheet, StockKind::Billet] {
+                        ui.selectable_value(&mut stock.kind, kind, kind.to_string());
+                    }
+                });
+                ui.end_row();
+
+                ui.label("Size (mm):");
+                ui.horizontal(|ui| {
+                    ui.add(egui::DragValue::new(&mut stock.size.x).speed(1.0).range(0.1..=10_000.0).prefix("W "));
+                    ui.add(egui::DragValue::new(&mut stock.size.y).speed(1.0).range(0.1..=10_000.0).prefix("L "));
+                });
+                ui.end_row();
+
+                ui.label(if stock.kind == StockKind::Sheet { "Thickness (mm):" } else { "Height (mm):" });
+                ui.add(egui::DragValue::new(&mut stock.size.z).speed(0.1).range(0.01..=1_000.0));
+                ui.end_row();
+
+                ui.label("Corner (mm):");
+                ui.horizontal(|ui| {
+                    ui.add(egui::DragValue::new(&mut stock.origin.x).speed(1.0).prefix("X "));
+                    ui.add(egui::DragValue::new(&mut stock.origin.y).speed(1.0).prefix("Y "));
+                    ui.add(egui::DragValue::new(&mut stock.origin.z).speed(0.1).prefix("Z "));
+                });
+                ui.end_row();
+            });
+        }
+        if ui.button("From mesh…").on_hover_text("Size the stock to a workpiece file (stl, dxf)").clicked() {
+            spawn_file_picker(std::sync::Arc::clone(&self.workpiece_data), "Workpiece mesh (stl,dxf)", &["stl", "dxf"]);
+        }
+
+        if self.stock != before {
+            self.mill_results = None;
+        }
+    }
+
+    /// Fixture list in the Models panel: add, place and remove clamps, vise
+    /// jaws and pins.
+    pub(crate) fn fixtures_ui(&mut self, ui: &mut egui::Ui) {
+        let before = self.fixtures.clone();
+        let mut remove = None;
+        for (k, f) in self.fixtures.iter_mut().enumerate() {
+            ui.push_id(k, |ui| {
+                ui.horizontal(|ui| {
+                    egui::ComboBox::from_id_salt("fixture_kind")
+                        .selected_text(f.kind.to_string())
+                        .width(80.0)
+                        .show_ui(ui, |ui| {
+                            for kind in FixtureKind::ALL {
+                                ui.selectable_value(&mut f.kind, kind, kind.to_string());
+                            }
+                        });
+                    ui.add(egui::TextEdit::singleline(&mut f.name).desired_width(80.0));
+                    if ui.small_button("✖").on_hover_text("Remove this fixture").clicked() {
+                        remove = Some(k);
+                    }
+                });
+                for (label, corner) in [("From:", &mut f.min), ("To:", &mut f.max)] {
+                    ui.horizontal(|ui| {
+                        ui.label(label);
+                        for (v, axis) in corner.iter_mut().zip(["X ", "Y ", "Z "]) {
+                            ui.add(egui::DragValue::new(v).speed(0.5).prefix(axis));
+                        }
+                    });
+                }
+            });
+        }
+        if let Some(k) = remove {
+            self.fixtures.remove(k);
+        }
+        ui.horizontal(|ui| {
+            ui.label("Add:");
+            for kind in FixtureKind::ALL {
+                if ui.small_button(kind.to_string()).clicked() {
+                    let n = self.fixtures.iter().filter(|f| f.kind == kind).count() + 1;
+                    self.fixtures.push(Fixture::new(kind, format!("{kind} {n}")));
+                }
+            }
+        });
+        if !self.fixtures.is_empty() {
+            ui.weak("Toolpaths keep a tool radius clear of fixtures; a pin stands in the middle of its box.");
+        }
+
+        if self.fixtures != before {
+            self.mill_results = None;
+            self.mill_sim = None;
+            self.cam_paths = None;
+        }
+    }
+
+    /// Replace the stock with a billet around a loaded workpiece file.
+    pub(crate) fn load_stock(&mut self, bytes: &[u8]) {
+        let Some(mesh) = load_mesh_from_bytes(bytes) else {
+            log::error!("Could not parse workpiece file");
+            toasts::error("Could not load the workpiece: unsupported or corrupt file", None);
+            return;
+        };
+        let material = self.stock.take().map(|s| s.material).unwrap_or_default();
+        self.stock = Some(Stock { material, ..Stock::around(&mesh.float()) });
+        self.mill_results = None;
+        log::info!("[alumina] workpiece loaded ({} bytes)", bytes.len());
+    }
+
+    /// Warnings for the 2-D tools: parts off the sheet, or a drill too
+    /// short to go through it.
+    pub(crate) fn stock_fit_ui(&self, ui: &mut egui::Ui) {
+        let Some(stock) = &self.stock else { return };
+        let warn = ui.visuals().warn_fg_color;
+        if self.sliced_layer.as_ref().is_some_and(|slice| !stock.holds(slice)) {
+            ui.colored_label(warn, "⚠ The parts extend past the stock sheet");
+        }
+        if self.selected_tool == Tool::Drill && self.drill_length < stock.size.z {
+            ui.colored_label(warn, format!("⚠ The drill is shorter than the {:.1} mm stock is thick", stock.size.z));
+        }
+    }
+}
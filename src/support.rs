@@ -0,0 +1,418 @@
+//! Support structures under overhangs.
+//!
+//! Downward-facing facets steeper than the overhang angle are grouped into
+//! connected regions. Each region gets contact points on a regular XY grid;
+//! grid supports drop a column from every contact, tree supports gather them
+//! into one trunk. Regions can be switched off individually from the
+//! viewport, and the slicer asks for the cross-section at each layer.
+
+use std::collections::HashMap;
+
+use csgrs::mesh::Mesh;
+use geo::{BooleanOps, Coord, LineString, MultiPolygon, Polygon};
+use nalgebra::{Vector2, Vector3};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SupportStyle {
+    Grid,
+    Tree,
+}
+
+impl std::fmt::Display for SupportStyle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            SupportStyle::Grid => "Grid",
+            SupportStyle::Tree => "Tree",
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SupportSettings {
+    pub enabled: bool,
+    pub style: SupportStyle,
+    /// Overhangs flatter than this, measured from vertical, are supported (degrees).
+    pub overhang_angle: f32,
+    /// Share of the area under an overhang that carries a column (percent).
+    pub density: f32,
+    /// Dense layers directly under the part.
+    pub interface_layers: u32,
+    /// Vertical air gap between support and part (mm).
+    pub z_gap: f32,
+}
+
+impl Default for SupportSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            style: SupportStyle::Grid,
+            overhang_angle: 50.0,
+            density: 15.0,
+            interface_layers: 2,
+            z_gap: 0.2,
+        }
+    }
+}
+
+/// Where a column meets the part.
+#[derive(Clone, Copy, Debug)]
+struct Contact {
+    xy: Vector2<f32>,
+    /// Top of the support (below the part by the Z gap).
+    top: f32,
+    /// Bottom: the bed or the part surface underneath.
+    base: f32,
+}
+
+/// A single trunk with one branch per contact.
+#[derive(Clone, Debug)]
+struct Trunk {
+    xy: Vector2<f32>,
+    base: f32,
+    /// Height where the branches leave the trunk.
+    fork: f32,
+}
+
+/// Supports for one connected overhang.
+#[derive(Clone, Debug)]
+pub struct SupportRegion {
+    pub enabled: bool,
+    /// Centre of the overhang; the pick handle in the viewport.
+    pub anchor: Vector3<f32>,
+    contacts: Vec<Contact>,
+    trunk: Option<Trunk>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Supports {
+    pub regions: Vec<SupportRegion>,
+}
+
+/// One triangle with its plane normal.
+#[derive(Clone, Copy)]
+struct Tri {
+    v: [Vector3<f32>; 3],
+    n: Vector3<f32>,
+}
+
+impl Tri {
+    /// Z of the triangle's plane at `p`, if `p` lies inside it in XY.
+    fn z_at(&self, p: Vector2<f32>) -> Option<f32> {
+        let [a, b, c] = self.v;
+        let det = (b.y - c.y) * (a.x - c.x) + (c.x - b.x) * (a.y - c.y);
+        if det.abs() < 1e-9 {
+            return None;
+        }
+        let l1 = ((b.y - c.y) * (p.x - c.x) + (c.x - b.x) * (p.y - c.y)) / det;
+        let l2 = ((c.y - a.y) * (p.x - c.x) + (a.x - c.x) * (p.y - c.y)) / det;
+        let l3 = 1.0 - l1 - l2;
+        (l1 >= -1e-5 && l2 >= -1e-5 && l3 >= -1e-5).then(|| l1 * a.z + l2 * b.z + l3 * c.z)
+    }
+}
+
+fn triangles<'a>(meshes: impl IntoIterator<Item = &'a Mesh<()>>) -> Vec<Tri> {
+    let mut out = Vec::new();
+    for mesh in meshes {
+        for p in &mesh.polygons {
+            let n = p.plane.normal();
+            let n = Vector3::new(n.x as f32, n.y as f32, n.z as f32).normalize();
+            let pts: Vec<Vector3<f32>> = p
+                .vertices
+                .iter()
+                .map(|v| Vector3::new(v.pos.x as f32, v.pos.y as f32, v.pos.z as f32))
+                .collect();
+            for i in 1..pts.len().saturating_sub(1) {
+                out.push(Tri { v: [pts[0], pts[i], pts[i + 1]], n });
+            }
+        }
+    }
+    out
+}
+
+/// Union-find root with path halving.
+fn root(parent: &mut [usize], mut i: usize) -> usize {
+    while parent[i] != i {
+        parent[i] = parent[parent[i]];
+        i = parent[i];
+    }
+    i
+}
+
+/// Detect overhangs and build supports. `z_floor` is the bed height,
+/// `line_width` the extrusion width (sets the column pitch with `density`).
+pub fn generate<'a>(settings: &SupportSettings, meshes: impl IntoIterator<Item = &'a Mesh<()>>, z_floor: f32, line_width: f32) -> Supports {
+    let tris = triangles(meshes);
+    let cos_limit = settings.overhang_angle.to_radians().cos();
+    let overhangs: Vec<usize> = (0..tris.len())
+        .filter(|&i| {
+            let t = &tris[i];
+            let z_lo = t.v.iter().map(|v| v.z).fold(f32::INFINITY, f32::min);
+            -t.n.z > cos_limit && z_lo > z_floor + 1e-3
+        })
+        .collect();
+
+    // group overhang triangles sharing a vertex (union-find on quantised keys)
+    let key = |v: &Vector3<f32>| ((v.x * 1000.0) as i64, (v.y * 1000.0) as i64, (v.z * 1000.0) as i64);
+    let mut parent: Vec<usize> = (0..overhangs.len()).collect();
+    let mut owner: HashMap<(i64, i64, i64), usize> = HashMap::new();
+    for (k, &t) in overhangs.iter().enumerate() {
+        for v in &tris[t].v {
+            if let Some(&other) = owner.get(&key(v)) {
+                let (a, b) = (root(&mut parent, k), root(&mut parent, other));
+                parent[a] = b;
+            } else {
+                owner.insert(key(v), k);
+            }
+        }
+    }
+    let mut groups: HashMap<usize, Vec<usize>> = HashMap::new();
+    for (k, &t) in overhangs.iter().enumerate() {
+        let r = root(&mut parent, k);
+        groups.entry(r).or_default().push(t);
+    }
+
+    let pitch = 2.0 * line_width.max(0.1) * 100.0 / settings.density.clamp(1.0, 100.0);
+    let base_at = |xy: Vector2<f32>, below: f32| {
+        // highest upward-facing surface under the point, else the bed
+        tris.iter()
+            .filter(|t| t.n.z > 0.0)
+            .filter_map(|t| t.z_at(xy))
+            .filter(|&z| z < below - 1e-3)
+            .fold(z_floor, f32::max)
+    };
+
+    let mut regions: Vec<SupportRegion> = groups
+        .into_values()
+        .map(|members| {
+            let region: Vec<Tri> = members.iter().map(|&i| tris[i]).collect();
+            let (lo, hi) = region.iter().flat_map(|t| t.v).fold(
+                (Vector2::repeat(f32::INFINITY), Vector2::repeat(f32::NEG_INFINITY)),
+                |(lo, hi), v| (lo.inf(&v.xy()), hi.sup(&v.xy())),
+            );
+            let centroid = region.iter().map(|t| (t.v[0] + t.v[1] + t.v[2]) / 3.0).sum::<Vector3<f32>>() / region.len() as f32;
+
+            // contacts on a world-aligned grid so neighbouring regions line up
+            let mut points = Vec::new();
+            let (mut x, y0) = ((lo.x / pitch).ceil() * pitch, (lo.y / pitch).ceil() * pitch);
+            while x <= hi.x {
+                let mut y = y0;
+                while y <= hi.y {
+                    points.push(Vector2::new(x, y));
+                    y += pitch;
+                }
+                x += pitch;
+            }
+            let contact_at = |xy: Vector2<f32>| {
+                let z = region.iter().filter_map(|t| t.z_at(xy)).fold(f32::INFINITY, f32::min);
+                let c = Contact {
+                    xy,
+                    top: z - settings.z_gap,
+                    base: base_at(xy, z),
+                };
+                (z.is_finite() && c.top > c.base).then_some(c)
+            };
+            let mut contacts: Vec<Contact> = points.into_iter().filter_map(&contact_at).collect();
+            if contacts.is_empty() {
+                // small islands between grid nodes still get one column
+                contacts.extend(contact_at(centroid.xy()));
+            }
+
+            let trunk = (settings.style == SupportStyle::Tree && !contacts.is_empty()).then(|| {
+                let xy = contacts.iter().map(|c| c.xy).sum::<Vector2<f32>>() / contacts.len() as f32;
+                let lowest = contacts.iter().map(|c| c.top).fold(f32::INFINITY, f32::min);
+                let base = base_at(xy, lowest);
+                // branches leave at 45°, so the fork sits one reach below the lowest contact
+                let reach = contacts.iter().map(|c| (c.xy - xy).norm()).fold(0.0, f32::max);
+                Trunk {
+                    xy,
+                    base,
+                    fork: (lowest - reach).max(base),
+                }
+            });
+
+            SupportRegion {
+                enabled: true,
+                anchor: centroid,
+                contacts,
+                trunk,
+            }
+        })
+        .filter(|r| !r.contacts.is_empty())
+        .collect();
+    regions.sort_by(|a, b| a.anchor.z.total_cmp(&b.anchor.z));
+    Supports { regions }
+}
+
+impl Supports {
+    /// Line segments for the 3-D preview: `(from, to, enabled)`.
+    pub fn segments(&self) -> Vec<(Vector3<f32>, Vector3<f32>, bool)> {
+        let mut out = Vec::new();
+        for r in &self.regions {
+            match &r.trunk {
+                Some(t) => {
+                    let fork = Vector3::new(t.xy.x, t.xy.y, t.fork);
+                    out.push((Vector3::new(t.xy.x, t.xy.y, t.base), fork, r.enabled));
+                    for c in &r.contacts {
+                        out.push((fork, Vector3::new(c.xy.x, c.xy.y, c.top), r.enabled));
+                    }
+                }
+                None => {
+                    for c in &r.contacts {
+                        out.push((Vector3::new(c.xy.x, c.xy.y, c.base), Vector3::new(c.xy.x, c.xy.y, c.top), r.enabled));
+                    }
+                }
+            }
+        }
+        out
+    }
+
+    /// Switch region `index` on or off; returns its new state.
+    pub fn toggle(&mut self, index: usize) -> Option<bool> {
+        let r = self.regions.get_mut(index)?;
+        r.enabled = !r.enabled;
+        Some(r.enabled)
+    }
+
+    /// Support paths for the layer `[z, z + height)` of enabled regions.
+    pub fn layer_paths(&self, settings: &SupportSettings, z: f32, height: f32, line_width: f32) -> Vec<LineString<f64>> {
+        let mid = z + height * 0.5;
+        let interface = settings.interface_layers as f32 * height;
+        let w = line_width.max(0.1);
+        let mut out = Vec::new();
+        for r in self.regions.iter().filter(|r| r.enabled) {
+            let active: Vec<&Contact> = r.contacts.iter().filter(|c| mid <= c.top && mid >= c.base).collect();
+
+            // interface: dense lines just under the part
+            let near_top: Vec<&Contact> = active.iter().copied().filter(|c| c.top - mid < interface).collect();
+            if !near_top.is_empty() {
+                out.extend(interface_fill(&near_top, w));
+            }
+
+            match &r.trunk {
+                Some(t) => {
+                    if mid >= t.base && mid <= t.fork {
+                        out.push(square(t.xy, w * 2.0));
+                    }
+                    for c in &active {
+                        // branch from fork to contact, 45° or steeper
+                        if mid > t.fork && c.top > t.fork {
+                            let f = ((mid - t.fork) / (c.top - t.fork)).clamp(0.0, 1.0);
+                            out.push(square(t.xy + (c.xy - t.xy) * f, w));
+                        }
+                    }
+                }
+                None => {
+                    let below: Vec<&Contact> = active.iter().copied().filter(|c| c.top - mid >= interface).collect();
+                    out.extend(grid(&below, w));
+                }
+            }
+        }
+        out
+    }
+}
+
+impl Supports {
+    /// The paths of [`Self::layer_paths`] as solid area a line width wide,
+    /// for resin masks; closed squares are filled.
+    pub fn layer_area(&self, settings: &SupportSettings, z: f32, height: f32, line_width: f32) -> MultiPolygon<f64> {
+        let h = f64::from(line_width.max(0.1)) * 0.5;
+        let mut area = MultiPolygon::new(Vec::new());
+        for path in self.layer_paths(settings, z, height, line_width) {
+            let mut parts: Vec<Polygon<f64>> = path
+                .lines()
+                .filter(|l| l.start != l.end)
+                .map(|l| {
+                    // the strip around the segment, ends included
+                    let (dx, dy) = (l.end.x - l.start.x, l.end.y - l.start.y);
+                    let len = dx.hypot(dy);
+                    let (ux, uy) = (dx / len * h, dy / len * h);
+                    let c = |x: f64, y: f64| Coord { x, y };
+                    Polygon::new(
+                        LineString::new(vec![
+                            c(l.start.x - ux + uy, l.start.y - uy - ux),
+                            c(l.end.x + ux + uy, l.end.y + uy - ux),
+                            c(l.end.x + ux - uy, l.end.y + uy + ux),
+                            c(l.start.x - ux - uy, l.start.y - uy + ux),
+                            c(l.start.x - ux + uy, l.start.y - uy - ux),
+                        ]),
+                        Vec::new(),
+                    )
+                })
+                .collect();
+            if path.is_closed() && path.0.len() >= 4 {
+                parts.push(Polygon::new(path, Vec::new()));
+            }
+            for p in parts {
+                area = area.union(&MultiPolygon::new(vec![p]));
+            }
+        }
+        area
+    }
+}
+
+/// Solid fill over the contacts' bounding box, one line width apart.
+fn interface_fill(contacts: &[&Contact], w: f32) -> Vec<LineString<f64>> {
+    let (lo, hi) = contacts.iter().fold(
+        (Vector2::repeat(f32::INFINITY), Vector2::repeat(f32::NEG_INFINITY)),
+        |(lo, hi), c| (lo.inf(&c.xy), hi.sup(&c.xy)),
+    );
+    let (lo, hi) = (lo.add_scalar(-w), hi.add_scalar(w));
+    let rect = geo::Polygon::new(
+        LineString::from(vec![
+            (f64::from(lo.x), f64::from(lo.y)),
+            (f64::from(hi.x), f64::from(lo.y)),
+            (f64::from(hi.x), f64::from(hi.y)),
+            (f64::from(lo.x), f64::from(hi.y)),
+            (f64::from(lo.x), f64::from(lo.y)),
+        ]),
+        Vec::new(),
+    );
+    crate::slicer::hatch(&[rect], f64::from(w), 0.0)
+}
+
+/// Lines joining contacts that share a grid row or column; isolated
+/// contacts become small squares.
+fn grid(contacts: &[&Contact], w: f32) -> Vec<LineString<f64>> {
+    let q = |v: f32| (v * 100.0).round() as i64;
+    let mut by_y: HashMap<i64, (f32, f32, f32)> = HashMap::new();
+    let mut by_x: HashMap<i64, (f32, f32, f32)> = HashMap::new();
+    for c in contacts {
+        let e = by_y.entry(q(c.xy.y)).or_insert((c.xy.x, c.xy.x, c.xy.y));
+        e.0 = e.0.min(c.xy.x);
+        e.1 = e.1.max(c.xy.x);
+        let e = by_x.entry(q(c.xy.x)).or_insert((c.xy.y, c.xy.y, c.xy.x));
+        e.0 = e.0.min(c.xy.y);
+        e.1 = e.1.max(c.xy.y);
+    }
+    let seg = |a: (f32, f32), b: (f32, f32)| {
+        LineString::from(vec![(f64::from(a.0), f64::from(a.1)), (f64::from(b.0), f64::from(b.1))])
+    };
+    let mut out = Vec::new();
+    for &(x0, x1, y) in by_y.values() {
+        if x1 - x0 < 1e-3 {
+            out.push(square(Vector2::new(x0, y), w));
+        } else {
+            out.push(seg((x0, y), (x1, y)));
+        }
+    }
+    for &(y0, y1, x) in by_x.values().filter(|(y0, y1, _)| y1 - y0 >= 1e-3) {
+        out.push(seg((x, y0), (x, y1)));
+    }
+    out
+}
+
+/// Closed square of side `size` around `c`.
+fn square(c: Vector2<f32>, size: f32) -> LineString<f64> {
+    let h = size * 0.5;
+    LineString::new(
+        [(-h, -h), (h, -h), (h, h), (-h, h), (-h, -h)]
+            .iter()
+            .map(|&(dx, dy)| Coord {
+                x: f64::from(c.x + dx),
+                y: f64::from(c.y + dy),
+            })
+            .collect(),
+    )
+}
@@ -0,0 +1,151 @@
+//! Non-blocking notifications in the corner of the window.
+//!
+//! Anything (including async tasks) can raise a toast with [`info`], [`warn`]
+//! or [`error`]; the app collects them once per frame, copies them into the
+//! diagnostics console and stacks them bottom-right. Toasts fade out on
+//! their own (errors stay longer) unless hovered; "details" jumps to the
+//! console, where the full text was logged.
+
+use std::sync::Mutex;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    fn color(self) -> egui::Color32 {
+        match self {
+            Severity::Info => egui::Color32::from_rgb(110, 200, 255),
+            Severity::Warning => egui::Color32::from_rgb(240, 190, 60),
+            Severity::Error => egui::Color32::from_rgb(240, 90, 90),
+        }
+    }
+
+    fn icon(self) -> &'static str {
+        match self {
+            Severity::Info => "ℹ",
+            Severity::Warning => "⚠",
+            Severity::Error => "✖",
+        }
+    }
+
+    fn lifetime_ms(self) -> f64 {
+        match self {
+            Severity::Info => 4000.0,
+            Severity::Warning => 8000.0,
+            Severity::Error => 15000.0,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Toast {
+    severity: Severity,
+    text: String,
+    /// Longer explanation, written to the console only.
+    details: Option<String>,
+    expires_ms: f64,
+}
+
+/// Shared so tasks on worker threads (desktop build) reach the UI too.
+static RAISED: Mutex<Vec<Toast>> = Mutex::new(Vec::new());
+
+fn raise(severity: Severity, text: impl Into<String>, details: Option<String>) {
+    let toast = Toast {
+        severity,
+        text: text.into(),
+        details,
+        expires_ms: 0.0,
+    };
+    RAISED.lock().unwrap().push(toast);
+}
+
+pub fn info(text: impl Into<String>) {
+    raise(Severity::Info, text, None);
+}
+
+pub fn warn(text: impl Into<String>, details: Option<String>) {
+    raise(Severity::Warning, text, details);
+}
+
+pub fn error(text: impl Into<String>, details: Option<String>) {
+    raise(Severity::Error, text, details);
+}
+
+/// At most this many toasts are on screen; older ones are dropped.
+const MAX_VISIBLE: usize = 5;
+
+#[derive(Default)]
+pub struct Toasts {
+    visible: Vec<Toast>,
+}
+
+impl Toasts {
+    /// Take toasts raised since the last frame. Returns their console lines.
+    pub fn collect(&mut self, now_ms: f64) -> Vec<String> {
+        let raised = std::mem::take(&mut *RAISED.lock().unwrap());
+        let mut lines = Vec::with_capacity(raised.len());
+        for mut t in raised {
+            let level = match t.severity {
+                Severity::Info => "info",
+                Severity::Warning => "warning",
+                Severity::Error => "error",
+            };
+            lines.push(match &t.details {
+                Some(d) => format!("{level}: {} – {d}", t.text),
+                None => format!("{level}: {}", t.text),
+            });
+            t.expires_ms = now_ms + t.severity.lifetime_ms();
+            self.visible.push(t);
+        }
+        let excess = self.visible.len().saturating_sub(MAX_VISIBLE);
+        self.visible.drain(..excess);
+        lines
+    }
+
+    /// Draw the stack. Returns `true` when the user asked for details.
+    pub fn show(&mut self, ctx: &egui::Context, now_ms: f64) -> bool {
+        let mut open_console = false;
+        let n = self.visible.len();
+        let mut keep = vec![true; n];
+        // newest at the bottom
+        for (i, t) in self.visible.iter_mut().enumerate() {
+            let offset = egui::vec2(-12.0, -12.0 - 56.0 * (n - 1 - i) as f32);
+            let resp = egui::Area::new(egui::Id::new(("toast", i)))
+                .anchor(egui::Align2::RIGHT_BOTTOM, offset)
+                .order(egui::Order::Foreground)
+                .interactable(true)
+                .show(ctx, |ui| {
+                    egui::Frame::popup(ui.style()).show(ui, |ui| {
+                        ui.set_max_width(320.0);
+                        ui.horizontal(|ui| {
+                            ui.colored_label(t.severity.color(), t.severity.icon());
+                            ui.label(t.text.as_str());
+                            if (t.details.is_some() || t.severity != Severity::Info) && ui.small_button("details").clicked() {
+                                open_console = true;
+                            }
+                            if ui.small_button("✖").clicked() {
+                                keep[i] = false;
+                            }
+                        });
+                    });
+                })
+                .response;
+            if resp.hovered() {
+                t.expires_ms = t.expires_ms.max(now_ms + 1000.0);
+            }
+            if now_ms >= t.expires_ms {
+                keep[i] = false;
+            }
+        }
+        let mut k = keep.into_iter();
+        self.visible.retain(|_| k.next().unwrap_or(true));
+        if !self.visible.is_empty() {
+            ctx.request_repaint_after(std::time::Duration::from_millis(250));
+        }
+        open_console
+    }
+}
@@ -0,0 +1,165 @@
+//! Trail of the positions the machine reports while a job runs, drawn over
+//! the job's planned path.
+//!
+//! Every position the readout polls during the job is appended, once the
+//! tool has moved a little. Each point is measured against the planned
+//! moves near where the last one matched; a point further than
+//! [`DEVIATION_MM`] from them is drawn red, so lost steps and a drifting
+//! machine show up as the job goes. Points arrive as often as the readout
+//! polls, so straight moves show as a few points and fast corners may be
+//! cut in the trail though not on the machine.
+
+use std::collections::VecDeque;
+
+use eframe::egui;
+
+use crate::{AluminaApp, engine};
+
+/// Points further than this from the planned path count as off it (mm).
+const DEVIATION_MM: f64 = 0.5;
+/// Smallest move that adds a point (mm).
+const MIN_STEP_MM: f64 = 0.05;
+/// Planned moves searched ahead of the last match for each point.
+const SEARCH_AHEAD: usize = 400;
+/// Oldest points are dropped past this many.
+const MAX_POINTS: usize = 50_000;
+
+const PLANNED: [f32; 3] = [0.35, 0.45, 0.55];
+const ON_PATH: [f32; 3] = [0.2, 0.95, 0.4];
+const OFF_PATH: [f32; 3] = [1.0, 0.15, 0.15];
+
+fn dist_to_segment(p: [f64; 3], a: [f64; 3], b: [f64; 3]) -> f64 {
+    let ab = [b[0] - a[0], b[1] - a[1], b[2] - a[2]];
+    let len2 = ab.iter().map(|v| v * v).sum::<f64>();
+    let t = if len2 > 0.0 { ((0..3).map(|i| (p[i] - a[i]) * ab[i]).sum::<f64>() / len2).clamp(0.0, 1.0) } else { 0.0 };
+    (0..3).map(|i| (p[i] - (a[i] + ab[i] * t)).powi(2)).sum::<f64>().sqrt()
+}
+
+pub(crate) struct Trail {
+    pub(crate) show: bool,
+    /// Name and start time of the job traced, to notice the next one.
+    job: Option<(String, f64)>,
+    planned: Vec<[f64; 3]>,
+    /// Reported positions and their distance from the planned path.
+    points: VecDeque<([f64; 3], f64)>,
+    /// Planned move the last point matched.
+    cursor: usize,
+    /// Readout update already taken.
+    seen_ms: f64,
+}
+
+impl Default for Trail {
+    fn default() -> Self {
+        Self { show: true, job: None, planned: Vec::new(), points: VecDeque::new(), cursor: 0, seen_ms: 0.0 }
+    }
+}
+
+impl Trail {
+    pub(crate) fn clear(&mut self) {
+        self.points.clear();
+        self.cursor = 0;
+    }
+
+    /// Distance of `p` from the planned moves at or after the cursor.
+    fn deviation(&mut self, p: [f64; 3]) -> f64 {
+        let end = self.planned.len().min(self.cursor + SEARCH_AHEAD);
+        let best = (self.cursor..end)
+            .map(|i| {
+                let a = self.planned[i.saturating_sub(1)];
+                (i, dist_to_segment(p, a, self.planned[i]))
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1));
+        match best {
+            Some((i, d)) => {
+                self.cursor = i;
+                d
+            }
+            None => 0.0,
+        }
+    }
+
+    fn push(&mut self, p: [f64; 3]) {
+        let last = self.points.back().map(|&(q, _)| q);
+        if last.is_some_and(|q| (0..3).map(|i| (p[i] - q[i]).powi(2)).sum::<f64>().sqrt() < MIN_STEP_MM) {
+            return;
+        }
+        let d = if self.planned.is_empty() { 0.0 } else { self.deviation(p) };
+        self.points.push_back((p, d));
+        if self.points.len() > MAX_POINTS {
+            self.points.pop_front();
+        }
+    }
+
+    fn off_path(&self) -> (usize, f64) {
+        let off = self.points.iter().filter(|&&(_, d)| d > DEVIATION_MM).count();
+        (off, self.points.iter().map(|&(_, d)| d).fold(0.0, f64::max))
+    }
+}
+
+impl AluminaApp {
+    /// Start a new trail with each job, and add the latest reported
+    /// position while it runs.
+    pub(crate) fn tick_trail(&mut self) {
+        let job = self.jobs.active.as_ref().and_then(|j| Some((j.name.clone(), j.started_ms()?)));
+        if job != self.trail.job {
+            self.trail.clear();
+            self.trail.planned = match (&job, &self.jobs.active) {
+                (Some(_), Some(j)) => engine::program_moves(&j.text()),
+                _ => Vec::new(),
+            };
+            self.trail.job = job;
+        }
+        if self.dro_updated == self.trail.seen_ms {
+            return;
+        }
+        self.trail.seen_ms = self.dro_updated;
+        let running = self.jobs.active.as_ref().is_some_and(|j| j.is_running());
+        if let (true, Some(pos)) = (running, self.dro_pos) {
+            let [x, y, z, _] = pos.display();
+            self.trail.push([x, y, z]);
+        }
+    }
+
+    /// Planned path, then the trail over it.
+    pub(crate) fn push_trail(&self, out: &mut Vec<f32>) {
+        if !self.trail.show || self.trail.job.is_none() {
+            return;
+        }
+        let mut line = |a: [f64; 3], b: [f64; 3], c: [f32; 3]| {
+            let (a, b) = (self.machine_to_scene([a[0], a[1], a[2], 0.0]), self.machine_to_scene([b[0], b[1], b[2], 0.0]));
+            out.extend_from_slice(&[a.x, a.y, a.z, c[0], c[1], c[2], b.x, b.y, b.z, c[0], c[1], c[2]]);
+        };
+        for w in self.trail.planned.windows(2) {
+            line(w[0], w[1], PLANNED);
+        }
+        for (&(a, _), &(b, d)) in self.trail.points.iter().zip(self.trail.points.iter().skip(1)) {
+            line(a, b, if d > DEVIATION_MM { OFF_PATH } else { ON_PATH });
+        }
+    }
+
+    pub(crate) fn trail_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.trail.show, "Show trail")
+                .on_hover_text("Reported positions over the job's planned path; red where they leave it");
+            if ui.add_enabled(!self.trail.points.is_empty(), egui::Button::new("Clear trail")).clicked() {
+                self.trail.clear();
+            }
+        });
+        if self.trail.job.is_none() {
+            return;
+        }
+        if !self.dro_poll && !self.dry_running() {
+            ui.weak("Turn on “Poll position” to record the trail");
+            return;
+        }
+        let (off, max) = self.trail.off_path();
+        if off > 0 {
+            ui.colored_label(
+                ui.visuals().warn_fg_color,
+                format!("⚠ {off} of {} point(s) off the planned path, up to {max:.2} mm", self.trail.points.len()),
+            );
+        } else {
+            ui.weak(format!("{} point(s), all within {DEVIATION_MM} mm of the path", self.trail.points.len()));
+        }
+    }
+}
@@ -0,0 +1,133 @@
+//! Trapped volumes in resin parts.
+//!
+//! A void enclosed in a layer (a hole in the slice, or the cavity of a
+//! hollowed part) is followed up the layer stack through every void it
+//! overlaps, giving the 3-D volume it belongs to. A volume whose floor is
+//! sealed (by the part, or by the build plate) presses against the vat film
+//! like a suction cup on every peel until it is closed off; one whose roof
+//! is sealed as well keeps its resin after the print. Both want a drain
+//! hole, suggested at the middle of the volume's lowest layer.
+
+use geo::{Area, BooleanOps, Coord, InteriorPoint, Intersects, LineString, MultiPolygon, Polygon};
+
+use crate::slicer::Layer;
+
+/// Smallest area that counts as an opening (mm²).
+const MIN_OPENING: f64 = 1e-3;
+
+/// One layer as the analysis sees it.
+pub struct LayerVoids {
+    pub layer: Layer,
+    /// Cured area of the layer.
+    pub solid: MultiPolygon<f64>,
+    /// Uncured areas enclosed by it.
+    pub voids: Vec<Polygon<f64>>,
+}
+
+impl LayerVoids {
+    /// Split a layer's slice (and the cavity hollowing leaves in it) into
+    /// cured area and enclosed voids.
+    pub fn new(layer: Layer, slice: &[Polygon<f64>], cavity: &[Polygon<f64>]) -> Self {
+        let cavity = MultiPolygon(cavity.to_vec());
+        let solid = MultiPolygon(slice.to_vec()).difference(&cavity);
+        let holes = MultiPolygon(slice.iter().flat_map(|p| p.interiors()).map(|r| Polygon::new(r.clone(), Vec::new())).collect());
+        let mut voids = holes.difference(&solid).0;
+        voids.extend(cavity.0);
+        Self { layer, solid, voids }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct TrappedVolume {
+    /// First and last layer it spans.
+    pub first: usize,
+    pub last: usize,
+    /// mm³
+    pub volume: f64,
+    /// Sealed above too: the resin stays inside after the print.
+    pub sealed_roof: bool,
+    /// Suggested drain hole, through the floor under the lowest layer.
+    pub drain: Coord<f64>,
+    pub drain_z: f64,
+    /// Outline of the volume on each of its layers.
+    pub outlines: Vec<(usize, LineString<f64>)>,
+}
+
+/// Part of `void` over neither cured nor enclosed area of `next`: where it
+/// opens to the resin around the part.
+fn opens_into(void: &Polygon<f64>, next: &LayerVoids) -> bool {
+    let rest = MultiPolygon(vec![void.clone()]).difference(&next.solid).difference(&MultiPolygon(next.voids.clone()));
+    rest.unsigned_area() > MIN_OPENING
+}
+
+fn find_root(parent: &mut [usize], mut i: usize) -> usize {
+    while parent[i] != i {
+        parent[i] = parent[parent[i]];
+        i = parent[i];
+    }
+    i
+}
+
+/// Volumes whose floor is sealed, lowest first.
+pub fn trapped_volumes(layers: &[LayerVoids]) -> Vec<TrappedVolume> {
+    // every void gets an index; `start[i]` is layer i's first
+    let mut start = Vec::with_capacity(layers.len() + 1);
+    start.push(0);
+    for l in layers {
+        start.push(start.last().copied().unwrap_or(0) + l.voids.len());
+    }
+    let total = start.last().copied().unwrap_or(0);
+    let mut parent: Vec<usize> = (0..total).collect();
+    for i in 1..layers.len() {
+        for (a, va) in layers[i].voids.iter().enumerate() {
+            for (b, vb) in layers[i - 1].voids.iter().enumerate() {
+                if va.intersects(vb) {
+                    let (ra, rb) = (find_root(&mut parent, start[i] + a), find_root(&mut parent, start[i - 1] + b));
+                    parent[ra] = rb;
+                }
+            }
+        }
+    }
+
+    // the plate seals the first layer from below; nothing covers the last
+    let mut groups: Vec<(usize, Vec<(usize, usize)>, bool, bool)> = Vec::new();
+    for (i, l) in layers.iter().enumerate() {
+        for (k, void) in l.voids.iter().enumerate() {
+            let root = find_root(&mut parent, start[i] + k);
+            let open_below = i > 0 && opens_into(void, &layers[i - 1]);
+            let open_above = i + 1 == layers.len() || opens_into(void, &layers[i + 1]);
+            match groups.iter_mut().find(|g| g.0 == root) {
+                Some(g) => {
+                    g.1.push((i, k));
+                    g.2 |= open_below;
+                    g.3 |= open_above;
+                }
+                None => groups.push((root, vec![(i, k)], open_below, open_above)),
+            }
+        }
+    }
+
+    groups
+        .into_iter()
+        .filter(|g| !g.2)
+        .filter_map(|(_, members, _, open_above)| {
+            let first = members.iter().map(|m| m.0).min()?;
+            let last = members.iter().map(|m| m.0).max()?;
+            let volume = members.iter().map(|&(i, k)| layers[i].voids[k].unsigned_area() * f64::from(layers[i].layer.height)).sum();
+            let lowest = members
+                .iter()
+                .filter(|m| m.0 == first)
+                .map(|&(i, k)| &layers[i].voids[k])
+                .max_by(|a, b| a.unsigned_area().total_cmp(&b.unsigned_area()))?;
+            Some(TrappedVolume {
+                first,
+                last,
+                volume,
+                sealed_roof: !open_above,
+                drain: lowest.interior_point()?.0,
+                drain_z: f64::from(layers[first].layer.z),
+                outlines: members.iter().map(|&(i, k)| (i, layers[i].voids[k].exterior().clone())).collect(),
+            })
+        })
+        .collect()
+}
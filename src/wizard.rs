@@ -0,0 +1,338 @@
+//! First-run machine setup: a short guided sequence (machine type, work
+//! area, origin, connection, test move) that fills in the machine profile
+//! and checks that the controller answers before the first real job.
+//!
+//! Opens on its own while no machine profile has been saved yet, and again
+//! from the Machine panel.
+
+use crate::{
+    AluminaApp, Tool,
+    control::{parse_position, spawn_position_query},
+    machine::{Firmware, MachineProfile},
+    net::{self, Endpoint, Pending},
+};
+
+const TOOLS: [(Tool, &str); 6] = [
+    (Tool::Laser, "Laser"),
+    (Tool::Plasma, "Plasma"),
+    (Tool::Extruder, "Extruder (FDM)"),
+    (Tool::Endmill, "Endmill (router)"),
+    (Tool::Drill, "Drill"),
+    (Tool::DlpLcd, "DLP / LCD resin"),
+];
+
+/// Distance (mm) of the test move along X, out and back.
+const TEST_MOVE_MM: f64 = 1.0;
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Step {
+    Machine,
+    WorkArea,
+    Origin,
+    Connection,
+    Test,
+}
+
+impl Step {
+    const ALL: [Step; 5] = [Step::Machine, Step::WorkArea, Step::Origin, Step::Connection, Step::Test];
+
+    fn title(self) -> &'static str {
+        match self {
+            Step::Machine => "Machine type",
+            Step::WorkArea => "Work area",
+            Step::Origin => "Origin",
+            Step::Connection => "Connection",
+            Step::Test => "Test move",
+        }
+    }
+
+    fn index(self) -> usize {
+        Self::ALL.iter().position(|&s| s == self).unwrap_or(0)
+    }
+}
+
+/// Where machine zero sits on the bed.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Origin {
+    FrontLeft,
+    Centre,
+}
+
+/// Progress of a connection check or test move.
+enum Check {
+    Idle,
+    /// In flight; the reply is turned into a message once it arrives.
+    Running(Pending, fn(&str) -> String),
+    Passed(String),
+    Failed(String),
+}
+
+impl Check {
+    fn poll(&mut self) -> bool {
+        let Check::Running(slot, describe) = self else { return false };
+        let describe = *describe;
+        let Some(result) = slot.lock().unwrap().take() else { return true };
+        *self = match result {
+            Ok(reply) => Check::Passed(describe(&reply)),
+            Err(e) => Check::Failed(e),
+        };
+        false
+    }
+
+    fn ui(&self, ui: &mut egui::Ui) {
+        match self {
+            Check::Idle => {}
+            Check::Running(..) => {
+                ui.spinner();
+            }
+            Check::Passed(msg) => {
+                ui.colored_label(egui::Color32::from_rgb(80, 200, 120), msg);
+            }
+            Check::Failed(msg) => {
+                ui.colored_label(ui.visuals().error_fg_color, msg);
+            }
+        }
+    }
+}
+
+pub(crate) struct Wizard {
+    step: Step,
+    tool: Tool,
+    name: String,
+    firmware: Firmware,
+    /// Travel X/Y/Z (mm).
+    size: [f64; 3],
+    origin: Origin,
+    connection: Check,
+    test_move: Check,
+}
+
+impl Wizard {
+    /// Start from the current profile, so re-running the wizard only changes
+    /// what the user edits.
+    pub(crate) fn new(profile: &MachineProfile, tool: Tool) -> Self {
+        let size = std::array::from_fn(|i| (profile.travel_max[i] - profile.travel_min[i]).max(1.0));
+        let centred = profile.travel_min[0] < 0.0 && profile.travel_min[1] < 0.0;
+        Self {
+            step: Step::Machine,
+            tool,
+            name: profile.name.clone(),
+            firmware: profile.firmware,
+            size,
+            origin: if centred { Origin::Centre } else { Origin::FrontLeft },
+            connection: Check::Idle,
+            test_move: Check::Idle,
+        }
+    }
+
+    fn travel(&self) -> ([f64; 3], [f64; 3]) {
+        let [x, y, z] = self.size;
+        match self.origin {
+            Origin::FrontLeft => ([0.0; 3], [x, y, z]),
+            Origin::Centre => ([-x / 2.0, -y / 2.0, 0.0], [x / 2.0, y / 2.0, z]),
+        }
+    }
+
+    fn connected(&self) -> bool {
+        matches!(self.connection, Check::Passed(_))
+    }
+
+    fn step_ui(&mut self, ui: &mut egui::Ui) {
+        match self.step {
+            Step::Machine => {
+                ui.label("What kind of machine is this?");
+                for (tool, label) in TOOLS {
+                    ui.radio_value(&mut self.tool, tool, label);
+                }
+                ui.add_space(6.0);
+                ui.horizontal(|ui| {
+                    ui.label("Name:");
+                    ui.text_edit_singleline(&mut self.name);
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Firmware:");
+                    egui::ComboBox::from_id_salt("wizard_firmware")
+                        .selected_text(self.firmware.to_string())
+                        .show_ui(ui, |ui| {
+                            for fw in [Firmware::Alumina, Firmware::Marlin, Firmware::Grbl] {
+                                ui.selectable_value(&mut self.firmware, fw, fw.to_string());
+                            }
+                        });
+                });
+            }
+            Step::WorkArea => {
+                ui.label("How far can the tool travel on each axis?");
+                egui::Grid::new("wizard_size").num_columns(2).show(ui, |ui| {
+                    for (name, v) in ["X", "Y", "Z"].into_iter().zip(self.size.iter_mut()) {
+                        ui.label(name);
+                        ui.add(egui::DragValue::new(v).range(1.0..=5000.0).suffix(" mm"));
+                        ui.end_row();
+                    }
+                });
+            }
+            Step::Origin => {
+                ui.label("Where is X0 Y0 after homing?");
+                ui.radio_value(&mut self.origin, Origin::FrontLeft, "Front-left corner");
+                ui.radio_value(&mut self.origin, Origin::Centre, "Centre of the bed");
+                let (min, max) = self.travel();
+                ui.weak(format!(
+                    "Travel limits: X {:.0}…{:.0}, Y {:.0}…{:.0}, Z {:.0}…{:.0} mm",
+                    min[0], max[0], min[1], max[1], min[2], max[2]
+                ));
+            }
+            Step::Connection => {
+                #[cfg(target_arch = "wasm32")]
+                ui.label(
+                    "The controller is reached over the network, at the address this page was loaded from, \
+                     unless you connect a USB board here (Chrome and Edge).",
+                );
+                #[cfg(not(target_arch = "wasm32"))]
+                ui.label(format!(
+                    "Pick a serial port for a USB-connected controller, or leave it unset to use the network \
+                     controller at {} (change it under Machine → Connection).",
+                    net::base_url()
+                ));
+                net::link_ui(ui);
+                ui.add_space(6.0);
+                ui.horizontal(|ui| {
+                    let running = matches!(self.connection, Check::Running(..));
+                    if ui.add_enabled(!running, egui::Button::new("Check connection")).clicked() {
+                        self.connection = Check::Running(spawn_position_query(self.firmware), describe_position);
+                    }
+                    self.connection.ui(ui);
+                });
+            }
+            Step::Test => {
+                ui.label(format!(
+                    "Make sure the tool is clear of the bed and the clamps, then move X by {TEST_MOVE_MM} mm and back."
+                ));
+                if !self.connected() {
+                    ui.colored_label(ui.visuals().warn_fg_color, "Check the connection first.");
+                }
+                ui.horizontal(|ui| {
+                    let running = matches!(self.test_move, Check::Running(..));
+                    if ui.add_enabled(self.connected() && !running, egui::Button::new("Test move")).clicked() {
+                        self.test_move = Check::Running(spawn_test_move(), |_| {
+                            format!("Controller accepted the {TEST_MOVE_MM} mm test move")
+                        });
+                    }
+                    self.test_move.ui(ui);
+                });
+                if matches!(self.test_move, Check::Passed(_)) {
+                    ui.label("Did the machine move? If not, check the wiring and the firmware setting.");
+                }
+            }
+        }
+    }
+}
+
+fn describe_position(reply: &str) -> String {
+    match parse_position(reply, &mut None) {
+        Some(p) => {
+            let [x, y, z, _] = p.display();
+            format!("Controller answered: X{x:.2} Y{y:.2} Z{z:.2}")
+        }
+        None => "Controller answered".to_owned(),
+    }
+}
+
+/// Relative move out and back, so the tool ends where it started.
+fn spawn_test_move() -> Pending {
+    let slot: Pending = std::sync::Arc::default();
+    let target = std::sync::Arc::clone(&slot);
+    crate::execute(async move {
+        let lines = ["G91".to_owned(), format!("G0 X{TEST_MOVE_MM}"), format!("G0 X-{TEST_MOVE_MM}"), "G90".to_owned()];
+        let mut result = Ok(String::new());
+        for line in lines {
+            if let Err(e) = net::fetch(&Endpoint::Queue(line.clone())).await {
+                result = Err(format!("`{line}` failed: {e}"));
+                break;
+            }
+        }
+        *target.lock().unwrap() = Some(result);
+    });
+    slot
+}
+
+impl AluminaApp {
+    pub(crate) fn open_wizard(&mut self) {
+        self.wizard = Some(Wizard::new(&self.machine, self.selected_tool));
+    }
+
+    fn finish_wizard(&mut self, w: &Wizard) {
+        let (min, max) = w.travel();
+        if !w.name.trim().is_empty() {
+            self.machine.name = w.name.trim().to_owned();
+        }
+        self.machine.firmware = w.firmware;
+        self.machine.travel_min = min;
+        self.machine.travel_max = max;
+        self.machine.save();
+        self.selected_tool = w.tool;
+        self.work_size = nalgebra::Vector3::new(w.size[0] as f32, w.size[1] as f32, w.size[2] as f32);
+        self.invalidate_layers();
+        self.refresh_slice();
+        crate::toasts::info(format!("Machine “{}” set up", self.machine.name));
+    }
+
+    pub(crate) fn wizard_dialog(&mut self, ctx: &egui::Context) {
+        let Some(mut w) = self.wizard.take() else { return };
+        if w.connection.poll() | w.test_move.poll() {
+            ctx.request_repaint_after(std::time::Duration::from_millis(100));
+        }
+
+        let mut open = true;
+        let mut finish = false;
+        egui::Window::new("Machine setup")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    for (i, step) in Step::ALL.into_iter().enumerate() {
+                        if i > 0 {
+                            ui.weak("›");
+                        }
+                        if step == w.step {
+                            ui.strong(step.title());
+                        } else {
+                            ui.weak(step.title());
+                        }
+                    }
+                });
+                ui.separator();
+                w.step_ui(ui);
+                ui.separator();
+                ui.horizontal(|ui| {
+                    let i = w.step.index();
+                    if ui.add_enabled(i > 0, egui::Button::new("Back")).clicked() {
+                        w.step = Step::ALL[i - 1];
+                    }
+                    if let Some(&next) = Step::ALL.get(i + 1) {
+                        if ui.button("Next").clicked() {
+                            w.step = next;
+                        }
+                    } else if ui.button("Finish").clicked() {
+                        finish = true;
+                    }
+                    if ui
+                        .button("Skip")
+                        .on_hover_text("Keep the current profile; the wizard stays available in the Machine panel")
+                        .clicked()
+                    {
+                        open = false;
+                    }
+                });
+            });
+
+        if finish {
+            self.finish_wizard(&w);
+        } else if open {
+            self.wizard = Some(w);
+        } else {
+            // keep the wizard from reopening on every start
+            self.machine.save();
+        }
+    }
+}
@@ -0,0 +1,209 @@
+//! Several independent projects (model set + design graph) open side by
+//! side in workspace tabs.
+//!
+//! The active workspace lives in the app's own fields, so the rest of the
+//! app is unaware of workspaces; switching swaps the parked state of the
+//! target tab in and the current state out. Slicer, tool and machine
+//! settings are shared by all workspaces.
+//!
+//! Each workspace is saved on its own, as a JSON file in the auto-save
+//! session format, and can be opened again into a new tab.
+
+use std::sync::{Arc, Mutex};
+
+use crate::{
+    AluminaApp, Confirm, ModelEntry, autosave::Session, design_graph, download_bytes, graph_history::GraphHistory, model_history::ModelHistory, spawn_file_picker,
+    toasts,
+};
+
+const EXTENSION: &str = "alumina.json";
+
+pub(crate) struct Workspace {
+    pub(crate) name: String,
+    /// Parked state; empty while the workspace is active.
+    models: Vec<ModelEntry>,
+    selected_model: Option<usize>,
+    design_state: design_graph::EditorState,
+    graph_history: GraphHistory,
+    model_history: ModelHistory,
+    models_dirty: bool,
+    graph_saved: Option<u64>,
+    graph_dirty: bool,
+}
+
+impl Workspace {
+    fn new(name: String) -> Self {
+        Self {
+            name,
+            models: Vec::new(),
+            selected_model: None,
+            design_state: design_graph::EditorState::default(),
+            graph_history: GraphHistory::default(),
+            model_history: ModelHistory::default(),
+            models_dirty: false,
+            graph_saved: None,
+            graph_dirty: false,
+        }
+    }
+}
+
+pub(crate) struct Workspaces {
+    tabs: Vec<Workspace>,
+    active: usize,
+    /// Counter for "Untitled N" names.
+    created: usize,
+    /// Workspace file picked for opening.
+    open_data: Arc<Mutex<Option<Vec<u8>>>>,
+}
+
+impl Default for Workspaces {
+    fn default() -> Self {
+        Self {
+            tabs: vec![Workspace::new("Untitled 1".to_owned())],
+            active: 0,
+            created: 1,
+            open_data: Arc::default(),
+        }
+    }
+}
+
+impl Workspaces {
+    pub(crate) fn active_name(&self) -> &str {
+        &self.tabs[self.active].name
+    }
+
+    /// Unsaved changes in any parked workspace.
+    pub(crate) fn parked_dirty(&self) -> bool {
+        self.tabs
+            .iter()
+            .enumerate()
+            .any(|(i, w)| i != self.active && (w.models_dirty || w.graph_dirty))
+    }
+}
+
+impl AluminaApp {
+    /// Exchange the app's project state with the parked state of tab `i`.
+    fn swap_workspace(&mut self, i: usize) {
+        let ws = &mut self.workspaces.tabs[i];
+        std::mem::swap(&mut self.models, &mut ws.models);
+        std::mem::swap(&mut self.selected_model, &mut ws.selected_model);
+        std::mem::swap(&mut self.design_state, &mut ws.design_state);
+        std::mem::swap(&mut self.graph_history, &mut ws.graph_history);
+        std::mem::swap(&mut self.model_history, &mut ws.model_history);
+        std::mem::swap(&mut self.dirty.models, &mut ws.models_dirty);
+        std::mem::swap(&mut self.dirty.graph_saved, &mut ws.graph_saved);
+        std::mem::swap(&mut self.dirty.graph, &mut ws.graph_dirty);
+    }
+
+    pub(crate) fn switch_workspace(&mut self, i: usize) {
+        if i == self.workspaces.active || i >= self.workspaces.tabs.len() {
+            return;
+        }
+        self.swap_workspace(self.workspaces.active);
+        self.swap_workspace(i);
+        self.workspaces.active = i;
+        self.invalidate_layers();
+        self.refresh_slice();
+    }
+
+    pub(crate) fn new_workspace(&mut self, name: Option<String>) {
+        self.workspaces.created += 1;
+        let name = name.unwrap_or_else(|| format!("Untitled {}", self.workspaces.created));
+        self.workspaces.tabs.push(Workspace::new(name));
+        self.switch_workspace(self.workspaces.tabs.len() - 1);
+    }
+
+    pub(crate) fn workspace_dirty(&self, i: usize) -> bool {
+        if i == self.workspaces.active {
+            self.dirty.models || self.dirty.graph
+        } else {
+            self.workspaces.tabs.get(i).is_some_and(|w| w.models_dirty || w.graph_dirty)
+        }
+    }
+
+    /// Close tab `i` (the last one is never closed).
+    pub(crate) fn close_workspace(&mut self, i: usize) {
+        let ws = &mut self.workspaces;
+        if ws.tabs.len() < 2 || i >= ws.tabs.len() {
+            return;
+        }
+        if i == ws.active {
+            self.switch_workspace(if i == 0 { 1 } else { i - 1 });
+        }
+        let ws = &mut self.workspaces;
+        ws.tabs.remove(i);
+        if i < ws.active {
+            ws.active -= 1;
+        }
+    }
+
+    /// Download the active workspace and mark it saved.
+    pub(crate) fn save_workspace(&mut self) {
+        let json = self.session().and_then(|s| serde_json::to_vec(&s).map_err(Into::into));
+        match json {
+            Ok(json) => {
+                download_bytes(&format!("{}.{EXTENSION}", self.workspaces.active_name()), &json);
+                self.dirty.models = false;
+                self.dirty.graph_saved = None;
+                self.dirty.settings_saved = None;
+            }
+            Err(e) => toasts::error("The workspace could not be saved", Some(e.to_string())),
+        }
+    }
+
+    fn open_workspace(&mut self, bytes: &[u8]) {
+        match serde_json::from_slice::<Session>(bytes) {
+            Ok(session) => {
+                let name = (!session.name.is_empty()).then(|| session.name.clone());
+                self.new_workspace(name);
+                self.restore_session(session);
+                self.dirty.models = false;
+                self.dirty.graph_saved = None;
+                self.dirty.settings_saved = None;
+            }
+            Err(e) => toasts::error("Not a workspace file", Some(e.to_string())),
+        }
+    }
+
+    pub(crate) fn pick_workspace_file(&self) {
+        spawn_file_picker(Arc::clone(&self.workspaces.open_data), "Workspace (json)", &["json"]);
+    }
+
+    /// Tab strip: switch, add, open, save and close workspaces.
+    pub(crate) fn workspace_bar(&mut self, ui: &mut egui::Ui) {
+        let opened = self.workspaces.open_data.lock().unwrap().take();
+        if let Some(bytes) = opened {
+            self.open_workspace(&bytes);
+        }
+
+        ui.horizontal(|ui| {
+            let mut switch = None;
+            for i in 0..self.workspaces.tabs.len() {
+                let name = &self.workspaces.tabs[i].name;
+                let label = if self.workspace_dirty(i) { format!("{name} •") } else { name.clone() };
+                if ui.selectable_label(i == self.workspaces.active, label).clicked() {
+                    switch = Some(i);
+                }
+            }
+            if let Some(i) = switch {
+                self.switch_workspace(i);
+            }
+            if ui.button("+").on_hover_text("New workspace").clicked() {
+                self.new_workspace(None);
+            }
+            ui.separator();
+            let active = self.workspaces.active;
+            ui.add(egui::TextEdit::singleline(&mut self.workspaces.tabs[active].name).desired_width(100.0))
+                .on_hover_text("Workspace name");
+            if ui.button("Save").on_hover_text("Download this workspace (models, graph and settings)").clicked() {
+                self.save_workspace();
+            }
+            if ui.button("Open…").on_hover_text("Open a saved workspace in a new tab").clicked() {
+                self.pick_workspace_file();
+            }
+            if self.workspaces.tabs.len() > 1 && ui.button("Close").clicked() {
+                self.guarded(Confirm::CloseWorkspace(active));
+            }
+        });
+    }
+}
@@ -0,0 +1,134 @@
+//! Saved designs must keep evaluating to the same meshes.
+//!
+//! Every `tests/graphs/NAME.graph` is evaluated headlessly and the stats of
+//! its output meshes compared with `NAME.golden.json`. Counts missing from a
+//! golden file are not checked. After an intended change to a node, record
+//! the new results with
+//!
+//! ```shell
+//! UPDATE_GOLDEN=1 cargo test --test golden_graphs
+//! ```
+
+use std::path::{Path, PathBuf};
+
+use alumina_ui::engine::{self, MeshStats};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize)]
+struct Golden {
+    meshes: Vec<Expected>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Expected {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    polygons: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    vertices: Option<usize>,
+    volume: f64,
+    min: [f64; 3],
+    max: [f64; 3],
+}
+
+impl From<&MeshStats> for Expected {
+    fn from(s: &MeshStats) -> Self {
+        Self { polygons: Some(s.polygons), vertices: Some(s.vertices), volume: s.volume, min: s.min, max: s.max }
+    }
+}
+
+fn close(a: f64, b: f64) -> bool {
+    (a - b).abs() <= 1e-6 * a.abs().max(b.abs()).max(1.0)
+}
+
+/// Differences between `got` and `want`, one line each.
+fn compare(got: &MeshStats, want: &Expected) -> Vec<String> {
+    let mut out = Vec::new();
+    if want.polygons.is_some_and(|n| n != got.polygons) {
+        out.push(format!("polygons {} != {}", got.polygons, want.polygons.unwrap_or_default()));
+    }
+    if want.vertices.is_some_and(|n| n != got.vertices) {
+        out.push(format!("vertices {} != {}", got.vertices, want.vertices.unwrap_or_default()));
+    }
+    if !close(got.volume, want.volume) {
+        out.push(format!("volume {} != {}", got.volume, want.volume));
+    }
+    if !(0..3).all(|i| close(got.min[i], want.min[i]) && close(got.max[i], want.max[i])) {
+        out.push(format!("bounds {:?}..{:?} != {:?}..{:?}", got.min, got.max, want.min, want.max));
+    }
+    out
+}
+
+fn graph_files() -> Vec<PathBuf> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/graphs");
+    let mut files: Vec<PathBuf> = std::fs::read_dir(&dir)
+        .expect("tests/graphs is missing")
+        .map(|e| e.expect("unreadable entry").path())
+        .filter(|p| p.extension().is_some_and(|e| e == "graph"))
+        .collect();
+    files.sort();
+    files
+}
+
+fn evaluate(path: &Path) -> anyhow::Result<Vec<MeshStats>> {
+    let graph = engine::parse_graph(&std::fs::read(path)?)?;
+    Ok(engine::evaluate_graph(graph)?.iter().map(engine::mesh_stats).collect())
+}
+
+#[test]
+fn saved_graphs_match_golden_stats() {
+    let update = std::env::var_os("UPDATE_GOLDEN").is_some();
+    let files = graph_files();
+    assert!(!files.is_empty(), "no .graph files in tests/graphs");
+
+    let mut failures = Vec::new();
+    for path in files {
+        let name = path.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+        let golden_path = path.with_extension("golden.json");
+        let stats = match evaluate(&path) {
+            Ok(stats) => stats,
+            Err(e) => {
+                failures.push(format!("{name}: evaluation failed: {e}"));
+                continue;
+            }
+        };
+        if update {
+            let golden = Golden { meshes: stats.iter().map(Expected::from).collect() };
+            let json = serde_json::to_string_pretty(&golden).expect("golden stats serialise");
+            std::fs::write(&golden_path, json + "\n").expect("golden file writable");
+            continue;
+        }
+        let golden: Golden = match std::fs::read(&golden_path).map_err(anyhow::Error::from).and_then(|b| Ok(serde_json::from_slice(&b)?)) {
+            Ok(golden) => golden,
+            Err(e) => {
+                failures.push(format!("{name}: no usable golden file ({e}); run with UPDATE_GOLDEN=1"));
+                continue;
+            }
+        };
+        if golden.meshes.len() != stats.len() {
+            failures.push(format!("{name}: {} meshes, expected {}", stats.len(), golden.meshes.len()));
+            continue;
+        }
+        for (i, (got, want)) in stats.iter().zip(&golden.meshes).enumerate() {
+            failures.extend(compare(got, want).into_iter().map(|d| format!("{name}[{i}]: {d}")));
+        }
+    }
+    assert!(failures.is_empty(), "graph regressions:\n{}", failures.join("\n"));
+}
+
+#[test]
+fn graph_json_round_trips() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/graphs/subtract.graph");
+    let graph = engine::parse_graph(&std::fs::read(&path).expect("test graph")).expect("parses");
+    let again = engine::parse_graph(engine::graph_json(&graph).expect("serialises").as_bytes()).expect("parses again");
+    let stats = |g| engine::evaluate_graph(g).expect("evaluates").iter().map(engine::mesh_stats).collect::<Vec<_>>();
+    assert_eq!(stats(graph), stats(again));
+}
+
+#[test]
+fn share_links_round_trip() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/graphs/translated_cuboid.graph");
+    let graph = engine::parse_graph(&std::fs::read(&path).expect("test graph")).expect("parses");
+    let link = graph.to_link().expect("encodes");
+    let back = engine::SavedGraph::from_link(&link).expect("decodes");
+    assert_eq!(engine::graph_json(&graph).ok(), engine::graph_json(&back).ok());
+}